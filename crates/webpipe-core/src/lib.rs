@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::time::Duration;
 
-#[derive(thiserror::Error, Debug)]
+#[derive(thiserror::Error, Debug, Clone)]
 pub enum Error {
     #[error("invalid url: {0}")]
     InvalidUrl(String),
@@ -36,6 +37,15 @@ pub struct FetchCachePolicy {
     pub write: bool,
     /// If set, cached entries older than this are treated as a miss.
     pub ttl_s: Option<u64>,
+    /// Also read cache entries for methods other than `GET`/`HEAD` (e.g. a `POST` to a search
+    /// API whose answer depends only on the body). Off: such requests always hit the network.
+    #[serde(default)]
+    pub read_non_idempotent: bool,
+    /// With `ttl_s`: an entry up to this many seconds past its TTL is returned immediately
+    /// (`revalidating: true`) while a background fetch refreshes it; past the window it is
+    /// refetched as usual.
+    #[serde(default)]
+    pub stale_while_revalidate_s: Option<u64>,
 }
 
 impl Default for FetchCachePolicy {
@@ -44,6 +54,8 @@ impl Default for FetchCachePolicy {
             read: true,
             write: true,
             ttl_s: None,
+            read_non_idempotent: false,
+            stale_while_revalidate_s: None,
         }
     }
 }
@@ -57,6 +69,12 @@ pub struct FetchRequest {
     pub max_bytes: Option<u64>,
     /// Optional headers to add (best-effort; adapter may drop unsafe headers).
     pub headers: BTreeMap<String, String>,
+    /// HTTP method (`None` = `GET`).
+    #[serde(default)]
+    pub method: Option<String>,
+    /// Request body, sent as-is (pair it with a `Content-Type` header).
+    #[serde(default)]
+    pub body: Option<Vec<u8>>,
     pub cache: FetchCachePolicy,
 }
 
@@ -64,6 +82,36 @@ impl FetchRequest {
     pub fn timeout(&self) -> Option<Duration> {
         self.timeout_ms.map(Duration::from_millis)
     }
+
+    /// Upper-cased method, `GET` when unset.
+    pub fn method(&self) -> String {
+        self.method
+            .as_deref()
+            .map(|m| m.trim().to_ascii_uppercase())
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| "GET".to_string())
+    }
+
+    /// Safe for caching and request coalescing: `GET` or `HEAD`. (`PUT`/`DELETE` are idempotent
+    /// but still change server state, so they must reach the origin every time.)
+    pub fn is_safe(&self) -> bool {
+        matches!(self.method().as_str(), "GET" | "HEAD")
+    }
+
+    /// Idempotent per RFC 9110 (repeating the request has the same effect as sending it once).
+    /// Decides retries only; see [`FetchRequest::is_safe`] for caching.
+    pub fn is_idempotent(&self) -> bool {
+        matches!(
+            self.method().as_str(),
+            "GET" | "HEAD" | "OPTIONS" | "TRACE" | "PUT" | "DELETE"
+        )
+    }
+
+    /// Whether a cache may answer this request: reads enabled, and the method is safe unless
+    /// [`FetchCachePolicy::read_non_idempotent`] says otherwise.
+    pub fn cache_readable(&self) -> bool {
+        self.cache.read && (self.is_safe() || self.cache.read_non_idempotent)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -72,6 +120,33 @@ pub enum FetchSource {
     Network,
 }
 
+/// How a response relates to the fetch cache, finer-grained than [`FetchSource`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheStatus {
+    /// Served from a cache entry that was still fresh.
+    Fresh,
+    /// The entry was stale; the origin answered `304 Not Modified` and the cached body was served.
+    Revalidated,
+    /// The entry was stale but served anyway (inside its `stale-while-revalidate` window).
+    Stale,
+    /// The cache was consulted but had no usable entry; the body came from the network.
+    Miss,
+}
+
+impl CacheStatus {
+    pub const ALL: [CacheStatus; 4] = [Self::Fresh, Self::Revalidated, Self::Stale, Self::Miss];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Fresh => "fresh",
+            Self::Revalidated => "revalidated",
+            Self::Stale => "stale",
+            Self::Miss => "miss",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FetchResponse {
     pub url: String,
@@ -80,8 +155,24 @@ pub struct FetchResponse {
     pub content_type: Option<String>,
     pub headers: BTreeMap<String, String>,
     pub bytes: Vec<u8>,
+    /// Body size as received on the wire, before Content-Encoding decompression.
+    ///
+    /// Equals `bytes.len()` for uncompressed bodies and non-HTTP sources (renders, transcripts).
+    /// When `truncated` is set this only counts what was read before the cap was hit.
+    #[serde(default)]
+    pub wire_bytes: u64,
     pub truncated: bool,
     pub source: FetchSource,
+    /// Cache outcome when a cache was consulted (`None`: cache disabled, or not a cached backend).
+    #[serde(default)]
+    pub cache_status: Option<CacheStatus>,
+    /// Where the body was written when it was streamed to disk instead of held in `bytes`
+    /// (which is then empty).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_path: Option<PathBuf>,
+    /// A stale cached body served while a background refresh of the entry runs.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub revalidating: bool,
     pub timings_ms: BTreeMap<String, u128>,
 }
 
@@ -89,6 +180,133 @@ impl FetchResponse {
     pub fn text_lossy(&self) -> String {
         String::from_utf8_lossy(&self.bytes).to_string()
     }
+
+    /// Server-suggested filename from `Content-Disposition`, if present and parseable.
+    pub fn filename(&self) -> Option<String> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("content-disposition"))
+            .and_then(|(_, v)| content_disposition_filename(v))
+    }
+}
+
+/// Parse the filename out of a `Content-Disposition` header value.
+///
+/// Notes:
+/// - Prefers RFC 5987 `filename*=charset'lang'pct-encoded` over plain `filename=`.
+/// - Never fails on malformed input: unparseable parameters are skipped.
+/// - The result is reduced to a bare file name (no path components or control chars), since
+///   callers may use it to name files on disk.
+pub fn content_disposition_filename(value: &str) -> Option<String> {
+    fn split_params(v: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut cur = String::new();
+        let mut in_quotes = false;
+        let mut escaped = false;
+        for ch in v.chars() {
+            if escaped {
+                cur.push(ch);
+                escaped = false;
+                continue;
+            }
+            match ch {
+                '\\' if in_quotes => {
+                    cur.push(ch);
+                    escaped = true;
+                }
+                '"' => {
+                    in_quotes = !in_quotes;
+                    cur.push(ch);
+                }
+                ';' if !in_quotes => out.push(std::mem::take(&mut cur)),
+                _ => cur.push(ch),
+            }
+        }
+        out.push(cur);
+        out
+    }
+
+    fn unquote(v: &str) -> String {
+        let v = v.trim();
+        let Some(inner) = v.strip_prefix('"') else {
+            return v.to_string();
+        };
+        let inner = inner.strip_suffix('"').unwrap_or(inner);
+        let mut out = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(ch) = chars.next() {
+            if ch == '\\' {
+                if let Some(next) = chars.next() {
+                    out.push(next);
+                }
+            } else {
+                out.push(ch);
+            }
+        }
+        out
+    }
+
+    fn decode_ext_value(v: &str) -> Option<String> {
+        // charset'language'percent-encoded-bytes
+        let v = unquote(v);
+        let mut parts = v.splitn(3, '\'');
+        let charset = parts.next()?.trim().to_ascii_lowercase();
+        let _lang = parts.next()?;
+        let encoded = parts.next()?;
+        let raw = encoded.as_bytes();
+        let mut bytes = Vec::with_capacity(raw.len());
+        let mut i = 0usize;
+        while i < raw.len() {
+            if raw[i] == b'%' {
+                let hex = raw.get(i + 1..i + 3)?;
+                if !hex.iter().all(|b| b.is_ascii_hexdigit()) {
+                    return None;
+                }
+                bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                i += 3;
+            } else {
+                bytes.push(raw[i]);
+                i += 1;
+            }
+        }
+        match charset.as_str() {
+            "utf-8" | "" => Some(String::from_utf8_lossy(&bytes).into_owned()),
+            "iso-8859-1" | "latin1" => Some(bytes.iter().map(|&b| b as char).collect()),
+            _ => None,
+        }
+    }
+
+    fn sanitize(name: &str) -> Option<String> {
+        let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+        let cleaned: String = base
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(255)
+            .collect::<String>()
+            .trim()
+            .to_string();
+        if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+            None
+        } else {
+            Some(cleaned)
+        }
+    }
+
+    let mut plain: Option<String> = None;
+    let mut extended: Option<String> = None;
+    for param in split_params(value).iter().skip(1) {
+        let Some((name, val)) = param.split_once('=') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "filename*" if extended.is_none() => {
+                extended = decode_ext_value(val).and_then(|s| sanitize(&s));
+            }
+            "filename" if plain.is_none() => plain = sanitize(&unquote(val)),
+            _ => {}
+        }
+    }
+    extended.or(plain)
 }
 
 #[async_trait::async_trait]
@@ -109,6 +327,81 @@ pub struct SearchQuery {
     /// - Callers should set a bounded value (e.g. 8_000..20_000) for interactive use.
     #[serde(default)]
     pub timeout_ms: Option<u64>,
+    /// Restrict results to one domain (and its subdomains), e.g. `docs.rs`.
+    ///
+    /// Providers with a native domain filter use it; the others search [`Self::site_scoped_query`].
+    #[serde(default)]
+    pub site: Option<String>,
+    /// Only results from the last `day`, `week`, `month` or `year`.
+    #[serde(default)]
+    pub freshness: Option<String>,
+    /// Only results published on or after this ISO date (`YYYY-MM-DD`).
+    #[serde(default)]
+    pub after: Option<String>,
+    /// Only results published on or before this ISO date (`YYYY-MM-DD`).
+    #[serde(default)]
+    pub before: Option<String>,
+    /// Skip this many results (for fetching results 11-30 and so on). Providers page in their own
+    /// units; [`SearchResponse::offset`] reports where the returned results actually start.
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+impl SearchQuery {
+    /// `query` with a `site:` operator prepended when [`Self::site`] is set.
+    pub fn site_scoped_query(&self) -> String {
+        match self
+            .site
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            Some(site) => format!("site:{site} {}", self.query),
+            None => self.query.clone(),
+        }
+    }
+
+    /// [`Self::freshness`] as one of `day`/`week`/`month`/`year` (`d`, `pw`, `past_month`, ...
+    /// are accepted too). `None` when unset or unrecognized.
+    pub fn freshness_window(&self) -> Option<&'static str> {
+        let f = self.freshness.as_deref()?.trim().to_ascii_lowercase();
+        let f = f
+            .strip_prefix("past_")
+            .or_else(|| f.strip_prefix("last_"))
+            .unwrap_or(&f);
+        match f {
+            "d" | "pd" | "day" | "24h" => Some("day"),
+            "w" | "pw" | "week" => Some("week"),
+            "m" | "pm" | "month" => Some("month"),
+            "y" | "py" | "year" => Some("year"),
+            _ => None,
+        }
+    }
+
+    /// [`Self::after`] / [`Self::before`] as `YYYY-MM-DD` (a full RFC 3339 timestamp is cut to its
+    /// date). Values that aren't dates come back as `None`.
+    pub fn date_range(&self) -> (Option<&str>, Option<&str>) {
+        fn iso_date(v: Option<&str>) -> Option<&str> {
+            let d = v?.trim().get(..10)?;
+            let b = d.as_bytes();
+            let ok = b.iter().enumerate().all(|(i, c)| match i {
+                4 | 7 => *c == b'-',
+                _ => c.is_ascii_digit(),
+            });
+            ok.then_some(d)
+        }
+        (
+            iso_date(self.after.as_deref()),
+            iso_date(self.before.as_deref()),
+        )
+    }
+
+    /// Whether any date filter was asked for, recognized or not.
+    pub fn has_date_filter(&self) -> bool {
+        [&self.freshness, &self.after, &self.before]
+            .iter()
+            .any(|v| v.as_deref().is_some_and(|s| !s.trim().is_empty()))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -125,6 +418,15 @@ pub struct SearchResponse {
     pub provider: String,
     pub cost_units: u64,
     pub timings_ms: BTreeMap<String, u128>,
+    /// Stable warning codes, e.g. `date_filter_unsupported` when the provider dropped a filter.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Rank of the first returned result (`0` = first page), after provider limits.
+    #[serde(default)]
+    pub offset: usize,
+    /// Whether a later page likely has more results (best effort; providers rarely say).
+    #[serde(default)]
+    pub has_more: bool,
 }
 
 #[async_trait::async_trait]
@@ -132,3 +434,149 @@ pub trait SearchProvider: Send + Sync {
     fn name(&self) -> &'static str;
     async fn search(&self, q: &SearchQuery) -> Result<SearchResponse>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fetch_request_method_defaults_to_get_and_gates_cache_reads() {
+        let mut req = FetchRequest {
+            url: "https://example.com/".to_string(),
+            timeout_ms: None,
+            max_bytes: None,
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy::default(),
+        };
+        assert_eq!(req.method(), "GET");
+        assert!(req.cache_readable());
+
+        req.method = Some(" post ".to_string());
+        assert_eq!(req.method(), "POST");
+        assert!(!req.is_idempotent());
+        assert!(!req.cache_readable());
+        req.cache.read_non_idempotent = true;
+        assert!(req.cache_readable());
+
+        req.method = Some("HEAD".to_string());
+        req.cache.read_non_idempotent = false;
+        assert!(req.cache_readable());
+
+        // Idempotent (retryable) but not safe: never answered from the cache.
+        for m in ["PUT", "DELETE"] {
+            req.method = Some(m.to_string());
+            assert!(req.is_idempotent());
+            assert!(!req.cache_readable());
+        }
+    }
+
+    #[test]
+    fn search_query_normalizes_freshness_and_iso_dates() {
+        let mut q = SearchQuery {
+            query: "q".to_string(),
+            max_results: None,
+            language: None,
+            country: None,
+            timeout_ms: None,
+            site: None,
+            freshness: Some(" Past_Week ".to_string()),
+            after: Some("2024-05-01T10:00:00Z".to_string()),
+            before: Some("May 2024".to_string()),
+            offset: None,
+        };
+        assert_eq!(q.freshness_window(), Some("week"));
+        assert_eq!(q.date_range(), (Some("2024-05-01"), None));
+        assert!(q.has_date_filter());
+
+        q.freshness = Some("pd".to_string());
+        assert_eq!(q.freshness_window(), Some("day"));
+        q.freshness = Some("hour".to_string());
+        assert_eq!(q.freshness_window(), None);
+
+        q.freshness = None;
+        q.after = Some(" ".to_string());
+        q.before = None;
+        assert!(!q.has_date_filter());
+    }
+
+    #[test]
+    fn content_disposition_filename_handles_quoted_and_rfc5987_forms() {
+        assert_eq!(
+            content_disposition_filename(r#"attachment; filename="a b.pdf""#).as_deref(),
+            Some("a b.pdf")
+        );
+        assert_eq!(
+            content_disposition_filename("attachment; filename*=UTF-8''na%C3%AFve%20report.pdf")
+                .as_deref(),
+            Some("naïve report.pdf")
+        );
+        // filename* wins over the ASCII fallback regardless of order.
+        assert_eq!(
+            content_disposition_filename(
+                r#"attachment; filename*=utf-8'en'%E2%82%AC%20rates.csv; filename="EUR rates.csv""#
+            )
+            .as_deref(),
+            Some("€ rates.csv")
+        );
+        assert_eq!(
+            content_disposition_filename(r#"inline; filename="semi;colon \"q\".txt""#).as_deref(),
+            Some(r#"semi;colon "q".txt"#)
+        );
+        assert_eq!(
+            content_disposition_filename("attachment; filename=../../etc/passwd").as_deref(),
+            Some("passwd")
+        );
+    }
+
+    #[test]
+    fn content_disposition_filename_never_panics_on_malformed_values() {
+        for v in [
+            "",
+            "attachment",
+            "attachment;",
+            "attachment; filename",
+            "attachment; filename=",
+            "attachment; filename=\"",
+            "attachment; filename*=UTF-8''%",
+            "attachment; filename*=UTF-8''%zz.txt",
+            "attachment; filename*=bogus",
+            "attachment; filename*=koi8-r''%C1.txt",
+            "attachment; filename=\"..\"",
+        ] {
+            assert_eq!(content_disposition_filename(v), None, "value={v:?}");
+        }
+        // A broken filename* falls back to the plain filename.
+        assert_eq!(
+            content_disposition_filename("attachment; filename*=UTF-8''%zz; filename=ok.txt")
+                .as_deref(),
+            Some("ok.txt")
+        );
+    }
+
+    #[test]
+    fn fetch_response_filename_reads_header_case_insensitively() {
+        let mut headers = BTreeMap::new();
+        headers.insert(
+            "Content-Disposition".to_string(),
+            "attachment; filename=report.pdf".to_string(),
+        );
+        let resp = FetchResponse {
+            url: "https://example.com/dl".to_string(),
+            final_url: "https://example.com/dl".to_string(),
+            status: 200,
+            content_type: Some("application/pdf".to_string()),
+            headers,
+            bytes: Vec::new(),
+            wire_bytes: 0,
+            truncated: false,
+            source: FetchSource::Network,
+            cache_status: None,
+            body_path: None,
+            revalidating: false,
+            timings_ms: BTreeMap::new(),
+        };
+        assert_eq!(resp.filename().as_deref(), Some("report.pdf"));
+    }
+}
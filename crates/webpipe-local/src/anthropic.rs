@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use webpipe_core::{Error, Result};
+
+fn env(key: &str) -> Option<String> {
+    std::env::var(key)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn anthropic_api_key_from_env() -> Option<String> {
+    env("WEBPIPE_ANTHROPIC_API_KEY").or_else(|| env("ANTHROPIC_API_KEY"))
+}
+
+/// Messages API version header value.
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// The Messages API requires `max_tokens`; used when the caller does not set one.
+const DEFAULT_MAX_TOKENS: u64 = 4096;
+
+#[derive(Debug, Clone)]
+pub struct AnthropicClient {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl AnthropicClient {
+    pub fn from_env(client: reqwest::Client, model_override: Option<String>) -> Result<Self> {
+        let api_key = anthropic_api_key_from_env().ok_or_else(|| {
+            Error::NotConfigured(
+                "missing WEBPIPE_ANTHROPIC_API_KEY (or ANTHROPIC_API_KEY)".to_string(),
+            )
+        })?;
+        let model = model_override
+            .filter(|m| !m.trim().is_empty())
+            .or_else(|| env("WEBPIPE_ANTHROPIC_MODEL"))
+            .ok_or_else(|| {
+                Error::NotConfigured(
+                    "missing model for anthropic (set llm_model or WEBPIPE_ANTHROPIC_MODEL)"
+                        .to_string(),
+                )
+            })?;
+        // Allow override for testing/proxies (do not include secrets here).
+        let base_url = env("WEBPIPE_ANTHROPIC_BASE_URL")
+            .unwrap_or_else(|| "https://api.anthropic.com".to_string());
+        Ok(Self {
+            client,
+            base_url,
+            api_key,
+            model,
+        })
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn endpoint_messages(&self) -> String {
+        format!("{}/v1/messages", self.base_url.trim_end_matches('/'))
+    }
+
+    /// One system + user turn. Returns the concatenated text blocks and token usage.
+    pub async fn messages(
+        &self,
+        system: &str,
+        user: &str,
+        timeout_ms: u64,
+        max_tokens: Option<u64>,
+        temperature: Option<f64>,
+        top_p: Option<f64>,
+    ) -> Result<MessagesResponse> {
+        let req = MessagesRequest {
+            model: self.model.clone(),
+            max_tokens: max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            system: (!system.trim().is_empty()).then(|| system.to_string()),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: user.to_string(),
+            }],
+            temperature,
+            top_p,
+        };
+        let resp = self
+            .client
+            .post(self.endpoint_messages())
+            .timeout(std::time::Duration::from_millis(timeout_ms))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .json(&req)
+            .send()
+            .await
+            .map_err(|e| Error::Llm(e.to_string()))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(Error::Llm(format!("anthropic messages HTTP {status}")));
+        }
+        resp.json().await.map_err(|e| Error::Llm(e.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MessagesResponse {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub content: Vec<ContentBlock>,
+    #[serde(default)]
+    pub stop_reason: Option<String>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+impl MessagesResponse {
+    /// Concatenated `text` blocks (other block types are skipped).
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter(|b| b.kind == "text")
+            .filter_map(|b| b.text.as_deref())
+            .collect::<Vec<_>>()
+            .join("")
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContentBlock {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_response_joins_text_blocks_only() {
+        let r: MessagesResponse = serde_json::from_value(serde_json::json!({
+            "model": "m",
+            "content": [
+                {"type": "text", "text": "Hello "},
+                {"type": "tool_use", "id": "x", "name": "t", "input": {}},
+                {"type": "text", "text": "world"}
+            ],
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 12, "output_tokens": 3}
+        }))
+        .unwrap();
+        assert_eq!(r.text(), "Hello world");
+        let u = r.usage.unwrap();
+        assert_eq!((u.input_tokens, u.output_tokens), (12, 3));
+    }
+}
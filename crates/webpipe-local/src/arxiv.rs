@@ -23,11 +23,23 @@ pub struct ArxivPaper {
     pub pdf_url: Option<String>,
     pub title: String,
     pub summary: String,
+    /// First-version submission date (RFC 3339).
     pub published: Option<String>,
+    /// Date of the latest version (RFC 3339).
     pub updated: Option<String>,
+    /// Latest version number (the `vN` suffix of the entry id).
+    pub version: Option<u32>,
     pub authors: Vec<String>,
     pub categories: Vec<String>,
     pub primary_category: Option<String>,
+    /// DOI of the version of record (`<arxiv:doi>`), when the authors supplied one.
+    pub doi: Option<String>,
+    /// `https://doi.org/<doi>` when `doi` is set.
+    pub doi_url: Option<String>,
+    /// Journal reference (`<arxiv:journal_ref>`).
+    pub journal_ref: Option<String>,
+    /// Author comment (`<arxiv:comment>`), e.g. page counts or "Accepted at ...".
+    pub comment: Option<String>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -68,6 +80,16 @@ pub fn arxiv_pdf_url(id: &str) -> String {
     format!("https://arxiv.org/pdf/{}.pdf", id.trim())
 }
 
+/// Version number from a versioned id (`1706.03762v7` -> 7).
+fn arxiv_version_from_id(id: &str) -> Option<u32> {
+    let (_, v) = id.trim().rsplit_once('v')?;
+    v.parse::<u32>().ok().filter(|n| *n > 0)
+}
+
+pub fn doi_url(doi: &str) -> String {
+    format!("https://doi.org/{}", doi.trim())
+}
+
 fn build_search_query(query: &str, categories: &[String]) -> String {
     // ArXiv query syntax:
     // - all:term
@@ -217,6 +239,9 @@ fn parse_atom(body: &str) -> (Option<u64>, Vec<ArxivPaper>, Vec<&'static str>) {
         categories: Vec<String>,
         primary_category: Option<String>,
         pdf_url: Option<String>,
+        doi: Option<String>,
+        journal_ref: Option<String>,
+        comment: Option<String>,
         in_entry: bool,
         in_author: bool,
         cur_text: String,
@@ -352,7 +377,13 @@ fn parse_atom(body: &str) -> (Option<u64>, Vec<ArxivPaper>, Vec<&'static str>) {
                 let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
                 if cur.in_entry {
                     let txt = normalize_ws(&cur.cur_text);
-                    if name.ends_with("id") {
+                    if name.ends_with("doi") {
+                        cur.doi = (!txt.is_empty()).then_some(txt);
+                    } else if name.ends_with("journal_ref") {
+                        cur.journal_ref = (!txt.is_empty()).then_some(txt);
+                    } else if name.ends_with("comment") {
+                        cur.comment = (!txt.is_empty()).then_some(txt);
+                    } else if name.ends_with("id") {
                         cur.id_url = txt;
                     } else if name.ends_with("title") {
                         cur.title = txt;
@@ -378,7 +409,14 @@ fn parse_atom(body: &str) -> (Option<u64>, Vec<ArxivPaper>, Vec<&'static str>) {
                             .pdf_url
                             .clone()
                             .or_else(|| Some(arxiv_pdf_url(&arxiv_id)));
+                        // Some entries carry several DOIs separated by whitespace; link the first.
+                        let doi = cur
+                            .doi
+                            .as_deref()
+                            .and_then(|d| d.split_whitespace().next())
+                            .map(str::to_string);
                         papers.push(ArxivPaper {
+                            version: arxiv_version_from_id(&arxiv_id),
                             arxiv_id,
                             url: url.clone(),
                             pdf_url,
@@ -389,6 +427,10 @@ fn parse_atom(body: &str) -> (Option<u64>, Vec<ArxivPaper>, Vec<&'static str>) {
                             authors: cur.authors.clone(),
                             categories: cur.categories.clone(),
                             primary_category: cur.primary_category.clone(),
+                            doi_url: doi.as_deref().map(doi_url),
+                            doi,
+                            journal_ref: cur.journal_ref.clone(),
+                            comment: cur.comment.clone(),
                         });
                     }
                 }
@@ -586,6 +628,54 @@ mod tests {
             .contains("0805.3415v1"));
         assert_eq!(papers[0].authors.len(), 2);
         assert!(papers[0].categories.iter().any(|c| c == "cs.LG"));
+        assert_eq!(papers[0].version, Some(1));
+        assert_eq!(papers[1].version, Some(2));
+        assert_eq!(papers[0].doi, None);
+        assert_eq!(papers[0].doi_url, None);
+    }
+
+    #[test]
+    fn parse_atom_extracts_doi_journal_ref_comment_and_version() {
+        let xml = r#"
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:arxiv="http://arxiv.org/schemas/atom">
+  <entry>
+    <id>http://arxiv.org/abs/1706.03762v7</id>
+    <updated>2023-08-02T00:41:18Z</updated>
+    <published>2017-06-12T17:57:34Z</published>
+    <title>Attention Is All You Need</title>
+    <summary>Transformers.</summary>
+    <author><name>Ashish Vaswani</name></author>
+    <arxiv:doi>10.48550/arXiv.1706.03762</arxiv:doi>
+    <arxiv:comment>15 pages, 5 figures</arxiv:comment>
+    <arxiv:journal_ref>Advances in Neural Information Processing Systems 30 (2017)</arxiv:journal_ref>
+    <link title="doi" href="http://dx.doi.org/10.48550/arXiv.1706.03762" rel="related"/>
+    <arxiv:primary_category term="cs.CL" scheme="http://arxiv.org/schemas/atom"/>
+    <category term="cs.CL" scheme="http://arxiv.org/schemas/atom"/>
+  </entry>
+</feed>
+"#;
+        let (_total, papers, warnings) = parse_atom(xml);
+        assert!(warnings.is_empty());
+        let p = &papers[0];
+        assert_eq!(p.arxiv_id, "1706.03762v7");
+        assert_eq!(p.version, Some(7));
+        assert_eq!(p.published.as_deref(), Some("2017-06-12T17:57:34Z"));
+        assert_eq!(p.updated.as_deref(), Some("2023-08-02T00:41:18Z"));
+        assert_eq!(p.doi.as_deref(), Some("10.48550/arXiv.1706.03762"));
+        assert_eq!(
+            p.doi_url.as_deref(),
+            Some("https://doi.org/10.48550/arXiv.1706.03762")
+        );
+        assert_eq!(
+            p.journal_ref.as_deref(),
+            Some("Advances in Neural Information Processing Systems 30 (2017)")
+        );
+        assert_eq!(p.comment.as_deref(), Some("15 pages, 5 figures"));
+        assert_eq!(p.primary_category.as_deref(), Some("cs.CL"));
+
+        // Old-style ids carry versions too; unversioned ids have none.
+        assert_eq!(arxiv_version_from_id("cs/9901001v3"), Some(3));
+        assert_eq!(arxiv_version_from_id("1706.03762"), None);
     }
 
     #[test]
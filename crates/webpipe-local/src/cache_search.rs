@@ -223,6 +223,7 @@ pub fn cache_search_extract(
     max_block_chars: usize,
     include_text: bool,
     max_scan_entries: usize,
+    keep_no_overlap_docs: bool,
 ) -> CacheSearchResult {
     let mut warnings: Vec<&'static str> = Vec::new();
 
@@ -314,6 +315,7 @@ pub fn cache_search_extract(
             max_outline_items,
             max_blocks,
             max_block_chars,
+            truncation_strategy: extract::TruncationStrategy::Head,
         };
         let pipe =
             extract::extract_pipeline_from_bytes(bytes, content_type.as_deref(), &final_url, cfg);
@@ -324,14 +326,22 @@ pub fn cache_search_extract(
         // query matching yields nothing, producing chunks with score=1. That’s useful for
         // urls-mode with no query, but it pollutes offline cache-corpus searches (it can cause
         // arbitrary PDFs to appear in the top chunks list).
+        //
+        // `keep_no_overlap_docs` (hybrid scoring) keeps those filler chunks at doc score 0 so a
+        // semantic scorer can still surface paraphrases that share no terms with the query.
         let mut chunks = pipe.chunks.clone();
-        if !chunks.is_empty() && chunks.iter().all(|c| c.score <= 1) {
+        let no_overlap = !chunks.is_empty() && chunks.iter().all(|c| c.score <= 1);
+        if no_overlap && !keep_no_overlap_docs {
             chunks.clear();
         }
-        let score: u64 = chunks.iter().map(|c| c.score).sum();
+        let score: u64 = if no_overlap {
+            0
+        } else {
+            chunks.iter().map(|c| c.score).sum()
+        };
         let extraction_engine = pipe.extracted.engine.to_string();
         let mut doc_warnings: Vec<&'static str> = pipe.extracted.warnings.clone();
-        if !pipe.chunks.is_empty() && chunks.is_empty() {
+        if no_overlap {
             doc_warnings.push("no_query_overlap_doc");
         }
         if (bytes_full.len() as u64) > max_bytes {
@@ -389,7 +399,7 @@ pub fn cache_search_extract(
     // Many cached docs are unrelated to a given query; keeping them bloats the payload and
     // increases the chance that “query-less fallback” snippets show up downstream.
     let before = hits.len();
-    hits.retain(|h| h.score > 0 && !h.chunks.is_empty());
+    hits.retain(|h| (keep_no_overlap_docs || h.score > 0) && !h.chunks.is_empty());
     if hits.len() < before {
         warnings.push("no_query_overlap_docs_dropped");
     }
@@ -1,4 +1,4 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 fn canonicalize_url_for_compare(url: &str) -> String {
     // Best-effort canonicalization for overlap metrics:
@@ -79,6 +79,265 @@ pub fn text_jaccard(a: &str, b: &str, k: usize) -> f64 {
     jaccard(&sa, &sb)
 }
 
+/// Token-level (precision, recall) of `extracted` against a `golden` reference text.
+///
+/// Tokens are compared as multisets (a token repeated twice in the golden must be extracted twice
+/// to be fully recalled). Empty-vs-empty is a perfect score; empty-vs-nonempty scores 0.
+pub fn token_precision_recall(extracted: &str, golden: &str) -> (f64, f64) {
+    fn counts(tokens: Vec<String>) -> BTreeMap<String, usize> {
+        let mut m = BTreeMap::new();
+        for t in tokens {
+            *m.entry(t).or_insert(0) += 1;
+        }
+        m
+    }
+    let e = counts(tokenize(extracted));
+    let g = counts(tokenize(golden));
+    let n_e: usize = e.values().sum();
+    let n_g: usize = g.values().sum();
+    if n_e == 0 && n_g == 0 {
+        return (1.0, 1.0);
+    }
+    let overlap: usize = e
+        .iter()
+        .map(|(t, n)| (*n).min(g.get(t).copied().unwrap_or(0)))
+        .sum();
+    let precision = if n_e == 0 {
+        0.0
+    } else {
+        overlap as f64 / n_e as f64
+    };
+    let recall = if n_g == 0 {
+        0.0
+    } else {
+        overlap as f64 / n_g as f64
+    };
+    (precision, recall)
+}
+
+/// One line of a [`DiffSummary`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "op", content = "line", rename_all = "snake_case")]
+pub enum DiffLine {
+    Equal(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Line-level diff of two texts, from [`text_diff`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DiffSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+    /// `2 * unchanged / (old lines + new lines)`; 1.0 when both sides are empty.
+    pub similarity: f64,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Edit distance past which [`text_diff`] stops looking for a minimal diff and reports the
+/// remaining middle as removed-then-added (bounds time and memory on rewrites).
+const MAX_DIFF_EDITS: usize = 4_000;
+
+/// Line-level diff of `old` vs `new` (Myers). Lines are compared with trailing whitespace trimmed,
+/// and blank lines are ignored: extracted text reflows blank lines freely between fetches.
+pub fn text_diff(old: &str, new: &str) -> DiffSummary {
+    fn lines(s: &str) -> Vec<&str> {
+        s.lines()
+            .map(str::trim_end)
+            .filter(|l| !l.trim().is_empty())
+            .collect()
+    }
+    let a = lines(old);
+    let b = lines(new);
+
+    // Common prefix/suffix are equal by definition; only the middle needs the edit search.
+    let pre = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suf = a[pre..]
+        .iter()
+        .rev()
+        .zip(b[pre..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (am, bm) = (&a[pre..a.len() - suf], &b[pre..b.len() - suf]);
+
+    let mut out: Vec<DiffLine> = a[..pre]
+        .iter()
+        .map(|l| DiffLine::Equal(l.to_string()))
+        .collect();
+    out.extend(myers(am, bm).unwrap_or_else(|| {
+        am.iter()
+            .map(|l| DiffLine::Removed(l.to_string()))
+            .chain(bm.iter().map(|l| DiffLine::Added(l.to_string())))
+            .collect()
+    }));
+    out.extend(
+        a[a.len() - suf..]
+            .iter()
+            .map(|l| DiffLine::Equal(l.to_string())),
+    );
+
+    let count = |f: fn(&DiffLine) -> bool| out.iter().filter(|l| f(l)).count();
+    let unchanged = count(|l| matches!(l, DiffLine::Equal(_)));
+    let total = a.len() + b.len();
+    DiffSummary {
+        added: count(|l| matches!(l, DiffLine::Added(_))),
+        removed: count(|l| matches!(l, DiffLine::Removed(_))),
+        unchanged,
+        similarity: if total == 0 {
+            1.0
+        } else {
+            (2 * unchanged) as f64 / total as f64
+        },
+        lines: out,
+    }
+}
+
+/// Minimal edit script (Myers' O(ND) greedy search with backtracking), or `None` past
+/// [`MAX_DIFF_EDITS`].
+fn myers(a: &[&str], b: &[&str]) -> Option<Vec<DiffLine>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (a.len() + b.len()).min(MAX_DIFF_EDITS) as isize;
+    let off = max + 1;
+    let mut v = vec![0isize; 2 * off as usize + 1];
+    // v as it was before each round d, for the backtrack.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut done = n == 0 && m == 0;
+    let mut d_end = 0;
+    for d in 0..=max {
+        if done {
+            break;
+        }
+        trace.push(v[(off - d) as usize..=(off + d) as usize].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let mut x =
+                if k == -d || (k != d && v[(off + k - 1) as usize] < v[(off + k + 1) as usize]) {
+                    v[(off + k + 1) as usize]
+                } else {
+                    v[(off + k - 1) as usize] + 1
+                };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(off + k) as usize] = x;
+            if x >= n && y >= m {
+                done = true;
+                d_end = d;
+                break;
+            }
+            k += 2;
+        }
+    }
+    if !done {
+        return None;
+    }
+
+    let mut rev: Vec<DiffLine> = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..=d_end).rev() {
+        let prev = &trace[d as usize];
+        let at = |k: isize| prev[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            rev.push(DiffLine::Equal(a[x as usize].to_string()));
+        }
+        if x == prev_x {
+            y -= 1;
+            rev.push(DiffLine::Added(b[y as usize].to_string()));
+        } else {
+            x -= 1;
+            rev.push(DiffLine::Removed(a[x as usize].to_string()));
+        }
+    }
+    while x > 0 && y > 0 {
+        x -= 1;
+        y -= 1;
+        rev.push(DiffLine::Equal(a[x as usize].to_string()));
+    }
+    rev.reverse();
+    Some(rev)
+}
+
+impl DiffSummary {
+    /// Unified-diff-style hunks (`@@ -l,n +l,n @@`, then ` `/`-`/`+` lines) with `context`
+    /// unchanged lines around each change, cut at `max_lines` output lines. The bool is true
+    /// when the cut dropped something.
+    pub fn unified(&self, context: usize, max_lines: usize) -> (String, bool) {
+        let changed: Vec<usize> = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| !matches!(l, DiffLine::Equal(_)))
+            .map(|(i, _)| i)
+            .collect();
+        // Merge change runs whose context windows touch into hunks of line indexes.
+        let mut hunks: Vec<(usize, usize)> = Vec::new();
+        for &i in &changed {
+            let lo = i.saturating_sub(context);
+            let hi = (i + context + 1).min(self.lines.len());
+            match hunks.last_mut() {
+                Some(h) if lo <= h.1 => h.1 = hi,
+                _ => hunks.push((lo, hi)),
+            }
+        }
+        // 1-based line numbers on each side at each index.
+        let mut old_no = Vec::with_capacity(self.lines.len());
+        let mut new_no = Vec::with_capacity(self.lines.len());
+        let (mut o, mut n) = (1usize, 1usize);
+        for l in &self.lines {
+            old_no.push(o);
+            new_no.push(n);
+            match l {
+                DiffLine::Equal(_) => {
+                    o += 1;
+                    n += 1;
+                }
+                DiffLine::Removed(_) => o += 1,
+                DiffLine::Added(_) => n += 1,
+            }
+        }
+
+        let mut out: Vec<String> = Vec::new();
+        let mut truncated = false;
+        'hunks: for (lo, hi) in hunks {
+            let span = &self.lines[lo..hi];
+            let olen = span
+                .iter()
+                .filter(|l| !matches!(l, DiffLine::Added(_)))
+                .count();
+            let nlen = span
+                .iter()
+                .filter(|l| !matches!(l, DiffLine::Removed(_)))
+                .count();
+            let header = format!("@@ -{},{} +{},{} @@", old_no[lo], olen, new_no[lo], nlen);
+            for line in std::iter::once(header).chain(span.iter().map(|l| match l {
+                DiffLine::Equal(s) => format!(" {s}"),
+                DiffLine::Removed(s) => format!("-{s}"),
+                DiffLine::Added(s) => format!("+{s}"),
+            })) {
+                if out.len() >= max_lines {
+                    truncated = true;
+                    break 'hunks;
+                }
+                out.push(line);
+            }
+        }
+        (out.join("\n"), truncated)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,4 +364,96 @@ mod tests {
         let j = text_jaccard(a, b, 2);
         assert!(j > 0.5, "j={}", j);
     }
+
+    #[test]
+    fn token_precision_recall_counts_multiset_overlap() {
+        let (p, r) = token_precision_recall("hello hello world nav", "Hello world, hello again.");
+        assert!((p - 0.75).abs() < 1e-9, "p={p}");
+        assert!((r - 0.75).abs() < 1e-9, "r={r}");
+        assert_eq!(token_precision_recall("", ""), (1.0, 1.0));
+        assert_eq!(token_precision_recall("", "x"), (0.0, 0.0));
+    }
+
+    #[test]
+    fn text_diff_counts_line_changes_and_renders_bounded_hunks() {
+        let old = "Title\n\nintro\nstep one\nstep two\nstep three\nfooter\n";
+        let new = "Title\nintro\nstep one\nstep 2\nstep three\nstep four\nfooter  \n";
+        let d = text_diff(old, new);
+        assert_eq!((d.added, d.removed, d.unchanged), (2, 1, 5));
+        assert!(
+            (d.similarity - 10.0 / 13.0).abs() < 1e-9,
+            "{}",
+            d.similarity
+        );
+
+        let (u, truncated) = d.unified(1, 100);
+        assert!(!truncated);
+        assert_eq!(
+            u,
+            "@@ -3,4 +3,5 @@\n step one\n-step two\n+step 2\n step three\n+step four\n footer"
+        );
+        let (u, truncated) = d.unified(0, 2);
+        assert!(truncated);
+        assert_eq!(u.lines().count(), 2);
+
+        let same = text_diff("a\nb\n", "a\n\nb");
+        assert_eq!((same.added, same.removed, same.similarity), (0, 0, 1.0));
+        assert_eq!(same.unified(3, 100), (String::new(), false));
+        assert_eq!(text_diff("", "").similarity, 1.0);
+        let fresh = text_diff("", "x\ny");
+        assert_eq!((fresh.added, fresh.similarity), (2, 0.0));
+    }
+
+    #[test]
+    fn text_diff_matches_a_brute_force_lcs() {
+        // Small alphabets force many overlapping matches.
+        let cases = [
+            ("abcabba", "cbabac"),
+            ("xaxbxc", "abc"),
+            ("aaaa", "aa"),
+            ("abc", "def"),
+            ("", "ab"),
+        ];
+        for (a, b) in cases {
+            let split = |s: &str| {
+                s.chars()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            let d = text_diff(&split(a), &split(b));
+            let (av, bv): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+            let mut t = vec![vec![0usize; bv.len() + 1]; av.len() + 1];
+            for i in (0..av.len()).rev() {
+                for j in (0..bv.len()).rev() {
+                    t[i][j] = if av[i] == bv[j] {
+                        t[i + 1][j + 1] + 1
+                    } else {
+                        t[i + 1][j].max(t[i][j + 1])
+                    };
+                }
+            }
+            assert_eq!(d.unchanged, t[0][0], "{a} vs {b}");
+            assert_eq!(d.removed, av.len() - t[0][0], "{a} vs {b}");
+            assert_eq!(d.added, bv.len() - t[0][0], "{a} vs {b}");
+            // Replaying the script rebuilds both sides.
+            let side = |keep: fn(&DiffLine) -> Option<&String>| {
+                d.lines.iter().filter_map(keep).cloned().collect::<String>()
+            };
+            assert_eq!(
+                side(|l| match l {
+                    DiffLine::Equal(s) | DiffLine::Removed(s) => Some(s),
+                    DiffLine::Added(_) => None,
+                }),
+                a
+            );
+            assert_eq!(
+                side(|l| match l {
+                    DiffLine::Equal(s) | DiffLine::Added(s) => Some(s),
+                    DiffLine::Removed(_) => None,
+                }),
+                b
+            );
+        }
+    }
 }
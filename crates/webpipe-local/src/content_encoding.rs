@@ -0,0 +1,331 @@
+//! Incremental `Content-Encoding` decoding for fetched bodies.
+//!
+//! We decode ourselves (instead of letting reqwest do it transparently) so the fetcher can
+//! count on-wire bytes separately from decoded bytes, and so `max_bytes` caps the *decoded*
+//! size even for highly compressible bodies. Supported: `gzip`, `deflate`, `br` and `zstd`.
+//!
+//! Misconfigured servers are handled leniently: a body that fails to decode (e.g. labelled
+//! `gzip` but sent plain) is kept as received and flagged via [`BodyDecoder::decode_failed`],
+//! and a gzip body that decodes to another gzip stream is unwrapped once more.
+
+use std::io::Write;
+
+/// Largest slice of compressed input handed to a decoder at once.
+///
+/// Keeps decompression-bomb expansion per step small enough that `max_bytes` checks stay tight.
+const FEED_SLICE: usize = 4 * 1024;
+
+/// Decoded output is never buffered past this multiple of the caller's decoded-size cap.
+///
+/// A single [`FEED_SLICE`] of a deflate bomb expands ~1000x; past the cap, output is discarded
+/// so memory stays bounded no matter what arrives.
+pub const DECODED_CAP_MULTIPLE: usize = 2;
+
+/// Output buffer that silently drops bytes past `cap`.
+///
+/// Dropping (rather than erroring) keeps the decoder's state consistent; the caller sees the
+/// body as truncated.
+pub struct CappedSink {
+    buf: Vec<u8>,
+    cap: usize,
+    overflowed: bool,
+}
+
+impl CappedSink {
+    fn new(cap: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            cap,
+            overflowed: false,
+        }
+    }
+}
+
+impl Write for CappedSink {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        let room = self.cap.saturating_sub(self.buf.len());
+        if data.len() > room {
+            self.overflowed = true;
+        }
+        self.buf.extend_from_slice(&data[..data.len().min(room)]);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Inner {
+    Identity(Vec<u8>),
+    Gzip(flate2::write::GzDecoder<CappedSink>),
+    Deflate(flate2::write::ZlibDecoder<CappedSink>),
+    Brotli(Box<brotli_decompressor::DecompressorWriter<CappedSink>>),
+    Zstd(Box<ZstdWriter>),
+}
+
+type ZstdWriter = zstd::stream::zio::Writer<CappedSink, zstd::stream::raw::Decoder<'static>>;
+
+pub struct BodyDecoder {
+    inner: Inner,
+    /// Input fed so far (up to `cap`), kept for the as-is fallback.
+    raw: Vec<u8>,
+    cap: usize,
+    decode_failed: bool,
+    /// Set when the stream errored after producing output (e.g. trailing junk): keep what was
+    /// decoded and ignore the rest.
+    stopped: bool,
+}
+
+impl BodyDecoder {
+    /// Decoder for a `Content-Encoding` header value; unknown/absent encodings pass through.
+    ///
+    /// `max_decoded` is the caller's decoded-size cap; output beyond
+    /// [`DECODED_CAP_MULTIPLE`] times that is discarded.
+    pub fn for_encoding(content_encoding: Option<&str>, max_decoded: usize) -> Self {
+        let cap = max_decoded.saturating_mul(DECODED_CAP_MULTIPLE);
+        let ce = content_encoding.unwrap_or("").trim().to_ascii_lowercase();
+        let inner = match ce.as_str() {
+            "gzip" | "x-gzip" => Inner::Gzip(flate2::write::GzDecoder::new(CappedSink::new(cap))),
+            "deflate" => Inner::Deflate(flate2::write::ZlibDecoder::new(CappedSink::new(cap))),
+            "br" => Inner::Brotli(Box::new(brotli_decompressor::DecompressorWriter::new(
+                CappedSink::new(cap),
+                FEED_SLICE,
+            ))),
+            "zstd" => match zstd::stream::raw::Decoder::new() {
+                Ok(d) => Inner::Zstd(Box::new(zstd::stream::zio::Writer::new(
+                    CappedSink::new(cap),
+                    d,
+                ))),
+                Err(_) => Inner::Identity(Vec::new()),
+            },
+            _ => Inner::Identity(Vec::new()),
+        };
+        Self {
+            inner,
+            raw: Vec::new(),
+            cap,
+            decode_failed: false,
+            stopped: false,
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        matches!(self.inner, Inner::Identity(_))
+    }
+
+    /// True once decoding failed and the body fell back to the bytes as received.
+    pub fn decode_failed(&self) -> bool {
+        self.decode_failed
+    }
+
+    /// Decoded bytes produced so far.
+    pub fn decoded_len(&self) -> usize {
+        match &self.inner {
+            Inner::Identity(v) => v.len(),
+            Inner::Gzip(d) => d.get_ref().buf.len(),
+            Inner::Deflate(d) => d.get_ref().buf.len(),
+            Inner::Brotli(d) => d.get_ref().buf.len(),
+            Inner::Zstd(d) => d.writer().buf.len(),
+        }
+    }
+
+    /// Feed compressed bytes, stopping early once more than `max_decoded` bytes are available.
+    ///
+    /// A decode error before any output switches the decoder to pass-through: everything
+    /// received so far (and the rest of `chunk`) is kept as-is. A later error keeps the output
+    /// decoded so far. Returns how many input bytes were consumed.
+    pub fn feed(&mut self, chunk: &[u8], max_decoded: usize) -> usize {
+        if let Inner::Identity(v) = &mut self.inner {
+            v.extend_from_slice(chunk);
+            return chunk.len();
+        }
+        if self.stopped {
+            return chunk.len();
+        }
+        let mut used = 0usize;
+        for part in chunk.chunks(FEED_SLICE) {
+            let r = match &mut self.inner {
+                Inner::Identity(_) => unreachable!(),
+                Inner::Gzip(d) => d.write_all(part).and_then(|_| d.flush()),
+                Inner::Deflate(d) => d.write_all(part).and_then(|_| d.flush()),
+                Inner::Brotli(d) => d.write_all(part).and_then(|_| d.flush()),
+                Inner::Zstd(d) => d.write_all(part).and_then(|_| d.flush()),
+            };
+            if r.is_err() {
+                if self.decoded_len() > 0 {
+                    self.stopped = true;
+                    return chunk.len();
+                }
+                self.fall_back();
+                return used + self.feed(&chunk[used..], max_decoded);
+            }
+            let room = self.cap.saturating_sub(self.raw.len());
+            self.raw.extend_from_slice(&part[..part.len().min(room)]);
+            used += part.len();
+            if self.decoded_len() > max_decoded {
+                break;
+            }
+        }
+        used
+    }
+
+    /// Switch to pass-through over the raw input seen so far.
+    fn fall_back(&mut self) {
+        self.decode_failed = true;
+        self.inner = Inner::Identity(std::mem::take(&mut self.raw));
+    }
+
+    /// Decoded output, and whether decoding failed (so the output is the input as received).
+    ///
+    /// Incomplete streams (e.g. after truncation) yield what was decoded so far. A stream that
+    /// produced nothing from non-empty input counts as a decode failure, and a gzip body wrapping
+    /// another gzip stream is unwrapped.
+    pub fn finish(self) -> (Vec<u8>, bool) {
+        self.finish_layer(true)
+    }
+
+    fn finish_layer(self, unwrap_nested: bool) -> (Vec<u8>, bool) {
+        let was_gzip = matches!(self.inner, Inner::Gzip(_));
+        let (out, overflowed, complete) = match self.inner {
+            Inner::Identity(v) => return (v, self.decode_failed),
+            Inner::Gzip(mut d) => {
+                let complete = d.try_finish().is_ok();
+                let s = d.get_mut();
+                (std::mem::take(&mut s.buf), s.overflowed, complete)
+            }
+            Inner::Deflate(mut d) => {
+                let complete = d.try_finish().is_ok();
+                let s = d.get_mut();
+                (std::mem::take(&mut s.buf), s.overflowed, complete)
+            }
+            Inner::Brotli(mut d) => {
+                let complete = d.close().is_ok();
+                match d.into_inner() {
+                    Ok(s) | Err(s) => (s.buf, s.overflowed, complete),
+                }
+            }
+            Inner::Zstd(mut d) => {
+                let complete = d.finish().is_ok();
+                let (s, _) = d.into_inner();
+                (s.buf, s.overflowed, complete)
+            }
+        };
+        // Nothing decoded and the stream never completed: not really encoded this way (an empty
+        // body that was encoded still completes).
+        if out.is_empty() && !complete && !self.raw.is_empty() {
+            return (self.raw, true);
+        }
+        // A double-gzipped body: still gzip after one complete pass.
+        if unwrap_nested && was_gzip && complete && !overflowed && out.starts_with(&[0x1f, 0x8b]) {
+            let mut nested = Self::for_encoding(Some("gzip"), self.cap / DECODED_CAP_MULTIPLE);
+            nested.feed(&out, usize::MAX);
+            if let (unwrapped, false) = nested.finish_layer(false) {
+                return (unwrapped, false);
+            }
+        }
+        (out, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        enc.write_all(data).unwrap();
+        enc.finish().unwrap()
+    }
+
+    #[test]
+    fn gzip_decodes_incrementally_and_respects_cap() {
+        let plain = "hello compressible world ".repeat(4_000);
+        let gz = gzip(plain.as_bytes());
+
+        let mut d = BodyDecoder::for_encoding(Some("gzip"), usize::MAX);
+        for c in gz.chunks(100) {
+            d.feed(c, usize::MAX);
+        }
+        assert_eq!(d.finish(), (plain.as_bytes().to_vec(), false));
+
+        let mut d = BodyDecoder::for_encoding(Some("GZIP"), 1_000);
+        let used = d.feed(&gz, 1_000);
+        assert!(d.decoded_len() > 1_000);
+        assert!(used <= gz.len());
+
+        let mut d = BodyDecoder::for_encoding(Some("compress"), usize::MAX);
+        assert!(d.is_identity());
+        d.feed(b"raw", usize::MAX);
+        assert_eq!(d.finish(), (b"raw".to_vec(), false));
+
+        // An encoded empty body completes with no output; it is not a decode failure.
+        let mut d = BodyDecoder::for_encoding(Some("gzip"), usize::MAX);
+        d.feed(&gzip(b""), usize::MAX);
+        assert_eq!(d.finish(), (Vec::new(), false));
+    }
+
+    #[test]
+    fn brotli_and_zstd_decode_incrementally_and_respect_cap() {
+        let plain = "brotli and zstd compressible text ".repeat(4_000);
+        let mut br = Vec::new();
+        brotli::BrotliCompress(
+            &mut plain.as_bytes(),
+            &mut br,
+            &brotli::enc::BrotliEncoderParams::default(),
+        )
+        .unwrap();
+        let zst = zstd::bulk::compress(plain.as_bytes(), 3).unwrap();
+
+        for (ce, body) in [("br", &br), ("zstd", &zst)] {
+            let mut d = BodyDecoder::for_encoding(Some(ce), usize::MAX);
+            assert!(!d.is_identity(), "{ce}");
+            for c in body.chunks(100) {
+                d.feed(c, usize::MAX);
+            }
+            assert_eq!(d.finish(), (plain.as_bytes().to_vec(), false), "{ce}");
+
+            let mut d = BodyDecoder::for_encoding(Some(ce), 1_000);
+            d.feed(body, 1_000);
+            assert!(d.decoded_len() > 1_000, "{ce}");
+            assert!(d.decoded_len() <= 1_000 * DECODED_CAP_MULTIPLE, "{ce}");
+        }
+    }
+
+    #[test]
+    fn mislabelled_and_double_encoded_bodies_fall_back_gracefully() {
+        let plain = b"<html><body>not actually compressed</body></html>";
+        for ce in ["gzip", "deflate", "br", "zstd"] {
+            let mut d = BodyDecoder::for_encoding(Some(ce), usize::MAX);
+            for c in plain.chunks(7) {
+                d.feed(c, usize::MAX);
+            }
+            assert!(d.decode_failed(), "{ce}");
+            assert_eq!(d.finish(), (plain.to_vec(), true), "{ce}");
+        }
+
+        // Too short to fail on the header: still returned as-is.
+        let mut d = BodyDecoder::for_encoding(Some("gzip"), usize::MAX);
+        d.feed(b"ok", usize::MAX);
+        assert_eq!(d.finish(), (b"ok".to_vec(), true));
+
+        let mut d = BodyDecoder::for_encoding(Some("gzip"), usize::MAX);
+        d.feed(&gzip(&gzip(plain)), usize::MAX);
+        assert_eq!(d.finish(), (plain.to_vec(), false));
+    }
+
+    #[test]
+    fn decompression_bomb_output_is_bounded() {
+        let bomb = gzip(&vec![0u8; 16 * 1024 * 1024]);
+        assert!(bomb.len() < 64 * 1024);
+        let max = 10_000;
+        let mut d = BodyDecoder::for_encoding(Some("gzip"), max);
+        let used = d.feed(&bomb, max);
+        assert!(used < bomb.len());
+        assert!(d.decoded_len() > max);
+        assert!(d.decoded_len() <= max * DECODED_CAP_MULTIPLE);
+        let (out, failed) = d.finish();
+        assert!(!failed);
+        assert!(out.len() <= max * DECODED_CAP_MULTIPLE);
+    }
+}
@@ -0,0 +1,190 @@
+//! Helpers for bounded site crawls: robots.txt rules and page titles.
+//!
+//! The crawl loop itself lives in the MCP layer (it owns the fetcher and the politeness state);
+//! everything here is pure and cheap to test.
+
+/// `robots.txt` rules that apply to one user agent.
+///
+/// Matching follows the common (Google/RFC 9309) reading: the longest matching `Allow`/`Disallow`
+/// pattern wins, ties go to `Allow`, `*` matches any run of characters and a trailing `$` anchors
+/// the end. A group for the specific user agent replaces the `*` group.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotsRules {
+    rules: Vec<(bool, String)>,
+    /// `Crawl-delay` in seconds, if the applicable group declared one.
+    pub crawl_delay_s: Option<f64>,
+}
+
+impl RobotsRules {
+    /// Parse `txt` for `user_agent` (a product token such as `webpipe`, matched
+    /// case-insensitively as a prefix of the group's `User-agent` value).
+    pub fn parse(txt: &str, user_agent: &str) -> Self {
+        let ua = user_agent.trim().to_ascii_lowercase();
+        let mut specific: Option<Self> = None;
+        let mut wildcard: Option<Self> = None;
+
+        // Consecutive User-agent lines share one group.
+        let mut agents: Vec<String> = Vec::new();
+        let mut group = Self::default();
+        let mut in_rules = false;
+        let mut flush = |agents: &[String], group: &Self| {
+            for a in agents {
+                if a == "*" {
+                    wildcard.get_or_insert_with(Self::default).merge(group);
+                } else if !ua.is_empty() && ua.starts_with(a.as_str()) {
+                    specific.get_or_insert_with(Self::default).merge(group);
+                }
+            }
+        };
+        for line in txt.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((k, v)) = line.split_once(':') else {
+                continue;
+            };
+            let k = k.trim().to_ascii_lowercase();
+            let v = v.trim();
+            match k.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        flush(&agents, &group);
+                        agents.clear();
+                        group = Self::default();
+                        in_rules = false;
+                    }
+                    agents.push(v.to_ascii_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow allows everything; it adds no rule.
+                    if !v.is_empty() {
+                        group.rules.push((k == "allow", v.to_string()));
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    group.crawl_delay_s = v.parse::<f64>().ok().filter(|d| *d >= 0.0);
+                }
+                _ => {}
+            }
+        }
+        flush(&agents, &group);
+        specific.or(wildcard).unwrap_or_default()
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.rules.extend(other.rules.iter().cloned());
+        if other.crawl_delay_s.is_some() {
+            self.crawl_delay_s = other.crawl_delay_s;
+        }
+    }
+
+    /// Whether `path` (path plus optional `?query`) may be fetched.
+    pub fn allows(&self, path: &str) -> bool {
+        self.matching_rule(path).is_none_or(|(allow, _)| allow)
+    }
+
+    /// The rule that decides `path`, as `(is_allow, pattern)`; `None` when no rule matches
+    /// (allowed).
+    pub fn matching_rule(&self, path: &str) -> Option<(bool, &str)> {
+        let path = if path.is_empty() { "/" } else { path };
+        let mut best: Option<(usize, bool, &str)> = None;
+        for (allow, pat) in &self.rules {
+            if !pattern_matches(pat, path) {
+                continue;
+            }
+            let len = pat.len();
+            best = match best {
+                Some((l, a, p)) if l > len || (l == len && a) => Some((l, a, p)),
+                _ => Some((len, *allow, pat.as_str())),
+            };
+        }
+        best.map(|(_, allow, pat)| (allow, pat))
+    }
+}
+
+fn pattern_matches(pat: &str, path: &str) -> bool {
+    let (pat, anchored) = match pat.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pat, false),
+    };
+    let mut parts = pat.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        let last = i + 1 == parts.len();
+        if last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    // No '*' at all: an anchored pattern must consume the whole path.
+    !anchored || !parts.is_empty() || rest.is_empty()
+}
+
+/// The document `<title>`, whitespace-normalized.
+pub fn page_title(html: &str) -> Option<String> {
+    let doc = html_scraper::Html::parse_document(html);
+    let sel = html_scraper::Selector::parse("title").ok()?;
+    let t = doc
+        .select(&sel)
+        .next()?
+        .text()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!t.is_empty()).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn robots_longest_match_wins_and_specific_group_replaces_wildcard() {
+        let txt = "\
+User-agent: *
+Disallow: /private
+Allow: /private/open
+Disallow: /*.pdf$
+Crawl-delay: 2
+
+User-agent: otherbot
+Disallow: /
+";
+        let r = RobotsRules::parse(txt, "webpipe");
+        assert!(r.allows("/"));
+        assert!(!r.allows("/private/x"));
+        assert!(r.allows("/private/open/page"));
+        assert!(!r.allows("/docs/paper.pdf"));
+        assert!(r.allows("/docs/paper.pdf?download=1"));
+        assert_eq!(r.crawl_delay_s, Some(2.0));
+        assert_eq!(r.matching_rule("/private/x"), Some((false, "/private")));
+        assert_eq!(
+            r.matching_rule("/private/open/page"),
+            Some((true, "/private/open"))
+        );
+        assert_eq!(r.matching_rule("/"), None);
+
+        let other = RobotsRules::parse(txt, "OtherBot/1.0");
+        assert!(!other.allows("/"));
+        assert_eq!(other.crawl_delay_s, None);
+
+        assert!(RobotsRules::parse("", "webpipe").allows("/anything"));
+        assert!(RobotsRules::parse("User-agent: *\nDisallow:\n", "webpipe").allows("/x"));
+    }
+
+    #[test]
+    fn page_title_normalizes_whitespace() {
+        let html = "<html><head><title>\n  Docs  |\tHome </title></head><body></body></html>";
+        assert_eq!(page_title(html).as_deref(), Some("Docs | Home"));
+        assert_eq!(page_title("<p>no title</p>"), None);
+    }
+}
@@ -0,0 +1,78 @@
+//! Stable document IDs, so tools can refer to the same page across calls, and body
+//! fingerprints, so callers can tell whether it changed.
+//!
+//! A `doc_id` is the first 16 hex chars of `sha256(canonical_url)` (the same hash and hex
+//! encoding as the fetch cache keys). Canonicalization is deliberately conservative: it only
+//! removes differences that never change which document is served.
+
+use sha2::{Digest, Sha256};
+
+/// Query parameters that only carry click/campaign attribution.
+const TRACKING_PARAMS: &[&str] = &[
+    "gclid", "dclid", "fbclid", "msclkid", "yclid", "mc_cid", "mc_eid", "_ga", "_gl", "igshid",
+];
+
+fn is_tracking_param(k: &str) -> bool {
+    let k = k.to_ascii_lowercase();
+    k.starts_with("utm_") || TRACKING_PARAMS.contains(&k.as_str())
+}
+
+/// `url` with the fragment and tracking parameters removed (scheme/host are lowercased and
+/// default ports dropped by the URL parser). Unparseable input is returned trimmed.
+pub fn canonical_url(url: &str) -> String {
+    let s = url.trim();
+    let Ok(mut u) = url::Url::parse(s) else {
+        return s.to_string();
+    };
+    u.set_fragment(None);
+    if u.query().is_some() {
+        let kept: Vec<(String, String)> = u
+            .query_pairs()
+            .filter(|(k, _)| !is_tracking_param(k))
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        if kept.is_empty() {
+            u.set_query(None);
+        } else {
+            u.query_pairs_mut().clear().extend_pairs(kept);
+        }
+    }
+    u.to_string()
+}
+
+/// Short, stable ID for the document at `url` (see the module docs).
+pub fn doc_id(url: &str) -> String {
+    let d = Sha256::digest(canonical_url(url).as_bytes());
+    hex::encode(&d[..8])
+}
+
+/// Hex sha256 of a response body (`web_fetch.body_sha256`).
+pub fn body_sha256(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracking_params_and_fragments_do_not_change_the_doc_id() {
+        let base = doc_id("https://example.com/docs/page?id=7");
+        for v in [
+            "https://EXAMPLE.com:443/docs/page?id=7",
+            "https://example.com/docs/page?utm_source=x&id=7&utm_medium=y",
+            "https://example.com/docs/page?id=7&gclid=abc#intro",
+            "  https://example.com/docs/page?id=7&fbclid=1  ",
+        ] {
+            assert_eq!(doc_id(v), base, "{v}");
+        }
+        assert_eq!(base.len(), 16);
+        assert!(base.chars().all(|c| c.is_ascii_hexdigit()));
+
+        assert_ne!(doc_id("https://example.com/docs/page?id=8"), base);
+        assert_eq!(
+            canonical_url("https://example.com/a?utm_campaign=z"),
+            "https://example.com/a"
+        );
+    }
+}
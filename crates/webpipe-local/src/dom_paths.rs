@@ -0,0 +1,169 @@
+//! Map extracted chunks back to the HTML element they came from (debugging aid).
+//!
+//! Chunks are plain text, so the mapping is heuristic: the document's visible text is tokenized
+//! into normalized words, each tagged with its nearest block-level ancestor, and the chunk's
+//! leading words are located as a consecutive run in that stream. The result is a simplified
+//! CSS-ish path such as `article > section:nth-of-type(2) > p`, rooted below `<body>` (or at the
+//! nearest ancestor with an `id`).
+
+use html_scraper::{ElementRef, Html};
+
+/// Elements a chunk can be attributed to (the nearest one wins).
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "li",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "pre",
+    "blockquote",
+    "td",
+    "th",
+    "dd",
+    "dt",
+    "figcaption",
+    "caption",
+    "summary",
+    "div",
+    "section",
+    "article",
+    "main",
+];
+const SKIP_TAGS: &[&str] = &["script", "style", "noscript", "template", "head"];
+/// Leading chunk words tried first as an exact run.
+const ANCHOR_WORDS: usize = 6;
+/// Fallback: any run of this many words within the first [`SCAN_WORDS`] chunk words.
+const FALLBACK_WORDS: usize = 4;
+const SCAN_WORDS: usize = 24;
+const MAX_SEGMENTS: usize = 8;
+
+fn words(s: &str) -> impl Iterator<Item = String> + '_ {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// One `dom_path` per chunk text (same order); `None` when the chunk can't be located.
+pub fn chunk_dom_paths(html: &str, chunk_texts: &[&str]) -> Vec<Option<String>> {
+    let doc = Html::parse_document(html);
+    let mut stream: Vec<(String, ElementRef)> = Vec::new();
+    for node in doc.root_element().descendants() {
+        let Some(t) = node.value().as_text() else {
+            continue;
+        };
+        let mut block = None;
+        let mut skipped = false;
+        for el in node.ancestors().filter_map(ElementRef::wrap) {
+            let name = el.value().name();
+            if SKIP_TAGS.contains(&name) {
+                skipped = true;
+                break;
+            }
+            if block.is_none() && BLOCK_TAGS.contains(&name) {
+                block = Some(el);
+            }
+        }
+        if skipped {
+            continue;
+        }
+        let Some(block) = block else {
+            continue;
+        };
+        stream.extend(words(t).map(|w| (w, block)));
+    }
+
+    chunk_texts
+        .iter()
+        .map(|text| {
+            let ws: Vec<String> = words(text).take(SCAN_WORDS).collect();
+            let anchor = &ws[..ws.len().min(ANCHOR_WORDS)];
+            let hit = find_run(&stream, anchor).or_else(|| {
+                ws.windows(FALLBACK_WORDS)
+                    .find_map(|run| find_run(&stream, run))
+            })?;
+            Some(element_path(stream[hit].1))
+        })
+        .collect()
+}
+
+fn find_run(stream: &[(String, ElementRef)], run: &[String]) -> Option<usize> {
+    if run.is_empty() || run.len() > stream.len() {
+        return None;
+    }
+    (0..=stream.len() - run.len()).find(|&i| run.iter().zip(&stream[i..]).all(|(w, (s, _))| w == s))
+}
+
+fn element_path(el: ElementRef) -> String {
+    let mut segs: Vec<String> = Vec::new();
+    let mut cur = Some(el);
+    while let Some(e) = cur {
+        let name = e.value().name();
+        if name == "body" || name == "html" || segs.len() >= MAX_SEGMENTS {
+            break;
+        }
+        if let Some(id) = e.value().id().filter(|id| !id.is_empty()) {
+            segs.push(format!("{name}#{id}"));
+            break;
+        }
+        let parent = e.parent().and_then(ElementRef::wrap);
+        let mut seg = name.to_string();
+        if let Some(p) = parent {
+            let same: Vec<_> = p
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|c| c.value().name() == name)
+                .collect();
+            if same.len() > 1 {
+                let k = same.iter().position(|c| c.id() == e.id()).unwrap_or(0) + 1;
+                seg.push_str(&format!(":nth-of-type({k})"));
+            }
+        }
+        segs.push(seg);
+        cur = parent;
+    }
+    segs.reverse();
+    segs.join(" > ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_dom_paths_locates_nested_sections_and_stops_at_ids() {
+        let html = r#"<html><head><title>Ignored title words here</title>
+<script>var alpha = "first section body text";</script></head><body>
+<article>
+  <section><h2>Intro</h2><p>First section body text about widgets.</p></section>
+  <section>
+    <p>Second section opens with a claim about gears and levers.</p>
+    <ul><li>one item</li><li>Second list item mentions sprockets twice</li></ul>
+  </section>
+</article>
+<div id="sidebar"><div><p>Sidebar note about unrelated topics entirely.</p></div></div>
+</body></html>"#;
+        let paths = chunk_dom_paths(
+            html,
+            &[
+                "First section body text about widgets.",
+                "Second section opens with a claim about gears and levers. one item",
+                "* Second list item mentions sprockets twice",
+                "Sidebar note about unrelated topics entirely.",
+                "nothing like this appears anywhere on the page",
+            ],
+        );
+        assert_eq!(
+            paths,
+            vec![
+                Some("article > section:nth-of-type(1) > p".to_string()),
+                Some("article > section:nth-of-type(2) > p".to_string()),
+                Some("article > section:nth-of-type(2) > ul > li:nth-of-type(2)".to_string()),
+                Some("div#sidebar > div > p".to_string()),
+                None,
+            ]
+        );
+    }
+}
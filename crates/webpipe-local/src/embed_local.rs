@@ -0,0 +1,272 @@
+//! Local sentence embeddings (no network), behind the `embed-local` feature.
+//!
+//! Loads a BERT-family sentence-embedding model from a directory holding the usual Hugging Face
+//! files (`config.json`, `tokenizer.json`, `model.safetensors`; e.g. a snapshot of
+//! `sentence-transformers/all-MiniLM-L6-v2`) and runs it on the CPU with candle. Vectors are
+//! mean-pooled over the attention mask and L2-normalized, so cosine similarity is a dot product.
+//!
+//! Notes:
+//! - Nothing is downloaded: the model directory must already exist (`no_network`-safe).
+//! - `embed` is CPU-bound and synchronous; async callers should use `spawn_blocking`.
+
+use crate::semantic::Embedder;
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config};
+use std::path::Path;
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+use webpipe_core::{Error, Result};
+
+/// Inputs per forward pass (bounds peak memory on long chunk lists).
+const BATCH: usize = 32;
+
+fn env(key: &str) -> Option<String> {
+    std::env::var(key)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// `WEBPIPE_EMBED_LOCAL_MODEL_DIR`: directory with `config.json`, `tokenizer.json` and
+/// `model.safetensors`.
+pub fn local_model_dir_from_env() -> Option<String> {
+    env("WEBPIPE_EMBED_LOCAL_MODEL_DIR")
+}
+
+/// Model name reported in results and used as the embedding cache key:
+/// `WEBPIPE_EMBED_LOCAL_MODEL`, else `local/<model dir name>`.
+pub fn local_model_id_from_env() -> Option<String> {
+    env("WEBPIPE_EMBED_LOCAL_MODEL").or_else(|| {
+        let dir = local_model_dir_from_env()?;
+        let name = Path::new(&dir).file_name()?.to_string_lossy().to_string();
+        Some(format!("local/{name}"))
+    })
+}
+
+/// Max tokens per input (`WEBPIPE_EMBED_LOCAL_MAX_TOKENS`, default 256; capped by the model's
+/// `max_position_embeddings`).
+pub fn local_max_tokens_from_env() -> usize {
+    env("WEBPIPE_EMBED_LOCAL_MAX_TOKENS")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(256)
+        .clamp(8, 8192)
+}
+
+pub struct LocalEmbedder {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    model_id: String,
+    dims: usize,
+}
+
+impl std::fmt::Debug for LocalEmbedder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalEmbedder")
+            .field("model_id", &self.model_id)
+            .field("dims", &self.dims)
+            .finish()
+    }
+}
+
+fn candle_err(e: candle_core::Error) -> Error {
+    Error::Llm(format!("local embeddings: {e}"))
+}
+
+impl LocalEmbedder {
+    /// Load the model named by `WEBPIPE_EMBED_LOCAL_MODEL_DIR`.
+    pub fn from_env() -> Result<Self> {
+        let dir = local_model_dir_from_env().ok_or_else(|| {
+            Error::NotConfigured("missing WEBPIPE_EMBED_LOCAL_MODEL_DIR".to_string())
+        })?;
+        let model_id = local_model_id_from_env().unwrap_or_else(|| "local".to_string());
+        Self::load(Path::new(&dir), model_id, local_max_tokens_from_env())
+    }
+
+    pub fn load(dir: &Path, model_id: impl Into<String>, max_tokens: usize) -> Result<Self> {
+        let file = |name: &str| {
+            let p = dir.join(name);
+            if p.is_file() {
+                Ok(p)
+            } else {
+                Err(Error::NotConfigured(format!(
+                    "local embeddings model file not found: {}",
+                    p.display()
+                )))
+            }
+        };
+        let config: Config = serde_json::from_slice(
+            &std::fs::read(file("config.json")?)
+                .map_err(|e| Error::NotConfigured(format!("read config.json: {e}")))?,
+        )
+        .map_err(|e| Error::NotSupported(format!("unsupported model config.json: {e}")))?;
+
+        let mut tokenizer = Tokenizer::from_file(file("tokenizer.json")?)
+            .map_err(|e| Error::NotSupported(format!("load tokenizer.json: {e}")))?;
+        let pad_id = config.pad_token_id as u32;
+        let pad_token = tokenizer
+            .id_to_token(pad_id)
+            .unwrap_or_else(|| "[PAD]".to_string());
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            pad_id,
+            pad_token,
+            ..Default::default()
+        }));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: max_tokens.min(config.max_position_embeddings),
+                ..Default::default()
+            }))
+            .map_err(|e| Error::NotSupported(format!("tokenizer truncation: {e}")))?;
+
+        let weights = file("model.safetensors")?;
+        // SAFETY: the file is memory-mapped read-only; we never hand out references that
+        // outlive the mapping (candle copies tensors into its own storage on load).
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(&[weights], DType::F32, &Device::Cpu)
+                .map_err(candle_err)?
+        };
+        let model = BertModel::load(vb, &config).map_err(candle_err)?;
+        Ok(Self {
+            model,
+            tokenizer,
+            model_id: model_id.into(),
+            dims: config.hidden_size,
+        })
+    }
+
+    /// Output vector length (the model's hidden size).
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let enc = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| Error::Llm(format!("local embeddings: tokenize: {e}")))?;
+        let dev = &self.model.device;
+        let rows = |f: &dyn Fn(&tokenizers::Encoding) -> &[u32]| -> Result<Tensor> {
+            let v: Vec<Tensor> = enc
+                .iter()
+                .map(|e| Tensor::new(f(e), dev))
+                .collect::<candle_core::Result<_>>()
+                .map_err(candle_err)?;
+            Tensor::stack(&v, 0).map_err(candle_err)
+        };
+        let ids = rows(&|e| e.get_ids())?;
+        let type_ids = rows(&|e| e.get_type_ids())?;
+        let mask = rows(&|e| e.get_attention_mask())?;
+
+        let pooled = (|| {
+            let hidden = self.model.forward(&ids, &type_ids, Some(&mask))?;
+            // Mean over real tokens only: padding must not change a text's vector.
+            let m = mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+            let sum = hidden.broadcast_mul(&m)?.sum(1)?;
+            let n = m.sum(1)?.clamp(1e-9, f64::MAX)?;
+            let mean = sum.broadcast_div(&n)?;
+            let norm = mean.sqr()?.sum_keepdim(1)?.sqrt()?.clamp(1e-12, f64::MAX)?;
+            mean.broadcast_div(&norm)?.to_vec2::<f32>()
+        })()
+        .map_err(candle_err)?;
+        Ok(pooled)
+    }
+}
+
+impl Embedder for LocalEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut out = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(BATCH) {
+            out.extend(self.embed_batch(batch)?);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tiny randomly-initialized BERT plus a word-level tokenizer, written like a model
+    /// snapshot directory.
+    fn write_tiny_model(dir: &Path) {
+        let config = serde_json::json!({
+            "vocab_size": 8,
+            "hidden_size": 16,
+            "num_hidden_layers": 1,
+            "num_attention_heads": 2,
+            "intermediate_size": 32,
+            "hidden_act": "gelu",
+            "hidden_dropout_prob": 0.0,
+            "max_position_embeddings": 32,
+            "type_vocab_size": 2,
+            "initializer_range": 0.02,
+            "layer_norm_eps": 1e-12,
+            "pad_token_id": 0,
+            "classifier_dropout": null,
+            "model_type": "bert"
+        });
+        std::fs::write(dir.join("config.json"), config.to_string()).unwrap();
+        let tokenizer = serde_json::json!({
+            "version": "1.0",
+            "truncation": null,
+            "padding": null,
+            "added_tokens": [],
+            "normalizer": null,
+            "pre_tokenizer": { "type": "Whitespace" },
+            "post_processor": null,
+            "decoder": null,
+            "model": {
+                "type": "WordLevel",
+                "vocab": { "[PAD]": 0, "[UNK]": 1, "tide": 2, "tables": 3, "bond": 4, "yields": 5 },
+                "unk_token": "[UNK]"
+            }
+        });
+        std::fs::write(dir.join("tokenizer.json"), tokenizer.to_string()).unwrap();
+
+        let cfg: Config = serde_json::from_value(config).unwrap();
+        let varmap = candle_nn::VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        BertModel::load(vb, &cfg).unwrap();
+        varmap.save(dir.join("model.safetensors")).unwrap();
+    }
+
+    #[test]
+    fn local_embedder_vectors_are_normalized_and_padding_independent() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_tiny_model(tmp.path());
+        let e = LocalEmbedder::load(tmp.path(), "local/tiny", 64).unwrap();
+        assert_eq!(e.model_id(), "local/tiny");
+        assert_eq!(e.dims(), 16);
+
+        let texts = vec![
+            "tide".to_string(),
+            "bond yields tide tables".to_string(),
+            "tide".to_string(),
+        ];
+        let v = e.embed(&texts).unwrap();
+        assert_eq!(v.len(), 3);
+        for x in &v {
+            assert_eq!(x.len(), 16);
+            let norm: f32 = x.iter().map(|a| a * a).sum::<f32>().sqrt();
+            assert!((norm - 1.0).abs() < 1e-4, "norm={norm}");
+        }
+        assert_eq!(v[0], v[2]);
+        // "tide" padded to the batch's longest input embeds like "tide" alone.
+        let alone = e.embed(&texts[..1]).unwrap();
+        for (a, b) in alone[0].iter().zip(&v[0]) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn local_embedder_reports_missing_model_files_as_not_configured() {
+        let tmp = tempfile::tempdir().unwrap();
+        let err = LocalEmbedder::load(tmp.path(), "local/none", 64).unwrap_err();
+        assert!(matches!(err, Error::NotConfigured(_)), "{err}");
+    }
+}
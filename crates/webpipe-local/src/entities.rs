@@ -0,0 +1,478 @@
+//! schema.org entities from JSON-LD, microdata, and RDFa.
+//!
+//! All three syntaxes are normalized into `{ type, properties }`. Only top-level items whose type
+//! is in [`ENTITY_TYPES`] are returned; nested items (offers, authors, ...) stay inside their
+//! parent's properties as objects with an `@type` key. When the same thing is described in more
+//! than one syntax (same type, and same `url` or `name`), the entities are merged: the first
+//! syntax wins (JSON-LD, then microdata, then RDFa) and disagreeing values are listed in
+//! `conflicts` with their source.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Types returned as top-level entities (article subtypes included).
+pub const ENTITY_TYPES: &[&str] = &[
+    "Product",
+    "Recipe",
+    "Article",
+    "NewsArticle",
+    "BlogPosting",
+    "TechArticle",
+    "ScholarlyArticle",
+    "Event",
+    "Organization",
+];
+
+const MAX_ENTITIES: usize = 50;
+const MAX_VALUES_PER_PROPERTY: usize = 20;
+const MAX_TEXT_CHARS: usize = 2_000;
+const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PropertyConflictValue {
+    pub source: &'static str,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PropertyConflict {
+    pub property: String,
+    pub values: Vec<PropertyConflictValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Entity {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub properties: Map<String, Value>,
+    /// Syntaxes this entity was found in: "json-ld", "microdata", "rdfa".
+    pub sources: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<PropertyConflict>,
+}
+
+/// Extract and merge schema.org entities from an HTML document.
+pub fn extract_entities(html: &str) -> Vec<Entity> {
+    let doc = html_scraper::Html::parse_document(html);
+    let mut found: Vec<Entity> = Vec::new();
+    found.extend(json_ld_entities(&doc));
+    found.extend(attr_entities(&doc, &MICRODATA));
+    found.extend(attr_entities(&doc, &RDFA));
+
+    let mut out: Vec<Entity> = Vec::new();
+    for e in found {
+        if let Some(o) = out.iter_mut().find(|o| same_entity(o, &e)) {
+            merge_into(o, e);
+        } else if out.len() < MAX_ENTITIES {
+            out.push(e);
+        }
+    }
+    out
+}
+
+/// "https://schema.org/Product", "schema:Product", "Product" => "Product".
+fn short_type(t: &str) -> String {
+    let t = t.trim();
+    let t = t.rsplit(['/', '#']).next().unwrap_or(t);
+    t.rsplit(':').next().unwrap_or(t).to_string()
+}
+
+/// First supported type among space-separated (or array) candidates.
+fn supported_type<'a>(types: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    types
+        .into_iter()
+        .map(short_type)
+        .find(|t| ENTITY_TYPES.contains(&t.as_str()))
+}
+
+fn norm_ws(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn bounded_text(s: &str) -> String {
+    let s = norm_ws(s);
+    match s.char_indices().nth(MAX_TEXT_CHARS) {
+        Some((i, _)) => s[..i].to_string(),
+        None => s,
+    }
+}
+
+// --- JSON-LD -----------------------------------------------------------------------------------
+
+fn json_ld_entities(doc: &html_scraper::Html) -> Vec<Entity> {
+    let Ok(sel) = html_scraper::Selector::parse("script[type]") else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for el in doc.select(&sel) {
+        let ty = el.value().attr("type").unwrap_or("");
+        if !ty.trim().eq_ignore_ascii_case("application/ld+json") {
+            continue;
+        }
+        let raw = el.text().collect::<String>();
+        let Ok(v) = serde_json::from_str::<Value>(raw.trim()) else {
+            continue;
+        };
+        collect_json_ld_nodes(&v, 0, &mut out);
+    }
+    out
+}
+
+fn collect_json_ld_nodes(v: &Value, depth: usize, out: &mut Vec<Entity>) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    match v {
+        Value::Array(xs) => {
+            for x in xs {
+                collect_json_ld_nodes(x, depth + 1, out);
+            }
+        }
+        Value::Object(m) => {
+            if let Some(g) = m.get("@graph") {
+                collect_json_ld_nodes(g, depth + 1, out);
+            }
+            let types: Vec<&str> = match m.get("@type") {
+                Some(Value::String(s)) => vec![s.as_str()],
+                Some(Value::Array(xs)) => xs.iter().filter_map(|x| x.as_str()).collect(),
+                _ => Vec::new(),
+            };
+            if let Some(kind) = supported_type(types) {
+                let mut properties = Map::new();
+                for (k, v) in m {
+                    if k == "@context" || k == "@type" || k == "@graph" {
+                        continue;
+                    }
+                    properties.insert(k.clone(), strip_context(v));
+                }
+                out.push(Entity {
+                    kind,
+                    properties,
+                    sources: vec!["json-ld"],
+                    conflicts: Vec::new(),
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+fn strip_context(v: &Value) -> Value {
+    match v {
+        Value::Object(m) => Value::Object(
+            m.iter()
+                .filter(|(k, _)| k.as_str() != "@context")
+                .map(|(k, v)| (k.clone(), strip_context(v)))
+                .collect(),
+        ),
+        Value::Array(xs) => Value::Array(xs.iter().map(strip_context).collect()),
+        _ => v.clone(),
+    }
+}
+
+// --- microdata / RDFa --------------------------------------------------------------------------
+
+/// Attribute conventions for an attribute-based syntax.
+struct AttrSyntax {
+    source: &'static str,
+    /// Attribute naming properties (`itemprop` / `property`).
+    prop_attr: &'static str,
+    /// Types of an element starting a new item, if it does.
+    item_types: fn(&html_scraper::node::Element) -> Option<Vec<String>>,
+}
+
+const MICRODATA: AttrSyntax = AttrSyntax {
+    source: "microdata",
+    prop_attr: "itemprop",
+    item_types: |el| {
+        el.attr("itemscope")?;
+        Some(
+            el.attr("itemtype")
+                .unwrap_or("")
+                .split_whitespace()
+                .map(short_type)
+                .collect(),
+        )
+    },
+};
+
+const RDFA: AttrSyntax = AttrSyntax {
+    source: "rdfa",
+    prop_attr: "property",
+    item_types: |el| {
+        Some(
+            el.attr("typeof")?
+                .split_whitespace()
+                .map(short_type)
+                .collect(),
+        )
+    },
+};
+
+fn attr_entities(doc: &html_scraper::Html, syn: &AttrSyntax) -> Vec<Entity> {
+    let mut out = Vec::new();
+    for node in doc.root_element().descendants() {
+        let Some(el) = html_scraper::ElementRef::wrap(node) else {
+            continue;
+        };
+        // Top-level items only: an item that is also a property belongs to its parent.
+        if el.value().attr(syn.prop_attr).is_some() {
+            continue;
+        }
+        let Some(types) = (syn.item_types)(el.value()) else {
+            continue;
+        };
+        let Some(kind) = supported_type(types.iter().map(String::as_str)) else {
+            continue;
+        };
+        let mut properties = Map::new();
+        collect_attr_props(el, syn, 0, &mut properties);
+        out.push(Entity {
+            kind,
+            properties,
+            sources: vec![syn.source],
+            conflicts: Vec::new(),
+        });
+    }
+    out
+}
+
+fn collect_attr_props(
+    el: html_scraper::ElementRef,
+    syn: &AttrSyntax,
+    depth: usize,
+    props: &mut Map<String, Value>,
+) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    for child in el.children().filter_map(html_scraper::ElementRef::wrap) {
+        let v = child.value();
+        let nested_types = (syn.item_types)(v);
+        if let Some(names) = v.attr(syn.prop_attr) {
+            let value = match &nested_types {
+                Some(types) => {
+                    let mut m = Map::new();
+                    if let Some(t) = types.first() {
+                        m.insert("@type".to_string(), Value::String(t.clone()));
+                    }
+                    collect_attr_props(child, syn, depth + 1, &mut m);
+                    Value::Object(m)
+                }
+                None => Value::String(attr_value(child)),
+            };
+            for name in names.split_whitespace() {
+                push_prop(props, &short_type(name), value.clone());
+            }
+        }
+        if nested_types.is_none() {
+            collect_attr_props(child, syn, depth + 1, props);
+        }
+    }
+}
+
+/// Property value per the microdata rules (`content` wins, then URL/value attributes, then text).
+fn attr_value(el: html_scraper::ElementRef) -> String {
+    let v = el.value();
+    if let Some(c) = v.attr("content") {
+        return bounded_text(c);
+    }
+    let attr = match v.name() {
+        "a" | "link" | "area" => Some("href"),
+        "img" | "audio" | "video" | "source" | "iframe" | "embed" | "track" => Some("src"),
+        "object" => Some("data"),
+        "data" | "meter" => Some("value"),
+        "time" => Some("datetime"),
+        _ => None,
+    };
+    if let Some(s) = attr.and_then(|a| v.attr(a)) {
+        return s.trim().to_string();
+    }
+    bounded_text(&el.text().collect::<String>())
+}
+
+fn push_prop(props: &mut Map<String, Value>, name: &str, value: Value) {
+    match props.get_mut(name) {
+        None => {
+            props.insert(name.to_string(), value);
+        }
+        Some(Value::Array(xs)) => {
+            if xs.len() < MAX_VALUES_PER_PROPERTY {
+                xs.push(value);
+            }
+        }
+        Some(prev) => {
+            let first = prev.take();
+            *prev = Value::Array(vec![first, value]);
+        }
+    }
+}
+
+// --- merging -----------------------------------------------------------------------------------
+
+/// Comparable form: whitespace/case-insensitive strings, numbers as strings, 1-arrays unwrapped.
+fn canon(v: &Value) -> Value {
+    match v {
+        Value::String(s) => Value::String(norm_ws(s).to_lowercase()),
+        Value::Number(n) => Value::String(n.to_string()),
+        Value::Bool(b) => Value::String(b.to_string()),
+        Value::Array(xs) if xs.len() == 1 => canon(&xs[0]),
+        Value::Array(xs) => Value::Array(xs.iter().map(canon).collect()),
+        Value::Object(m) => Value::Object(m.iter().map(|(k, v)| (k.clone(), canon(v))).collect()),
+        Value::Null => Value::Null,
+    }
+}
+
+fn identity_eq(a: &Entity, b: &Entity, key: &str) -> Option<bool> {
+    let x = a.properties.get(key)?;
+    let y = b.properties.get(key)?;
+    Some(canon(x) == canon(y))
+}
+
+fn same_entity(a: &Entity, b: &Entity) -> bool {
+    if a.kind != b.kind || a.sources.iter().any(|s| b.sources.contains(s)) {
+        return false;
+    }
+    identity_eq(a, b, "url")
+        .or_else(|| identity_eq(a, b, "name"))
+        .unwrap_or(false)
+}
+
+fn merge_into(into: &mut Entity, from: Entity) {
+    let src = from.sources.first().copied().unwrap_or("");
+    let first_src = into.sources.first().copied().unwrap_or("");
+    for (k, v) in from.properties {
+        match into.properties.get(&k) {
+            None => {
+                into.properties.insert(k, v);
+            }
+            Some(prev) if canon(prev) == canon(&v) => {}
+            Some(prev) => {
+                let prev = prev.clone();
+                match into.conflicts.iter_mut().find(|c| c.property == k) {
+                    Some(c) => c.values.push(PropertyConflictValue {
+                        source: src,
+                        value: v,
+                    }),
+                    None => into.conflicts.push(PropertyConflict {
+                        property: k,
+                        values: vec![
+                            PropertyConflictValue {
+                                source: first_src,
+                                value: prev,
+                            },
+                            PropertyConflictValue {
+                                source: src,
+                                value: v,
+                            },
+                        ],
+                    }),
+                }
+            }
+        }
+    }
+    into.sources.extend(from.sources);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn microdata_product_normalizes_nested_items_and_typed_values() {
+        let html = r#"<html><body>
+<div itemscope itemtype="https://schema.org/Product">
+  <h1 itemprop="name">Blue  Widget</h1>
+  <img itemprop="image" src="https://shop.example/w.png">
+  <div itemprop="brand" itemscope itemtype="https://schema.org/Brand">
+    <span itemprop="name">Acme</span>
+  </div>
+  <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+    <span itemprop="price" content="19.99">$19.99</span>
+    <meta itemprop="priceCurrency" content="USD">
+    <link itemprop="availability" href="https://schema.org/InStock">
+  </div>
+  <span itemprop="color">blue</span><span itemprop="color">navy</span>
+</div>
+<div itemscope itemtype="https://schema.org/Person"><span itemprop="name">Skip</span></div>
+</body></html>"#;
+        let es = extract_entities(html);
+        assert_eq!(es.len(), 1, "{es:?}");
+        let e = &es[0];
+        assert_eq!(e.kind, "Product");
+        assert_eq!(e.sources, vec!["microdata"]);
+        let p = &e.properties;
+        assert_eq!(p["name"], "Blue Widget");
+        assert_eq!(p["image"], "https://shop.example/w.png");
+        assert_eq!(p["brand"]["@type"], "Brand");
+        assert_eq!(p["brand"]["name"], "Acme");
+        assert_eq!(p["offers"]["@type"], "Offer");
+        assert_eq!(p["offers"]["price"], "19.99");
+        assert_eq!(p["offers"]["priceCurrency"], "USD");
+        assert_eq!(p["offers"]["availability"], "https://schema.org/InStock");
+        assert_eq!(p["color"], serde_json::json!(["blue", "navy"]));
+        // Nested item properties do not leak into the parent.
+        assert!(p.get("price").is_none());
+    }
+
+    #[test]
+    fn json_ld_recipe_in_graph_is_extracted_without_context() {
+        let html = r#"<html><head><script type="application/ld+json">
+{"@context":"https://schema.org","@graph":[
+  {"@type":"WebSite","name":"Cooking"},
+  {"@type":"Recipe","name":"Pancakes","recipeYield":"4 servings",
+   "recipeIngredient":["2 eggs","1 cup flour"],
+   "author":{"@type":"Person","name":"Sam"},
+   "nutrition":{"@context":"https://schema.org","@type":"NutritionInformation","calories":"250 kcal"}}
+]}
+</script><script type="application/ld+json">{ not json</script></head><body></body></html>"#;
+        let es = extract_entities(html);
+        assert_eq!(es.len(), 1, "{es:?}");
+        let e = &es[0];
+        assert_eq!(e.kind, "Recipe");
+        assert_eq!(e.sources, vec!["json-ld"]);
+        assert_eq!(e.properties["name"], "Pancakes");
+        assert_eq!(
+            e.properties["recipeIngredient"],
+            serde_json::json!(["2 eggs", "1 cup flour"])
+        );
+        assert_eq!(e.properties["author"]["name"], "Sam");
+        assert!(e.properties["nutrition"].get("@context").is_none());
+        assert!(e.conflicts.is_empty());
+    }
+
+    #[test]
+    fn same_entity_across_formats_merges_with_conflict_notes() {
+        let html = r#"<html><head><script type="application/ld+json">
+{"@context":"https://schema.org","@type":"Product","name":"Blue Widget","sku":"W-1","offers":{"@type":"Offer","price":19.99}}
+</script></head><body>
+<div itemscope itemtype="http://schema.org/Product">
+  <span itemprop="name">blue widget</span>
+  <span itemprop="description">A widget.</span>
+  <div itemprop="offers" itemscope itemtype="http://schema.org/Offer"><span itemprop="price">17.50</span></div>
+</div>
+<div vocab="https://schema.org/" typeof="Product">
+  <span property="name">Blue Widget</span><span property="sku">W-2</span>
+</div>
+</body></html>"#;
+        let es = extract_entities(html);
+        assert_eq!(es.len(), 1, "{es:?}");
+        let e = &es[0];
+        assert_eq!(e.sources, vec!["json-ld", "microdata", "rdfa"]);
+        // First syntax wins; missing properties are filled from later ones.
+        assert_eq!(e.properties["sku"], "W-1");
+        assert_eq!(e.properties["description"], "A widget.");
+        let offers = e.conflicts.iter().find(|c| c.property == "offers").unwrap();
+        assert_eq!(offers.values[0].source, "json-ld");
+        assert_eq!(offers.values[1].source, "microdata");
+        let sku = e.conflicts.iter().find(|c| c.property == "sku").unwrap();
+        assert_eq!(
+            sku.values
+                .iter()
+                .map(|v| (v.source, v.value.as_str().unwrap()))
+                .collect::<Vec<_>>(),
+            vec![("json-ld", "W-1"), ("rdfa", "W-2")]
+        );
+        assert!(e.conflicts.iter().all(|c| c.property != "name"));
+    }
+}
@@ -1,5 +1,7 @@
+use crate::office;
 use crate::shellout;
 use crate::textprep;
+use crate::youtube;
 use serde::Serialize;
 use std::io::Cursor;
 use std::process::Command;
@@ -54,6 +56,427 @@ pub fn html_to_text(html: &str, width: usize) -> String {
     }
 }
 
+/// Counters reported by [`HtmlTextStream`], mainly so callers (and tests) can check that work
+/// stayed bounded regardless of input size.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct HtmlStreamStats {
+    /// Number of `feed` calls that were actually processed.
+    pub chunks: usize,
+    /// Input bytes consumed (stops growing once output is clipped).
+    pub bytes_in: usize,
+    /// Largest pending scratch buffer (text + tag bytes) observed.
+    pub peak_scratch_bytes: usize,
+    /// Chars in the final output.
+    pub out_chars: usize,
+    /// True when output reached `max_chars` and the remaining input was ignored.
+    pub clipped: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StreamState {
+    Text,
+    TagOpen,
+    Tag,
+    Comment,
+    Raw,
+}
+
+/// Incremental HTML-to-text converter for bodies too large to parse into a DOM.
+///
+/// Notes:
+/// - A small tag scanner, not a spec tokenizer: block elements become paragraph breaks,
+///   `script`/`style`/`noscript`/`template`/`title` bodies are skipped without buffering.
+/// - Memory is bounded by `max_chars` (output) plus a fixed scratch buffer, independent of how
+///   many bytes are fed in. Once the output is full, further input is ignored.
+/// - On ordinary pages the text matches [`html_to_text`] up to whitespace/wrapping; links are
+///   rendered as their anchor text (no footnotes) and tables as space-separated cells.
+pub struct HtmlTextStream {
+    width: usize,
+    max_chars: usize,
+    state: StreamState,
+    text: Vec<u8>,
+    tag: Vec<u8>,
+    tag_quote: Option<u8>,
+    tag_last: u8,
+    comment_dashes: usize,
+    raw_close: &'static [u8],
+    raw_matched: usize,
+    para: String,
+    para_chars: usize,
+    para_prefix: &'static str,
+    pending_space: bool,
+    pending_break: usize,
+    out: String,
+    out_chars: usize,
+    stats: HtmlStreamStats,
+}
+
+const STREAM_SCRATCH_MAX: usize = 8 * 1024;
+const STREAM_TAG_MAX: usize = 1024;
+
+impl HtmlTextStream {
+    pub fn new(width: usize, max_chars: usize) -> Self {
+        Self {
+            width,
+            max_chars,
+            state: StreamState::Text,
+            text: Vec::new(),
+            tag: Vec::new(),
+            tag_quote: None,
+            tag_last: 0,
+            comment_dashes: 0,
+            raw_close: b"",
+            raw_matched: 0,
+            para: String::new(),
+            para_chars: 0,
+            para_prefix: "",
+            pending_space: false,
+            pending_break: 0,
+            out: String::new(),
+            out_chars: 0,
+            stats: HtmlStreamStats::default(),
+        }
+    }
+
+    fn full(&self) -> bool {
+        self.out_chars + self.para_chars >= self.max_chars
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if self.stats.clipped {
+            return;
+        }
+        self.stats.chunks += 1;
+        self.stats.bytes_in += chunk.len();
+        let mut i = 0usize;
+        while i < chunk.len() {
+            if self.stats.clipped {
+                return;
+            }
+            let b = chunk[i];
+            match self.state {
+                StreamState::Text => {
+                    if b == b'<' {
+                        self.flush_text(true);
+                        self.state = StreamState::TagOpen;
+                    } else {
+                        self.text.push(b);
+                        if self.text.len() >= STREAM_SCRATCH_MAX {
+                            self.flush_text(false);
+                        }
+                    }
+                }
+                StreamState::TagOpen => {
+                    if b.is_ascii_alphabetic() || matches!(b, b'/' | b'!' | b'?') {
+                        self.tag.clear();
+                        self.tag.push(b);
+                        self.tag_quote = None;
+                        self.tag_last = b;
+                        self.state = StreamState::Tag;
+                    } else {
+                        // A bare '<' in text (e.g. "a < b"): keep it and reprocess this byte.
+                        self.text.push(b'<');
+                        self.state = StreamState::Text;
+                        continue;
+                    }
+                }
+                StreamState::Tag => {
+                    if let Some(q) = self.tag_quote {
+                        if b == q {
+                            self.tag_quote = None;
+                        }
+                    } else if (b == b'"' || b == b'\'') && self.tag_last == b'=' {
+                        self.tag_quote = Some(b);
+                    } else if b == b'>' {
+                        self.end_tag();
+                        i += 1;
+                        continue;
+                    }
+                    if !b.is_ascii_whitespace() {
+                        self.tag_last = b;
+                    }
+                    if self.tag.len() < STREAM_TAG_MAX {
+                        self.tag.push(b);
+                    }
+                    if self.tag.as_slice() == b"!--" {
+                        self.state = StreamState::Comment;
+                        self.comment_dashes = 0;
+                    }
+                }
+                StreamState::Comment => {
+                    if b == b'-' {
+                        self.comment_dashes += 1;
+                    } else if b == b'>' && self.comment_dashes >= 2 {
+                        self.state = StreamState::Text;
+                    } else {
+                        self.comment_dashes = 0;
+                    }
+                }
+                StreamState::Raw => {
+                    let want = self.raw_close[self.raw_matched];
+                    if b.to_ascii_lowercase() == want {
+                        self.raw_matched += 1;
+                        if self.raw_matched == self.raw_close.len() {
+                            // Consume the rest of the closing tag as an ordinary (no-op) tag.
+                            self.tag.clear();
+                            self.tag.push(b'/');
+                            self.tag_quote = None;
+                            self.tag_last = b'/';
+                            self.state = StreamState::Tag;
+                        }
+                    } else {
+                        self.raw_matched = usize::from(b == b'<');
+                    }
+                }
+            }
+            self.stats.peak_scratch_bytes = self
+                .stats
+                .peak_scratch_bytes
+                .max(self.text.len() + self.tag.len());
+            i += 1;
+        }
+    }
+
+    fn flush_text(&mut self, complete: bool) {
+        if self.text.is_empty() {
+            return;
+        }
+        let mut cut = self.text.len();
+        if !complete {
+            // Cut after the last ASCII whitespace so entities/codepoints never straddle chunks;
+            // otherwise back off to a UTF-8 boundary.
+            if let Some(p) = self.text.iter().rposition(|b| b.is_ascii_whitespace()) {
+                cut = p + 1;
+            } else {
+                while cut > 0 && self.text.len() - cut < 4 && (self.text[cut - 1] & 0xC0) == 0x80 {
+                    cut -= 1;
+                }
+                if cut > 0 && self.text[cut - 1] >= 0xC0 {
+                    cut -= 1;
+                }
+                if cut == 0 {
+                    cut = self.text.len();
+                }
+            }
+        }
+        let raw: Vec<u8> = self.text.drain(..cut).collect();
+        let s = String::from_utf8_lossy(&raw);
+        let decoded = decode_html_entities(&s);
+        for ch in decoded.chars() {
+            if self.full() {
+                self.stats.clipped = true;
+                return;
+            }
+            if ch.is_whitespace() {
+                self.pending_space = self.para_chars > 0;
+                continue;
+            }
+            if self.pending_space {
+                self.para.push(' ');
+                self.para_chars += 1;
+                self.pending_space = false;
+            }
+            self.para.push(ch);
+            self.para_chars += 1;
+        }
+    }
+
+    fn end_tag(&mut self) {
+        self.state = StreamState::Text;
+        let t = std::mem::take(&mut self.tag);
+        let (close, rest) = match t.first() {
+            Some(b'/') => (true, &t[1..]),
+            Some(b'!') | Some(b'?') => return,
+            _ => (false, &t[..]),
+        };
+        let name_len = rest
+            .iter()
+            .position(|b| !b.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let name = rest[..name_len].to_ascii_lowercase();
+        self.tag = t;
+        self.tag.clear();
+
+        if !close {
+            let raw_close: Option<&'static [u8]> = match name.as_slice() {
+                b"script" => Some(b"</script"),
+                b"style" => Some(b"</style"),
+                b"noscript" => Some(b"</noscript"),
+                b"template" => Some(b"</template"),
+                b"title" => Some(b"</title"),
+                _ => None,
+            };
+            if let Some(pat) = raw_close {
+                self.raw_close = pat;
+                self.raw_matched = 0;
+                self.state = StreamState::Raw;
+                return;
+            }
+        }
+
+        match name.as_slice() {
+            b"br" => self.flush_para(1),
+            // Mirror html2text's emphasis marker so both paths agree on inline emphasis.
+            b"em" | b"i" => {
+                if !close && self.pending_space {
+                    self.para.push(' ');
+                    self.para_chars += 1;
+                    self.pending_space = false;
+                }
+                self.para.push('*');
+                self.para_chars += 1;
+            }
+            b"td" | b"th" => self.pending_space = self.para_chars > 0,
+            b"h1" | b"h2" | b"h3" | b"h4" | b"h5" | b"h6" => {
+                self.flush_para(2);
+                if !close {
+                    self.para_prefix = ["# ", "## ", "### ", "#### ", "##### ", "###### "]
+                        [(name[1] - b'1') as usize];
+                }
+            }
+            b"li" => {
+                self.flush_para(1);
+                if !close {
+                    self.para_prefix = "* ";
+                }
+            }
+            b"tr" | b"dt" | b"dd" | b"option" => self.flush_para(1),
+            b"address" | b"article" | b"aside" | b"blockquote" | b"body" | b"caption"
+            | b"details" | b"dialog" | b"div" | b"dl" | b"fieldset" | b"figcaption" | b"figure"
+            | b"footer" | b"form" | b"header" | b"hgroup" | b"hr" | b"html" | b"main" | b"nav"
+            | b"ol" | b"p" | b"pre" | b"section" | b"summary" | b"table" | b"ul" => {
+                self.flush_para(2)
+            }
+            _ => {}
+        }
+    }
+
+    fn flush_para(&mut self, brk: usize) {
+        if self.para_chars > 0 {
+            if self.out_chars > 0 {
+                for _ in 0..self.pending_break.max(1) {
+                    self.out.push('\n');
+                    self.out_chars += 1;
+                }
+            }
+            let para = std::mem::take(&mut self.para);
+            let prefix = std::mem::take(&mut self.para_prefix);
+            let mut line_chars = 0usize;
+            self.out.push_str(prefix);
+            self.out_chars += prefix.chars().count();
+            line_chars += prefix.chars().count();
+            for (wi, word) in para.split(' ').enumerate() {
+                let wc = word.chars().count();
+                if wi > 0 {
+                    if self.width > 0 && line_chars + 1 + wc > self.width {
+                        self.out.push('\n');
+                        line_chars = 0;
+                    } else {
+                        self.out.push(' ');
+                        line_chars += 1;
+                    }
+                    self.out_chars += 1;
+                }
+                self.out.push_str(word);
+                self.out_chars += wc;
+                line_chars += wc;
+            }
+            self.para_chars = 0;
+            self.pending_break = brk;
+        } else {
+            self.pending_break = self.pending_break.max(brk);
+        }
+        self.pending_space = false;
+    }
+
+    /// Flush pending text and return the output (bounded by `max_chars`) plus counters.
+    pub fn finish(mut self) -> (String, HtmlStreamStats) {
+        if self.state == StreamState::Text {
+            self.flush_text(true);
+        }
+        self.flush_para(0);
+        let (mut out, n, clipped) = truncate_to_chars(&self.out, self.max_chars);
+        if !has_any_text(&out) {
+            out.clear();
+        }
+        self.stats.out_chars = n;
+        self.stats.clipped |= clipped;
+        (out, self.stats)
+    }
+}
+
+/// One-shot wrapper over [`HtmlTextStream`] that feeds `bytes` in fixed-size chunks.
+pub fn html_to_text_streaming(
+    bytes: &[u8],
+    width: usize,
+    max_chars: usize,
+) -> (String, HtmlStreamStats) {
+    let mut st = HtmlTextStream::new(width, max_chars);
+    for chunk in bytes.chunks(64 * 1024) {
+        st.feed(chunk);
+        if st.stats.clipped {
+            break;
+        }
+    }
+    st.finish()
+}
+
+fn decode_html_entities(s: &str) -> std::borrow::Cow<'_, str> {
+    if !s.contains('&') {
+        return std::borrow::Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        let tail = &rest[amp + 1..];
+        let semi = tail.find(';').filter(|&i| i > 0 && i <= 10);
+        let decoded = semi.and_then(|i| {
+            let ent = &tail[..i];
+            let ch = if let Some(num) = ent.strip_prefix('#') {
+                let cp = if let Some(hex) = num.strip_prefix(['x', 'X']) {
+                    u32::from_str_radix(hex, 16).ok()
+                } else {
+                    num.parse::<u32>().ok()
+                };
+                cp.and_then(char::from_u32)
+            } else {
+                match ent {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "nbsp" => Some(' '),
+                    "copy" => Some('©'),
+                    "reg" => Some('®'),
+                    "ndash" => Some('–'),
+                    "mdash" => Some('—'),
+                    "hellip" => Some('…'),
+                    "lsquo" => Some('‘'),
+                    "rsquo" => Some('’'),
+                    "ldquo" => Some('“'),
+                    "rdquo" => Some('”'),
+                    _ => None,
+                }
+            };
+            ch.map(|c| (c, i))
+        });
+        match decoded {
+            Some((c, i)) => {
+                out.push(c);
+                rest = &tail[i + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = tail;
+            }
+        }
+    }
+    out.push_str(rest);
+    std::borrow::Cow::Owned(out)
+}
+
 fn norm_ws(s: &str) -> String {
     s.split_whitespace().collect::<Vec<_>>().join(" ")
 }
@@ -94,6 +517,9 @@ pub struct StructuredBlock {
     pub end_char: usize,
     /// Bounded block text.
     pub text: String,
+    /// Heading level (1 = `h1` / `#`); only set for `kind = "heading"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<u8>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -108,6 +534,76 @@ pub struct ExtractedStructure {
     pub warnings: Vec<&'static str>,
 }
 
+/// One heading-delimited span of [`ExtractedStructure::structure_text`].
+#[derive(Debug, Clone, Serialize)]
+pub struct StructureSection {
+    /// Heading text; `None` for the intro (content before the first heading).
+    pub heading: Option<String>,
+    /// Heading level (1 = `h1` / `#`); 0 for the intro.
+    pub level: u8,
+    pub text: String,
+    /// Character offset into `structure_text`.
+    pub start_char: usize,
+    /// Character offset into `structure_text`.
+    pub end_char: usize,
+}
+
+/// Split a structure into flat sections keyed by heading.
+///
+/// Each section runs from its heading to the next heading of the same or higher level, so a
+/// parent section's text includes its subsections. Content before the first heading becomes an
+/// intro section (`heading: None`, `level: 0`). Bounded by the structure's own block limits.
+pub fn structure_sections(s: &ExtractedStructure) -> Vec<StructureSection> {
+    let chars: Vec<char> = s.structure_text.chars().collect();
+    let span = |a: usize, b: usize| -> (String, usize) {
+        let t: String = chars[a.min(chars.len())..b.min(chars.len())]
+            .iter()
+            .collect::<String>()
+            .trim_end()
+            .to_string();
+        let n = t.chars().count();
+        (t, a + n)
+    };
+    let headings: Vec<(&StructuredBlock, u8)> = s
+        .blocks
+        .iter()
+        .filter(|b| b.kind == "heading")
+        .map(|b| (b, b.level.unwrap_or(1)))
+        .collect();
+
+    let mut out = Vec::new();
+    let intro_end = headings
+        .first()
+        .map(|(b, _)| b.start_char)
+        .unwrap_or(chars.len());
+    let (text, end_char) = span(0, intro_end);
+    if !text.trim().is_empty() {
+        out.push(StructureSection {
+            heading: None,
+            level: 0,
+            text,
+            start_char: 0,
+            end_char,
+        });
+    }
+    for (i, (b, level)) in headings.iter().enumerate() {
+        let end = headings[i + 1..]
+            .iter()
+            .find(|(_, l)| l <= level)
+            .map(|(nb, _)| nb.start_char)
+            .unwrap_or(chars.len());
+        let (text, end_char) = span(b.start_char, end);
+        out.push(StructureSection {
+            heading: Some(b.text.clone()),
+            level: *level,
+            text,
+            start_char: b.start_char,
+            end_char,
+        });
+    }
+    out
+}
+
 /// Extract text from a PDF body (in-memory bytes).
 ///
 /// This is used for endpoints like arXiv `/pdf/...` and other PDF-first sources.
@@ -587,56 +1083,415 @@ pub fn html_main_to_text(html: &str, width: usize) -> Option<String> {
     has_any_text(&out).then_some(out)
 }
 
-fn pick_readability_text(html: &str, max_elems: usize, width: usize) -> Option<String> {
-    // Readability-lite: only consider article/main containers (avoid div/section explosion).
-    let max_elems = max_elems.clamp(50, 50_000);
-    let doc = html_scraper::Html::parse_document(html);
-    let sel = html_scraper::Selector::parse("article, main").ok()?;
-    let sel_p = html_scraper::Selector::parse("p").ok();
-    let mut seen = 0usize;
-    let mut best_score: i64 = 0;
-    let mut best_html: Option<String> = None;
-    for el in doc.select(&sel) {
-        seen += 1;
-        if seen > max_elems {
-            break;
+/// Below this many chars of picked text, [`readability`] falls back to other engines.
+const READABILITY_MIN_CHARS: usize = 250;
+/// DOM nodes visited per page (bounds worst-case scoring cost).
+const READABILITY_MAX_NODES: usize = 50_000;
+
+/// What the readability scorer picked, before fallbacks.
+struct ReadabilityPick {
+    text: String,
+    /// Scored paragraph-like nodes (>= 80 chars) inside the picked content.
+    paragraphs: usize,
+    link_density: f64,
+    /// The page has an `<article>` element or `og:type=article`.
+    article_signal: bool,
+}
+
+impl ReadabilityPick {
+    /// Enough prose in one place that the pick should beat whole-page heuristics.
+    fn looks_like_article(&self) -> bool {
+        self.link_density < 0.25
+            && self.text.chars().count() >= 500
+            && (self.paragraphs >= 5 || (self.article_signal && self.paragraphs >= 3))
+    }
+}
+
+/// Subtrees readability never scores or renders: chrome tags, hidden nodes, ARIA landmarks
+/// other than main content, and boilerplate-named containers (unless also content-named).
+fn readability_skips(el: &html_scraper::ElementRef) -> bool {
+    let v = el.value();
+    let tag = v.name();
+    if matches!(
+        tag,
+        "nav"
+            | "aside"
+            | "footer"
+            | "header"
+            | "form"
+            | "script"
+            | "style"
+            | "noscript"
+            | "iframe"
+            | "svg"
+            | "button"
+            | "select"
+            | "textarea"
+            | "dialog"
+            | "menu"
+            | "template"
+    ) {
+        return true;
+    }
+    if v.attr("hidden").is_some() || v.attr("aria-hidden") == Some("true") {
+        return true;
+    }
+    if matches!(
+        v.attr("role"),
+        Some("navigation" | "complementary" | "contentinfo" | "banner" | "menu" | "dialog")
+    ) {
+        return true;
+    }
+    if matches!(tag, "html" | "body" | "article" | "main") {
+        return false;
+    }
+    let s = class_or_id_lc(el);
+    let maybe_content = ["article", "content", "main", "body", "column"]
+        .iter()
+        .any(|w| s.contains(w));
+    !maybe_content && is_generic_boilerplate_container(el)
+}
+
+/// Starting score for a candidate container: its tag plus +/-25 per content-ish or
+/// boilerplate-ish `class`/`id`.
+fn readability_base_score(el: &html_scraper::ElementRef) -> f64 {
+    const NEGATIVE: [&str; 24] = [
+        "hidden",
+        "banner",
+        "combx",
+        "comment",
+        "com-",
+        "contact",
+        "foot",
+        "footnote",
+        "gdpr",
+        "masthead",
+        "media",
+        "meta",
+        "outbrain",
+        "promo",
+        "related",
+        "scroll",
+        "share",
+        "shoutbox",
+        "sidebar",
+        "skyscraper",
+        "sponsor",
+        "shopping",
+        "tags",
+        "widget",
+    ];
+    const POSITIVE: [&str; 13] = [
+        "article",
+        "body",
+        "content",
+        "entry",
+        "hentry",
+        "h-entry",
+        "main",
+        "page",
+        "pagination",
+        "post",
+        "text",
+        "blog",
+        "story",
+    ];
+    let mut score = match el.value().name() {
+        "div" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    for attr in ["class", "id"] {
+        let Some(v) = el.value().attr(attr) else {
+            continue;
+        };
+        let v = v.to_ascii_lowercase();
+        if NEGATIVE.iter().any(|w| v.contains(w)) {
+            score -= 25.0;
         }
-        if is_generic_boilerplate_container(&el) {
+        if POSITIVE.iter().any(|w| v.contains(w)) {
+            score += 25.0;
+        }
+    }
+    score
+}
+
+/// Share of `el`'s text inside links. Links to a fragment of the page itself (`#...`, or
+/// `base_url#...`) are usually footnotes/TOCs within the article, so they count 30%.
+fn readability_link_density(
+    el: &html_scraper::ElementRef,
+    sel_a: &html_scraper::Selector,
+    page_url: &str,
+) -> f64 {
+    let total = element_text_chars(el);
+    if total == 0 {
+        return 0.0;
+    }
+    let links: f64 = el
+        .select(sel_a)
+        .map(|a| {
+            let href = a.value().attr("href").unwrap_or("").trim();
+            let same_page = href.starts_with('#')
+                || (!page_url.is_empty()
+                    && href
+                        .strip_prefix(page_url)
+                        .is_some_and(|r| r.starts_with('#')));
+            let w = if same_page { 0.3 } else { 1.0 };
+            w * element_text_chars(&a) as f64
+        })
+        .sum();
+    (links / total as f64).min(1.0)
+}
+
+/// Serialize `el` back to HTML without the subtrees `skip` rejects.
+fn readability_write_html(
+    el: html_scraper::ElementRef,
+    skip: &dyn Fn(&html_scraper::ElementRef) -> bool,
+    out: &mut String,
+) {
+    fn escape(s: &str, attr: bool, out: &mut String) {
+        for c in s.chars() {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' if attr => out.push_str("&quot;"),
+                _ => out.push(c),
+            }
+        }
+    }
+    let v = el.value();
+    out.push('<');
+    out.push_str(v.name());
+    for (k, val) in v.attrs() {
+        out.push(' ');
+        out.push_str(k);
+        out.push_str("=\"");
+        escape(val, true, out);
+        out.push('"');
+    }
+    out.push('>');
+    if matches!(
+        v.name(),
+        "br" | "hr" | "img" | "input" | "meta" | "link" | "wbr" | "source" | "col" | "area"
+    ) {
+        return;
+    }
+    for child in el.children() {
+        if let Some(c) = html_scraper::ElementRef::wrap(child) {
+            if !skip(&c) {
+                readability_write_html(c, skip, out);
+            }
+        } else if let Some(t) = child.value().as_text() {
+            escape(t, false, out);
+        }
+    }
+    out.push_str("</");
+    out.push_str(v.name());
+    out.push('>');
+}
+
+fn readability_pick(html: &str, base_url: &str, width: usize) -> Option<ReadabilityPick> {
+    use html_scraper::ElementRef;
+    use std::collections::{HashMap, HashSet};
+
+    let doc = html_scraper::Html::parse_document(html);
+    let sel_a = html_scraper::Selector::parse("a").ok()?;
+    let page_url = base_url.split('#').next().unwrap_or("");
+
+    // One pre-order pass: mark skipped subtrees, score paragraph-like nodes, and credit each
+    // score to up to five ancestors (parent in full, grandparent half, then 1/(3*level)).
+    let mut skipped = HashSet::new();
+    let mut paragraphs = HashMap::new();
+    let mut candidates = Vec::new();
+    let mut candidate_ix = HashMap::new();
+    for node in doc.root_element().descendants().take(READABILITY_MAX_NODES) {
+        let Some(el) = ElementRef::wrap(node) else {
+            continue;
+        };
+        if node.parent().is_some_and(|p| skipped.contains(&p.id())) || readability_skips(&el) {
+            skipped.insert(el.id());
             continue;
         }
-        let txt = element_text_chars(&el);
-        if txt < 50 {
+        let scorable = match el.value().name() {
+            "p" | "pre" | "td" | "blockquote" => true,
+            // A div with no block children is a paragraph in disguise.
+            "div" => !el.children().filter_map(ElementRef::wrap).any(|c| {
+                matches!(
+                    c.value().name(),
+                    "p" | "div"
+                        | "section"
+                        | "article"
+                        | "table"
+                        | "ul"
+                        | "ol"
+                        | "dl"
+                        | "pre"
+                        | "blockquote"
+                        | "figure"
+                        | "h1"
+                        | "h2"
+                        | "h3"
+                        | "h4"
+                        | "h5"
+                        | "h6"
+                )
+            }),
+            _ => false,
+        };
+        if !scorable {
             continue;
         }
-        let link_txt = element_link_text_chars(&el);
-        let non_link = txt.saturating_sub(link_txt);
-        let mut score = (non_link as i64) - 4 * (link_txt as i64);
-        let tag = el.value().name();
-        if tag == "article" {
-            score += 700;
-        } else if tag == "main" {
-            score += 400;
+        let text = norm_ws(&el.text().collect::<Vec<_>>().join(" "));
+        let len = text.chars().count();
+        if len < 25 {
+            continue;
         }
-        if let Some(sel) = sel_p.as_ref() {
-            let pc = el.select(sel).take(80).count() as i64;
-            score += 30 * pc.min(12);
+        paragraphs.insert(el.id(), len);
+        let score = 1.0 + text.matches([',', '，']).count() as f64 + (len / 100).min(3) as f64;
+        let mut anc = el.parent().and_then(ElementRef::wrap);
+        for level in 0..5 {
+            let Some(a) = anc else { break };
+            if a.value().name() == "html" {
+                break;
+            }
+            let ix = *candidate_ix.entry(a.id()).or_insert_with(|| {
+                candidates.push((a, readability_base_score(&a)));
+                candidates.len() - 1
+            });
+            let divider = match level {
+                0 => 1.0,
+                1 => 2.0,
+                l => l as f64 * 3.0,
+            };
+            candidates[ix].1 += score / divider;
+            anc = a.parent().and_then(ElementRef::wrap);
         }
-        if non_link < 150 {
-            score -= 300;
+    }
+
+    // Scale by non-link share; first in document order wins ties.
+    let mut finals = HashMap::new();
+    let mut best: Option<(ElementRef, f64)> = None;
+    for (el, score) in &candidates {
+        let f = score * (1.0 - readability_link_density(el, &sel_a, page_url));
+        finals.insert(el.id(), f);
+        if best.is_none_or(|(_, b)| f > b) {
+            best = Some((*el, f));
         }
-        if score > best_score {
-            best_score = score;
-            best_html = Some(el.html());
+    }
+    let (top, top_score) = best?;
+
+    // Pull in siblings that score close to the winner, or plain prose paragraphs next to it.
+    let threshold = (top_score * 0.2).max(10.0);
+    let top_class = top.value().attr("class").filter(|c| !c.trim().is_empty());
+    let mut parts = vec![top];
+    if let Some(parent) = top.parent().and_then(ElementRef::wrap) {
+        parts.clear();
+        for sib in parent.children().filter_map(ElementRef::wrap) {
+            if sib.id() == top.id() {
+                parts.push(sib);
+                continue;
+            }
+            if skipped.contains(&sib.id()) {
+                continue;
+            }
+            let bonus = if top_class.is_some() && sib.value().attr("class") == top_class {
+                top_score * 0.2
+            } else {
+                0.0
+            };
+            let scored = finals
+                .get(&sib.id())
+                .is_some_and(|f| f + bonus >= threshold);
+            let prose = sib.value().name() == "p" && {
+                let text = norm_ws(&sib.text().collect::<Vec<_>>().join(" "));
+                let len = text.chars().count();
+                let ld = readability_link_density(&sib, &sel_a, page_url);
+                (len > 80 && ld < 0.25)
+                    || (len > 0 && ld == 0.0 && (text.ends_with('.') || text.contains(". ")))
+            };
+            if scored || prose {
+                parts.push(sib);
+            }
         }
     }
-    let frag = best_html?;
-    let txt = html_to_text(&frag, width);
-    has_any_text(&txt).then_some(txt)
+
+    let skip = |e: &ElementRef| skipped.contains(&e.id());
+    let mut frag = String::new();
+    let mut n_paragraphs = 0usize;
+    let (mut chars, mut link_chars) = (0f64, 0f64);
+    for p in &parts {
+        readability_write_html(*p, &skip, &mut frag);
+        n_paragraphs += p
+            .descendants()
+            .filter(|d| paragraphs.get(&d.id()).is_some_and(|len| *len >= 80))
+            .count();
+        let n = element_text_chars(p) as f64;
+        chars += n;
+        link_chars += n * readability_link_density(p, &sel_a, page_url);
+    }
+    let article_signal = html_scraper::Selector::parse("article, meta[property=\"og:type\"]")
+        .ok()
+        .is_some_and(|sel| {
+            doc.select(&sel).any(|e| {
+                e.value().name() == "article"
+                    || e.value()
+                        .attr("content")
+                        .is_some_and(|c| c.trim().eq_ignore_ascii_case("article"))
+            })
+        });
+    let text = html_to_text(&frag, width);
+    has_any_text(&text).then(|| ReadabilityPick {
+        text,
+        paragraphs: n_paragraphs,
+        link_density: if chars > 0.0 { link_chars / chars } else { 0.0 },
+        article_signal,
+    })
+}
+
+/// Mozilla Readability-style main-content extraction.
+///
+/// Paragraph-like nodes are scored by length and comma count; each score flows up to the
+/// node's ancestors, every candidate is scaled by its non-link text share, and the winner is
+/// rendered together with siblings that score close to it. `nav`/`aside`/`footer`/`header`,
+/// scripts, hidden nodes and boilerplate-named containers are dropped first. `base_url` lets
+/// same-page `#fragment` links (TOCs, footnotes) count less toward link density.
+///
+/// When the pick is missing or shorter than 250 chars, falls back to `html_main` and then
+/// whole-page html2text, with a `readability_fallback` warning (`engine` names what was used).
+pub fn readability(html: &str, base_url: &str) -> ExtractedText {
+    readability_with_width(html, base_url, 100)
+}
+
+fn readability_with_width(html: &str, base_url: &str, width: usize) -> ExtractedText {
+    if let Some(pick) = readability_pick(html, base_url, width)
+        .filter(|p| p.text.chars().count() >= READABILITY_MIN_CHARS)
+    {
+        return ExtractedText {
+            engine: "readability",
+            text: clean_extracted_text(pick.text),
+            warnings: Vec::new(),
+        };
+    }
+    let warnings = vec!["readability_fallback"];
+    if let Some(main) = html_main_to_text(html, width) {
+        return ExtractedText {
+            engine: "html_main",
+            text: clean_extracted_text(main),
+            warnings,
+        };
+    }
+    ExtractedText {
+        engine: "html2text",
+        text: clean_extracted_text(html_to_text(html, width)),
+        warnings,
+    }
 }
 
 pub fn html_readability_to_text(html: &str, width: usize) -> Option<String> {
-    let out = pick_readability_text(html, 20_000, width)?;
-    has_any_text(&out).then_some(out)
+    readability_pick(html, "", width).map(|p| p.text)
 }
 
 #[derive(Debug, Clone)]
@@ -693,6 +1548,88 @@ fn truncate_to_chars(s: &str, max_chars: usize) -> (String, usize, bool) {
     (out, n, clipped)
 }
 
+/// How `max_chars` truncation chooses which part of a long text to keep.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncationStrategy {
+    /// Keep the prefix (or, with a query, a window around the best-matching chunk).
+    #[default]
+    Head,
+    /// Keep the first 70% and the last 30% of the budget, so conclusions/references survive.
+    HeadTail,
+    /// Drop the middle: keep equal halves from both ends.
+    MiddleOut,
+}
+
+impl TruncationStrategy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "head" => Some(Self::Head),
+            "head_tail" => Some(Self::HeadTail),
+            "middle_out" => Some(Self::MiddleOut),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Head => "head",
+            Self::HeadTail => "head_tail",
+            Self::MiddleOut => "middle_out",
+        }
+    }
+
+    /// Share of the kept budget that goes to the head (the rest is the tail).
+    fn head_pct(self) -> usize {
+        match self {
+            Self::Head => 100,
+            Self::HeadTail => 70,
+            Self::MiddleOut => 50,
+        }
+    }
+}
+
+/// Keep both ends of `s` within `max_chars` chars, joined by a `[... truncated N chars ...]`
+/// marker (counted against the budget). Falls back to prefix truncation when the budget is
+/// too small to hold the marker plus some text from each end.
+///
+/// Returns: (text, text_chars, clipped)
+pub fn truncate_keep_ends(
+    s: &str,
+    max_chars: usize,
+    strategy: TruncationStrategy,
+) -> (String, usize, bool) {
+    let total_chars = s.chars().count();
+    if total_chars <= max_chars || strategy == TruncationStrategy::Head {
+        return truncate_to_chars(s, max_chars);
+    }
+    // Size the marker for the largest possible drop so the final output never overshoots.
+    let marker = |n: usize| format!("\n\n[... truncated {n} chars ...]\n\n");
+    let marker_max = marker(total_chars).chars().count();
+    let keep = max_chars.saturating_sub(marker_max);
+    if keep < marker_max {
+        return truncate_to_chars(s, max_chars);
+    }
+    let head = keep * strategy.head_pct() / 100;
+    let tail = keep - head;
+    let dropped = total_chars - head - tail;
+    // Char counts → byte offsets (always on UTF-8 boundaries).
+    let byte_at = |char_idx: usize| {
+        s.char_indices()
+            .nth(char_idx)
+            .map(|(i, _)| i)
+            .unwrap_or(s.len())
+    };
+    let head_end = byte_at(head);
+    let tail_start = byte_at(total_chars - tail);
+    let m = marker(dropped);
+    let mut out = String::with_capacity(head_end + m.len() + (s.len() - tail_start));
+    out.push_str(s[..head_end].trim_end());
+    out.push_str(&m);
+    out.push_str(s[tail_start..].trim_start());
+    let n = out.chars().count();
+    (out, n, true)
+}
+
 fn smart_truncate_to_chars_for_query(
     text: &str,
     query: &str,
@@ -791,6 +1728,8 @@ pub struct ExtractPipelineCfg<'a> {
     pub max_outline_items: usize,
     pub max_blocks: usize,
     pub max_block_chars: usize,
+    /// Non-`Head` strategies take precedence over the query window.
+    pub truncation_strategy: TruncationStrategy,
 }
 
 /// Shared “extract pipeline” used by multiple tools:
@@ -806,12 +1745,19 @@ pub fn extract_pipeline_from_extracted(
     cfg: ExtractPipelineCfg<'_>,
 ) -> ExtractPipelineResult {
     let query = cfg.query.unwrap_or("").trim();
-    let (text, text_chars, text_truncated, used_query_window) = smart_truncate_to_chars_for_query(
-        &extracted0.text,
-        query,
-        cfg.max_chars,
-        cfg.max_chunk_chars,
-    );
+    let (text, text_chars, text_truncated, used_query_window) =
+        if cfg.truncation_strategy == TruncationStrategy::Head {
+            smart_truncate_to_chars_for_query(
+                &extracted0.text,
+                query,
+                cfg.max_chars,
+                cfg.max_chunk_chars,
+            )
+        } else {
+            let (t, n, clipped) =
+                truncate_keep_ends(&extracted0.text, cfg.max_chars, cfg.truncation_strategy);
+            (t, n, clipped, false)
+        };
     let mut warnings = extracted0.warnings;
     if used_query_window {
         warnings.push("text_windowed_for_query");
@@ -867,6 +1813,104 @@ pub fn extract_pipeline_from_extracted(
     }
 }
 
+/// Common “JS app shell” / challenge-page signatures (e.g. Next.js hydration payloads,
+/// "please enable JavaScript" walls).
+pub fn looks_like_bundle_gunk(s: &str) -> bool {
+    let t = s.trim();
+    if t.is_empty() {
+        return false;
+    }
+    let lc = t.to_ascii_lowercase();
+    // Check for common blocking/challenge messages
+    if lc.contains("please enable javascript")
+        || lc.contains("enable javascript to continue")
+        || lc.contains("javascript is required")
+        || lc.contains("browser does not support javascript")
+        || lc.contains("checking your browser")
+        || lc.contains("cloudflare") && lc.contains("ray id")
+        || lc.contains("verify you are human")
+        || lc.contains("pardon our interruption")
+        || lc.contains("turn on javascript")
+        || lc.contains("cookie") && lc.contains("consent") && t.len() < 1000
+        || lc.contains("403 forbidden") && t.len() < 500
+        || lc.contains("access denied") && t.len() < 500
+    {
+        return true;
+    }
+
+    lc.contains("self.__next_s")
+        || lc.contains("suppresshydrationwarning")
+        || lc.contains("__webpack")
+        || lc.contains("webpackchunk")
+        || lc.contains("window.__nuxt")
+        || lc.contains("(function(a,b,c,d)")
+        || lc.contains(".push([0,{\"suppresshydrationwarning\"")
+}
+
+/// Below this, [`extraction_confidence`] treats a page as low-signal.
+///
+/// Pages that trip the boolean gunk / UI-shell heuristics score below it.
+pub const EXTRACTION_CONFIDENCE_LOW: f32 = 0.4;
+
+/// Graded extraction quality in `[0, 1]` (1 = clean prose, 0 = nothing usable).
+///
+/// Product of three sub-scores, so any single failing signal pulls the page down:
+/// - text density: extracted chars, saturating around 800;
+/// - alpha ratio: share of letters + whitespace (0 at the ~1/3 “gunk” cutoff, 1 at 3/4);
+/// - block shape: 0 for nav-shaped structures (mostly short list items), 1 for mixed blocks.
+///
+/// JS-shell / challenge text is capped at 0.1. Deterministic and cheap; no structure means the
+/// shape signal is neutral.
+pub fn extraction_confidence(
+    extracted: &ExtractedText,
+    structure: Option<&ExtractedStructure>,
+) -> f32 {
+    let t = extracted.text.trim();
+    if t.is_empty() {
+        return 0.0;
+    }
+
+    let mut letters_spaces = 0usize;
+    let mut total = 0usize;
+    for ch in t.chars() {
+        total += 1;
+        if ch.is_alphabetic() || ch.is_whitespace() {
+            letters_spaces += 1;
+        }
+    }
+    let density = ((total as f32) / 800.0).min(1.0).sqrt();
+    let alpha = if total < 120 {
+        // Too short for a stable ratio; density already accounts for it.
+        1.0
+    } else {
+        let r = (letters_spaces as f32) / (total as f32);
+        ((r - 1.0 / 3.0) / (0.75 - 1.0 / 3.0)).clamp(0.0, 1.0)
+    };
+    let shape = structure
+        .filter(|s| !s.blocks.is_empty())
+        .map(|s| {
+            let n = s.blocks.len() as f32;
+            let list = s.blocks.iter().filter(|b| b.kind == "list_item").count() as f32 / n;
+            let short = s
+                .blocks
+                .iter()
+                .filter(|b| b.text.chars().count() <= 40)
+                .count() as f32
+                / n;
+            // Reaches 0 exactly where the boolean UI-shell rule fires:
+            // (list >= 0.60 && short >= 0.60) || list >= 0.85.
+            let shellness = list.min(short).max(list - 0.25);
+            1.0 - ((shellness - 0.5) / 0.1).clamp(0.0, 1.0)
+        })
+        .unwrap_or(1.0);
+
+    let mut c = density * alpha * shape;
+    if looks_like_bundle_gunk(t) {
+        c = c.min(0.1);
+    }
+    c.clamp(0.0, 1.0)
+}
+
 fn best_chunks_default(text: &str, top_k: usize, max_chunk_chars: usize) -> Vec<ScoredChunk> {
     let top_k = top_k.clamp(1, 50);
     let max_chunk_chars = max_chunk_chars.clamp(50, 5_000);
@@ -989,6 +2033,7 @@ fn push_block(
         start_char,
         end_char,
         text: clipped,
+        level: None,
     });
 }
 
@@ -1051,6 +2096,9 @@ fn extract_structure_from_html(
                         text,
                         max_block_chars,
                     );
+                    if let Some(b) = blocks.last_mut() {
+                        b.level = tag[1..].parse().ok();
+                    }
                 }
                 "p" => {
                     push_block(
@@ -1167,6 +2215,7 @@ fn extract_structure_from_text(
         } else {
             "paragraph"
         };
+        let level = (kind == "heading").then(|| p.chars().take_while(|&c| c == '#').count());
         let cleaned = if kind == "heading" {
             p.trim_start_matches('#').trim().to_string()
         } else {
@@ -1175,6 +2224,7 @@ fn extract_structure_from_text(
         if kind == "heading" && outline.len() < max_outline && !cleaned.is_empty() {
             outline.push(cleaned.clone());
         }
+        let before = blocks.len();
         push_block(
             &mut blocks,
             &mut structure_text,
@@ -1182,6 +2232,11 @@ fn extract_structure_from_text(
             cleaned,
             max_block_chars,
         );
+        if let (Some(n), true) = (level, blocks.len() > before) {
+            if let Some(b) = blocks.last_mut() {
+                b.level = Some(n.clamp(1, 6) as u8);
+            }
+        }
     }
 
     ExtractedStructure {
@@ -1234,7 +2289,9 @@ pub fn best_effort_structure_from_bytes(
 
     // JSON: pretty-print if parseable, and use top-level keys as outline.
     let ct0 = content_type_lc_prefix(content_type);
-    let is_json = ct0 == "application/json" || ct0.ends_with("+json") || extracted.engine == "json";
+    // Timed transcripts are JSON on the wire, but their structure comes from the text projection.
+    let is_json = ct0 != youtube::TRANSCRIPT_JSON_CONTENT_TYPE
+        && (ct0 == "application/json" || ct0.ends_with("+json") || extracted.engine == "json");
     if is_json {
         if let Ok(v) = serde_json::from_slice::<serde_json::Value>(bytes) {
             let mut outline: Vec<String> = Vec::new();
@@ -1320,6 +2377,67 @@ fn strip_tag_blocks(html: &str, tag: &str) -> String {
     out
 }
 
+/// Text of the page's `<noscript>` fallback blocks (nested scripts/styles stripped).
+///
+/// Extraction normally drops `<noscript>`; some JS-rendered pages put their real content there.
+pub fn html_noscript_text(html: &str, width: usize) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut blocks: Vec<String> = Vec::new();
+    let mut i = 0usize;
+    while let Some(rel) = lower[i..].find("<noscript") {
+        let open = i + rel;
+        let Some(rel_gt) = lower[open..].find('>') else {
+            break;
+        };
+        let body_start = open + rel_gt + 1;
+        let Some(rel_end) = lower[body_start..].find("</noscript>") else {
+            break;
+        };
+        let body_end = body_start + rel_end;
+        let inner = strip_tag_blocks(&html[body_start..body_end], "script");
+        let inner = strip_tag_blocks(&inner, "style");
+        let t = html_to_text(&inner, width);
+        if has_any_text(&t) {
+            blocks.push(t.trim().to_string());
+        }
+        i = body_end + "</noscript>".len();
+    }
+    blocks.join("\n\n")
+}
+
+/// Append `<noscript>` text to `base`, skipping paragraphs that `base` already contains and
+/// "please enable JavaScript" notices. Returns `None` when nothing new would be added.
+pub fn merge_noscript_text(base: &str, noscript: &str) -> Option<String> {
+    let base_norm = norm_ws(base).to_lowercase();
+    let mut seen: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut added: Vec<&str> = Vec::new();
+    for para in noscript.split("\n\n") {
+        let para = para.trim();
+        let key = norm_ws(para).to_lowercase();
+        if key.is_empty() || base_norm.contains(&key) || !seen.insert(key.clone()) {
+            continue;
+        }
+        let js_notice = key.contains("javascript")
+            && key.chars().count() < 200
+            && ["enable", "turn on", "requires", "needs"]
+                .iter()
+                .any(|w| key.contains(w));
+        if js_notice {
+            continue;
+        }
+        added.push(para);
+    }
+    if added.is_empty() {
+        return None;
+    }
+    let extra = added.join("\n\n");
+    Some(if has_any_text(base) {
+        format!("{}\n\n{extra}", base.trim_end())
+    } else {
+        extra
+    })
+}
+
 fn detect_client_redirect(html: &str) -> Option<String> {
     let doc = html_scraper::Html::parse_document(html);
     // 1. Meta refresh: <meta http-equiv="refresh" content="0; url=..." />
@@ -1440,10 +2558,46 @@ fn openreview_notes_api_to_text(v: &serde_json::Value) -> Option<String> {
     Some(out)
 }
 
+/// Which HTML extractor [`best_effort_text_from_bytes_with_engine`] uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HtmlEngine {
+    /// Pick between readability, `html_main` and whole-page html2text by text quality.
+    #[default]
+    Auto,
+    /// [`readability`], with its own fallbacks.
+    Readability,
+    /// The densest `article`/`main`/`section`/`div` block.
+    Main,
+    /// Whole-page html2text.
+    Html2text,
+}
+
+impl HtmlEngine {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "readability" => Some(Self::Readability),
+            "html_main" => Some(Self::Main),
+            "html2text" => Some(Self::Html2text),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Readability => "readability",
+            Self::Main => "html_main",
+            Self::Html2text => "html2text",
+        }
+    }
+}
+
 /// Extract best-effort readable text from a fetched body.
 ///
 /// The goal is “good enough” evidence text for downstream chunking/LLM use:
-/// - HTML: html2text, with hint-text fallback when empty.
+/// - HTML: readability for article-like pages, else the best of readability/html_main/html2text,
+///   with hint-text fallback when empty.
 /// - PDF: pdf-extract.
 /// - Markdown/text/json/xml: treat as text (no HTML rendering).
 /// - Unknown/binary: empty text + warning.
@@ -1453,6 +2607,27 @@ pub fn best_effort_text_from_bytes(
     final_url: &str,
     width: usize,
     hint_max_chars: usize,
+) -> ExtractedText {
+    best_effort_text_from_bytes_with_engine(
+        bytes,
+        content_type,
+        final_url,
+        width,
+        hint_max_chars,
+        HtmlEngine::Auto,
+    )
+}
+
+/// [`best_effort_text_from_bytes`] with a fixed HTML extractor. Non-HTML bodies (and bodies
+/// large enough to be streamed) ignore `engine`. A fixed `html_main`/`html2text` that yields no
+/// text falls through to `Auto` with an `extract_engine_fallback` warning.
+pub fn best_effort_text_from_bytes_with_engine(
+    bytes: &[u8],
+    content_type: Option<&str>,
+    final_url: &str,
+    width: usize,
+    hint_max_chars: usize,
+    engine: HtmlEngine,
 ) -> ExtractedText {
     let mut warnings: Vec<&'static str> = Vec::new();
 
@@ -1537,7 +2712,7 @@ pub fn best_effort_text_from_bytes(
     }
 
     // YouTube transcripts (produced by LocalFetcher as text) should have a stable engine.
-    if ct0 == "text/x-youtube-transcript" {
+    if ct0 == youtube::TRANSCRIPT_CONTENT_TYPE {
         let text = clean_extracted_text(String::from_utf8_lossy(bytes).to_string());
         return ExtractedText {
             engine: "youtube_transcript",
@@ -1545,6 +2720,44 @@ pub fn best_effort_text_from_bytes(
             warnings,
         };
     }
+    // Timed transcripts: extraction works on the plain-text projection.
+    if ct0 == youtube::TRANSCRIPT_JSON_CONTENT_TYPE {
+        let text = match serde_json::from_slice::<youtube::TimedTranscript>(bytes) {
+            Ok(t) => t.text,
+            Err(_) => {
+                // e.g. cut by max_bytes: the raw JSON still carries the words.
+                warnings.push("youtube_transcript_json_invalid");
+                String::from_utf8_lossy(bytes).to_string()
+            }
+        };
+        return ExtractedText {
+            engine: "youtube_transcript",
+            text: clean_extracted_text(text),
+            warnings,
+        };
+    }
+
+    // Spreadsheets and slide decks: read the OOXML parts directly (bounded by the same char cap
+    // as pandoc output). Unlike docx, pandoc's readers for these are not dependable.
+    if let Some(kind) = office::OfficeKind::detect(&ct0, final_url) {
+        let max_chars = shellout::max_chars_from_env("WEBPIPE_PANDOC_MAX_CHARS", 200_000);
+        return match office::office_to_text(kind, bytes, max_chars) {
+            Ok(text) => ExtractedText {
+                engine: kind.engine(),
+                text: clean_extracted_text(text),
+                warnings,
+            },
+            Err(code) => {
+                warnings.push(kind.failed_warning());
+                warnings.push(code);
+                ExtractedText {
+                    engine: kind.engine(),
+                    text: String::new(),
+                    warnings,
+                }
+            }
+        };
+    }
 
     // Pandoc: opportunistic conversion for common “document” formats.
     // This is a robustness feature: without it, these content-types are “unknown” (empty).
@@ -1564,6 +2777,7 @@ pub fn best_effort_text_from_bytes(
                 let mode = shellout::pandoc_mode_from_env();
                 if mode == "auto" {
                     warnings.push("pandoc_failed");
+                    warnings.push(code);
                     // Keep going: fall through to image/text/html heuristics.
                 } else if mode == "strict" {
                     warnings.push(code);
@@ -1688,6 +2902,35 @@ pub fn best_effort_text_from_bytes(
     // Bound worst-case HTML parsing cost: extraction output is bounded by max_chars anyway.
     let max_html_bytes =
         env_usize("WEBPIPE_EXTRACT_MAX_BYTES", 2_000_000).clamp(50_000, 20_000_000);
+    // Very large bodies: stream the whole thing through a DOM-less converter instead of
+    // truncating and building several DOMs. Memory stays ~bounded by the output cap.
+    let streaming_on = !matches!(
+        env("WEBPIPE_EXTRACT_STREAMING").as_deref(),
+        Some("0" | "off" | "false")
+    );
+    if streaming_on && bytes.len() > max_html_bytes {
+        let head_n = truncate_len_utf8_boundary(bytes, 64 * 1024);
+        let head = String::from_utf8_lossy(&bytes[..head_n]);
+        if let Some(target) = detect_client_redirect(&head) {
+            warnings.push("client_side_redirect");
+            return ExtractedText {
+                engine: "redirect",
+                text: target,
+                warnings,
+            };
+        }
+        let max_chars =
+            env_usize("WEBPIPE_EXTRACT_STREAMING_MAX_CHARS", 200_000).clamp(1_000, 2_000_000);
+        let (text, _stats) = html_to_text_streaming(bytes, width, max_chars);
+        if has_any_text(&text) {
+            warnings.push("extract_streamed");
+            return ExtractedText {
+                engine: "html_stream",
+                text: clean_extracted_text(text),
+                warnings,
+            };
+        }
+    }
     let html_bytes = if bytes.len() > max_html_bytes {
         warnings.push("extract_input_truncated");
         let n = truncate_len_utf8_boundary(bytes, max_html_bytes);
@@ -1712,9 +2955,45 @@ pub fn best_effort_text_from_bytes(
     let html1 = strip_tag_blocks(&html0, "script");
     let html2 = strip_tag_blocks(&html1, "style");
     let html = strip_tag_blocks(&html2, "noscript");
+    match engine {
+        HtmlEngine::Auto => {}
+        HtmlEngine::Readability => {
+            let mut ex = readability_with_width(&html, final_url, width);
+            warnings.append(&mut ex.warnings);
+            ex.warnings = warnings;
+            return ex;
+        }
+        HtmlEngine::Main | HtmlEngine::Html2text => {
+            let text = if engine == HtmlEngine::Main {
+                html_main_to_text(&html, width)
+            } else {
+                Some(html_to_text(&html, width)).filter(|t| has_any_text(t))
+            };
+            if let Some(text) = text {
+                return ExtractedText {
+                    engine: engine.as_str(),
+                    text: clean_extracted_text(text),
+                    warnings,
+                };
+            }
+            warnings.push("extract_engine_fallback");
+        }
+    }
     let full = html_to_text(&html, width);
     let main = html_main_to_text(&html, width);
-    let readability = html_readability_to_text(&html, width);
+    let read_pick = readability_pick(&html, final_url, width);
+    // Article-like pages (enough low-link prose under one node): trust readability outright.
+    if let Some(pick) = read_pick.as_ref().filter(|p| p.looks_like_article()) {
+        if pick.text.chars().count() < full.chars().count() {
+            warnings.push("boilerplate_reduced");
+        }
+        return ExtractedText {
+            engine: "readability",
+            text: clean_extracted_text(pick.text.clone()),
+            warnings,
+        };
+    }
+    let readability = read_pick.map(|p| p.text);
 
     fn quality_score(s: &str) -> i64 {
         let non_ws = s.chars().filter(|c| !c.is_whitespace()).count() as i64;
@@ -1871,25 +3150,417 @@ pub fn html_hint_text(html: &str, max_chars: usize) -> String {
     if let Some(d) = first_attr(&doc, "meta[name=\"description\"]", "content") {
         parts.push(d);
     }
-    // Twitter cards are common on docs/blogs; keep it as a best-effort hint source.
-    if let Some(d) = first_attr(&doc, "meta[name=\"twitter:description\"]", "content") {
-        parts.push(d);
+    // Twitter cards are common on docs/blogs; keep it as a best-effort hint source.
+    if let Some(d) = first_attr(&doc, "meta[name=\"twitter:description\"]", "content") {
+        parts.push(d);
+    }
+    if let Some(d) = first_attr(&doc, "meta[property=\"og:description\"]", "content") {
+        parts.push(d);
+    }
+    if let Some(t) = first_text(&doc, "h1") {
+        parts.push(t);
+    }
+    if let Some(t) = first_text(&doc, "h2") {
+        parts.push(t);
+    }
+    if let Some(t) = first_text(&doc, "h3") {
+        parts.push(t);
+    }
+
+    let joined = parts.join("\n");
+    let (out, _clipped) = truncate_chars(&joined, max_chars);
+    out
+}
+
+/// A data table pulled out of HTML. Rows are padded to one width; spanned cells are empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Table {
+    /// Header row (`<th>` cells or `<thead>`); empty when the table has none.
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// The cell budget ran out inside this table, so later rows were dropped.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// Cap on cells across all tables of one page (pathological pages have 100k-cell grids).
+pub const MAX_TABLE_CELLS: usize = 10_000;
+
+impl Table {
+    fn width(&self) -> usize {
+        self.rows
+            .iter()
+            .map(Vec::len)
+            .chain([self.headers.len()])
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// GitHub-flavored Markdown (a headerless table gets an empty header row).
+    pub fn to_markdown(&self) -> String {
+        fn line(cells: &[String], width: usize) -> String {
+            let mut out = String::from("|");
+            for i in 0..width {
+                let c = cells.get(i).map(String::as_str).unwrap_or("");
+                out.push(' ');
+                out.push_str(&c.replace('|', "\\|"));
+                out.push_str(" |");
+            }
+            out
+        }
+        let width = self.width();
+        if width == 0 {
+            return String::new();
+        }
+        let mut lines = vec![
+            line(&self.headers, width),
+            format!("|{}", " --- |".repeat(width)),
+        ];
+        lines.extend(self.rows.iter().map(|r| line(r, width)));
+        lines.join("\n")
+    }
+
+    /// RFC 4180 CSV, header row first when present.
+    pub fn to_csv(&self) -> String {
+        fn field(c: &str) -> String {
+            if c.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", c.replace('"', "\"\""))
+            } else {
+                c.to_string()
+            }
+        }
+        let header = (!self.headers.is_empty()).then_some(&self.headers);
+        header
+            .into_iter()
+            .chain(&self.rows)
+            .map(|r| r.iter().map(|c| field(c)).collect::<Vec<_>>().join(","))
+            .map(|l| l + "\r\n")
+            .collect()
+    }
+}
+
+/// Data tables in `html`, in document order.
+///
+/// `colspan`/`rowspan` are expanded into padding cells so every row has the same width. Layout
+/// tables are skipped: `role="presentation"`, a single column, or no `<th>` with most of the
+/// text inside links. Nested tables are extracted on their own. Bounded by [`MAX_TABLE_CELLS`].
+pub fn extract_tables(html: &str) -> Vec<Table> {
+    use html_scraper::{ElementRef, Html, Selector};
+    let doc = Html::parse_document(html);
+    let (Ok(sel_table), Ok(sel_tr), Ok(sel_a)) = (
+        Selector::parse("table"),
+        Selector::parse("tr"),
+        Selector::parse("a"),
+    ) else {
+        return Vec::new();
+    };
+    let cell_text = |el: ElementRef<'_>| {
+        el.text()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let span = |el: &ElementRef<'_>, attr: &str, max: usize| {
+        el.value()
+            .attr(attr)
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .unwrap_or(1)
+            .clamp(1, max)
+    };
+    /// Pad `row` over columns still covered by a `rowspan` from above.
+    fn fill_spanned(row: &mut Vec<String>, spans: &mut [usize]) {
+        while let Some(left) = spans.get_mut(row.len()).filter(|l| **l > 0) {
+            *left -= 1;
+            row.push(String::new());
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut budget = MAX_TABLE_CELLS;
+    for table in doc.select(&sel_table) {
+        if budget == 0 {
+            break;
+        }
+        if table
+            .value()
+            .attr("role")
+            .is_some_and(|r| r.eq_ignore_ascii_case("presentation"))
+        {
+            continue;
+        }
+        // Only this table's own rows, not those of a table nested inside it.
+        let rows = table.select(&sel_tr).filter(|tr| {
+            tr.ancestors()
+                .filter_map(ElementRef::wrap)
+                .find(|a| a.value().name() == "table")
+                .is_some_and(|t| t.id() == table.id())
+        });
+        // Rows each column is still covered for by a `rowspan` above.
+        let mut spans: Vec<usize> = Vec::new();
+        let mut grid: Vec<(Vec<String>, bool)> = Vec::new();
+        let mut has_th = false;
+        let mut truncated = false;
+        for tr in rows {
+            let in_thead = tr
+                .parent()
+                .and_then(ElementRef::wrap)
+                .is_some_and(|p| p.value().name() == "thead");
+            let mut row: Vec<String> = Vec::new();
+            let mut all_th = true;
+            let mut any_cell = false;
+            for cell in tr.children().filter_map(ElementRef::wrap) {
+                let name = cell.value().name();
+                if name != "td" && name != "th" {
+                    continue;
+                }
+                any_cell = true;
+                all_th &= name == "th";
+                has_th |= name == "th";
+                fill_spanned(&mut row, &mut spans);
+                let colspan = span(&cell, "colspan", 50);
+                let rowspan = span(&cell, "rowspan", 100);
+                let start = row.len();
+                row.push(cell_text(cell));
+                row.extend(std::iter::repeat_n(String::new(), colspan - 1));
+                if spans.len() < row.len() {
+                    spans.resize(row.len(), 0);
+                }
+                for left in &mut spans[start..row.len()] {
+                    *left = rowspan - 1;
+                }
+            }
+            if !any_cell {
+                continue;
+            }
+            fill_spanned(&mut row, &mut spans);
+            if row.len() > budget {
+                truncated = true;
+                break;
+            }
+            budget -= row.len();
+            grid.push((row, in_thead || all_th));
+        }
+
+        let header_row = grid.first().is_some_and(|(_, h)| *h);
+        let mut t = Table {
+            truncated,
+            ..Table::default()
+        };
+        for (i, (row, _)) in grid.into_iter().enumerate() {
+            if i == 0 && header_row {
+                t.headers = row;
+            } else {
+                t.rows.push(row);
+            }
+        }
+        let width = t.width();
+        if width <= 1 || t.rows.is_empty() {
+            continue;
+        }
+        if !has_th {
+            // Link density over visible characters (markup whitespace would dilute it).
+            let visible = |el: ElementRef<'_>| {
+                el.text()
+                    .flat_map(str::chars)
+                    .filter(|c| !c.is_whitespace())
+                    .count()
+            };
+            let links: usize = table.select(&sel_a).map(visible).sum();
+            if links * 2 > visible(table).max(1) {
+                continue;
+            }
+        }
+        for r in t
+            .rows
+            .iter_mut()
+            .chain([&mut t.headers].into_iter().filter(|h| !h.is_empty()))
+        {
+            r.resize(width, String::new());
+        }
+        out.push(t);
+    }
+    out
+}
+
+/// Link-preview metadata from OpenGraph, Twitter cards, standard `<meta>`/`<link>` tags and
+/// JSON-LD. URLs are returned as written; callers resolve them against the page URL.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PageMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<String>,
+    /// Parsed `application/ld+json` objects; top-level arrays and `@graph` are flattened.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub json_ld: Vec<serde_json::Value>,
+    /// `application/ld+json` blocks that did not parse (skipped).
+    #[serde(skip_serializing_if = "is_zero")]
+    pub json_ld_invalid: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// JSON-LD objects kept per page.
+const MAX_JSON_LD_OBJECTS: usize = 32;
+/// Longest metadata string kept (descriptions can be whole articles on some CMSes).
+const MAX_META_CHARS: usize = 1_000;
+
+/// Extract [`PageMeta`] from an HTML document.
+///
+/// Each field takes the first source that has it: OpenGraph, then Twitter, then plain HTML
+/// (`<title>`, `meta[name=description|author]`, `link[rel=canonical]`), then JSON-LD
+/// (`headline`/`name`, `description`, `author`, `image`, `publisher.name`). Malformed JSON-LD
+/// blocks are counted in `json_ld_invalid` and otherwise ignored.
+pub fn extract_metadata(html: &str) -> PageMeta {
+    use std::collections::HashMap;
+
+    fn bounded(s: &str) -> Option<String> {
+        let s = norm_ws(s);
+        if s.is_empty() {
+            return None;
+        }
+        Some(match s.char_indices().nth(MAX_META_CHARS) {
+            Some((i, _)) => s[..i].to_string(),
+            None => s,
+        })
     }
-    if let Some(d) = first_attr(&doc, "meta[property=\"og:description\"]", "content") {
-        parts.push(d);
+    /// A JSON-LD value as display text: a string, `{name}`/`{url}`/`{@id}`, or the first of an array.
+    fn ld_text(v: &serde_json::Value) -> Option<String> {
+        match v {
+            serde_json::Value::String(s) => bounded(s),
+            serde_json::Value::Array(xs) => xs.iter().find_map(ld_text),
+            serde_json::Value::Object(m) => ["name", "url", "@id"]
+                .iter()
+                .find_map(|k| m.get(*k).and_then(ld_text)),
+            _ => None,
+        }
     }
-    if let Some(t) = first_text(&doc, "h1") {
-        parts.push(t);
+    fn flatten(v: serde_json::Value, depth: usize, out: &mut Vec<serde_json::Value>) {
+        if depth > 4 || out.len() >= MAX_JSON_LD_OBJECTS {
+            return;
+        }
+        match v {
+            serde_json::Value::Array(xs) => {
+                for x in xs {
+                    flatten(x, depth + 1, out);
+                }
+            }
+            serde_json::Value::Object(mut m) => match m.remove("@graph") {
+                Some(g) => flatten(g, depth + 1, out),
+                None => out.push(serde_json::Value::Object(m)),
+            },
+            _ => {}
+        }
     }
-    if let Some(t) = first_text(&doc, "h2") {
-        parts.push(t);
+
+    let doc = html_scraper::Html::parse_document(html);
+    let mut out = PageMeta::default();
+
+    // First value per lower-cased meta key (`property`, `name`, or `itemprop`).
+    let mut meta: HashMap<String, String> = HashMap::new();
+    if let Ok(sel) = html_scraper::Selector::parse("meta[content]") {
+        for m in doc.select(&sel) {
+            let el = m.value();
+            let Some(v) = el.attr("content").and_then(bounded) else {
+                continue;
+            };
+            for a in ["property", "name", "itemprop"] {
+                if let Some(k) = el.attr(a) {
+                    meta.entry(k.trim().to_ascii_lowercase())
+                        .or_insert_with(|| v.clone());
+                }
+            }
+        }
     }
-    if let Some(t) = first_text(&doc, "h3") {
-        parts.push(t);
+    let first = |keys: &[&str]| keys.iter().find_map(|k| meta.get(*k).cloned());
+
+    if let Ok(sel) = html_scraper::Selector::parse("script[type]") {
+        for el in doc.select(&sel) {
+            let ty = el.value().attr("type").unwrap_or("");
+            if !ty.trim().eq_ignore_ascii_case("application/ld+json") {
+                continue;
+            }
+            let raw = el.text().collect::<String>();
+            // CMSes wrap blocks in HTML comments / CDATA and leave trailing semicolons.
+            let raw = raw
+                .trim()
+                .trim_start_matches("<!--")
+                .trim_end_matches("-->")
+                .trim()
+                .trim_start_matches("//<![CDATA[")
+                .trim_end_matches("//]]>")
+                .trim()
+                .trim_end_matches(';');
+            match serde_json::from_str::<serde_json::Value>(raw) {
+                Ok(v) => flatten(v, 0, &mut out.json_ld),
+                Err(_) => out.json_ld_invalid += 1,
+            }
+        }
     }
+    let ld = |key: &str| {
+        out.json_ld
+            .iter()
+            .find_map(|v| v.get(key).and_then(ld_text))
+    };
 
-    let joined = parts.join("\n");
-    let (out, _clipped) = truncate_chars(&joined, max_chars);
+    let html_title = html_scraper::Selector::parse("title")
+        .ok()
+        .and_then(|sel| doc.select(&sel).next())
+        .and_then(|t| bounded(&t.text().collect::<String>()));
+    let canonical = html_scraper::Selector::parse("link[rel][href]")
+        .ok()
+        .and_then(|sel| {
+            doc.select(&sel).find_map(|l| {
+                let rel = l.value().attr("rel")?.to_ascii_lowercase();
+                rel.split_whitespace()
+                    .any(|r| r == "canonical")
+                    .then(|| l.value().attr("href").and_then(bounded))?
+            })
+        });
+
+    let title = first(&["og:title", "twitter:title"])
+        .or(html_title)
+        .or_else(|| ld("headline"))
+        .or_else(|| ld("name"));
+    let description = first(&["og:description", "twitter:description", "description"])
+        .or_else(|| ld("description"));
+    let site_name = first(&["og:site_name", "application-name"]).or_else(|| {
+        out.json_ld
+            .iter()
+            .find_map(|v| v.get("publisher").and_then(ld_text))
+    });
+    let image = first(&[
+        "og:image:secure_url",
+        "og:image",
+        "og:image:url",
+        "twitter:image",
+        "twitter:image:src",
+    ])
+    .or_else(|| ld("image"));
+    let author = first(&["author", "article:author"]).or_else(|| ld("author"));
+    let canonical_url = canonical.or_else(|| first(&["og:url"]));
+
+    out.title = title;
+    out.description = description;
+    out.site_name = site_name;
+    out.image = image;
+    out.author = author;
+    out.canonical_url = canonical_url;
+    out.published_time = crate::published::published_time(html);
     out
 }
 
@@ -1905,6 +3576,121 @@ pub struct ScoredChunk {
     pub text: String,
 }
 
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct SentenceSpan {
+    /// Sentence text (trimmed).
+    pub text: String,
+    /// Character offset into the provided text.
+    pub start_char: usize,
+    /// Character offset into the provided text (exclusive).
+    pub end_char: usize,
+}
+
+/// Split text into sentences with char offsets (bounded to `max_sentences`).
+///
+/// Notes:
+/// - Heuristic, not linguistic: a boundary is `.`/`!`/`?` (plus closing quotes/brackets) followed
+///   by whitespace and an uppercase letter, digit, or opening quote; blank lines always split.
+/// - Common abbreviations (`e.g.`, `Dr.`, `U.S.`), single-letter initials, and decimals (`3.14`)
+///   do not split.
+pub fn split_sentences(text: &str, max_sentences: usize) -> Vec<SentenceSpan> {
+    const ABBREVIATIONS: &[&str] = &[
+        "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "vs", "etc", "cf", "al", "fig", "figs",
+        "eq", "no", "nos", "vol", "pp", "approx", "inc", "ltd", "co", "corp", "dept", "est", "jan",
+        "feb", "mar", "apr", "jun", "jul", "aug", "sep", "sept", "oct", "nov", "dec", "e.g", "i.e",
+    ];
+    fn is_closer(c: char) -> bool {
+        matches!(c, '"' | '\'' | ')' | ']' | '”' | '’' | '»')
+    }
+    fn starts_sentence(c: char) -> bool {
+        c.is_uppercase()
+            || c.is_ascii_digit()
+            || matches!(c, '"' | '\'' | '(' | '[' | '“' | '‘' | '«')
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = Vec::new();
+    let push = |start: usize, end: usize, out: &mut Vec<SentenceSpan>| {
+        let mut a = start;
+        let mut b = end;
+        while a < b && chars[a].is_whitespace() {
+            a += 1;
+        }
+        while b > a && chars[b - 1].is_whitespace() {
+            b -= 1;
+        }
+        if a < b {
+            out.push(SentenceSpan {
+                text: chars[a..b].iter().collect(),
+                start_char: a,
+                end_char: b,
+            });
+        }
+    };
+
+    let mut start = 0usize;
+    let mut i = 0usize;
+    while i < chars.len() && out.len() < max_sentences {
+        let c = chars[i];
+        if c == '\n' && chars.get(i + 1) == Some(&'\n') {
+            push(start, i, &mut out);
+            start = i + 2;
+            i += 2;
+            continue;
+        }
+        if !matches!(c, '.' | '!' | '?' | '。' | '！' | '？') {
+            i += 1;
+            continue;
+        }
+        // Extend over repeated terminators and closing quotes/brackets: `?!`, `."`, `.)`.
+        let mut end = i + 1;
+        while end < chars.len() && (matches!(chars[end], '.' | '!' | '?') || is_closer(chars[end]))
+        {
+            end += 1;
+        }
+        let cjk = matches!(c, '。' | '！' | '？');
+        let at_end = end >= chars.len();
+        let followed_by_space = !at_end && chars[end].is_whitespace();
+        if !cjk && !at_end && !followed_by_space {
+            // `3.14`, `example.com`, `U.S.A`: mid-token punctuation.
+            i = end;
+            continue;
+        }
+        if c == '.' && !cjk {
+            // The word immediately before the period.
+            let mut w0 = i;
+            while w0 > start && !chars[w0 - 1].is_whitespace() && chars[w0 - 1] != '(' {
+                w0 -= 1;
+            }
+            let word: String = chars[w0..i].iter().collect::<String>().to_lowercase();
+            let letters = word.chars().filter(|ch| ch.is_alphabetic()).count();
+            let dotted = word.contains('.') && word.split('.').all(|seg| seg.chars().count() <= 2);
+            let initial = letters == 1 && word.chars().count() == 1;
+            if ABBREVIATIONS.contains(&word.as_str()) || dotted || initial {
+                i = end;
+                continue;
+            }
+        }
+        if !at_end && !cjk {
+            let mut j = end;
+            while j < chars.len() && chars[j].is_whitespace() {
+                j += 1;
+            }
+            if j < chars.len() && !starts_sentence(chars[j]) {
+                i = end;
+                continue;
+            }
+        }
+        push(start, end, &mut out);
+        start = end;
+        i = end;
+    }
+    if out.len() < max_sentences {
+        push(start, chars.len(), &mut out);
+    }
+    out
+}
+
 fn is_query_stopword(t: &str) -> bool {
     // Stopwords are high-recall across most pages and usually harm ranking quality
     // when treated as evidence-bearing query tokens.
@@ -2862,6 +4648,114 @@ mod tests {
         assert!(out.contains("world"));
     }
 
+    #[test]
+    fn streaming_html_matches_html2text_on_small_page() {
+        let html = r#"<!doctype html><html><head><title>T</title>
+<style>body { color: red }</style></head><body>
+<h1>Release notes</h1>
+<!-- build: 1234 -->
+<p>Fixes &amp; <b>improvements</b> for the&nbsp;parser.</p>
+<script>var secret = "do not show";</script>
+<ul><li>First item</li><li>Second item</li></ul>
+<div>Closing <em>remarks</em> here.</div>
+</body></html>"#;
+        let (streamed, stats) = html_to_text_streaming(html.as_bytes(), 80, 10_000);
+        let dom = html_to_text(html, 80);
+        assert_eq!(
+            norm_ws(&streamed),
+            norm_ws(&dom),
+            "streamed={streamed:?} dom={dom:?}"
+        );
+        assert!(!streamed.contains("secret"));
+        assert!(!stats.clipped);
+    }
+
+    #[test]
+    fn streaming_html_bounds_memory_on_huge_input() {
+        // ~24MB synthetic page generated chunk-by-chunk (never materialized): a huge inline
+        // script followed by endless paragraphs. Scratch memory and output must stay bounded.
+        let max_chars = 5_000;
+        let mut st = HtmlTextStream::new(100, max_chars);
+        st.feed(b"<html><body><script>");
+        let js = "var x = '<p>not text</p>'; ".repeat(2_000);
+        for _ in 0..200 {
+            st.feed(js.as_bytes());
+        }
+        st.feed(b"</script>");
+        let para = "<p>Real paragraph text with &amp; entities.</p>\n".repeat(1_000);
+        let mut fed = 0usize;
+        for _ in 0..250 {
+            st.feed(para.as_bytes());
+            fed += para.len();
+        }
+        let (out, stats) = st.finish();
+        assert!(stats.clipped);
+        assert!(stats.out_chars <= max_chars);
+        assert!(out.chars().count() <= max_chars);
+        assert!(stats.peak_scratch_bytes <= STREAM_SCRATCH_MAX + STREAM_TAG_MAX);
+        assert!(out.starts_with("Real paragraph text with & entities."));
+        assert!(!out.contains("not text"));
+        // Input after the output filled up was ignored rather than scanned.
+        assert!(stats.bytes_in < js.len() * 200 + fed);
+    }
+
+    #[test]
+    fn best_effort_streams_bodies_over_extract_max_bytes() {
+        let body = format!(
+            "<html><body>{}</body></html>",
+            "<p>Paragraph of ordinary article text.</p>".repeat(60_000)
+        );
+        assert!(body.len() > 2_000_000);
+        let ex =
+            best_effort_text_from_bytes(body.as_bytes(), Some("text/html"), "https://x/", 100, 200);
+        assert_eq!(ex.engine, "html_stream");
+        assert!(ex.warnings.contains(&"extract_streamed"));
+        assert!(!ex.warnings.contains(&"extract_input_truncated"));
+        assert!(ex.text.starts_with("Paragraph of ordinary article text."));
+    }
+
+    #[test]
+    fn split_sentences_keeps_abbreviations_and_decimals_intact() {
+        let text = "Use a constant, e.g. Pi is 3.14 here. The U.S. economy grew. Dr. Smith agreed!\n\nNew para";
+        let got = split_sentences(text, 50);
+        let texts: Vec<&str> = got.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "Use a constant, e.g. Pi is 3.14 here.",
+                "The U.S. economy grew.",
+                "Dr. Smith agreed!",
+                "New para",
+            ]
+        );
+        let chars: Vec<char> = text.chars().collect();
+        for s in &got {
+            let slice: String = chars[s.start_char..s.end_char].iter().collect();
+            assert_eq!(slice, s.text);
+        }
+        assert_eq!(split_sentences(text, 2).len(), 2);
+        assert!(split_sentences("   ", 10).is_empty());
+    }
+
+    #[test]
+    fn noscript_text_merges_without_duplicating_visible_text() {
+        let html = r#"<html><body><div id="root">Shared intro line.</div>
+<noscript><p>Please enable JavaScript to run this app.</p></noscript>
+<noscript><style>p{}</style><h1>Pricing</h1><p>Shared intro line.</p><p>Plans start at 5 USD per month.</p></noscript>
+</body></html>"#;
+        let ns = html_noscript_text(html, 100);
+        assert!(ns.contains("Plans start at 5 USD per month."));
+        assert!(!ns.contains("p{}"));
+
+        let merged = merge_noscript_text("Shared intro line.", &ns).expect("new text");
+        assert_eq!(merged.matches("Shared intro line.").count(), 1);
+        assert!(merged.contains("Plans start at 5 USD per month."));
+        assert!(!merged.contains("enable JavaScript"));
+
+        assert_eq!(merge_noscript_text(&merged, &ns), None);
+        assert_eq!(html_noscript_text("<p>no fallbacks</p>", 100), "");
+    }
+
     #[test]
     fn bytes_look_like_pdf_sniffs_magic_header() {
         assert!(bytes_look_like_pdf(b"%PDF-1.7\n%..."));
@@ -2979,6 +4873,120 @@ mod tests {
         assert!(ex.warnings.contains(&"image_no_text_extraction"));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn best_effort_text_from_bytes_routes_office_formats() {
+        use std::os::unix::fs::PermissionsExt;
+        // Stub pandoc: print fixed text for a .docx input (format comes from the extension).
+        let dir = tempfile::tempdir().unwrap();
+        let stub = dir.path().join("pandoc");
+        std::fs::write(
+            &stub,
+            "#!/bin/sh\ncase \"$1\" in *.docx) printf 'Quarterly report\\n\\nRevenue grew 12%%.\\n' ;; *) exit 3 ;; esac\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::env::set_var("WEBPIPE_PANDOC_BIN", &stub);
+        std::env::remove_var("WEBPIPE_PANDOC");
+
+        let docx = b"PK\x03\x04 not really a docx";
+        let ct = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+        for (ct, url) in [
+            (Some(ct), "https://x/report"),
+            (Some("application/octet-stream"), "https://x/report.docx"),
+        ] {
+            let ex = best_effort_text_from_bytes(docx, ct, url, 80, 200);
+            assert_eq!(ex.engine, "pandoc", "{ct:?} {url}");
+            assert_eq!(ex.text, "Quarterly report\n\nRevenue grew 12%.\n");
+            assert!(ex.warnings.contains(&"pandoc_used"));
+        }
+
+        // A failing pandoc leaves a specific warning next to the generic one.
+        let ex = best_effort_text_from_bytes(docx, Some("application/rtf"), "https://x/a", 80, 200);
+        assert!(ex.warnings.contains(&"pandoc_failed"), "{:?}", ex.warnings);
+        assert!(
+            ex.warnings.contains(&"shellout_nonzero_exit"),
+            "{:?}",
+            ex.warnings
+        );
+        std::env::remove_var("WEBPIPE_PANDOC_BIN");
+
+        // Spreadsheets/decks never reach pandoc; unreadable ones say so.
+        let ex = best_effort_text_from_bytes(b"garbage", None, "https://x/deck.pptx?dl=1", 80, 200);
+        assert_eq!(ex.engine, "pptx");
+        assert!(ex.text.is_empty());
+        assert_eq!(ex.warnings, ["pptx_extract_failed", "office_zip_invalid"]);
+    }
+
+    #[test]
+    fn readability_picks_the_article_and_drops_chrome() {
+        let para = |i: usize| {
+            format!(
+                "<p>Paragraph {i} explains, in some detail, how the tide tables are computed, \
+                 why the harmonic constants matter, and what changes near estuaries.</p>"
+            )
+        };
+        let body: String = (1..=6).map(para).collect();
+        let html = format!(
+            r##"<html><head><meta property="og:type" content="article"></head><body>
+            <header><a href="/">Home</a> <a href="/news">News</a></header>
+            <nav><a href="/a">Section A</a><a href="/b">Section B</a></nav>
+            <div class="sidebar"><p>Popular: <a href="/p1">one</a>, <a href="/p2">two</a></p></div>
+            <div id="story"><h1>Tides</h1>{body}
+              <div class="share-buttons"><a href="/s">Share this article on every network</a></div>
+              <p>See <a href="#fn1">note 1</a> for the full derivation of the constants here.</p>
+            </div>
+            <footer><p>Copyright, all rights reserved, contact us for licensing.</p></footer>
+            </body></html>"##
+        );
+        let ex = readability(&html, "https://example.com/tides");
+        assert_eq!(ex.engine, "readability");
+        assert!(ex.warnings.is_empty(), "{:?}", ex.warnings);
+        assert!(ex.text.contains("Paragraph 1") && ex.text.contains("Paragraph 6"));
+        assert!(ex.text.contains("full derivation"));
+        for chrome in ["Section A", "Popular", "Share this", "Copyright"] {
+            assert!(!ex.text.contains(chrome), "{chrome}: {}", ex.text);
+        }
+
+        // The auto path prefers readability for article-like pages.
+        let auto =
+            best_effort_text_from_bytes(html.as_bytes(), Some("text/html"), "https://x/", 100, 200);
+        assert_eq!(auto.engine, "readability");
+        assert!(auto.warnings.contains(&"boilerplate_reduced"));
+
+        // Too little text: fall back and say so.
+        let tiny = readability("<html><body><p>Just a line.</p></body></html>", "");
+        assert_ne!(tiny.engine, "readability");
+        assert_eq!(tiny.warnings, vec!["readability_fallback"]);
+        assert!(tiny.text.contains("Just a line."));
+    }
+
+    #[test]
+    fn fixed_html_engines_override_auto_selection() {
+        assert_eq!(HtmlEngine::parse(" HTML_MAIN "), Some(HtmlEngine::Main));
+        assert_eq!(HtmlEngine::parse("boilerpipe"), None);
+        let html = "<html><body><nav><a href=\"/a\">Menu</a></nav><p>Body text.</p></body></html>";
+        let ex = best_effort_text_from_bytes_with_engine(
+            html.as_bytes(),
+            Some("text/html"),
+            "https://x/",
+            100,
+            200,
+            HtmlEngine::Html2text,
+        );
+        assert_eq!(ex.engine, "html2text");
+        assert!(ex.text.contains("Menu") && ex.text.contains("Body text."));
+        let ex = best_effort_text_from_bytes_with_engine(
+            b"<html><body></body></html>",
+            Some("text/html"),
+            "https://x/",
+            100,
+            200,
+            HtmlEngine::Main,
+        );
+        assert!(ex.warnings.contains(&"extract_engine_fallback"), "{ex:?}");
+    }
+
     #[test]
     fn html_main_to_text_prefers_article_like_blocks() {
         let html = r#"
@@ -3114,6 +5122,7 @@ mod tests {
             max_outline_items: 40,
             max_blocks: 20,
             max_block_chars: 200,
+            truncation_strategy: TruncationStrategy::Head,
         };
         let r =
             extract_pipeline_from_extracted(b"", None, "https://nextjs.org/docs", extracted0, cfg);
@@ -3163,6 +5172,66 @@ mod tests {
         assert!(!s.blocks.is_empty());
     }
 
+    #[test]
+    fn structure_sections_span_to_next_same_or_higher_heading() {
+        let html = "<html><body><main>\
+            <p>Lead paragraph.</p>\
+            <h1>Guide</h1><p>Guide body.</p>\
+            <h2>Install</h2><p>Run the installer.</p>\
+            <h3>Linux</h3><li>apt install tool</li>\
+            <h2>Usage</h2><p>Call tool.</p>\
+            <h1>Appendix</h1><p>Extra notes.</p>\
+            </main></body></html>";
+        let s = extract_structure_from_html(html, 25, 40, 400);
+        let secs = structure_sections(&s);
+        let labels: Vec<(Option<&str>, u8)> = secs
+            .iter()
+            .map(|x| (x.heading.as_deref(), x.level))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                (None, 0),
+                (Some("Guide"), 1),
+                (Some("Install"), 2),
+                (Some("Linux"), 3),
+                (Some("Usage"), 2),
+                (Some("Appendix"), 1),
+            ]
+        );
+        assert_eq!(secs[0].text, "Lead paragraph.");
+        // h1 runs through its nested h2/h3 up to the next h1.
+        assert!(secs[1].text.starts_with("Guide\n\nGuide body."));
+        assert!(secs[1].text.contains("apt install tool") && secs[1].text.contains("Call tool."));
+        assert!(!secs[1].text.contains("Appendix"));
+        // h2 "Install" includes its h3 but stops at the sibling h2.
+        assert_eq!(
+            secs[2].text,
+            "Install\n\nRun the installer.\n\nLinux\n\napt install tool"
+        );
+        assert_eq!(secs[3].text, "Linux\n\napt install tool");
+        assert_eq!(secs[5].text, "Appendix\n\nExtra notes.");
+        for x in &secs {
+            let got: String = s
+                .structure_text
+                .chars()
+                .skip(x.start_char)
+                .take(x.end_char - x.start_char)
+                .collect();
+            assert_eq!(got, x.text);
+        }
+
+        // Markdown headings carry their `#` depth; no intro when the doc opens with a heading.
+        let md = extract_structure_from_text("markdown", "# A\n\none\n\n## B\n\ntwo", 25, 40, 400);
+        let secs = structure_sections(&md);
+        assert_eq!(secs.len(), 2);
+        assert_eq!(
+            (secs[0].level, secs[0].text.as_str()),
+            (1, "A\n\none\n\nB\n\ntwo")
+        );
+        assert_eq!((secs[1].level, secs[1].text.as_str()), (2, "B\n\ntwo"));
+    }
+
     #[test]
     fn best_chunks_for_query_prefers_matching_paragraphs() {
         let text = "alpha beta\n\nbravo CHARLIE delta\n\nzzz";
@@ -3423,6 +5492,7 @@ Second paragraph also long enough to be a usable chunk for humans.
             max_outline_items: 0,
             max_blocks: 0,
             max_block_chars: 0,
+            truncation_strategy: TruncationStrategy::Head,
         };
         let r = extract_pipeline_from_extracted(b"", None, "https://example.com/", extracted0, cfg);
         assert!(
@@ -3431,6 +5501,66 @@ Second paragraph also long enough to be a usable chunk for humans.
         );
     }
 
+    #[test]
+    fn timed_youtube_transcript_extracts_and_chunks_its_text_projection() {
+        let timed = youtube::TimedTranscript {
+            text: "Tide tables explained.\n\nBond yields rose sharply this week.".to_string(),
+            cues: vec![
+                youtube::TranscriptCue {
+                    start_s: 0.0,
+                    text: "Tide tables explained.".to_string(),
+                },
+                youtube::TranscriptCue {
+                    start_s: 42.5,
+                    text: "Bond yields rose sharply this week.".to_string(),
+                },
+            ],
+            chapters: vec![youtube::TranscriptChapter {
+                start_s: 40.0,
+                title: "Markets".to_string(),
+            }],
+        };
+        let bytes = serde_json::to_vec(&timed).unwrap();
+        let cfg = ExtractPipelineCfg {
+            query: Some("bond yields"),
+            width: 80,
+            max_chars: 10_000,
+            top_chunks: 2,
+            max_chunk_chars: 200,
+            include_structure: true,
+            max_outline_items: 10,
+            max_blocks: 10,
+            max_block_chars: 200,
+            truncation_strategy: TruncationStrategy::Head,
+        };
+        let r = extract_pipeline_from_bytes(
+            &bytes,
+            Some(youtube::TRANSCRIPT_JSON_CONTENT_TYPE),
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            cfg,
+        );
+        assert_eq!(r.extracted.engine, "youtube_transcript");
+        assert!(
+            !r.extracted.text.contains("start_s"),
+            "{}",
+            r.extracted.text
+        );
+        assert!(r.chunks[0].text.contains("Bond yields rose"));
+        let st = r.structure.expect("structure");
+        assert!(!st.structure_text.contains("start_s"));
+
+        // A cut-off body still yields its words, with a warning.
+        let ex = best_effort_text_from_bytes(
+            &bytes[..bytes.len() / 2],
+            Some(youtube::TRANSCRIPT_JSON_CONTENT_TYPE),
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            80,
+            500,
+        );
+        assert!(ex.warnings.contains(&"youtube_transcript_json_invalid"));
+        assert!(ex.text.contains("Tide tables"));
+    }
+
     #[test]
     fn extract_pipeline_fallback_prefers_non_promo_paragraph_over_prefix() {
         let promo = "Join us for four days of incredible opportunities. Buy your ticket now!";
@@ -3452,6 +5582,7 @@ Second paragraph also long enough to be a usable chunk for humans.
             max_outline_items: 0,
             max_blocks: 0,
             max_block_chars: 0,
+            truncation_strategy: TruncationStrategy::Head,
         };
         let r = extract_pipeline_from_extracted(
             &[],
@@ -3485,4 +5616,256 @@ Second paragraph also long enough to be a usable chunk for humans.
             Some("https://example.com/".to_string())
         );
     }
+
+    fn blocks_structure(kinds_and_texts: &[(&'static str, String)]) -> ExtractedStructure {
+        let blocks = kinds_and_texts
+            .iter()
+            .map(|(kind, text)| StructuredBlock {
+                kind,
+                start_char: 0,
+                end_char: text.chars().count(),
+                text: text.clone(),
+                level: None,
+            })
+            .collect();
+        ExtractedStructure {
+            engine: "test",
+            title: None,
+            outline: vec![],
+            structure_text: String::new(),
+            text_chars: 0,
+            blocks,
+            warnings: vec![],
+        }
+    }
+
+    #[test]
+    fn extraction_confidence_scores_clean_article_high() {
+        let para = "Tokamaks confine hot plasma with strong magnetic fields so that fusion \
+                    reactions can happen at useful rates. The field lines twist around the \
+                    torus, which keeps charged particles from drifting into the walls.";
+        let html = format!(
+            "<html><body><main><h1>How tokamaks work</h1><p>{para}</p><p>{para}</p>\
+             <p>{para}</p><p>{para}</p></main></body></html>"
+        );
+        let extracted = ExtractedText {
+            engine: "html2text",
+            text: html_to_text(&html, 100),
+            warnings: vec![],
+        };
+        let s = best_effort_structure_from_bytes(
+            html.as_bytes(),
+            Some("text/html"),
+            "https://example.com/tokamak",
+            &extracted,
+            25,
+            40,
+            400,
+        );
+        let c = extraction_confidence(&extracted, Some(&s));
+        assert!(c >= 0.8, "confidence={c}");
+        assert!(extraction_confidence(&extracted, None) >= 0.8);
+    }
+
+    #[test]
+    fn extraction_confidence_scores_js_shell_low() {
+        let shell = ExtractedText {
+            engine: "html_main",
+            text: format!(
+                "Please enable JavaScript to continue. {}",
+                "self.__next_s.push([0,{\"suppressHydrationWarning\":true}]) ".repeat(20)
+            ),
+            warnings: vec![],
+        };
+        assert!(looks_like_bundle_gunk(&shell.text));
+        assert!(extraction_confidence(&shell, None) <= 0.1);
+
+        // Escaped-JSON soup trips the ~1/3 alpha cutoff used by the chunk filter.
+        let soup = ExtractedText {
+            engine: "html2text",
+            text: "{\"a\":[1,2,3],\"b\":{\"c\":\"d\"}}".repeat(20),
+            warnings: vec![],
+        };
+        assert!(extraction_confidence(&soup, None) < EXTRACTION_CONFIDENCE_LOW);
+
+        let empty = ExtractedText {
+            engine: "html2text",
+            text: "  \n ".to_string(),
+            warnings: vec![],
+        };
+        assert_eq!(extraction_confidence(&empty, None), 0.0);
+    }
+
+    #[test]
+    fn extraction_confidence_low_threshold_matches_ui_shell_rule() {
+        let prose = ExtractedText {
+            engine: "html_main",
+            text: "Substantive documentation text about route handlers. ".repeat(30),
+            warnings: vec![],
+        };
+        let item = |n: usize| ("list_item", format!("Nav {n}"));
+        let para = || ("paragraph", "A longer paragraph block. ".repeat(4));
+
+        // 6/10 short list items: the UI-shell rule fires.
+        let mut shell: Vec<_> = (0..6).map(item).collect();
+        shell.extend((0..4).map(|_| para()));
+        let c = extraction_confidence(&prose, Some(&blocks_structure(&shell)));
+        assert!(c < EXTRACTION_CONFIDENCE_LOW, "confidence={c}");
+
+        // 9/10 list items (even long ones): also a shell.
+        let mut listy: Vec<_> = (0..9).map(|_| ("list_item", "x".repeat(80))).collect();
+        listy.push(para());
+        let c = extraction_confidence(&prose, Some(&blocks_structure(&listy)));
+        assert!(c < EXTRACTION_CONFIDENCE_LOW, "confidence={c}");
+
+        // 5/10 short list items: not a shell.
+        let mut mixed: Vec<_> = (0..5).map(item).collect();
+        mixed.extend((0..5).map(|_| para()));
+        let c = extraction_confidence(&prose, Some(&blocks_structure(&mixed)));
+        assert!(c >= EXTRACTION_CONFIDENCE_LOW, "confidence={c}");
+    }
+
+    #[test]
+    fn truncate_keep_ends_retains_both_ends_on_char_boundaries() {
+        // Multi-byte chars everywhere, so a byte-based cut would split a code point.
+        let body = "é🙂中 ".repeat(500);
+        let text = format!("INTRO {body} REFERENCES");
+        let total = text.chars().count();
+
+        for (strategy, head_min) in [
+            (TruncationStrategy::HeadTail, 380),
+            (TruncationStrategy::MiddleOut, 250),
+        ] {
+            let (out, n, clipped) = truncate_keep_ends(&text, 600, strategy);
+            assert!(clipped);
+            assert_eq!(n, out.chars().count());
+            assert!(n <= 600, "{strategy:?}: {n} chars");
+            assert!(out.starts_with("INTRO "), "{strategy:?}");
+            assert!(out.ends_with(" REFERENCES"), "{strategy:?}");
+            let (head, rest) = out.split_once("\n\n[... truncated ").expect("marker");
+            let (dropped, tail) = rest.split_once(" chars ...]\n\n").expect("marker end");
+            assert!(text.starts_with(head) && text.ends_with(tail));
+            assert!(head.chars().count() >= head_min, "{strategy:?}");
+            let dropped: usize = dropped.parse().expect("dropped count");
+            assert!(dropped > 0 && dropped < total);
+        }
+
+        // Head (default) and texts within budget are plain prefix truncation.
+        let (out, n, clipped) = truncate_keep_ends(&text, 600, TruncationStrategy::Head);
+        assert!(clipped && n == 600 && !out.contains("[... truncated"));
+        let (out, _, clipped) = truncate_keep_ends("short", 600, TruncationStrategy::HeadTail);
+        assert!(!clipped && out == "short");
+        // Budget too small for the marker: prefix truncation.
+        let (out, n, _) = truncate_keep_ends(&text, 20, TruncationStrategy::HeadTail);
+        assert!(n == 20 && out.starts_with("INTRO "));
+
+        assert_eq!(
+            TruncationStrategy::parse(" Head_Tail "),
+            Some(TruncationStrategy::HeadTail)
+        );
+        assert_eq!(TruncationStrategy::parse("tail"), None);
+    }
+
+    #[test]
+    fn extract_tables_pads_spans_and_skips_layout_tables() {
+        let html = r#"<html><body>
+          <table role="presentation"><tr><td>a</td><td>b</td></tr></table>
+          <table><tr><td><a href="/1">Home</a></td><td><a href="/2">About us</a></td></tr>
+                 <tr><td><a href="/3">Blog</a></td><td>x</td></tr></table>
+          <table><tr><td>only</td></tr><tr><td>one column</td></tr></table>
+          <table>
+            <thead><tr><th>Name</th><th colspan="2">Score | pts</th></tr></thead>
+            <tbody>
+              <tr><td rowspan="2">Ann</td><td>1</td><td>2</td></tr>
+              <tr><td>3, "x"</td><td>4</td></tr>
+              <tr><td>Bo</td><td>5
+                 <table><tr><th>inner</th><th>t</th></tr><tr><td>i</td><td>j</td></tr></table>
+              </td></tr>
+            </tbody>
+          </table>
+        </body></html>"#;
+        let tables = extract_tables(html);
+        assert_eq!(tables.len(), 2, "{tables:?}");
+        let t = &tables[0];
+        assert_eq!(t.headers, vec!["Name", "Score | pts", ""]);
+        assert_eq!(
+            t.rows,
+            vec![
+                vec!["Ann", "1", "2"],
+                vec!["", "3, \"x\"", "4"],
+                vec!["Bo", "5 inner t i j", ""],
+            ]
+        );
+        assert_eq!(tables[1].headers, vec!["inner", "t"]);
+        assert_eq!(tables[1].rows, vec![vec!["i", "j"]]);
+
+        assert_eq!(
+            t.to_markdown().lines().take(3).collect::<Vec<_>>(),
+            vec![
+                "| Name | Score \\| pts |  |",
+                "| --- | --- | --- |",
+                "| Ann | 1 | 2 |"
+            ]
+        );
+        assert!(t
+            .to_csv()
+            .starts_with("Name,Score | pts,\r\nAnn,1,2\r\n,\"3, \"\"x\"\"\",4\r\n"));
+    }
+
+    #[test]
+    fn extract_metadata_prefers_opengraph_and_tolerates_bad_json_ld() {
+        let html = r#"<html><head>
+          <title>Fallback title | Site</title>
+          <meta property="og:title" content="  Tide  tables explained ">
+          <meta name="twitter:title" content="Twitter title">
+          <meta name="description" content="Plain description.">
+          <meta name="twitter:image" content="/img/card.png">
+          <link rel="canonical" href="https://example.com/tides">
+          <meta property="article:published_time" content="2024-03-05T08:00:00Z">
+          <script type="application/ld+json">
+            {"@context":"https://schema.org","@graph":[
+              {"@type":"NewsArticle","headline":"LD headline","author":[{"@type":"Person","name":"Ada Lovelace"}]},
+              {"@type":"Organization","name":"Example News"}]}
+          </script>
+          <script type="application/ld+json">[{"@type":"BreadcrumbList"}];</script>
+          <script type="application/ld+json">{ "@type": "Broken", </script>
+        </head><body></body></html>"#;
+        let m = extract_metadata(html);
+        assert_eq!(m.title.as_deref(), Some("Tide tables explained"));
+        assert_eq!(m.description.as_deref(), Some("Plain description."));
+        assert_eq!(m.image.as_deref(), Some("/img/card.png"));
+        assert_eq!(m.author.as_deref(), Some("Ada Lovelace"));
+        assert_eq!(
+            m.canonical_url.as_deref(),
+            Some("https://example.com/tides")
+        );
+        assert_eq!(m.published_time.as_deref(), Some("2024-03-05T08:00:00Z"));
+        assert_eq!(m.site_name, None);
+        let types: Vec<_> = m
+            .json_ld
+            .iter()
+            .filter_map(|v| v["@type"].as_str())
+            .collect();
+        assert_eq!(types, vec!["NewsArticle", "Organization", "BreadcrumbList"]);
+        assert_eq!(m.json_ld_invalid, 1);
+
+        // Nothing but a <title>: other fields stay absent in JSON.
+        let m = extract_metadata("<html><head><title>Only</title></head></html>");
+        assert_eq!(
+            serde_json::to_value(&m).unwrap(),
+            serde_json::json!({"title": "Only"})
+        );
+    }
+
+    #[test]
+    fn extract_tables_stops_at_the_cell_budget() {
+        let row = "<tr><th>k</th><td>v</td></tr>".repeat(MAX_TABLE_CELLS);
+        let html = format!("<table>{row}</table><table>{row}</table>");
+        let tables = extract_tables(&html);
+        assert_eq!(tables.len(), 1);
+        assert!(tables[0].truncated);
+        let cells: usize =
+            tables[0].rows.iter().map(Vec::len).sum::<usize>() + tables[0].headers.len();
+        assert_eq!(cells, MAX_TABLE_CELLS);
+    }
 }
@@ -86,6 +86,204 @@ impl FirecrawlClient {
             elapsed_ms: t0.elapsed().as_millis(),
         })
     }
+
+    fn crawl_endpoint_v2() -> String {
+        // Same override story as `endpoint_v2`. Job status lives at `<endpoint>/<id>`.
+        std::env::var("WEBPIPE_FIRECRAWL_CRAWL_ENDPOINT_V2")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| "https://api.firecrawl.dev/v2/crawl".to_string())
+    }
+
+    /// Delay between crawl status polls (`WEBPIPE_FIRECRAWL_CRAWL_POLL_MS`, default 1000).
+    fn crawl_poll_interval() -> std::time::Duration {
+        let ms = std::env::var("WEBPIPE_FIRECRAWL_CRAWL_POLL_MS")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(1_000)
+            .clamp(10, 10_000);
+        std::time::Duration::from_millis(ms)
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let resp = self
+            .client
+            .get(url)
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("Bearer {}", self.api_key),
+            )
+            .send()
+            .await
+            .map_err(|e| Error::Fetch(e.to_string()))?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(Error::Fetch(format!(
+                "firecrawl crawl status HTTP {status}"
+            )));
+        }
+        resp.json().await.map_err(|e| Error::Fetch(e.to_string()))
+    }
+
+    /// Crawl from `root_url` with Firecrawl's crawl endpoint: start a job for at most `limit`
+    /// pages and `max_depth` link hops, then poll it until it finishes or `timeout_ms` runs out.
+    /// A timed-out crawl returns the pages scraped so far with `completed=false`.
+    pub async fn crawl(
+        &self,
+        root_url: &str,
+        limit: usize,
+        max_depth: usize,
+        timeout_ms: u64,
+    ) -> Result<FirecrawlCrawlResult> {
+        let t0 = Instant::now();
+        let deadline = t0 + std::time::Duration::from_millis(timeout_ms);
+
+        let body = serde_json::json!({
+            "url": root_url,
+            "limit": limit,
+            "maxDiscoveryDepth": max_depth,
+            "scrapeOptions": { "formats": ["markdown"], "onlyMainContent": true }
+        });
+        let resp = self
+            .client
+            .post(Self::crawl_endpoint_v2())
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("Bearer {}", self.api_key),
+            )
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| Error::Fetch(e.to_string()))?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(Error::Fetch(format!("firecrawl crawl HTTP {status}")));
+        }
+        let started: FirecrawlCrawlStarted =
+            resp.json().await.map_err(|e| Error::Fetch(e.to_string()))?;
+        let id = match (started.success, started.id) {
+            (true, Some(id)) if !id.trim().is_empty() => id,
+            _ => {
+                return Err(Error::Fetch(
+                    "firecrawl crawl returned success=false".to_string(),
+                ))
+            }
+        };
+        let status_url = format!("{}/{id}", Self::crawl_endpoint_v2().trim_end_matches('/'));
+
+        let mut page: FirecrawlCrawlStatus;
+        loop {
+            page = self.get_json(&status_url).await?;
+            match page.status.as_str() {
+                "completed" => break,
+                "failed" | "cancelled" => {
+                    return Err(Error::Fetch(format!("firecrawl crawl {}", page.status)));
+                }
+                _ => {}
+            }
+            let wait = Self::crawl_poll_interval();
+            if Instant::now() + wait >= deadline {
+                break;
+            }
+            tokio::time::sleep(wait).await;
+        }
+        let completed = page.status == "completed";
+        let credits_used = page.credits_used;
+
+        // Large results are paginated through `next`.
+        let mut docs: Vec<FirecrawlDoc> = Vec::new();
+        loop {
+            docs.extend(page.data.drain(..).filter_map(FirecrawlCrawlData::into_doc));
+            let next = page.next.take().filter(|n| !n.trim().is_empty());
+            match next {
+                Some(next) if docs.len() < limit && Instant::now() < deadline => {
+                    page = self.get_json(&next).await?;
+                }
+                _ => break,
+            }
+        }
+        docs.truncate(limit);
+
+        Ok(FirecrawlCrawlResult {
+            docs,
+            completed,
+            credits_used,
+            elapsed_ms: t0.elapsed().as_millis(),
+        })
+    }
+}
+
+/// One crawled page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirecrawlDoc {
+    pub url: String,
+    pub title: Option<String>,
+    pub status_code: Option<u16>,
+    pub markdown: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct FirecrawlCrawlResult {
+    pub docs: Vec<FirecrawlDoc>,
+    /// False when the crawl was still running at the deadline (`docs` is partial).
+    pub completed: bool,
+    /// Firecrawl's own credit count for the job, when reported.
+    pub credits_used: Option<u64>,
+    pub elapsed_ms: u128,
+}
+
+#[derive(Debug, Deserialize)]
+struct FirecrawlCrawlStarted {
+    success: bool,
+    id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FirecrawlCrawlStatus {
+    status: String,
+    #[serde(default, rename = "creditsUsed")]
+    credits_used: Option<u64>,
+    #[serde(default)]
+    data: Vec<FirecrawlCrawlData>,
+    #[serde(default)]
+    next: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FirecrawlCrawlData {
+    markdown: Option<String>,
+    #[serde(default)]
+    metadata: Option<FirecrawlCrawlMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FirecrawlCrawlMetadata {
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default, rename = "sourceURL")]
+    source_url: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default, rename = "statusCode")]
+    status_code: Option<u16>,
+}
+
+impl FirecrawlCrawlData {
+    /// Pages without a URL can't be cited, so they're dropped.
+    fn into_doc(self) -> Option<FirecrawlDoc> {
+        let meta = self.metadata?;
+        let url = meta
+            .url
+            .or(meta.source_url)
+            .filter(|u| !u.trim().is_empty())?;
+        Some(FirecrawlDoc {
+            url,
+            title: meta.title.filter(|t| !t.trim().is_empty()),
+            status_code: meta.status_code,
+            markdown: self.markdown.unwrap_or_default(),
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -147,4 +345,54 @@ mod tests {
         assert!(parsed.success);
         assert_eq!(parsed.data.unwrap().markdown.unwrap(), "# Hi");
     }
+
+    #[test]
+    fn parses_crawl_status_pages_and_drops_urlless_docs() {
+        let js = r##"
+        {
+          "status": "completed",
+          "total": 3,
+          "completed": 3,
+          "creditsUsed": 3,
+          "next": "https://api.firecrawl.dev/v2/crawl/job-1?skip=3",
+          "data": [
+            { "markdown": "# A", "metadata": { "sourceURL": "https://a.example/", "title": "A", "statusCode": 200 } },
+            { "markdown": "# B", "metadata": { "url": "https://a.example/b", "sourceURL": "https://a.example/b?ref=x", "title": "" } },
+            { "markdown": "# orphan" }
+          ]
+        }
+        "##;
+        let parsed: FirecrawlCrawlStatus = serde_json::from_str(js).unwrap();
+        assert_eq!(parsed.status, "completed");
+        assert_eq!(parsed.credits_used, Some(3));
+        assert!(parsed.next.is_some());
+        let docs: Vec<FirecrawlDoc> = parsed
+            .data
+            .into_iter()
+            .filter_map(FirecrawlCrawlData::into_doc)
+            .collect();
+        assert_eq!(
+            docs,
+            vec![
+                FirecrawlDoc {
+                    url: "https://a.example/".to_string(),
+                    title: Some("A".to_string()),
+                    status_code: Some(200),
+                    markdown: "# A".to_string(),
+                },
+                FirecrawlDoc {
+                    url: "https://a.example/b".to_string(),
+                    title: None,
+                    status_code: None,
+                    markdown: "# B".to_string(),
+                },
+            ]
+        );
+
+        // A job that is still running has no data yet.
+        let parsed: FirecrawlCrawlStatus =
+            serde_json::from_str(r#"{ "status": "scraping", "total": 0, "completed": 0 }"#)
+                .unwrap();
+        assert!(parsed.data.is_empty());
+    }
 }
@@ -0,0 +1,209 @@
+//! Filesystem-safe relative paths for URLs, for tools that write one file per document (cache
+//! export, artifact dumps).
+//!
+//! The fetch cache itself never needs this (its files are named by hex keys), but a readable
+//! layout like `example.com/docs/page` has to survive Windows: no `<>:"/\|?*` or control chars,
+//! no trailing dots/spaces, no reserved device names (`CON`, `NUL`, `COM1`, ...), and bounded
+//! component and path lengths. Sanitizing is lossy, so the last component always ends with the
+//! first 16 hex chars of `sha256(url)`: distinct URLs never share a path.
+
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+
+/// Max chars per path component (before the hash suffix on the last one).
+pub const MAX_COMPONENT_CHARS: usize = 64;
+/// Max chars of the whole relative path (separators included), well under Windows' 260-char
+/// `MAX_PATH` once joined onto an export dir. Directories that don't fit are dropped (the URL is
+/// still identified by the file name's hash).
+pub const MAX_PATH_CHARS: usize = 200;
+
+/// Windows device names, reserved with or without an extension (`con.txt` is still `CON`).
+const WINDOWS_RESERVED: &[&str] = &[
+    "AUX", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "CON", "LPT1",
+    "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9", "NUL", "PRN",
+];
+
+/// One path component: invalid chars become `_`, length is bounded, trailing dots/spaces are
+/// dropped, and reserved names get a `_` prefix. Never empty, `.` or `..`.
+fn sanitize_component(s: &str) -> String {
+    let mut out: String = s
+        .chars()
+        .map(|c| {
+            if c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .take(MAX_COMPONENT_CHARS)
+        .collect();
+    let kept = out.trim_end_matches(['.', ' ']).len();
+    out.truncate(kept);
+    if out.is_empty() {
+        return "_".to_string();
+    }
+    let stem = out.split('.').next().unwrap_or("").trim_end();
+    if WINDOWS_RESERVED
+        .iter()
+        .any(|r| r.eq_ignore_ascii_case(stem))
+    {
+        out.insert(0, '_');
+    }
+    out
+}
+
+/// Relative path for storing `url` on disk: `<host>/<path segments...>/<last>-<hash16>`.
+///
+/// The query is folded into the last component (after `_`) so `?page=2` stays readable; the
+/// fragment is dropped. Unparseable input is treated as a single opaque name.
+pub fn sanitize_for_fs(url: &str) -> PathBuf {
+    let url = url.trim();
+    let hash = hex::encode(&Sha256::digest(url.as_bytes())[..8]);
+
+    let (mut parts, query): (Vec<String>, Option<String>) = match url::Url::parse(url) {
+        Ok(u) => {
+            let host = match (u.host_str(), u.port()) {
+                (Some(h), Some(p)) => format!("{h}_{p}"),
+                (Some(h), None) => h.to_string(),
+                (None, _) => u.scheme().to_string(),
+            };
+            let segs = u
+                .path_segments()
+                .map(|s| {
+                    s.filter(|s| !s.is_empty())
+                        .map(str::to_string)
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            let mut parts = vec![host];
+            parts.extend(segs);
+            (parts, u.query().map(str::to_string))
+        }
+        Err(_) => (vec![url.to_string()], None),
+    };
+
+    // The file name: the last URL segment (or `index` for directory-like URLs) plus the query.
+    let mut last = if parts.len() > 1 {
+        parts.pop().unwrap_or_default()
+    } else {
+        "index".to_string()
+    };
+    if let Some(q) = query.filter(|q| !q.is_empty()) {
+        last = format!("{last}_{q}");
+    }
+
+    let name = format!("{}-{hash}", sanitize_component(&last));
+    let mut budget = MAX_PATH_CHARS.saturating_sub(name.chars().count());
+    let mut out = PathBuf::new();
+    for p in &parts {
+        let c = sanitize_component(p);
+        let need = c.chars().count() + 1;
+        if need > budget {
+            break;
+        }
+        budget -= need;
+        out.push(c);
+    }
+    out.push(name);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_portable(p: &std::path::Path) {
+        for c in p.components() {
+            let s = c.as_os_str().to_str().unwrap();
+            assert!(!s.is_empty() && s != "." && s != "..", "{p:?}");
+            assert!(s.chars().count() <= MAX_COMPONENT_CHARS + 17, "{p:?}");
+            assert!(
+                !s.chars()
+                    .any(|c| c.is_control() || "<>:\"/\\|?*".contains(c)),
+                "{p:?}"
+            );
+            assert!(!s.ends_with('.') && !s.ends_with(' '), "{p:?}");
+            let stem = s.split('.').next().unwrap();
+            assert!(
+                !WINDOWS_RESERVED
+                    .iter()
+                    .any(|r| r.eq_ignore_ascii_case(stem)),
+                "{p:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn urls_map_to_readable_portable_paths() {
+        let p = sanitize_for_fs("https://example.com/docs/page?id=7&q=a:b");
+        assert_portable(&p);
+        let parts: Vec<&str> = p.iter().map(|c| c.to_str().unwrap()).collect();
+        assert_eq!(parts[..2], ["example.com", "docs"]);
+        assert!(parts[2].starts_with("page_id=7&q=a_b-"), "{p:?}");
+
+        let p = sanitize_for_fs("http://127.0.0.1:8080/");
+        assert_eq!(p.iter().next().unwrap(), "127.0.0.1_8080");
+        assert!(p
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("index-"));
+    }
+
+    #[test]
+    fn reserved_names_long_paths_and_odd_chars_are_made_safe() {
+        for u in [
+            "https://example.com/CON",
+            "https://example.com/nul.txt/aux",
+            "https://example.com/com1?x=1",
+            "https://example.com/a%3Ab%2Fc%7C%3F%2A/trailing.",
+            "https://example.com/..%2F..%2Fetc/passwd",
+            "not a url: \u{7}?*",
+        ] {
+            assert_portable(&sanitize_for_fs(u));
+        }
+        let p = sanitize_for_fs("https://example.com/CON");
+        assert!(p.ends_with(format!(
+            "_CON-{}",
+            hex::encode(&Sha256::digest(b"https://example.com/CON")[..8])
+        )));
+
+        let deep = format!(
+            "https://example.com/{}",
+            vec!["x".repeat(300); 20].join("/")
+        );
+        let p = sanitize_for_fs(&deep);
+        assert_portable(&p);
+        assert!(
+            p.to_str().unwrap().chars().count() <= MAX_PATH_CHARS,
+            "{p:?}"
+        );
+        assert_eq!(p.iter().next().unwrap(), "example.com");
+    }
+
+    #[test]
+    fn lossy_sanitizing_never_merges_distinct_urls() {
+        let mut urls: Vec<String> = [
+            "https://example.com/a?b",
+            "https://example.com/a_b",
+            "https://example.com/a:b",
+            "https://example.com/a*b",
+            "https://example.com/CON",
+            "https://example.com/_CON",
+        ]
+        .map(String::from)
+        .to_vec();
+        urls.push(format!("https://example.com/{}1", "y".repeat(200)));
+        urls.push(format!("https://example.com/{}2", "y".repeat(200)));
+        let paths: std::collections::BTreeSet<PathBuf> =
+            urls.iter().map(|u| sanitize_for_fs(u)).collect();
+        assert_eq!(paths.len(), urls.len());
+        assert_eq!(
+            sanitize_for_fs(&urls[0]),
+            sanitize_for_fs(&urls[0]),
+            "deterministic"
+        );
+    }
+}
@@ -0,0 +1,344 @@
+//! Deterministic keyphrases and entity-ish spans, for topic filters without a model.
+//!
+//! Keyphrases are RAKE-style: candidates are runs of content words between stopwords and
+//! punctuation, each word scores `degree / frequency` over all candidates, and a phrase scores the
+//! sum of its words. Entities are surface patterns only: URLs, emails, and runs of two or more
+//! capitalized words (`kind: "name"`).
+
+use serde::Serialize;
+
+/// Upper bound on `top_n` for [`keyword_index`].
+pub const MAX_KEYPHRASES: usize = 50;
+const MAX_ENTITIES: usize = 50;
+/// Longer content-word runs are split into phrases of at most this many words.
+const MAX_PHRASE_WORDS: usize = 4;
+const MAX_ENTITY_CHARS: usize = 200;
+/// Function words missing from the `textprep` list that would otherwise glue onto phrases
+/// (RAKE relies on stopwords to split candidates). Sorted.
+const EXTRA_STOPWORDS: &[&str] = &[
+    "also",
+    "although",
+    "among",
+    "another",
+    "can",
+    "could",
+    "either",
+    "even",
+    "every",
+    "however",
+    "including",
+    "instead",
+    "just",
+    "less",
+    "like",
+    "many",
+    "may",
+    "might",
+    "much",
+    "must",
+    "often",
+    "per",
+    "rather",
+    "said",
+    "says",
+    "several",
+    "shall",
+    "since",
+    "still",
+    "though",
+    "thus",
+    "toward",
+    "towards",
+    "upon",
+    "via",
+    "whether",
+    "within",
+    "without",
+    "would",
+    "yet",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Keyphrase {
+    /// Lowercased phrase.
+    pub phrase: String,
+    /// RAKE score, rounded to two decimals.
+    pub score: f32,
+    /// Occurrences in the text.
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TextEntity {
+    /// As written (first occurrence).
+    pub text: String,
+    /// `"name"`, `"url"` or `"email"`.
+    pub kind: &'static str,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct KeywordIndex {
+    /// Highest score first; ties by count, then first occurrence.
+    pub keyphrases: Vec<Keyphrase>,
+    /// Most frequent first; ties by first occurrence.
+    pub entities: Vec<TextEntity>,
+}
+
+enum Tok<'a> {
+    Word(&'a str),
+    /// Punctuation, a blank line or a URL/email: ends both keyphrase candidates and name runs.
+    Break,
+}
+
+fn trim_url_punct(s: &str) -> &str {
+    s.trim_start_matches(['(', '[', '<', '"', '\''])
+        .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '>', '"', '\''])
+}
+
+fn looks_like_url(s: &str) -> bool {
+    let l = s.to_ascii_lowercase();
+    (l.starts_with("http://") || l.starts_with("https://") || l.starts_with("www.")) && s.len() > 8
+}
+
+fn looks_like_email(s: &str) -> bool {
+    let Some((local, domain)) = s.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._%+-".contains(c))
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+}
+
+/// Split `text` into words and breaks, pushing URLs/emails (with their token position) to
+/// `entities` as they are seen.
+fn tokenize<'a>(text: &'a str, entities: &mut Vec<(String, &'static str, usize)>) -> Vec<Tok<'a>> {
+    let mut out = Vec::new();
+    // Blank lines end phrases too (paragraphs, headings); single newlines are often just wrapping.
+    let words = text.lines().flat_map(|l| {
+        let blank = l.trim().is_empty().then_some("\n");
+        l.split_whitespace().chain(blank)
+    });
+    for raw in words {
+        let t = trim_url_punct(raw);
+        let kind = if looks_like_url(t) {
+            Some("url")
+        } else if looks_like_email(t) {
+            Some("email")
+        } else {
+            None
+        };
+        if let Some(kind) = kind {
+            entities.push((t.to_string(), kind, out.len()));
+            out.push(Tok::Break);
+            continue;
+        }
+        // Words are alphanumeric runs; `-` and `'` join only between alphanumerics.
+        let mut start: Option<usize> = None;
+        for (i, c) in raw.char_indices() {
+            let joiner = (c == '-' || c == '\'')
+                && start.is_some()
+                && raw[i + 1..]
+                    .chars()
+                    .next()
+                    .is_some_and(|n| n.is_alphanumeric());
+            if c.is_alphanumeric() || joiner {
+                start.get_or_insert(i);
+                continue;
+            }
+            if let Some(s) = start.take() {
+                out.push(Tok::Word(&raw[s..i]));
+            }
+            out.push(Tok::Break);
+        }
+        if let Some(s) = start {
+            out.push(Tok::Word(&raw[s..]));
+        }
+    }
+    out
+}
+
+fn is_content_word(lower: &str) -> bool {
+    lower.chars().count() >= 2 && !lower.chars().all(|c| c.is_ascii_digit()) && !is_stopword(lower)
+}
+
+fn is_stopword(lower: &str) -> bool {
+    textprep_crate::stopwords::is_english_stopword(lower)
+        || EXTRA_STOPWORDS.binary_search(&lower).is_ok()
+}
+
+fn is_capitalized(w: &str) -> bool {
+    w.chars().next().is_some_and(|c| c.is_uppercase())
+}
+
+/// Top `top_n` keyphrases (bounded by [`MAX_KEYPHRASES`]) and the entities found in `text`.
+pub fn keyword_index(text: &str, top_n: usize) -> KeywordIndex {
+    let top_n = top_n.clamp(1, MAX_KEYPHRASES);
+
+    // Entity occurrences as (text, kind, token position).
+    let mut hits: Vec<(String, &'static str, usize)> = Vec::new();
+    let toks = tokenize(text, &mut hits);
+
+    // Candidate phrases (lowercased words), plus capitalized name runs.
+    let mut candidates: Vec<Vec<String>> = Vec::new();
+    let mut run: Vec<String> = Vec::new();
+    let mut name_run: Vec<&str> = Vec::new();
+    let flush = |run: &mut Vec<String>, candidates: &mut Vec<Vec<String>>| {
+        for chunk in run.chunks(MAX_PHRASE_WORDS) {
+            candidates.push(chunk.to_vec());
+        }
+        run.clear();
+    };
+    let flush_name = |name_run: &mut Vec<&str>, hits: &mut Vec<_>, pos: usize| {
+        // A leading stopword is usually a capitalized sentence start ("The Council ...").
+        let skip = name_run
+            .first()
+            .is_some_and(|w| is_stopword(&w.to_lowercase())) as usize;
+        if name_run.len() >= skip + 2 {
+            hits.push((name_run[skip..].join(" "), "name", pos));
+        }
+        name_run.clear();
+    };
+    for (pos, tok) in toks.iter().enumerate() {
+        match tok {
+            Tok::Word(w) => {
+                let lower = w.to_lowercase();
+                if is_content_word(&lower) {
+                    run.push(lower);
+                } else {
+                    flush(&mut run, &mut candidates);
+                }
+                if is_capitalized(w) {
+                    name_run.push(w);
+                } else {
+                    flush_name(&mut name_run, &mut hits, pos);
+                }
+            }
+            Tok::Break => {
+                flush(&mut run, &mut candidates);
+                flush_name(&mut name_run, &mut hits, pos);
+            }
+        }
+    }
+    flush(&mut run, &mut candidates);
+    flush_name(&mut name_run, &mut hits, toks.len());
+
+    let mut freq: std::collections::HashMap<&str, f32> = std::collections::HashMap::new();
+    let mut degree: std::collections::HashMap<&str, f32> = std::collections::HashMap::new();
+    for c in &candidates {
+        for w in c {
+            *freq.entry(w.as_str()).or_default() += 1.0;
+            *degree.entry(w.as_str()).or_default() += c.len() as f32;
+        }
+    }
+
+    // (phrase, score, count, first position)
+    let mut phrases: Vec<(String, f32, usize, usize)> = Vec::new();
+    let mut index: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for (pos, c) in candidates.iter().enumerate() {
+        let phrase = c.join(" ");
+        if let Some(&i) = index.get(&phrase) {
+            phrases[i].2 += 1;
+            continue;
+        }
+        let score: f32 = c
+            .iter()
+            .map(|w| degree[w.as_str()] / freq[w.as_str()])
+            .sum();
+        index.insert(phrase.clone(), phrases.len());
+        phrases.push((phrase, score, 1, pos));
+    }
+    phrases.sort_by(|a, b| b.1.total_cmp(&a.1).then(b.2.cmp(&a.2)).then(a.3.cmp(&b.3)));
+    let keyphrases = phrases
+        .into_iter()
+        .take(top_n)
+        .map(|(phrase, score, count, _)| Keyphrase {
+            phrase,
+            score: (score * 100.0).round() / 100.0,
+            count,
+        })
+        .collect();
+
+    // (text, kind, count, first position)
+    hits.sort_by_key(|h| h.2);
+    let mut entities: Vec<(String, &'static str, usize, usize)> = Vec::new();
+    for (t, kind, pos) in hits {
+        if t.chars().count() > MAX_ENTITY_CHARS {
+            continue;
+        }
+        match entities.iter_mut().find(|e| e.0 == t && e.1 == kind) {
+            Some(e) => e.2 += 1,
+            None => entities.push((t, kind, 1, pos)),
+        }
+    }
+    entities.sort_by(|a, b| b.2.cmp(&a.2).then(a.3.cmp(&b.3)));
+    let entities = entities
+        .into_iter()
+        .take(MAX_ENTITIES)
+        .map(|(text, kind, count, _)| TextEntity { text, kind, count })
+        .collect();
+
+    KeywordIndex {
+        keyphrases,
+        entities,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rake_ranks_multiword_technical_phrases_highest() {
+        let text =
+            "Compatibility of systems of linear constraints over the set of natural numbers. \
+            Criteria of compatibility of a system of linear Diophantine equations, strict \
+            inequations, and nonstrict inequations are considered. Upper bounds for components \
+            of a minimal set of solutions and algorithms of construction of minimal generating \
+            sets of solutions for all types of systems are given.";
+        let idx = keyword_index(text, 5);
+        let top: Vec<&str> = idx.keyphrases.iter().map(|k| k.phrase.as_str()).collect();
+        assert_eq!(top.len(), 5);
+        assert!(
+            top[..2].contains(&"minimal generating sets")
+                && top[..2].contains(&"linear diophantine equations"),
+            "top={top:?}"
+        );
+        assert!(idx.keyphrases.windows(2).all(|w| w[0].score >= w[1].score));
+        assert_eq!(keyword_index(text, 5), idx, "deterministic");
+    }
+
+    #[test]
+    fn extra_stopwords_stay_sorted() {
+        assert!(EXTRA_STOPWORDS.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn detects_names_urls_and_emails() {
+        let text = "The Open Source Initiative met in New York. Contact press@example.org or see \
+            https://example.org/news/osi. The Open Source Initiative (OSI) was founded in 1998.";
+        let idx = keyword_index(text, 10);
+        let get = |kind: &str, t: &str| {
+            idx.entities
+                .iter()
+                .find(|e| e.kind == kind && e.text == t)
+                .map(|e| e.count)
+        };
+        assert_eq!(get("name", "Open Source Initiative"), Some(2), "{idx:?}");
+        assert_eq!(get("name", "New York"), Some(1));
+        assert_eq!(get("email", "press@example.org"), Some(1));
+        assert_eq!(get("url", "https://example.org/news/osi"), Some(1));
+        // Sentence-start stopwords are not part of names; single capitalized words are not names.
+        assert!(idx.entities.iter().all(|e| !e.text.starts_with("The ")));
+        assert!(get("name", "Contact").is_none());
+        // The URL is not mined for keyphrases.
+        assert!(idx.keyphrases.iter().all(|k| !k.phrase.contains("https")));
+    }
+}
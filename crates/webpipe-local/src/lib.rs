@@ -3,19 +3,36 @@ use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use webpipe_core::{Error, FetchBackend, FetchRequest, FetchResponse, FetchSource, Result};
+use webpipe_core::{
+    CacheStatus, Error, FetchBackend, FetchRequest, FetchResponse, FetchSource, Result,
+};
 
+pub mod anthropic;
 pub mod arxiv;
 pub mod cache_search;
 pub mod compare;
+pub mod content_encoding;
+pub mod crawl;
+pub mod doc_id;
+pub mod dom_paths;
+#[cfg(feature = "embed-local")]
+pub mod embed_local;
+pub mod entities;
 pub mod extract;
 pub mod firecrawl;
+pub mod fs_path;
+pub mod keywords;
 pub mod links;
+pub mod llm;
+pub mod office;
 pub mod ollama;
 pub mod openai_compat;
 pub mod papers;
 pub mod perplexity;
+pub mod published;
 pub mod render_playwright;
+pub mod results;
+pub mod retry;
 pub mod rewrite;
 pub mod search;
 pub mod semantic;
@@ -28,11 +45,206 @@ pub mod youtube;
 #[derive(Debug, Clone)]
 pub struct FsCache {
     root: PathBuf,
+    /// Size budget for bodies plus meta (`None` = unbounded); see [`FsCache::evict`].
+    max_bytes: Option<u64>,
+    /// Running size estimate, seeded by the first `put` and reset by each eviction walk
+    /// (`u64::MAX` = not measured yet).
+    approx_bytes: std::sync::Arc<std::sync::atomic::AtomicU64>,
+    /// Set while a background measure/eviction from [`FsCache::put`] runs (at most one per cache).
+    evicting: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+/// One cached document, as listed by [`FsCache::recent_entries`]. Built from the entry's meta only
+/// (which never holds non-allowlisted headers such as `Set-Cookie`).
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CacheEntryInfo {
+    pub key: String,
+    pub url: String,
+    pub final_url: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub fetched_at_epoch_s: u64,
+    /// Body size in bytes (after content decoding).
+    pub bytes: u64,
+}
+
+impl CacheEntryInfo {
+    fn from_meta(key: &str, meta: &serde_json::Value) -> Option<Self> {
+        let url = meta.get("url")?.as_str()?.to_string();
+        let final_url = meta
+            .get("final_url")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .unwrap_or(&url)
+            .to_string();
+        Some(Self {
+            key: key.to_string(),
+            final_url,
+            url,
+            status: meta.get("status").and_then(|v| v.as_u64()).unwrap_or(0) as u16,
+            content_type: meta
+                .get("content_type")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            fetched_at_epoch_s: meta
+                .get("fetched_at_epoch_s")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0),
+            bytes: meta
+                .get("decoded_bytes")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0),
+        })
+    }
+}
+
+/// Counts from [`FsCache::evict`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CacheEvictReport {
+    /// Entries (meta files) examined.
+    pub scanned: usize,
+    /// Bytes of bodies plus meta before eviction.
+    pub bytes_before: u64,
+    /// Entries removed, oldest `fetched_at_epoch_s` first.
+    pub evicted: usize,
+    pub evicted_bytes: u64,
+    pub bytes_after: u64,
+    /// Over-budget entries kept because they were written during the walk (or are the entry the
+    /// triggering `put` just wrote).
+    pub skipped_recent: usize,
+}
+
+/// Counts from [`FsCache::migrate_all`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CacheMigrateReport {
+    /// Meta files examined (bounded by `max_entries`).
+    pub scanned: usize,
+    /// Entries stored under a legacy v1-only key.
+    pub legacy_found: usize,
+    /// Legacy entries copied into the v2 key space by this run.
+    pub migrated: usize,
+    /// Legacy entries whose v2 twin already existed (e.g. from lazy read-path migration).
+    pub already_migrated: usize,
+    /// Legacy files removed after migration (only when `remove_legacy=true`).
+    pub removed_legacy: usize,
+    /// Entries that can't be re-keyed: unreadable meta, missing `url`, or missing body; or an
+    /// older entry keyed with request headers. Meta never records request headers, so such a key
+    /// can't be rebuilt, and a v1 one can't be told apart from a v2 one.
+    pub skipped: usize,
+    /// Entries already in the v2 key space: their key rebuilds from the meta, or the meta records
+    /// the request knobs (`request_max_bytes`) that only v2 writes store.
+    pub not_legacy: usize,
+    /// True when `max_entries` stopped the walk early.
+    pub truncated: bool,
+}
+
+/// Counts from [`FsCache::export`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CacheExportReport {
+    /// Meta files examined (bounded by `max_entries`).
+    pub scanned: usize,
+    /// Bodies written under the export dir.
+    pub exported: usize,
+    /// Entries whose URL was already exported from another cache key (e.g. a different
+    /// `max_bytes`); the first one in key order wins.
+    pub duplicates: usize,
+    /// Entries that can't be exported: unreadable meta, missing `url`, missing body, or a failed
+    /// write.
+    pub skipped: usize,
+    /// True when `max_entries` stopped the walk early.
+    pub truncated: bool,
+}
+
+/// The `Cache-Control` response directives the cache understands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    max_age: Option<u64>,
+    stale_while_revalidate: Option<u64>,
+}
+
+impl CacheControl {
+    fn from_headers(headers: &BTreeMap<String, String>) -> Self {
+        let mut out = Self::default();
+        for (k, v) in headers {
+            if !k.trim().eq_ignore_ascii_case("cache-control") {
+                continue;
+            }
+            for d in v.split(',') {
+                let (name, arg) = match d.split_once('=') {
+                    Some((n, a)) => (n, Some(a.trim().trim_matches('"'))),
+                    None => (d, None),
+                };
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "no-store" => out.no_store = true,
+                    "no-cache" => out.no_cache = true,
+                    "max-age" => {
+                        if let Some(n) = arg.and_then(|a| a.parse::<u64>().ok()) {
+                            out.max_age = Some(out.max_age.map_or(n, |m| m.min(n)));
+                        }
+                    }
+                    "stale-while-revalidate" => {
+                        if let Some(n) = arg.and_then(|a| a.parse::<u64>().ok()) {
+                            out.stale_while_revalidate = Some(n);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        out
+    }
+}
+
+/// What [`FsCache::lookup`] found for a request.
+#[derive(Debug, Clone)]
+pub enum CacheLookup {
+    Miss,
+    /// Usable as-is (`cache_status` is already [`CacheStatus::Fresh`]).
+    Fresh(FetchResponse),
+    /// Expired. `serve_stale`: still inside its `stale-while-revalidate` window. Otherwise the
+    /// entry is only useful for its validators (`ETag` / `Last-Modified`).
+    Stale {
+        resp: FetchResponse,
+        serve_stale: bool,
+    },
 }
 
 impl FsCache {
     pub fn new(root: PathBuf) -> Self {
-        Self { root }
+        Self {
+            root,
+            max_bytes: None,
+            approx_bytes: std::sync::Arc::new(std::sync::atomic::AtomicU64::new(u64::MAX)),
+            evicting: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+        }
+    }
+
+    /// Cap the cache at `max_bytes` (bodies plus meta). Once a `put` pushes the running total past
+    /// the cap by [`FsCache::EVICT_SLACK_DIVISOR`]ths, a background [`FsCache::evict`] trims it.
+    pub fn with_max_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Eviction starts at `max + max / EVICT_SLACK_DIVISOR` so a full cache isn't re-walked on
+    /// every write.
+    pub const EVICT_SLACK_DIVISOR: u64 = 10;
+
+    /// Opt-in (`WEBPIPE_RESPECT_CACHE_CONTROL=1`): when the caller sets no `ttl_s`, follow the
+    /// server's `Cache-Control` (`no-store` → not cached, `no-cache` → always revalidated,
+    /// `max-age` → TTL, `stale-while-revalidate` → stale entries served while a background refresh
+    /// runs). An explicit `ttl_s` always wins.
+    fn respect_cache_control_from_env() -> bool {
+        matches!(
+            std::env::var("WEBPIPE_RESPECT_CACHE_CONTROL")
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase()
+                .as_str(),
+            "1" | "true" | "yes" | "on"
+        )
     }
 
     fn cache_meta_headers(headers: &BTreeMap<String, String>) -> BTreeMap<String, String> {
@@ -43,8 +255,14 @@ impl FsCache {
         let mut out = BTreeMap::new();
         for (k, v) in headers {
             match k.trim().to_ascii_lowercase().as_str() {
-                "content-type" | "content-length" | "etag" | "last-modified" | "cache-control"
-                | "retry-after" => {
+                "content-type"
+                | "content-length"
+                | "etag"
+                | "last-modified"
+                | "cache-control"
+                | "retry-after"
+                | "content-disposition"
+                | "vary" => {
                     out.insert(k.clone(), v.clone());
                 }
                 _ => {}
@@ -54,10 +272,65 @@ impl FsCache {
     }
 
     fn key_for_fetch(req: &FetchRequest) -> String {
-        Self::key_for_fetch_v2(req)
+        Self::key_for_fetch_v2(req, &[])
+    }
+
+    /// Lowercased, sorted header names from a response's `Vary` (`*` is dropped: it names no
+    /// request header we could key on).
+    fn vary_names(headers: &BTreeMap<String, String>) -> Vec<String> {
+        let mut out: Vec<String> = headers
+            .iter()
+            .filter(|(k, _)| k.trim().eq_ignore_ascii_case("vary"))
+            .flat_map(|(_, v)| v.split(','))
+            .map(|s| s.trim().to_ascii_lowercase())
+            .filter(|s| !s.is_empty() && s != "*")
+            .collect();
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    /// True when `vary` (from [`FsCache::vary_names`]) names a credential header. Keying such a
+    /// response would hash raw cookies/tokens into cache file names, so unless unsafe headers are
+    /// allowed, these responses are neither stored nor served.
+    fn varies_on_credentials(vary: &[String]) -> bool {
+        !Self::allow_unsafe_headers_from_env()
+            && vary.iter().any(|n| {
+                matches!(
+                    n.as_str(),
+                    "authorization" | "cookie" | "proxy-authorization"
+                )
+            })
     }
 
-    fn key_for_fetch_v2(req: &FetchRequest) -> String {
+    /// `vary` as stored in an entry's meta.
+    fn meta_vary(meta: &serde_json::Value) -> Vec<String> {
+        meta.get("vary")
+            .and_then(|v| v.as_array())
+            .map(|a| {
+                a.iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn allow_unsafe_headers_from_env() -> bool {
+        matches!(
+            std::env::var("WEBPIPE_ALLOW_UNSAFE_HEADERS")
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase()
+                .as_str(),
+            "1" | "true" | "yes" | "on"
+        )
+    }
+
+    /// `vary` is the header set a prior response declared in `Vary` (empty on first contact, which
+    /// yields the plain key). Each named header's request value (or its absence) feeds the key.
+    /// Credential headers only get here when unsafe headers are allowed (see
+    /// [`FsCache::varies_on_credentials`]).
+    fn key_for_fetch_v2(req: &FetchRequest, vary: &[String]) -> String {
         // Deterministic key: url + relevant knobs. Keep it stable and readable-ish.
         let mut h = Sha256::new();
         h.update(b"url:");
@@ -68,14 +341,7 @@ impl FsCache {
             None => h.update(b"none"),
         }
         h.update(b"\nheaders:");
-        let allow_unsafe = matches!(
-            std::env::var("WEBPIPE_ALLOW_UNSAFE_HEADERS")
-                .unwrap_or_default()
-                .trim()
-                .to_ascii_lowercase()
-                .as_str(),
-            "1" | "true" | "yes" | "on"
-        );
+        let allow_unsafe = Self::allow_unsafe_headers_from_env();
         for (k, v) in &req.headers {
             // Safety default: do not allow request-secret headers to influence caching keys unless
             // explicitly opted in. This avoids "hashing secrets" and avoids cache fragmentation.
@@ -93,6 +359,34 @@ impl FsCache {
             h.update(v.as_bytes());
             h.update(b"\n");
         }
+        // Plain GETs keep their pre-method keys, so existing entries stay addressable.
+        let method = req.method();
+        if method != "GET" || req.body.is_some() {
+            h.update(b"method:");
+            h.update(method.as_bytes());
+            h.update(b"\nbody_sha256:");
+            if let Some(body) = &req.body {
+                h.update(hex::encode(Sha256::digest(body)).as_bytes());
+            }
+        }
+        if !vary.is_empty() {
+            h.update(b"\nvary:");
+            for name in vary {
+                h.update(name.as_bytes());
+                match req
+                    .headers
+                    .iter()
+                    .find(|(k, _)| k.trim().eq_ignore_ascii_case(name))
+                {
+                    Some((_, v)) => {
+                        h.update(b"=");
+                        h.update(v.trim().as_bytes());
+                    }
+                    None => h.update(b"!"),
+                }
+                h.update(b"\n");
+            }
+        }
         hex::encode(h.finalize())
     }
 
@@ -105,14 +399,7 @@ impl FsCache {
         h.update(b"\nmax_bytes:");
         h.update(req.max_bytes.unwrap_or(0).to_string().as_bytes());
         h.update(b"\nheaders:");
-        let allow_unsafe = matches!(
-            std::env::var("WEBPIPE_ALLOW_UNSAFE_HEADERS")
-                .unwrap_or_default()
-                .trim()
-                .to_ascii_lowercase()
-                .as_str(),
-            "1" | "true" | "yes" | "on"
-        );
+        let allow_unsafe = Self::allow_unsafe_headers_from_env();
         for (k, v) in &req.headers {
             if !allow_unsafe {
                 let kl = k.trim().to_ascii_lowercase();
@@ -138,44 +425,104 @@ impl FsCache {
         (meta, body)
     }
 
-    pub fn get(&self, req: &FetchRequest) -> Result<Option<FetchResponse>> {
-        if !req.cache.read {
+    /// Parsed meta and body for `key`, or `None` unless both files exist.
+    fn read_entry(&self, key: &str) -> Result<Option<(serde_json::Value, Vec<u8>)>> {
+        let (meta_p, body_p) = self.paths(key);
+        if !meta_p.exists() || !body_p.exists() {
             return Ok(None);
         }
-        let key_v2 = Self::key_for_fetch_v2(req);
-        let (meta_p, body_p) = self.paths(&key_v2);
-        let (meta_bytes, body, used_legacy_key) = if meta_p.exists() && body_p.exists() {
-            (
-                fs::read(&meta_p).map_err(|e| Error::Cache(e.to_string()))?,
-                fs::read(&body_p).map_err(|e| Error::Cache(e.to_string()))?,
-                false,
-            )
+        let meta_bytes = fs::read(&meta_p).map_err(|e| Error::Cache(e.to_string()))?;
+        let body = fs::read(&body_p).map_err(|e| Error::Cache(e.to_string()))?;
+        let meta = serde_json::from_slice(&meta_bytes).map_err(|e| Error::Cache(e.to_string()))?;
+        Ok(Some((meta, body)))
+    }
+
+    /// Fresh entries only; see [`FsCache::lookup`] for stale ones.
+    pub fn get(&self, req: &FetchRequest) -> Result<Option<FetchResponse>> {
+        Ok(match self.lookup(req)? {
+            CacheLookup::Fresh(resp) => Some(resp),
+            CacheLookup::Stale { .. } | CacheLookup::Miss => None,
+        })
+    }
+
+    /// Read the entry for `req` and classify its freshness (no network).
+    pub fn lookup(&self, req: &FetchRequest) -> Result<CacheLookup> {
+        if !req.cache_readable() {
+            return Ok(CacheLookup::Miss);
+        }
+        let key_v2 = Self::key_for_fetch_v2(req, &[]);
+        let (mut meta, body, used_legacy_key) = if let Some((meta, body)) =
+            self.read_entry(&key_v2)?
+        {
+            // The plain key holds the first response seen; when that one declared `Vary`, it only
+            // answers requests with matching values, and other variants live under their own keys.
+            let vary = Self::meta_vary(&meta);
+            if Self::varies_on_credentials(&vary) {
+                return Ok(CacheLookup::Miss);
+            }
+            let variant = (!vary.is_empty()).then(|| Self::key_for_fetch_v2(req, &vary));
+            match variant {
+                Some(k) if meta.get("vary_key").and_then(|v| v.as_str()) != Some(k.as_str()) => {
+                    match self.read_entry(&k)? {
+                        Some((meta, body)) => (meta, body, false),
+                        None => return Ok(CacheLookup::Miss),
+                    }
+                }
+                _ => (meta, body, false),
+            }
         } else {
-            let key_legacy = Self::key_for_fetch_legacy_v1(req);
-            let (meta_p2, body_p2) = self.paths(&key_legacy);
-            if !meta_p2.exists() || !body_p2.exists() {
-                return Ok(None);
+            // v1 keys predate methods and bodies: only plain GETs can have one.
+            if req.method() != "GET" || req.body.is_some() {
+                return Ok(CacheLookup::Miss);
+            }
+            match self.read_entry(&Self::key_for_fetch_legacy_v1(req))? {
+                Some((meta, body)) => (meta, body, true),
+                None => return Ok(CacheLookup::Miss),
             }
-            (
-                fs::read(&meta_p2).map_err(|e| Error::Cache(e.to_string()))?,
-                fs::read(&body_p2).map_err(|e| Error::Cache(e.to_string()))?,
-                true,
-            )
         };
 
-        let mut meta: serde_json::Value =
-            serde_json::from_slice(&meta_bytes).map_err(|e| Error::Cache(e.to_string()))?;
         let fetched_at = meta
             .get("fetched_at_epoch_s")
             .and_then(|v| v.as_u64())
             .unwrap_or(0);
+        let now_s = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::from_secs(0))
+            .as_secs();
+        let age_s = now_s.saturating_sub(fetched_at);
+        let mut stale = false;
+        let mut serve_stale = false;
         if let Some(ttl_s) = req.cache.ttl_s {
-            let now_s = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or(Duration::from_secs(0))
-                .as_secs();
-            if now_s.saturating_sub(fetched_at) > ttl_s {
-                return Ok(None);
+            stale = age_s > ttl_s;
+            serve_stale = stale
+                && req
+                    .cache
+                    .stale_while_revalidate_s
+                    .is_some_and(|w| age_s <= ttl_s.saturating_add(w));
+        } else if Self::respect_cache_control_from_env() {
+            let stored: BTreeMap<String, String> = meta
+                .get("headers")
+                .and_then(|v| v.as_object())
+                .map(|h| {
+                    h.iter()
+                        .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let cc = CacheControl::from_headers(&stored);
+            // HTTP freshness: fresh while age < max-age. `no-cache` entries must be revalidated
+            // before every use; `stale-while-revalidate` extends how long a stale entry may be
+            // served as-is.
+            if cc.no_store {
+                return Ok(CacheLookup::Miss);
+            }
+            if cc.no_cache {
+                stale = true;
+            } else if let Some(m) = cc.max_age.filter(|m| age_s >= *m) {
+                stale = true;
+                serve_stale = cc
+                    .stale_while_revalidate
+                    .is_some_and(|w| age_s < m.saturating_add(w));
             }
         }
 
@@ -199,6 +546,11 @@ impl FsCache {
             .get("truncated")
             .and_then(|v| v.as_bool())
             .unwrap_or(false);
+        // Older entries predate wire-size tracking; fall back to the decoded size.
+        let wire_bytes = meta
+            .get("wire_bytes")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(body.len() as u64);
 
         let mut headers = BTreeMap::new();
         if let Some(h) = meta.get_mut("headers").and_then(|v| v.as_object_mut()) {
@@ -216,10 +568,20 @@ impl FsCache {
             content_type,
             headers,
             bytes: body,
+            wire_bytes,
             truncated,
             source: FetchSource::Cache,
+            cache_status: None,
+            body_path: None,
+            revalidating: false,
             timings_ms: BTreeMap::new(),
         };
+        if stale {
+            return Ok(CacheLookup::Stale {
+                resp: out,
+                serve_stale,
+            });
+        }
 
         // Best-effort migration: if we hit via a legacy key and writes are enabled,
         // copy into the v2 key space so future reads are fast and unambiguous.
@@ -228,15 +590,49 @@ impl FsCache {
             let _ = self.put(req, &out);
         }
 
-        Ok(Some(out))
+        Ok(CacheLookup::Fresh(FetchResponse {
+            cache_status: Some(CacheStatus::Fresh),
+            body_path: None,
+            ..out
+        }))
     }
 
     pub fn put(&self, req: &FetchRequest, resp: &FetchResponse) -> Result<()> {
         if !req.cache.write {
             return Ok(());
         }
-        let key = Self::key_for_fetch(req);
+        let base_key = Self::key_for_fetch(req);
+        let vary = Self::vary_names(&resp.headers);
+        if Self::varies_on_credentials(&vary) {
+            return Ok(());
+        }
+        let vary_key = (!vary.is_empty()).then(|| Self::key_for_fetch_v2(req, &vary));
+        // A varying response takes the plain key unless that already holds a different variant
+        // (see `lookup`), so the first request is stored exactly as before.
+        let key = match &vary_key {
+            Some(vk) => {
+                let (base_meta_p, _) = self.paths(&base_key);
+                let held = fs::read(&base_meta_p)
+                    .ok()
+                    .and_then(|b| serde_json::from_slice::<serde_json::Value>(&b).ok())
+                    .and_then(|m| m.get("vary_key")?.as_str().map(str::to_string));
+                match held {
+                    Some(other) if other != *vk => vk.clone(),
+                    _ => base_key,
+                }
+            }
+            None => base_key,
+        };
         let (meta_p, body_p) = self.paths(&key);
+        if req.cache.ttl_s.is_none()
+            && Self::respect_cache_control_from_env()
+            && CacheControl::from_headers(&resp.headers).no_store
+        {
+            // Also drop any copy stored before the server started sending no-store.
+            let _ = fs::remove_file(&meta_p);
+            let _ = fs::remove_file(&body_p);
+            return Ok(());
+        }
         if let Some(parent) = meta_p.parent() {
             fs::create_dir_all(parent).map_err(|e| Error::Cache(e.to_string()))?;
         }
@@ -245,25 +641,405 @@ impl FsCache {
             .unwrap_or(Duration::from_secs(0))
             .as_secs();
 
-        let meta = serde_json::json!({
+        let mut meta = serde_json::json!({
             "schema_version": 1,
             "fetched_at_epoch_s": now_s,
+            // Request knobs that feed the key, so entries can be re-keyed offline later.
+            "request_max_bytes": req.max_bytes,
             "url": resp.url,
             "final_url": resp.final_url,
             "status": resp.status,
             "content_type": resp.content_type,
             "headers": Self::cache_meta_headers(&resp.headers),
             "truncated": resp.truncated,
+            "wire_bytes": resp.wire_bytes,
+            "decoded_bytes": resp.bytes.len(),
         });
+        if let (Some(obj), Some(vk)) = (meta.as_object_mut(), vary_key) {
+            obj.insert("vary".to_string(), serde_json::json!(vary));
+            obj.insert("vary_key".to_string(), serde_json::json!(vk));
+        }
 
+        let meta = serde_json::to_vec(&meta).map_err(|e| Error::Cache(e.to_string()))?;
         fs::write(&body_p, &resp.bytes).map_err(|e| Error::Cache(e.to_string()))?;
-        fs::write(
-            &meta_p,
-            serde_json::to_vec(&meta).map_err(|e| Error::Cache(e.to_string()))?,
-        )
-        .map_err(|e| Error::Cache(e.to_string()))?;
+        fs::write(&meta_p, &meta).map_err(|e| Error::Cache(e.to_string()))?;
+        self.maybe_evict_after_put(&key, (resp.bytes.len() + meta.len()) as u64);
         Ok(())
     }
+
+    /// Count a write against the budget and, past the slack, trim in the background. The walk
+    /// runs off the caller's thread: `put` runs under the cache IO timeout, and a large cache
+    /// takes longer than that to stat. The first write after startup only seeds the running total
+    /// (a `stat`-only [`FsCache::measure`]); the full eviction walk follows only if that is over.
+    fn maybe_evict_after_put(&self, key: &str, written: u64) {
+        use std::sync::atomic::Ordering;
+        let Some(max) = self.max_bytes else {
+            return;
+        };
+        let total = self
+            .approx_bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |b| {
+                (b != u64::MAX).then(|| b.saturating_add(written))
+            })
+            .map(|b| b.saturating_add(written))
+            .unwrap_or(u64::MAX);
+        let limit = max.saturating_add(max / Self::EVICT_SLACK_DIVISOR);
+        let unmeasured = total == u64::MAX;
+        if !unmeasured && total <= limit {
+            return;
+        }
+        if self.evicting.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let cache = self.clone();
+        let key = key.to_string();
+        std::thread::spawn(move || {
+            if !unmeasured || cache.measure() > limit {
+                cache.evict_protecting(Some(&key));
+            }
+            cache.evicting.store(false, Ordering::Release);
+        });
+    }
+
+    /// Total size of the entries (meta plus body) from file sizes alone, stored as the running
+    /// estimate. Much cheaper than [`FsCache::evict`], which also reads every meta.
+    fn measure(&self) -> u64 {
+        let (files, _) = self.meta_files(usize::MAX);
+        let total = files
+            .iter()
+            .map(|(key, meta_p)| {
+                let len = |p: &std::path::Path| fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+                len(meta_p) + len(&self.paths(key).1)
+            })
+            .sum();
+        self.approx_bytes
+            .store(total, std::sync::atomic::Ordering::Relaxed);
+        total
+    }
+
+    /// Delete the oldest entries (by `fetched_at_epoch_s`; unreadable meta counts as oldest) until
+    /// the cache fits `max_bytes`. A no-op without a budget.
+    ///
+    /// Safe next to concurrent writers: entries whose meta was modified after the walk started are
+    /// never removed, and meta goes before body, so a reader sees either a whole entry or a miss.
+    pub fn evict(&self) -> CacheEvictReport {
+        self.evict_protecting(None)
+    }
+
+    fn evict_protecting(&self, keep: Option<&str>) -> CacheEvictReport {
+        let mut rep = CacheEvictReport::default();
+        let Some(max) = self.max_bytes else {
+            return rep;
+        };
+        // Filesystem mtimes can be coarse (1s on some), so "during the walk" starts a bit early.
+        let started = SystemTime::now() - Duration::from_secs(1);
+        let (files, _) = self.meta_files(usize::MAX);
+        // (fetched_at, key, bytes, written during the walk)
+        let mut entries: Vec<(u64, String, u64, bool)> = Vec::with_capacity(files.len());
+        for (key, meta_p) in files {
+            let Ok(md) = fs::metadata(&meta_p) else {
+                continue;
+            };
+            rep.scanned += 1;
+            let body_len = fs::metadata(self.paths(&key).1)
+                .map(|m| m.len())
+                .unwrap_or(0);
+            let recent = md.modified().map(|t| t >= started).unwrap_or(false);
+            let fetched_at = fs::read(&meta_p)
+                .ok()
+                .and_then(|b| serde_json::from_slice::<serde_json::Value>(&b).ok())
+                .and_then(|m| m.get("fetched_at_epoch_s").and_then(|v| v.as_u64()))
+                .unwrap_or(0);
+            entries.push((fetched_at, key, md.len() + body_len, recent));
+        }
+        entries.sort();
+        rep.bytes_before = entries.iter().map(|e| e.2).sum();
+        let mut total = rep.bytes_before;
+        for (_, key, bytes, recent) in entries {
+            if total <= max {
+                break;
+            }
+            if recent || keep == Some(key.as_str()) {
+                rep.skipped_recent += 1;
+                continue;
+            }
+            let (meta_p, body_p) = self.paths(&key);
+            if fs::remove_file(&meta_p).is_err() {
+                // Already gone (another evictor, or a no-store put).
+                continue;
+            }
+            let _ = fs::remove_file(&body_p);
+            rep.evicted += 1;
+            rep.evicted_bytes += bytes;
+            total -= bytes;
+        }
+        rep.bytes_after = total;
+        self.approx_bytes
+            .store(total, std::sync::atomic::Ordering::Relaxed);
+        rep
+    }
+
+    /// Remove one entry: meta first, then body, as in [`FsCache::evict`] — a crash in between
+    /// leaves an orphaned body that reads as a miss, never a meta pointing at a missing body.
+    /// False when there was no meta to remove.
+    fn remove_entry(&self, key: &str) -> bool {
+        let (meta_p, body_p) = self.paths(key);
+        let removed = fs::remove_file(&meta_p).is_ok();
+        let _ = fs::remove_file(&body_p);
+        removed
+    }
+
+    /// Drop the entry for `req`: its v2 key (or, when the URL varies, the variant `req` selects)
+    /// and, for plain GETs, its legacy v1 key. Returns how many entries were removed (0..=2).
+    pub fn invalidate(&self, req: &FetchRequest) -> usize {
+        let base_key = Self::key_for_fetch_v2(req, &[]);
+        let base_meta = fs::read(self.paths(&base_key).0)
+            .ok()
+            .and_then(|b| serde_json::from_slice::<serde_json::Value>(&b).ok());
+        let vary = base_meta.as_ref().map(Self::meta_vary).unwrap_or_default();
+        let key = if vary.is_empty() {
+            base_key
+        } else {
+            let vk = Self::key_for_fetch_v2(req, &vary);
+            let held = base_meta.as_ref().and_then(|m| m.get("vary_key")?.as_str());
+            if held == Some(vk.as_str()) {
+                base_key
+            } else {
+                vk
+            }
+        };
+        let mut keys = vec![key];
+        if req.method() == "GET" && req.body.is_none() {
+            keys.push(Self::key_for_fetch_legacy_v1(req));
+        }
+        keys.dedup();
+        keys.iter().filter(|k| self.remove_entry(k)).count()
+    }
+
+    /// Drop every entry whose stored `url` is exactly `url`, whatever request knobs
+    /// (`max_bytes`, headers, method) keyed it. Returns the count removed.
+    pub fn invalidate_url(&self, url: &str) -> usize {
+        self.invalidate_where(|u| u == url)
+    }
+
+    /// Drop every entry whose stored `url` starts with `prefix` (e.g. `https://example.com/docs/`).
+    /// Returns the count removed.
+    pub fn invalidate_url_prefix(&self, prefix: &str) -> usize {
+        self.invalidate_where(|u| u.starts_with(prefix))
+    }
+
+    fn invalidate_where(&self, matches: impl Fn(&str) -> bool) -> usize {
+        let (files, _) = self.meta_files(usize::MAX);
+        let mut removed = 0;
+        for (key, meta_p) in files {
+            let url = fs::read(&meta_p)
+                .ok()
+                .and_then(|b| serde_json::from_slice::<serde_json::Value>(&b).ok())
+                .and_then(|m| m.get("url").and_then(|v| v.as_str()).map(str::to_string));
+            if url.is_some_and(|u| matches(&u)) && self.remove_entry(&key) {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// The `max_entries` most recently fetched entries, newest first. Entries with unreadable meta
+    /// or no body are skipped.
+    pub fn recent_entries(&self, max_entries: usize) -> Vec<CacheEntryInfo> {
+        let (files, _) = self.meta_files(usize::MAX);
+        let mut out: Vec<CacheEntryInfo> = files
+            .into_iter()
+            .filter(|(key, _)| self.paths(key).1.exists())
+            .filter_map(|(key, meta_p)| {
+                let meta: serde_json::Value =
+                    serde_json::from_slice(&fs::read(meta_p).ok()?).ok()?;
+                CacheEntryInfo::from_meta(&key, &meta)
+            })
+            .collect();
+        out.sort_by(|a, b| {
+            b.fetched_at_epoch_s
+                .cmp(&a.fetched_at_epoch_s)
+                .then_with(|| a.key.cmp(&b.key))
+        });
+        out.truncate(max_entries);
+        out
+    }
+
+    /// The entry stored under `key` (as listed by [`FsCache::recent_entries`]) and its body,
+    /// regardless of freshness. `None` for a missing entry or a malformed key.
+    pub fn read_by_key(&self, key: &str) -> Result<Option<(CacheEntryInfo, Vec<u8>)>> {
+        // Keys name files: only accept the exact shape we write, never a path.
+        if key.len() != 64 || !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Ok(None);
+        }
+        let key = key.to_ascii_lowercase();
+        Ok(self
+            .read_entry(&key)?
+            .and_then(|(meta, body)| Some((CacheEntryInfo::from_meta(&key, &meta)?, body))))
+    }
+
+    /// Sorted `<key>.json` meta paths under the `xx/yy/` cache layout, at most `max_entries`.
+    fn meta_files(&self, max_entries: usize) -> (Vec<(String, PathBuf)>, bool) {
+        fn sorted_dir(p: &std::path::Path) -> Vec<PathBuf> {
+            let mut v: Vec<PathBuf> = fs::read_dir(p)
+                .map(|rd| rd.flatten().map(|e| e.path()).collect())
+                .unwrap_or_default();
+            v.sort();
+            v
+        }
+        let mut out = Vec::new();
+        for p1 in sorted_dir(&self.root).into_iter().filter(|p| p.is_dir()) {
+            for p2 in sorted_dir(&p1).into_iter().filter(|p| p.is_dir()) {
+                for p in sorted_dir(&p2) {
+                    if p.extension().and_then(|x| x.to_str()) != Some("json") {
+                        continue;
+                    }
+                    let Some(key) = p.file_stem().and_then(|x| x.to_str()) else {
+                        continue;
+                    };
+                    if key.len() != 64 || !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+                        continue;
+                    }
+                    if out.len() >= max_entries {
+                        return (out, true);
+                    }
+                    out.push((key.to_string(), p));
+                }
+            }
+        }
+        (out, false)
+    }
+
+    /// Bulk version of the read-path migration in [`FsCache::get`]: walk the cache and copy every
+    /// legacy v1-only entry into the v2 key space.
+    ///
+    /// v1 and v2 keys only differ for `max_bytes=None` (v1 hashed it as `0`), so an entry is
+    /// legacy iff its file name equals the v1 key rebuilt from its stored `url` (no headers).
+    /// The v2 target uses the stored `request_max_bytes` when present, else `None`, matching
+    /// what a default read would have migrated to. Older entries that match neither key were
+    /// keyed with request headers and are counted as `skipped`.
+    pub fn migrate_all(&self, max_entries: usize, remove_legacy: bool) -> CacheMigrateReport {
+        let mut rep = CacheMigrateReport::default();
+        let (files, truncated) = self.meta_files(max_entries);
+        rep.truncated = truncated;
+        for (key, meta_p) in files {
+            rep.scanned += 1;
+            let meta: Option<serde_json::Value> = fs::read(&meta_p)
+                .ok()
+                .and_then(|b| serde_json::from_slice(&b).ok());
+            let Some(url) = meta
+                .as_ref()
+                .and_then(|m| m.get("url"))
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+            else {
+                rep.skipped += 1;
+                continue;
+            };
+            let max_bytes = meta
+                .as_ref()
+                .and_then(|m| m.get("request_max_bytes"))
+                .and_then(|v| v.as_u64());
+            let req = FetchRequest {
+                url: url.to_string(),
+                timeout_ms: None,
+                max_bytes,
+                headers: BTreeMap::new(),
+                method: None,
+                body: None,
+                cache: webpipe_core::FetchCachePolicy {
+                    read: true,
+                    write: true,
+                    ttl_s: None,
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
+                },
+            };
+            let key_v2 = Self::key_for_fetch_v2(&req, &[]);
+            let v2_meta = meta
+                .as_ref()
+                .is_some_and(|m| m.get("request_max_bytes").is_some());
+            if key_v2 == key || v2_meta {
+                rep.not_legacy += 1;
+                continue;
+            }
+            if Self::key_for_fetch_legacy_v1(&req) != key {
+                rep.skipped += 1;
+                continue;
+            }
+            rep.legacy_found += 1;
+            let (_, body_p) = self.paths(&key);
+            if !body_p.exists() {
+                rep.skipped += 1;
+                continue;
+            }
+            let (meta2_p, body2_p) = self.paths(&key_v2);
+            if meta2_p.exists() && body2_p.exists() {
+                rep.already_migrated += 1;
+            } else {
+                let copied = meta2_p
+                    .parent()
+                    .map(fs::create_dir_all)
+                    .unwrap_or(Ok(()))
+                    .and_then(|_| fs::copy(&body_p, &body2_p))
+                    .and_then(|_| fs::copy(&meta_p, &meta2_p));
+                if copied.is_err() {
+                    let _ = fs::remove_file(&body2_p);
+                    rep.skipped += 1;
+                    continue;
+                }
+                rep.migrated += 1;
+            }
+            if remove_legacy && fs::remove_file(&meta_p).is_ok() {
+                let _ = fs::remove_file(&body_p);
+                rep.removed_legacy += 1;
+            }
+        }
+        rep
+    }
+
+    /// Copy every cached body to `out_dir/<fs_path::sanitize_for_fs(url)>`, for browsing or
+    /// archiving the cache outside webpipe. Existing files at those paths are overwritten.
+    pub fn export(&self, out_dir: &std::path::Path, max_entries: usize) -> CacheExportReport {
+        let mut rep = CacheExportReport::default();
+        let (files, truncated) = self.meta_files(max_entries);
+        rep.truncated = truncated;
+        let mut seen = std::collections::BTreeSet::new();
+        for (key, meta_p) in files {
+            rep.scanned += 1;
+            let meta: Option<serde_json::Value> = fs::read(&meta_p)
+                .ok()
+                .and_then(|b| serde_json::from_slice(&b).ok());
+            let Some(url) = meta
+                .as_ref()
+                .and_then(|m| m.get("url"))
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+            else {
+                rep.skipped += 1;
+                continue;
+            };
+            let rel = fs_path::sanitize_for_fs(url);
+            if seen.contains(&rel) {
+                rep.duplicates += 1;
+                continue;
+            }
+            let (_, body_p) = self.paths(&key);
+            let dest = out_dir.join(&rel);
+            let copied = dest
+                .parent()
+                .map(fs::create_dir_all)
+                .unwrap_or(Ok(()))
+                .and_then(|_| fs::copy(&body_p, &dest));
+            if copied.is_err() {
+                rep.skipped += 1;
+                continue;
+            }
+            seen.insert(rel);
+            rep.exported += 1;
+        }
+        rep
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -271,12 +1047,55 @@ pub struct LocalFetcher {
     client: reqwest::Client,
     cache: Option<FsCache>,
     rate_limiter: Option<std::sync::Arc<RateLimiter>>,
+    host_limiter: Option<std::sync::Arc<HostLimiter>>,
     cache_io_disabled: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    user_agents: std::sync::Arc<Vec<String>>,
+    user_agent_cursor: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    default_headers: std::sync::Arc<BTreeMap<String, String>>,
+    /// Per-[`CacheStatus`] counters (indexed like `CacheStatus::ALL`).
+    cache_status_counts: std::sync::Arc<[std::sync::atomic::AtomicU64; 4]>,
+    inflight: std::sync::Arc<Inflight>,
+    /// Cache keys with a stale-while-revalidate refresh in flight.
+    revalidating: std::sync::Arc<Revalidating>,
+    retry: std::sync::Arc<retry::RetryPolicy>,
+    /// Skip URLs their host's robots.txt disallows (see [`LocalFetcher::with_robots`]).
+    respect_robots: bool,
+    robots: std::sync::Arc<RobotsCache>,
+}
+
+const DEFAULT_USER_AGENT: &str = "webpipe-local/0.1";
+
+type SharedFetch =
+    futures_util::future::Shared<futures_util::future::BoxFuture<'static, Result<FetchResponse>>>;
+
+/// In-flight fetches by [`LocalFetcher::flight_key`]: concurrent identical requests await the
+/// first one's fetch instead of each hitting the network (and each writing the cache).
+#[derive(Default)]
+struct Inflight(std::sync::Mutex<std::collections::HashMap<String, SharedFetch>>);
+
+type Revalidating = std::sync::Mutex<std::collections::HashSet<String>>;
+
+/// Parsed robots.txt per origin, with when it was read (`None` rules: no usable robots.txt).
+type RobotsCache = std::sync::Mutex<
+    std::collections::HashMap<String, (std::time::Instant, Option<crawl::RobotsRules>)>,
+>;
+
+/// Product token matched against robots.txt `User-agent` groups.
+const ROBOTS_USER_AGENT: &str = "webpipe";
+
+impl std::fmt::Debug for Inflight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let n = self.0.lock().map(|m| m.len()).unwrap_or(0);
+        f.debug_tuple("Inflight").field(&n).finish()
+    }
 }
 
 #[derive(Debug)]
 struct RateLimiter {
     interval: Duration,
+    /// Requests that may go out back-to-back after an idle spell (1 = strict spacing).
+    burst: u32,
+    /// When the bucket would next be full (GCRA "theoretical arrival time").
     next_ok: tokio::sync::Mutex<std::time::Instant>,
 }
 
@@ -316,33 +1135,87 @@ impl RateLimiter {
         let nanos = per.as_nanos() / (n as u128);
         let nanos = nanos.max(1);
         let interval = Duration::from_nanos(nanos as u64);
-        Some(Self {
+        Some(Self::new(interval, 1))
+    }
+
+    /// Token bucket refilling `rps` tokens per second, holding up to `ceil(rps)` of them.
+    fn per_second(rps: f64) -> Option<Self> {
+        if !rps.is_finite() || rps <= 0.0 {
+            return None;
+        }
+        let interval = Duration::from_secs_f64(1.0 / rps).max(Duration::from_nanos(1));
+        Some(Self::new(interval, rps.ceil().min(u32::MAX as f64) as u32))
+    }
+
+    fn new(interval: Duration, burst: u32) -> Self {
+        Self {
             interval,
+            burst: burst.max(1),
             next_ok: tokio::sync::Mutex::new(std::time::Instant::now()),
-        })
+        }
     }
 
     async fn wait(&self) {
-        // Deterministic “spacing” limiter (good enough for politeness / fewer bans).
-        // Not a precise token bucket; avoids additional deps.
+        // Token bucket in GCRA form (one timestamp, no refill task); with `burst = 1` this is
+        // plain spacing. Good enough for politeness / fewer bans; avoids additional deps.
         let mut guard = self.next_ok.lock().await;
         let now = std::time::Instant::now();
-        if *guard > now {
-            tokio::time::sleep(*guard - now).await;
+        let slack = self.interval * (self.burst - 1);
+        let tat = (*guard).max(now);
+        if tat > now + slack {
+            tokio::time::sleep(tat - slack - now).await;
         }
-        *guard = std::time::Instant::now() + self.interval;
+        *guard = tat + self.interval;
     }
 }
 
-fn parse_simple_duration(s: &str) -> Option<Duration> {
-    // Accept: "<n>ms", "<n>s", "<n>m", "<n>h" (case-insensitive), or raw seconds "<n>".
-    let ss = s.trim().to_ascii_lowercase();
-    if ss.is_empty() {
-        return None;
+/// Caps concurrent network fetches per host (see [`LocalFetcher::with_limits`]).
+#[derive(Debug)]
+struct HostLimiter {
+    max_in_flight: usize,
+    hosts:
+        std::sync::Mutex<std::collections::HashMap<String, std::sync::Arc<tokio::sync::Semaphore>>>,
+}
+
+impl HostLimiter {
+    fn new(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight: max_in_flight.max(1),
+            hosts: Default::default(),
+        }
     }
-    let (num, unit) = if ss.ends_with("ms") {
-        (&ss[..ss.len() - 2], "ms")
-    } else if ss.ends_with('s') {
+
+    fn from_env() -> Option<Self> {
+        std::env::var("WEBPIPE_MAX_IN_FLIGHT_PER_HOST")
+            .ok()
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .map(Self::new)
+    }
+
+    async fn acquire(&self, host: &str) -> tokio::sync::OwnedSemaphorePermit {
+        let sem = self
+            .hosts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(host.to_ascii_lowercase())
+            .or_insert_with(|| std::sync::Arc::new(tokio::sync::Semaphore::new(self.max_in_flight)))
+            .clone();
+        sem.acquire_owned()
+            .await
+            .expect("host semaphores are never closed")
+    }
+}
+
+fn parse_simple_duration(s: &str) -> Option<Duration> {
+    // Accept: "<n>ms", "<n>s", "<n>m", "<n>h" (case-insensitive), or raw seconds "<n>".
+    let ss = s.trim().to_ascii_lowercase();
+    if ss.is_empty() {
+        return None;
+    }
+    let (num, unit) = if ss.ends_with("ms") {
+        (&ss[..ss.len() - 2], "ms")
+    } else if ss.ends_with('s') {
         (&ss[..ss.len() - 1], "s")
     } else if ss.ends_with('m') {
         (&ss[..ss.len() - 1], "m")
@@ -374,6 +1247,14 @@ impl LocalFetcher {
             .clamp(0, 30_000)
     }
 
+    /// Fetch cache size cap (`WEBPIPE_CACHE_MAX_BYTES`; unset or 0 = unbounded).
+    fn cache_max_bytes_from_env() -> Option<u64> {
+        std::env::var("WEBPIPE_CACHE_MAX_BYTES")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .filter(|&n| n > 0)
+    }
+
     fn privacy_mode_from_env() -> String {
         // Values: "normal" (default), "offline", "anonymous".
         std::env::var("WEBPIPE_PRIVACY_MODE")
@@ -382,6 +1263,51 @@ impl LocalFetcher {
             .to_ascii_lowercase()
     }
 
+    /// Content types that are open-ended by design (server push, log tails, live feeds).
+    fn is_streaming_content_type(content_type: Option<&str>) -> bool {
+        let ct = content_type
+            .unwrap_or("")
+            .split(';')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase();
+        matches!(
+            ct.as_str(),
+            "text/event-stream"
+                | "application/x-ndjson"
+                | "application/stream+json"
+                | "multipart/x-mixed-replace"
+        )
+    }
+
+    /// Min cached body size for `If-Range` revalidation (`WEBPIPE_CACHE_IF_RANGE_MIN_BYTES`;
+    /// default 0 = off).
+    fn if_range_min_bytes_from_env() -> usize {
+        std::env::var("WEBPIPE_CACHE_IF_RANGE_MIN_BYTES")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .unwrap_or(0)
+    }
+
+    /// Caps for open-ended streaming bodies (see [`Self::is_streaming_content_type`]):
+    /// `(max_bytes, max_ms)`, where 0 disables a cap.
+    ///
+    /// Other bodies are never capped this way, even without a `Content-Length`: plenty of
+    /// ordinary pages and downloads are sent chunked and are merely slow.
+    fn streaming_caps_from_env() -> (usize, u64) {
+        let env_u64 = |k: &str, default: u64| {
+            std::env::var(k)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        (
+            env_u64("WEBPIPE_STREAMING_MAX_BYTES", 256 * 1024) as usize,
+            env_u64("WEBPIPE_STREAMING_MAX_MS", 5_000),
+        )
+    }
+
     fn anon_proxy_from_env() -> Option<String> {
         // Prefer an explicit webpipe knob; fall back to standard proxy envs.
         // (Do not treat this as secret; but callers may still prefer not to log it.)
@@ -402,19 +1328,244 @@ impl LocalFetcher {
         None
     }
 
+    fn user_agents_from_env() -> Vec<String> {
+        // WEBPIPE_USER_AGENTS: newline- or '|'-separated pool. Empty/unset => the single default UA.
+        let pool: Vec<String> = std::env::var("WEBPIPE_USER_AGENTS")
+            .unwrap_or_default()
+            .split(['\n', '|'])
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if pool.is_empty() {
+            vec![DEFAULT_USER_AGENT.to_string()]
+        } else {
+            pool
+        }
+    }
+
+    fn default_headers_from_env() -> BTreeMap<String, String> {
+        // WEBPIPE_DEFAULT_HEADERS: JSON object of header name -> string value. Invalid JSON or
+        // non-string values are ignored. Secrets never come from here, even with
+        // WEBPIPE_ALLOW_UNSAFE_HEADERS (they'd go to every host and into every cache key).
+        let raw = std::env::var("WEBPIPE_DEFAULT_HEADERS").unwrap_or_default();
+        let Ok(serde_json::Value::Object(m)) = serde_json::from_str(raw.trim()) else {
+            return BTreeMap::new();
+        };
+        m.into_iter()
+            .filter_map(|(k, v)| {
+                let k = k.trim().to_ascii_lowercase();
+                let v = v.as_str()?.trim().to_string();
+                let name = reqwest::header::HeaderName::from_bytes(k.as_bytes()).ok()?;
+                (!Self::is_sensitive_request_header(&name)).then_some((k, v))
+            })
+            .collect()
+    }
+
+    /// `req` with the `WEBPIPE_DEFAULT_HEADERS` merged in (per-request headers win, compared
+    /// case-insensitively). Applied before the cache lookup so defaults are part of the key.
+    fn with_default_headers<'a>(
+        &self,
+        req: &'a FetchRequest,
+    ) -> std::borrow::Cow<'a, FetchRequest> {
+        let missing: Vec<_> = self
+            .default_headers
+            .iter()
+            .filter(|(k, _)| !req.headers.keys().any(|h| h.eq_ignore_ascii_case(k)))
+            .collect();
+        if missing.is_empty() {
+            return std::borrow::Cow::Borrowed(req);
+        }
+        let mut out = req.clone();
+        for (k, v) in missing {
+            out.headers.insert(k.clone(), v.clone());
+        }
+        std::borrow::Cow::Owned(out)
+    }
+
+    /// UA for this request from the `WEBPIPE_USER_AGENTS` pool.
+    ///
+    /// With caching in play the pick is keyed by URL hash, so a given URL always goes out with the
+    /// same UA (and therefore gets the same content back). Without caching we round-robin.
+    /// The UA is never part of the cache key.
+    fn user_agent_for(&self, req: &FetchRequest) -> &str {
+        let n = self.user_agents.len();
+        if n <= 1 {
+            return self
+                .user_agents
+                .first()
+                .map(|s| s.as_str())
+                .unwrap_or(DEFAULT_USER_AGENT);
+        }
+        let caching = self.cache.is_some() && (req.cache.read || req.cache.write);
+        let i = if caching {
+            let d = Sha256::digest(req.url.as_bytes());
+            let mut b = [0u8; 8];
+            b.copy_from_slice(&d[..8]);
+            (u64::from_le_bytes(b) % n as u64) as usize
+        } else {
+            self.user_agent_cursor
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+                % n
+        };
+        &self.user_agents[i]
+    }
+
     fn is_localhost_host(host: &str) -> bool {
         let h = host.trim().to_ascii_lowercase();
         h == "localhost" || h == "127.0.0.1" || h == "::1" || h.ends_with(".localhost")
     }
 
+    /// `WEBPIPE_RESPECT_ROBOTS`, when set, overrides the constructor's choice.
+    fn respect_robots_from_env() -> Option<bool> {
+        let v = std::env::var("WEBPIPE_RESPECT_ROBOTS").ok()?;
+        match v.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Some(true),
+            "0" | "false" | "no" | "off" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// How long a host's robots.txt is trusted (`WEBPIPE_ROBOTS_TTL_S`, default 1 day).
+    fn robots_ttl_s_from_env() -> u64 {
+        std::env::var("WEBPIPE_ROBOTS_TTL_S")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(86_400)
+    }
+
+    fn allow_file_urls_from_env() -> bool {
+        // Safety default: `file://` URLs are refused unless explicitly enabled (local corpora/tests).
+        matches!(
+            std::env::var("WEBPIPE_ALLOW_FILE_URLS")
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase()
+                .as_str(),
+            "1" | "true" | "yes" | "on"
+        )
+    }
+
+    fn file_url_root_from_env() -> Result<PathBuf> {
+        // WEBPIPE_FILE_URL_ROOT bounds which files may be read; default: current working directory.
+        let root = std::env::var("WEBPIPE_FILE_URL_ROOT")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .map(PathBuf::from)
+            .map(Ok)
+            .unwrap_or_else(std::env::current_dir)
+            .map_err(|e| Error::Fetch(format!("file url root: {e}")))?;
+        root.canonicalize()
+            .map_err(|e| Error::NotConfigured(format!("WEBPIPE_FILE_URL_ROOT is not usable: {e}")))
+    }
+
+    /// Content-type for a local file, inferred from its extension.
+    fn content_type_for_path(path: &std::path::Path) -> &'static str {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match ext.as_str() {
+            "html" | "htm" | "xhtml" => "text/html",
+            "pdf" => "application/pdf",
+            "md" | "markdown" => "text/markdown",
+            "txt" | "text" | "log" => "text/plain",
+            "json" => "application/json",
+            "xml" => "application/xml",
+            "csv" => "text/csv",
+            _ => "application/octet-stream",
+        }
+    }
+
+    /// Read a `file://` URL into a `FetchResponse` (status 200, `source: Network`).
+    ///
+    /// Bypasses the cache: local files are already local, and caching them would hide edits.
+    async fn fetch_file_url(&self, req: &FetchRequest, url: &url::Url) -> Result<FetchResponse> {
+        if !Self::allow_file_urls_from_env() {
+            return Err(Error::NotSupported(
+                "file:// URLs are disabled (set WEBPIPE_ALLOW_FILE_URLS=1)".to_string(),
+            ));
+        }
+        let path = url
+            .to_file_path()
+            .map_err(|_| Error::InvalidUrl("file:// URL is not a local path".to_string()))?;
+        let root = Self::file_url_root_from_env()?;
+        let max_bytes = req.max_bytes.unwrap_or(u64::MAX);
+        let t0 = std::time::Instant::now();
+        let (path, mut bytes, len) = tokio::task::spawn_blocking(move || -> Result<_> {
+            // Canonicalize before the root check so `..` and symlinks cannot escape it.
+            let path = path
+                .canonicalize()
+                .map_err(|e| Error::Fetch(format!("file read failed: {e}")))?;
+            if !path.starts_with(&root) {
+                return Err(Error::NotSupported(
+                    "file:// path is outside the allowed root (WEBPIPE_FILE_URL_ROOT)".to_string(),
+                ));
+            }
+            if !path.is_file() {
+                return Err(Error::Fetch(
+                    "file:// path is not a regular file".to_string(),
+                ));
+            }
+            use std::io::Read;
+            let f = std::fs::File::open(&path)
+                .map_err(|e| Error::Fetch(format!("file read failed: {e}")))?;
+            let len = f.metadata().map(|m| m.len()).unwrap_or(0);
+            let mut bytes = Vec::new();
+            f.take(max_bytes.saturating_add(1))
+                .read_to_end(&mut bytes)
+                .map_err(|e| Error::Fetch(format!("file read failed: {e}")))?;
+            Ok((path, bytes, len))
+        })
+        .await
+        .map_err(|e| Error::Fetch(format!("file read join failed: {e}")))??;
+
+        let truncated = bytes.len() as u64 > max_bytes;
+        if truncated {
+            bytes.truncate(max_bytes as usize);
+        }
+        let mut timings_ms = BTreeMap::new();
+        timings_ms.insert("file_read".to_string(), t0.elapsed().as_millis());
+        let mut headers = BTreeMap::new();
+        headers.insert("content-length".to_string(), len.to_string());
+        Ok(FetchResponse {
+            url: req.url.clone(),
+            final_url: url::Url::from_file_path(&path)
+                .map(|u| u.to_string())
+                .unwrap_or_else(|_| req.url.clone()),
+            status: 200,
+            content_type: Some(Self::content_type_for_path(&path).to_string()),
+            headers,
+            wire_bytes: bytes.len() as u64,
+            bytes,
+            truncated,
+            source: FetchSource::Network,
+            cache_status: None,
+            body_path: None,
+            revalidating: false,
+            timings_ms,
+        })
+    }
+
     pub fn new(cache_dir: Option<PathBuf>) -> Result<Self> {
+        Self::with_robots(cache_dir, false)
+    }
+
+    /// Like [`LocalFetcher::new`], choosing whether to honor robots.txt (`WEBPIPE_RESPECT_ROBOTS`
+    /// overrides `respect`). Disallowed URLs fail with [`Error::NotSupported`] naming the rule.
+    pub fn with_robots(cache_dir: Option<PathBuf>, respect: bool) -> Result<Self> {
         let mut b = reqwest::Client::builder()
-            .user_agent("webpipe-local/0.1")
+            .user_agent(DEFAULT_USER_AGENT)
             .redirect(reqwest::redirect::Policy::limited(10))
             // Safety defaults: avoid “hang forever” on DNS/TLS/body stalls.
             // Per-request timeouts (FetchRequest.timeout_ms) can still override this.
             .connect_timeout(Duration::from_secs(10))
-            .timeout(Duration::from_secs(30));
+            .timeout(Duration::from_secs(30))
+            // Bodies are decoded in `fetch` (see `content_encoding`) so we can report wire size.
+            .no_gzip()
+            .no_brotli()
+            .no_deflate();
 
         // Anonymous mode: route outbound traffic via explicit proxy if provided.
         // (If no proxy is set, the higher-level MCP layer is expected to block outbound
@@ -426,16 +1577,195 @@ impl LocalFetcher {
         }
 
         let client = b.build().map_err(|e| Error::Fetch(e.to_string()))?;
-        let cache = cache_dir.map(FsCache::new);
+        let cache_max_bytes = Self::cache_max_bytes_from_env();
+        let cache = cache_dir.map(|d| FsCache::new(d).with_max_bytes(cache_max_bytes));
         let rate_limiter = RateLimiter::from_env().map(std::sync::Arc::new);
         Ok(Self {
             client,
             cache,
             rate_limiter,
+            host_limiter: HostLimiter::from_env().map(std::sync::Arc::new),
             cache_io_disabled: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            user_agents: std::sync::Arc::new(Self::user_agents_from_env()),
+            user_agent_cursor: std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            default_headers: std::sync::Arc::new(Self::default_headers_from_env()),
+            cache_status_counts: std::sync::Arc::new(Default::default()),
+            inflight: Default::default(),
+            revalidating: Default::default(),
+            retry: std::sync::Arc::new(retry::RetryPolicy::from_env()),
+            respect_robots: Self::respect_robots_from_env().unwrap_or(respect),
+            robots: Default::default(),
+        })
+    }
+
+    /// Throttle network fetches: at most `max_in_flight_per_host` concurrent requests to one host
+    /// (`WEBPIPE_MAX_IN_FLIGHT_PER_HOST`), and a token bucket of `global_rps` requests per second
+    /// across all non-localhost hosts (`WEBPIPE_RATE_LIMIT`). `None` lifts a limit. Cache hits
+    /// never wait.
+    pub fn with_limits(
+        mut self,
+        max_in_flight_per_host: Option<usize>,
+        global_rps: Option<f64>,
+    ) -> Self {
+        self.host_limiter = max_in_flight_per_host
+            .filter(|&n| n > 0)
+            .map(|n| std::sync::Arc::new(HostLimiter::new(n)));
+        self.rate_limiter = global_rps
+            .and_then(RateLimiter::per_second)
+            .map(std::sync::Arc::new);
+        self
+    }
+
+    /// Replace the retry policy (default: [`retry::RetryPolicy::from_env`]).
+    pub fn with_retry_policy(mut self, policy: retry::RetryPolicy) -> Self {
+        self.retry = std::sync::Arc::new(policy);
+        self
+    }
+
+    /// How many fetches ended with each [`CacheStatus`] since start (or the last reset).
+    pub fn cache_status_counts(&self) -> BTreeMap<&'static str, u64> {
+        CacheStatus::ALL
+            .iter()
+            .zip(self.cache_status_counts.iter())
+            .map(|(s, n)| (s.as_str(), n.load(std::sync::atomic::Ordering::Relaxed)))
+            .collect()
+    }
+
+    pub fn reset_cache_status_counts(&self) {
+        for n in self.cache_status_counts.iter() {
+            n.store(0, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    fn record_cache_status(&self, resp: FetchResponse) -> FetchResponse {
+        if let Some(st) = resp.cache_status {
+            let i = CacheStatus::ALL.iter().position(|s| *s == st).unwrap_or(0);
+            self.cache_status_counts[i].fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+        resp
+    }
+
+    /// Conditional-request headers from a stale entry's validators, unless the caller set their
+    /// own.
+    fn apply_validators(
+        rb: reqwest::RequestBuilder,
+        stale: &FetchResponse,
+        req_headers: &BTreeMap<String, String>,
+    ) -> reqwest::RequestBuilder {
+        let get = |h: &BTreeMap<String, String>, name: &str| {
+            h.iter()
+                .find(|(k, _)| k.trim().eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.clone())
+        };
+        let mut rb = rb;
+        for (validator, conditional) in [
+            ("etag", reqwest::header::IF_NONE_MATCH),
+            ("last-modified", reqwest::header::IF_MODIFIED_SINCE),
+        ] {
+            if get(req_headers, conditional.as_str()).is_some() {
+                continue;
+            }
+            if let Some(v) = get(&stale.headers, validator) {
+                rb = rb.header(conditional, v);
+            }
+        }
+        rb
+    }
+
+    /// `(cached length, ETag)` for an `If-Range` revalidation of `stale`: only when
+    /// `WEBPIPE_CACHE_IF_RANGE_MIN_BYTES` is set, the cached body is complete and at least that
+    /// large, it has a strong ETag, and the caller set no range/encoding headers of their own.
+    fn if_range_offset(stale: &FetchResponse, req: &FetchRequest) -> Option<(usize, String)> {
+        let min = Self::if_range_min_bytes_from_env();
+        if min == 0 || stale.truncated || stale.bytes.len() < min {
+            return None;
+        }
+        if req.headers.keys().any(|k| {
+            let k = k.trim();
+            k.eq_ignore_ascii_case("range")
+                || k.eq_ignore_ascii_case("if-range")
+                || k.eq_ignore_ascii_case("accept-encoding")
+        }) {
+            return None;
+        }
+        let etag = stale
+            .headers
+            .iter()
+            .find(|(k, _)| k.trim().eq_ignore_ascii_case("etag"))
+            .map(|(_, v)| v.trim().to_string())?;
+        (!etag.starts_with("W/") && !etag.is_empty()).then_some((stale.bytes.len(), etag))
+    }
+
+    /// Whether a 206/416 answer to an `If-Range` request is for the cached representation
+    /// (same ETag) and, for 206, starts right after the cached bytes.
+    fn if_range_extends(
+        headers: &reqwest::header::HeaderMap,
+        status: u16,
+        offset: usize,
+        etag: &str,
+    ) -> bool {
+        let header = |name: reqwest::header::HeaderName| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.trim().to_string())
+        };
+        if header(reqwest::header::ETAG).as_deref() != Some(etag) {
+            return false;
+        }
+        if status == 416 {
+            return true;
+        }
+        // `Content-Range: bytes <start>-<end>/<total>`
+        let Some(cr) = header(reqwest::header::CONTENT_RANGE) else {
+            return false;
+        };
+        let Some((start, rest)) = cr.strip_prefix("bytes ").and_then(|r| r.split_once('-')) else {
+            return false;
+        };
+        let Some((end, total)) = rest.split_once('/') else {
+            return false;
+        };
+        let (Ok(start), Ok(end)) = (start.trim().parse::<usize>(), end.trim().parse::<usize>())
+        else {
+            return false;
+        };
+        let total_ok =
+            total.trim() == "*" || total.trim().parse::<usize>().is_ok_and(|t| t == end + 1);
+        start == offset && end >= start && total_ok
+    }
+
+    /// Whether a stale entry can be revalidated with a conditional request.
+    fn has_validators(resp: &FetchResponse) -> bool {
+        resp.headers.keys().any(|k| {
+            k.trim().eq_ignore_ascii_case("etag") || k.trim().eq_ignore_ascii_case("last-modified")
         })
     }
 
+    /// Fold a revalidation answer's validators and freshness (a 304, or a 206 extending the body)
+    /// into a cached entry's headers, replacing the stored ones.
+    fn merge_validators(cached: &mut BTreeMap<String, String>, fresh: &reqwest::header::HeaderMap) {
+        for (k, v) in fresh {
+            let k = k.as_str();
+            if !matches!(k, "cache-control" | "etag" | "last-modified") {
+                continue;
+            }
+            if let Ok(v) = v.to_str() {
+                cached.retain(|h, _| !h.eq_ignore_ascii_case(k));
+                cached.insert(k.to_string(), v.to_string());
+            }
+        }
+    }
+
+    /// reqwest's `Display` does not say "timeout"; spell it out so callers can classify failures.
+    fn fetch_error(e: reqwest::Error) -> Error {
+        if e.is_timeout() {
+            Error::Fetch(format!("request timed out: {e}"))
+        } else {
+            Error::Fetch(e.to_string())
+        }
+    }
+
     fn allow_unsafe_request_headers() -> bool {
         // Safety default: do not forward secrets (Authorization/Cookie) to arbitrary URLs.
         // Opt-in escape hatch for debugging / private controlled endpoints only.
@@ -525,53 +1855,32 @@ impl LocalFetcher {
         let Some(cache) = self.cache.clone() else {
             return Ok(None);
         };
-        cache.get(req)
+        cache.get(&self.with_default_headers(req))
     }
 
-    fn apply_headers(
+    /// Write `out` to the cache, bounded by the cache I/O timeout (a timeout disables cache I/O
+    /// for the rest of the process, like reads).
+    async fn cache_put_bounded(
         &self,
-        mut rb: reqwest::RequestBuilder,
-        headers: &BTreeMap<String, String>,
-        url: &url::Url,
-    ) -> reqwest::RequestBuilder {
-        let allow_unsafe = Self::allow_unsafe_request_headers();
-        for (k, v) in headers {
-            if let (Ok(name), Ok(value)) = (
-                reqwest::header::HeaderName::from_bytes(k.as_bytes()),
-                reqwest::header::HeaderValue::from_str(v),
-            ) {
-                if !allow_unsafe
-                    && Self::is_sensitive_request_header(&name)
-                    && !Self::allow_sensitive_header_for_url(url)
-                {
-                    continue;
-                }
-                rb = rb.header(name, value);
-            }
-        }
-        rb
-    }
-}
-
-#[async_trait::async_trait]
-impl FetchBackend for LocalFetcher {
-    async fn fetch(&self, req: &FetchRequest) -> Result<FetchResponse> {
-        let mut timings_ms = BTreeMap::new();
-
+        req: &FetchRequest,
+        out: &FetchResponse,
+        timings_ms: &mut BTreeMap<String, u128>,
+    ) -> Result<()> {
         if let Some(cache) = self.cache.clone() {
             let req2 = req.clone();
-            let t0 = std::time::Instant::now();
+            let out2 = out.clone();
+            let t_put = std::time::Instant::now();
             let cache_timeout_ms = Self::cache_io_timeout_ms_from_env();
             let cache_io_disabled = self
                 .cache_io_disabled
                 .load(std::sync::atomic::Ordering::Relaxed);
             if !cache_io_disabled {
                 if cache_timeout_ms == 0 {
-                    timings_ms.insert("cache_get_timeout".to_string(), 0);
+                    timings_ms.insert("cache_put_timeout".to_string(), 0);
                     self.cache_io_disabled
                         .store(true, std::sync::atomic::Ordering::Relaxed);
                 } else {
-                    let mut handle = tokio::task::spawn_blocking(move || cache.get(&req2));
+                    let mut handle = tokio::task::spawn_blocking(move || cache.put(&req2, &out2));
                     let join = tokio::select! {
                         r = &mut handle => Ok(r),
                         _ = tokio::time::sleep(Duration::from_millis(cache_timeout_ms)) => {
@@ -581,18 +1890,14 @@ impl FetchBackend for LocalFetcher {
                     };
                     match join {
                         Ok(r) => {
-                            let hit = r.map_err(|e| {
-                                Error::Cache(format!("cache get join failed: {e}"))
-                            })??;
-                            timings_ms.insert("cache_get".to_string(), t0.elapsed().as_millis());
-                            if let Some(mut hit) = hit {
-                                hit.timings_ms = timings_ms;
-                                return Ok(hit);
-                            }
+                            r.map_err(|e| Error::Cache(format!("cache put join failed: {e}")))??;
+                            timings_ms.insert("cache_put".to_string(), t_put.elapsed().as_millis());
                         }
                         Err(()) => {
-                            timings_ms
-                                .insert("cache_get_timeout".to_string(), t0.elapsed().as_millis());
+                            timings_ms.insert(
+                                "cache_put_timeout".to_string(),
+                                t_put.elapsed().as_millis(),
+                            );
                             self.cache_io_disabled
                                 .store(true, std::sync::atomic::Ordering::Relaxed);
                         }
@@ -601,11 +1906,37 @@ impl FetchBackend for LocalFetcher {
             }
         }
 
-        let t_req = std::time::Instant::now();
-        let url = url::Url::parse(&req.url).map_err(|e| Error::InvalidUrl(e.to_string()))?;
+        Ok(())
+    }
 
-        // Anonymous mode: fail closed unless a proxy is configured, for any non-localhost URL.
-        // We do this *after* cache lookup, so warmed-cache workflows still work without proxy.
+    fn apply_headers(
+        &self,
+        mut rb: reqwest::RequestBuilder,
+        headers: &BTreeMap<String, String>,
+        url: &url::Url,
+    ) -> reqwest::RequestBuilder {
+        let allow_unsafe = Self::allow_unsafe_request_headers();
+        for (k, v) in headers {
+            if let (Ok(name), Ok(value)) = (
+                reqwest::header::HeaderName::from_bytes(k.as_bytes()),
+                reqwest::header::HeaderValue::from_str(v),
+            ) {
+                if !allow_unsafe
+                    && Self::is_sensitive_request_header(&name)
+                    && !Self::allow_sensitive_header_for_url(url)
+                {
+                    continue;
+                }
+                rb = rb.header(name, value);
+            }
+        }
+        rb
+    }
+}
+
+impl LocalFetcher {
+    /// Anonymous mode: fail closed unless a proxy is configured, for any non-localhost URL.
+    fn check_anonymous_mode(url: &url::Url) -> Result<()> {
         if Self::privacy_mode_from_env() == "anonymous" {
             let host = url.host_str().unwrap_or("");
             if !Self::is_localhost_host(host) && Self::anon_proxy_from_env().is_none() {
@@ -615,90 +1946,365 @@ impl FetchBackend for LocalFetcher {
                 ));
             }
         }
+        Ok(())
+    }
 
-        // YouTube: transcript-first via yt-dlp (opt-in/auto).
-        //
-        // Rationale: HTML scraping is brittle; yt-dlp already implements the moving target logic.
-        // We convert transcripts into a stable text/plain body so the rest of the pipeline (chunking,
-        // cache search, semantic rerank) can reuse existing logic.
-        let yt_mode = youtube::youtube_transcripts_mode_from_env();
-        let yt_enabled = yt_mode != "off";
-        if yt_enabled && youtube::youtube_video_id(&url).is_some() {
-            let timeout = req.timeout().unwrap_or(Duration::from_secs(20));
-            let url_s = req.url.clone();
-            let t0 = std::time::Instant::now();
-            let r = tokio::task::spawn_blocking(move || {
-                youtube::fetch_transcript_via_ytdlp(&url_s, timeout)
-            })
-            .await
-            .map_err(|e| Error::Fetch(format!("youtube transcript join failed: {e}")))?;
-            match r {
-                Ok(text) => {
-                    timings_ms.insert("youtube_transcript".to_string(), t0.elapsed().as_millis());
-                    let mut bytes = text.into_bytes();
-                    let max_bytes = req.max_bytes.unwrap_or(u64::MAX) as usize;
-                    let mut truncated = false;
-                    if bytes.len() > max_bytes {
-                        bytes.truncate(max_bytes);
+    /// With robots.txt respected, fail URLs their host disallows. The host's robots.txt is
+    /// fetched once per `WEBPIPE_ROBOTS_TTL_S` (short timeout, 500 KB cap, through the fetch
+    /// cache with the same TTL); a missing or unreachable one allows everything.
+    ///
+    /// `timings_ms` gets `robots_fetch` when robots.txt was (re)read, and `robots_allowed` /
+    /// `robots_unavailable` markers for the outcome.
+    async fn check_robots(
+        &self,
+        req: &FetchRequest,
+        url: &url::Url,
+        timings_ms: &mut BTreeMap<String, u128>,
+    ) -> Result<()> {
+        if !self.respect_robots || url.path() == "/robots.txt" {
+            return Ok(());
+        }
+        let origin = url.origin().ascii_serialization();
+        let ttl_s = Self::robots_ttl_s_from_env();
+        let cached = {
+            let map = self.robots.lock().unwrap_or_else(|e| e.into_inner());
+            map.get(&origin)
+                .filter(|(at, _)| at.elapsed() < Duration::from_secs(ttl_s))
+                .map(|(_, r)| r.clone())
+        };
+        let rules = match cached {
+            Some(r) => r,
+            None => {
+                let t0 = std::time::Instant::now();
+                let robots_req = FetchRequest {
+                    url: format!("{origin}/robots.txt"),
+                    timeout_ms: Some(req.timeout_ms.unwrap_or(5_000).min(5_000)),
+                    max_bytes: Some(500_000),
+                    headers: BTreeMap::new(),
+                    method: None,
+                    body: None,
+                    cache: webpipe_core::FetchCachePolicy {
+                        read: req.cache.read,
+                        write: req.cache.write,
+                        ttl_s: Some(ttl_s),
+                        read_non_idempotent: false,
+                        stale_while_revalidate_s: None,
+                    },
+                };
+                let rules = match Box::pin(self.fetch_uncoalesced(&robots_req)).await {
+                    Ok(r) if r.status == 200 => Some(crawl::RobotsRules::parse(
+                        &r.text_lossy(),
+                        ROBOTS_USER_AGENT,
+                    )),
+                    // 4xx: no robots.txt, everything allowed.
+                    Ok(r) if (400..500).contains(&r.status) => None,
+                    _ => {
+                        timings_ms.insert("robots_unavailable".to_string(), 1);
+                        None
+                    }
+                };
+                timings_ms.insert("robots_fetch".to_string(), t0.elapsed().as_millis());
+                self.robots
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(origin, (std::time::Instant::now(), rules.clone()));
+                rules
+            }
+        };
+        let Some(rules) = rules else {
+            return Ok(());
+        };
+        let path = match url.query() {
+            Some(q) => format!("{}?{q}", url.path()),
+            None => url.path().to_string(),
+        };
+        match rules.matching_rule(&path) {
+            Some((false, pat)) => Err(Error::NotSupported(format!(
+                "blocked by robots.txt (Disallow: {pat})"
+            ))),
+            Some((true, _)) | None => {
+                timings_ms.insert("robots_allowed".to_string(), 1);
+                Ok(())
+            }
+        }
+    }
+
+    /// Best-effort politeness limits (helps avoid bans / “silent throttles”), waited on right
+    /// before the network call: the per-host concurrency cap, then the global rate limiter (only
+    /// for non-localhost fetches). The returned permit is the host slot; hold it until the body is
+    /// read. The combined wait is `rate_limit_wait`.
+    async fn rate_limit_wait(
+        &self,
+        url: &url::Url,
+        timings_ms: &mut BTreeMap<String, u128>,
+    ) -> Option<tokio::sync::OwnedSemaphorePermit> {
+        let host = url.host_str().unwrap_or("");
+        let t0 = std::time::Instant::now();
+        let permit = match self.host_limiter.as_ref() {
+            Some(lim) => Some(lim.acquire(host).await),
+            None => None,
+        };
+        let rate_limiter = self
+            .rate_limiter
+            .as_ref()
+            .filter(|_| !Self::is_localhost_host(host));
+        if let Some(lim) = rate_limiter {
+            lim.wait().await;
+        }
+        if permit.is_some() || rate_limiter.is_some() {
+            timings_ms.insert("rate_limit_wait".to_string(), t0.elapsed().as_millis());
+        }
+        permit
+    }
+
+    /// Send `build()`, retrying transient failures per the [`retry::RetryPolicy`]. Only
+    /// idempotent requests are retried, and never past the request's own timeout. Retries are
+    /// reported as `retries` / `backoff_ms` in `timings_ms`.
+    async fn send_with_retry(
+        &self,
+        req: &FetchRequest,
+        build: impl Fn() -> reqwest::RequestBuilder,
+        timings_ms: &mut BTreeMap<String, u128>,
+    ) -> Result<reqwest::Response> {
+        let policy = &*self.retry;
+        let attempts = if req.is_idempotent() {
+            policy.max_attempts.max(1)
+        } else {
+            1
+        };
+        let t0 = std::time::Instant::now();
+        let mut retries = 0u32;
+        let mut backoff = Duration::ZERO;
+        let out = loop {
+            let r = build().send().await;
+            let last = retries + 1 >= attempts;
+            // Retryable: the server's Retry-After hint, if it sent one.
+            let retry_after = match &r {
+                Ok(resp) if !last && policy.retries_status(resp.status().as_u16()) => {
+                    match resp.status().as_u16() {
+                        429 | 503 => resp
+                            .headers()
+                            .get(reqwest::header::RETRY_AFTER)
+                            .and_then(|v| v.to_str().ok())
+                            .and_then(|v| {
+                                let now_s = SystemTime::now()
+                                    .duration_since(UNIX_EPOCH)
+                                    .map(|d| d.as_secs())
+                                    .unwrap_or(0);
+                                retry::parse_retry_after(v, now_s)
+                            }),
+                        _ => None,
+                    }
+                }
+                Err(e) if !last && (e.is_connect() || e.is_timeout() || e.is_request()) => None,
+                _ => break r,
+            };
+            let delay = policy.delay(retries + 1, retry_after);
+            if req.timeout().is_some_and(|to| t0.elapsed() + delay >= to) {
+                break r;
+            }
+            drop(r);
+            tokio::time::sleep(delay).await;
+            retries += 1;
+            backoff += delay;
+        };
+        if retries > 0 {
+            timings_ms.insert("retries".to_string(), retries as u128);
+            timings_ms.insert("backoff_ms".to_string(), backoff.as_millis());
+        }
+        out.map_err(Self::fetch_error)
+    }
+
+    /// Single-flight key: the cache key plus the read policy, so a cache-bypassing request is
+    /// never answered by a concurrent cache read.
+    fn flight_key(req: &FetchRequest) -> String {
+        format!(
+            "{}\nread:{}\nttl_s:{:?}\nswr_s:{:?}",
+            FsCache::key_for_fetch(req),
+            req.cache_readable(),
+            req.cache.ttl_s,
+            req.cache.stale_while_revalidate_s
+        )
+    }
+
+    /// Refresh `req`'s stale cache entry in the background with a cache-bypassing fetch that
+    /// rewrites it. At most one refresh per cache key runs at a time; a failed (or panicking)
+    /// refresh just leaves the stale entry in place.
+    fn spawn_revalidation(&self, req: &FetchRequest) {
+        use futures_util::FutureExt;
+
+        let key = FsCache::key_for_fetch(req);
+        let claimed = self
+            .revalidating
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.clone());
+        if !claimed {
+            return;
+        }
+        // Releases the key on drop, so it is freed even if the refresh panics.
+        struct Release(std::sync::Arc<Revalidating>, String);
+        impl Drop for Release {
+            fn drop(&mut self) {
+                self.0
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .remove(&self.1);
+            }
+        }
+        let release = Release(self.revalidating.clone(), key);
+        let this = self.clone();
+        let mut refresh = req.clone();
+        refresh.cache.read = false;
+        tokio::spawn(async move {
+            let _release = release;
+            let _ = std::panic::AssertUnwindSafe(this.fetch(&refresh))
+                .catch_unwind()
+                .await;
+        });
+    }
+
+    /// One fetch: cache lookup, network, cache write. `req` already carries the default headers;
+    /// [`FetchBackend::fetch`] coalesces concurrent identical calls onto one of these.
+    async fn fetch_uncoalesced(&self, req: &FetchRequest) -> Result<FetchResponse> {
+        let mut timings_ms = BTreeMap::new();
+        // Set once the cache was actually read: network results then count as a miss.
+        let mut cache_consulted = false;
+        let mut stale_entry: Option<FetchResponse> = None;
+
+        if let Some(cache) = self.cache.clone() {
+            let req2 = req.clone();
+            let t0 = std::time::Instant::now();
+            let cache_timeout_ms = Self::cache_io_timeout_ms_from_env();
+            let cache_io_disabled = self
+                .cache_io_disabled
+                .load(std::sync::atomic::Ordering::Relaxed);
+            if !cache_io_disabled {
+                if cache_timeout_ms == 0 {
+                    timings_ms.insert("cache_get_timeout".to_string(), 0);
+                    self.cache_io_disabled
+                        .store(true, std::sync::atomic::Ordering::Relaxed);
+                } else {
+                    let mut handle = tokio::task::spawn_blocking(move || cache.lookup(&req2));
+                    let join = tokio::select! {
+                        r = &mut handle => Ok(r),
+                        _ = tokio::time::sleep(Duration::from_millis(cache_timeout_ms)) => {
+                            handle.abort();
+                            Err(())
+                        }
+                    };
+                    match join {
+                        Ok(r) => {
+                            let hit = r.map_err(|e| {
+                                Error::Cache(format!("cache get join failed: {e}"))
+                            })??;
+                            timings_ms.insert("cache_get".to_string(), t0.elapsed().as_millis());
+                            cache_consulted = req.cache_readable();
+                            match hit {
+                                CacheLookup::Fresh(mut hit) => {
+                                    hit.timings_ms = timings_ms;
+                                    return Ok(self.record_cache_status(hit));
+                                }
+                                CacheLookup::Stale {
+                                    mut resp,
+                                    serve_stale: true,
+                                } => {
+                                    // stale-while-revalidate: answer now, refresh in the
+                                    // background, but never for a URL robots.txt now disallows.
+                                    let allowed = match url::Url::parse(&req.url) {
+                                        Ok(url) => self
+                                            .check_robots(req, &url, &mut timings_ms)
+                                            .await
+                                            .is_ok(),
+                                        Err(_) => false,
+                                    };
+                                    if allowed {
+                                        self.spawn_revalidation(req);
+                                    }
+                                    resp.cache_status = Some(CacheStatus::Stale);
+                                    resp.revalidating = allowed;
+                                    resp.timings_ms = timings_ms;
+                                    return Ok(self.record_cache_status(resp));
+                                }
+                                CacheLookup::Stale { resp, .. } => {
+                                    stale_entry = Some(resp).filter(Self::has_validators);
+                                }
+                                CacheLookup::Miss => {}
+                            }
+                        }
+                        Err(()) => {
+                            timings_ms
+                                .insert("cache_get_timeout".to_string(), t0.elapsed().as_millis());
+                            self.cache_io_disabled
+                                .store(true, std::sync::atomic::Ordering::Relaxed);
+                        }
+                    }
+                }
+            }
+        }
+
+        let t_req = std::time::Instant::now();
+        let url = url::Url::parse(&req.url).map_err(|e| Error::InvalidUrl(e.to_string()))?;
+        let method = reqwest::Method::from_bytes(req.method().as_bytes())
+            .map_err(|_| Error::Fetch(format!("invalid http method: {}", req.method())))?;
+
+        // We do this *after* cache lookup, so warmed-cache workflows still work without proxy.
+        Self::check_anonymous_mode(&url)?;
+        self.check_robots(req, &url, &mut timings_ms).await?;
+
+        // YouTube: transcript-first via yt-dlp (opt-in/auto).
+        //
+        // Rationale: HTML scraping is brittle; yt-dlp already implements the moving target logic.
+        // We convert transcripts into a stable text body (or, with WEBPIPE_YOUTUBE_TIMESTAMPS, JSON
+        // carrying that text plus cue/chapter times) so the rest of the pipeline (chunking, cache
+        // search, semantic rerank) can reuse existing logic.
+        let yt_mode = youtube::youtube_transcripts_mode_from_env();
+        let yt_enabled = yt_mode != "off";
+        if yt_enabled && method == reqwest::Method::GET && youtube::youtube_video_id(&url).is_some()
+        {
+            let timeout = req.timeout().unwrap_or(Duration::from_secs(20));
+            let url_s = req.url.clone();
+            let timestamps = youtube::youtube_timestamps_from_env();
+            let t0 = std::time::Instant::now();
+            let r = tokio::task::spawn_blocking(move || {
+                youtube::fetch_transcript_via_ytdlp(&url_s, timeout, timestamps)
+            })
+            .await
+            .map_err(|e| Error::Fetch(format!("youtube transcript join failed: {e}")))?;
+            match r {
+                Ok(text) => {
+                    timings_ms.insert("youtube_transcript".to_string(), t0.elapsed().as_millis());
+                    let mut bytes = text.into_bytes();
+                    let max_bytes = req.max_bytes.unwrap_or(u64::MAX) as usize;
+                    let mut truncated = false;
+                    if bytes.len() > max_bytes {
+                        bytes.truncate(max_bytes);
                         truncated = true;
                     }
                     let out = FetchResponse {
                         url: req.url.clone(),
                         final_url: req.url.clone(),
                         status: 200,
-                        content_type: Some("text/x-youtube-transcript".to_string()),
+                        content_type: Some(
+                            if timestamps {
+                                youtube::TRANSCRIPT_JSON_CONTENT_TYPE
+                            } else {
+                                youtube::TRANSCRIPT_CONTENT_TYPE
+                            }
+                            .to_string(),
+                        ),
                         headers: BTreeMap::new(),
+                        wire_bytes: bytes.len() as u64,
                         bytes,
                         truncated,
                         source: FetchSource::Network,
+                        cache_status: cache_consulted.then_some(CacheStatus::Miss),
+                        body_path: None,
+                        revalidating: false,
                         timings_ms: timings_ms.clone(),
                     };
-                    if let Some(cache) = self.cache.clone() {
-                        let req2 = req.clone();
-                        let out2 = out.clone();
-                        let t_put = std::time::Instant::now();
-                        let cache_timeout_ms = Self::cache_io_timeout_ms_from_env();
-                        let cache_io_disabled = self
-                            .cache_io_disabled
-                            .load(std::sync::atomic::Ordering::Relaxed);
-                        if !cache_io_disabled {
-                            if cache_timeout_ms == 0 {
-                                timings_ms.insert("cache_put_timeout".to_string(), 0);
-                                self.cache_io_disabled
-                                    .store(true, std::sync::atomic::Ordering::Relaxed);
-                            } else {
-                                let mut handle =
-                                    tokio::task::spawn_blocking(move || cache.put(&req2, &out2));
-                                let join = tokio::select! {
-                                    r = &mut handle => Ok(r),
-                                    _ = tokio::time::sleep(Duration::from_millis(cache_timeout_ms)) => {
-                                        handle.abort();
-                                        Err(())
-                                    }
-                                };
-                                match join {
-                                    Ok(r) => {
-                                        r.map_err(|e| {
-                                            Error::Cache(format!("cache put join failed: {e}"))
-                                        })??;
-                                        timings_ms.insert(
-                                            "cache_put".to_string(),
-                                            t_put.elapsed().as_millis(),
-                                        );
-                                    }
-                                    Err(()) => {
-                                        timings_ms.insert(
-                                            "cache_put_timeout".to_string(),
-                                            t_put.elapsed().as_millis(),
-                                        );
-                                        self.cache_io_disabled
-                                            .store(true, std::sync::atomic::Ordering::Relaxed);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    return Ok(FetchResponse { timings_ms, ..out });
+                    self.cache_put_bounded(req, &out, &mut timings_ms).await?;
+                    return Ok(self.record_cache_status(FetchResponse { timings_ms, ..out }));
                 }
                 Err(e) => {
                     if yt_mode == "yt-dlp" || yt_mode == "strict" {
@@ -709,23 +2315,118 @@ impl FetchBackend for LocalFetcher {
             }
         }
 
-        // Best-effort politeness limiter (helps avoid bans / “silent throttles”).
-        // Only apply to non-localhost network fetches.
-        if let Some(lim) = self.rate_limiter.as_ref() {
-            let host = url.host_str().unwrap_or("");
-            if !Self::is_localhost_host(host) {
-                let t0 = std::time::Instant::now();
-                lim.wait().await;
-                timings_ms.insert("rate_limit_wait".to_string(), t0.elapsed().as_millis());
+        let _host_permit = self.rate_limit_wait(&url, &mut timings_ms).await;
+
+        // Large stale bodies with a strong ETag can be revalidated as a ranged request for what
+        // follows the cached bytes (`WEBPIPE_CACHE_IF_RANGE_MIN_BYTES`).
+        let if_range = stale_entry
+            .as_ref()
+            .and_then(|stale| Self::if_range_offset(stale, req));
+        let build = |stale: Option<&FetchResponse>, if_range: Option<&(usize, String)>| {
+            let mut rb = self.client.request(method.clone(), url.clone());
+            if let Some(to) = req.timeout() {
+                rb = rb.timeout(to);
+            }
+            if let Some(body) = &req.body {
+                rb = rb.body(body.clone());
+            }
+            if !req
+                .headers
+                .keys()
+                .any(|k| k.eq_ignore_ascii_case("accept-encoding"))
+            {
+                // Byte ranges must address the same (unencoded) representation that is cached.
+                let ae = if if_range.is_some() {
+                    "identity"
+                } else {
+                    "gzip, br, zstd, deflate"
+                };
+                rb = rb.header(reqwest::header::ACCEPT_ENCODING, ae);
+            }
+            // A caller-supplied User-Agent header wins over the pool.
+            if !req
+                .headers
+                .keys()
+                .any(|k| k.eq_ignore_ascii_case("user-agent"))
+            {
+                rb = rb.header(reqwest::header::USER_AGENT, self.user_agent_for(req));
+            }
+            rb = self.apply_headers(rb, &req.headers, &url);
+            if let Some(stale) = stale {
+                rb = Self::apply_validators(rb, stale, &req.headers);
+            }
+            if let Some((offset, etag)) = if_range {
+                // Unchanged: 304 (If-None-Match). Same representation, grown: 206 with the tail.
+                // Changed: If-Range fails and the full new body comes back.
+                rb = rb
+                    .header(reqwest::header::RANGE, format!("bytes={offset}-"))
+                    .header(reqwest::header::IF_RANGE, etag.as_str());
+            }
+            rb
+        };
+        let mut resp = self
+            .send_with_retry(
+                req,
+                || build(stale_entry.as_ref(), if_range.as_ref()),
+                &mut timings_ms,
+            )
+            .await?;
+        if let Some((offset, etag)) = if_range.as_ref() {
+            let st = resp.status().as_u16();
+            if st == 206 || st == 416 {
+                let extends = Self::if_range_extends(resp.headers(), st, *offset, etag);
+                let room = req.max_bytes.unwrap_or(u64::MAX) as usize;
+                if extends {
+                    let fresh_headers = resp.headers().clone();
+                    let tail = resp.bytes().await.map_err(Self::fetch_error)?;
+                    let tail = if st == 206 { &tail[..] } else { &[][..] };
+                    if offset.saturating_add(tail.len()) <= room {
+                        if let Some(mut cached) = stale_entry.take() {
+                            timings_ms
+                                .insert("network_fetch".to_string(), t_req.elapsed().as_millis());
+                            timings_ms.insert("if_range_partial".to_string(), tail.len() as u128);
+                            cached.bytes.extend_from_slice(tail);
+                            cached.wire_bytes = tail.len() as u64;
+                            // The entry now holds the whole body, as revalidated by this answer.
+                            Self::merge_validators(&mut cached.headers, &fresh_headers);
+                            cached.headers.retain(|h, _| {
+                                !h.eq_ignore_ascii_case("content-length")
+                                    && !h.eq_ignore_ascii_case("content-range")
+                            });
+                            cached.headers.insert(
+                                "content-length".to_string(),
+                                cached.bytes.len().to_string(),
+                            );
+                            cached.cache_status = Some(CacheStatus::Revalidated);
+                            self.cache_put_bounded(req, &cached, &mut timings_ms)
+                                .await?;
+                            cached.timings_ms = timings_ms;
+                            return Ok(self.record_cache_status(cached));
+                        }
+                    }
+                }
+                // The server ignored If-Range (a range of a changed body) or the range did not
+                // line up with the cached bytes: fetch the whole body instead.
+                timings_ms.insert("if_range_ignored".to_string(), t_req.elapsed().as_millis());
+                resp = build(stale_entry.as_ref(), None)
+                    .send()
+                    .await
+                    .map_err(Self::fetch_error)?;
             }
         }
-
-        let mut rb = self.client.get(url.clone());
-        if let Some(to) = req.timeout() {
-            rb = rb.timeout(to);
+        if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+            if let Some(mut cached) = stale_entry.take() {
+                timings_ms.insert("network_fetch".to_string(), t_req.elapsed().as_millis());
+                // The 304's validators/freshness supersede the stored ones.
+                Self::merge_validators(&mut cached.headers, resp.headers());
+                cached.cache_status = Some(CacheStatus::Revalidated);
+                // Re-store to restart the entry's age.
+                self.cache_put_bounded(req, &cached, &mut timings_ms)
+                    .await?;
+                cached.timings_ms = timings_ms;
+                return Ok(self.record_cache_status(cached));
+            }
         }
-        rb = self.apply_headers(rb, &req.headers, &url);
-        let resp = rb.send().await.map_err(|e| Error::Fetch(e.to_string()))?;
         let final_url = resp.url().to_string();
         let status = resp.status().as_u16();
         let content_type = resp
@@ -740,24 +2441,79 @@ impl FetchBackend for LocalFetcher {
                 headers.insert(k.as_str().to_string(), s.to_string());
             }
         }
+        let streaming = Self::is_streaming_content_type(content_type.as_deref());
 
-        let max_bytes = req.max_bytes.unwrap_or(u64::MAX) as usize;
+        let mut max_bytes = req.max_bytes.unwrap_or(u64::MAX) as usize;
+        // Open-ended streams (SSE, ndjson, ...) could otherwise hold the fetch open until
+        // max_bytes or the request timeout. Cap them and keep what arrived (`streaming_capped`).
+        let (stream_max_bytes, stream_max_ms) = Self::streaming_caps_from_env();
+        let stream_byte_cap = streaming && stream_max_bytes > 0 && stream_max_bytes < max_bytes;
+        if stream_byte_cap {
+            max_bytes = stream_max_bytes;
+        }
+        let mut decoder = content_encoding::BodyDecoder::for_encoding(
+            headers.get("content-encoding").map(|s| s.as_str()),
+            max_bytes,
+        );
+        if !decoder.is_identity() {
+            // Match what transparent decompression used to expose: the body is decoded, so
+            // its encoding/length headers no longer describe `bytes`.
+            headers.remove("content-encoding");
+            headers.remove("content-length");
+        }
+        let deadline = (streaming && stream_max_ms > 0).then(|| {
+            let mut d = tokio::time::Instant::now() + Duration::from_millis(stream_max_ms);
+            // Stop before reqwest's own timeout turns a partial body into an error.
+            if let Some(to) = req.timeout() {
+                d = d.min(tokio::time::Instant::from_std(t_req) + to.mul_f64(0.9));
+            }
+            d
+        });
         let mut truncated = false;
-        let mut bytes = Vec::new();
+        let mut streaming_capped = false;
+        let mut wire_bytes = 0u64;
         let mut stream = resp.bytes_stream();
         use futures_util::StreamExt;
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk.map_err(|e| Error::Fetch(e.to_string()))?;
-            if bytes.len().saturating_add(chunk.len()) > max_bytes {
-                let can_take = max_bytes.saturating_sub(bytes.len());
-                bytes.extend_from_slice(&chunk[..can_take]);
+        loop {
+            let next = match deadline {
+                Some(d) => match tokio::time::timeout_at(d, stream.next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        streaming_capped = true;
+                        break;
+                    }
+                },
+                None => stream.next().await,
+            };
+            let Some(chunk) = next else {
+                break;
+            };
+            let chunk = chunk.map_err(Self::fetch_error)?;
+            let used = decoder.feed(&chunk, max_bytes);
+            wire_bytes += used as u64;
+            if decoder.decoded_len() > max_bytes {
                 truncated = true;
+                streaming_capped = stream_byte_cap;
                 break;
             }
-            bytes.extend_from_slice(&chunk);
+        }
+        let (mut bytes, decode_failed) = decoder.finish();
+        if bytes.len() > max_bytes {
+            if wire_bytes == bytes.len() as u64 {
+                wire_bytes = max_bytes as u64;
+            }
+            bytes.truncate(max_bytes);
         }
 
         timings_ms.insert("network_fetch".to_string(), t_req.elapsed().as_millis());
+        if streaming_capped {
+            truncated = true;
+            timings_ms.insert("streaming_capped".to_string(), t_req.elapsed().as_millis());
+        }
+        if decode_failed {
+            // Mislabelled encoding (e.g. `gzip` on a plain body): `bytes` are as received.
+            timings_ms.insert("decode_failed".to_string(), t_req.elapsed().as_millis());
+        }
         let out = FetchResponse {
             url: req.url.clone(),
             final_url,
@@ -765,73 +2521,913 @@ impl FetchBackend for LocalFetcher {
             content_type,
             headers,
             bytes,
+            wire_bytes,
             truncated,
             source: FetchSource::Network,
+            cache_status: cache_consulted.then_some(CacheStatus::Miss),
+            body_path: None,
+            revalidating: false,
             timings_ms: timings_ms.clone(),
         };
 
-        if let Some(cache) = self.cache.clone() {
-            let req2 = req.clone();
-            let out2 = out.clone();
-            let t_put = std::time::Instant::now();
-            let cache_timeout_ms = Self::cache_io_timeout_ms_from_env();
-            let cache_io_disabled = self
-                .cache_io_disabled
-                .load(std::sync::atomic::Ordering::Relaxed);
-            if !cache_io_disabled {
-                if cache_timeout_ms == 0 {
-                    timings_ms.insert("cache_put_timeout".to_string(), 0);
-                    self.cache_io_disabled
-                        .store(true, std::sync::atomic::Ordering::Relaxed);
-                } else {
-                    let mut handle = tokio::task::spawn_blocking(move || cache.put(&req2, &out2));
-                    let join = tokio::select! {
-                        r = &mut handle => Ok(r),
-                        _ = tokio::time::sleep(Duration::from_millis(cache_timeout_ms)) => {
-                            handle.abort();
-                            Err(())
-                        }
-                    };
-                    match join {
-                        Ok(r) => {
-                            r.map_err(|e| Error::Cache(format!("cache put join failed: {e}")))??;
-                            timings_ms.insert("cache_put".to_string(), t_put.elapsed().as_millis());
-                        }
-                        Err(()) => {
-                            timings_ms.insert(
-                                "cache_put_timeout".to_string(),
-                                t_put.elapsed().as_millis(),
-                            );
-                            self.cache_io_disabled
-                                .store(true, std::sync::atomic::Ordering::Relaxed);
-                        }
-                    }
+        self.cache_put_bounded(req, &out, &mut timings_ms).await?;
+
+        Ok(self.record_cache_status(FetchResponse { timings_ms, ..out }))
+    }
+
+    /// Like [`FetchBackend::fetch`], but stream the body straight into `dest` instead of
+    /// memory, for responses too large to hold (big PDFs, media).
+    ///
+    /// The result has empty `bytes` and `body_path = Some(dest)`. `max_bytes` caps what is
+    /// written (`truncated` is set when it cuts the body short). The body is asked for and stored
+    /// as-is (`Accept-Encoding: identity`; a server that encodes anyway keeps its
+    /// `content-encoding` header). The fetch cache is neither read nor written, and a failed
+    /// fetch removes whatever was written to `dest`.
+    pub async fn fetch_to_path(
+        &self,
+        req: &FetchRequest,
+        dest: &std::path::Path,
+    ) -> Result<FetchResponse> {
+        use futures_util::StreamExt;
+        use tokio::io::AsyncWriteExt;
+
+        let req = &*self.with_default_headers(req);
+        let mut timings_ms = BTreeMap::new();
+        let t_req = std::time::Instant::now();
+        let url = url::Url::parse(&req.url).map_err(|e| Error::InvalidUrl(e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(Error::NotSupported(format!(
+                "fetch_to_path for {}:// urls",
+                url.scheme()
+            )));
+        }
+        let method = reqwest::Method::from_bytes(req.method().as_bytes())
+            .map_err(|_| Error::Fetch(format!("invalid http method: {}", req.method())))?;
+        Self::check_anonymous_mode(&url)?;
+        self.check_robots(req, &url, &mut timings_ms).await?;
+        let _host_permit = self.rate_limit_wait(&url, &mut timings_ms).await;
+
+        let mut rb = self.client.request(method, url.clone());
+        if let Some(to) = req.timeout() {
+            rb = rb.timeout(to);
+        }
+        if let Some(body) = &req.body {
+            rb = rb.body(body.clone());
+        }
+        if !req
+            .headers
+            .keys()
+            .any(|k| k.eq_ignore_ascii_case("accept-encoding"))
+        {
+            rb = rb.header(reqwest::header::ACCEPT_ENCODING, "identity");
+        }
+        if !req
+            .headers
+            .keys()
+            .any(|k| k.eq_ignore_ascii_case("user-agent"))
+        {
+            rb = rb.header(reqwest::header::USER_AGENT, self.user_agent_for(req));
+        }
+        rb = self.apply_headers(rb, &req.headers, &url);
+        let resp = self
+            .send_with_retry(
+                req,
+                || rb.try_clone().expect("request body is in memory"),
+                &mut timings_ms,
+            )
+            .await?;
+
+        let final_url = resp.url().to_string();
+        let status = resp.status().as_u16();
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        let mut headers = BTreeMap::new();
+        for (k, v) in resp.headers().iter() {
+            if let Ok(s) = v.to_str() {
+                headers.insert(k.as_str().to_string(), s.to_string());
+            }
+        }
+
+        let max_bytes = req.max_bytes.unwrap_or(u64::MAX);
+        let mut file = tokio::fs::File::create(dest)
+            .await
+            .map_err(|e| Error::Fetch(format!("create {}: {e}", dest.display())))?;
+        let mut written = 0u64;
+        let mut truncated = false;
+        let mut stream = resp.bytes_stream();
+        let copied: Result<()> = async {
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(Self::fetch_error)?;
+                let room = max_bytes - written;
+                let take = (chunk.len() as u64).min(room) as usize;
+                file.write_all(&chunk[..take])
+                    .await
+                    .map_err(|e| Error::Fetch(format!("write {}: {e}", dest.display())))?;
+                written += take as u64;
+                if take < chunk.len() {
+                    truncated = true;
+                    break;
                 }
             }
+            file.flush()
+                .await
+                .map_err(|e| Error::Fetch(format!("write {}: {e}", dest.display())))
         }
+        .await;
+        drop(file);
+        if let Err(e) = copied {
+            let _ = tokio::fs::remove_file(dest).await;
+            return Err(e);
+        }
+        timings_ms.insert("network_fetch".to_string(), t_req.elapsed().as_millis());
+
+        Ok(FetchResponse {
+            url: req.url.clone(),
+            final_url,
+            status,
+            content_type,
+            headers,
+            bytes: Vec::new(),
+            wire_bytes: written,
+            truncated,
+            source: FetchSource::Network,
+            cache_status: None,
+            body_path: Some(dest.to_path_buf()),
+            revalidating: false,
+            timings_ms,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl FetchBackend for LocalFetcher {
+    async fn fetch(&self, req: &FetchRequest) -> Result<FetchResponse> {
+        use futures_util::FutureExt;
+
+        let req = &*self.with_default_headers(req);
+        if let Ok(u) = url::Url::parse(&req.url) {
+            if u.scheme() == "file" {
+                if req.method() != "GET" || req.body.is_some() {
+                    return Err(Error::NotSupported(format!(
+                        "{} file:// urls (only GET)",
+                        req.method()
+                    )));
+                }
+                return self.fetch_file_url(req, &u).await;
+            }
+        }
+        // Two identical POSTs/PUTs/DELETEs are two side effects; only share a flight the cache
+        // could answer.
+        if !req.is_safe() && !req.cache_readable() {
+            return self.fetch_uncoalesced(req).await;
+        }
+        let key = Self::flight_key(req);
+        let (flight, joined) = {
+            let mut inflight = self.inflight.0.lock().unwrap_or_else(|e| e.into_inner());
+            match inflight.get(&key) {
+                Some(f) => (f.clone(), true),
+                None => {
+                    let this = self.clone();
+                    let req = req.clone();
+                    let k = key.clone();
+                    // Whichever caller polls drives the fetch, so it survives the first caller
+                    // being cancelled; the entry goes once the result is in.
+                    let f = async move {
+                        let r = this.fetch_uncoalesced(&req).await;
+                        this.inflight
+                            .0
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .remove(&k);
+                        r
+                    }
+                    .boxed()
+                    .shared();
+                    inflight.insert(key, f.clone());
+                    (f, false)
+                }
+            }
+        };
+        if !joined {
+            return flight.await;
+        }
+        let t0 = std::time::Instant::now();
+        let mut resp = flight.await?;
+        resp.timings_ms
+            .insert("single_flight_wait".to_string(), t0.elapsed().as_millis());
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::header, http::StatusCode, routing::get, Router};
+    use proptest::prelude::*;
+    use std::net::SocketAddr;
+    use std::path::Path;
+    use std::sync::Mutex;
+    use webpipe_core::FetchCachePolicy;
+
+    // Env vars are process-global; serialize tests that mutate them.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    #[tokio::test]
+    async fn local_fetcher_hits_cache() {
+        let app = Router::new().route(
+            "/",
+            get(|| async { ([(header::CONTENT_TYPE, "text/plain")], "hello") }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let tmp = tempfile::tempdir().unwrap();
+        let fetcher = LocalFetcher::new(Some(tmp.path().to_path_buf())).unwrap();
+
+        let req = FetchRequest {
+            url: format!("http://{}/", addr),
+            timeout_ms: Some(2_000),
+            max_bytes: Some(1_000_000),
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy {
+                read: true,
+                write: true,
+                ttl_s: Some(60),
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
+            },
+        };
+
+        let r1 = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(r1.source, FetchSource::Network);
+        let r2 = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(r2.source, FetchSource::Cache);
+    }
+
+    #[tokio::test]
+    async fn post_bodies_are_sent_keyed_and_only_read_from_cache_on_opt_in() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let hits = std::sync::Arc::new(AtomicUsize::new(0));
+        let h = hits.clone();
+        let echo = move |body: String| {
+            let h = h.clone();
+            async move {
+                h.fetch_add(1, Ordering::SeqCst);
+                format!("got {body}")
+            }
+        };
+        let app = Router::new().route("/echo", axum::routing::post(echo.clone()).put(echo));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let tmp = tempfile::tempdir().unwrap();
+        let fetcher = LocalFetcher::new(Some(tmp.path().to_path_buf())).unwrap();
+        let req = |body: &str, read_non_idempotent: bool| FetchRequest {
+            url: format!("http://{addr}/echo"),
+            timeout_ms: Some(2_000),
+            max_bytes: Some(1_000_000),
+            headers: BTreeMap::new(),
+            method: Some("post".to_string()),
+            body: Some(body.as_bytes().to_vec()),
+            cache: FetchCachePolicy {
+                read: true,
+                write: true,
+                ttl_s: Some(60),
+                read_non_idempotent,
+                stale_while_revalidate_s: None,
+            },
+        };
+
+        let a = fetcher.fetch(&req("a", false)).await.unwrap();
+        assert_eq!(a.bytes, b"got a");
+        // Not idempotent: the cached entry is not reused by default.
+        let a2 = fetcher.fetch(&req("a", false)).await.unwrap();
+        assert_eq!(a2.source, FetchSource::Network);
+        assert_eq!(a2.cache_status, None);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        let a3 = fetcher.fetch(&req("a", true)).await.unwrap();
+        assert_eq!(a3.source, FetchSource::Cache);
+        assert_eq!(a3.bytes, b"got a");
+        // A different body is a different entry.
+        let b = fetcher.fetch(&req("b", true)).await.unwrap();
+        assert_eq!(b.source, FetchSource::Network);
+        assert_eq!(b.bytes, b"got b");
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+        // PUT is idempotent, but repeating it must still reach the server.
+        let put = FetchRequest {
+            method: Some("PUT".to_string()),
+            ..req("a", false)
+        };
+        for _ in 0..2 {
+            assert_eq!(
+                fetcher.fetch(&put).await.unwrap().source,
+                FetchSource::Network
+            );
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 5);
+
+        let get = FetchRequest {
+            method: None,
+            body: None,
+            ..req("a", true)
+        };
+        assert_ne!(
+            FsCache::key_for_fetch_v2(&get, &[]),
+            FsCache::key_for_fetch_v2(&req("a", true), &[])
+        );
+    }
+
+    #[tokio::test]
+    async fn transient_statuses_are_retried_for_idempotent_requests_only() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let hits = std::sync::Arc::new(AtomicUsize::new(0));
+        let flaky = |hits: std::sync::Arc<AtomicUsize>| {
+            move || {
+                let n = hits.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if n % 3 < 2 {
+                        (
+                            StatusCode::SERVICE_UNAVAILABLE,
+                            [(header::RETRY_AFTER, "0")],
+                            "busy",
+                        )
+                    } else {
+                        (StatusCode::OK, [(header::RETRY_AFTER, "0")], "ok")
+                    }
+                }
+            }
+        };
+        let app = Router::new().route("/flaky", get(flaky(hits.clone())).post(flaky(hits.clone())));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let fetcher = LocalFetcher::new(None)
+            .unwrap()
+            .with_retry_policy(retry::RetryPolicy {
+                max_attempts: 3,
+                base_delay_ms: 10,
+                ..Default::default()
+            });
+        let mut req = FetchRequest {
+            url: format!("http://{addr}/flaky"),
+            timeout_ms: Some(5_000),
+            max_bytes: None,
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy::default(),
+        };
+        let r = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(r.status, 200);
+        assert_eq!(r.bytes, b"ok");
+        assert_eq!(r.timings_ms.get("retries"), Some(&2));
+        assert!(r.timings_ms.contains_key("backoff_ms"));
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+        req.method = Some("POST".to_string());
+        let r = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(r.status, 503);
+        assert!(!r.timings_ms.contains_key("retries"));
+        assert_eq!(hits.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn robots_txt_blocks_disallowed_paths_only_when_respected() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        std::env::remove_var("WEBPIPE_RESPECT_ROBOTS");
+        let robots_hits = std::sync::Arc::new(AtomicUsize::new(0));
+        let h = robots_hits.clone();
+        let app = Router::new()
+            .route(
+                "/robots.txt",
+                get(move || {
+                    h.fetch_add(1, Ordering::SeqCst);
+                    async { "User-agent: *\nDisallow: /private\nAllow: /private/open\n" }
+                }),
+            )
+            .route("/private/x", get(|| async { "secret" }))
+            .route("/private/open/y", get(|| async { "open" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        let req = |path: &str| FetchRequest {
+            url: format!("http://{addr}{path}"),
+            timeout_ms: Some(2_000),
+            max_bytes: None,
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy::default(),
+        };
+
+        // Off by default.
+        let fetcher = LocalFetcher::new(None).unwrap();
+        assert_eq!(
+            fetcher.fetch(&req("/private/x")).await.unwrap().bytes,
+            b"secret"
+        );
+        assert_eq!(robots_hits.load(Ordering::SeqCst), 0);
+
+        let fetcher = LocalFetcher::with_robots(None, true).unwrap();
+        let err = fetcher.fetch(&req("/private/x")).await.unwrap_err();
+        assert!(
+            matches!(&err, Error::NotSupported(m) if m.contains("blocked by robots.txt") && m.contains("/private")),
+            "{err}"
+        );
+        let ok = fetcher.fetch(&req("/private/open/y")).await.unwrap();
+        assert_eq!(ok.bytes, b"open");
+        assert!(ok.timings_ms.contains_key("robots_allowed"));
+        // robots.txt was read once for the host.
+        assert_eq!(robots_hits.load(Ordering::SeqCst), 1);
+
+        std::env::set_var("WEBPIPE_RESPECT_ROBOTS", "0");
+        let fetcher = LocalFetcher::with_robots(None, true).unwrap();
+        std::env::remove_var("WEBPIPE_RESPECT_ROBOTS");
+        assert!(fetcher.fetch(&req("/private/x")).await.is_ok());
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn stale_entries_for_robots_disallowed_urls_are_not_revalidated() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        std::env::remove_var("WEBPIPE_RESPECT_ROBOTS");
+        let hits = std::sync::Arc::new(AtomicUsize::new(0));
+        let h = hits.clone();
+        let app = Router::new()
+            .route(
+                "/robots.txt",
+                get(|| async { "User-agent: *\nDisallow: /private\n" }),
+            )
+            .route(
+                "/private/x",
+                get(move || {
+                    h.fetch_add(1, Ordering::SeqCst);
+                    async { "secret" }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = FsCache::new(tmp.path().to_path_buf());
+        let req = FetchRequest {
+            url: format!("http://{addr}/private/x"),
+            timeout_ms: Some(2_000),
+            max_bytes: None,
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy {
+                ttl_s: Some(60),
+                stale_while_revalidate_s: Some(600),
+                ..Default::default()
+            },
+        };
+        // Cached while robots.txt was ignored, then aged into the stale-while-revalidate window.
+        let fetcher = LocalFetcher::new(Some(tmp.path().to_path_buf())).unwrap();
+        fetcher.fetch(&req).await.unwrap();
+        let (meta_p, _) = cache.paths(&FsCache::key_for_fetch(&req));
+        let mut meta: serde_json::Value =
+            serde_json::from_slice(&fs::read(&meta_p).unwrap()).unwrap();
+        let at = meta["fetched_at_epoch_s"].as_u64().unwrap();
+        meta["fetched_at_epoch_s"] = serde_json::json!(at - 120);
+        fs::write(&meta_p, serde_json::to_vec(&meta).unwrap()).unwrap();
+
+        // The stale copy is still served, but nothing goes back to the origin for it.
+        let fetcher = LocalFetcher::with_robots(Some(tmp.path().to_path_buf()), true).unwrap();
+        let r = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(
+            (r.cache_status, r.revalidating),
+            (Some(CacheStatus::Stale), false)
+        );
+        assert_eq!(r.bytes, b"secret");
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn fetch_to_path_streams_the_body_to_disk_and_honors_max_bytes() {
+        let body: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let served = body.clone();
+        let app = Router::new().route(
+            "/big.bin",
+            get(move || {
+                let b = served.clone();
+                async move { ([(header::CONTENT_TYPE, "application/octet-stream")], b) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let tmp = tempfile::tempdir().unwrap();
+        let fetcher = LocalFetcher::new(Some(tmp.path().join("cache"))).unwrap();
+        let mut req = FetchRequest {
+            url: format!("http://{addr}/big.bin"),
+            timeout_ms: Some(5_000),
+            max_bytes: None,
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy::default(),
+        };
+
+        let dest = tmp.path().join("full.bin");
+        let r = fetcher.fetch_to_path(&req, &dest).await.unwrap();
+        assert_eq!(r.status, 200);
+        assert!(r.bytes.is_empty());
+        assert!(!r.truncated);
+        assert_eq!(r.body_path.as_deref(), Some(dest.as_path()));
+        assert_eq!(r.wire_bytes, body.len() as u64);
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+        // Never written to the fetch cache.
+        assert!(fetcher.cache_get(&req).unwrap().is_none());
+
+        req.max_bytes = Some(10_000);
+        let dest = tmp.path().join("capped.bin");
+        let r = fetcher.fetch_to_path(&req, &dest).await.unwrap();
+        assert!(r.truncated);
+        assert_eq!(r.wire_bytes, 10_000);
+        assert_eq!(std::fs::read(&dest).unwrap(), &body[..10_000]);
+    }
+
+    #[tokio::test]
+    async fn rate_limiter_allows_a_burst_then_spaces_requests() {
+        let lim = RateLimiter::per_second(20.0).unwrap();
+        assert_eq!(lim.burst, 20);
+        let t0 = std::time::Instant::now();
+        for _ in 0..20 {
+            lim.wait().await;
+        }
+        assert!(
+            t0.elapsed() < Duration::from_millis(40),
+            "{:?}",
+            t0.elapsed()
+        );
+        lim.wait().await;
+        assert!(
+            t0.elapsed() >= Duration::from_millis(45),
+            "{:?}",
+            t0.elapsed()
+        );
+        assert!(RateLimiter::per_second(0.0).is_none());
+    }
+
+    #[tokio::test]
+    async fn per_host_limit_serializes_network_fetches_but_not_cache_hits() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let in_flight = std::sync::Arc::new(AtomicUsize::new(0));
+        let peak = std::sync::Arc::new(AtomicUsize::new(0));
+        let (f, p) = (in_flight.clone(), peak.clone());
+        let app = Router::new().route(
+            "/:n",
+            get(move || {
+                let (f, p) = (f.clone(), p.clone());
+                async move {
+                    let now = f.fetch_add(1, Ordering::SeqCst) + 1;
+                    p.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    f.fetch_sub(1, Ordering::SeqCst);
+                    "page"
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let tmp = tempfile::tempdir().unwrap();
+        let fetcher = LocalFetcher::new(Some(tmp.path().to_path_buf()))
+            .unwrap()
+            .with_limits(Some(1), None);
+        let req = |n: usize| FetchRequest {
+            url: format!("http://{addr}/{n}"),
+            timeout_ms: Some(5_000),
+            max_bytes: None,
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy::default(),
+        };
+        let rs = futures_util::future::join_all((0..4).map(|n| {
+            let fetcher = fetcher.clone();
+            async move { fetcher.fetch(&req(n)).await.unwrap() }
+        }))
+        .await;
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        assert!(rs
+            .iter()
+            .all(|r| r.timings_ms.contains_key("rate_limit_wait")));
+        assert!(rs.iter().any(|r| r.timings_ms["rate_limit_wait"] >= 100));
+
+        let hit = fetcher.fetch(&req(0)).await.unwrap();
+        assert_eq!(hit.source, FetchSource::Cache);
+        assert!(!hit.timings_ms.contains_key("rate_limit_wait"));
+    }
+
+    #[tokio::test]
+    async fn concurrent_identical_fetches_share_one_network_request() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let hits = std::sync::Arc::new(AtomicUsize::new(0));
+        let h = hits.clone();
+        let app = Router::new().route(
+            "/slow",
+            get(move || {
+                let h = h.clone();
+                async move {
+                    h.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    "slow body"
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        // No cache dir: only single-flight can keep the followers off the network.
+        let fetcher = LocalFetcher::new(None).unwrap();
+        let req = FetchRequest {
+            url: format!("http://{addr}/slow"),
+            timeout_ms: Some(5_000),
+            max_bytes: Some(10_000),
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy {
+                read: true,
+                write: true,
+                ttl_s: None,
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
+            },
+        };
+        let all = futures_util::future::join_all((0..8).map(|_| fetcher.fetch(&req))).await;
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        for r in &all {
+            assert_eq!(r.as_ref().unwrap().bytes, b"slow body");
+        }
+        let waited = all
+            .iter()
+            .filter(|r| {
+                r.as_ref()
+                    .unwrap()
+                    .timings_ms
+                    .contains_key("single_flight_wait")
+            })
+            .count();
+        assert_eq!(waited, 7);
+
+        // The flight is gone once it finished, and a different key never joins it.
+        fetcher.fetch(&req).await.unwrap();
+        let mut other = req.clone();
+        other.max_bytes = Some(20_000);
+        fetcher.fetch(&other).await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn cache_honors_cache_control_when_opted_in() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let cc_route =
+            |cc: &'static str| get(move || async move { ([(header::CACHE_CONTROL, cc)], "body") });
+        let app = Router::new()
+            .route("/max-age", cc_route("public, max-age=600"))
+            .route("/expired", cc_route("max-age=0"))
+            .route("/no-store", cc_route("no-store"))
+            .route("/no-cache", cc_route("no-cache"))
+            .route("/plain", get(|| async { "body" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let tmp = tempfile::tempdir().unwrap();
+        let fetcher = LocalFetcher::new(Some(tmp.path().to_path_buf())).unwrap();
+        let req = |path: &str, ttl_s: Option<u64>| FetchRequest {
+            url: format!("http://{addr}/{path}"),
+            timeout_ms: Some(2_000),
+            max_bytes: Some(100_000),
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy {
+                read: true,
+                write: true,
+                ttl_s,
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
+            },
+        };
+        // Source of the second of two identical fetches.
+        let second = |r: FetchRequest| {
+            let fetcher = fetcher.clone();
+            async move {
+                let first = fetcher.fetch(&r).await.unwrap();
+                assert_eq!(first.source, FetchSource::Network, "{}", r.url);
+                fetcher.fetch(&r).await.unwrap().source
+            }
+        };
+
+        std::env::set_var("WEBPIPE_RESPECT_CACHE_CONTROL", "1");
+        assert_eq!(second(req("max-age", None)).await, FetchSource::Cache);
+        assert_eq!(second(req("expired", None)).await, FetchSource::Network);
+        assert_eq!(second(req("no-store", None)).await, FetchSource::Network);
+        assert!(fetcher
+            .cache
+            .as_ref()
+            .unwrap()
+            .get(&FetchRequest {
+                method: None,
+                body: None,
+                cache: FetchCachePolicy {
+                    read: true,
+                    write: false,
+                    ttl_s: Some(600),
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
+                },
+                ..req("no-store", None)
+            })
+            .unwrap()
+            .is_none());
+        assert_eq!(second(req("no-cache", None)).await, FetchSource::Network);
+        assert_eq!(second(req("plain", None)).await, FetchSource::Cache);
+        // An explicit ttl_s overrides the server's directives.
+        let explicit = FetchRequest {
+            max_bytes: Some(100_001),
+            ..req("no-store", Some(600))
+        };
+        assert_eq!(second(explicit).await, FetchSource::Cache);
+
+        // Off by default: directives are persisted but ignored.
+        std::env::remove_var("WEBPIPE_RESPECT_CACHE_CONTROL");
+        let r = FetchRequest {
+            max_bytes: Some(100_002),
+            ..req("no-cache", None)
+        };
+        assert_eq!(second(r).await, FetchSource::Cache);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn cache_status_separates_fresh_revalidated_and_stale() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        use axum::response::IntoResponse;
+        let full_bodies = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let served = full_bodies.clone();
+        let app = Router::new()
+            .route(
+                "/fresh",
+                get(|| async { ([(header::CACHE_CONTROL, "max-age=600")], "fresh body") }),
+            )
+            .route(
+                "/etag",
+                get(move |headers: axum::http::HeaderMap| {
+                    let served = served.clone();
+                    async move {
+                        if headers
+                            .get(header::IF_NONE_MATCH)
+                            .is_some_and(|v| v == "\"v1\"")
+                        {
+                            return (StatusCode::NOT_MODIFIED, [(header::ETAG, "\"v1\"")], "")
+                                .into_response();
+                        }
+                        served.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        (
+                            [
+                                (header::CACHE_CONTROL, "no-cache"),
+                                (header::ETAG, "\"v1\""),
+                            ],
+                            "etag body",
+                        )
+                            .into_response()
+                    }
+                }),
+            )
+            .route(
+                "/swr",
+                get(|| async {
+                    (
+                        [(
+                            header::CACHE_CONTROL,
+                            "max-age=0, stale-while-revalidate=600",
+                        )],
+                        "swr body",
+                    )
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
 
-        Ok(FetchResponse { timings_ms, ..out })
-    }
-}
+        std::env::set_var("WEBPIPE_RESPECT_CACHE_CONTROL", "1");
+        let tmp = tempfile::tempdir().unwrap();
+        let fetcher = LocalFetcher::new(Some(tmp.path().to_path_buf())).unwrap();
+        let req = |path: &str| FetchRequest {
+            url: format!("http://{addr}/{path}"),
+            timeout_ms: Some(2_000),
+            max_bytes: Some(100_000),
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy::default(),
+        };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use axum::{http::header, http::StatusCode, routing::get, Router};
-    use proptest::prelude::*;
-    use std::net::SocketAddr;
-    use std::path::Path;
-    use std::sync::Mutex;
-    use webpipe_core::FetchCachePolicy;
+        let first = fetcher.fetch(&req("fresh")).await.unwrap();
+        assert_eq!(first.cache_status, Some(CacheStatus::Miss));
+        let r = fetcher.fetch(&req("fresh")).await.unwrap();
+        assert_eq!(
+            (r.source, r.cache_status),
+            (FetchSource::Cache, Some(CacheStatus::Fresh))
+        );
 
-    // Env vars are process-global; serialize tests that mutate them.
-    static ENV_LOCK: Mutex<()> = Mutex::new(());
+        // no-cache + ETag: every reuse is a conditional request answered with 304.
+        fetcher.fetch(&req("etag")).await.unwrap();
+        let r = fetcher.fetch(&req("etag")).await.unwrap();
+        assert_eq!(
+            (r.source.clone(), r.cache_status),
+            (FetchSource::Cache, Some(CacheStatus::Revalidated))
+        );
+        assert_eq!((r.status, r.text_lossy().as_str()), (200, "etag body"));
+        assert_eq!(full_bodies.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Expired but within stale-while-revalidate: served from cache without waiting.
+        fetcher.fetch(&req("swr")).await.unwrap();
+        let r = fetcher.fetch(&req("swr")).await.unwrap();
+        assert_eq!(
+            (r.source.clone(), r.cache_status),
+            (FetchSource::Cache, Some(CacheStatus::Stale))
+        );
+        assert!(r.revalidating);
+        assert_eq!(r.text_lossy(), "swr body");
+
+        // Bypassing the cache read leaves the status unset.
+        let mut no_read = req("fresh");
+        no_read.cache.read = false;
+        assert_eq!(fetcher.fetch(&no_read).await.unwrap().cache_status, None);
+
+        std::env::remove_var("WEBPIPE_RESPECT_CACHE_CONTROL");
+        let counts = fetcher.cache_status_counts();
+        assert_eq!(counts["fresh"], 1);
+        assert_eq!(counts["revalidated"], 1);
+        assert_eq!(counts["stale"], 1);
+        assert_eq!(counts["miss"], 3);
+        fetcher.reset_cache_status_counts();
+        assert_eq!(fetcher.cache_status_counts()["miss"], 0);
+    }
 
     #[tokio::test]
-    async fn local_fetcher_hits_cache() {
+    async fn stale_while_revalidate_policy_serves_stale_and_refreshes_once() {
+        let hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let served = hits.clone();
         let app = Router::new().route(
             "/",
-            get(|| async { ([(header::CONTENT_TYPE, "text/plain")], "hello") }),
+            get(move || {
+                let served = served.clone();
+                async move {
+                    let n = served.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                    if n > 1 {
+                        // Keep the refresh in flight while further stale reads arrive.
+                        tokio::time::sleep(Duration::from_millis(300)).await;
+                    }
+                    format!("v{n}")
+                }
+            }),
         );
         let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
         let addr: SocketAddr = listener.local_addr().unwrap();
@@ -841,23 +3437,483 @@ mod tests {
 
         let tmp = tempfile::tempdir().unwrap();
         let fetcher = LocalFetcher::new(Some(tmp.path().to_path_buf())).unwrap();
+        let cache = FsCache::new(tmp.path().to_path_buf());
+        let req = FetchRequest {
+            url: format!("http://{addr}/"),
+            timeout_ms: Some(2_000),
+            max_bytes: Some(100_000),
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy {
+                ttl_s: Some(60),
+                stale_while_revalidate_s: Some(600),
+                ..Default::default()
+            },
+        };
+        // Backdate the cached entry by `age_s`.
+        let age_entry = |age_s: u64| {
+            let (meta_p, _) = cache.paths(&FsCache::key_for_fetch(&req));
+            let mut meta: serde_json::Value =
+                serde_json::from_slice(&fs::read(&meta_p).unwrap()).unwrap();
+            let at = meta["fetched_at_epoch_s"].as_u64().unwrap();
+            meta["fetched_at_epoch_s"] = serde_json::json!(at - age_s);
+            fs::write(&meta_p, serde_json::to_vec(&meta).unwrap()).unwrap();
+        };
+
+        let first = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(
+            (first.text_lossy().as_str(), first.revalidating),
+            ("v1", false)
+        );
+
+        // Past the TTL but inside the window: the stale body comes back at once, and two reads
+        // while the refresh is running start only one refresh.
+        age_entry(120);
+        for _ in 0..2 {
+            let t0 = std::time::Instant::now();
+            let r = fetcher.fetch(&req).await.unwrap();
+            assert!(t0.elapsed() < Duration::from_millis(250));
+            assert_eq!(
+                (r.source.clone(), r.cache_status, r.revalidating),
+                (FetchSource::Cache, Some(CacheStatus::Stale), true)
+            );
+            assert_eq!(r.text_lossy(), "v1");
+        }
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        let refreshed = loop {
+            let r = fetcher.fetch(&req).await.unwrap();
+            if r.cache_status == Some(CacheStatus::Fresh) || std::time::Instant::now() > deadline {
+                break r;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        };
+        assert_eq!(refreshed.text_lossy(), "v2");
+        assert!(!refreshed.revalidating);
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        // Past the window: a normal (blocking) network fetch.
+        age_entry(10_000);
+        let r = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(
+            (r.source.clone(), r.revalidating),
+            (FetchSource::Network, false)
+        );
+        assert_eq!(r.text_lossy(), "v3");
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn ttl_expiry_revalidates_and_full_replacements_rewrite_validators() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        use axum::response::IntoResponse;
+        type Seen = std::sync::Arc<std::sync::Mutex<Vec<(Option<String>, Option<String>)>>>;
+        let version = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(1));
+        let seen: Seen = Default::default();
+        let (v, s) = (version.clone(), seen.clone());
+        let app = Router::new().route(
+            "/doc",
+            get(move |headers: axum::http::HeaderMap| {
+                let (v, s) = (v.clone(), s.clone());
+                async move {
+                    let h = |n: header::HeaderName| {
+                        headers
+                            .get(n)
+                            .and_then(|v| v.to_str().ok())
+                            .map(str::to_string)
+                    };
+                    let (inm, ims) = (h(header::IF_NONE_MATCH), h(header::IF_MODIFIED_SINCE));
+                    s.lock().unwrap().push((inm.clone(), ims));
+                    let n = v.load(std::sync::atomic::Ordering::SeqCst);
+                    let etag = format!("\"v{n}\"");
+                    if inm.as_deref() == Some(etag.as_str()) {
+                        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+                    }
+                    (
+                        [
+                            (header::ETAG, etag),
+                            (
+                                header::LAST_MODIFIED,
+                                format!("Mon, 0{n} Jan 2024 00:00:00 GMT"),
+                            ),
+                        ],
+                        format!("body v{n}"),
+                    )
+                        .into_response()
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
 
+        std::env::remove_var("WEBPIPE_RESPECT_CACHE_CONTROL");
+        let tmp = tempfile::tempdir().unwrap();
+        let fetcher = LocalFetcher::new(Some(tmp.path().to_path_buf())).unwrap();
         let req = FetchRequest {
-            url: format!("http://{}/", addr),
+            url: format!("http://{addr}/doc"),
             timeout_ms: Some(2_000),
-            max_bytes: Some(1_000_000),
+            max_bytes: Some(100_000),
             headers: BTreeMap::new(),
+            method: None,
+            body: None,
             cache: FetchCachePolicy {
                 read: true,
                 write: true,
                 ttl_s: Some(60),
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
             },
         };
+        let cache = fetcher.cache.as_ref().unwrap();
+        let (meta_p, _) = cache.paths(&FsCache::key_for_fetch_v2(&req, &[]));
+        let meta = || -> serde_json::Value {
+            serde_json::from_slice(&std::fs::read(&meta_p).unwrap()).unwrap()
+        };
+        // Age the entry past ttl_s.
+        let expire = || {
+            let mut m = meta();
+            m["fetched_at_epoch_s"] = serde_json::json!(0);
+            std::fs::write(&meta_p, serde_json::to_vec(&m).unwrap()).unwrap();
+        };
 
-        let r1 = fetcher.fetch(&req).await.unwrap();
-        assert_eq!(r1.source, FetchSource::Network);
-        let r2 = fetcher.fetch(&req).await.unwrap();
-        assert_eq!(r2.source, FetchSource::Cache);
+        assert_eq!(
+            fetcher.fetch(&req).await.unwrap().source,
+            FetchSource::Network
+        );
+        assert_eq!(
+            fetcher.fetch(&req).await.unwrap().source,
+            FetchSource::Cache
+        );
+        assert_eq!(seen.lock().unwrap().len(), 1, "fresh hit stays offline");
+
+        // Expired + unchanged: conditional request, 304, cached body, restarted age.
+        expire();
+        let r = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(
+            (r.source.clone(), r.cache_status),
+            (FetchSource::Cache, Some(CacheStatus::Revalidated))
+        );
+        assert_eq!(r.text_lossy(), "body v1");
+        assert_eq!(
+            seen.lock().unwrap()[1],
+            (
+                Some("\"v1\"".to_string()),
+                Some("Mon, 01 Jan 2024 00:00:00 GMT".to_string())
+            )
+        );
+        assert!(meta()["fetched_at_epoch_s"].as_u64().unwrap() > 0);
+
+        // Expired + changed: the 200 replaces the entry and its validators.
+        version.store(2, std::sync::atomic::Ordering::SeqCst);
+        expire();
+        let r = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(r.source, FetchSource::Network);
+        assert_eq!((r.status, r.text_lossy().as_str()), (200, "body v2"));
+        assert_eq!(meta()["headers"]["etag"].as_str(), Some("\"v2\""));
+        // Conditional headers belong to the request only: not stored, not in the cache key.
+        assert!(!meta()
+            .to_string()
+            .to_ascii_lowercase()
+            .contains("if-none-match"));
+
+        expire();
+        let r = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(r.cache_status, Some(CacheStatus::Revalidated));
+        assert_eq!(r.text_lossy(), "body v2");
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 4);
+        assert_eq!(seen[3].0.as_deref(), Some("\"v2\""));
+        assert_eq!(seen[3].1.as_deref(), Some("Mon, 02 Jan 2024 00:00:00 GMT"));
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn if_range_revalidation_reuses_large_bodies_and_falls_back_when_ignored() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        use axum::extract::{Path, State};
+        use axum::response::IntoResponse;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::{Arc, Mutex};
+
+        /// (Range, If-Range, Accept-Encoding) of one request.
+        type Seen = (Option<String>, Option<String>, Option<String>);
+        #[derive(Clone, Default)]
+        struct Srv {
+            version: Arc<AtomicUsize>,
+            seen: Arc<Mutex<Vec<Seen>>>,
+        }
+        // Version 1 is 4000 bytes; later versions append 500 more.
+        fn body(v: usize) -> Vec<u8> {
+            let mut b = vec![b'a'; 4_000];
+            if v > 1 {
+                b.extend(vec![b'b'; 500]);
+            }
+            b
+        }
+        async fn route(
+            State(srv): State<Srv>,
+            Path(kind): Path<String>,
+            headers: axum::http::HeaderMap,
+        ) -> axum::response::Response {
+            let h = |n: header::HeaderName| {
+                headers
+                    .get(n)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|s| s.to_string())
+            };
+            srv.seen.lock().unwrap().push((
+                h(header::RANGE),
+                h(header::IF_RANGE),
+                h(header::ACCEPT_ENCODING),
+            ));
+            let v = srv.version.load(Ordering::SeqCst);
+            let etag = match kind.as_str() {
+                // `append` keeps its ETag while growing (and ignores If-None-Match).
+                "append" => "\"a\"".to_string(),
+                _ => format!("\"{kind}{v}\""),
+            };
+            let full = body(v);
+            if kind == "doc" && h(header::IF_NONE_MATCH).as_deref() == Some(etag.as_str()) {
+                return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+            }
+            // `ignores` serves ranges without checking If-Range.
+            let range_ok = kind == "ignores" || h(header::IF_RANGE).as_deref() == Some(&etag);
+            let start = h(header::RANGE).and_then(|r| {
+                r.strip_prefix("bytes=")?
+                    .strip_suffix('-')?
+                    .parse::<usize>()
+                    .ok()
+            });
+            let last_modified = format!("Mon, 0{v} Jan 2024 00:00:00 GMT");
+            if let (true, Some(start)) = (range_ok, start.filter(|s| *s < full.len())) {
+                let cr = format!("bytes {start}-{}/{}", full.len() - 1, full.len());
+                return (
+                    StatusCode::PARTIAL_CONTENT,
+                    [
+                        (header::ETAG, etag),
+                        (header::CONTENT_RANGE, cr),
+                        (header::CACHE_CONTROL, "no-cache".to_string()),
+                        (header::LAST_MODIFIED, last_modified),
+                    ],
+                    full[start..].to_vec(),
+                )
+                    .into_response();
+            }
+            (
+                [
+                    (header::ETAG, etag),
+                    (header::CACHE_CONTROL, "no-cache".to_string()),
+                    (header::LAST_MODIFIED, last_modified),
+                ],
+                full,
+            )
+                .into_response()
+        }
+
+        let srv = Srv::default();
+        srv.version.store(1, Ordering::SeqCst);
+        let app = Router::new()
+            .route("/:kind", get(route))
+            .with_state(srv.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        std::env::set_var("WEBPIPE_RESPECT_CACHE_CONTROL", "1");
+        std::env::set_var("WEBPIPE_CACHE_IF_RANGE_MIN_BYTES", "1000");
+        let tmp = tempfile::tempdir().unwrap();
+        let fetcher = LocalFetcher::new(Some(tmp.path().to_path_buf())).unwrap();
+        let req = |kind: &str| FetchRequest {
+            url: format!("http://{addr}/{kind}"),
+            timeout_ms: Some(2_000),
+            max_bytes: Some(100_000),
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy::default(),
+        };
+        let last_seen = || srv.seen.lock().unwrap().last().cloned().unwrap();
+
+        for kind in ["doc", "append", "ignores"] {
+            let r = fetcher.fetch(&req(kind)).await.unwrap();
+            assert_eq!(r.bytes, body(1), "{kind}");
+            assert_eq!(last_seen().0, None, "first fetch is not ranged");
+        }
+
+        // Unchanged: the ranged conditional request is answered with 304.
+        let r = fetcher.fetch(&req("doc")).await.unwrap();
+        assert_eq!(r.cache_status, Some(CacheStatus::Revalidated));
+        assert_eq!(r.bytes, body(1));
+        assert_eq!(
+            last_seen(),
+            (
+                Some("bytes=4000-".to_string()),
+                Some("\"doc1\"".to_string()),
+                Some("identity".to_string())
+            )
+        );
+
+        srv.version.store(2, Ordering::SeqCst);
+
+        // Changed: If-Range no longer matches, so the full new body comes back.
+        let r = fetcher.fetch(&req("doc")).await.unwrap();
+        assert_eq!(r.cache_status, Some(CacheStatus::Miss));
+        assert_eq!(r.bytes, body(2));
+        assert!(!r.timings_ms.contains_key("if_range_ignored"));
+
+        // Same ETag, grown body: only the appended range is transferred.
+        let r = fetcher.fetch(&req("append")).await.unwrap();
+        assert_eq!(r.cache_status, Some(CacheStatus::Revalidated));
+        assert_eq!(r.bytes, body(2));
+        assert_eq!(r.wire_bytes, 500);
+        assert_eq!(r.timings_ms.get("if_range_partial"), Some(&500));
+        // The stored headers describe the whole body, with the 206's validators.
+        let cache = FsCache::new(tmp.path().to_path_buf());
+        let (meta_p, _) = cache.paths(&FsCache::key_for_fetch(&req("append")));
+        let meta: serde_json::Value = serde_json::from_slice(&fs::read(&meta_p).unwrap()).unwrap();
+        let stored: BTreeMap<String, String> =
+            serde_json::from_value(meta["headers"].clone()).unwrap();
+        for h in [&r.headers, &stored] {
+            assert_eq!(
+                h.get("last-modified").map(String::as_str),
+                Some("Mon, 02 Jan 2024 00:00:00 GMT")
+            );
+            assert_eq!(h.get("content-length").map(String::as_str), Some("4500"));
+            assert!(!h.contains_key("content-range"));
+        }
+
+        // A server that ignores If-Range returns a range of a changed body: refetch in full.
+        let n = srv.seen.lock().unwrap().len();
+        let r = fetcher.fetch(&req("ignores")).await.unwrap();
+        assert_eq!(r.bytes, body(2));
+        assert!(r.timings_ms.contains_key("if_range_ignored"));
+        let seen = srv.seen.lock().unwrap()[n..].to_vec();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[1].0, None, "fallback fetch is not ranged");
+
+        std::env::remove_var("WEBPIPE_CACHE_IF_RANGE_MIN_BYTES");
+        std::env::remove_var("WEBPIPE_RESPECT_CACHE_CONTROL");
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn open_ended_bodies_are_capped_and_flagged_streaming_capped() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        std::env::set_var("WEBPIPE_STREAMING_MAX_BYTES", "4096");
+        std::env::set_var("WEBPIPE_STREAMING_MAX_MS", "300");
+
+        // Endless bodies: `every` between chunks, never finishing.
+        fn endless(chunk: &'static str, every: Duration) -> axum::body::Body {
+            axum::body::Body::from_stream(futures_util::stream::unfold((), move |()| async move {
+                tokio::time::sleep(every).await;
+                Some((Ok::<_, std::io::Error>(chunk), ()))
+            }))
+        }
+        let app = Router::new()
+            .route(
+                "/sse",
+                get(|| async {
+                    (
+                        [(header::CONTENT_TYPE, "text/event-stream")],
+                        endless(
+                            "data: tick tick tick tick tick tick\n\n",
+                            Duration::from_millis(1),
+                        ),
+                    )
+                }),
+            )
+            .route(
+                "/trickle",
+                get(|| async {
+                    (
+                        [(header::CONTENT_TYPE, "application/x-ndjson")],
+                        endless("{}\n", Duration::from_millis(50)),
+                    )
+                }),
+            )
+            .route(
+                "/slow-html",
+                get(|| async {
+                    // Chunked (no Content-Length) and slower than the cap, but finite.
+                    let chunks = futures_util::stream::unfold(0, |i| async move {
+                        if i == 8 {
+                            return None;
+                        }
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        Some((Ok::<_, std::io::Error>(format!("<p>part {i}</p>")), i + 1))
+                    });
+                    (
+                        [(header::CONTENT_TYPE, "text/html")],
+                        axum::body::Body::from_stream(chunks),
+                    )
+                }),
+            )
+            .route("/plain", get(|| async { "complete body" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let fetcher = LocalFetcher::new(None).unwrap();
+        let fetch = |path: &str| {
+            let fetcher = fetcher.clone();
+            let req = FetchRequest {
+                url: format!("http://{addr}/{path}"),
+                timeout_ms: Some(20_000),
+                max_bytes: Some(10_000_000),
+                headers: BTreeMap::new(),
+                method: None,
+                body: None,
+                cache: FetchCachePolicy {
+                    read: false,
+                    write: false,
+                    ttl_s: None,
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
+                },
+            };
+            async move {
+                let t0 = std::time::Instant::now();
+                let r = fetcher.fetch(&req).await.expect("fetch");
+                (r, t0.elapsed())
+            }
+        };
+
+        let (sse, took) = fetch("sse").await;
+        assert_eq!(sse.bytes.len(), 4096);
+        assert!(sse.truncated);
+        assert!(sse.timings_ms.contains_key("streaming_capped"));
+        assert!(took < Duration::from_secs(3), "{took:?}");
+
+        let (trickle, took) = fetch("trickle").await;
+        assert!(!trickle.bytes.is_empty());
+        assert!(trickle.bytes.len() < 4096);
+        assert!(trickle.truncated);
+        assert!(trickle.timings_ms.contains_key("streaming_capped"));
+        assert!(took < Duration::from_secs(3), "{took:?}");
+
+        // Not a streaming type: a slow chunked page is read to the end.
+        let (html, took) = fetch("slow-html").await;
+        assert!(took > Duration::from_millis(300), "{took:?}");
+        assert!(!html.headers.contains_key("content-length"));
+        let want: String = (0..8).map(|i| format!("<p>part {i}</p>")).collect();
+        assert_eq!(html.text_lossy(), want);
+        assert!(!html.truncated);
+        assert!(!html.timings_ms.contains_key("streaming_capped"));
+
+        let (plain, _) = fetch("plain").await;
+        assert_eq!(plain.bytes, b"complete body");
+        assert!(!plain.truncated);
+        assert!(!plain.timings_ms.contains_key("streaming_capped"));
+
+        std::env::remove_var("WEBPIPE_STREAMING_MAX_BYTES");
+        std::env::remove_var("WEBPIPE_STREAMING_MAX_MS");
     }
 
     #[tokio::test]
@@ -916,10 +3972,14 @@ mod tests {
             timeout_ms: Some(2_000),
             max_bytes: Some(100_000),
             headers: hdrs,
+            method: None,
+            body: None,
             cache: FetchCachePolicy {
                 read: false,
                 write: false,
                 ttl_s: None,
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
             },
         };
 
@@ -933,6 +3993,78 @@ mod tests {
         assert!(body.contains("ok accept-language=en-US"));
     }
 
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn local_fetcher_merges_default_headers_under_request_headers() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        std::env::remove_var("WEBPIPE_ALLOW_UNSAFE_HEADERS");
+        std::env::set_var(
+            "WEBPIPE_DEFAULT_HEADERS",
+            r#"{"DNT":"1","Accept-Language":"de-DE","Cookie":"session=secret","X-Num":7}"#,
+        );
+
+        let app = Router::new().route(
+            "/",
+            get(|headers: axum::http::HeaderMap| async move {
+                let h = |n: &str| {
+                    headers
+                        .get(n)
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or("-")
+                        .to_string()
+                };
+                format!(
+                    "dnt={} lang={} cookie={} num={}",
+                    h("dnt"),
+                    h("accept-language"),
+                    h("cookie"),
+                    h("x-num")
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let tmp = tempfile::tempdir().unwrap();
+        let fetcher = LocalFetcher::new(Some(tmp.path().to_path_buf())).unwrap();
+        std::env::remove_var("WEBPIPE_DEFAULT_HEADERS");
+        let req = |headers: BTreeMap<String, String>| FetchRequest {
+            url: format!("http://{}/", addr),
+            timeout_ms: Some(2_000),
+            max_bytes: Some(100_000),
+            headers,
+            method: None,
+            body: None,
+            cache: FetchCachePolicy {
+                read: true,
+                write: true,
+                ttl_s: None,
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
+            },
+        };
+
+        let plain = req(BTreeMap::new());
+        let resp = fetcher.fetch(&plain).await.unwrap();
+        assert_eq!(resp.text_lossy(), "dnt=1 lang=de-DE cookie=- num=-");
+
+        let mut hdrs = BTreeMap::new();
+        hdrs.insert("accept-language".to_string(), "en-US".to_string());
+        let resp = fetcher.fetch(&req(hdrs)).await.unwrap();
+        assert_eq!(resp.text_lossy(), "dnt=1 lang=en-US cookie=- num=-");
+
+        // Defaults are part of the cache key: the fetcher finds its own entry, while the bare
+        // request (as a fetcher without defaults would key it) misses.
+        assert!(fetcher.cache_get(&plain).unwrap().is_some());
+        assert!(FsCache::new(tmp.path().to_path_buf())
+            .get(&plain)
+            .unwrap()
+            .is_none());
+    }
+
     #[tokio::test]
     #[allow(clippy::await_holding_lock)]
     async fn local_fetcher_can_forward_sensitive_headers_when_explicitly_allowed() {
@@ -963,24 +4095,105 @@ mod tests {
         let mut hdrs = BTreeMap::new();
         hdrs.insert("authorization".to_string(), "Bearer ok".to_string()); // lower-case to test normalization
 
-        let req = FetchRequest {
-            url: format!("http://{}/", addr),
+        let req = FetchRequest {
+            url: format!("http://{}/", addr),
+            timeout_ms: Some(2_000),
+            max_bytes: Some(100_000),
+            headers: hdrs,
+            method: None,
+            body: None,
+            cache: FetchCachePolicy {
+                read: false,
+                write: false,
+                ttl_s: None,
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
+            },
+        };
+
+        let resp = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(resp.status, 200);
+        assert!(resp.text_lossy().contains("auth=Bearer ok"));
+
+        // Clean up to avoid leaking env state across tests.
+        std::env::remove_var("WEBPIPE_ALLOW_UNSAFE_HEADERS");
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn user_agent_pool_is_deterministic_by_url_when_caching() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let app = Router::new().route(
+            "/:page",
+            get(|headers: axum::http::HeaderMap| async move {
+                let ua = headers
+                    .get(header::USER_AGENT)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or("")
+                    .to_string();
+                ([(header::CONTENT_TYPE, "text/plain")], ua)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        std::env::set_var("WEBPIPE_USER_AGENTS", "ua-alpha|ua-beta\nua-gamma\n");
+        let tmp = tempfile::tempdir().unwrap();
+        let fetcher = LocalFetcher::new(Some(tmp.path().to_path_buf())).unwrap();
+        let fetcher2 = LocalFetcher::new(Some(tmp.path().to_path_buf())).unwrap();
+        let no_cache = LocalFetcher::new(None).unwrap();
+        std::env::remove_var("WEBPIPE_USER_AGENTS");
+        let pool = ["ua-alpha", "ua-beta", "ua-gamma"];
+
+        let req_for = |page: usize| FetchRequest {
+            url: format!("http://{addr}/p{page}"),
             timeout_ms: Some(2_000),
-            max_bytes: Some(100_000),
-            headers: hdrs,
+            max_bytes: Some(10_000),
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
             cache: FetchCachePolicy {
                 read: false,
-                write: false,
-                ttl_s: None,
+                write: true,
+                ttl_s: Some(60),
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
             },
         };
 
-        let resp = fetcher.fetch(&req).await.unwrap();
-        assert_eq!(resp.status, 200);
-        assert!(resp.text_lossy().contains("auth=Bearer ok"));
+        let mut seen = std::collections::BTreeSet::new();
+        for page in 0..12 {
+            let req = req_for(page);
+            let ua = fetcher.user_agent_for(&req).to_string();
+            assert!(pool.contains(&ua.as_str()), "ua={ua}");
+            // Same URL => same UA, across calls and fetcher instances.
+            assert_eq!(fetcher.user_agent_for(&req), ua);
+            assert_eq!(fetcher2.user_agent_for(&req), ua);
+            let r = fetcher.fetch(&req).await.unwrap();
+            assert_eq!(String::from_utf8_lossy(&r.bytes), ua);
+            seen.insert(ua);
+        }
+        assert!(seen.len() >= 2, "seen={seen:?}");
 
-        // Clean up to avoid leaking env state across tests.
-        std::env::remove_var("WEBPIPE_ALLOW_UNSAFE_HEADERS");
+        // The UA does not leak into the cache key.
+        let mut req = req_for(0);
+        req.cache.read = true;
+        let r = fetcher2.fetch(&req).await.unwrap();
+        assert_eq!(r.source, FetchSource::Cache);
+
+        // Without caching the pool is walked round-robin.
+        let picks: Vec<String> = (0..4)
+            .map(|_| no_cache.user_agent_for(&req_for(0)).to_string())
+            .collect();
+        assert_eq!(picks, ["ua-alpha", "ua-beta", "ua-gamma", "ua-alpha"]);
+
+        // Unset => the single default UA.
+        let default = LocalFetcher::new(None).unwrap();
+        assert_eq!(default.user_agent_for(&req_for(3)), DEFAULT_USER_AGENT);
     }
 
     #[tokio::test]
@@ -1006,10 +4219,14 @@ mod tests {
             timeout_ms: Some(2_000),
             max_bytes: Some(100_000),
             headers: BTreeMap::new(),
+            method: None,
+            body: None,
             cache: FetchCachePolicy {
                 read: true,
                 write: true,
                 ttl_s: Some(60),
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
             },
         };
 
@@ -1102,10 +4319,14 @@ mod tests {
             timeout_ms: Some(2_000),
             max_bytes: Some(100_000),
             headers: BTreeMap::new(),
+            method: None,
+            body: None,
             cache: FetchCachePolicy {
                 read: true,
                 write: true,
                 ttl_s: Some(60),
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
             },
         };
 
@@ -1133,6 +4354,243 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn local_fetcher_reports_wire_and_decoded_bytes_for_gzip() {
+        use std::io::Write;
+        let plain = "compressible paragraph text ".repeat(2_000);
+        let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        enc.write_all(plain.as_bytes()).unwrap();
+        let gz = enc.finish().unwrap();
+        let gz_len = gz.len() as u64;
+
+        let app = Router::new().route(
+            "/",
+            get(move || {
+                let gz = gz.clone();
+                async move {
+                    (
+                        [
+                            (header::CONTENT_TYPE, "text/plain"),
+                            (header::CONTENT_ENCODING, "gzip"),
+                        ],
+                        gz,
+                    )
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let tmp = tempfile::tempdir().unwrap();
+        let fetcher = LocalFetcher::new(Some(tmp.path().to_path_buf())).unwrap();
+        let req = FetchRequest {
+            url: format!("http://{}/", addr),
+            timeout_ms: Some(2_000),
+            max_bytes: Some(1_000_000),
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy {
+                read: true,
+                write: true,
+                ttl_s: Some(60),
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
+            },
+        };
+
+        let r1 = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(r1.source, FetchSource::Network);
+        assert_eq!(r1.bytes, plain.as_bytes());
+        assert_eq!(r1.wire_bytes, gz_len);
+        assert!(r1.wire_bytes < r1.bytes.len() as u64);
+        assert!(!r1.headers.contains_key("content-encoding"));
+
+        let mut files = Vec::new();
+        collect_files_recursively(tmp.path(), &mut files);
+        let meta_p = files
+            .into_iter()
+            .find(|p| p.extension().and_then(|s| s.to_str()) == Some("json"))
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_slice(&std::fs::read(meta_p).unwrap()).unwrap();
+        assert_eq!(v["wire_bytes"].as_u64(), Some(gz_len));
+        assert_eq!(v["decoded_bytes"].as_u64(), Some(plain.len() as u64));
+
+        let r2 = fetcher.fetch(&req).await.unwrap();
+        assert_eq!(r2.source, FetchSource::Cache);
+        assert_eq!(r2.wire_bytes, gz_len);
+
+        // max_bytes caps the decoded size, not the wire size.
+        let req_small = FetchRequest {
+            max_bytes: Some(1_000),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy {
+                read: false,
+                write: false,
+                ttl_s: None,
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
+            },
+            ..req
+        };
+        let r3 = fetcher.fetch(&req_small).await.unwrap();
+        assert!(r3.truncated);
+        assert_eq!(r3.bytes.len(), 1_000);
+        assert!(r3.wire_bytes <= gz_len);
+    }
+
+    #[tokio::test]
+    async fn brotli_and_zstd_bodies_are_decoded_before_extraction() {
+        let para = "Brotli and zstd encoded pages should extract as readable prose. ";
+        let html = format!(
+            "<html><head><title>Encoded</title></head><body><article><p>{}</p></article></body></html>",
+            para.repeat(200)
+        );
+        let mut br = Vec::new();
+        brotli::BrotliCompress(
+            &mut html.as_bytes(),
+            &mut br,
+            &brotli::enc::BrotliEncoderParams::default(),
+        )
+        .unwrap();
+        let zst = zstd::bulk::compress(html.as_bytes(), 3).unwrap();
+        let route = |ce: &'static str, body: Vec<u8>| {
+            get(move || {
+                let body = body.clone();
+                async move {
+                    (
+                        [
+                            (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+                            (header::CONTENT_ENCODING, ce),
+                        ],
+                        body,
+                    )
+                }
+            })
+        };
+        let app = Router::new()
+            .route("/br", route("br", br.clone()))
+            .route("/zstd", route("zstd", zst.clone()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let fetcher = LocalFetcher::new(None).unwrap();
+        for (path, wire) in [("br", br.len()), ("zstd", zst.len())] {
+            let req = |max_bytes: u64| FetchRequest {
+                url: format!("http://{addr}/{path}"),
+                timeout_ms: Some(2_000),
+                max_bytes: Some(max_bytes),
+                headers: BTreeMap::new(),
+                method: None,
+                body: None,
+                cache: FetchCachePolicy::default(),
+            };
+            let r = fetcher.fetch(&req(1_000_000)).await.unwrap();
+            assert_eq!(r.bytes, html.as_bytes(), "{path}");
+            assert_eq!(r.wire_bytes, wire as u64, "{path}");
+            assert!(!r.truncated, "{path}");
+            assert!(!r.headers.contains_key("content-encoding"), "{path}");
+            let ex = extract::best_effort_text_from_bytes(
+                &r.bytes,
+                r.content_type.as_deref(),
+                &r.final_url,
+                100,
+                10_000,
+            );
+            assert!(
+                ex.text.contains("should extract as readable prose"),
+                "{path}"
+            );
+            assert!(!ex.warnings.contains(&"empty_extraction"), "{path}");
+
+            // max_bytes applies to the decoded size.
+            let r = fetcher.fetch(&req(2_000)).await.unwrap();
+            assert!(r.truncated, "{path}");
+            assert_eq!(r.bytes.len(), 2_000, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn local_fetcher_falls_back_on_mislabelled_encoding_and_caps_bombs() {
+        use std::io::Write;
+        let plain = "<html><body>plain body labelled gzip</body></html>";
+        let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        enc.write_all(&vec![b'a'; 8 * 1024 * 1024]).unwrap();
+        let bomb = enc.finish().unwrap();
+        let bomb_len = bomb.len() as u64;
+
+        let app = Router::new()
+            .route(
+                "/mislabelled",
+                get(move || async move {
+                    (
+                        [
+                            (header::CONTENT_TYPE, "text/html"),
+                            (header::CONTENT_ENCODING, "gzip"),
+                        ],
+                        plain,
+                    )
+                }),
+            )
+            .route(
+                "/bomb",
+                get(move || {
+                    let bomb = bomb.clone();
+                    async move {
+                        (
+                            [
+                                (header::CONTENT_TYPE, "text/plain"),
+                                (header::CONTENT_ENCODING, "gzip"),
+                            ],
+                            bomb,
+                        )
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let fetcher = LocalFetcher::new(None).unwrap();
+        let req = |path: &str| FetchRequest {
+            url: format!("http://{addr}{path}"),
+            timeout_ms: Some(2_000),
+            max_bytes: Some(50_000),
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy {
+                read: false,
+                write: false,
+                ttl_s: None,
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
+            },
+        };
+
+        let r = fetcher.fetch(&req("/mislabelled")).await.unwrap();
+        assert_eq!(r.status, 200);
+        assert_eq!(r.bytes, plain.as_bytes());
+        assert!(!r.truncated);
+        assert!(r.timings_ms.contains_key("decode_failed"));
+
+        let r = fetcher.fetch(&req("/bomb")).await.unwrap();
+        assert!(r.truncated);
+        assert_eq!(r.bytes.len(), 50_000);
+        assert!(r.bytes.iter().all(|b| *b == b'a'));
+        assert!(r.wire_bytes < bomb_len);
+        assert!(!r.timings_ms.contains_key("decode_failed"));
+    }
+
     #[test]
     fn cache_key_v2_distinguishes_none_from_zero() {
         let base = FetchRequest {
@@ -1140,10 +4598,14 @@ mod tests {
             timeout_ms: None,
             max_bytes: None,
             headers: BTreeMap::new(),
+            method: None,
+            body: None,
             cache: FetchCachePolicy {
                 read: true,
                 write: true,
                 ttl_s: None,
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
             },
         };
         let mut none = base.clone();
@@ -1151,8 +4613,8 @@ mod tests {
         let mut zero = base.clone();
         zero.max_bytes = Some(0);
 
-        let k2_none = FsCache::key_for_fetch_v2(&none);
-        let k2_zero = FsCache::key_for_fetch_v2(&zero);
+        let k2_none = FsCache::key_for_fetch_v2(&none, &[]);
+        let k2_zero = FsCache::key_for_fetch_v2(&zero, &[]);
         assert_ne!(k2_none, k2_zero, "v2 must distinguish None vs Some(0)");
 
         let k1_none = FsCache::key_for_fetch_legacy_v1(&none);
@@ -1173,10 +4635,14 @@ mod tests {
             timeout_ms: None,
             max_bytes: None, // legacy collision case
             headers: BTreeMap::new(),
+            method: None,
+            body: None,
             cache: FetchCachePolicy {
                 read: true,
                 write: true, // enable migration
                 ttl_s: None,
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
             },
         };
 
@@ -1201,12 +4667,532 @@ mod tests {
         assert_eq!(got.bytes, b"hello");
 
         // The read should migrate into v2 key space.
-        let v2_key = FsCache::key_for_fetch_v2(&req);
+        let v2_key = FsCache::key_for_fetch_v2(&req, &[]);
         let (meta2_p, body2_p) = cache.paths(&v2_key);
         assert!(meta2_p.exists(), "expected v2 meta to be written");
         assert!(body2_p.exists(), "expected v2 body to be written");
     }
 
+    #[test]
+    fn export_writes_one_sanitized_file_per_url() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = FsCache::new(tmp.path().join("cache"));
+        let put = |key: &str, url: Option<&str>, body: Option<&[u8]>| {
+            let (meta_p, body_p) = cache.paths(key);
+            std::fs::create_dir_all(meta_p.parent().unwrap()).unwrap();
+            let meta = serde_json::json!({ "schema_version": 1, "url": url, "status": 200 });
+            std::fs::write(&meta_p, serde_json::to_vec(&meta).unwrap()).unwrap();
+            if let Some(b) = body {
+                std::fs::write(&body_p, b).unwrap();
+            }
+        };
+        put(
+            &"a".repeat(64),
+            Some("https://example.com/docs/CON?x=1"),
+            Some(b"one"),
+        );
+        // Same URL under another key (e.g. another max_bytes): exported once.
+        put(
+            &"b".repeat(64),
+            Some("https://example.com/docs/CON?x=1"),
+            Some(b"dup"),
+        );
+        put(&"c".repeat(64), Some("https://example.com/"), Some(b"root"));
+        put(&"d".repeat(64), None, Some(b"no url"));
+        put(&"e".repeat(64), Some("https://example.com/gone"), None);
+
+        let out = tmp.path().join("out");
+        let rep = cache.export(&out, 100);
+        assert_eq!(
+            rep,
+            CacheExportReport {
+                scanned: 5,
+                exported: 2,
+                duplicates: 1,
+                skipped: 2,
+                truncated: false,
+            }
+        );
+        let page = out.join(fs_path::sanitize_for_fs("https://example.com/docs/CON?x=1"));
+        assert!(page.starts_with(out.join("example.com").join("docs")));
+        assert_eq!(std::fs::read(&page).unwrap(), b"one");
+        let root = out.join(fs_path::sanitize_for_fs("https://example.com/"));
+        assert_eq!(std::fs::read(root).unwrap(), b"root");
+
+        let rep = cache.export(&tmp.path().join("out2"), 2);
+        assert!(rep.truncated);
+        assert_eq!(rep.scanned, 2);
+    }
+
+    #[test]
+    fn migrate_all_rekeys_legacy_v1_entries_in_bulk() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = FsCache::new(tmp.path().to_path_buf());
+        let req_for = |url: &str| FetchRequest {
+            url: url.to_string(),
+            timeout_ms: None,
+            max_bytes: None,
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy {
+                read: true,
+                write: true,
+                ttl_s: None,
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
+            },
+        };
+        let write_v1 = |url: &str, meta: serde_json::Value| {
+            let key = FsCache::key_for_fetch_legacy_v1(&req_for(url));
+            let (meta_p, body_p) = cache.paths(&key);
+            std::fs::create_dir_all(meta_p.parent().unwrap()).unwrap();
+            std::fs::write(&body_p, url.as_bytes()).unwrap();
+            std::fs::write(&meta_p, serde_json::to_vec(&meta).unwrap()).unwrap();
+            key
+        };
+
+        let urls = [
+            "https://a.example/",
+            "https://b.example/x",
+            "https://c.example/y",
+        ];
+        let mut legacy_keys = Vec::new();
+        for u in urls {
+            legacy_keys.push(write_v1(
+                u,
+                serde_json::json!({"schema_version": 1, "url": u, "status": 200}),
+            ));
+        }
+        // Can't be re-keyed: no url in meta.
+        write_v1("https://d.example/", serde_json::json!({"status": 200}));
+        // Nor can a v1 entry keyed with request headers, which its meta doesn't record.
+        let mut with_headers = req_for("https://f.example/");
+        with_headers.headers = BTreeMap::from([("Accept".to_string(), "text/html".to_string())]);
+        let (meta_p, body_p) = cache.paths(&FsCache::key_for_fetch_legacy_v1(&with_headers));
+        std::fs::create_dir_all(meta_p.parent().unwrap()).unwrap();
+        std::fs::write(&body_p, b"f").unwrap();
+        let meta = serde_json::json!({"schema_version": 1, "url": with_headers.url, "status": 200});
+        std::fs::write(&meta_p, serde_json::to_vec(&meta).unwrap()).unwrap();
+        // A normal v2 entry is left alone.
+        let mut v2_req = req_for("https://e.example/");
+        v2_req.max_bytes = Some(1_000);
+        cache
+            .put(
+                &v2_req,
+                &FetchResponse {
+                    url: v2_req.url.clone(),
+                    final_url: v2_req.url.clone(),
+                    status: 200,
+                    content_type: None,
+                    headers: BTreeMap::new(),
+                    bytes: b"e".to_vec(),
+                    wire_bytes: 1,
+                    truncated: false,
+                    source: FetchSource::Network,
+                    cache_status: None,
+                    body_path: None,
+                    revalidating: false,
+                    timings_ms: BTreeMap::new(),
+                },
+            )
+            .unwrap();
+
+        let rep = cache.migrate_all(1_000, false);
+        assert_eq!(rep.scanned, 6);
+        assert_eq!(rep.legacy_found, 3);
+        assert_eq!(rep.migrated, 3);
+        assert_eq!(rep.skipped, 2);
+        assert_eq!(rep.not_legacy, 1);
+        assert!(!rep.truncated);
+        for u in urls {
+            let (m, b) = cache.paths(&FsCache::key_for_fetch_v2(&req_for(u), &[]));
+            assert!(m.exists() && b.exists(), "missing v2 entry for {u}");
+            assert_eq!(std::fs::read(&b).unwrap(), u.as_bytes());
+        }
+
+        // Second run: idempotent, and removal of v1 files is opt-in.
+        let rep = cache.migrate_all(1_000, true);
+        assert_eq!(rep.migrated, 0);
+        assert_eq!(rep.already_migrated, 3);
+        assert_eq!(rep.removed_legacy, 3);
+        for k in &legacy_keys {
+            let (m, b) = cache.paths(k);
+            assert!(!m.exists() && !b.exists());
+        }
+        assert_eq!(
+            cache.get(&req_for(urls[0])).unwrap().unwrap().bytes,
+            urls[0].as_bytes()
+        );
+
+        // Bounded walk.
+        let rep = cache.migrate_all(2, false);
+        assert_eq!(rep.scanned, 2);
+        assert!(rep.truncated);
+    }
+
+    #[test]
+    fn evict_drops_oldest_entries_and_spares_recent_writes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = FsCache::new(tmp.path().to_path_buf());
+        let long_ago = SystemTime::now() - Duration::from_secs(3_600);
+        let write = |cache: &FsCache, key: &str, fetched_at: u64, old: bool| {
+            let (meta_p, body_p) = cache.paths(key);
+            std::fs::create_dir_all(meta_p.parent().unwrap()).unwrap();
+            std::fs::write(&body_p, vec![b'x'; 1_000]).unwrap();
+            let meta = serde_json::json!({"fetched_at_epoch_s": fetched_at, "status": 200});
+            std::fs::write(&meta_p, serde_json::to_vec(&meta).unwrap()).unwrap();
+            if old {
+                let f = std::fs::File::options().write(true).open(&meta_p).unwrap();
+                f.set_modified(long_ago).unwrap();
+            }
+            std::fs::metadata(&meta_p).unwrap().len() + 1_000
+        };
+        let key = |c: char| c.to_string().repeat(64);
+        let keys = [key('a'), key('b'), key('c'), key('d')];
+        let mut sizes = Vec::new();
+        for (i, k) in keys.iter().enumerate() {
+            sizes.push(write(&cache, k, 100 + i as u64, true));
+        }
+
+        // No budget: nothing to do.
+        assert_eq!(cache.evict(), CacheEvictReport::default());
+
+        let cache = cache.with_max_bytes(Some(sizes[2] + sizes[3]));
+        let rep = cache.evict();
+        assert_eq!(rep.scanned, 4);
+        assert_eq!(rep.evicted, 2);
+        assert_eq!(rep.evicted_bytes, sizes[0] + sizes[1]);
+        assert_eq!(rep.bytes_after, sizes[2] + sizes[3]);
+        for (i, k) in keys.iter().enumerate() {
+            let (m, b) = cache.paths(k);
+            assert_eq!(m.exists(), i >= 2, "meta for {k}");
+            assert_eq!(b.exists(), i >= 2, "body for {k}");
+        }
+
+        // An entry written during the walk is never evicted, even when it is the oldest.
+        write(&cache, &key('e'), 1, false);
+        let rep = cache.evict();
+        assert_eq!(rep.skipped_recent, 1);
+        assert_eq!(rep.evicted, 1);
+        assert!(cache.paths(&key('e')).0.exists());
+        assert!(!cache.paths(&keys[2]).0.exists());
+        assert!(cache.paths(&keys[3]).0.exists());
+    }
+
+    #[test]
+    fn first_put_only_measures_the_cache_and_evicts_once_over_budget() {
+        use std::sync::atomic::Ordering;
+        let tmp = tempfile::tempdir().unwrap();
+        let req_for = |i: usize| FetchRequest {
+            url: format!("https://docs.example/{i}"),
+            timeout_ms: None,
+            max_bytes: None,
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy::default(),
+        };
+        let resp_for = |req: &FetchRequest| FetchResponse {
+            url: req.url.clone(),
+            final_url: req.url.clone(),
+            status: 200,
+            content_type: None,
+            headers: BTreeMap::new(),
+            bytes: vec![b'x'; 1_000],
+            wire_bytes: 1_000,
+            truncated: false,
+            source: FetchSource::Network,
+            cache_status: None,
+            body_path: None,
+            revalidating: false,
+            timings_ms: BTreeMap::new(),
+        };
+        let unbounded = FsCache::new(tmp.path().to_path_buf());
+        for i in 0..3 {
+            unbounded.put(&req_for(i), &resp_for(&req_for(i))).unwrap();
+        }
+        let settle = |cache: &FsCache| {
+            let deadline = std::time::Instant::now() + Duration::from_secs(5);
+            while cache.evicting.load(Ordering::Acquire) && std::time::Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        };
+        let cached = |cache: &FsCache, i: usize| cache.get(&req_for(i)).unwrap().is_some();
+
+        // A fresh handle doesn't know the total: the first put measures it, and nothing is evicted
+        // while the cache still fits.
+        let cache = FsCache::new(tmp.path().to_path_buf()).with_max_bytes(Some(6_000));
+        cache.put(&req_for(3), &resp_for(&req_for(3))).unwrap();
+        settle(&cache);
+        let total = cache.approx_bytes.load(Ordering::Relaxed);
+        assert!((4_000..6_000).contains(&total), "{total}");
+        assert!((0..4).all(|i| cached(&cache, i)));
+
+        // Going over the budget (plus slack) trims the oldest entries (the eviction walk spares
+        // ones written just now, so age the existing ones).
+        let long_ago = SystemTime::now() - Duration::from_secs(3_600);
+        for i in 0..4 {
+            let (meta_p, _) = cache.paths(&FsCache::key_for_fetch(&req_for(i)));
+            let f = std::fs::File::options().write(true).open(&meta_p).unwrap();
+            f.set_modified(long_ago).unwrap();
+        }
+        for i in 4..8 {
+            cache.put(&req_for(i), &resp_for(&req_for(i))).unwrap();
+            settle(&cache);
+        }
+        assert!(cache.approx_bytes.load(Ordering::Relaxed) <= 6_000);
+        assert!(cached(&cache, 7));
+        assert!(!(0..8).all(|i| cached(&cache, i)));
+    }
+
+    #[test]
+    fn invalidate_removes_exact_keys_and_url_prefix_matches() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = FsCache::new(tmp.path().to_path_buf());
+        let req_for = |url: &str, max_bytes: Option<u64>| FetchRequest {
+            url: url.to_string(),
+            timeout_ms: None,
+            max_bytes,
+            headers: BTreeMap::new(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy::default(),
+        };
+        let put = |req: &FetchRequest| {
+            cache
+                .put(
+                    req,
+                    &FetchResponse {
+                        url: req.url.clone(),
+                        final_url: req.url.clone(),
+                        status: 200,
+                        content_type: None,
+                        headers: BTreeMap::new(),
+                        bytes: req.url.as_bytes().to_vec(),
+                        wire_bytes: req.url.len() as u64,
+                        truncated: false,
+                        source: FetchSource::Network,
+                        cache_status: None,
+                        body_path: None,
+                        revalidating: false,
+                        timings_ms: BTreeMap::new(),
+                    },
+                )
+                .unwrap();
+        };
+        let cached = |req: &FetchRequest| cache.get(req).unwrap().is_some();
+
+        let a = req_for("https://docs.example/a", None);
+        let a_capped = req_for("https://docs.example/a", Some(1_000));
+        let b = req_for("https://docs.example/guide/b", None);
+        let other = req_for("https://other.example/docs", None);
+        for r in [&a, &a_capped, &b, &other] {
+            put(r);
+        }
+        // A legacy v1 copy of `a` goes too.
+        let (v1_meta, v1_body) = cache.paths(&FsCache::key_for_fetch_legacy_v1(&a));
+        fs::create_dir_all(v1_meta.parent().unwrap()).unwrap();
+        fs::write(&v1_body, b"old").unwrap();
+        fs::write(
+            &v1_meta,
+            br#"{"url":"https://docs.example/a","status":200}"#,
+        )
+        .unwrap();
+
+        assert_eq!(cache.invalidate(&a), 2);
+        assert!(!v1_meta.exists() && !v1_body.exists());
+        assert!(!cached(&a));
+        // Other request knobs key a separate entry.
+        assert!(cached(&a_capped));
+        assert_eq!(cache.invalidate(&a), 0);
+
+        assert_eq!(cache.invalidate_url("https://docs.example/a"), 1);
+        assert!(!cached(&a_capped));
+
+        put(&a);
+        assert_eq!(cache.invalidate_url_prefix("https://docs.example/"), 2);
+        assert!(!cached(&a) && !cached(&b));
+        assert!(cached(&other));
+        // Body and meta both go.
+        let (m, body) = cache.paths(&FsCache::key_for_fetch(&b));
+        assert!(!m.exists() && !body.exists());
+    }
+
+    #[test]
+    fn recent_entries_lists_newest_first_and_read_by_key_rejects_paths() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = FsCache::new(tmp.path().to_path_buf());
+        let write = |key: &str, url: &str, fetched_at: u64, body: bool| {
+            let (meta_p, body_p) = cache.paths(key);
+            fs::create_dir_all(meta_p.parent().unwrap()).unwrap();
+            if body {
+                fs::write(&body_p, url.as_bytes()).unwrap();
+            }
+            let meta = serde_json::json!({
+                "fetched_at_epoch_s": fetched_at,
+                "url": url,
+                "final_url": format!("{url}/"),
+                "status": 200,
+                "content_type": "text/html",
+                "decoded_bytes": url.len(),
+                "headers": {"content-type": "text/html"},
+            });
+            fs::write(&meta_p, serde_json::to_vec(&meta).unwrap()).unwrap();
+        };
+        let key = |c: char| c.to_string().repeat(64);
+        write(&key('a'), "https://a.example", 100, true);
+        write(&key('b'), "https://b.example", 300, true);
+        write(&key('c'), "https://c.example", 200, true);
+        // Meta without a body is not a readable entry.
+        write(&key('d'), "https://d.example", 400, false);
+
+        let got = cache.recent_entries(2);
+        assert_eq!(
+            got.iter().map(|e| e.url.as_str()).collect::<Vec<_>>(),
+            vec!["https://b.example", "https://c.example"]
+        );
+        assert_eq!(got[0].final_url, "https://b.example/");
+        assert_eq!(got[0].content_type.as_deref(), Some("text/html"));
+        assert_eq!(got[0].bytes, "https://b.example".len() as u64);
+        assert_eq!(cache.recent_entries(10).len(), 3);
+
+        let (info, body) = cache.read_by_key(&key('c')).unwrap().unwrap();
+        assert_eq!(info.url, "https://c.example");
+        assert_eq!(body, b"https://c.example");
+        assert!(cache.read_by_key(&key('d')).unwrap().is_none());
+        assert!(cache.read_by_key("../../etc/passwd").unwrap().is_none());
+        assert!(cache.read_by_key(&"z".repeat(64)).unwrap().is_none());
+    }
+
+    #[test]
+    fn vary_on_credentials_is_only_cached_when_unsafe_headers_are_allowed() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        std::env::remove_var("WEBPIPE_ALLOW_UNSAFE_HEADERS");
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = FsCache::new(tmp.path().to_path_buf());
+        let req_for = |cookie: Option<&str>| FetchRequest {
+            url: "https://app.example/home".to_string(),
+            timeout_ms: None,
+            max_bytes: None,
+            headers: cookie
+                .map(|c| BTreeMap::from([("Cookie".to_string(), c.to_string())]))
+                .unwrap_or_default(),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy::default(),
+        };
+        let put = |req: &FetchRequest, body: &str| {
+            let resp = FetchResponse {
+                url: req.url.clone(),
+                final_url: req.url.clone(),
+                status: 200,
+                content_type: Some("text/html".to_string()),
+                headers: BTreeMap::from([("Vary".to_string(), "Cookie, *".to_string())]),
+                bytes: body.as_bytes().to_vec(),
+                wire_bytes: body.len() as u64,
+                truncated: false,
+                source: FetchSource::Network,
+                cache_status: None,
+                body_path: None,
+                revalidating: false,
+                timings_ms: BTreeMap::new(),
+            };
+            cache.put(req, &resp).unwrap();
+        };
+        let body = |req: &FetchRequest| cache.get(req).unwrap().map(|r| r.bytes);
+
+        // Cookies never feed the plain key, so both users share it until a response says Vary.
+        let (alice, bob) = (req_for(Some("u=alice")), req_for(Some("u=bob")));
+        assert_eq!(FsCache::key_for_fetch(&alice), FsCache::key_for_fetch(&bob));
+        assert_eq!(
+            FsCache::vary_names(&BTreeMap::from([(
+                "vary".to_string(),
+                "Cookie, *".to_string()
+            )])),
+            vec!["cookie"]
+        );
+
+        // Varying on Cookie would hash the raw cookie into the key: such responses aren't stored.
+        put(&alice, "alice");
+        let (meta_p, _) = cache.paths(&FsCache::key_for_fetch(&alice));
+        assert!(!meta_p.exists());
+        assert_eq!(body(&alice), None);
+        assert_eq!(body(&bob), None);
+
+        // Nor served, if an entry was written while unsafe headers were allowed.
+        std::env::set_var("WEBPIPE_ALLOW_UNSAFE_HEADERS", "1");
+        let anon = req_for(None);
+        put(&anon, "anon");
+        assert_eq!(body(&anon).as_deref(), Some(&b"anon"[..]));
+        std::env::remove_var("WEBPIPE_ALLOW_UNSAFE_HEADERS");
+        assert_eq!(body(&anon), None);
+
+        // With the opt-in, each cookie gets its own entry, stored with its Vary set.
+        std::env::set_var("WEBPIPE_ALLOW_UNSAFE_HEADERS", "1");
+        put(&alice, "alice");
+        put(&bob, "bob");
+        let (meta_p, _) = cache.paths(&FsCache::key_for_fetch(&alice));
+        let meta: serde_json::Value = serde_json::from_slice(&fs::read(&meta_p).unwrap()).unwrap();
+        assert_eq!(meta["vary"], serde_json::json!(["cookie"]));
+        assert_eq!(meta["headers"]["Vary"], "Cookie, *");
+        assert_eq!(body(&alice).as_deref(), Some(&b"alice"[..]));
+        assert_eq!(body(&bob).as_deref(), Some(&b"bob"[..]));
+
+        assert_eq!(cache.invalidate(&bob), 1);
+        assert_eq!(body(&bob), None);
+        assert_eq!(body(&alice).as_deref(), Some(&b"alice"[..]));
+        std::env::remove_var("WEBPIPE_ALLOW_UNSAFE_HEADERS");
+    }
+
+    #[test]
+    fn vary_response_keys_later_reads_on_the_declared_request_headers() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        std::env::remove_var("WEBPIPE_ALLOW_UNSAFE_HEADERS");
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = FsCache::new(tmp.path().to_path_buf());
+        let req = FetchRequest {
+            url: "https://app.example/home".to_string(),
+            timeout_ms: None,
+            max_bytes: None,
+            headers: BTreeMap::from([("Accept-Language".to_string(), "en".to_string())]),
+            method: None,
+            body: None,
+            cache: FetchCachePolicy::default(),
+        };
+        let resp = FetchResponse {
+            url: req.url.clone(),
+            final_url: req.url.clone(),
+            status: 200,
+            content_type: Some("text/html".to_string()),
+            headers: BTreeMap::from([("Vary".to_string(), "Accept-Language".to_string())]),
+            bytes: b"hello".to_vec(),
+            wire_bytes: 5,
+            truncated: false,
+            source: FetchSource::Network,
+            cache_status: None,
+            body_path: None,
+            revalidating: false,
+            timings_ms: BTreeMap::new(),
+        };
+
+        // The first response is stored under the plain key, as before, with its Vary set.
+        cache.put(&req, &resp).unwrap();
+        let (meta_p, _) = cache.paths(&FsCache::key_for_fetch(&req));
+        let meta: serde_json::Value = serde_json::from_slice(&fs::read(&meta_p).unwrap()).unwrap();
+        assert_eq!(meta["vary"], serde_json::json!(["accept-language"]));
+        assert_eq!(
+            meta["vary_key"].as_str(),
+            Some(FsCache::key_for_fetch_v2(&req, &["accept-language".to_string()]).as_str())
+        );
+        assert_eq!(
+            cache.get(&req).unwrap().map(|r| r.bytes).as_deref(),
+            Some(&b"hello"[..])
+        );
+        assert_eq!(cache.invalidate(&req), 1);
+        assert!(cache.get(&req).unwrap().is_none());
+    }
+
     proptest! {
         #[test]
         fn key_for_fetch_v2_is_hex_and_never_panics(
@@ -1223,10 +5209,12 @@ mod tests {
                 timeout_ms: None,
                 max_bytes,
                 headers,
-                cache: FetchCachePolicy { read: true, write: true, ttl_s: None },
+                method: None,
+                body: None,
+                cache: FetchCachePolicy { read: true, write: true, ttl_s: None, read_non_idempotent: false, stale_while_revalidate_s: None },
             };
 
-            let k = FsCache::key_for_fetch_v2(&req);
+            let k = FsCache::key_for_fetch_v2(&req, &[]);
             prop_assert_eq!(k.len(), 64);
             prop_assert!(k.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')));
 
@@ -1266,6 +5254,7 @@ mod tests {
                             | "last-modified"
                             | "cache-control"
                             | "retry-after"
+                            | "content-disposition"
                     ),
                     "unexpected cached meta header key: {kl}"
                 );
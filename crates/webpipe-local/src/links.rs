@@ -1,4 +1,5 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, VecDeque};
+use webpipe_core::{FetchBackend, FetchCachePolicy, FetchRequest};
 
 #[derive(Debug, Clone)]
 pub struct LinkCandidate {
@@ -6,6 +7,173 @@ pub struct LinkCandidate {
     pub text: String,
 }
 
+/// A `<link rel="alternate" hreflang=...>` entry.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct HreflangLink {
+    pub lang: String,
+    pub url: String,
+}
+
+/// URLs a page declares for itself: canonical, AMP, and language alternates.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct PageAlternates {
+    pub canonical: Option<String>,
+    pub amp: Option<String>,
+    pub hreflang: Vec<HreflangLink>,
+}
+
+const MAX_HREFLANG: usize = 100;
+
+fn resolve_href(href: &str, base: Option<&url::Url>) -> Option<String> {
+    let href = href.trim();
+    if href.is_empty() {
+        return None;
+    }
+    let mut u = match url::Url::parse(href) {
+        Ok(u) => u,
+        Err(_) => base?.join(href).ok()?,
+    };
+    if u.scheme() != "http" && u.scheme() != "https" {
+        return None;
+    }
+    u.set_fragment(None);
+    Some(u.to_string())
+}
+
+/// Best-effort registrable domain ("eTLD+1") for a host, without a public-suffix list.
+///
+/// Handles the common two-label public suffixes (`co.uk`, `com.au`, ...); everything else
+/// keeps the last two labels. IP literals are returned as-is.
+pub fn registrable_domain(host: &str) -> String {
+    let h = host
+        .trim()
+        .trim_end_matches('.')
+        .trim_start_matches("www.")
+        .to_ascii_lowercase();
+    if h.parse::<std::net::IpAddr>().is_ok() || h.starts_with('[') {
+        return h;
+    }
+    let labels: Vec<&str> = h.split('.').filter(|l| !l.is_empty()).collect();
+    if labels.len() <= 2 {
+        return labels.join(".");
+    }
+    let n = labels.len();
+    let sld = labels[n - 2];
+    let two_label_suffix = labels[n - 1].len() == 2
+        && matches!(
+            sld,
+            "co" | "com" | "net" | "org" | "gov" | "ac" | "edu" | "ne" | "or"
+        );
+    let keep = if two_label_suffix { 3 } else { 2 };
+    labels[n - keep..].join(".")
+}
+
+/// How a link relates to the page it was found on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkScope {
+    /// Same host (scheme and port are ignored).
+    SameHost,
+    /// Different host under the same registrable domain (`docs.example.com` from `example.com`).
+    SameSite,
+    External,
+}
+
+impl LinkScope {
+    /// Same host or same registrable domain.
+    pub fn is_internal(self) -> bool {
+        self != LinkScope::External
+    }
+}
+
+/// Classify `link` relative to the page at `from`.
+pub fn link_scope(from: &url::Url, link: &url::Url) -> LinkScope {
+    let (a, b) = (from.host_str().unwrap_or(""), link.host_str().unwrap_or(""));
+    if a.eq_ignore_ascii_case(b) {
+        LinkScope::SameHost
+    } else if !a.is_empty() && registrable_domain(a) == registrable_domain(b) {
+        LinkScope::SameSite
+    } else {
+        LinkScope::External
+    }
+}
+
+/// `<base href>` resolved against `base_url` when present, else `base_url` itself.
+fn document_base(doc: &html_scraper::Html, base_url: Option<&str>) -> Option<url::Url> {
+    let base = base_url.and_then(|u| url::Url::parse(u).ok());
+    let sel = html_scraper::Selector::parse("base[href]").ok()?;
+    let Some(h) = doc
+        .select(&sel)
+        .next()
+        .and_then(|el| el.value().attr("href"))
+    else {
+        return base;
+    };
+    let joined = match &base {
+        Some(b) => b.join(h.trim()).ok(),
+        None => url::Url::parse(h.trim()).ok(),
+    };
+    joined.or(base)
+}
+
+/// Extract canonical / AMP / hreflang alternates from `<link>` tags.
+///
+/// - `rel` is matched token-wise and case-insensitively (`rel="Canonical"` and
+///   `rel="alternate canonical"` both count).
+/// - URLs are resolved against `<base href>` when present, else `base_url`.
+/// - First canonical/amphtml wins; hreflang entries are deduped and bounded.
+pub fn extract_alternates(html: &str, base_url: Option<&str>) -> PageAlternates {
+    let doc = html_scraper::Html::parse_document(html);
+    let base = document_base(&doc, base_url);
+    let sel = match html_scraper::Selector::parse("link[rel][href]") {
+        Ok(s) => s,
+        Err(_) => return PageAlternates::default(),
+    };
+
+    let mut out = PageAlternates::default();
+    let mut seen = BTreeSet::<(String, String)>::new();
+    for el in doc.select(&sel) {
+        let v = el.value();
+        let rel = v.attr("rel").unwrap_or("").to_ascii_lowercase();
+        let has = |t: &str| rel.split_ascii_whitespace().any(|x| x == t);
+        let Some(url) = v.attr("href").and_then(|h| resolve_href(h, base.as_ref())) else {
+            continue;
+        };
+        if has("canonical") && out.canonical.is_none() {
+            out.canonical = Some(url.clone());
+        }
+        if has("amphtml") && out.amp.is_none() {
+            out.amp = Some(url.clone());
+        }
+        if has("alternate") && out.hreflang.len() < MAX_HREFLANG {
+            if let Some(lang) = v.attr("hreflang").map(str::trim).filter(|s| !s.is_empty()) {
+                if seen.insert((lang.to_ascii_lowercase(), url.clone())) {
+                    out.hreflang.push(HreflangLink {
+                        lang: lang.to_string(),
+                        url,
+                    });
+                }
+            }
+        }
+    }
+    out
+}
+
+/// `<iframe src>` URLs in document order, resolved like links (http(s) only, no fragments,
+/// deduped). `srcdoc`-only and `about:blank` frames are skipped.
+pub fn extract_iframe_sources(html: &str, base_url: Option<&str>) -> Vec<String> {
+    let doc = html_scraper::Html::parse_document(html);
+    let base = document_base(&doc, base_url);
+    let Ok(sel) = html_scraper::Selector::parse("iframe[src]") else {
+        return Vec::new();
+    };
+    let mut seen = BTreeSet::new();
+    doc.select(&sel)
+        .filter_map(|el| resolve_href(el.value().attr("src")?, base.as_ref()))
+        .filter(|u| seen.insert(u.clone()))
+        .collect()
+}
+
 /// Extract (deduped) absolute links from HTML.
 ///
 /// - Resolves relative links against `base_url` when provided.
@@ -218,10 +386,378 @@ pub fn extract_markdown_link_candidates(
     out
 }
 
+/// Sitemap documents [`discover_sitemap`] fetches at most (indexes included).
+const MAX_SITEMAP_FETCHES: usize = 20;
+/// Wire cap per sitemap document; the sitemaps.org limit is 50 MB uncompressed.
+const MAX_SITEMAP_BYTES: u64 = 10_000_000;
+/// Cap on a gunzipped `.xml.gz` sitemap.
+const MAX_SITEMAP_GUNZIP_BYTES: u64 = 50_000_000;
+/// Index nesting followed: an index's sitemaps may themselves be indexes once; deeper ones are
+/// ignored.
+const MAX_SITEMAP_DEPTH: usize = 2;
+
+/// `Sitemap:` directives in a `robots.txt` (any group; they are not agent-specific).
+pub fn robots_sitemaps(txt: &str) -> Vec<String> {
+    txt.lines()
+        .filter_map(|line| {
+            let (k, v) = line.split('#').next()?.split_once(':')?;
+            let v = v.trim();
+            (k.trim().eq_ignore_ascii_case("sitemap") && !v.is_empty()).then(|| v.to_string())
+        })
+        .collect()
+}
+
+/// `<loc>` values of a sitemap or sitemap index, entity-decoded, plus whether it is an index.
+pub fn parse_sitemap_xml(xml: &str) -> (bool, Vec<String>) {
+    let is_index = xml.contains("<sitemapindex");
+    let mut out = Vec::new();
+    let mut rest = xml;
+    while let Some(s) = rest.find("<loc>") {
+        rest = &rest[s + "<loc>".len()..];
+        let Some(e) = rest.find("</loc>") else {
+            break;
+        };
+        let raw = rest[..e].trim();
+        let raw = raw
+            .strip_prefix("<![CDATA[")
+            .and_then(|r| r.strip_suffix("]]>"))
+            .unwrap_or(raw)
+            .trim();
+        let loc = raw
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&");
+        if !loc.is_empty() {
+            out.push(loc);
+        }
+        rest = &rest[e + "</loc>".len()..];
+    }
+    (is_index, out)
+}
+
+/// Gunzip a `.xml.gz` body (detected by magic bytes, since servers label these inconsistently).
+/// Plain bodies are returned as-is; a corrupt gzip stream yields `None`.
+fn sitemap_body(bytes: &[u8]) -> Option<Vec<u8>> {
+    use std::io::Read as _;
+    if !bytes.starts_with(&[0x1f, 0x8b]) {
+        return Some(bytes.to_vec());
+    }
+    let mut out = Vec::new();
+    flate2::read::GzDecoder::new(bytes)
+        .take(MAX_SITEMAP_GUNZIP_BYTES)
+        .read_to_end(&mut out)
+        .ok()?;
+    Some(out)
+}
+
+fn http_url(s: &str) -> Option<url::Url> {
+    url::Url::parse(s.trim())
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https"))
+}
+
+/// Page URLs a site publishes in its sitemaps, for seeding a crawl; at most `max_urls`.
+///
+/// Reads `/robots.txt` at `base_url`'s origin and follows its `Sitemap:` directives, falling back
+/// to `/sitemap.xml` when there are none. Sitemap indexes are expanded (see
+/// [`MAX_SITEMAP_DEPTH`]), `.xml.gz` sitemaps are gunzipped, and non-HTTP(S) entries are skipped.
+/// Same-origin URLs that robots.txt disallows for `webpipe` are dropped. Fetch errors are
+/// tolerated: whatever was found so far is returned, deduped, in sitemap order.
+pub async fn discover_sitemap(
+    base_url: &str,
+    fetcher: &dyn FetchBackend,
+    max_urls: usize,
+) -> Vec<String> {
+    let Some(base) = http_url(base_url) else {
+        return Vec::new();
+    };
+    let origin = base.origin().ascii_serialization();
+    let get = |url: String| FetchRequest {
+        url,
+        timeout_ms: Some(10_000),
+        max_bytes: Some(MAX_SITEMAP_BYTES),
+        headers: Default::default(),
+        method: None,
+        body: None,
+        cache: FetchCachePolicy::default(),
+    };
+
+    let robots = match fetcher.fetch(&get(format!("{origin}/robots.txt"))).await {
+        Ok(r) if r.status == 200 => Some(r.text_lossy()),
+        _ => None,
+    };
+    let rules = robots
+        .as_deref()
+        .map(|txt| crate::crawl::RobotsRules::parse(txt, "webpipe"));
+    let allowed = |u: &url::Url| {
+        let Some(rules) = rules.as_ref() else {
+            return true;
+        };
+        if u.origin().ascii_serialization() != origin {
+            return true;
+        }
+        match u.query() {
+            Some(q) => rules.allows(&format!("{}?{q}", u.path())),
+            None => rules.allows(u.path()),
+        }
+    };
+
+    let mut queue: VecDeque<(String, usize)> = robots
+        .as_deref()
+        .map(robots_sitemaps)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|u| (u, 0))
+        .collect();
+    if queue.is_empty() {
+        queue.push_back((format!("{origin}/sitemap.xml"), 0));
+    }
+
+    let mut seen_sitemaps = BTreeSet::new();
+    let mut seen = BTreeSet::new();
+    let mut out = Vec::new();
+    while let Some((sitemap, depth)) = queue.pop_front() {
+        if out.len() >= max_urls || seen_sitemaps.len() >= MAX_SITEMAP_FETCHES {
+            break;
+        }
+        let Some(sitemap) = http_url(&sitemap) else {
+            continue;
+        };
+        if !seen_sitemaps.insert(sitemap.to_string()) {
+            continue;
+        }
+        let Ok(r) = fetcher.fetch(&get(sitemap.to_string())).await else {
+            continue;
+        };
+        if !(200..300).contains(&r.status) {
+            continue;
+        }
+        let Some(body) = sitemap_body(&r.bytes) else {
+            continue;
+        };
+        let (is_index, locs) = parse_sitemap_xml(&String::from_utf8_lossy(&body));
+        if is_index {
+            if depth < MAX_SITEMAP_DEPTH {
+                queue.extend(locs.into_iter().map(|u| (u, depth + 1)));
+            }
+            continue;
+        }
+        for loc in locs {
+            if out.len() >= max_urls {
+                break;
+            }
+            let Some(mut u) = http_url(&loc) else {
+                continue;
+            };
+            u.set_fragment(None);
+            if allowed(&u) && seen.insert(u.to_string()) {
+                out.push(u.to_string());
+            }
+        }
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn registrable_domain_groups_subdomains() {
+        assert_eq!(registrable_domain("docs.example.com"), "example.com");
+        assert_eq!(registrable_domain("www.example.com"), "example.com");
+        assert_eq!(registrable_domain("example.com"), "example.com");
+        assert_eq!(registrable_domain("news.bbc.co.uk"), "bbc.co.uk");
+        assert_eq!(registrable_domain("127.0.0.1"), "127.0.0.1");
+    }
+
+    #[test]
+    fn link_scope_separates_host_site_and_external() {
+        let u = |s: &str| url::Url::parse(s).unwrap();
+        let from = u("https://example.com/docs/");
+        assert_eq!(
+            link_scope(&from, &u("http://EXAMPLE.com:8080/a")),
+            LinkScope::SameHost
+        );
+        assert_eq!(
+            link_scope(&from, &u("https://docs.example.com/")),
+            LinkScope::SameSite
+        );
+        assert_eq!(
+            link_scope(&from, &u("https://example.org/")),
+            LinkScope::External
+        );
+        assert!(LinkScope::SameSite.is_internal());
+        assert!(!LinkScope::External.is_internal());
+    }
+
+    #[test]
+    fn extract_iframe_sources_resolves_dedupes_and_skips_non_http() {
+        let html = r#"<html><head><base href="/docs/"></head><body>
+<iframe src="frame.html#top"></iframe>
+<iframe src="about:blank"></iframe>
+<iframe srcdoc="<p>inline</p>"></iframe>
+<iframe src="https://cdn.example.net/embed?id=1"></iframe>
+<iframe src="./frame.html"></iframe>
+</body></html>"#;
+        assert_eq!(
+            extract_iframe_sources(html, Some("https://example.com/guide/page")),
+            vec![
+                "https://example.com/docs/frame.html".to_string(),
+                "https://cdn.example.net/embed?id=1".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_sitemap_xml_decodes_entities_and_cdata() {
+        let (is_index, locs) = parse_sitemap_xml(
+            "<urlset><url><loc> https://a.example/x?a=1&amp;b=2 </loc></url>\
+             <url><loc><![CDATA[https://a.example/y]]></loc></url><url><loc></loc></url></urlset>",
+        );
+        assert!(!is_index);
+        assert_eq!(locs, ["https://a.example/x?a=1&b=2", "https://a.example/y"]);
+        assert!(
+            parse_sitemap_xml("<sitemapindex><sitemap><loc>u</loc></sitemap></sitemapindex>").0
+        );
+        assert_eq!(
+            robots_sitemaps("User-agent: *\nSITEMAP: https://a.example/s.xml # main\nSitemap:\n"),
+            ["https://a.example/s.xml"]
+        );
+    }
+
+    struct MapFetcher(std::collections::BTreeMap<String, Vec<u8>>);
+
+    #[async_trait::async_trait]
+    impl FetchBackend for MapFetcher {
+        async fn fetch(
+            &self,
+            req: &FetchRequest,
+        ) -> webpipe_core::Result<webpipe_core::FetchResponse> {
+            let (status, bytes) = match self.0.get(&req.url) {
+                Some(b) => (200, b.clone()),
+                None => (404, Vec::new()),
+            };
+            Ok(webpipe_core::FetchResponse {
+                url: req.url.clone(),
+                final_url: req.url.clone(),
+                status,
+                content_type: None,
+                headers: Default::default(),
+                wire_bytes: bytes.len() as u64,
+                bytes,
+                truncated: false,
+                source: webpipe_core::FetchSource::Network,
+                cache_status: None,
+                body_path: None,
+                revalidating: false,
+                timings_ms: Default::default(),
+            })
+        }
+    }
+
+    fn urlset(locs: &[&str]) -> Vec<u8> {
+        let body: String = locs
+            .iter()
+            .map(|l| format!("<url><loc>{l}</loc></url>"))
+            .collect();
+        format!("<?xml version=\"1.0\"?><urlset>{body}</urlset>").into_bytes()
+    }
+
+    fn index(locs: &[&str]) -> Vec<u8> {
+        let body: String = locs
+            .iter()
+            .map(|l| format!("<sitemap><loc>{l}</loc></sitemap>"))
+            .collect();
+        format!("<sitemapindex>{body}</sitemapindex>").into_bytes()
+    }
+
+    #[tokio::test]
+    async fn discover_sitemap_follows_robots_indexes_and_gzip() {
+        use std::io::Write as _;
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&urlset(&[
+            "https://s.example/gz-page",
+            "https://s.example/private/x",
+        ]))
+        .unwrap();
+        let gz = gz.finish().unwrap();
+        let f = MapFetcher(
+            [
+                (
+                    "https://s.example/robots.txt",
+                    b"User-agent: *\nDisallow: /private/\nSitemap: https://s.example/index.xml\n"
+                        .to_vec(),
+                ),
+                (
+                    "https://s.example/index.xml",
+                    index(&[
+                        "https://s.example/nested.xml",
+                        "https://s.example/pages.xml.gz",
+                    ]),
+                ),
+                (
+                    "https://s.example/nested.xml",
+                    index(&[
+                        "https://s.example/a.xml",
+                        "https://s.example/deep.xml",
+                        "ftp://s.example/b.xml",
+                    ]),
+                ),
+                (
+                    "https://s.example/a.xml",
+                    urlset(&[
+                        "https://s.example/a#top",
+                        "mailto:x@s.example",
+                        "https://other.example/private/ok",
+                    ]),
+                ),
+                // Third index level: not followed.
+                (
+                    "https://s.example/deep.xml",
+                    index(&["https://s.example/too-deep.xml"]),
+                ),
+                (
+                    "https://s.example/too-deep.xml",
+                    urlset(&["https://s.example/too-deep"]),
+                ),
+                ("https://s.example/pages.xml.gz", gz),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect(),
+        );
+        assert_eq!(
+            discover_sitemap("https://s.example/docs/", &f, 100).await,
+            [
+                "https://s.example/gz-page",
+                "https://s.example/a",
+                "https://other.example/private/ok",
+            ]
+        );
+        assert_eq!(
+            discover_sitemap("https://s.example/", &f, 2).await,
+            ["https://s.example/gz-page", "https://s.example/a"]
+        );
+
+        // No robots.txt: fall back to /sitemap.xml.
+        let f = MapFetcher(
+            [(
+                "https://t.example/sitemap.xml".to_string(),
+                urlset(&["https://t.example/", "https://t.example/"]),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(
+            discover_sitemap("https://t.example/x", &f, 10).await,
+            ["https://t.example/"]
+        );
+        assert!(discover_sitemap("file:///etc", &f, 10).await.is_empty());
+    }
+
     #[test]
     fn extracts_and_resolves_links() {
         let html = r#"
@@ -264,4 +800,60 @@ See the [Cursor MCP docs](/context/model-context-protocol) and [Example](https:/
             .iter()
             .any(|c| c.text.to_lowercase().contains("cursor")));
     }
+
+    #[test]
+    fn extracts_canonical_amp_and_hreflang_alternates() {
+        let html = r#"
+        <html><head>
+          <link rel="Canonical" href="/articles/42#top">
+          <link rel="canonical" href="https://example.com/ignored-second">
+          <link rel="amphtml" href="amp/42">
+          <link rel="alternate" hreflang="en" href="https://example.com/articles/42">
+          <link rel="alternate" hreflang="de" href="/de/artikel/42">
+          <link rel="alternate" hreflang="x-default" href="//example.com/articles/42">
+          <link rel="alternate" hreflang="de" href="/de/artikel/42">
+          <link rel="alternate" type="application/rss+xml" href="/feed.xml">
+          <link rel="stylesheet" href="/site.css">
+        </head><body><p>hi</p></body></html>
+        "#;
+        let a = extract_alternates(html, Some("https://example.com/articles/42?utm=x"));
+        assert_eq!(
+            a.canonical.as_deref(),
+            Some("https://example.com/articles/42")
+        );
+        assert_eq!(
+            a.amp.as_deref(),
+            Some("https://example.com/articles/amp/42")
+        );
+        let got: Vec<(&str, &str)> = a
+            .hreflang
+            .iter()
+            .map(|h| (h.lang.as_str(), h.url.as_str()))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                ("en", "https://example.com/articles/42"),
+                ("de", "https://example.com/de/artikel/42"),
+                ("x-default", "https://example.com/articles/42"),
+            ]
+        );
+    }
+
+    #[test]
+    fn alternates_respect_base_href_and_missing_tags() {
+        let html = r#"<html><head><base href="https://cdn.example.org/en/">
+          <link rel="canonical" href="page"></head></html>"#;
+        let a = extract_alternates(html, Some("https://example.com/x"));
+        assert_eq!(
+            a.canonical.as_deref(),
+            Some("https://cdn.example.org/en/page")
+        );
+        assert!(a.amp.is_none());
+        assert!(a.hreflang.is_empty());
+        assert_eq!(
+            extract_alternates("<p>no head</p>", None),
+            PageAlternates::default()
+        );
+    }
 }
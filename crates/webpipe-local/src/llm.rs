@@ -0,0 +1,314 @@
+//! Pluggable LLM backends.
+//!
+//! Synthesis-style tools talk to an [`LlmBackend`] picked by name from an [`LlmRegistry`]
+//! instead of matching on backend strings. The built-in backends (`anthropic`, `perplexity`,
+//! `ollama`, `openai_compat`) read their configuration from the environment on every call, so a registry
+//! can be built once at startup and still follow env changes. Embedders add their own backends
+//! with [`LlmRegistry::register`].
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use webpipe_core::{Error, Result};
+
+/// A single-turn prompt: one system message and one user message.
+#[derive(Debug, Clone, Default)]
+pub struct Prompt {
+    pub system: String,
+    pub user: String,
+}
+
+/// Per-call knobs. Backends ignore the ones they do not support.
+#[derive(Debug, Clone, Default)]
+pub struct CompletionOpts {
+    pub timeout_ms: u64,
+    /// Model override (`openai_compat`/`anthropic`: fall back to `WEBPIPE_{OPENAI_COMPAT,ANTHROPIC}_MODEL`;
+    /// `perplexity`: required).
+    pub model: Option<String>,
+    pub max_tokens: Option<u64>,
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    /// Perplexity-specific provider-side browsing mode.
+    pub search_mode: Option<String>,
+    /// Perplexity-specific; only applicable for sonar-deep-research.
+    pub reasoning_effort: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Completion {
+    pub text: String,
+    /// Provider-reported citations, when the backend browses on its own.
+    pub citations: Option<Vec<String>>,
+    pub usage: Option<serde_json::Value>,
+    pub timings_ms: Option<serde_json::Value>,
+}
+
+#[async_trait::async_trait]
+pub trait LlmBackend: Send + Sync {
+    /// Cheap configuration check (no network); errors are usually `Error::NotConfigured`.
+    fn check_configured(&self, _opts: &CompletionOpts) -> Result<()> {
+        Ok(())
+    }
+
+    /// Base URL this backend calls, when known, so callers can enforce localhost-only policies.
+    fn endpoint(&self, _opts: &CompletionOpts) -> Option<String> {
+        None
+    }
+
+    /// True for hosted APIs that can never run under `no_network=true`.
+    fn network_only(&self) -> bool {
+        false
+    }
+
+    async fn complete(&self, prompt: &Prompt, opts: &CompletionOpts) -> Result<Completion>;
+}
+
+/// Name → backend map.
+#[derive(Clone, Default)]
+pub struct LlmRegistry {
+    backends: BTreeMap<String, Arc<dyn LlmBackend>>,
+}
+
+impl LlmRegistry {
+    /// Registry with the built-in env-configured backends.
+    pub fn with_builtins(http: reqwest::Client) -> Self {
+        let mut r = Self::default();
+        r.register(
+            "anthropic",
+            Arc::new(AnthropicBackend { http: http.clone() }),
+        );
+        r.register(
+            "perplexity",
+            Arc::new(PerplexityBackend { http: http.clone() }),
+        );
+        r.register("ollama", Arc::new(OllamaBackend { http: http.clone() }));
+        r.register("openai_compat", Arc::new(OpenAiCompatBackend { http }));
+        r
+    }
+
+    /// Add (or replace) a backend.
+    pub fn register(&mut self, name: impl Into<String>, backend: Arc<dyn LlmBackend>) {
+        self.backends.insert(name.into(), backend);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn LlmBackend>> {
+        self.backends.get(name).cloned()
+    }
+
+    /// Registered names, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        self.backends.keys().map(String::as_str).collect()
+    }
+}
+
+struct PerplexityBackend {
+    http: reqwest::Client,
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for PerplexityBackend {
+    fn check_configured(&self, _opts: &CompletionOpts) -> Result<()> {
+        crate::perplexity::PerplexityClient::from_env(self.http.clone()).map(|_| ())
+    }
+
+    fn network_only(&self) -> bool {
+        true
+    }
+
+    async fn complete(&self, prompt: &Prompt, opts: &CompletionOpts) -> Result<Completion> {
+        let c = crate::perplexity::PerplexityClient::from_env(self.http.clone())?;
+        let model = opts
+            .model
+            .clone()
+            .ok_or_else(|| Error::NotConfigured("missing model for perplexity".to_string()))?;
+        let req = crate::perplexity::ChatCompletionsRequest {
+            model,
+            messages: vec![
+                crate::perplexity::Message {
+                    role: "system".to_string(),
+                    content: prompt.system.clone(),
+                },
+                crate::perplexity::Message {
+                    role: "user".to_string(),
+                    content: prompt.user.clone(),
+                },
+            ],
+            max_tokens: opts.max_tokens,
+            temperature: opts.temperature,
+            top_p: opts.top_p,
+            search_mode: opts.search_mode.clone(),
+            reasoning_effort: opts.reasoning_effort.clone(),
+        };
+        let r = c.chat_completions(req).await?;
+        Ok(Completion {
+            text: r
+                .choices
+                .first()
+                .map(|c| c.message.content.clone())
+                .unwrap_or_default(),
+            citations: r.citations,
+            usage: r.usage,
+            timings_ms: r.timings_ms,
+        })
+    }
+}
+
+struct OllamaBackend {
+    http: reqwest::Client,
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for OllamaBackend {
+    fn check_configured(&self, _opts: &CompletionOpts) -> Result<()> {
+        crate::ollama::OllamaClient::from_env(self.http.clone()).map(|_| ())
+    }
+
+    fn endpoint(&self, _opts: &CompletionOpts) -> Option<String> {
+        crate::ollama::OllamaClient::from_env(self.http.clone())
+            .ok()
+            .map(|c| c.base_url().to_string())
+    }
+
+    async fn complete(&self, prompt: &Prompt, opts: &CompletionOpts) -> Result<Completion> {
+        let c = crate::ollama::OllamaClient::from_env(self.http.clone())?;
+        let text = c
+            .chat(&prompt.system, &prompt.user, opts.timeout_ms)
+            .await?;
+        Ok(Completion {
+            text,
+            ..Default::default()
+        })
+    }
+}
+
+struct OpenAiCompatBackend {
+    http: reqwest::Client,
+}
+
+impl OpenAiCompatBackend {
+    fn client(&self, opts: &CompletionOpts) -> Result<crate::openai_compat::OpenAiCompatClient> {
+        crate::openai_compat::OpenAiCompatClient::from_env(self.http.clone(), opts.model.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for OpenAiCompatBackend {
+    fn check_configured(&self, opts: &CompletionOpts) -> Result<()> {
+        self.client(opts).map(|_| ())
+    }
+
+    fn endpoint(&self, opts: &CompletionOpts) -> Option<String> {
+        self.client(opts).ok().map(|c| c.base_url().to_string())
+    }
+
+    async fn complete(&self, prompt: &Prompt, opts: &CompletionOpts) -> Result<Completion> {
+        let text = self
+            .client(opts)?
+            .chat(
+                &prompt.system,
+                &prompt.user,
+                opts.timeout_ms,
+                opts.max_tokens,
+                opts.temperature,
+                opts.top_p,
+            )
+            .await?;
+        Ok(Completion {
+            text,
+            ..Default::default()
+        })
+    }
+}
+
+struct AnthropicBackend {
+    http: reqwest::Client,
+}
+
+impl AnthropicBackend {
+    fn client(&self, opts: &CompletionOpts) -> Result<crate::anthropic::AnthropicClient> {
+        crate::anthropic::AnthropicClient::from_env(self.http.clone(), opts.model.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for AnthropicBackend {
+    fn check_configured(&self, opts: &CompletionOpts) -> Result<()> {
+        self.client(opts).map(|_| ())
+    }
+
+    fn network_only(&self) -> bool {
+        true
+    }
+
+    async fn complete(&self, prompt: &Prompt, opts: &CompletionOpts) -> Result<Completion> {
+        let r = self
+            .client(opts)?
+            .messages(
+                &prompt.system,
+                &prompt.user,
+                opts.timeout_ms,
+                opts.max_tokens,
+                opts.temperature,
+                opts.top_p,
+            )
+            .await?;
+        let text = r.text();
+        // Normalize to the OpenAI-style keys the other backends report.
+        let usage = r.usage.map(|u| {
+            serde_json::json!({
+                "prompt_tokens": u.input_tokens,
+                "completion_tokens": u.output_tokens,
+                "total_tokens": u.input_tokens + u.output_tokens,
+            })
+        });
+        Ok(Completion {
+            text,
+            usage,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    #[async_trait::async_trait]
+    impl LlmBackend for Echo {
+        async fn complete(&self, prompt: &Prompt, _opts: &CompletionOpts) -> Result<Completion> {
+            Ok(Completion {
+                text: format!("{}|{}", prompt.system, prompt.user),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn registry_holds_builtins_and_custom_backends() {
+        let mut r = LlmRegistry::with_builtins(reqwest::Client::new());
+        assert_eq!(
+            r.names(),
+            vec!["anthropic", "ollama", "openai_compat", "perplexity"]
+        );
+        assert!(r.get("perplexity").unwrap().network_only());
+        assert!(r.get("anthropic").unwrap().network_only());
+        assert!(r.get("echo").is_none());
+
+        r.register("echo", Arc::new(Echo));
+        let b = r.get("echo").expect("registered");
+        assert!(!b.network_only());
+        assert!(b.check_configured(&CompletionOpts::default()).is_ok());
+        let c = b
+            .complete(
+                &Prompt {
+                    system: "s".to_string(),
+                    user: "u".to_string(),
+                },
+                &CompletionOpts::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(c.text, "s|u");
+    }
+}
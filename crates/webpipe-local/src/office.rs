@@ -0,0 +1,541 @@
+//! Text from Office Open XML spreadsheets and slide decks (`.xlsx`, `.pptx`).
+//!
+//! Both formats are zip archives of XML parts, so a small reader is enough: the central
+//! directory gives each part's offset, stored and deflated entries are supported, and every
+//! part is inflated under a size cap (no zip64, no encryption). `.docx` goes through pandoc
+//! instead (see [`crate::shellout::pandoc_to_text`]).
+
+use quick_xml::events::Event;
+use std::io::Read;
+
+/// Inflated size cap per part (guards against zip bombs).
+const MAX_PART_BYTES: u64 = 32 << 20;
+/// Cap on central directory entries we look at.
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfficeKind {
+    Xlsx,
+    Pptx,
+}
+
+impl OfficeKind {
+    /// Detect from the (lowercased, parameter-free) content type, then the URL path's extension.
+    pub fn detect(ct0: &str, final_url: &str) -> Option<Self> {
+        let ct = ct0.trim().to_ascii_lowercase();
+        // Avoid false positives when a “.xlsx” URL serves an HTML error page.
+        if ct.starts_with("text/html") {
+            return None;
+        }
+        match ct.as_str() {
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => {
+                return Some(Self::Xlsx)
+            }
+            "application/vnd.openxmlformats-officedocument.presentationml.presentation" => {
+                return Some(Self::Pptx)
+            }
+            _ => {}
+        }
+        let path = final_url.split(['?', '#']).next().unwrap_or("");
+        let u = path.to_ascii_lowercase();
+        if u.ends_with(".xlsx") {
+            Some(Self::Xlsx)
+        } else if u.ends_with(".pptx") {
+            Some(Self::Pptx)
+        } else {
+            None
+        }
+    }
+
+    /// `ExtractedText::engine` for this format.
+    pub fn engine(self) -> &'static str {
+        match self {
+            Self::Xlsx => "xlsx",
+            Self::Pptx => "pptx",
+        }
+    }
+
+    /// Warning pushed when the document could not be read.
+    pub fn failed_warning(self) -> &'static str {
+        match self {
+            Self::Xlsx => "xlsx_extract_failed",
+            Self::Pptx => "pptx_extract_failed",
+        }
+    }
+}
+
+/// Text for an `.xlsx` (one CSV-ish block per sheet) or `.pptx` (one block per slide), clipped
+/// to `max_chars`. Errors are stable warning codes.
+pub fn office_to_text(
+    kind: OfficeKind,
+    bytes: &[u8],
+    max_chars: usize,
+) -> Result<String, &'static str> {
+    let zip = Zip::parse(bytes)?;
+    let text = match kind {
+        OfficeKind::Xlsx => xlsx_text(&zip, max_chars)?,
+        OfficeKind::Pptx => pptx_text(&zip, max_chars)?,
+    };
+    let clipped: String = text.chars().take(max_chars).collect();
+    if clipped.chars().any(|c| !c.is_whitespace()) {
+        Ok(clipped)
+    } else {
+        Err("office_empty_output")
+    }
+}
+
+struct Entry {
+    name: String,
+    method: u16,
+    compressed_size: usize,
+    local_offset: usize,
+}
+
+struct Zip<'a> {
+    bytes: &'a [u8],
+    entries: Vec<Entry>,
+}
+
+fn u16_at(b: &[u8], off: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(b.get(off..off + 2)?.try_into().ok()?))
+}
+
+fn u32_at(b: &[u8], off: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(b.get(off..off + 4)?.try_into().ok()?))
+}
+
+impl<'a> Zip<'a> {
+    fn parse(bytes: &'a [u8]) -> Result<Self, &'static str> {
+        const BAD: &str = "office_zip_invalid";
+        // The end-of-central-directory record sits in the last 22 bytes plus a comment (<64 KiB).
+        let last = bytes.len().checked_sub(22).ok_or(BAD)?;
+        let eocd = (last.saturating_sub(0xFFFF)..=last)
+            .rev()
+            .find(|&i| u32_at(bytes, i) == Some(0x0605_4b50))
+            .ok_or(BAD)?;
+        let count = u16_at(bytes, eocd + 10).ok_or(BAD)? as usize;
+        let mut off = u32_at(bytes, eocd + 16).ok_or(BAD)? as usize;
+        let mut entries = Vec::new();
+        for _ in 0..count.min(MAX_ENTRIES) {
+            if u32_at(bytes, off) != Some(0x0201_4b50) {
+                return Err(BAD);
+            }
+            let method = u16_at(bytes, off + 10).ok_or(BAD)?;
+            let compressed_size = u32_at(bytes, off + 20).ok_or(BAD)? as usize;
+            let name_len = u16_at(bytes, off + 28).ok_or(BAD)? as usize;
+            let extra_len = u16_at(bytes, off + 30).ok_or(BAD)? as usize;
+            let comment_len = u16_at(bytes, off + 32).ok_or(BAD)? as usize;
+            let local_offset = u32_at(bytes, off + 42).ok_or(BAD)? as usize;
+            let name = bytes.get(off + 46..off + 46 + name_len).ok_or(BAD)?;
+            entries.push(Entry {
+                name: String::from_utf8_lossy(name).to_string(),
+                method,
+                compressed_size,
+                local_offset,
+            });
+            off += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(Self { bytes, entries })
+    }
+
+    /// Inflated bytes of the part called `name`, if present.
+    fn part(&self, name: &str) -> Result<Option<Vec<u8>>, &'static str> {
+        const BAD: &str = "office_zip_invalid";
+        let Some(e) = self.entries.iter().find(|e| e.name == name) else {
+            return Ok(None);
+        };
+        let b = self.bytes;
+        let off = e.local_offset;
+        if u32_at(b, off) != Some(0x0403_4b50) {
+            return Err(BAD);
+        }
+        let name_len = u16_at(b, off + 26).ok_or(BAD)? as usize;
+        let extra_len = u16_at(b, off + 28).ok_or(BAD)? as usize;
+        let start = off + 30 + name_len + extra_len;
+        let data = b.get(start..start + e.compressed_size).ok_or(BAD)?;
+        let mut out = Vec::new();
+        match e.method {
+            0 => out.extend_from_slice(&data[..data.len().min(MAX_PART_BYTES as usize + 1)]),
+            8 => {
+                flate2::read::DeflateDecoder::new(data)
+                    .take(MAX_PART_BYTES + 1)
+                    .read_to_end(&mut out)
+                    .map_err(|_| BAD)?;
+            }
+            _ => return Err("office_zip_unsupported_compression"),
+        }
+        if out.len() as u64 > MAX_PART_BYTES {
+            return Err("office_part_too_large");
+        }
+        Ok(Some(out))
+    }
+
+    /// Names matching `{prefix}N.xml`, ordered by `N`.
+    fn numbered_parts(&self, prefix: &str) -> Vec<&str> {
+        let mut out: Vec<(u32, &str)> = self
+            .entries
+            .iter()
+            .filter_map(|e| {
+                let n = e.name.strip_prefix(prefix)?.strip_suffix(".xml")?;
+                Some((n.parse().ok()?, e.name.as_str()))
+            })
+            .collect();
+        out.sort();
+        out.into_iter().map(|(_, n)| n).collect()
+    }
+}
+
+fn attr(e: &quick_xml::events::BytesStart<'_>, key: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == key)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.to_string()))
+}
+
+/// Slides as `Slide N` blocks, one line per paragraph (`<a:p>`).
+fn pptx_text(zip: &Zip<'_>, max_chars: usize) -> Result<String, &'static str> {
+    let slides = zip.numbered_parts("ppt/slides/slide");
+    if slides.is_empty() {
+        return Err("pptx_no_slides");
+    }
+    let mut out = String::new();
+    for (i, name) in slides.into_iter().enumerate() {
+        let Some(xml) = zip.part(name)? else {
+            continue;
+        };
+        let mut reader = quick_xml::Reader::from_reader(xml.as_slice());
+        let mut buf = Vec::new();
+        let (mut in_t, mut line, mut lines) = (false, String::new(), Vec::<String>::new());
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Eof) => break,
+                Ok(Event::Start(e)) if e.local_name().as_ref() == b"t" => in_t = true,
+                Ok(Event::Text(t)) if in_t => {
+                    line.push_str(&t.unescape().map(|t| t.to_string()).unwrap_or_default());
+                }
+                Ok(Event::End(e)) => match e.local_name().as_ref() {
+                    b"t" => in_t = false,
+                    b"p" => {
+                        let l = std::mem::take(&mut line);
+                        if !l.trim().is_empty() {
+                            lines.push(l.trim().to_string());
+                        }
+                    }
+                    _ => {}
+                },
+                Ok(_) => {}
+                Err(_) => return Err("pptx_xml_invalid"),
+            }
+            buf.clear();
+        }
+        if lines.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("Slide {}\n{}\n\n", i + 1, lines.join("\n")));
+        if out.len() >= max_chars.saturating_mul(4) {
+            break;
+        }
+    }
+    Ok(out)
+}
+
+/// The `<si>` strings of `xl/sharedStrings.xml` (rich-text runs concatenated).
+fn xlsx_shared_strings(zip: &Zip<'_>) -> Result<Vec<String>, &'static str> {
+    let Some(xml) = zip.part("xl/sharedStrings.xml")? else {
+        return Ok(Vec::new());
+    };
+    let mut reader = quick_xml::Reader::from_reader(xml.as_slice());
+    let mut buf = Vec::new();
+    let (mut out, mut cur, mut in_t, mut in_phonetic) = (Vec::new(), String::new(), false, false);
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Eof) => break,
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"t" => in_t = true,
+                b"rPh" => in_phonetic = true,
+                _ => {}
+            },
+            Ok(Event::Text(t)) if in_t && !in_phonetic => {
+                cur.push_str(&t.unescape().map(|t| t.to_string()).unwrap_or_default());
+            }
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"t" => in_t = false,
+                b"rPh" => in_phonetic = false,
+                b"si" => out.push(std::mem::take(&mut cur)),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(_) => return Err("xlsx_xml_invalid"),
+        }
+        buf.clear();
+    }
+    Ok(out)
+}
+
+/// Zero-based column index from a cell reference like `AB12`.
+fn column_index(cell_ref: &str) -> Option<usize> {
+    let letters: Vec<u8> = cell_ref
+        .bytes()
+        .take_while(|b| b.is_ascii_alphabetic())
+        .collect();
+    if letters.is_empty() {
+        return None;
+    }
+    let n = letters.iter().fold(0usize, |n, b| {
+        n.saturating_mul(26) + (b.to_ascii_uppercase() - b'A' + 1) as usize
+    });
+    Some(n - 1)
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Sheets as `# <name>` blocks of comma-separated rows (empty rows skipped).
+fn xlsx_text(zip: &Zip<'_>, max_chars: usize) -> Result<String, &'static str> {
+    let sheets = zip.numbered_parts("xl/worksheets/sheet");
+    if sheets.is_empty() {
+        return Err("xlsx_no_sheets");
+    }
+    let shared = xlsx_shared_strings(zip)?;
+    // Sheet names in workbook order; `sheetN.xml` numbering follows it for typical files.
+    let mut names = Vec::new();
+    if let Some(xml) = zip.part("xl/workbook.xml")? {
+        let mut reader = quick_xml::Reader::from_reader(xml.as_slice());
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Eof) | Err(_) => break,
+                Ok(Event::Start(e) | Event::Empty(e)) if e.local_name().as_ref() == b"sheet" => {
+                    names.extend(attr(&e, b"name"));
+                }
+                Ok(_) => {}
+            }
+            buf.clear();
+        }
+    }
+
+    let mut out = String::new();
+    for (i, part) in sheets.into_iter().enumerate() {
+        let Some(xml) = zip.part(part)? else {
+            continue;
+        };
+        let name = names
+            .get(i)
+            .cloned()
+            .unwrap_or_else(|| format!("Sheet{}", i + 1));
+        let mut rows: Vec<String> = Vec::new();
+        let mut row: Vec<String> = Vec::new();
+        let (mut col, mut cell_type, mut value) = (0usize, String::new(), String::new());
+        let mut in_value = false;
+        let mut reader = quick_xml::Reader::from_reader(xml.as_slice());
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Eof) => break,
+                Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                    b"row" => row.clear(),
+                    b"c" => {
+                        col = attr(&e, b"r")
+                            .and_then(|r| column_index(&r))
+                            .unwrap_or(row.len());
+                        cell_type = attr(&e, b"t").unwrap_or_default();
+                        value.clear();
+                    }
+                    b"v" | b"t" => in_value = true,
+                    _ => {}
+                },
+                Ok(Event::Text(t)) if in_value => {
+                    value.push_str(&t.unescape().map(|t| t.to_string()).unwrap_or_default());
+                }
+                Ok(Event::End(e)) => match e.local_name().as_ref() {
+                    b"v" | b"t" => in_value = false,
+                    b"c" => {
+                        let v = match cell_type.as_str() {
+                            "s" => value
+                                .trim()
+                                .parse::<usize>()
+                                .ok()
+                                .and_then(|i| shared.get(i).cloned())
+                                .unwrap_or_default(),
+                            "b" => if value.trim() == "1" { "TRUE" } else { "FALSE" }.to_string(),
+                            _ => value.clone(),
+                        };
+                        // Cap the column so a stray `XFD1` can't allocate a huge row.
+                        let col = col.min(row.len() + 1_000);
+                        if row.len() <= col {
+                            row.resize(col + 1, String::new());
+                        }
+                        row[col] = v;
+                    }
+                    b"row" => {
+                        while row.last().is_some_and(|c| c.trim().is_empty()) {
+                            row.pop();
+                        }
+                        if !row.is_empty() {
+                            let cells: Vec<String> = row.iter().map(|c| csv_field(c)).collect();
+                            rows.push(cells.join(","));
+                        }
+                    }
+                    _ => {}
+                },
+                Ok(_) => {}
+                Err(_) => return Err("xlsx_xml_invalid"),
+            }
+            buf.clear();
+        }
+        if rows.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("# {name}\n{}\n\n", rows.join("\n")));
+        if out.len() >= max_chars.saturating_mul(4) {
+            break;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A zip of `(name, contents)` parts; `deflate` compresses them (method 8), else stored.
+    fn zip_of(parts: &[(&str, &str)], deflate: bool) -> Vec<u8> {
+        use std::io::Write;
+        let (mut out, mut central) = (Vec::new(), Vec::new());
+        for (name, body) in parts {
+            let data = if deflate {
+                let mut enc =
+                    flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                enc.write_all(body.as_bytes()).unwrap();
+                enc.finish().unwrap()
+            } else {
+                body.as_bytes().to_vec()
+            };
+            let method: u16 = if deflate { 8 } else { 0 };
+            let offset = out.len() as u32;
+            out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+            out.extend_from_slice(&[20, 0, 0, 0]);
+            out.extend_from_slice(&method.to_le_bytes());
+            out.extend_from_slice(&[0; 8]); // time, date, crc (unchecked)
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            out.extend_from_slice(&[0, 0]);
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&data);
+
+            central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            central.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            central.extend_from_slice(&method.to_le_bytes());
+            central.extend_from_slice(&[0; 8]);
+            central.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            central.extend_from_slice(&[0; 12]); // extra, comment, disk, attrs
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+        let cd_offset = out.len() as u32;
+        out.extend_from_slice(&central);
+        out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&(parts.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(parts.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        out.extend_from_slice(&cd_offset.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out
+    }
+
+    #[test]
+    fn xlsx_flattens_sheets_to_csv_rows() {
+        let bytes = zip_of(
+            &[
+                (
+                    "xl/workbook.xml",
+                    r#"<workbook><sheets><sheet name="Budget" sheetId="1"/><sheet name="Notes" sheetId="2"/></sheets></workbook>"#,
+                ),
+                (
+                    "xl/sharedStrings.xml",
+                    r#"<sst><si><t>Item</t></si><si><t>Cost</t></si><si><r><t>Coffee, </t></r><r><t>beans</t></r></si></sst>"#,
+                ),
+                (
+                    "xl/worksheets/sheet1.xml",
+                    r#"<worksheet><sheetData>
+<row r="1"><c r="A1" t="s"><v>0</v></c><c r="B1" t="s"><v>1</v></c></row>
+<row r="2"><c r="A2" t="s"><v>2</v></c><c r="C2"><v>12.5</v></c></row>
+<row r="3"></row>
+</sheetData></worksheet>"#,
+                ),
+                (
+                    "xl/worksheets/sheet2.xml",
+                    r#"<worksheet><sheetData><row r="1"><c r="B1" t="inlineStr"><is><t>Paid &amp; filed</t></is></c><c r="C1" t="b"><v>1</v></c></row></sheetData></worksheet>"#,
+                ),
+            ],
+            true,
+        );
+        let text = office_to_text(OfficeKind::Xlsx, &bytes, 10_000).unwrap();
+        assert_eq!(
+            text,
+            "# Budget\nItem,Cost\n\"Coffee, beans\",,12.5\n\n# Notes\n,Paid & filed,TRUE\n\n"
+        );
+        assert_eq!(
+            office_to_text(OfficeKind::Xlsx, &bytes, 10).unwrap(),
+            "# Budget\nI"
+        );
+    }
+
+    #[test]
+    fn pptx_emits_one_block_per_slide_in_numeric_order() {
+        let slide = |t: &str| {
+            format!(
+                r#"<p:sld xmlns:a="a" xmlns:p="p"><p:cSld><p:spTree><p:sp><p:txBody>{t}</p:txBody></p:sp></p:spTree></p:cSld></p:sld>"#
+            )
+        };
+        let s1 = slide("<a:p><a:r><a:t>Roadmap</a:t></a:r></a:p>");
+        let s2 = slide("<a:p><a:r><a:t>Ship </a:t></a:r><a:r><a:t>v2</a:t></a:r></a:p><a:p><a:r><a:t>Hire</a:t></a:r></a:p>");
+        let s10 = slide("<a:p><a:r><a:t>Questions?</a:t></a:r></a:p>");
+        let bytes = zip_of(
+            &[
+                ("ppt/slides/slide10.xml", s10.as_str()),
+                ("ppt/slides/slide2.xml", s2.as_str()),
+                ("ppt/slides/slide1.xml", s1.as_str()),
+                ("ppt/slides/_rels/slide1.xml.rels", "<Relationships/>"),
+            ],
+            false,
+        );
+        let text = office_to_text(OfficeKind::Pptx, &bytes, 10_000).unwrap();
+        assert_eq!(
+            text,
+            "Slide 1\nRoadmap\n\nSlide 2\nShip v2\nHire\n\nSlide 3\nQuestions?\n\n"
+        );
+    }
+
+    #[test]
+    fn detection_and_failures_are_explicit() {
+        let ct = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+        assert_eq!(
+            OfficeKind::detect(ct, "https://x/a"),
+            Some(OfficeKind::Xlsx)
+        );
+        assert_eq!(
+            OfficeKind::detect("application/octet-stream", "https://x/deck.pptx"),
+            Some(OfficeKind::Pptx)
+        );
+        assert_eq!(OfficeKind::detect("text/html", "https://x/a.xlsx"), None);
+        assert_eq!(
+            office_to_text(OfficeKind::Xlsx, b"not a zip", 100),
+            Err("office_zip_invalid")
+        );
+        let empty = zip_of(&[("docProps/app.xml", "<x/>")], false);
+        assert_eq!(
+            office_to_text(OfficeKind::Pptx, &empty, 100),
+            Err("pptx_no_slides")
+        );
+    }
+}
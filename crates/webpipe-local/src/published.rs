@@ -0,0 +1,265 @@
+//! Published dates from HTML metadata, and the recency factor `web_search_extract` uses to
+//! weight chunk scores by document age.
+//!
+//! Only machine-readable metadata is consulted (meta tags, `<time datetime>`, JSON-LD); dates in
+//! visible prose are too ambiguous to trust. Parsing accepts ISO 8601 / RFC 3339 prefixes
+//! (`YYYY-MM-DD`, optionally followed by a time and a `Z`/`±HH:MM` offset).
+
+use html_scraper::{Html, Selector};
+
+/// `<meta>` names/properties carrying a publication date, in priority order.
+const META_KEYS: &[&str] = &[
+    "article:published_time",
+    "og:published_time",
+    "datepublished",
+    "citation_publication_date",
+    "citation_date",
+    "dc.date.issued",
+    "dc.date",
+    "dcterms.date",
+    "publishdate",
+    "pubdate",
+    "date",
+];
+
+/// The document's published date as written in its metadata (unparsed), if any parses.
+pub fn published_time(html: &str) -> Option<String> {
+    let doc = Html::parse_document(html);
+    let accept = |v: &str| {
+        let v = v.trim();
+        parse_date_epoch_s(v).is_some().then(|| v.to_string())
+    };
+
+    if let Ok(sel) = Selector::parse("meta[content]") {
+        let mut best: Option<(usize, String)> = None;
+        for m in doc.select(&sel) {
+            let el = m.value();
+            let key = ["property", "name", "itemprop"]
+                .iter()
+                .find_map(|a| el.attr(a))
+                .map(str::to_ascii_lowercase);
+            let Some(rank) = key.and_then(|k| META_KEYS.iter().position(|x| *x == k)) else {
+                continue;
+            };
+            if best.as_ref().is_some_and(|(r, _)| *r <= rank) {
+                continue;
+            }
+            if let Some(v) = el.attr("content").and_then(accept) {
+                best = Some((rank, v));
+            }
+        }
+        if let Some((_, v)) = best {
+            return Some(v);
+        }
+    }
+
+    if let Ok(sel) = Selector::parse(r#"script[type="application/ld+json"]"#) {
+        for s in doc.select(&sel) {
+            let txt = s.text().collect::<String>();
+            let Ok(v) = serde_json::from_str::<serde_json::Value>(&txt) else {
+                continue;
+            };
+            if let Some(d) = json_ld_date(&v).and_then(accept) {
+                return Some(d);
+            }
+        }
+    }
+
+    // A `<time pubdate>` beats the first `<time>` (which may be an "updated" stamp).
+    let pubdate = Selector::parse("time[pubdate][datetime]").ok()?;
+    let any = Selector::parse("time[datetime]").ok()?;
+    let t = doc
+        .select(&pubdate)
+        .next()
+        .or_else(|| doc.select(&any).next())?;
+    t.value().attr("datetime").and_then(accept)
+}
+
+fn json_ld_date(v: &serde_json::Value) -> Option<&str> {
+    match v {
+        serde_json::Value::Array(xs) => xs.iter().find_map(json_ld_date),
+        serde_json::Value::Object(m) => m
+            .get("datePublished")
+            .and_then(|d| d.as_str())
+            .or_else(|| m.get("@graph").and_then(json_ld_date)),
+        _ => None,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's `days_from_civil`).
+pub(crate) fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Inverse of [`days_from_civil`]: `(year, month, day)` for days since 1970-01-01.
+pub(crate) fn civil_from_days(z: i64) -> (i64, i64, i64) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(m <= 2), m, d)
+}
+
+fn digits(s: &str, n: usize) -> Option<i64> {
+    let d = s.get(..n)?;
+    d.bytes()
+        .all(|b| b.is_ascii_digit())
+        .then(|| d.parse().ok())?
+}
+
+/// Unix seconds for an ISO 8601 date or date-time (see the module docs). A missing time means
+/// midnight and a missing offset means UTC.
+pub fn parse_date_epoch_s(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let y = digits(s, 4)?;
+    let (m, d, date_len) = if s.as_bytes().get(4) == Some(&b'-') {
+        if s.as_bytes().get(7) != Some(&b'-') {
+            return None;
+        }
+        (digits(&s[5..], 2)?, digits(s.get(8..)?, 2)?, 10)
+    } else {
+        (digits(s.get(4..)?, 2)?, digits(s.get(6..)?, 2)?, 8)
+    };
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return None;
+    }
+    let mut secs = days_from_civil(y, m, d) * 86_400;
+
+    let rest = &s[date_len..];
+    let rest = match rest.as_bytes().first() {
+        None => return Some(secs),
+        Some(b'T' | b't' | b' ') => &rest[1..],
+        Some(_) => return None,
+    };
+    let hh = digits(rest, 2)?;
+    let mm = rest
+        .get(2..)
+        .and_then(|r| r.strip_prefix(':'))
+        .and_then(|r| digits(r, 2))
+        .unwrap_or(0);
+    if hh > 23 || mm > 59 {
+        return None;
+    }
+    secs += hh * 3600 + mm * 60;
+    // Seconds and fractions don't matter at day granularity; only the offset does.
+    if let Some(i) = rest.find(['+', '-']) {
+        let sign = if rest.as_bytes()[i] == b'-' { 1 } else { -1 };
+        let off = &rest[i + 1..];
+        let oh = digits(off, 2)?;
+        let om = digits(off.get(2..)?.trim_start_matches(':'), 2).unwrap_or(0);
+        secs += sign * (oh * 3600 + om * 60);
+    }
+    Some(secs)
+}
+
+/// Score multiplier for a document published at `published_epoch_s`:
+/// `1 + boost * 0.5^(age_days / half_life_days)`, so a brand-new page gets `1 + boost` and the
+/// bonus halves every `half_life_days`. Undated pages (and a non-positive boost) get `1.0`;
+/// future dates count as age 0.
+pub fn recency_factor(
+    published_epoch_s: Option<i64>,
+    now_epoch_s: i64,
+    boost: f64,
+    half_life_days: f64,
+) -> f64 {
+    let Some(t) = published_epoch_s else {
+        return 1.0;
+    };
+    if boost <= 0.0 || half_life_days <= 0.0 {
+        return 1.0;
+    }
+    let age_days = ((now_epoch_s - t).max(0) as f64) / 86_400.0;
+    1.0 + boost * 0.5f64.powf(age_days / half_life_days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_date_epoch_s_handles_dates_times_and_offsets() {
+        assert_eq!(parse_date_epoch_s("1970-01-01"), Some(0));
+        assert_eq!(parse_date_epoch_s("2000-03-01"), Some(951_868_800));
+        assert_eq!(parse_date_epoch_s("20000301"), Some(951_868_800));
+        assert_eq!(
+            parse_date_epoch_s("2000-03-01T12:30:15.250Z"),
+            Some(951_868_800 + 12 * 3600 + 30 * 60)
+        );
+        assert_eq!(
+            parse_date_epoch_s("2000-03-01T02:00:00+02:00"),
+            Some(951_868_800)
+        );
+        assert_eq!(
+            parse_date_epoch_s("2000-02-29 22:00-0200"),
+            Some(951_868_800)
+        );
+        for bad in [
+            "",
+            "March 1, 2000",
+            "2000-13-01",
+            "2000-03",
+            "2000-03-01X",
+            "2000-03-01T25:00",
+        ] {
+            assert_eq!(parse_date_epoch_s(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn civil_from_days_inverts_days_from_civil() {
+        for (y, m, d) in [
+            (1970, 1, 1),
+            (2000, 2, 29),
+            (2000, 3, 1),
+            (1969, 12, 31),
+            (2024, 12, 31),
+        ] {
+            assert_eq!(civil_from_days(days_from_civil(y, m, d)), (y, m, d));
+        }
+    }
+
+    #[test]
+    fn published_time_prefers_meta_then_json_ld_then_time() {
+        let meta = r#"<html><head>
+<meta name="date" content="2019-01-01">
+<meta property="article:published_time" content="2021-06-05T10:00:00Z">
+</head><body><time datetime="2001-01-01">old</time></body></html>"#;
+        assert_eq!(
+            published_time(meta).as_deref(),
+            Some("2021-06-05T10:00:00Z")
+        );
+
+        let ld = r#"<html><head><meta name="date" content="sometime last year">
+<script type="application/ld+json">{"@graph":[{"@type":"WebPage"},{"@type":"Article","datePublished":"2022-02-02"}]}</script>
+</head><body></body></html>"#;
+        assert_eq!(published_time(ld).as_deref(), Some("2022-02-02"));
+
+        let time = r#"<p>Updated <time datetime="2023-01-09">Jan 9</time>,
+posted <time pubdate datetime="2023-01-02">Jan 2</time></p>"#;
+        assert_eq!(published_time(time).as_deref(), Some("2023-01-02"));
+
+        assert_eq!(published_time("<p>Published March 2020</p>"), None);
+    }
+
+    #[test]
+    fn recency_factor_halves_the_bonus_each_half_life() {
+        let now = parse_date_epoch_s("2024-01-31").unwrap();
+        let f = |d: &str| recency_factor(parse_date_epoch_s(d), now, 1.0, 30.0);
+        assert!((f("2024-01-31") - 2.0).abs() < 1e-9);
+        assert!((f("2024-01-01") - 1.5).abs() < 1e-9);
+        assert!((f("2030-01-01") - 2.0).abs() < 1e-9);
+        assert!(f("2024-01-01") > f("2023-01-01"));
+        assert_eq!(recency_factor(None, now, 1.0, 30.0), 1.0);
+        assert_eq!(recency_factor(Some(now), now, 0.0, 30.0), 1.0);
+    }
+}
@@ -0,0 +1,169 @@
+//! Typed shapes for the extraction tools' JSON output.
+//!
+//! The MCP layer builds these and serializes them with `serde_json::to_value`, then layers the
+//! optional, tool-specific fields (warnings, semantic rerank, links, ...) onto the resulting
+//! object. JSON objects are key-sorted, so field order here doesn't affect the output; what must
+//! stay stable is the field names and which fields are omitted when empty.
+
+use serde::Serialize;
+
+use crate::extract::{ExtractedStructure, ScoredChunk};
+
+/// A ranked chunk attributed to its source URL (`top_chunks[]` / `contrasting_chunks[]`).
+#[derive(Debug, Clone, Serialize)]
+pub struct Chunk {
+    pub url: String,
+    pub score: u64,
+    pub start_char: usize,
+    pub end_char: usize,
+    pub text: String,
+    /// Why this chunk was picked as a counterpoint (`balance=true` only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contrast: Option<serde_json::Value>,
+}
+
+/// The `extract` object: `web_extract.extract` and `web_search_extract.results[].extract`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractResult {
+    pub engine: String,
+    pub width: usize,
+    pub max_chars: usize,
+    pub text_chars: usize,
+    pub text_truncated: bool,
+    /// `extraction_confidence`, rounded to two decimals.
+    pub confidence: f32,
+    pub top_chunks: usize,
+    pub max_chunk_chars: usize,
+    pub chunks: Vec<ScoredChunk>,
+    /// Full text (`include_text=true`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Single-line preview (per-URL results only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_preview: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_preview_source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_preview_truncated: Option<bool>,
+    /// The quality scorecard (`kind: "webpipe_extract_quality"`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structure: Option<ExtractedStructure>,
+}
+
+impl ExtractResult {
+    /// Round a raw `extraction_confidence` the way the tools report it.
+    pub fn rounded_confidence(confidence: f32) -> f32 {
+        (confidence * 100.0).round() / 100.0
+    }
+}
+
+/// One successfully fetched URL in `web_search_extract.results[]`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PerUrlResult {
+    pub url: String,
+    pub ok: bool,
+    pub fetch_backend: String,
+    pub final_url: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub bytes: usize,
+    pub truncated: bool,
+    pub fetch_source: String,
+    /// Fallback attempts (firecrawl/render), `null` when none ran.
+    pub attempts: serde_json::Value,
+    pub extract: ExtractResult,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_time: Option<String>,
+    pub elapsed_ms: u128,
+}
+
+/// The core of a `web_search_extract` response.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchExtractResult {
+    pub ok: bool,
+    /// `"search"` or `"urls"`.
+    pub mode: String,
+    pub provider: String,
+    pub query: String,
+    pub request: serde_json::Value,
+    pub url_count_in: usize,
+    pub url_count_used: usize,
+    /// [`PerUrlResult`] objects, plus per-URL error objects for URLs that failed to fetch.
+    pub results: Vec<serde_json::Value>,
+    pub top_chunks: Vec<Chunk>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(start: usize, text: &str) -> ScoredChunk {
+        ScoredChunk {
+            start_char: start,
+            end_char: start + text.chars().count(),
+            score: 7,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn per_url_result_serializes_to_the_tool_json_shape() {
+        let r = PerUrlResult {
+            url: "https://example.com/a".to_string(),
+            ok: true,
+            fetch_backend: "local".to_string(),
+            final_url: "https://example.com/a".to_string(),
+            status: 200,
+            content_type: None,
+            bytes: 42,
+            truncated: false,
+            fetch_source: "network".to_string(),
+            attempts: serde_json::Value::Null,
+            extract: ExtractResult {
+                engine: "html_main".to_string(),
+                width: 100,
+                max_chars: 20_000,
+                text_chars: 11,
+                text_truncated: false,
+                confidence: ExtractResult::rounded_confidence(0.749),
+                top_chunks: 5,
+                max_chunk_chars: 500,
+                chunks: vec![chunk(0, "hello world")],
+                text: None,
+                text_preview: Some("hello world".to_string()),
+                text_preview_source: Some("top_chunk".to_string()),
+                text_preview_truncated: Some(false),
+                quality: None,
+                structure: None,
+            },
+            published_time: None,
+            elapsed_ms: 3,
+        };
+        let expected = r#"{"attempts":null,"bytes":42,"content_type":null,"elapsed_ms":3,"extract":{"chunks":[{"end_char":11,"score":7,"start_char":0,"text":"hello world"}],"confidence":0.75,"engine":"html_main","max_chars":20000,"max_chunk_chars":500,"text_chars":11,"text_preview":"hello world","text_preview_source":"top_chunk","text_preview_truncated":false,"text_truncated":false,"top_chunks":5,"width":100},"fetch_backend":"local","fetch_source":"network","final_url":"https://example.com/a","ok":true,"status":200,"truncated":false,"url":"https://example.com/a"}"#;
+        let got = serde_json::to_value(&r).unwrap().to_string();
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn chunk_omits_contrast_unless_set() {
+        let mut c = Chunk {
+            url: "https://example.com/".to_string(),
+            score: 3,
+            start_char: 1,
+            end_char: 2,
+            text: "x".to_string(),
+            contrast: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&c).unwrap().to_string(),
+            r#"{"end_char":2,"score":3,"start_char":1,"text":"x","url":"https://example.com/"}"#
+        );
+        c.contrast = Some(serde_json::json!({"cue": "however"}));
+        assert_eq!(
+            serde_json::to_value(&c).unwrap()["contrast"]["cue"],
+            "however"
+        );
+    }
+}
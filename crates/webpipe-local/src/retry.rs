@@ -0,0 +1,153 @@
+//! Retries for transient fetch failures (`503`s, resets, timeouts).
+//!
+//! Off by default (`max_attempts = 1`): a retry costs the caller wall-clock time, so it is opted
+//! into via [`RetryPolicy::from_env`] or [`crate::LocalFetcher::with_retry_policy`].
+//!
+//! Delays grow exponentially from `base_delay_ms` with "equal jitter" (half fixed, half random),
+//! capped at `max_delay_ms`. A `Retry-After` on `429`/`503` replaces the computed delay, still
+//! capped. Only idempotent requests are retried.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total tries, including the first (`1` = never retry).
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for each later one.
+    pub base_delay_ms: u64,
+    /// Upper bound for any single delay, including a server's `Retry-After`.
+    pub max_delay_ms: u64,
+    /// Response statuses worth another try. Connection and timeout errors always are.
+    pub retry_on: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay_ms: 250,
+            max_delay_ms: 10_000,
+            retry_on: vec![408, 429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// `WEBPIPE_FETCH_RETRY_MAX_ATTEMPTS` (default 1 = off), `WEBPIPE_FETCH_RETRY_BASE_DELAY_MS`
+    /// (default 250), `WEBPIPE_FETCH_RETRY_MAX_DELAY_MS` (default 10000).
+    pub fn from_env() -> Self {
+        fn num(k: &str) -> Option<u64> {
+            std::env::var(k).ok().and_then(|s| s.trim().parse().ok())
+        }
+        let d = Self::default();
+        Self {
+            max_attempts: num("WEBPIPE_FETCH_RETRY_MAX_ATTEMPTS")
+                .map(|n| n.clamp(1, 10) as u32)
+                .unwrap_or(d.max_attempts),
+            base_delay_ms: num("WEBPIPE_FETCH_RETRY_BASE_DELAY_MS").unwrap_or(d.base_delay_ms),
+            max_delay_ms: num("WEBPIPE_FETCH_RETRY_MAX_DELAY_MS").unwrap_or(d.max_delay_ms),
+            ..d
+        }
+    }
+
+    pub fn retries_status(&self, status: u16) -> bool {
+        self.retry_on.contains(&status)
+    }
+
+    /// Delay before retry number `retry` (1-based). `retry_after` is the server's hint, if any.
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        let cap = Duration::from_millis(self.max_delay_ms);
+        if let Some(d) = retry_after {
+            return d.min(cap);
+        }
+        let exp = self
+            .base_delay_ms
+            .saturating_mul(1u64 << retry.saturating_sub(1).min(20))
+            .min(self.max_delay_ms);
+        let half = exp / 2;
+        Duration::from_millis(half + jitter(half + 1))
+    }
+}
+
+/// Cheap uniform-ish value in `0..n` (no RNG dependency; quality doesn't matter here).
+fn jitter(n: u64) -> u64 {
+    let t = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or(0);
+    t.wrapping_mul(0x9E37_79B9_7F4A_7C15) % n.max(1)
+}
+
+/// Parse a `Retry-After` value: delta-seconds or an IMF-fixdate
+/// (`Sun, 06 Nov 1994 08:49:37 GMT`). Dates in the past mean "now".
+pub fn parse_retry_after(v: &str, now_epoch_s: u64) -> Option<Duration> {
+    let v = v.trim();
+    if let Ok(s) = v.parse::<u64>() {
+        return Some(Duration::from_secs(s));
+    }
+    let parts: Vec<&str> = v.split_whitespace().collect();
+    let [_, day, mon, year, hms, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let m = MONTHS.iter().position(|x| x == mon)? as i64 + 1;
+    let d: i64 = day.parse().ok()?;
+    let y: i64 = year.parse().ok()?;
+    let mut t = hms.split(':').map(|x| x.parse::<i64>().ok());
+    let (hh, mm, ss) = (t.next()??, t.next()??, t.next()??);
+    if !(1..=31).contains(&d) || hh > 23 || mm > 59 || ss > 60 {
+        return None;
+    }
+    let at = crate::published::days_from_civil(y, m, d) * 86_400 + hh * 3600 + mm * 60 + ss;
+    Some(Duration::from_secs((at - now_epoch_s as i64).max(0) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_grow_with_jitter_and_respect_the_cap() {
+        let p = RetryPolicy {
+            max_attempts: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 300,
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            let d1 = p.delay(1, None).as_millis();
+            assert!((50..=100).contains(&d1), "{d1}");
+            let d2 = p.delay(2, None).as_millis();
+            assert!((100..=200).contains(&d2), "{d2}");
+            let d9 = p.delay(9, None).as_millis();
+            assert!((150..=300).contains(&d9), "{d9}");
+        }
+        assert_eq!(
+            p.delay(1, Some(Duration::from_secs(60))),
+            Duration::from_millis(300)
+        );
+        assert_eq!(
+            p.delay(1, Some(Duration::from_millis(20))),
+            Duration::from_millis(20)
+        );
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_http_dates() {
+        assert_eq!(parse_retry_after(" 7 ", 0), Some(Duration::from_secs(7)));
+        // 1994-11-06T08:49:37Z = 784111777.
+        let v = "Sun, 06 Nov 1994 08:49:37 GMT";
+        assert_eq!(
+            parse_retry_after(v, 784_111_770),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(
+            parse_retry_after(v, 784_111_800),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(parse_retry_after("soon", 0), None);
+        assert_eq!(parse_retry_after("Sun, 06 Foo 1994 08:49:37 GMT", 0), None);
+    }
+}
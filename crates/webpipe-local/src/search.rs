@@ -45,6 +45,71 @@ fn tavily_endpoint_from_env() -> Option<String> {
         .filter(|s| !s.is_empty())
 }
 
+fn ddg_endpoint_from_env() -> Option<String> {
+    std::env::var("WEBPIPE_DDG_ENDPOINT")
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Warning code for a date filter (`freshness`/`after`/`before`) the provider had to drop.
+pub const DATE_FILTER_UNSUPPORTED: &str = "date_filter_unsupported";
+
+/// Date filters from a query, split into what providers can map and whether anything given
+/// was unusable (an unknown `freshness`, or `after`/`before` that isn't a date).
+struct DateFilter<'a> {
+    window: Option<&'static str>,
+    after: Option<&'a str>,
+    before: Option<&'a str>,
+    invalid: bool,
+}
+
+impl<'a> DateFilter<'a> {
+    fn of(q: &'a SearchQuery) -> Self {
+        let (after, before) = q.date_range();
+        let window = q.freshness_window();
+        let given = |v: &Option<String>| v.as_deref().is_some_and(|s| !s.trim().is_empty());
+        let invalid = (given(&q.freshness) && window.is_none())
+            || (given(&q.after) && after.is_none())
+            || (given(&q.before) && before.is_none());
+        Self {
+            window,
+            after,
+            before,
+            invalid,
+        }
+    }
+
+    fn has_range(&self) -> bool {
+        self.after.is_some() || self.before.is_some()
+    }
+
+    /// `warnings` for a provider that dropped part of the filter.
+    fn warnings(&self, dropped: bool) -> Vec<String> {
+        if dropped || self.invalid {
+            vec![DATE_FILTER_UNSUPPORTED.to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+fn today_iso() -> String {
+    let days = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+    let (y, m, d) = crate::published::civil_from_days(days);
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+/// SearXNG's page size is an instance setting; `pageno` math assumes the usual default.
+const SEARXNG_PAGE_SIZE: usize = 10;
+/// Brave serves at most 10 pages (`offset` 0..=9).
+const BRAVE_MAX_PAGE: usize = 9;
+/// Tavily has no offset: pages come from over-fetching, and one call returns at most this many.
+const TAVILY_MAX_RESULTS: usize = 20;
+
 pub fn searxng_endpoints_from_env() -> Vec<String> {
     let mut out: Vec<String> = Vec::new();
 
@@ -91,6 +156,15 @@ pub struct SearxngSearchProvider {
     endpoints: Vec<String>,
 }
 
+/// Keyless provider that scrapes DuckDuckGo's HTML-only results page.
+///
+/// There is no API contract here: if the markup stops matching what we parse, searches fail
+/// with an `Error::Search` naming the layout rather than silently returning nothing.
+#[derive(Debug, Clone)]
+pub struct DuckDuckGoProvider {
+    client: reqwest::Client,
+}
+
 impl TavilySearchProvider {
     pub fn from_env(client: reqwest::Client) -> Result<Self> {
         let api_key = tavily_api_key_from_env().ok_or_else(|| {
@@ -186,12 +260,25 @@ pub async fn searxng_search_at_endpoint(
     let endpoint_search = SearxngSearchProvider::endpoint_search_for(base_endpoint);
     let mut req = client
         .get(endpoint_search)
-        .query(&[("q", q.query.as_str()), ("format", "json")]);
+        .query(&[("q", q.site_scoped_query().as_str()), ("format", "json")]);
 
     // Best-effort hints: SearXNG supports `language` on many instances.
     if let Some(lang) = q.language.as_deref() {
         req = req.query(&[("language", lang)]);
     }
+    // SearXNG has `time_range` (day/week/month/year) but no absolute dates.
+    let dates = DateFilter::of(q);
+    if let Some(w) = dates.window {
+        req = req.query(&[("time_range", w)]);
+    }
+    let warnings = dates.warnings(dates.has_range());
+    // Offsets that don't land on a page boundary skip into the page.
+    let offset = q.offset.unwrap_or(0);
+    let skip = offset % SEARXNG_PAGE_SIZE;
+    let pageno = offset / SEARXNG_PAGE_SIZE + 1;
+    if pageno > 1 {
+        req = req.query(&[("pageno", pageno.to_string())]);
+    }
 
     let resp = req
         .timeout(std::time::Duration::from_millis(timeout_ms))
@@ -209,8 +296,10 @@ pub async fn searxng_search_at_endpoint(
         .map_err(|e| Error::Search(e.to_string()))?;
 
     let mut out = Vec::new();
+    let mut page_len = 0;
     if let Some(rs) = parsed.results {
-        for r in rs.into_iter().take(max_results) {
+        page_len = rs.len();
+        for r in rs.into_iter().skip(skip).take(max_results) {
             let Some(url) = r.url else { continue };
             out.push(SearchResult {
                 url,
@@ -223,6 +312,8 @@ pub async fn searxng_search_at_endpoint(
 
     let mut timings_ms = BTreeMap::new();
     timings_ms.insert("search".to_string(), t0.elapsed().as_millis());
+    // A full page (or leftovers on this one) suggests there is a next page.
+    let has_more = page_len >= SEARXNG_PAGE_SIZE || page_len > skip + out.len();
 
     Ok(SearchResponse {
         results: out,
@@ -231,12 +322,21 @@ pub async fn searxng_search_at_endpoint(
         // wants to map it to a budget externally.
         cost_units: 0,
         timings_ms,
+        warnings,
+        offset,
+        has_more,
     })
 }
 
 #[derive(Debug, Deserialize)]
 struct BraveWebSearchResponse {
     web: Option<BraveWeb>,
+    query: Option<BraveQueryInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BraveQueryInfo {
+    more_results_available: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -266,12 +366,23 @@ impl SearchProvider for BraveSearchProvider {
             .client
             .get(Self::endpoint())
             .header("X-Subscription-Token", &self.api_key)
-            .query(&[("q", q.query.as_str())]);
+            .query(&[("q", q.site_scoped_query().as_str())]);
 
         if let Some(n) = q.max_results {
             // Brave uses `count` for result count.
             req = req.query(&[("count", n.to_string())]);
         }
+        // Brave's `offset` counts pages of `count` results, not results.
+        let count = q.max_results.unwrap_or(20).clamp(1, 20);
+        let page = (q.offset.unwrap_or(0) / count).min(BRAVE_MAX_PAGE);
+        let skip = q
+            .offset
+            .unwrap_or(0)
+            .saturating_sub(page * count)
+            .min(count);
+        if page > 0 {
+            req = req.query(&[("offset", page.to_string())]);
+        }
         if let Some(lang) = q.language.as_deref() {
             // Best-effort: Brave docs include optional knobs; treat these as hints.
             req = req.query(&[("search_lang", lang)]);
@@ -279,6 +390,22 @@ impl SearchProvider for BraveSearchProvider {
         if let Some(country) = q.country.as_deref() {
             req = req.query(&[("country", country)]);
         }
+        // Brave's `freshness` takes either `pd`/`pw`/`pm`/`py` or `YYYY-MM-DDtoYYYY-MM-DD`, not
+        // both: an explicit range wins and a window given alongside it is dropped.
+        let dates = DateFilter::of(q);
+        let freshness = if dates.has_range() {
+            Some(format!(
+                "{}to{}",
+                dates.after.unwrap_or("1970-01-01"),
+                dates.before.map(str::to_string).unwrap_or_else(today_iso)
+            ))
+        } else {
+            dates.window.map(|w| format!("p{}", &w[..1]))
+        };
+        if let Some(f) = freshness {
+            req = req.query(&[("freshness", f)]);
+        }
+        let warnings = dates.warnings(dates.has_range() && dates.window.is_some());
 
         let resp = req
             .timeout(std::time::Duration::from_millis(timeout_ms))
@@ -295,9 +422,11 @@ impl SearchProvider for BraveSearchProvider {
             .await
             .map_err(|e| Error::Search(e.to_string()))?;
         let mut out = Vec::new();
+        let mut page_len = 0;
         if let Some(web) = parsed.web {
             if let Some(results) = web.results {
-                for r in results {
+                page_len = results.len();
+                for r in results.into_iter().skip(skip) {
                     out.push(SearchResult {
                         url: r.url,
                         title: r.title,
@@ -316,6 +445,13 @@ impl SearchProvider for BraveSearchProvider {
             provider: "brave".to_string(),
             cost_units: 1,
             timings_ms,
+            warnings,
+            offset: page * count + skip,
+            has_more: page < BRAVE_MAX_PAGE
+                && parsed
+                    .query
+                    .and_then(|qi| qi.more_results_available)
+                    .unwrap_or(page_len >= count),
         })
     }
 }
@@ -348,10 +484,12 @@ impl SearchProvider for TavilySearchProvider {
         let t0 = Instant::now();
         let max_results = q.max_results.unwrap_or(5).min(20);
         let timeout_ms = timeout_ms_from_query(q);
+        let offset = q.offset.unwrap_or(0).min(TAVILY_MAX_RESULTS);
+        let want = (offset + max_results).min(TAVILY_MAX_RESULTS);
 
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "query": q.query,
-            "max_results": max_results,
+            "max_results": want,
             // Keep it comparable to Brave: don't ask for answer/raw_content.
             "include_answer": false,
             "include_raw_content": false,
@@ -360,6 +498,21 @@ impl SearchProvider for TavilySearchProvider {
             // Best-effort hints; Tavily accepts country only for some topics, but it is safe to send.
             "country": q.country,
         });
+        // Tavily filters domains natively.
+        if let Some(site) = q.site.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            body["include_domains"] = serde_json::json!([site]);
+        }
+        let dates = DateFilter::of(q);
+        if let Some(w) = dates.window {
+            body["time_range"] = serde_json::json!(w);
+        }
+        if let Some(d) = dates.after {
+            body["start_date"] = serde_json::json!(d);
+        }
+        if let Some(d) = dates.before {
+            body["end_date"] = serde_json::json!(d);
+        }
+        let warnings = dates.warnings(false);
 
         let resp = self
             .client
@@ -384,8 +537,9 @@ impl SearchProvider for TavilySearchProvider {
             .await
             .map_err(|e| Error::Search(e.to_string()))?;
 
+        let page_len = parsed.results.len();
         let mut out = Vec::new();
-        for r in parsed.results {
+        for r in parsed.results.into_iter().skip(offset) {
             out.push(SearchResult {
                 url: r.url,
                 title: r.title,
@@ -402,6 +556,9 @@ impl SearchProvider for TavilySearchProvider {
             provider: "tavily".to_string(),
             cost_units: parsed.usage.and_then(|u| u.credits).unwrap_or(1),
             timings_ms,
+            warnings,
+            offset,
+            has_more: want < TAVILY_MAX_RESULTS && page_len >= want,
         })
     }
 }
@@ -433,6 +590,178 @@ impl SearchProvider for SearxngSearchProvider {
     }
 }
 
+impl DuckDuckGoProvider {
+    /// Always succeeds (no key needed); `WEBPIPE_DDG_ENDPOINT` overrides the endpoint.
+    pub fn from_env(client: reqwest::Client) -> Result<Self> {
+        Ok(Self { client })
+    }
+
+    fn endpoint() -> String {
+        ddg_endpoint_from_env().unwrap_or_else(|| "https://html.duckduckgo.com/html/".to_string())
+    }
+
+    /// DuckDuckGo's `kl` region code (`us-en`, `de-de`, ...). A country that already looks like a
+    /// region code is passed through; otherwise both parts are needed.
+    fn region(q: &SearchQuery) -> Option<String> {
+        let country = q
+            .country
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())?;
+        if country.contains('-') {
+            return Some(country.to_ascii_lowercase());
+        }
+        let lang = q
+            .language
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())?;
+        // `en-US` style language tags: keep the primary subtag.
+        let lang = lang.split(['-', '_']).next().unwrap_or(lang);
+        Some(format!(
+            "{}-{}",
+            country.to_ascii_lowercase(),
+            lang.to_ascii_lowercase()
+        ))
+    }
+}
+
+/// Result links are usually `//duckduckgo.com/l/?uddg=<target>&rut=...` redirects.
+fn ddg_unwrap_href(href: &str) -> Option<String> {
+    let href = href.trim();
+    let abs = if href.starts_with("//") {
+        format!("https:{href}")
+    } else if href.starts_with('/') {
+        format!("https://duckduckgo.com{href}")
+    } else {
+        href.to_string()
+    };
+    let u = url::Url::parse(&abs).ok()?;
+    if u.host_str().is_some_and(|h| h.ends_with("duckduckgo.com")) && u.path() == "/l/" {
+        let target = u.query_pairs().find(|(k, _)| k == "uddg")?.1.into_owned();
+        return url::Url::parse(&target).ok().map(|t| t.to_string());
+    }
+    matches!(u.scheme(), "http" | "https").then(|| u.to_string())
+}
+
+/// Results, plus whether the page offers a "Next" page (or results we cut off).
+fn ddg_parse_results(html: &str, max_results: usize) -> Result<(Vec<SearchResult>, bool)> {
+    use html_scraper::{Html, Selector};
+    let sel = |s: &str| Selector::parse(s).expect("static selector");
+    let doc = Html::parse_document(html);
+    let text = |e: html_scraper::ElementRef<'_>| {
+        let t = e.text().collect::<Vec<_>>().join("");
+        let t = t.split_whitespace().collect::<Vec<_>>().join(" ");
+        (!t.is_empty()).then_some(t)
+    };
+
+    let result_sel = sel("div.result");
+    let title_sel = sel("a.result__a");
+    let snippet_sel = sel(".result__snippet");
+    let mut out = Vec::new();
+    let mut seen_markup = false;
+    let mut cut_off = false;
+    for r in doc.select(&result_sel) {
+        let class = r.value().attr("class").unwrap_or("");
+        if class.split_whitespace().any(|c| c == "result--ad") {
+            continue;
+        }
+        let Some(a) = r.select(&title_sel).next() else {
+            continue;
+        };
+        seen_markup = true;
+        if out.len() >= max_results {
+            cut_off = true;
+            break;
+        }
+        let Some(url) = a.value().attr("href").and_then(ddg_unwrap_href) else {
+            continue;
+        };
+        out.push(SearchResult {
+            url,
+            title: text(a),
+            snippet: r.select(&snippet_sel).next().and_then(text),
+            source: "duckduckgo".to_string(),
+        });
+    }
+    if seen_markup || doc.select(&sel(".no-results")).next().is_some() {
+        let next_page = doc
+            .select(&sel(r#"input[type="submit"][value="Next"]"#))
+            .next();
+        return Ok((out, cut_off || next_page.is_some()));
+    }
+    if doc.select(&sel(".anomaly-modal")).next().is_some() {
+        return Err(Error::Search(
+            "duckduckgo returned a bot challenge page; retry later or switch provider".to_string(),
+        ));
+    }
+    Err(Error::Search(
+        "duckduckgo HTML layout not recognized (no result__a links); the page format may have changed"
+            .to_string(),
+    ))
+}
+
+#[async_trait::async_trait]
+impl SearchProvider for DuckDuckGoProvider {
+    fn name(&self) -> &'static str {
+        "duckduckgo"
+    }
+
+    async fn search(&self, q: &SearchQuery) -> Result<SearchResponse> {
+        let t0 = Instant::now();
+        // One HTML page carries ~30 organic results; we don't paginate.
+        let max_results = q.max_results.unwrap_or(10).min(30);
+        let timeout_ms = timeout_ms_from_query(q);
+
+        let mut form = vec![("q", q.site_scoped_query())];
+        if let Some(kl) = Self::region(q) {
+            form.push(("kl", kl));
+        }
+        // `df` = d/w/m/y; the HTML endpoint has no absolute date range.
+        let dates = DateFilter::of(q);
+        if let Some(w) = dates.window {
+            form.push(("df", w[..1].to_string()));
+        }
+        let warnings = dates.warnings(dates.has_range());
+        // `s` is a result offset; `dc` (1-based) mirrors what the "Next" form sends.
+        let offset = q.offset.unwrap_or(0);
+        if offset > 0 {
+            form.push(("s", offset.to_string()));
+            form.push(("dc", (offset + 1).to_string()));
+        }
+        let resp = self
+            .client
+            .post(Self::endpoint())
+            .form(&form)
+            .timeout(std::time::Duration::from_millis(timeout_ms))
+            .send()
+            .await
+            .map_err(|e| Error::Search(e.to_string()))?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(Error::Search(format!("duckduckgo search HTTP {status}")));
+        }
+        let html = resp
+            .text()
+            .await
+            .map_err(|e| Error::Search(e.to_string()))?;
+        let (out, has_more) = ddg_parse_results(&html, max_results)?;
+
+        let mut timings_ms = BTreeMap::new();
+        timings_ms.insert("search".to_string(), t0.elapsed().as_millis());
+
+        Ok(SearchResponse {
+            results: out,
+            provider: "duckduckgo".to_string(),
+            cost_units: 0,
+            timings_ms,
+            warnings,
+            offset,
+            has_more,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -477,10 +806,15 @@ mod tests {
             "results": [
               {"url":"https://example.com","title":"Example","description":"Hello"}
             ]
-          }
+          },
+          "query": { "more_results_available": true }
         }
         "#;
         let parsed: BraveWebSearchResponse = serde_json::from_str(js).unwrap();
+        assert_eq!(
+            parsed.query.as_ref().unwrap().more_results_available,
+            Some(true)
+        );
         let web = parsed.web.unwrap();
         let rs = web.results.unwrap();
         assert_eq!(rs.len(), 1);
@@ -520,6 +854,105 @@ mod tests {
         assert_eq!(parsed.results.unwrap().len(), 1);
     }
 
+    #[test]
+    fn parses_minimal_duckduckgo_shape() {
+        let html = r##"
+        <div class="results">
+          <div class="result results_links result--ad">
+            <h2 class="result__title"><a class="result__a" href="https://ads.example/">Ad</a></h2>
+          </div>
+          <div class="result results_links results_links_deep web-result">
+            <h2 class="result__title">
+              <a rel="nofollow" class="result__a"
+                 href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fexample.com%2Fa%3Fx%3D1&amp;rut=abc">Example <b>Domain</b></a>
+            </h2>
+            <a class="result__snippet" href="#">Hello   <b>world</b></a>
+          </div>
+          <div class="result results_links web-result">
+            <h2 class="result__title"><a class="result__a" href="https://example.org/b">B</a></h2>
+          </div>
+        </div>
+        "##;
+        let (rs, has_more) = ddg_parse_results(html, 10).unwrap();
+        assert!(!has_more);
+        assert_eq!(rs.len(), 2);
+        assert_eq!(rs[0].url, "https://example.com/a?x=1");
+        assert_eq!(rs[0].title.as_deref(), Some("Example Domain"));
+        assert_eq!(rs[0].snippet.as_deref(), Some("Hello world"));
+        assert_eq!(rs[0].source, "duckduckgo");
+        assert_eq!(rs[1].url, "https://example.org/b");
+        assert_eq!(rs[1].snippet, None);
+        let (rs, has_more) = ddg_parse_results(html, 1).unwrap();
+        assert_eq!(rs.len(), 1);
+        assert!(has_more);
+        let paged = html.replace(
+            "</div>\n        </div>",
+            r#"</div><div class="nav-link"><form><input type="submit" value="Next"></form></div></div>"#,
+        );
+        assert!(ddg_parse_results(&paged, 10).unwrap().1);
+
+        let empty = r#"<div class="no-results">No results.</div>"#;
+        let (rs, has_more) = ddg_parse_results(empty, 10).unwrap();
+        assert!(rs.is_empty() && !has_more);
+        let changed = r#"<html><body><ol><li>something new</li></ol></body></html>"#;
+        let err = ddg_parse_results(changed, 10).unwrap_err().to_string();
+        assert!(err.contains("layout"), "{err}");
+    }
+
+    #[test]
+    fn date_filters_flag_what_a_provider_cannot_express() {
+        let mut q = SearchQuery {
+            query: "q".to_string(),
+            max_results: None,
+            language: None,
+            country: None,
+            timeout_ms: None,
+            site: None,
+            freshness: Some("week".to_string()),
+            after: None,
+            before: None,
+            offset: None,
+        };
+        let f = DateFilter::of(&q);
+        assert_eq!(f.window, Some("week"));
+        assert!(f.warnings(f.has_range()).is_empty());
+
+        q.after = Some("2024-01-02T03:04:05Z".to_string());
+        let f = DateFilter::of(&q);
+        assert_eq!(f.after, Some("2024-01-02"));
+        // SearXNG/DDG: a range can't be expressed.
+        assert_eq!(f.warnings(f.has_range()), vec![DATE_FILTER_UNSUPPORTED]);
+        // Tavily: everything maps.
+        assert!(f.warnings(false).is_empty());
+
+        q.freshness = Some("fortnight".to_string());
+        q.after = None;
+        let f = DateFilter::of(&q);
+        assert_eq!(f.window, None);
+        assert_eq!(f.warnings(false), vec![DATE_FILTER_UNSUPPORTED]);
+    }
+
+    #[test]
+    fn duckduckgo_region_combines_country_and_language() {
+        let mut q = SearchQuery {
+            query: "q".to_string(),
+            max_results: None,
+            language: Some("en-US".to_string()),
+            country: Some("US".to_string()),
+            timeout_ms: None,
+            site: None,
+            freshness: None,
+            after: None,
+            before: None,
+            offset: None,
+        };
+        assert_eq!(DuckDuckGoProvider::region(&q).as_deref(), Some("us-en"));
+        q.country = Some("de-de".to_string());
+        assert_eq!(DuckDuckGoProvider::region(&q).as_deref(), Some("de-de"));
+        q.country = None;
+        assert_eq!(DuckDuckGoProvider::region(&q), None);
+    }
+
     #[test]
     fn searxng_endpoints_from_env_accepts_list_and_dedups() {
         let _g1 = EnvGuard::set("WEBPIPE_SEARXNG_ENDPOINTS", "http://a, http://b http://a");
@@ -540,6 +973,11 @@ mod tests {
             language: Some("en".to_string()),
             country: Some("us".to_string()),
             timeout_ms: None,
+            site: None,
+            freshness: None,
+            after: None,
+            before: None,
+            offset: None,
         };
         let i1 = p.pick_endpoint_index(&q);
         let i2 = p.pick_endpoint_index(&q);
@@ -3,8 +3,177 @@
 //! This is intentionally self-contained (no external embeddings backends).
 //! It provides a best-effort “semantic-ish” score based on token overlap,
 //! which is often good enough to improve chunk ordering without network calls.
+//!
+//! Callers that do use an embeddings backend persist its vectors in an [`EmbeddingCache`], so a
+//! chunk is embedded once per model rather than once per call.
 
 use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static EMBEDDING_CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static EMBEDDING_CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+static EMBEDDING_CACHE_WRITES: AtomicU64 = AtomicU64::new(0);
+
+/// Process-wide [`EmbeddingCache`] counters since start (or the last reset).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct EmbeddingCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub writes: u64,
+}
+
+pub fn embedding_cache_stats() -> EmbeddingCacheStats {
+    EmbeddingCacheStats {
+        hits: EMBEDDING_CACHE_HITS.load(Ordering::Relaxed),
+        misses: EMBEDDING_CACHE_MISSES.load(Ordering::Relaxed),
+        writes: EMBEDDING_CACHE_WRITES.load(Ordering::Relaxed),
+    }
+}
+
+pub fn reset_embedding_cache_stats() {
+    for n in [
+        &EMBEDDING_CACHE_HITS,
+        &EMBEDDING_CACHE_MISSES,
+        &EMBEDDING_CACHE_WRITES,
+    ] {
+        n.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Embedding vectors on disk, under an [`crate::FsCache`] root:
+/// `embeddings/<model>/<sha256(text)>.bin`.
+///
+/// Each file is `WPE1`, the dimension as a little-endian `u32`, then the `f32` components.
+/// Every model directory also records the dimension it was last written with (`dims`); vectors
+/// of any other length are misses, so a model that changes its output size (or two models whose
+/// names sanitize alike) never mixes vectors.
+#[derive(Debug, Clone)]
+pub struct EmbeddingCache {
+    root: PathBuf,
+}
+
+impl EmbeddingCache {
+    const MAGIC: &'static [u8; 4] = b"WPE1";
+
+    /// `cache_root` is the fetch cache directory; vectors go in its `embeddings/` subdirectory.
+    pub fn new(cache_root: &Path) -> Self {
+        Self {
+            root: cache_root.join("embeddings"),
+        }
+    }
+
+    fn model_dir(&self, model: &str) -> PathBuf {
+        let slug: String = model
+            .trim()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .take(80)
+            .collect();
+        // The short hash keeps `a/b` and `a_b` apart after sanitizing.
+        let h = hex::encode(&Sha256::digest(model.trim().as_bytes())[..4]);
+        self.root.join(format!("{slug}-{h}"))
+    }
+
+    fn text_path(dir: &Path, text: &str) -> PathBuf {
+        dir.join(format!(
+            "{}.bin",
+            hex::encode(Sha256::digest(text.as_bytes()))
+        ))
+    }
+
+    fn model_dims(dir: &Path) -> Option<usize> {
+        std::fs::read_to_string(dir.join("dims"))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Cached vectors for `texts` under `model`, in order (`None` = miss).
+    pub fn lookup(&self, model: &str, texts: &[String]) -> Vec<Option<Vec<f32>>> {
+        let dir = self.model_dir(model);
+        let dims = Self::model_dims(&dir);
+        texts
+            .iter()
+            .map(|t| {
+                let v = dims.and_then(|d| Self::read_vector(&Self::text_path(&dir, t), d));
+                let counter = if v.is_some() {
+                    &EMBEDDING_CACHE_HITS
+                } else {
+                    &EMBEDDING_CACHE_MISSES
+                };
+                counter.fetch_add(1, Ordering::Relaxed);
+                v
+            })
+            .collect()
+    }
+
+    fn read_vector(path: &Path, dims: usize) -> Option<Vec<f32>> {
+        let b = std::fs::read(path).ok()?;
+        let (head, body) = b.split_at_checked(8)?;
+        if &head[..4] != Self::MAGIC {
+            return None;
+        }
+        let n = u32::from_le_bytes(head[4..8].try_into().ok()?) as usize;
+        if n != dims || body.len() != n * 4 {
+            return None;
+        }
+        Some(
+            body.chunks_exact(4)
+                .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                .collect(),
+        )
+    }
+
+    /// Persist `vectors[i]` as the embedding of `texts[i]`. Best-effort: IO errors are ignored.
+    pub fn store(&self, model: &str, texts: &[String], vectors: &[Vec<f32>]) {
+        let Some(dims) = vectors.first().map(Vec::len).filter(|d| *d > 0) else {
+            return;
+        };
+        let dir = self.model_dir(model);
+        if std::fs::create_dir_all(&dir).is_err() {
+            return;
+        }
+        if Self::model_dims(&dir) != Some(dims) {
+            let _ = std::fs::write(dir.join("dims"), dims.to_string());
+        }
+        for (t, v) in texts.iter().zip(vectors).filter(|(_, v)| v.len() == dims) {
+            let mut b = Vec::with_capacity(8 + v.len() * 4);
+            b.extend_from_slice(Self::MAGIC);
+            b.extend_from_slice(&(dims as u32).to_le_bytes());
+            for x in v {
+                b.extend_from_slice(&x.to_le_bytes());
+            }
+            // Write-then-rename so a concurrent reader never sees a partial vector.
+            let path = Self::text_path(&dir, t);
+            let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+            if std::fs::write(&tmp, &b).is_ok() && std::fs::rename(&tmp, &path).is_ok() {
+                EMBEDDING_CACHE_WRITES.fetch_add(1, Ordering::Relaxed);
+            } else {
+                let _ = std::fs::remove_file(&tmp);
+            }
+        }
+    }
+}
+
+/// A text embeddings backend: one vector per input, in input order, all the same length.
+///
+/// Implemented by in-process models (see `embed_local`); hosted APIs are called from the async
+/// tool layer instead.
+pub trait Embedder: Send + Sync {
+    /// Model name; also the [`EmbeddingCache`] key.
+    fn model_id(&self) -> &str;
+
+    fn embed(&self, texts: &[String]) -> webpipe_core::Result<Vec<Vec<f32>>>;
+}
 
 #[derive(Debug, Clone, Serialize)]
 pub struct SemanticChunk {
@@ -23,6 +192,34 @@ pub struct SemanticRerankResult {
     pub cache_misses: u64,
     pub chunks: Vec<SemanticChunk>,
     pub warnings: Vec<&'static str>,
+    /// Why semantic scoring could not run at all (e.g. `feature_disabled`,
+    /// `backend_not_configured`); absent when it ran.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unavailable_reason: Option<&'static str>,
+}
+
+impl SemanticRerankResult {
+    /// A result that reranked nothing; `warning` says why (callers keep their lexical order).
+    pub fn skipped(warning: &'static str) -> Self {
+        Self {
+            ok: false,
+            backend: "none".to_string(),
+            model_id: None,
+            cache_hits: 0,
+            cache_misses: 0,
+            chunks: Vec::new(),
+            warnings: vec![warning],
+            unavailable_reason: None,
+        }
+    }
+
+    /// Semantic rerank was requested but can't run in this build/configuration.
+    pub fn unavailable(reason: &'static str) -> Self {
+        Self {
+            unavailable_reason: Some(reason),
+            ..Self::skipped("semantic_unavailable")
+        }
+    }
 }
 
 fn tokenize(s: &str) -> Vec<String> {
@@ -85,6 +282,7 @@ pub fn semantic_rerank_chunks(
             cache_misses: 0,
             chunks: Vec::new(),
             warnings: vec!["empty_query_or_candidates"],
+            unavailable_reason: None,
         };
     }
     let q_toks = tokenize(q);
@@ -121,5 +319,40 @@ pub fn semantic_rerank_chunks(
         cache_misses: 0,
         chunks: scored,
         warnings: Vec::new(),
+        unavailable_reason: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedding_cache_round_trips_and_keys_on_model_and_dims() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = EmbeddingCache::new(tmp.path());
+        let texts = vec!["alpha".to_string(), "beta".to_string()];
+        let before = embedding_cache_stats();
+
+        assert_eq!(cache.lookup("m/one", &texts), vec![None, None]);
+        cache.store("m/one", &texts, &[vec![1.0, 2.0], vec![3.0, 4.5]]);
+        assert_eq!(
+            cache.lookup("m/one", &texts),
+            vec![Some(vec![1.0, 2.0]), Some(vec![3.0, 4.5])]
+        );
+        // Another model (even one that sanitizes to the same slug) sees nothing.
+        assert_eq!(cache.lookup("m_one", &texts[..1]), vec![None]);
+
+        // Same model, new output size: old vectors stop matching.
+        cache.store("m/one", &texts[..1], &[vec![9.0, 9.0, 9.0]]);
+        assert_eq!(
+            cache.lookup("m/one", &texts),
+            vec![Some(vec![9.0, 9.0, 9.0]), None]
+        );
+
+        let after = embedding_cache_stats();
+        assert_eq!(after.hits - before.hits, 3);
+        assert_eq!(after.misses - before.misses, 4);
+        assert_eq!(after.writes - before.writes, 3);
     }
 }
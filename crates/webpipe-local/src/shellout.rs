@@ -200,6 +200,21 @@ pub fn media_subs_mode_from_env() -> String {
     normalize_mode(env("WEBPIPE_MEDIA_SUBTITLES"))
 }
 
+/// The pandoc executable: `WEBPIPE_PANDOC_BIN` (a path or a name on `PATH`), else `pandoc`.
+pub fn pandoc_bin() -> PathBuf {
+    let bin = env("WEBPIPE_PANDOC_BIN").unwrap_or_else(|| "pandoc".to_string());
+    let p = PathBuf::from(&bin);
+    if p.components().count() > 1 {
+        return p;
+    }
+    which(&bin).unwrap_or(p)
+}
+
+/// Whether [`pandoc_bin`] resolves to an existing file.
+pub fn has_pandoc() -> bool {
+    pandoc_bin().is_file()
+}
+
 pub fn pandoc_to_text(
     bytes: &[u8],
     content_type: Option<&str>,
@@ -209,20 +224,23 @@ pub fn pandoc_to_text(
     if mode == "off" {
         return Err("pandoc_disabled");
     }
-    if !has("pandoc") {
+    if !has_pandoc() {
         return Err("pandoc_not_found");
     }
     let timeout = timeout_from_env_ms("WEBPIPE_PANDOC_TIMEOUT_MS", 20_000);
     let max_chars = max_chars_from_env("WEBPIPE_PANDOC_MAX_CHARS", 200_000);
     let max_stdout_bytes = max_chars.saturating_mul(4).clamp(1_000, 8_000_000);
+    // pandoc picks its reader from the extension, so a generic content type (octet-stream)
+    // defers to the URL's.
     let suffix = content_type
         .map(suffix_for_content_type)
+        .filter(|s| *s != ".bin")
         .unwrap_or_else(|| url_suffix_hint(final_url));
     let tmp = write_temp_file(bytes, suffix)?;
     let path = tmp.path().to_string_lossy().to_string();
 
     // pandoc <in> -t plain --wrap=none
-    let mut cmd = Command::new("pandoc");
+    let mut cmd = Command::new(pandoc_bin());
     cmd.arg(&path).arg("-t").arg("plain").arg("--wrap=none");
     let out = run_stdout_bounded(cmd, timeout, max_stdout_bytes)?;
     let s = String::from_utf8_lossy(&out).to_string();
@@ -142,6 +142,484 @@ pub fn scrub(s: &str) -> String {
     out.trim().to_string()
 }
 
+/// Coarse query intent, used to pick retrieval defaults when the caller doesn't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intent {
+    /// A single fact ("what year did ...", "who wrote ...").
+    Factoid,
+    /// Procedural ("how to install ...", "configure ...").
+    HowTo,
+    /// Breadth ("compare A vs B", "best ... alternatives", "overview of ...").
+    Survey,
+    /// Looking for a specific site/page ("github tokio", "rust docs").
+    Navigational,
+    /// Identifiers, error messages, or code snippets.
+    Code,
+}
+
+impl Intent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Factoid => "factoid",
+            Self::HowTo => "howto",
+            Self::Survey => "survey",
+            Self::Navigational => "navigational",
+            Self::Code => "code",
+        }
+    }
+}
+
+/// Deterministic, rule-based query intent classifier.
+///
+/// Checked in order: code tokens, survey cues, how-to cues (question form or leading
+/// imperative verb), factoid question words, then short/site-like queries as navigational.
+/// Anything else defaults to `Factoid`.
+pub fn classify_intent(query: &str) -> Intent {
+    let raw = query.trim();
+    let lc = raw.to_lowercase();
+    let key = scrub(raw);
+    let toks: Vec<&str> = key.split_whitespace().collect();
+    let has = |phrase: &str| {
+        let p = format!(" {phrase} ");
+        format!(" {key} ").contains(&p)
+    };
+
+    // Code: punctuation that only shows up in identifiers / snippets / error messages.
+    let code_marks = [
+        "::", "()", "->", "=>", "`", "#[", "error[e", "{", "};", "$ ",
+    ];
+    let code_ident = raw.split_whitespace().any(|w| {
+        let w = w.trim_matches(|c: char| !c.is_alphanumeric() && c != '_');
+        let snake = w.contains('_') && w.chars().any(|c| c.is_ascii_alphabetic());
+        let camel = w.len() > 3
+            && w.chars().next().is_some_and(|c| c.is_ascii_lowercase())
+            && w.chars().skip(1).any(|c| c.is_ascii_uppercase());
+        snake || camel
+    });
+    if code_marks.iter().any(|m| lc.contains(m))
+        || code_ident
+        || has("stack trace")
+        || has("traceback")
+        || has("segfault")
+        || has("compile error")
+    {
+        return Intent::Code;
+    }
+
+    if has("vs")
+        || has("versus")
+        || has("compare")
+        || has("comparison")
+        || has("alternatives")
+        || has("alternative to")
+        || has("pros and cons")
+        || has("overview")
+        || has("survey")
+        || has("state of the art")
+        || has("list of")
+        || has("differences between")
+        || has("difference between")
+        || toks.first().is_some_and(|t| matches!(*t, "best" | "top"))
+    {
+        return Intent::Survey;
+    }
+
+    const IMPERATIVE: [&str; 14] = [
+        "install",
+        "configure",
+        "setup",
+        "set",
+        "create",
+        "build",
+        "deploy",
+        "enable",
+        "disable",
+        "fix",
+        "run",
+        "use",
+        "migrate",
+        "upgrade",
+    ];
+    if key.starts_with("how to ")
+        || key.starts_with("how do ")
+        || key.starts_with("how can ")
+        || key.starts_with("how should ")
+        || has("tutorial")
+        || has("step by step")
+        || toks.first().is_some_and(|t| IMPERATIVE.contains(t))
+    {
+        return Intent::HowTo;
+    }
+
+    const QUESTION: [&str; 13] = [
+        "what", "when", "who", "whom", "whose", "where", "which", "why", "how", "is", "are",
+        "does", "did",
+    ];
+    if toks.first().is_some_and(|t| QUESTION.contains(t)) || raw.ends_with('?') {
+        return Intent::Factoid;
+    }
+
+    let site_like = raw.split_whitespace().any(|w| {
+        let w = w.to_ascii_lowercase();
+        w.starts_with("http://")
+            || w.starts_with("https://")
+            || w.starts_with("www.")
+            || [".com", ".org", ".io", ".dev", ".net", ".rs"]
+                .iter()
+                .any(|tld| w.ends_with(tld))
+    });
+    if site_like
+        || toks.len() <= 2
+        || has("homepage")
+        || has("official site")
+        || has("login")
+        || has("docs")
+        || has("documentation")
+    {
+        return Intent::Navigational;
+    }
+
+    Intent::Factoid
+}
+
+/// Most frequent content terms in `text` (scrubbed), most frequent first.
+///
+/// Drops English stopwords, pure numbers, and tokens shorter than 3 chars. Ties keep
+/// first-occurrence order so the result is deterministic.
+pub fn top_keywords(text: &str, k: usize) -> Vec<String> {
+    let key = scrub(text);
+    let mut counts: Vec<(String, usize, usize)> = Vec::new();
+    let mut index: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+    for (pos, t) in key.split_whitespace().enumerate() {
+        if t.len() < 3
+            || t.chars().all(|c| c.is_ascii_digit())
+            || textprep_crate::stopwords::is_english_stopword(t)
+        {
+            continue;
+        }
+        match index.get(t) {
+            Some(&i) => counts[i].1 += 1,
+            None => {
+                index.insert(t, counts.len());
+                counts.push((t.to_string(), 1, pos));
+            }
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.2.cmp(&b.2)));
+    counts.into_iter().take(k).map(|(t, _, _)| t).collect()
+}
+
+/// How to read all-numeric dates such as `5/1/24`, where either field could be the month.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateOrder {
+    /// Don't guess: readings that are valid both ways are flagged `ambiguous`.
+    #[default]
+    Auto,
+    /// US style, `M/D/Y`.
+    MonthFirst,
+    /// Day first, `D/M/Y` (most of the world).
+    DayFirst,
+}
+
+impl DateOrder {
+    /// `"mdy"` / `"dmy"`; anything else is `Auto`.
+    pub fn parse(s: &str) -> Self {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mdy" => Self::MonthFirst,
+            "dmy" => Self::DayFirst,
+            _ => Self::Auto,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::MonthFirst => "mdy",
+            Self::DayFirst => "dmy",
+        }
+    }
+}
+
+/// One date (or date-time) found in text.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DateMention {
+    /// The text as written.
+    pub raw: String,
+    /// ISO 8601 form (`2024-01-05`, `2024-01` for month-year, or `2024-01-05T09:30:00Z`);
+    /// `None` when ambiguous.
+    pub iso: Option<String>,
+    /// Offset in chars (not bytes) of `raw` within the text.
+    pub char_offset: usize,
+    pub ambiguous: bool,
+    /// The possible readings of an ambiguous mention, month-first reading first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<String>,
+}
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+fn days_in_month(y: u32, m: u32) -> u32 {
+    match m {
+        2 if (y.is_multiple_of(4) && !y.is_multiple_of(100)) || y.is_multiple_of(400) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn iso_date(y: u32, m: u32, d: u32) -> Option<String> {
+    ((1..=12).contains(&m) && d >= 1 && d <= days_in_month(y, m))
+        .then(|| format!("{y:04}-{m:02}-{d:02}"))
+}
+
+/// Digit run at `i`: `(value, len)`, or `None` if it is empty or longer than `max_len`.
+fn digit_run(cs: &[char], i: usize, max_len: usize) -> Option<(u32, usize)> {
+    let len = cs[i.min(cs.len())..]
+        .iter()
+        .take_while(|c| c.is_ascii_digit())
+        .count();
+    if len == 0 || len > max_len {
+        return None;
+    }
+    let v = cs[i..i + len].iter().collect::<String>().parse().ok()?;
+    Some((v, len))
+}
+
+fn word_end(cs: &[char], i: usize) -> bool {
+    cs.get(i).is_none_or(|c| !c.is_alphanumeric())
+}
+
+/// Month named by the word at `i` (full name or 3+ letter prefix, optional trailing `.`):
+/// `(month, len)`. Only capitalized words count, so the verb "may" isn't a month.
+fn month_word(cs: &[char], i: usize) -> Option<(u32, usize)> {
+    if !cs.get(i)?.is_uppercase() {
+        return None;
+    }
+    let len = cs[i..].iter().take_while(|c| c.is_alphabetic()).count();
+    let w: String = cs[i..i + len].iter().collect::<String>().to_lowercase();
+    if len < 3 {
+        return None;
+    }
+    let m = MONTHS.iter().position(|name| {
+        *name == w || (name.starts_with(w.as_str()) && (len == 3 || w == "sept"))
+    })?;
+    let dot = usize::from(len < MONTHS[m].len() && cs.get(i + len) == Some(&'.'));
+    Some((m as u32 + 1, len + dot))
+}
+
+fn spaces(cs: &[char], i: usize) -> usize {
+    cs[i.min(cs.len())..]
+        .iter()
+        .take_while(|c| **c == ' ' || **c == '\u{a0}')
+        .count()
+}
+
+/// Day number with an optional ordinal suffix (`5`, `05`, `5th`): `(day, len)`.
+fn day_number(cs: &[char], i: usize) -> Option<(u32, usize)> {
+    let (d, mut len) = digit_run(cs, i, 2)?;
+    let suffix: String = cs[i + len..]
+        .iter()
+        .take(2)
+        .collect::<String>()
+        .to_lowercase();
+    if matches!(suffix.as_str(), "st" | "nd" | "rd" | "th") && word_end(cs, i + len + 2) {
+        len += 2;
+    }
+    Some((d, len))
+}
+
+/// `YYYY-MM-DD` or `YYYY/MM/DD`, optionally followed by `THH:MM[:SS][.fff][Z|±HH:MM]`.
+fn iso_at(cs: &[char], i: usize) -> Option<(usize, String)> {
+    let (y, 4) = digit_run(cs, i, 4)? else {
+        return None;
+    };
+    let sep = *cs.get(i + 4)?;
+    if sep != '-' && sep != '/' {
+        return None;
+    }
+    let (m, ml) = digit_run(cs, i + 5, 2)?;
+    let mut j = i + 5 + ml;
+    if cs.get(j) != Some(&sep) {
+        return None;
+    }
+    let (d, dl) = digit_run(cs, j + 1, 2)?;
+    j += 1 + dl;
+    let mut iso = iso_date(y, m, d)?;
+
+    // Optional time: only ISO's own `T` separator (a space would swallow unrelated numbers).
+    if cs.get(j) == Some(&'T') {
+        let two = |k: usize| digit_run(cs, k, 2).filter(|(_, l)| *l == 2).map(|(v, _)| v);
+        let hh = two(j + 1);
+        let mm = (cs.get(j + 3) == Some(&':')).then(|| two(j + 4)).flatten();
+        if let (Some(hh), Some(mm)) = (hh, mm) {
+            let mut k = j + 6;
+            let mut ss = 0;
+            if cs.get(k) == Some(&':') {
+                if let Some(v) = two(k + 1) {
+                    ss = v;
+                    k += 3;
+                    if cs.get(k) == Some(&'.') {
+                        k += 1 + cs[k + 1..]
+                            .iter()
+                            .take_while(|c| c.is_ascii_digit())
+                            .count();
+                    }
+                }
+            }
+            if hh < 24 && mm < 60 && ss < 61 {
+                iso.push_str(&format!("T{hh:02}:{mm:02}:{ss:02}"));
+                match cs.get(k) {
+                    Some('Z') => {
+                        iso.push('Z');
+                        k += 1;
+                    }
+                    Some(&sign @ ('+' | '-')) => {
+                        if let (Some(oh), true, Some(om)) =
+                            (two(k + 1), cs.get(k + 3) == Some(&':'), two(k + 4))
+                        {
+                            iso.push_str(&format!("{sign}{oh:02}:{om:02}"));
+                            k += 6;
+                        }
+                    }
+                    _ => {}
+                }
+                j = k;
+            }
+        }
+    }
+    word_end(cs, j).then_some((j - i, iso))
+}
+
+/// `M/D/Y` or `D/M/Y` with `/`, or `-`/`.` with a 4-digit year. Two-digit years pivot at 70.
+fn numeric_at(cs: &[char], i: usize, order: DateOrder) -> Option<(usize, Vec<String>)> {
+    let (a, al) = digit_run(cs, i, 2)?;
+    let sep = *cs.get(i + al)?;
+    if !matches!(sep, '/' | '-' | '.') {
+        return None;
+    }
+    let (b, bl) = digit_run(cs, i + al + 1, 2)?;
+    let j = i + al + 1 + bl;
+    if cs.get(j) != Some(&sep) {
+        return None;
+    }
+    let (y, yl) = digit_run(cs, j + 1, 4)?;
+    if !(yl == 4 || (yl == 2 && sep == '/')) || !word_end(cs, j + 1 + yl) {
+        return None;
+    }
+    let y = match yl {
+        2 if y < 70 => 2000 + y,
+        2 => 1900 + y,
+        _ => y,
+    };
+    let month_first = iso_date(y, a, b);
+    let day_first = iso_date(y, b, a);
+    let readings: Vec<String> = match (month_first, day_first) {
+        (Some(mf), Some(df)) if mf == df => vec![mf],
+        (Some(mf), Some(df)) => match order {
+            DateOrder::MonthFirst => vec![mf],
+            DateOrder::DayFirst => vec![df],
+            DateOrder::Auto => vec![mf, df],
+        },
+        (Some(only), None) | (None, Some(only)) => vec![only],
+        (None, None) => return None,
+    };
+    Some((j + 1 + yl - i, readings))
+}
+
+/// `Jan 5, 2024`, `January 5th 2024`, `January 2024`, `5 Jan 2024`, `5th of January, 2024`.
+fn named_at(cs: &[char], i: usize) -> Option<(usize, String)> {
+    let year_at = |k: usize| {
+        let k = k + usize::from(cs.get(k) == Some(&','));
+        let sp = spaces(cs, k);
+        let (y, 4) = digit_run(cs, k + sp, 4)? else {
+            return None;
+        };
+        (sp > 0 && word_end(cs, k + sp + 4)).then_some((y, k + sp + 4))
+    };
+
+    if let Some((m, ml)) = month_word(cs, i) {
+        let k = i + ml;
+        let sp = spaces(cs, k);
+        if sp == 0 {
+            return None;
+        }
+        if let Some((d, dl)) = day_number(cs, k + sp).filter(|(_, dl)| word_end(cs, k + sp + dl)) {
+            let (y, end) = year_at(k + sp + dl)?;
+            return Some((end - i, iso_date(y, m, d)?));
+        }
+        let (y, end) = year_at(k)?;
+        return Some((end - i, format!("{y:04}-{m:02}")));
+    }
+
+    let (d, dl) = day_number(cs, i)?;
+    let mut k = i + dl;
+    let sp = spaces(cs, k);
+    if sp == 0 {
+        return None;
+    }
+    k += sp;
+    if cs[k..].iter().take(3).collect::<String>() == "of " {
+        k += 3;
+    }
+    let (m, ml) = month_word(cs, k)?;
+    let (y, end) = year_at(k + ml)?;
+    Some((end - i, iso_date(y, m, d)?))
+}
+
+/// Dates in `text` with [`DateOrder::Auto`]; see [`extract_dates_with`].
+pub fn extract_dates(text: &str) -> Vec<DateMention> {
+    extract_dates_with(text, DateOrder::Auto)
+}
+
+/// Dates and ISO date-times in `text`, normalized to ISO 8601, in text order.
+///
+/// Recognized: ISO (`2024-01-05`, `2024-01-05T09:30Z`), month names (`Jan 5, 2024`,
+/// `5 January 2024`, `March 2024`) and numeric `5/1/24`, `05.01.2024`. Numeric dates whose
+/// fields are valid either way follow `order`; with `Auto` they are returned `ambiguous` with
+/// both readings instead of a guess. Impossible dates (`2023-02-30`) are skipped.
+pub fn extract_dates_with(text: &str, order: DateOrder) -> Vec<DateMention> {
+    let cs: Vec<char> = text.chars().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < cs.len() {
+        if i > 0 && cs[i - 1].is_alphanumeric() {
+            i += 1;
+            continue;
+        }
+        let found = iso_at(&cs, i)
+            .map(|(len, iso)| (len, vec![iso]))
+            .or_else(|| named_at(&cs, i).map(|(len, iso)| (len, vec![iso])))
+            .or_else(|| numeric_at(&cs, i, order));
+        let Some((len, mut readings)) = found else {
+            i += 1;
+            continue;
+        };
+        let ambiguous = readings.len() > 1;
+        out.push(DateMention {
+            raw: cs[i..i + len].iter().collect(),
+            iso: (!ambiguous).then(|| readings.remove(0)),
+            char_offset: i,
+            ambiguous,
+            alternatives: if ambiguous { readings } else { Vec::new() },
+        });
+        i += len;
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -182,4 +660,90 @@ mod tests {
             "alpha beta gamma delta epsilon lambda mu pi phi rho sigma theta omega"
         );
     }
+
+    #[test]
+    fn classify_intent_maps_example_queries() {
+        let cases = [
+            ("how to install ripgrep on ubuntu", Intent::HowTo),
+            ("configure nginx reverse proxy", Intent::HowTo),
+            ("compare tokio vs async-std", Intent::Survey),
+            ("best rust web frameworks", Intent::Survey),
+            (
+                "what year did the apollo 11 landing happen",
+                Intent::Factoid,
+            ),
+            ("who wrote the rust book?", Intent::Factoid),
+            ("tokio::spawn requires Send", Intent::Code),
+            ("error[E0502] cannot borrow as mutable", Intent::Code),
+            ("serde_json from_str example", Intent::Code),
+            ("docs.rs", Intent::Navigational),
+            ("github tokio", Intent::Navigational),
+            (
+                "tokamak plasma confinement physics research",
+                Intent::Factoid,
+            ),
+        ];
+        for (q, want) in cases {
+            assert_eq!(classify_intent(q), want, "query={q:?}");
+        }
+        assert_eq!(Intent::HowTo.as_str(), "howto");
+    }
+
+    #[test]
+    fn top_keywords_ranks_content_terms_by_frequency() {
+        let text = "Tokamak plasma confinement. The tokamak holds plasma with magnets; \
+                    plasma heating in a tokamak reaches 100 million degrees.";
+        let kw = top_keywords(text, 3);
+        assert_eq!(kw, vec!["tokamak", "plasma", "confinement"]);
+        assert!(top_keywords("the and of 2024", 5).is_empty());
+    }
+
+    #[test]
+    fn extract_dates_normalizes_common_formats_with_char_offsets() {
+        let text = "Café opened 2023-11-02, moved on Jan. 5, 2024 and again on 5th of March 2024. \
+                    Logs: 2024-03-07T09:30:00.125+02:00; invoice 13/02/2024; EU 25.01.2024; \
+                    since September 2022. Not dates: v1.2.3, 2023-02-30, 12345-01-01.";
+        let got: Vec<(String, Option<String>, usize)> = extract_dates(text)
+            .into_iter()
+            .map(|d| (d.raw, d.iso, d.char_offset))
+            .collect();
+        let at = |raw: &str| text[..text.find(raw).unwrap()].chars().count();
+        let want = [
+            ("2023-11-02", "2023-11-02"),
+            ("Jan. 5, 2024", "2024-01-05"),
+            ("5th of March 2024", "2024-03-05"),
+            ("2024-03-07T09:30:00.125+02:00", "2024-03-07T09:30:00+02:00"),
+            ("13/02/2024", "2024-02-13"),
+            ("25.01.2024", "2024-01-25"),
+            ("September 2022", "2022-09"),
+        ];
+        assert_eq!(
+            got,
+            want.iter()
+                .map(|(raw, iso)| (raw.to_string(), Some(iso.to_string()), at(raw)))
+                .collect::<Vec<_>>()
+        );
+        // Offsets count chars, not bytes ("Café" has a 2-byte "é").
+        assert_eq!(got[0].2, 12);
+    }
+
+    #[test]
+    fn extract_dates_flags_ambiguous_numeric_dates_unless_order_is_set() {
+        let text = "Due 5/1/24, shipped 5/5/24.";
+        let d = extract_dates(text);
+        assert_eq!(d.len(), 2);
+        assert!(d[0].ambiguous);
+        assert_eq!(d[0].iso, None);
+        assert_eq!(d[0].alternatives, vec!["2024-05-01", "2024-01-05"]);
+        assert_eq!(d[0].char_offset, 4);
+        assert!(!d[1].ambiguous);
+        assert_eq!(d[1].iso.as_deref(), Some("2024-05-05"));
+
+        let us = extract_dates_with(text, DateOrder::parse("mdy"));
+        assert_eq!(us[0].iso.as_deref(), Some("2024-05-01"));
+        assert!(!us[0].ambiguous && us[0].alternatives.is_empty());
+        let eu = extract_dates_with(text, DateOrder::DayFirst);
+        assert_eq!(eu[0].iso.as_deref(), Some("2024-01-05"));
+        assert_eq!(DateOrder::parse("whatever"), DateOrder::Auto);
+    }
 }
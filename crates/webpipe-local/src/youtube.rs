@@ -1,4 +1,4 @@
-//! YouTube helpers (transcripts).
+//! YouTube helpers (transcripts, playlist/channel expansion).
 //!
 //! Design goals:
 //! - Bounded (size/time).
@@ -7,7 +7,7 @@
 //!   YouTube’s moving target logic).
 
 use std::path::PathBuf;
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::time::Duration;
 
 pub fn is_youtube_host(host: &str) -> bool {
@@ -57,6 +57,152 @@ pub fn youtube_video_id(u: &url::Url) -> Option<String> {
     None
 }
 
+/// Playlist (`/playlist?list=...`) or channel (`/@handle`, `/channel/<id>`, `/c/<name>`,
+/// `/user/<name>`, optionally with a tab like `/videos`) URL: something [`expand_playlist`] can
+/// turn into video URLs. Single videos (even `watch?v=...&list=...`) are not collections.
+pub fn is_youtube_collection(u: &url::Url) -> bool {
+    let Some(host) = u.host_str() else {
+        return false;
+    };
+    if !is_youtube_host(host) || host.eq_ignore_ascii_case("youtu.be") {
+        return false;
+    }
+    if youtube_video_id(u).is_some() {
+        return false;
+    }
+    let mut segs = u
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .filter(|s| !s.is_empty());
+    match segs.next() {
+        Some("playlist") => u
+            .query_pairs()
+            .any(|(k, v)| k == "list" && !v.trim().is_empty()),
+        Some(a) if a.starts_with('@') && a.len() > 1 => true,
+        Some("channel" | "c" | "user") => segs.next().is_some(),
+        _ => false,
+    }
+}
+
+/// What to hand yt-dlp: a bare channel URL lists its tabs (Videos, Shorts, ...) rather than
+/// videos, so point it at the uploads tab.
+fn flat_playlist_target(u: &url::Url) -> String {
+    let segs: Vec<&str> = u
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .filter(|s| !s.is_empty())
+        .collect();
+    let bare_channel = match segs.as_slice() {
+        [a] => a.starts_with('@'),
+        ["channel" | "c" | "user", _] => true,
+        _ => false,
+    };
+    if bare_channel {
+        let mut t = u.clone();
+        t.set_path(&format!("{}/videos", segs.join("/")));
+        t.to_string()
+    } else {
+        u.to_string()
+    }
+}
+
+/// Video URLs from yt-dlp `--flat-playlist -j` output (one JSON object per line), in playlist
+/// order, deduplicated, at most `max`. Entries that aren't videos (nested playlists, tabs) are
+/// skipped.
+pub fn parse_flat_playlist(stdout: &str, max: usize) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    let mut out = Vec::new();
+    for line in stdout.lines() {
+        if out.len() >= max {
+            break;
+        }
+        let Ok(v) = serde_json::from_str::<serde_json::Value>(line.trim()) else {
+            continue;
+        };
+        let from_url = v
+            .get("url")
+            .and_then(|x| x.as_str())
+            .and_then(|s| url::Url::parse(s).ok())
+            .and_then(|u| youtube_video_id(&u));
+        let from_id = v
+            .get("id")
+            .and_then(|x| x.as_str())
+            .filter(|_| v.get("ie_key").and_then(|x| x.as_str()) == Some("Youtube"))
+            .map(str::to_string);
+        let Some(id) = from_url.or(from_id) else {
+            continue;
+        };
+        if seen.insert(id.clone()) {
+            out.push(format!("https://www.youtube.com/watch?v={id}"));
+        }
+    }
+    out
+}
+
+/// List up to `max` video URLs of a playlist or channel via `yt-dlp --flat-playlist` (metadata
+/// only; nothing is downloaded). Bounded by `WEBPIPE_YOUTUBE_PLAYLIST_TIMEOUT_MS` (default 30s).
+///
+/// Errors are stable codes: `youtube_ytdlp_not_found` when yt-dlp isn't installed,
+/// `youtube_not_a_collection`, `youtube_playlist_empty`, and the usual yt-dlp failures.
+pub fn expand_playlist(url: &str, max: usize) -> Result<Vec<String>, String> {
+    let u = url::Url::parse(url).map_err(|_| "youtube_invalid_url".to_string())?;
+    if !is_youtube_collection(&u) {
+        return Err("youtube_not_a_collection".to_string());
+    }
+    if !crate::shellout::has("yt-dlp") {
+        return Err("youtube_ytdlp_not_found".to_string());
+    }
+    let max = max.clamp(1, 500);
+    let timeout =
+        crate::shellout::timeout_from_env_ms("WEBPIPE_YOUTUBE_PLAYLIST_TIMEOUT_MS", 30_000);
+
+    let tmpdir = tempfile::tempdir().map_err(|_| "youtube_tempdir_failed".to_string())?;
+    // stdout goes to a file: a few hundred flat entries overflow a pipe buffer, and yt-dlp would
+    // block on it while we poll for exit.
+    let stdout_path = tmpdir.path().join("yt-dlp.stdout");
+    let stdout_file =
+        std::fs::File::create(&stdout_path).map_err(|_| "youtube_tempdir_failed".to_string())?;
+    let mut child = Command::new("yt-dlp")
+        .arg("--flat-playlist")
+        .arg("-j")
+        .arg("--playlist-end")
+        .arg(max.to_string())
+        .arg("--no-warnings")
+        .arg(flat_playlist_target(&u))
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(stdout_file))
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("youtube_ytdlp_spawn_failed: {e}"))?;
+    let start = std::time::Instant::now();
+    loop {
+        if let Some(st) = child
+            .try_wait()
+            .map_err(|e| format!("youtube_ytdlp_wait_failed: {e}"))?
+        {
+            if !st.success() {
+                return Err("youtube_ytdlp_nonzero_exit".to_string());
+            }
+            break;
+        }
+        if start.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err("youtube_ytdlp_timeout".to_string());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let stdout =
+        std::fs::read_to_string(&stdout_path).map_err(|e| format!("youtube_read_failed: {e}"))?;
+    let urls = parse_flat_playlist(&stdout, max);
+    if urls.is_empty() {
+        return Err("youtube_playlist_empty".to_string());
+    }
+    Ok(urls)
+}
+
 fn env(key: &str) -> Option<String> {
     std::env::var(key)
         .ok()
@@ -82,6 +228,137 @@ pub fn youtube_langs_from_env() -> Vec<String> {
         .collect()
 }
 
+/// `WEBPIPE_YOUTUBE_TIMESTAMPS=1`: transcripts keep per-cue start times and chapter markers, as
+/// [`TimedTranscript`] JSON ([`TRANSCRIPT_JSON_CONTENT_TYPE`]) instead of plain text.
+pub fn youtube_timestamps_from_env() -> bool {
+    matches!(
+        env("WEBPIPE_YOUTUBE_TIMESTAMPS")
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+            .as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+/// Content type of a plain transcript body.
+pub const TRANSCRIPT_CONTENT_TYPE: &str = "text/x-youtube-transcript";
+/// Content type of a [`TimedTranscript`] body.
+pub const TRANSCRIPT_JSON_CONTENT_TYPE: &str = "application/x-youtube-transcript+json";
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TranscriptCue {
+    pub start_s: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TranscriptChapter {
+    pub start_s: f64,
+    pub title: String,
+}
+
+/// Transcript with timing. `text` is the same plain text a timestamp-less fetch returns, so
+/// extraction and chunking read it and ignore the rest.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TimedTranscript {
+    pub text: String,
+    pub cues: Vec<TranscriptCue>,
+    #[serde(default)]
+    pub chapters: Vec<TranscriptChapter>,
+}
+
+/// `HH:MM:SS.mmm` or `MM:SS.mmm` (the start of a VTT timing line) in seconds.
+fn vtt_timestamp_s(s: &str) -> Option<f64> {
+    let mut total = 0.0;
+    for part in s.trim().split(':') {
+        total = total * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(total)
+}
+
+/// Cues with their start times. Inline markup (`<c>`, word timings) is dropped, and rolling
+/// auto-caption repeats (a cue whose text equals the previous one) are collapsed.
+fn vtt_to_cues(vtt: &str, max_chars: usize) -> Vec<TranscriptCue> {
+    fn strip_tags(s: &str) -> String {
+        let mut out = String::with_capacity(s.len());
+        let mut in_tag = false;
+        for ch in s.chars() {
+            match ch {
+                '<' => in_tag = true,
+                '>' if in_tag => in_tag = false,
+                _ if !in_tag => out.push(ch),
+                _ => {}
+            }
+        }
+        out
+    }
+
+    let mut cues: Vec<TranscriptCue> = Vec::new();
+    let mut chars = 0usize;
+    let mut cur: Option<TranscriptCue> = None;
+    fn flush(cur: &mut Option<TranscriptCue>, cues: &mut Vec<TranscriptCue>, chars: &mut usize) {
+        if let Some(c) = cur.take() {
+            let dup = cues.last().is_some_and(|p| p.text == c.text);
+            if !c.text.is_empty() && !dup {
+                *chars += c.text.chars().count();
+                cues.push(c);
+            }
+        }
+    }
+    for line in vtt.lines() {
+        let l = line.trim();
+        if let Some((start, _)) = l.split_once("-->") {
+            flush(&mut cur, &mut cues, &mut chars);
+            if chars >= max_chars {
+                break;
+            }
+            cur = vtt_timestamp_s(start).map(|start_s| TranscriptCue {
+                start_s,
+                text: String::new(),
+            });
+            continue;
+        }
+        if l.is_empty() {
+            flush(&mut cur, &mut cues, &mut chars);
+            continue;
+        }
+        if let Some(c) = cur.as_mut() {
+            let cleaned = strip_tags(l)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            if cleaned.is_empty() {
+                continue;
+            }
+            if !c.text.is_empty() {
+                c.text.push(' ');
+            }
+            c.text.push_str(&cleaned);
+        }
+    }
+    flush(&mut cur, &mut cues, &mut chars);
+    cues
+}
+
+/// Chapters from a yt-dlp `.info.json` (`chapters: [{start_time, title, ...}]`); empty when the
+/// video has none.
+fn info_json_chapters(info: &serde_json::Value) -> Vec<TranscriptChapter> {
+    info.get("chapters")
+        .and_then(|v| v.as_array())
+        .map(|a| {
+            a.iter()
+                .filter_map(|c| {
+                    Some(TranscriptChapter {
+                        start_s: c.get("start_time")?.as_f64()?,
+                        title: c.get("title")?.as_str()?.trim().to_string(),
+                    })
+                })
+                .filter(|c| !c.title.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 fn vtt_to_text(vtt: &str, max_chars: usize) -> String {
     // Very small, deterministic VTT -> text:
     // - drop WEBVTT header and timing lines
@@ -135,76 +412,242 @@ pub fn vtt_to_text_for_media(vtt: &str, max_chars: usize) -> String {
     vtt_to_text(vtt, max_chars)
 }
 
-pub fn fetch_transcript_via_ytdlp(url: &str, timeout: Duration) -> Result<String, String> {
-    // Bounds:
-    // - yt-dlp itself can take time; caller supplies timeout.
-    // - transcript is clipped post-hoc.
-    let max_chars = env_usize("WEBPIPE_YOUTUBE_MAX_CHARS", 200_000).min(2_000_000);
-    let langs = youtube_langs_from_env();
-    let langs_arg = if langs.is_empty() {
-        "en,en-US".to_string()
-    } else {
-        langs.join(",")
-    };
+/// Why a transcript attempt failed.
+///
+/// `NoTranscript` is final (the video has no captions, yt-dlp is missing, ...): retrying
+/// won't help. `Transient` covers throttling/timeouts, which are worth a bounded retry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranscriptError {
+    NoTranscript(String),
+    Transient(String),
+}
 
-    let tmpdir = tempfile::tempdir().map_err(|_| "youtube_tempdir_failed".to_string())?;
-    let out_tmpl = tmpdir.path().join("%(id)s.%(ext)s");
-
-    // Use VTT when possible (easy to parse deterministically).
-    // We ask for both human subs and auto subs; yt-dlp will use what exists.
-    let mut cmd = Command::new("yt-dlp");
-    cmd.arg("--skip-download")
-        .arg("--write-sub")
-        .arg("--write-auto-sub")
-        .arg("--sub-lang")
-        .arg(&langs_arg)
-        .arg("--sub-format")
-        .arg("vtt")
-        .arg("-o")
-        .arg(out_tmpl.to_string_lossy().to_string())
-        .arg("--no-warnings")
-        .arg(url);
+impl TranscriptError {
+    pub fn is_transient(&self) -> bool {
+        matches!(self, TranscriptError::Transient(_))
+    }
 
-    // Best-effort timeout: if it runs too long, kill.
-    // std::process::Command doesn't have builtin timeout; we do a coarse approach:
-    // spawn and wait with a sleep loop.
-    let mut child = cmd
-        .spawn()
-        .map_err(|e| format!("youtube_ytdlp_spawn_failed: {e}"))?;
-    let start = std::time::Instant::now();
-    loop {
-        if let Some(st) = child
-            .try_wait()
-            .map_err(|e| format!("youtube_ytdlp_wait_failed: {e}"))?
-        {
-            if !st.success() {
-                return Err("youtube_ytdlp_nonzero_exit".to_string());
-            }
-            break;
-        }
-        if start.elapsed() > timeout {
-            let _ = child.kill();
-            return Err("youtube_ytdlp_timeout".to_string());
+    pub fn code(&self) -> &str {
+        match self {
+            TranscriptError::NoTranscript(s) | TranscriptError::Transient(s) => s,
         }
-        std::thread::sleep(Duration::from_millis(50));
     }
+}
+
+impl std::fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// One transcript attempt. The real implementation shells out to `yt-dlp`; tests script it.
+pub trait TranscriptRunner {
+    fn run(&self, url: &str, timeout: Duration) -> Result<String, TranscriptError>;
+}
+
+/// `yt-dlp`-backed runner. With `timestamps`, it returns [`TimedTranscript`] JSON rather than
+/// plain text.
+pub struct YtDlpRunner {
+    pub timestamps: bool,
+}
+
+/// Heuristic: does yt-dlp's stderr look like throttling / a flaky network rather than
+/// a permanent "this video can't be fetched"?
+fn ytdlp_stderr_is_transient(stderr: &str) -> bool {
+    let s = stderr.to_ascii_lowercase();
+    [
+        "429",
+        "too many requests",
+        "timed out",
+        "connection reset",
+        "temporarily unavailable",
+        "temporary failure",
+        "http error 5",
+    ]
+    .iter()
+    .any(|m| s.contains(m))
+}
+
+impl TranscriptRunner for YtDlpRunner {
+    fn run(&self, url: &str, timeout: Duration) -> Result<String, TranscriptError> {
+        use TranscriptError::{NoTranscript, Transient};
+
+        // Bounds:
+        // - yt-dlp itself can take time; caller supplies timeout.
+        // - transcript is clipped post-hoc.
+        let max_chars = env_usize("WEBPIPE_YOUTUBE_MAX_CHARS", 200_000).min(2_000_000);
+        let langs = youtube_langs_from_env();
+        let langs_arg = if langs.is_empty() {
+            "en,en-US".to_string()
+        } else {
+            langs.join(",")
+        };
 
-    // Pick the first VTT file produced.
-    let mut best: Option<PathBuf> = None;
-    if let Ok(rd) = std::fs::read_dir(tmpdir.path()) {
-        for ent in rd.flatten() {
-            let p = ent.path();
-            if p.extension().and_then(|s| s.to_str()) == Some("vtt") {
-                best = Some(p);
+        let tmpdir =
+            tempfile::tempdir().map_err(|_| Transient("youtube_tempdir_failed".to_string()))?;
+        let out_tmpl = tmpdir.path().join("%(id)s.%(ext)s");
+        // stderr goes to a file (not a pipe) so a chatty yt-dlp can't block on a full buffer
+        // while we poll; we only read it to classify failures.
+        let stderr_path = tmpdir.path().join("yt-dlp.stderr");
+        let stderr_file = std::fs::File::create(&stderr_path)
+            .map_err(|_| Transient("youtube_tempdir_failed".to_string()))?;
+
+        // Use VTT when possible (easy to parse deterministically).
+        // We ask for both human subs and auto subs; yt-dlp will use what exists.
+        let mut cmd = Command::new("yt-dlp");
+        cmd.arg("--skip-download")
+            .arg("--write-sub")
+            .arg("--write-auto-sub")
+            .arg("--sub-lang")
+            .arg(&langs_arg)
+            .arg("--sub-format")
+            .arg("vtt")
+            .arg("-o")
+            .arg(out_tmpl.to_string_lossy().to_string())
+            .arg("--no-warnings");
+        if self.timestamps {
+            // Chapters only come with the video metadata.
+            cmd.arg("--write-info-json");
+        }
+        cmd.arg(url)
+            .stdout(Stdio::null())
+            .stderr(Stdio::from(stderr_file));
+
+        // Best-effort timeout: if it runs too long, kill.
+        // std::process::Command doesn't have builtin timeout; we do a coarse approach:
+        // spawn and wait with a sleep loop.
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| NoTranscript(format!("youtube_ytdlp_spawn_failed: {e}")))?;
+        let start = std::time::Instant::now();
+        loop {
+            if let Some(st) = child
+                .try_wait()
+                .map_err(|e| Transient(format!("youtube_ytdlp_wait_failed: {e}")))?
+            {
+                if !st.success() {
+                    let stderr = std::fs::read_to_string(&stderr_path).unwrap_or_default();
+                    let code = "youtube_ytdlp_nonzero_exit".to_string();
+                    return Err(if ytdlp_stderr_is_transient(&stderr) {
+                        Transient(code)
+                    } else {
+                        NoTranscript(code)
+                    });
+                }
                 break;
             }
+            if start.elapsed() > timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(Transient("youtube_ytdlp_timeout".to_string()));
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        // Pick the first VTT file produced.
+        let mut best: Option<PathBuf> = None;
+        if let Ok(rd) = std::fs::read_dir(tmpdir.path()) {
+            for ent in rd.flatten() {
+                let p = ent.path();
+                if p.extension().and_then(|s| s.to_str()) == Some("vtt") {
+                    best = Some(p);
+                    break;
+                }
+            }
         }
+        let Some(p) = best else {
+            return Err(NoTranscript("youtube_no_captions_found".to_string()));
+        };
+        let vtt = std::fs::read_to_string(&p)
+            .map_err(|e| Transient(format!("youtube_read_failed: {e}")))?;
+        let text = vtt_to_text(&vtt, max_chars);
+        if !self.timestamps {
+            return Ok(text);
+        }
+        let chapters = std::fs::read_dir(tmpdir.path())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|ent| ent.path())
+            .find(|p| p.to_string_lossy().ends_with(".info.json"))
+            .and_then(|p| std::fs::read(p).ok())
+            .and_then(|b| serde_json::from_slice::<serde_json::Value>(&b).ok())
+            .map(|info| info_json_chapters(&info))
+            .unwrap_or_default();
+        let timed = TimedTranscript {
+            text,
+            cues: vtt_to_cues(&vtt, max_chars),
+            chapters,
+        };
+        serde_json::to_string(&timed).map_err(|e| Transient(format!("youtube_encode_failed: {e}")))
     }
-    let Some(p) = best else {
-        return Err("youtube_no_captions_found".to_string());
-    };
-    let vtt = std::fs::read_to_string(&p).map_err(|e| format!("youtube_read_failed: {e}"))?;
-    Ok(vtt_to_text(&vtt, max_chars))
+}
+
+/// Retry knobs for the transcript path.
+#[derive(Debug, Clone, Copy)]
+pub struct TranscriptRetry {
+    /// Extra attempts after the first one (0 = no retry).
+    pub retries: usize,
+    /// Sleep before the first retry; doubles on each subsequent retry.
+    pub backoff: Duration,
+}
+
+impl TranscriptRetry {
+    pub fn from_env() -> Self {
+        Self {
+            retries: env_usize("WEBPIPE_YOUTUBE_RETRIES", 1).min(5),
+            backoff: Duration::from_millis(
+                env_usize("WEBPIPE_YOUTUBE_RETRY_BACKOFF_MS", 500).min(10_000) as u64,
+            ),
+        }
+    }
+}
+
+/// Run `runner` with a bounded retry on transient failures.
+///
+/// `timeout` is the budget for the whole call (attempts + backoff), not per attempt, so
+/// callers keep the same worst-case latency they had before retries existed.
+pub fn fetch_transcript_with_retry(
+    runner: &dyn TranscriptRunner,
+    url: &str,
+    timeout: Duration,
+    retry: TranscriptRetry,
+) -> Result<String, TranscriptError> {
+    let start = std::time::Instant::now();
+    let mut backoff = retry.backoff;
+    let mut attempt = 0usize;
+    loop {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        let err = match runner.run(url, remaining) {
+            Ok(text) => return Ok(text),
+            Err(e) => e,
+        };
+        if !err.is_transient() || attempt >= retry.retries {
+            return Err(err);
+        }
+        // Don't start a retry we can't finish inside the budget.
+        if start.elapsed() + backoff >= timeout {
+            return Err(err);
+        }
+        std::thread::sleep(backoff);
+        backoff = backoff.saturating_mul(2);
+        attempt += 1;
+    }
+}
+
+/// Plain text, or [`TimedTranscript`] JSON with `timestamps`.
+pub fn fetch_transcript_via_ytdlp(
+    url: &str,
+    timeout: Duration,
+    timestamps: bool,
+) -> Result<String, String> {
+    fetch_transcript_with_retry(
+        &YtDlpRunner { timestamps },
+        url,
+        timeout,
+        TranscriptRetry::from_env(),
+    )
+    .map_err(|e| e.to_string())
 }
 
 #[cfg(test)]
@@ -223,6 +666,65 @@ mod tests {
         assert_eq!(youtube_video_id(&u).as_deref(), Some("dQw4w9WgXcQ"));
     }
 
+    #[test]
+    fn youtube_collection_urls_and_flat_playlist_targets() {
+        let parse = |s: &str| url::Url::parse(s).unwrap();
+        for s in [
+            "https://www.youtube.com/playlist?list=PL123",
+            "https://www.youtube.com/@somecreator",
+            "https://www.youtube.com/@somecreator/streams",
+            "https://youtube.com/channel/UC123",
+            "https://m.youtube.com/c/somename",
+        ] {
+            assert!(is_youtube_collection(&parse(s)), "{s}");
+        }
+        for s in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PL123",
+            "https://youtu.be/dQw4w9WgXcQ",
+            "https://www.youtube.com/playlist",
+            "https://www.youtube.com/channel",
+            "https://www.youtube.com/",
+            "https://example.com/@someone",
+        ] {
+            assert!(!is_youtube_collection(&parse(s)), "{s}");
+        }
+        assert_eq!(
+            flat_playlist_target(&parse("https://www.youtube.com/@somecreator")),
+            "https://www.youtube.com/@somecreator/videos"
+        );
+        assert_eq!(
+            flat_playlist_target(&parse("https://www.youtube.com/@somecreator/shorts")),
+            "https://www.youtube.com/@somecreator/shorts"
+        );
+        assert_eq!(
+            flat_playlist_target(&parse("https://www.youtube.com/playlist?list=PL123")),
+            "https://www.youtube.com/playlist?list=PL123"
+        );
+    }
+
+    #[test]
+    fn parse_flat_playlist_keeps_videos_in_order_bounded() {
+        let out = r#"{"_type": "url", "ie_key": "Youtube", "id": "aaaaaaaaaaa", "url": "https://www.youtube.com/watch?v=aaaaaaaaaaa"}
+{"_type": "url", "ie_key": "Youtube", "id": "bbbbbbbbbbb"}
+not json
+{"_type": "url", "ie_key": "YoutubeTab", "id": "UC123", "url": "https://www.youtube.com/channel/UC123/shorts"}
+{"_type": "url", "ie_key": "Youtube", "id": "aaaaaaaaaaa"}
+{"_type": "url", "url": "https://youtu.be/ccccccccccc"}
+{"_type": "url", "ie_key": "Youtube", "id": "ddddddddddd"}
+"#;
+        assert_eq!(
+            parse_flat_playlist(out, 10),
+            vec![
+                "https://www.youtube.com/watch?v=aaaaaaaaaaa",
+                "https://www.youtube.com/watch?v=bbbbbbbbbbb",
+                "https://www.youtube.com/watch?v=ccccccccccc",
+                "https://www.youtube.com/watch?v=ddddddddddd",
+            ]
+        );
+        assert_eq!(parse_flat_playlist(out, 2).len(), 2);
+        assert!(parse_flat_playlist("", 10).is_empty());
+    }
+
     #[test]
     fn vtt_to_text_drops_timings() {
         let vtt = r#"WEBVTT
@@ -238,4 +740,144 @@ Second line
         assert!(t.contains("Second line"));
         assert!(!t.contains("-->"));
     }
+
+    #[test]
+    fn vtt_to_cues_keeps_start_times_and_collapses_rolling_repeats() {
+        let vtt = r#"WEBVTT
+Kind: captions
+
+00:00:01.500 --> 00:00:03.000 align:start position:0%
+Hello<00:00:02.000><c> world</c>
+
+00:00:03.000 --> 00:00:04.000
+Hello world
+
+01:02:03.250 --> 01:02:05.000
+Second   line
+"#;
+        let cues = vtt_to_cues(vtt, 10_000);
+        assert_eq!(
+            cues,
+            vec![
+                TranscriptCue {
+                    start_s: 1.5,
+                    text: "Hello world".to_string()
+                },
+                TranscriptCue {
+                    start_s: 3723.25,
+                    text: "Second line".to_string()
+                },
+            ]
+        );
+        // The char budget stops at a cue boundary.
+        assert_eq!(vtt_to_cues(vtt, 5).len(), 1);
+
+        let info = serde_json::json!({
+            "chapters": [
+                {"start_time": 0.0, "end_time": 60.0, "title": "Intro"},
+                {"start_time": 60.0, "end_time": 120.0, "title": " "},
+                {"start_time": 120.0, "end_time": 300.0, "title": "Results"}
+            ]
+        });
+        assert_eq!(
+            info_json_chapters(&info),
+            vec![
+                TranscriptChapter {
+                    start_s: 0.0,
+                    title: "Intro".to_string()
+                },
+                TranscriptChapter {
+                    start_s: 120.0,
+                    title: "Results".to_string()
+                },
+            ]
+        );
+        assert!(info_json_chapters(&serde_json::json!({})).is_empty());
+    }
+
+    /// Scripted runner: pops one result per attempt and counts calls.
+    struct MockRunner {
+        results: std::sync::Mutex<Vec<Result<String, TranscriptError>>>,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl MockRunner {
+        fn new(mut results: Vec<Result<String, TranscriptError>>) -> Self {
+            results.reverse();
+            Self {
+                results: std::sync::Mutex::new(results),
+                calls: std::sync::atomic::AtomicUsize::new(0),
+            }
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    impl TranscriptRunner for MockRunner {
+        fn run(&self, _url: &str, _timeout: Duration) -> Result<String, TranscriptError> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.results
+                .lock()
+                .unwrap()
+                .pop()
+                .unwrap_or_else(|| Err(TranscriptError::Transient("exhausted".to_string())))
+        }
+    }
+
+    const URL: &str = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+
+    fn retry(n: usize) -> TranscriptRetry {
+        TranscriptRetry {
+            retries: n,
+            backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn transcript_retries_transient_then_succeeds() {
+        let runner = MockRunner::new(vec![
+            Err(TranscriptError::Transient(
+                "youtube_ytdlp_timeout".to_string(),
+            )),
+            Ok("hello world".to_string()),
+        ]);
+        let r = fetch_transcript_with_retry(&runner, URL, Duration::from_secs(5), retry(2));
+        assert_eq!(r.as_deref(), Ok("hello world"));
+        assert_eq!(runner.calls(), 2);
+    }
+
+    #[test]
+    fn transcript_does_not_retry_when_no_captions() {
+        let runner = MockRunner::new(vec![
+            Err(TranscriptError::NoTranscript(
+                "youtube_no_captions_found".to_string(),
+            )),
+            Ok("unreachable".to_string()),
+        ]);
+        let r = fetch_transcript_with_retry(&runner, URL, Duration::from_secs(5), retry(3));
+        let e = r.unwrap_err();
+        assert!(!e.is_transient());
+        assert_eq!(e.code(), "youtube_no_captions_found");
+        assert_eq!(runner.calls(), 1);
+    }
+
+    #[test]
+    fn transcript_retry_is_bounded() {
+        let runner = MockRunner::new(vec![]);
+        let r = fetch_transcript_with_retry(&runner, URL, Duration::from_secs(5), retry(2));
+        assert!(r.unwrap_err().is_transient());
+        assert_eq!(runner.calls(), 3);
+    }
+
+    #[test]
+    fn ytdlp_stderr_classification() {
+        assert!(ytdlp_stderr_is_transient(
+            "ERROR: unable to download: HTTP Error 429: Too Many Requests"
+        ));
+        assert!(!ytdlp_stderr_is_transient(
+            "ERROR: [youtube] abc: Video unavailable"
+        ));
+    }
 }
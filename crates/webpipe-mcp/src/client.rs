@@ -0,0 +1,343 @@
+//! High-level client for embedding webpipe as a library.
+//!
+//! [`Webpipe`] wires up the same pieces the MCP server uses (the cached [`LocalFetcher`], the
+//! env-configured search providers and the extraction pipeline) behind typed methods. It does
+//! not depend on the MCP layer, so it is available with `--no-default-features`.
+
+use std::path::PathBuf;
+
+use webpipe_core::{
+    Error, FetchBackend, FetchCachePolicy, FetchRequest, FetchResponse, Result, SearchProvider,
+    SearchQuery, SearchResponse, SearchResult,
+};
+use webpipe_local::extract::{ExtractPipelineCfg, ScoredChunk, TruncationStrategy};
+use webpipe_local::LocalFetcher;
+
+/// Search providers in the order `provider: "auto"` tries them: free before paid, cheaper paid
+/// before pricier paid (the server's `cost_cascade` order).
+const AUTO_PROVIDERS: &[&str] = &["searxng", "brave", "tavily"];
+
+/// Options for [`Webpipe::extract`]. Defaults match `web_extract`.
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    /// Rank chunks against this query (and window long texts around it).
+    pub query: Option<String>,
+    pub timeout_ms: u64,
+    pub max_bytes: u64,
+    /// Text wrap width for HTML rendering.
+    pub width: usize,
+    /// Cap on `Extracted::text`, in chars.
+    pub max_chars: usize,
+    pub top_chunks: usize,
+    pub max_chunk_chars: usize,
+    pub cache: FetchCachePolicy,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            query: None,
+            timeout_ms: 20_000,
+            max_bytes: 5_000_000,
+            width: 100,
+            max_chars: 20_000,
+            top_chunks: 5,
+            max_chunk_chars: 500,
+            cache: FetchCachePolicy::default(),
+        }
+    }
+}
+
+/// Options for [`Webpipe::search`].
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    /// `"auto"` (default), `"searxng"`, `"brave"`, `"tavily"` or `"ddg"` (keyless DuckDuckGo).
+    pub provider: String,
+    pub max_results: usize,
+    pub language: Option<String>,
+    pub country: Option<String>,
+    pub timeout_ms: Option<u64>,
+    /// `"day"`, `"week"`, `"month"` or `"year"`; see [`SearchQuery::freshness`].
+    pub freshness: Option<String>,
+    /// ISO dates (`YYYY-MM-DD`) bounding publication time.
+    pub after: Option<String>,
+    pub before: Option<String>,
+    /// Skip this many results (see [`SearchQuery::offset`]).
+    pub offset: Option<usize>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            provider: "auto".to_string(),
+            max_results: 10,
+            language: None,
+            country: None,
+            timeout_ms: None,
+            freshness: None,
+            after: None,
+            before: None,
+            offset: None,
+        }
+    }
+}
+
+/// Options for [`Webpipe::search_extract`].
+#[derive(Debug, Clone)]
+pub struct SearchExtractOptions {
+    pub search: SearchOptions,
+    /// Extract at most this many of the top search results.
+    pub max_urls: usize,
+    /// Per-URL extraction options; `query` defaults to the search query.
+    pub extract: ExtractOptions,
+}
+
+impl Default for SearchExtractOptions {
+    fn default() -> Self {
+        Self {
+            search: SearchOptions::default(),
+            max_urls: 3,
+            extract: ExtractOptions::default(),
+        }
+    }
+}
+
+/// Text and top chunks extracted from one URL.
+#[derive(Debug, Clone)]
+pub struct Extracted {
+    pub url: String,
+    pub final_url: String,
+    /// HTTP status of the fetch; non-2xx bodies are still extracted.
+    pub status: u16,
+    pub content_type: Option<String>,
+    /// Extraction engine that produced `text` (e.g. `html_main`, `pdf-extract`).
+    pub engine: String,
+    pub text: String,
+    /// Chars in `text` (after `max_chars`).
+    pub text_chars: usize,
+    pub text_truncated: bool,
+    pub chunks: Vec<ScoredChunk>,
+    /// Published date from the page metadata (HTML only), as written.
+    pub published_time: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+/// One search result and its extraction (or why it failed).
+#[derive(Debug, Clone)]
+pub struct SearchExtractItem {
+    pub result: SearchResult,
+    pub extract: Option<Extracted>,
+    pub error: Option<String>,
+}
+
+/// Output of [`Webpipe::search_extract`].
+#[derive(Debug, Clone)]
+pub struct SearchExtractOutput {
+    pub provider: String,
+    pub items: Vec<SearchExtractItem>,
+}
+
+/// Library entrypoint: fetch, extract and search without running the MCP server.
+pub struct Webpipe {
+    fetcher: LocalFetcher,
+    http: reqwest::Client,
+}
+
+impl Webpipe {
+    /// Build from the environment the server reads: `WEBPIPE_CACHE_DIR` (default: the per-user
+    /// cache dir) plus the search provider keys/endpoints, which are resolved per call.
+    pub fn from_env() -> Result<Self> {
+        let cache_dir = std::env::var("WEBPIPE_CACHE_DIR")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                dirs::cache_dir()
+                    .unwrap_or_else(std::env::temp_dir)
+                    .join("webpipe-cache")
+            });
+        Self::with_cache_dir(Some(cache_dir))
+    }
+
+    /// Build with an explicit fetch cache dir (`None` disables the cache).
+    pub fn with_cache_dir(cache_dir: Option<PathBuf>) -> Result<Self> {
+        let fetcher = LocalFetcher::new(cache_dir)?;
+        let http = reqwest::Client::builder()
+            .user_agent("webpipe/0.1")
+            .build()
+            .map_err(|e| Error::Fetch(e.to_string()))?;
+        Ok(Self { fetcher, http })
+    }
+
+    /// Fetch `url` with the server's default bounds (20s, 5 MB, cache read+write).
+    pub async fn fetch(&self, url: &str) -> Result<FetchResponse> {
+        let opts = ExtractOptions::default();
+        self.fetcher.fetch(&fetch_request(url, &opts)).await
+    }
+
+    /// Fetch `url` and run the extraction pipeline over the body.
+    pub async fn extract(&self, url: &str, opts: &ExtractOptions) -> Result<Extracted> {
+        let resp = self.fetcher.fetch(&fetch_request(url, opts)).await?;
+        let ct = resp.content_type.as_deref();
+        let r = webpipe_local::extract::extract_pipeline_from_bytes(
+            &resp.bytes,
+            ct,
+            &resp.final_url,
+            ExtractPipelineCfg {
+                query: opts.query.as_deref(),
+                width: opts.width.clamp(20, 240),
+                max_chars: opts.max_chars.min(200_000),
+                top_chunks: opts.top_chunks.clamp(1, 50),
+                max_chunk_chars: opts.max_chunk_chars.clamp(50, 5_000),
+                include_structure: false,
+                max_outline_items: 0,
+                max_blocks: 0,
+                max_block_chars: 0,
+                truncation_strategy: TruncationStrategy::Head,
+            },
+        );
+        let ct0 = ct
+            .and_then(|c| c.split(';').next())
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase();
+        let html_like = ct0 == "text/html"
+            || ct0 == "application/xhtml+xml"
+            || webpipe_local::extract::bytes_look_like_html(&resp.bytes);
+        let published_time = html_like
+            .then(|| webpipe_local::published::published_time(&resp.text_lossy()))
+            .flatten();
+        let mut warnings: Vec<String> =
+            r.extracted.warnings.iter().map(|w| w.to_string()).collect();
+        if resp.truncated {
+            warnings.push("body_truncated_by_max_bytes".to_string());
+        }
+        if r.text_truncated {
+            warnings.push("text_truncated_by_max_chars".to_string());
+        }
+        Ok(Extracted {
+            url: resp.url,
+            final_url: resp.final_url,
+            status: resp.status,
+            content_type: resp.content_type,
+            engine: r.extracted.engine.to_string(),
+            text: r.extracted.text,
+            text_chars: r.text_chars,
+            text_truncated: r.text_truncated,
+            chunks: r.chunks,
+            published_time,
+            warnings,
+        })
+    }
+
+    /// Search with one provider, or with `"auto"`: each configured provider in
+    /// [`AUTO_PROVIDERS`] order until one succeeds.
+    pub async fn search(&self, query: &str, opts: &SearchOptions) -> Result<SearchResponse> {
+        let q = SearchQuery {
+            query: query.to_string(),
+            max_results: Some(opts.max_results.clamp(1, 20)),
+            language: opts.language.clone(),
+            country: opts.country.clone(),
+            timeout_ms: opts.timeout_ms,
+            site: None,
+            freshness: opts.freshness.clone(),
+            after: opts.after.clone(),
+            before: opts.before.clone(),
+            offset: opts.offset,
+        };
+        let provider = opts.provider.trim().to_ascii_lowercase();
+        if provider != "auto" {
+            return self.search_with(&provider, &q).await;
+        }
+        // Unconfigured providers are skipped; the last real failure wins over "not configured".
+        let mut last_err = None;
+        for name in AUTO_PROVIDERS {
+            match self.search_with(name, &q).await {
+                Ok(r) => return Ok(r),
+                Err(Error::NotConfigured(_)) => {}
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            Error::NotConfigured(
+                "no web search providers configured (set WEBPIPE_SEARXNG_ENDPOINT, \
+                 WEBPIPE_BRAVE_API_KEY or WEBPIPE_TAVILY_API_KEY)"
+                    .to_string(),
+            )
+        }))
+    }
+
+    async fn search_with(&self, provider: &str, q: &SearchQuery) -> Result<SearchResponse> {
+        use webpipe_local::search::{
+            BraveSearchProvider, DuckDuckGoProvider, SearxngSearchProvider, TavilySearchProvider,
+        };
+        let client = self.http.clone();
+        match provider {
+            "searxng" => SearxngSearchProvider::from_env(client)?.search(q).await,
+            "brave" => BraveSearchProvider::from_env(client)?.search(q).await,
+            "tavily" => TavilySearchProvider::from_env(client)?.search(q).await,
+            "ddg" => DuckDuckGoProvider::from_env(client)?.search(q).await,
+            other => Err(Error::NotSupported(format!(
+                "unknown search provider: {other} (expected auto, searxng, brave, tavily or ddg)"
+            ))),
+        }
+    }
+
+    /// Search, then extract the top `max_urls` results concurrently. Per-URL failures are
+    /// reported on the item instead of failing the call.
+    pub async fn search_extract(
+        &self,
+        query: &str,
+        opts: &SearchExtractOptions,
+    ) -> Result<SearchExtractOutput> {
+        let resp = self.search(query, &opts.search).await?;
+        let mut extract = opts.extract.clone();
+        if extract.query.is_none() {
+            extract.query = Some(query.to_string());
+        }
+        let top: Vec<SearchResult> = resp
+            .results
+            .into_iter()
+            .take(opts.max_urls.clamp(1, 10))
+            .collect();
+        let extracted =
+            futures::future::join_all(top.iter().map(|r| self.extract(&r.url, &extract))).await;
+        let items = top
+            .into_iter()
+            .zip(extracted)
+            .map(|(result, x)| match x {
+                Ok(x) if (200..300).contains(&x.status) => SearchExtractItem {
+                    result,
+                    extract: Some(x),
+                    error: None,
+                },
+                Ok(x) => SearchExtractItem {
+                    result,
+                    extract: None,
+                    error: Some(format!("http status {}", x.status)),
+                },
+                Err(e) => SearchExtractItem {
+                    result,
+                    extract: None,
+                    error: Some(e.to_string()),
+                },
+            })
+            .collect();
+        Ok(SearchExtractOutput {
+            provider: resp.provider,
+            items,
+        })
+    }
+}
+
+fn fetch_request(url: &str, opts: &ExtractOptions) -> FetchRequest {
+    FetchRequest {
+        url: url.to_string(),
+        timeout_ms: Some(opts.timeout_ms.min(60_000)),
+        max_bytes: Some(opts.max_bytes),
+        headers: Default::default(),
+        method: None,
+        body: None,
+        cache: opts.cache.clone(),
+    }
+}
@@ -284,6 +284,11 @@ pub async fn eval_search(spec: EvalSearchSpec) -> Result<PathBuf> {
             language: spec.language.clone(),
             country: spec.country.clone(),
             timeout_ms: Some(20_000),
+            site: None,
+            freshness: None,
+            after: None,
+            before: None,
+            offset: None,
         };
 
         let mut per_provider = Vec::new();
@@ -478,6 +483,8 @@ pub async fn eval_fetch(spec: EvalFetchSpec) -> Result<PathBuf> {
                         timeout_ms: Some(spec.timeout_ms),
                         max_bytes: Some(spec.max_bytes),
                         headers: BTreeMap::new(),
+                        method: None,
+                        body: None,
                         cache: FetchCachePolicy::default(),
                     };
                     let t0 = std::time::Instant::now();
@@ -498,6 +505,8 @@ pub async fn eval_fetch(spec: EvalFetchSpec) -> Result<PathBuf> {
                                     max_outline_items: 0,
                                     max_blocks: 0,
                                     max_block_chars: 0,
+                                    truncation_strategy:
+                                        webpipe_local::extract::TruncationStrategy::Head,
                                 };
                                 let pipe = webpipe_local::extract::extract_pipeline_from_bytes(
                                     &r.bytes,
@@ -618,6 +627,158 @@ pub async fn eval_fetch(spec: EvalFetchSpec) -> Result<PathBuf> {
     Ok(spec.out)
 }
 
+pub struct EvalExtractSpec {
+    /// Directory of `<name>.html` fixtures with `<name>.expected.txt` goldens.
+    pub fixtures_dir: PathBuf,
+    pub engines: Vec<String>, // html2text, html_main, readability, auto
+    pub width: usize,
+    pub out: PathBuf,
+    pub now_epoch_s: Option<u64>,
+}
+
+/// Engines understood by `eval_extract`.
+pub const EVAL_EXTRACT_ENGINES: &[&str] = &["html2text", "html_main", "readability", "auto"];
+
+fn run_extract_engine(engine: &str, html: &str, width: usize) -> Option<String> {
+    use webpipe_local::extract;
+    match engine {
+        "html2text" => Some(extract::html_to_text(html, width)),
+        "html_main" => extract::html_main_to_text(html, width),
+        "readability" => extract::html_readability_to_text(html, width),
+        // The default pipeline engine selection (what tools actually use).
+        "auto" => Some(
+            extract::best_effort_text_from_bytes(
+                html.as_bytes(),
+                Some("text/html"),
+                "file://fixture.html",
+                width,
+                500,
+            )
+            .text,
+        ),
+        _ => None,
+    }
+}
+
+/// Load `(name, html, golden)` triples, sorted by name so artifacts are deterministic.
+fn load_extract_fixtures(dir: &Path) -> Result<Vec<(String, String, String)>> {
+    let mut out = Vec::new();
+    for e in fs::read_dir(dir)? {
+        let p = e?.path();
+        if p.extension().and_then(|x| x.to_str()) != Some("html") {
+            continue;
+        }
+        let Some(stem) = p.file_stem().and_then(|x| x.to_str()) else {
+            continue;
+        };
+        let golden_p = dir.join(format!("{stem}.expected.txt"));
+        if !golden_p.exists() {
+            continue;
+        }
+        out.push((
+            stem.to_string(),
+            fs::read_to_string(&p)?,
+            fs::read_to_string(&golden_p)?,
+        ));
+    }
+    out.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(out)
+}
+
+pub fn eval_extract(spec: EvalExtractSpec) -> Result<PathBuf> {
+    let generated_at_epoch_s = spec.now_epoch_s.unwrap_or_else(now_epoch_s);
+    for e in &spec.engines {
+        if !EVAL_EXTRACT_ENGINES.contains(&e.as_str()) {
+            anyhow::bail!(
+                "unknown extract engine: {e} (allowed: {})",
+                EVAL_EXTRACT_ENGINES.join(",")
+            );
+        }
+    }
+    let fixtures = load_extract_fixtures(&spec.fixtures_dir)?;
+    if fixtures.is_empty() {
+        anyhow::bail!(
+            "no fixtures found in {} (expected <name>.html + <name>.expected.txt)",
+            spec.fixtures_dir.display()
+        );
+    }
+
+    let mut per_fixture = Vec::new();
+    let mut sums: BTreeMap<String, (f64, f64, f64, usize)> = BTreeMap::new();
+    for (name, html, golden) in &fixtures {
+        let mut per_engine = Vec::new();
+        for engine in &spec.engines {
+            let t0 = std::time::Instant::now();
+            let text = run_extract_engine(engine, html, spec.width).unwrap_or_default();
+            let elapsed_us = t0.elapsed().as_micros();
+            let (precision, recall) = webpipe_local::compare::token_precision_recall(&text, golden);
+            let f1 = if precision + recall > 0.0 {
+                2.0 * precision * recall / (precision + recall)
+            } else {
+                0.0
+            };
+            let e = sums.entry(engine.clone()).or_insert((0.0, 0.0, 0.0, 0));
+            e.0 += precision;
+            e.1 += recall;
+            e.2 += elapsed_us as f64 / 1000.0;
+            e.3 += usize::from(text.trim().is_empty());
+            per_engine.push(serde_json::json!({
+                "engine": engine,
+                "precision": precision,
+                "recall": recall,
+                "f1": f1,
+                "text_chars": text.chars().count(),
+                "elapsed_us": elapsed_us,
+            }));
+        }
+        per_fixture.push(serde_json::json!({
+            "fixture": name,
+            "golden_chars": golden.chars().count(),
+            "engines": per_engine,
+        }));
+    }
+
+    let n = fixtures.len() as f64;
+    let mut engines = serde_json::Map::new();
+    for engine in &spec.engines {
+        let (p, r, ms, empty) = sums.get(engine).copied().unwrap_or((0.0, 0.0, 0.0, 0));
+        let (p, r) = (p / n, r / n);
+        let f1 = if p + r > 0.0 {
+            2.0 * p * r / (p + r)
+        } else {
+            0.0
+        };
+        engines.insert(
+            engine.clone(),
+            serde_json::json!({
+                "mean_precision": p,
+                "mean_recall": r,
+                "f1": f1,
+                "mean_extract_ms": ms / n,
+                "empty_outputs": empty,
+            }),
+        );
+    }
+
+    let payload = serde_json::json!({
+        "schema_version": 1,
+        "kind": "eval_extract",
+        "generated_at_epoch_s": generated_at_epoch_s,
+        "inputs": {
+            "fixtures_dir": spec.fixtures_dir.display().to_string(),
+            "engines": spec.engines,
+            "width": spec.width,
+            "fixture_count": fixtures.len()
+        },
+        "engines": engines,
+        "fixtures": per_fixture
+    });
+
+    ensure_parent_dir(&spec.out)?;
+    fs::write(&spec.out, serde_json::to_string_pretty(&payload)? + "\n")?;
+    Ok(spec.out)
+}
+
 pub fn load_queries(files: &[PathBuf], inline: &[String]) -> Result<Vec<String>> {
     let mut out = Vec::new();
     for p in files {
@@ -659,6 +820,24 @@ pub struct E2eQueryV1 {
     pub url_paths: Vec<String>,
 }
 
+impl E2eQueryV1 {
+    /// `url_paths` as absolute URLs: relative paths are joined onto `base_url`.
+    pub fn expand_urls(&self, base_url: &str) -> Vec<String> {
+        let base_url = base_url.trim_end_matches('/');
+        self.url_paths
+            .iter()
+            .map(|p| {
+                let p = p.trim();
+                if p.starts_with("http://") || p.starts_with("https://") {
+                    p.to_string()
+                } else {
+                    format!("{base_url}/{}", p.trim_start_matches('/'))
+                }
+            })
+            .collect()
+    }
+}
+
 pub fn load_e2e_queries_v1(path: &Path) -> Result<E2eQueriesV1> {
     let raw = fs::read_to_string(path)?;
     let v: E2eQueriesV1 = serde_json::from_str(&raw)?;
@@ -802,4 +981,55 @@ mod tests {
             .iter()
             .all(|q| !q.expected_url_substrings.is_empty()));
     }
+
+    #[test]
+    fn eval_extract_writes_per_engine_metrics() {
+        let base = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures")
+            .join("extract");
+        let tmp = tempfile::tempdir().expect("tmp");
+        let out = tmp.path().join("eval-extract.json");
+        let spec = EvalExtractSpec {
+            fixtures_dir: base,
+            engines: EVAL_EXTRACT_ENGINES.iter().map(|s| s.to_string()).collect(),
+            width: 100,
+            out: out.clone(),
+            now_epoch_s: Some(1_700_000_000),
+        };
+        let p = eval_extract(spec).expect("eval_extract");
+        let v: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(p).unwrap()).unwrap();
+        assert_eq!(v["kind"].as_str(), Some("eval_extract"));
+        assert_eq!(v["generated_at_epoch_s"].as_u64(), Some(1_700_000_000));
+        assert!(v["inputs"]["fixture_count"].as_u64().unwrap() >= 3);
+        for e in EVAL_EXTRACT_ENGINES {
+            let m = &v["engines"][*e];
+            for k in ["mean_precision", "mean_recall", "f1", "mean_extract_ms"] {
+                let x = m[k].as_f64().unwrap_or(-1.0);
+                assert!(x >= 0.0, "engine={e} metric={k} x={x}");
+            }
+            assert!(m["mean_precision"].as_f64().unwrap() <= 1.0);
+        }
+        // The fixture goldens are main-content only, so the default (boilerplate-reducing) pipeline
+        // should beat whole-page html2text on precision.
+        assert!(
+            v["engines"]["auto"]["mean_precision"].as_f64().unwrap()
+                > v["engines"]["html2text"]["mean_precision"]
+                    .as_f64()
+                    .unwrap()
+        );
+        assert_eq!(
+            v["fixtures"][0]["fixture"].as_str(),
+            Some("article_with_nav")
+        );
+
+        let bad = EvalExtractSpec {
+            fixtures_dir: tmp.path().to_path_buf(),
+            engines: vec!["nope".to_string()],
+            width: 100,
+            out: tmp.path().join("x.json"),
+            now_epoch_s: None,
+        };
+        assert!(eval_extract(bad).is_err());
+    }
 }
@@ -3,5 +3,19 @@
 //! The primary entrypoint for end users is the `webpipe` binary (CLI + MCP stdio).
 //! This library module exists to support embedding and to provide a stable way to
 //! reuse core types without depending on internal crate layout.
+//!
+//! For fetch/extract/search from Rust, use the [`Webpipe`] client.
+
+pub mod client;
+
+/// Version of the MCP tool output shape, emitted as `schema_version` on every tool envelope.
+///
+/// Bump it only for breaking changes (a field removed, renamed or retyped); additive fields do
+/// not need a bump. Every bump needs an entry under "Schema versions" in `CHANGELOG.md`.
+pub const SCHEMA_VERSION: u64 = 2;
 
+pub use client::{
+    ExtractOptions, Extracted, SearchExtractItem, SearchExtractOptions, SearchExtractOutput,
+    SearchOptions, Webpipe,
+};
 pub use webpipe_core as core;
@@ -1,7 +1,7 @@
 #![recursion_limit = "256"]
 
 use anyhow::Result;
-#[cfg(feature = "vlm")]
+#[cfg(any(feature = "vlm", feature = "msgpack"))]
 use base64::Engine;
 use clap::{Parser, Subcommand};
 #[cfg(feature = "eval")]
@@ -70,12 +70,23 @@ enum Commands {
     /// Run a local search->fetch->extract evaluation harness (writes a JSON artifact).
     #[cfg(all(feature = "eval", feature = "stdio"))]
     EvalSearchExtract(EvalSearchExtractCmd),
+    /// Benchmark HTML->text engines against golden fixtures (writes a JSON artifact).
+    ///
+    /// Offline and deterministic: reads `<name>.html` + `<name>.expected.txt` pairs and reports
+    /// per-engine token precision/recall and mean extraction time.
+    #[cfg(feature = "eval")]
+    EvalExtract(EvalExtractCmd),
     /// Run a small E2E matrix over a versioned query set (writes a JSONL artifact).
     #[cfg(all(feature = "eval", feature = "stdio"))]
     EvalMatrix(EvalMatrixCmd),
     /// Score an eval-matrix JSONL artifact against an E2E qrels file (json).
     #[cfg(feature = "eval")]
     EvalMatrixScore(EvalMatrixScoreCmd),
+    /// Compare two eval-matrix-score artifacts: per-metric deltas, per-query flips, verdict (json).
+    ///
+    /// Exits non-zero when any metric regresses by more than `--tolerance` (usable as a CI gate).
+    #[cfg(feature = "eval")]
+    EvalMatrixScoreDiff(EvalMatrixScoreDiffCmd),
     /// Export eval-matrix JSONL rows into a judge-ready JSONL dataset (optionally joined with qrels).
     #[cfg(feature = "eval")]
     EvalMatrixExport(EvalMatrixExportCmd),
@@ -127,6 +138,12 @@ enum Commands {
     EvalGenerateDomainPack(EvalGenerateDomainPackCmd),
     /// Diagnose configuration/launch issues (json; no secrets).
     Doctor(DoctorCmd),
+    /// Copy cached bodies into a readable per-URL tree, e.g. `<out>/example.com/docs/page-<hash>` (json).
+    CacheExport(CacheExportCmd),
+    /// Remove fetch-cache entries for one URL, or for every URL under a prefix (json).
+    ///
+    /// Use after a site changes, instead of clearing the whole cache.
+    CachePurge(CachePurgeCmd),
     /// List tools exposed by the MCP stdio server (for auditing what Cursor sees).
     #[cfg(feature = "stdio")]
     McpListTools(McpListToolsCmd),
@@ -217,10 +234,10 @@ struct EvalSearchExtractCmd {
     /// Provider to use. Allowed: auto, brave, tavily, searxng
     #[arg(long, default_value = "auto")]
     provider: String,
-    /// When provider="auto", choose routing mode. Allowed: fallback, merge, mab
+    /// When provider="auto", choose routing mode. Allowed: fallback, merge, mab, cost_cascade
     #[arg(long, default_value = "fallback")]
     auto_mode: String,
-    /// How to select `top_chunks` across URLs. Allowed: score, pareto
+    /// How to select `top_chunks` across URLs. Allowed: score, pareto, mmr
     #[arg(long, default_value = "score")]
     selection_mode: String,
     /// Optional: run multiple selection modes and include a summary comparison.
@@ -287,6 +304,18 @@ struct EvalSearchExtractCmd {
     #[arg(long)]
     agentic_frontier_max: Option<usize>,
 
+    /// When agentic=true, maximum link-discovery depth from the seed URLs (default: unlimited).
+    #[arg(long)]
+    agentic_max_depth: Option<usize>,
+
+    /// When agentic=true, search again if no chunk from the initial URLs scores at least this.
+    #[arg(long)]
+    agentic_min_quality_score: Option<u64>,
+
+    /// When agentic=true, keep up to this many frontier candidates in flight (prefetched into cache).
+    #[arg(long)]
+    agentic_prefetch: Option<usize>,
+
     /// Max planner (LLM) calls for a single request.
     #[arg(long)]
     planner_max_calls: Option<usize>,
@@ -348,10 +377,17 @@ struct EvalMatrixCmd {
     /// Provider to use for the "search" leg. Allowed: auto, brave, tavily, searxng
     #[arg(long, default_value = "searxng")]
     provider: String,
-    /// When provider="auto", choose routing mode. Allowed: fallback, merge, mab
+    /// Comma-separated providers to compare in one pass (overrides --provider), e.g.
+    /// `brave,tavily,searxng`. Each search row is tagged with its `provider`.
+    #[arg(long)]
+    providers: Option<String>,
+    /// Max providers queried concurrently per query when --providers is set (max: 8).
+    #[arg(long, default_value_t = 2)]
+    provider_concurrency: usize,
+    /// When provider="auto", choose routing mode. Allowed: fallback, merge, mab, cost_cascade
     #[arg(long, default_value = "fallback")]
     auto_mode: String,
-    /// How to select `top_chunks` across URLs. Allowed: score, pareto
+    /// How to select `top_chunks` across URLs. Allowed: score, pareto, mmr
     #[arg(long, default_value = "score")]
     selection_mode: String,
     /// Which fetch backend to use. Allowed: local, firecrawl
@@ -400,6 +436,26 @@ struct EvalMatrixScoreCmd {
     now_epoch_s: Option<u64>,
 }
 
+#[cfg(feature = "eval")]
+#[derive(clap::Args, Debug)]
+struct EvalMatrixScoreDiffCmd {
+    /// Baseline score artifact produced by `eval-matrix-score` (json).
+    #[arg(long)]
+    baseline: std::path::PathBuf,
+    /// Candidate score artifact produced by `eval-matrix-score` (json).
+    #[arg(long)]
+    candidate: std::path::PathBuf,
+    /// Allowed per-metric drop (absolute, in rate units) before the diff counts as a regression.
+    #[arg(long, default_value_t = 0.0)]
+    tolerance: f64,
+    /// Output JSON path (default: .generated/webpipe-eval-matrix-score-diff-<epoch>.json)
+    #[arg(long)]
+    out: Option<std::path::PathBuf>,
+    /// Override "now" for deterministic outputs.
+    #[arg(long)]
+    now_epoch_s: Option<u64>,
+}
+
 #[cfg(feature = "eval")]
 #[derive(clap::Args, Debug)]
 struct EvalMatrixExportCmd {
@@ -512,10 +568,10 @@ struct EvalMatrixRunCmd {
     /// Provider to use for the "search" leg. Allowed: auto, brave, tavily, searxng
     #[arg(long, default_value = "searxng")]
     provider: String,
-    /// When provider="auto", choose routing mode. Allowed: fallback, merge, mab
+    /// When provider="auto", choose routing mode. Allowed: fallback, merge, mab, cost_cascade
     #[arg(long, default_value = "fallback")]
     auto_mode: String,
-    /// How to select `top_chunks` across URLs. Allowed: score, pareto
+    /// How to select `top_chunks` across URLs. Allowed: score, pareto, mmr
     #[arg(long, default_value = "score")]
     selection_mode: String,
     /// Which fetch backend to use. Allowed: local, firecrawl
@@ -536,6 +592,14 @@ struct EvalMatrixRunCmd {
     /// Output format: json|text
     #[arg(long = "output", alias = "format", default_value = "text")]
     output: String,
+    /// Print the resolved plan (queries, expanded URLs, artifact paths, sub-commands) as JSON and exit.
+    ///
+    /// No network calls are made and no artifacts are written (besides `--plan-out`, if set).
+    #[arg(long)]
+    dry_run: bool,
+    /// With `--dry-run`, also write the plan JSON to this path.
+    #[arg(long)]
+    plan_out: Option<std::path::PathBuf>,
 }
 
 #[cfg(feature = "eval")]
@@ -554,10 +618,10 @@ struct EvalJudgeSwarmCmd {
     /// Provider to use for the "search" leg. Allowed: auto, brave, tavily, searxng
     #[arg(long, default_value = "searxng")]
     provider: String,
-    /// When provider="auto", choose routing mode. Allowed: fallback, merge, mab
+    /// When provider="auto", choose routing mode. Allowed: fallback, merge, mab, cost_cascade
     #[arg(long, default_value = "fallback")]
     auto_mode: String,
-    /// How to select `top_chunks` across URLs. Allowed: score, pareto
+    /// How to select `top_chunks` across URLs. Allowed: score, pareto, mmr
     #[arg(long, default_value = "score")]
     selection_mode: String,
     /// Which fetch backend to use. Allowed: local, firecrawl
@@ -826,6 +890,26 @@ struct EvalFetchCmd {
     now_epoch_s: Option<u64>,
 }
 
+#[cfg(feature = "eval")]
+#[derive(clap::Args, Debug)]
+struct EvalExtractCmd {
+    /// Directory of `<name>.html` fixtures with `<name>.expected.txt` goldens.
+    #[arg(long, default_value = "crates/webpipe-mcp/fixtures/extract")]
+    fixtures_dir: std::path::PathBuf,
+    /// Engine list (comma-separated). Allowed: html2text,html_main,readability,auto
+    #[arg(long, default_value = "html2text,html_main,readability,auto")]
+    engines: String,
+    /// Width used for HTML->text extraction (default: 100).
+    #[arg(long, default_value_t = 100)]
+    width: usize,
+    /// Output JSON path (default: .generated/webpipe-eval-extract-<epoch>.json)
+    #[arg(long)]
+    out: Option<std::path::PathBuf>,
+    /// Override "now" for deterministic outputs.
+    #[arg(long)]
+    now_epoch_s: Option<u64>,
+}
+
 #[derive(clap::Args, Debug)]
 struct DoctorCmd {
     /// Output format: pretty|json|text
@@ -842,6 +926,33 @@ struct DoctorCmd {
     timeout_ms: u64,
 }
 
+#[derive(clap::Args, Debug)]
+struct CacheExportCmd {
+    /// Directory to write into (created if missing; existing files at the same paths are replaced).
+    #[arg(long)]
+    out: std::path::PathBuf,
+    /// Max cache entries to scan.
+    #[arg(long, default_value_t = 100_000)]
+    max_entries: usize,
+    /// Cache directory (default: WEBPIPE_CACHE_DIR, else the per-user webpipe cache).
+    #[arg(long)]
+    cache_dir: Option<std::path::PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group(clap::ArgGroup::new("target").required(true).args(["url", "prefix"])))]
+struct CachePurgeCmd {
+    /// Remove the entries stored for exactly this URL (whatever max_bytes/headers keyed them).
+    #[arg(long)]
+    url: Option<String>,
+    /// Remove the entries whose URL starts with this prefix, e.g. https://example.com/docs/
+    #[arg(long)]
+    prefix: Option<String>,
+    /// Cache directory (default: WEBPIPE_CACHE_DIR, else the per-user webpipe cache).
+    #[arg(long)]
+    cache_dir: Option<std::path::PathBuf>,
+}
+
 #[cfg(feature = "stdio")]
 #[derive(clap::Args, Debug)]
 struct McpListToolsCmd {
@@ -1717,10 +1828,12 @@ mod mcp {
         Error as WebpipeError, FetchBackend, FetchCachePolicy, FetchRequest, FetchSource,
         SearchProvider, SearchQuery,
     };
+    #[cfg(feature = "embed-local")]
+    use webpipe_local::embed_local::LocalEmbedder;
     use webpipe_local::LocalFetcher;
 
-    // Breaking output shape changes (legacy field removal) are tracked via schema_version.
-    const SCHEMA_VERSION: u64 = 2;
+    // Breaking output shape changes are tracked via schema_version (see `webpipe::SCHEMA_VERSION`).
+    use webpipe::SCHEMA_VERSION;
 
     fn p<T>(v: T) -> Parameters<Option<T>> {
         Parameters(Some(v))
@@ -1835,6 +1948,7 @@ mod mcp {
     #[path = "envelope.rs"]
     mod envelope;
     use envelope::*;
+    use webpipe_local::links::registrable_domain;
 
     // ---- Minimal self-contained helpers (public-repo friendly) ----
     //
@@ -2147,8 +2261,76 @@ mod mcp {
             }
         }
 
+        /// How [`select_mab`] turns an arm's windowed ok-rate into a score.
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+        #[serde(rename_all = "snake_case")]
+        pub enum MabStrategy {
+            /// Mean ok-rate plus a UCB exploration bonus (deterministic).
+            #[default]
+            Ucb,
+            /// A draw from Beta(ok + 1, fail + 1) per arm, from an RNG seeded by `MabConfig::seed`
+            /// and the total call count (reproducible for the same stats and seed).
+            Thompson,
+        }
+
+        impl MabStrategy {
+            /// `WEBPIPE_MAB_STRATEGY=ucb|thompson` (default: ucb).
+            pub fn from_env() -> Self {
+                match std::env::var("WEBPIPE_MAB_STRATEGY")
+                    .ok()
+                    .map(|v| v.trim().to_ascii_lowercase())
+                    .as_deref()
+                {
+                    Some("thompson") => Self::Thompson,
+                    _ => Self::Ucb,
+                }
+            }
+        }
+
+        /// `WEBPIPE_MAB_SEED` (default: 0).
+        pub fn mab_seed_from_env() -> u64 {
+            std::env::var("WEBPIPE_MAB_SEED")
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .unwrap_or(0)
+        }
+
+        /// splitmix64: tiny seeded RNG (no external deps).
+        struct SplitMix64(u64);
+
+        impl SplitMix64 {
+            fn next_u64(&mut self) -> u64 {
+                self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                let mut z = self.0;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^ (z >> 31)
+            }
+
+            /// Uniform in (0, 1].
+            fn next_unit(&mut self) -> f64 {
+                ((self.next_u64() >> 11) as f64 + 1.0) / ((1u64 << 53) as f64)
+            }
+
+            /// Gamma(k, 1) for integer k >= 1, as a sum of k unit exponentials. Arms are
+            /// scored over a bounded stats window, so k stays small.
+            fn gamma_int(&mut self, k: u64) -> f64 {
+                (0..k.max(1)).map(|_| -self.next_unit().ln()).sum()
+            }
+
+            /// Beta(a, b) for integer a, b >= 1.
+            fn beta_int(&mut self, a: u64, b: u64) -> f64 {
+                let x = self.gamma_int(a);
+                let y = self.gamma_int(b);
+                x / (x + y)
+            }
+        }
+
         #[derive(Debug, Clone)]
         pub struct MabConfig {
+            pub strategy: MabStrategy,
+            /// Thompson sampling seed (ignored by UCB).
+            pub seed: u64,
             pub exploration_c: f64,
             pub cost_weight: f64,
             pub latency_weight: f64,
@@ -2163,6 +2345,8 @@ mod mcp {
         impl Default for MabConfig {
             fn default() -> Self {
                 Self {
+                    strategy: MabStrategy::Ucb,
+                    seed: 0,
                     exploration_c: 0.7,
                     cost_weight: 0.0,
                     latency_weight: 0.0,
@@ -2192,6 +2376,7 @@ mod mcp {
 
         #[derive(Debug, Clone, Serialize)]
         pub struct MabSelection {
+            pub strategy: MabStrategy,
             pub chosen: String,
             pub frontier: Vec<String>,
             pub candidates: Vec<CandidateRow>,
@@ -2204,6 +2389,7 @@ mod mcp {
         ) -> MabSelection {
             let total_calls: u64 = summaries.values().map(|s| s.calls).sum();
             let ln_total = ((total_calls.max(1)) as f64).ln();
+            let mut rng = SplitMix64(cfg.seed ^ total_calls.wrapping_mul(0x2545_f491_4f6c_dd1d));
 
             let mut rows: Vec<CandidateRow> = Vec::new();
             for name in order {
@@ -2229,19 +2415,27 @@ mod mcp {
                     filtered |= mean_cost_units > max;
                 }
 
-                // Deterministic UCB-ish objective (maximize).
-                // We use ok_rate as the primary reward and subtract weighted costs/risks.
-                let base = ok_rate
-                    - cfg.cost_weight * mean_cost_units
-                    - cfg.latency_weight * (mean_latency_ms / 1000.0)
-                    - cfg.junk_weight * junk_rate
-                    - cfg.hard_junk_weight * hard_junk_rate;
-                let explore = if s.calls == 0 {
-                    cfg.exploration_c
-                } else {
-                    cfg.exploration_c * ((ln_total / (s.calls as f64)).sqrt())
+                // Objective (maximize): a reward estimate minus weighted costs/risks.
+                // UCB uses the mean ok_rate plus an exploration bonus; Thompson uses a posterior
+                // draw, whose spread does the exploring.
+                let penalty = cfg.cost_weight * mean_cost_units
+                    + cfg.latency_weight * (mean_latency_ms / 1000.0)
+                    + cfg.junk_weight * junk_rate
+                    + cfg.hard_junk_weight * hard_junk_rate;
+                let score = match cfg.strategy {
+                    MabStrategy::Ucb => {
+                        let explore = if s.calls == 0 {
+                            cfg.exploration_c
+                        } else {
+                            cfg.exploration_c * ((ln_total / (s.calls as f64)).sqrt())
+                        };
+                        ok_rate - penalty + explore
+                    }
+                    MabStrategy::Thompson => {
+                        let fails = s.calls.saturating_sub(s.ok);
+                        rng.beta_int(s.ok + 1, fails + 1) - penalty
+                    }
                 };
-                let score = base + explore;
 
                 rows.push(CandidateRow {
                     name: name.clone(),
@@ -2327,6 +2521,7 @@ mod mcp {
             let frontier_names = frontier.into_iter().take(3).map(|r| r.name).collect();
 
             MabSelection {
+                strategy: cfg.strategy,
                 chosen,
                 frontier: frontier_names,
                 candidates: rows,
@@ -2338,6 +2533,19 @@ mod mcp {
         std::env::var("WEBPIPE_CACHE_DIR").ok().map(PathBuf::from)
     }
 
+    /// URI scheme for cached documents in `resources/list` (`webpipe-cache://<cache key>`).
+    const CACHE_RESOURCE_SCHEME: &str = "webpipe-cache://";
+
+    /// How many cached documents `resources/list` exposes, newest first
+    /// (`WEBPIPE_MCP_CACHE_RESOURCES_MAX`, default 50, max 1000; 0 lists none).
+    fn cache_resources_max_from_env() -> usize {
+        std::env::var("WEBPIPE_MCP_CACHE_RESOURCES_MAX")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .unwrap_or(50)
+            .min(1_000)
+    }
+
     #[derive(Clone, Copy, Debug, Eq, PartialEq)]
     enum PrivacyMode {
         Normal,
@@ -2521,6 +2729,131 @@ mod mcp {
         u.contains("dl.acm.org/")
     }
 
+    /// Why one `web_search_extract` result contributed no evidence (`None` = it did).
+    fn search_extract_failure_class(r: &serde_json::Value) -> Option<&'static str> {
+        let has = |code: &str| {
+            ["warnings", "warning_codes"].iter().any(|k| {
+                r[*k]
+                    .as_array()
+                    .is_some_and(|a| a.iter().any(|w| w.as_str() == Some(code)))
+            })
+        };
+        let ok = r["ok"].as_bool() == Some(true);
+        let status = r["status"].as_u64().unwrap_or(0);
+        let chunks = r["extract"]["chunks"].as_array().map_or(0, |a| a.len());
+        let msg = r["error"]["message"]
+            .as_str()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        if has("blocked_by_js_challenge") {
+            Some("js_challenge")
+        } else if ok && status < 400 && chunks > 0 {
+            None
+        } else if msg.contains("timed out")
+            || msg.contains("timeout")
+            || has("extract_pipeline_timeout")
+        {
+            Some("timeout")
+        } else if (400..500).contains(&status) {
+            Some("http_4xx")
+        } else if status >= 500 {
+            Some("http_5xx")
+        } else if has("empty_extraction") || (ok && r["extract"]["text_chars"].as_u64() == Some(0))
+        {
+            Some("empty_extraction")
+        } else if has("no_network_may_require_warm_cache") {
+            Some("cache_miss")
+        } else if ok {
+            Some("no_chunks")
+        } else {
+            Some("other")
+        }
+    }
+
+    /// Remediation for a `diagnostics.dominant_failure` class.
+    fn search_extract_failure_hint(class: &str) -> &'static str {
+        match class {
+            "no_urls" => "Nothing was fetched: broaden the query, try another provider, or pass urls=[...] directly.",
+            "timeout" => "Fetches timed out: raise timeout_ms (and deadline_ms), lower max_parallel_urls, or retry later.",
+            "js_challenge" => "Pages are behind JS/CAPTCHA walls: use fetch_backend=\"render\" or render_fallback_on_low_signal=true, or fetch_backend=\"firecrawl\" if configured.",
+            "empty_extraction" => "Pages yielded no text (JS app shells or image-only docs): enable firecrawl_fallback_on_empty_extraction or render_fallback_on_empty_extraction.",
+            "http_4xx" => "Servers refused the requests (4xx): check the URLs for typos or auth walls, or pick other sources.",
+            "http_5xx" => "Servers errored (5xx): retry later or pick other sources.",
+            "cache_miss" => "no_network=true but the cache is cold: run once with no_network=false to warm it.",
+            "no_chunks" => "Pages were fetched but produced no chunks: rephrase the query or raise max_chars.",
+            _ => "URLs failed for mixed reasons: inspect results[].error and results[].warnings.",
+        }
+    }
+
+    /// Top-level `diagnostics` for dead-end runs: zero selected chunks, or every URL failed.
+    ///
+    /// Names the dominant failure class across results and a concrete next step.
+    fn search_extract_diagnostics(
+        per_url: &[serde_json::Value],
+        top_chunks_selected: usize,
+    ) -> Option<serde_json::Value> {
+        let mut counts = std::collections::BTreeMap::<&'static str, usize>::new();
+        for r in per_url {
+            if let Some(c) = search_extract_failure_class(r) {
+                *counts.entry(c).or_insert(0) += 1;
+            }
+        }
+        let failed: usize = counts.values().sum();
+        let all_failed = failed == per_url.len();
+        if top_chunks_selected > 0 && !all_failed {
+            return None;
+        }
+        let reason = if per_url.is_empty() {
+            "no_urls"
+        } else if all_failed {
+            "all_urls_failed"
+        } else {
+            "no_chunks_selected"
+        };
+        // Highest count wins; ties break alphabetically (BTreeMap order) for determinism.
+        let dominant = counts
+            .iter()
+            .fold(None::<(&str, usize)>, |best, (&c, &n)| match best {
+                Some((_, bn)) if bn >= n => best,
+                _ => Some((c, n)),
+            })
+            .map(|(c, _)| c)
+            .unwrap_or("no_urls");
+        Some(serde_json::json!({
+            "reason": reason,
+            "dominant_failure": dominant,
+            "unanimous": failed > 0 && counts.len() == 1 && all_failed,
+            "failure_counts": counts,
+            "url_count": per_url.len(),
+            "hint": search_extract_failure_hint(dominant),
+        }))
+    }
+
+    /// Caller-supplied `Referer`: an absolute http(s) URL, fragment dropped (browsers never send it).
+    fn parse_referer(raw: &str) -> Option<String> {
+        let mut u = reqwest::Url::parse(raw.trim()).ok()?;
+        if !matches!(u.scheme(), "http" | "https") {
+            return None;
+        }
+        u.set_fragment(None);
+        Some(u.to_string())
+    }
+
+    /// Set `Referer`, replacing any differently-cased copy so the cache key stays deterministic.
+    fn set_referer_header(headers: &mut BTreeMap<String, String>, referer: String) {
+        headers.retain(|k, _| !k.trim().eq_ignore_ascii_case("referer"));
+        headers.insert("Referer".to_string(), referer);
+    }
+
+    /// Request headers for a fetch that only carries an (optional) `Referer`.
+    fn referer_headers(referer: Option<&str>) -> BTreeMap<String, String> {
+        let mut headers = BTreeMap::new();
+        if let Some(r) = referer {
+            set_referer_header(&mut headers, r.to_string());
+        }
+        headers
+    }
+
     fn url_is_localhost(url: &str) -> bool {
         // Used to interpret `no_network=true` as “no non-localhost networking”.
         //
@@ -2542,6 +2875,34 @@ mod mcp {
         false
     }
 
+    /// Whether `url` points at localhost or a private/link-local address literal.
+    fn url_is_internal_network(url: &str) -> bool {
+        if url_is_localhost(url) {
+            return true;
+        }
+        let Ok(u) = reqwest::Url::parse(url.trim()) else {
+            return false;
+        };
+        let host = u.host_str().unwrap_or("");
+        match host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<std::net::IpAddr>()
+        {
+            Ok(std::net::IpAddr::V4(ip)) => {
+                ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+            }
+            Ok(std::net::IpAddr::V6(ip)) => {
+                let seg0 = ip.segments()[0];
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    || (seg0 & 0xfe00) == 0xfc00
+                    || (seg0 & 0xffc0) == 0xfe80
+            }
+            Err(_) => false,
+        }
+    }
+
     fn normalize_domain_rule(raw: &str) -> Option<String> {
         // Accept either a bare hostname ("example.com") or a full URL ("https://example.com/x").
         // Normalize to lowercase host only.
@@ -2610,6 +2971,118 @@ mod mcp {
         true
     }
 
+    /// Attach `sentences[]` to each chunk in `extract.chunks` (offsets into the extracted text).
+    fn add_chunk_sentences(extract: &mut serde_json::Value) {
+        const MAX_SENTENCES_PER_CHUNK: usize = 50;
+        let Some(chunks) = extract.get_mut("chunks").and_then(|v| v.as_array_mut()) else {
+            return;
+        };
+        for c in chunks.iter_mut() {
+            let base = c.get("start_char").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+            let text = c.get("text").and_then(|v| v.as_str()).unwrap_or("");
+            let sents: Vec<serde_json::Value> =
+                webpipe_local::extract::split_sentences(text, MAX_SENTENCES_PER_CHUNK)
+                    .into_iter()
+                    .map(|s| {
+                        serde_json::json!({
+                            "text": s.text,
+                            "start_char": base + s.start_char,
+                            "end_char": base + s.end_char
+                        })
+                    })
+                    .collect();
+            c["sentences"] = serde_json::json!(sents);
+        }
+    }
+
+    /// Fold provider warnings (e.g. `date_filter_unsupported`) into a `web_search` payload's
+    /// `warnings`/`warning_codes`/`warning_hints`, keeping any already there.
+    fn merge_search_warnings(payload: &mut serde_json::Value, warnings: &[String]) {
+        if warnings.is_empty() {
+            return;
+        }
+        let mut ws: Vec<String> = payload["warnings"]
+            .as_array()
+            .map(|a| {
+                a.iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        for w in warnings {
+            if !ws.contains(w) {
+                ws.push(w.clone());
+            }
+        }
+        let codes: Vec<&str> = ws.iter().map(|w| normalize_warning_code(w)).collect();
+        payload["warning_codes"] = serde_json::json!(codes);
+        payload["warning_hints"] = warning_hints_from(&codes);
+        payload["warnings"] = serde_json::json!(ws);
+    }
+
+    /// `metadata` for web_fetch/web_extract: link-preview fields and JSON-LD, with `image` and
+    /// `canonical_url` resolved against the page's final URL.
+    fn page_metadata_json(html: &str, final_url: &str) -> serde_json::Value {
+        let mut m = webpipe_local::extract::extract_metadata(html);
+        if let Ok(base) = reqwest::Url::parse(final_url) {
+            for u in [&mut m.image, &mut m.canonical_url].into_iter().flatten() {
+                if let Ok(abs) = base.join(u.as_str()) {
+                    *u = abs.to_string();
+                }
+            }
+        }
+        serde_json::json!(m)
+    }
+
+    /// `extract.tables[]` for web_extract: each table's JSON, plus a `markdown`/`csv` rendering
+    /// when `format` asks for one.
+    fn tables_json(tables: &[webpipe_local::extract::Table], format: &str) -> serde_json::Value {
+        let rows: Vec<serde_json::Value> = tables
+            .iter()
+            .map(|t| {
+                let mut v = serde_json::json!(t);
+                match format {
+                    "markdown" => v["markdown"] = serde_json::json!(t.to_markdown()),
+                    "csv" => v["csv"] = serde_json::json!(t.to_csv()),
+                    _ => {}
+                }
+                v
+            })
+            .collect();
+        serde_json::Value::Array(rows)
+    }
+
+    /// Where a `web_search` payload's `results[]` start, and whether a next page likely exists.
+    fn set_search_page(payload: &mut serde_json::Value, offset: usize, has_more: bool) {
+        payload["offset"] = serde_json::json!(offset);
+        payload["has_more"] = serde_json::json!(has_more);
+    }
+
+    /// Cap `payload.results[]` to `max_per_domain` per registrable domain, preserving rank order
+    /// (the highest-ranked results from each domain are kept). Returns how many were dropped.
+    fn diversify_search_results(payload: &mut serde_json::Value, max_per_domain: usize) -> usize {
+        let Some(results) = payload.get_mut("results").and_then(|v| v.as_array_mut()) else {
+            return 0;
+        };
+        let mut per_domain: std::collections::HashMap<String, usize> =
+            std::collections::HashMap::new();
+        let before = results.len();
+        results.retain(|r| {
+            let url = r.get("url").and_then(|v| v.as_str()).unwrap_or("");
+            let Some(host) = reqwest::Url::parse(url)
+                .ok()
+                .and_then(|u| u.host_str().map(|h| h.to_string()))
+            else {
+                // Unparseable URLs don't belong to any domain bucket; keep them.
+                return true;
+            };
+            let n = per_domain.entry(registrable_domain(&host)).or_insert(0);
+            *n += 1;
+            *n <= max_per_domain
+        });
+        before - results.len()
+    }
+
     fn url_looks_like_promo_or_tracking(url: &str) -> bool {
         // Heuristics for "marketing/promo" URLs that frequently show up in search results and
         // waste extraction budget (e.g. banners, home pages with UTM tags).
@@ -2914,6 +3387,16 @@ mod mcp {
         false
     }
 
+    /// Warning for a truncated response: `streaming_capped` when the fetcher cut an open-ended
+    /// streaming (SSE, ndjson, ...) body short, else `body_truncated_by_max_bytes`.
+    fn truncation_warning(timings_ms: &BTreeMap<String, u128>) -> &'static str {
+        if timings_ms.contains_key("streaming_capped") {
+            "streaming_capped"
+        } else {
+            "body_truncated_by_max_bytes"
+        }
+    }
+
     fn warning_codes_from(warnings: &[&'static str]) -> Vec<&'static str> {
         warnings.iter().map(|w| normalize_warning_code(w)).collect()
     }
@@ -3047,12 +3530,47 @@ mod mcp {
         default_hint.to_string()
     }
 
+    /// `WEBPIPE_ENVELOPE_FORMAT=msgpack`: the payload travels as a MessagePack blob resource in
+    /// `content[1]` instead of `structured_content`, with the JSON text kept in `content[0]`
+    /// for clients that only read text. `None` means "use the JSON envelope".
+    #[cfg(feature = "msgpack")]
+    fn msgpack_tool_result(payload: &serde_json::Value) -> Option<CallToolResult> {
+        if EnvelopeFormat::from_env() != EnvelopeFormat::Msgpack {
+            return None;
+        }
+        // An unencodable payload (never expected for JSON values) falls back to JSON.
+        let bytes = payload_to_msgpack(payload).ok()?;
+        let content = vec![
+            Content::text(payload.to_string()),
+            Content::resource(ResourceContents::BlobResourceContents {
+                uri: MSGPACK_PAYLOAD_URI.to_string(),
+                mime_type: Some(MSGPACK_MIME_TYPE.to_string()),
+                blob: base64::engine::general_purpose::STANDARD.encode(bytes),
+                meta: None,
+            }),
+        ];
+        let ok = payload.get("ok").and_then(|v| v.as_bool()).unwrap_or(true);
+        Some(if ok {
+            CallToolResult::success(content)
+        } else {
+            CallToolResult::error(content)
+        })
+    }
+
+    #[cfg(not(feature = "msgpack"))]
+    fn msgpack_tool_result(_payload: &serde_json::Value) -> Option<CallToolResult> {
+        None
+    }
+
     fn tool_result(payload: serde_json::Value) -> CallToolResult {
         // Always attach structured content for machine consumers, and include a text fallback
         // for older clients/tests that only read `content[0].text`.
         //
         // Also set MCP-level `isError` when our envelope says ok=false, so clients that
         // rely on MCP error signaling behave correctly.
+        if let Some(r) = msgpack_tool_result(&payload) {
+            return r;
+        }
         let ok = payload.get("ok").and_then(|v| v.as_bool()).unwrap_or(true);
         let mut r = if ok {
             CallToolResult::structured(payload.clone())
@@ -3073,6 +3591,11 @@ mod mcp {
         // Optional debug knob: include the full JSON payload as a second text item when
         // WEBPIPE_MCP_INCLUDE_JSON_TEXT=true (useful for copy/paste without digging into
         // structured_content tooling).
+        //
+        // The MessagePack envelope is for embedders, not UIs: it carries no Markdown view.
+        if let Some(r) = msgpack_tool_result(&payload) {
+            return r;
+        }
         let ok = payload.get("ok").and_then(|v| v.as_bool()).unwrap_or(true);
         let mut r = if ok {
             CallToolResult::structured(payload.clone())
@@ -3261,6 +3784,24 @@ mod mcp {
             md.push('\n');
         }
 
+        if let Some(rs) = payload.get("rounds").and_then(|v| v.as_array()) {
+            if !rs.is_empty() {
+                md.push_str("## Rounds\n\n");
+                for r in rs {
+                    let pass = r.get("pass").and_then(|v| v.as_u64()).unwrap_or(0);
+                    let q = r.get("query").and_then(|v| v.as_str()).unwrap_or("");
+                    let by = r.get("refined_by").and_then(|v| v.as_str()).unwrap_or("");
+                    let n_urls = r
+                        .get("urls")
+                        .and_then(|v| v.as_array())
+                        .map(|a| a.len())
+                        .unwrap_or(0);
+                    md.push_str(&format!("- pass {pass} (`{by}`): {q} — {n_urls} url(s)\n"));
+                }
+                md.push('\n');
+            }
+        }
+
         md.push_str("## Answer\n\n");
         if answer.is_empty() {
             md.push_str("_No answer text was produced._\n");
@@ -3272,6 +3813,24 @@ mod mcp {
             md.push_str("\n_Note: answer was truncated to stay bounded._\n");
         }
 
+        if let Some(fs) = payload.get("followups").and_then(|v| v.as_array()) {
+            if !fs.is_empty() {
+                md.push_str("\n## Follow-up questions\n\n");
+                for f in fs {
+                    let q = f.get("question").and_then(|v| v.as_str()).unwrap_or("");
+                    let r = f.get("rationale").and_then(|v| v.as_str()).unwrap_or("");
+                    md.push_str("- ");
+                    md.push_str(q);
+                    if !r.is_empty() {
+                        md.push_str(" — _");
+                        md.push_str(r);
+                        md.push('_');
+                    }
+                    md.push('\n');
+                }
+            }
+        }
+
         if let Some(ws) = payload.get("warnings").and_then(|v| v.as_array()) {
             if !ws.is_empty() {
                 md.push_str("\n## Warnings\n\n");
@@ -3691,6 +4250,20 @@ mod mcp {
             }
         }
 
+        if let Some(d) = payload.get("diagnostics").and_then(|v| v.as_object()) {
+            md.push_str("## Diagnostics\n\n");
+            for k in ["reason", "dominant_failure", "hint"] {
+                if let Some(v) = d.get(k).and_then(|v| v.as_str()) {
+                    md.push_str("- **");
+                    md.push_str(k);
+                    md.push_str("**: ");
+                    md.push_str(v);
+                    md.push('\n');
+                }
+            }
+            md.push('\n');
+        }
+
         // Warnings summary (bounded).
         if let Some(ws) = payload.get("warnings").and_then(|v| v.as_array()) {
             if !ws.is_empty() {
@@ -4388,6 +4961,16 @@ mod mcp {
         if truncated {
             md.push_str("- **truncated**: true\n");
         }
+        if let Some(f) = payload.get("filename").and_then(|v| v.as_str()) {
+            md.push_str("- **filename**: `");
+            md.push_str(f);
+            md.push_str("`\n");
+        }
+        if let Some(c) = payload.get("changed").and_then(|v| v.as_bool()) {
+            md.push_str("- **changed**: ");
+            md.push_str(if c { "true" } else { "false" });
+            md.push('\n');
+        }
         if let Some(ms) = payload.get("elapsed_ms").and_then(|v| v.as_u64()) {
             md.push_str("- **elapsed_ms**: ");
             md.push_str(&ms.to_string());
@@ -5069,6 +5652,65 @@ mod mcp {
             md.push('\n');
         }
 
+        if let Some(rows) = payload
+            .pointer("/usage/search_window/by_query_key")
+            .and_then(|v| v.as_array())
+        {
+            md.push_str("## Query keys\n\n");
+            if rows.is_empty() {
+                md.push_str("_No per-query-key windows (set WEBPIPE_ROUTING_CONTEXT=query_key or both)._\n\n");
+            }
+            for r in rows {
+                md.push_str("- `");
+                md.push_str(r["query_key"].as_str().unwrap_or(""));
+                md.push_str("`:");
+                if let Some(m) = r["summaries"].as_object() {
+                    for (name, sum) in m {
+                        md.push_str(&format!(
+                            " {name} {}/{} ok",
+                            sum["ok"].as_u64().unwrap_or(0),
+                            sum["calls"].as_u64().unwrap_or(0)
+                        ));
+                    }
+                }
+                md.push('\n');
+            }
+            if !rows.is_empty() {
+                md.push('\n');
+            }
+        }
+
+        let mut latency_rows: Vec<String> = Vec::new();
+        for group in ["search_providers", "fetch_backends", "llm_backends"] {
+            let Some(m) = payload
+                .pointer(&format!("/usage/{group}"))
+                .and_then(|v| v.as_object())
+            else {
+                continue;
+            };
+            for (name, u) in m {
+                let l = &u["latency"];
+                if l["count"].as_u64().unwrap_or(0) == 0 {
+                    continue;
+                }
+                let ms = |k: &str| l[k].as_u64().map(|v| v.to_string()).unwrap_or_default();
+                latency_rows.push(format!(
+                    "- `{name}` ({group}): p50 {} / p95 {} / p99 {} / max {} ms\n",
+                    ms("p50"),
+                    ms("p95"),
+                    ms("p99"),
+                    ms("max")
+                ));
+            }
+        }
+        if !latency_rows.is_empty() {
+            md.push_str("## Latency\n\n");
+            for row in latency_rows {
+                md.push_str(&row);
+            }
+            md.push('\n');
+        }
+
         md.push_str("## Warnings\n\n");
         if warning_counts.is_none_or(|m| m.is_empty()) {
             md.push_str("_No warnings recorded._\n");
@@ -5133,6 +5775,7 @@ mod mcp {
                 md.push_str("- **llm**:\n");
                 for k in [
                     "perplexity",
+                    "anthropic",
                     "ollama",
                     "openai_compat",
                     "openrouter",
@@ -5221,6 +5864,206 @@ mod mcp {
         md
     }
 
+    /// Normalize a `web_site_search` domain: lowercase host only (scheme, path, port and any
+    /// `site:` prefix stripped). None unless it looks like a dotted hostname.
+    fn site_search_domain(raw: &str) -> Option<String> {
+        let s = raw.trim();
+        let s = s.strip_prefix("site:").unwrap_or(s).trim();
+        let host = if s.contains("://") {
+            reqwest::Url::parse(s).ok()?.host_str()?.to_string()
+        } else {
+            s.split(['/', '?', '#'])
+                .next()?
+                .split(':')
+                .next()?
+                .to_string()
+        };
+        let host = host.trim_matches('.').to_ascii_lowercase();
+        let valid = host.contains('.')
+            && !host.contains("..")
+            && host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+        valid.then_some(host)
+    }
+
+    /// True when `url`'s host is `domain` or one of its subdomains.
+    fn url_on_domain(url: &str, domain: &str) -> bool {
+        reqwest::Url::parse(url)
+            .ok()
+            .and_then(|u| {
+                u.host_str()
+                    .map(|h| h.trim_end_matches('.').to_ascii_lowercase())
+            })
+            .is_some_and(|h| h == domain || h.ends_with(&format!(".{domain}")))
+    }
+
+    /// Fixed `web_summarize` prompt: numbered excerpts in, at most `max_bullets` "- " lines out.
+    fn summarize_prompt(
+        source: &str,
+        chunks: &[String],
+        max_bullets: usize,
+    ) -> webpipe_local::llm::Prompt {
+        let system = format!(
+            "You summarize web page content.\n\
+             Rules:\n\
+             - Reply with at most {max_bullets} bullet points, one per line, each starting with \"- \".\n\
+             - Use only facts stated in the excerpts; do not add outside knowledge.\n\
+             - Most important points first. No preamble, headings, or closing remarks."
+        );
+        let mut user = format!("Source: {source}\n\nExcerpts:\n");
+        for (i, c) in chunks.iter().enumerate() {
+            user.push_str(&format!("\n[{}] {}\n", i + 1, c.trim()));
+        }
+        webpipe_local::llm::Prompt { system, user }
+    }
+
+    /// Parse an LLM summary reply into at most `max_bullets` bullets whose total length is at most
+    /// `max_chars` (the last kept bullet is clipped when needed). Lines with a list marker
+    /// (`-`, `*`, `•`, `1.`) are preferred; a reply without any falls back to its non-empty lines.
+    ///
+    /// Returns (bullets, truncated).
+    fn summary_bullets(reply: &str, max_bullets: usize, max_chars: usize) -> (Vec<String>, bool) {
+        fn strip_marker(line: &str) -> Option<&str> {
+            let l = line.trim();
+            if let Some(rest) = l
+                .strip_prefix("- ")
+                .or_else(|| l.strip_prefix("* "))
+                .or_else(|| l.strip_prefix('•'))
+            {
+                return Some(rest.trim());
+            }
+            let digits = l.chars().take_while(|c| c.is_ascii_digit()).count();
+            if digits > 0 {
+                if let Some(rest) = l[digits..]
+                    .strip_prefix(". ")
+                    .or_else(|| l[digits..].strip_prefix(") "))
+                {
+                    return Some(rest.trim());
+                }
+            }
+            None
+        }
+        let mut lines: Vec<&str> = reply.lines().filter_map(strip_marker).collect();
+        if lines.is_empty() {
+            lines = reply.lines().map(str::trim).collect();
+        }
+        let lines: Vec<&str> = lines.into_iter().filter(|l| !l.is_empty()).collect();
+
+        let mut out = Vec::new();
+        let mut truncated = lines.len() > max_bullets;
+        let mut budget = max_chars;
+        for l in lines.into_iter().take(max_bullets) {
+            let (b, n, clipped) = WebpipeMcp::truncate_to_chars(l, budget);
+            if !b.is_empty() {
+                out.push(b);
+            }
+            budget -= n;
+            if clipped {
+                truncated = true;
+                break;
+            }
+        }
+        (out, truncated)
+    }
+
+    fn web_summarize_markdown(payload: &serde_json::Value) -> String {
+        let mut md = String::new();
+        if payload["ok"].as_bool() != Some(true) {
+            let err = &payload["error"];
+            md.push_str("## Error\n\n");
+            md.push_str(&format!(
+                "- **code**: `{}`\n- **message**: {}\n",
+                err["code"].as_str().unwrap_or("error"),
+                err["message"].as_str().unwrap_or("unknown")
+            ));
+            if let Some(h) = err["hint"].as_str().filter(|h| !h.is_empty()) {
+                md.push_str(&format!("\n**Hint**: {h}\n"));
+            }
+            return md;
+        }
+        md.push_str("## Summary\n\n");
+        for b in payload["summary"]["bullets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str())
+        {
+            md.push_str("- ");
+            md.push_str(b);
+            md.push('\n');
+        }
+        if let Some(u) = payload["final_url"]
+            .as_str()
+            .or_else(|| payload["url"].as_str())
+        {
+            md.push_str("\n## Source\n\n- ");
+            md.push_str(u);
+            md.push('\n');
+        }
+        md.push_str(&format!(
+            "\n---\n*backend: {} | chunks: {} | elapsed: {}ms*\n",
+            payload["provider"].as_str().unwrap_or(""),
+            payload["chunks"].as_array().map_or(0, |a| a.len()),
+            payload["elapsed_ms"].as_u64().unwrap_or(0)
+        ));
+        md
+    }
+
+    /// Derive a "find similar pages" query from a page title and body text.
+    ///
+    /// Title terms come first (site-name suffixes like " | Site" are dropped), then the most
+    /// frequent body terms not already in the title, up to 8 terms total.
+    fn related_query_from_page(
+        title: Option<&str>,
+        text: &str,
+    ) -> (String, Vec<String>, Vec<String>) {
+        const MAX_TERMS: usize = 8;
+        let title_main = title
+            .unwrap_or("")
+            .split(['|', '—', '–', '·'])
+            .flat_map(|s| s.split(" - "))
+            .map(str::trim)
+            .find(|s| !s.is_empty())
+            .unwrap_or("");
+        let title_terms = webpipe_local::textprep::top_keywords(title_main, 6);
+        let keywords: Vec<String> = webpipe_local::textprep::top_keywords(text, MAX_TERMS * 2)
+            .into_iter()
+            .filter(|k| !title_terms.contains(k))
+            .take(MAX_TERMS.saturating_sub(title_terms.len()))
+            .collect();
+        let query = title_terms
+            .iter()
+            .chain(keywords.iter())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+        (query, title_terms, keywords)
+    }
+
+    fn web_related_markdown(payload: &serde_json::Value) -> String {
+        let mut md = String::new();
+        md.push_str("## Source\n\n");
+        if let Some(u) = payload.get("url").and_then(|v| v.as_str()) {
+            md.push_str("- **url**: ");
+            md.push_str(u);
+            md.push('\n');
+        }
+        if let Some(d) = payload.get("source_domain").and_then(|v| v.as_str()) {
+            md.push_str("- **excluded_domain**: `");
+            md.push_str(d);
+            md.push_str("`\n");
+        }
+        if let Some(n) = payload.get("excluded_count").and_then(|v| v.as_u64()) {
+            md.push_str("- **excluded_count**: ");
+            md.push_str(&n.to_string());
+            md.push('\n');
+        }
+        md.push('\n');
+        md.push_str(&web_search_markdown(payload));
+        md
+    }
+
     fn web_seed_urls_markdown(payload: &serde_json::Value) -> String {
         let ok = payload.get("ok").and_then(|v| v.as_bool()).unwrap_or(true);
         let seeds = payload.get("seeds").and_then(|v| v.as_array());
@@ -5439,6 +6282,15 @@ mod mcp {
                     md.push_str(pdf);
                     md.push('\n');
                 }
+                if let Some(ft) = p.get("fulltext") {
+                    if ft["ok"].as_bool() == Some(true) {
+                        let n = ft["text_chars"].as_u64().unwrap_or(0);
+                        md.push_str(&format!("   - fulltext: {n} chars\n"));
+                    } else {
+                        let code = ft["error"]["code"].as_str().unwrap_or("unknown_error");
+                        md.push_str(&format!("   - fulltext: unavailable (`{code}`)\n"));
+                    }
+                }
             }
         }
         md.push('\n');
@@ -5541,6 +6393,12 @@ mod mcp {
                 .unwrap_or("")
                 .trim();
             let year = p.get("year").and_then(|v| v.as_u64());
+            let s = |k: &str| {
+                p.get(k)
+                    .and_then(|v| v.as_str())
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+            };
             md.push_str("## Paper\n\n");
             if !title.is_empty() {
                 md.push_str("- **title**: ");
@@ -5562,6 +6420,36 @@ mod mcp {
                 md.push_str(pdf);
                 md.push('\n');
             }
+            if let Some(v) = p.get("version").and_then(|v| v.as_u64()) {
+                md.push_str("- **version**: v");
+                md.push_str(&v.to_string());
+                if let Some(u) = s("updated") {
+                    md.push_str(" (updated ");
+                    md.push_str(u);
+                    md.push(')');
+                }
+                md.push('\n');
+            }
+            if let Some(d) = s("published") {
+                md.push_str("- **submitted**: ");
+                md.push_str(d);
+                md.push('\n');
+            }
+            if let Some(u) = s("doi_url") {
+                md.push_str("- **doi**: ");
+                md.push_str(u);
+                md.push('\n');
+            }
+            if let Some(j) = s("journal_ref") {
+                md.push_str("- **journal_ref**: ");
+                md.push_str(j);
+                md.push('\n');
+            }
+            if let Some(c) = s("comment") {
+                md.push_str("- **comment**: ");
+                md.push_str(c);
+                md.push('\n');
+            }
             md.push('\n');
         }
 
@@ -5593,6 +6481,41 @@ mod mcp {
             md.push('\n');
         }
 
+        if let Some(ds) = payload.get("discussions").and_then(|v| v.as_array()) {
+            md.push_str("## Discussions\n\n");
+            for (i, d) in ds.iter().enumerate() {
+                let url = d.get("url").and_then(|v| v.as_str()).unwrap_or("").trim();
+                let title = d.get("title").and_then(|v| v.as_str()).unwrap_or("").trim();
+                md.push_str(&format!(
+                    "{}. **{}**\n",
+                    i + 1,
+                    if title.is_empty() { url } else { title }
+                ));
+                if !title.is_empty() && !url.is_empty() {
+                    md.push_str("   - ");
+                    md.push_str(url);
+                    md.push('\n');
+                }
+                if d.get("ok").and_then(|v| v.as_bool()) == Some(true) {
+                    let snippet = d.get("snippet").and_then(|v| v.as_str()).unwrap_or("");
+                    let (snippet, _, _) = WebpipeMcp::truncate_to_chars(snippet.trim(), 300);
+                    if !snippet.is_empty() {
+                        md.push_str("   - ");
+                        md.push_str(&snippet.replace('\n', " "));
+                        md.push('\n');
+                    }
+                } else {
+                    let code = d
+                        .get("error")
+                        .and_then(|e| e.get("code"))
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("unknown_error");
+                    md.push_str(&format!("   - failed: `{code}`\n"));
+                }
+            }
+            md.push('\n');
+        }
+
         md
     }
 
@@ -5630,6 +6553,15 @@ mod mcp {
             md.push_str(&n.to_string());
             md.push('\n');
         }
+        if let Some(mode) = payload
+            .get("scoring")
+            .and_then(|s| s.get("mode"))
+            .and_then(|v| v.as_str())
+        {
+            md.push_str("- **scoring**: ");
+            md.push_str(mode);
+            md.push('\n');
+        }
         if let Some(d) = payload.get("dedup").and_then(|v| v.as_object()) {
             let dropped = d.get("dropped").and_then(|v| v.as_u64()).unwrap_or(0);
             if dropped > 0 {
@@ -5779,6 +6711,483 @@ mod mcp {
         serde_json::from_str(&s).unwrap_or_else(|_| serde_json::json!({}))
     }
 
+    /// Max follow-up questions returned by `web_deep_research(suggest_followups=true)`.
+    const DEEP_RESEARCH_MAX_FOLLOWUPS: usize = 5;
+
+    /// Max concurrent fetches per host while `agentic_prefetch` is active (the current pick counts).
+    const AGENTIC_PREFETCH_PER_HOST: usize = 2;
+
+    /// Relevance/diversity balance for `selection_mode="mmr"` when `mmr_lambda` is not given.
+    const MMR_DEFAULT_LAMBDA: f64 = 0.7;
+
+    /// Parse an LLM follow-up reply into bounded `{question, rationale}` objects.
+    ///
+    /// Accepts a bare JSON array, an object with a `followups` array, or either wrapped in prose /
+    /// code fences. Items without a non-empty `question` are dropped.
+    fn parse_deep_research_followups(raw: &str) -> Vec<serde_json::Value> {
+        let s = raw.trim();
+        let parsed: Option<serde_json::Value> = serde_json::from_str(s).ok().or_else(|| {
+            let a = s.find('[')?;
+            let b = s.rfind(']')?;
+            if b <= a {
+                return None;
+            }
+            serde_json::from_str(&s[a..=b]).ok()
+        });
+        let items = match parsed {
+            Some(serde_json::Value::Array(a)) => a,
+            Some(serde_json::Value::Object(mut o)) => match o.remove("followups") {
+                Some(serde_json::Value::Array(a)) => a,
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+        let clip = |v: Option<&serde_json::Value>, n: usize| -> String {
+            v.and_then(|x| x.as_str())
+                .unwrap_or("")
+                .trim()
+                .chars()
+                .take(n)
+                .collect()
+        };
+        let mut out = Vec::new();
+        for it in items {
+            let question = clip(it.get("question"), 300);
+            if question.is_empty() {
+                continue;
+            }
+            let rationale = clip(it.get("rationale").or_else(|| it.get("gap")), 500);
+            out.push(serde_json::json!({ "question": question, "rationale": rationale }));
+            if out.len() >= DEEP_RESEARCH_MAX_FOLLOWUPS {
+                break;
+            }
+        }
+        out
+    }
+
+    /// Max sections kept in a `web_deep_research(structured_output=true)` report.
+    const DEEP_RESEARCH_MAX_REPORT_SECTIONS: usize = 12;
+
+    /// Output contract shared by the structured-report synthesis and repair prompts.
+    const DEEP_RESEARCH_REPORT_SCHEMA: &str = "The object must be shaped like {\"summary\": string, \"sections\": [{\"heading\": string, \"content\": string, \"citations\": [number]}], \"sources\": [{\"n\": number, \"url\": string, \"title\": string}]}. Cite evidence only by the `n` numbers in the provided `sources` list.";
+
+    /// Numbered sources for a structured report: evidence URLs in `top_chunks` order, then any
+    /// remaining `results` URLs. `title` is null when the evidence pack has none.
+    fn deep_research_report_sources(evidence_pack: &serde_json::Value) -> Vec<serde_json::Value> {
+        let mut urls: Vec<(String, serde_json::Value)> = Vec::new();
+        let chunks = evidence_pack
+            .get("top_chunks")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten();
+        let results = evidence_pack
+            .get("results")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten();
+        for it in chunks.chain(results) {
+            let Some(u) = it.get("url").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let title = it.get("title").cloned().unwrap_or(serde_json::Value::Null);
+            match urls.iter_mut().find(|(x, _)| x == u) {
+                Some((_, t)) if t.is_null() => *t = title,
+                Some(_) => {}
+                None => urls.push((u.to_string(), title)),
+            }
+        }
+        urls.into_iter()
+            .enumerate()
+            .map(|(i, (url, title))| serde_json::json!({ "n": i + 1, "url": url, "title": title }))
+            .collect()
+    }
+
+    /// Validate an LLM structured-report reply against the numbered `sources`.
+    ///
+    /// Accepts the object bare or wrapped in prose / code fences. Requires a non-empty `summary`
+    /// and at least one section with a `heading` and `content`; every citation must be the `n` of a
+    /// listed source. The returned report always carries the server's `sources` list. `Err` is a
+    /// short reason suitable for a repair prompt.
+    fn validate_deep_research_report(
+        raw: &str,
+        sources: &[serde_json::Value],
+    ) -> Result<serde_json::Value, String> {
+        let s = raw.trim();
+        let parsed: Option<serde_json::Value> = serde_json::from_str(s).ok().or_else(|| {
+            let a = s.find('{')?;
+            let b = s.rfind('}')?;
+            if b <= a {
+                return None;
+            }
+            serde_json::from_str(&s[a..=b]).ok()
+        });
+        let Some(v) = parsed.filter(|v| v.is_object()) else {
+            return Err("reply is not a JSON object".to_string());
+        };
+        let clip = |v: Option<&serde_json::Value>, n: usize| -> String {
+            v.and_then(|x| x.as_str())
+                .unwrap_or("")
+                .trim()
+                .chars()
+                .take(n)
+                .collect()
+        };
+        let summary = clip(v.get("summary"), 4_000);
+        if summary.is_empty() {
+            return Err("missing non-empty string field `summary`".to_string());
+        }
+        let known: std::collections::BTreeSet<u64> = sources
+            .iter()
+            .filter_map(|s| s.get("n").and_then(|n| n.as_u64()))
+            .collect();
+        let Some(raw_sections) = v.get("sections").and_then(|x| x.as_array()) else {
+            return Err("missing array field `sections`".to_string());
+        };
+        let mut sections = Vec::new();
+        for (i, sec) in raw_sections
+            .iter()
+            .take(DEEP_RESEARCH_MAX_REPORT_SECTIONS)
+            .enumerate()
+        {
+            let heading = clip(sec.get("heading"), 200);
+            let content = clip(sec.get("content"), 8_000);
+            if heading.is_empty() || content.is_empty() {
+                return Err(format!(
+                    "sections[{i}] needs non-empty `heading` and `content` strings"
+                ));
+            }
+            let mut citations: Vec<u64> = Vec::new();
+            for c in sec
+                .get("citations")
+                .and_then(|x| x.as_array())
+                .into_iter()
+                .flatten()
+            {
+                match c.as_u64() {
+                    Some(n) if known.contains(&n) => {
+                        if !citations.contains(&n) {
+                            citations.push(n);
+                        }
+                    }
+                    _ => {
+                        return Err(format!(
+                            "sections[{i}] cites {c}, which is not a listed source number"
+                        ))
+                    }
+                }
+            }
+            sections.push(serde_json::json!({
+                "heading": heading,
+                "content": content,
+                "citations": citations,
+            }));
+        }
+        if sections.is_empty() {
+            return Err("`sections` must not be empty".to_string());
+        }
+        Ok(serde_json::json!({
+            "summary": summary,
+            "sections": sections,
+            "sources": sources,
+        }))
+    }
+
+    /// Structured report built without an LLM: one section per source (its top chunks, in
+    /// evidence order) and a summary of the first sentence of the top 3 chunks.
+    fn deep_research_extractive_report(
+        evidence_pack: &serde_json::Value,
+        sources: &[serde_json::Value],
+    ) -> serde_json::Value {
+        let chunks: Vec<(&str, &str)> = evidence_pack
+            .get("top_chunks")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|c| {
+                let u = c.get("url").and_then(|v| v.as_str())?;
+                let t = c.get("text").and_then(|v| v.as_str())?.trim();
+                (!t.is_empty()).then_some((u, t))
+            })
+            .collect();
+        let n_of = |u: &str| {
+            sources
+                .iter()
+                .find(|s| s.get("url").and_then(|v| v.as_str()) == Some(u))
+                .and_then(|s| s.get("n").and_then(|n| n.as_u64()))
+        };
+        let summary = chunks
+            .iter()
+            .take(3)
+            .filter_map(|(u, t)| {
+                let first = webpipe_local::extract::split_sentences(t, 1)
+                    .into_iter()
+                    .next()?;
+                Some(format!("{} [{}]", first.text, n_of(u)?))
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut sections = Vec::new();
+        for s in sources.iter().take(DEEP_RESEARCH_MAX_REPORT_SECTIONS) {
+            let url = s.get("url").and_then(|v| v.as_str()).unwrap_or("");
+            let texts: Vec<&str> = chunks
+                .iter()
+                .filter(|(u, _)| *u == url)
+                .map(|(_, t)| *t)
+                .collect();
+            if texts.is_empty() {
+                continue;
+            }
+            let heading = s
+                .get("title")
+                .and_then(|v| v.as_str())
+                .filter(|t| !t.trim().is_empty())
+                .unwrap_or(url);
+            let content: String = texts.join("\n\n").chars().take(8_000).collect();
+            sections.push(serde_json::json!({
+                "heading": heading,
+                "content": content,
+                "citations": [s.get("n").cloned().unwrap_or(serde_json::Value::Null)],
+            }));
+        }
+        serde_json::json!({
+            "summary": if summary.is_empty() { "No evidence text was extracted for this question.".to_string() } else { summary },
+            "sections": sections,
+            "sources": sources,
+        })
+    }
+
+    /// Plain-text rendering of a structured report for `answer.text`: the summary, then one
+    /// `## heading` block per section with its citations as `[n]` markers.
+    fn deep_research_report_text(report: &serde_json::Value) -> String {
+        let mut out = report
+            .get("summary")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        for sec in report
+            .get("sections")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let heading = sec.get("heading").and_then(|v| v.as_str()).unwrap_or("");
+            let content = sec.get("content").and_then(|v| v.as_str()).unwrap_or("");
+            out.push_str("\n\n## ");
+            out.push_str(heading);
+            out.push_str("\n\n");
+            out.push_str(content);
+            for n in sec
+                .get("citations")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|n| n.as_u64())
+            {
+                out.push_str(&format!(" [{n}]"));
+            }
+        }
+        out
+    }
+
+    /// LLM backend picked by `llm_backend="auto"`: Perplexity, then Anthropic (online only; needs
+    /// `WEBPIPE_ANTHROPIC_MODEL`), then OpenAI-compatible, then Ollama, else "none".
+    fn deep_research_auto_llm_backend(no_network: bool) -> &'static str {
+        if !no_network && (has_env("WEBPIPE_PERPLEXITY_API_KEY") || has_env("PERPLEXITY_API_KEY")) {
+            "perplexity"
+        } else if !no_network
+            && (has_env("WEBPIPE_ANTHROPIC_API_KEY") || has_env("ANTHROPIC_API_KEY"))
+            && has_env("WEBPIPE_ANTHROPIC_MODEL")
+        {
+            "anthropic"
+        } else if has_env("WEBPIPE_OPENAI_COMPAT_BASE_URL") {
+            "openai_compat"
+        } else if has_env("WEBPIPE_OLLAMA_ENABLE")
+            && std::env::var("WEBPIPE_OLLAMA_ENABLE")
+                .ok()
+                .is_some_and(|v| v.trim().eq_ignore_ascii_case("true"))
+        {
+            "ollama"
+        } else {
+            "none"
+        }
+    }
+
+    /// True when an LLM backend endpoint points at this machine (the `no_network=true` policy).
+    fn llm_endpoint_is_localhost(endpoint: &str) -> bool {
+        endpoint.contains("127.0.0.1")
+            || endpoint.contains("localhost")
+            || endpoint.contains("[::1]")
+    }
+
+    /// Base completion options for `web_deep_research` LLM calls.
+    ///
+    /// `model` (default "sonar-deep-research") is Perplexity's; other backends take `llm_model`.
+    fn deep_research_llm_opts(
+        backend: &str,
+        model: &str,
+        llm_model: Option<String>,
+        timeout_ms: u64,
+    ) -> webpipe_local::llm::CompletionOpts {
+        webpipe_local::llm::CompletionOpts {
+            timeout_ms,
+            model: if backend == "perplexity" {
+                Some(model.to_string())
+            } else {
+                llm_model
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Parse an LLM refined-query reply: `{"query": ...}` (optionally wrapped in prose / code
+    /// fences), else the first non-empty line. Bounded to 300 chars.
+    fn parse_deep_research_refined_query(raw: &str) -> Option<String> {
+        let s = raw.trim();
+        let parsed: Option<serde_json::Value> = serde_json::from_str(s).ok().or_else(|| {
+            let a = s.find('{')?;
+            let b = s.rfind('}')?;
+            if b <= a {
+                return None;
+            }
+            serde_json::from_str(&s[a..=b]).ok()
+        });
+        let q = match parsed {
+            Some(v) => v
+                .get("query")
+                .and_then(|x| x.as_str())
+                .unwrap_or("")
+                .to_string(),
+            None => s
+                .lines()
+                .map(|l| l.trim().trim_matches('`').trim_matches('"').trim())
+                .find(|l| !l.is_empty())
+                .unwrap_or("")
+                .to_string(),
+        };
+        let q: String = q.split_whitespace().collect::<Vec<_>>().join(" ");
+        let q: String = q.chars().take(300).collect();
+        (!q.is_empty()).then_some(q)
+    }
+
+    /// Deterministic pass-2 query: the original query plus up to 4 top keywords from the pass-1
+    /// chunks that the query does not already mention. None when nothing new turns up.
+    fn deep_research_offline_refined_query(
+        query: &str,
+        chunks: &[serde_json::Value],
+    ) -> Option<String> {
+        let text = chunks
+            .iter()
+            .filter_map(|c| c.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let have = webpipe_local::textprep::scrub(query);
+        let have: std::collections::BTreeSet<&str> = have.split_whitespace().collect();
+        let extra: Vec<String> = webpipe_local::textprep::top_keywords(&text, 16)
+            .into_iter()
+            .filter(|k| !have.contains(k.as_str()))
+            .take(4)
+            .collect();
+        (!extra.is_empty()).then(|| format!("{} {}", query.trim(), extra.join(" ")))
+    }
+
+    /// Provenance for one `web_deep_research` evidence round.
+    fn deep_research_round_summary(
+        pass: u8,
+        query: &str,
+        refined_by: &str,
+        evidence: &serde_json::Value,
+    ) -> serde_json::Value {
+        let urls: Vec<serde_json::Value> = evidence
+            .get("results")
+            .and_then(|v| v.as_array())
+            .map(|rs| rs.iter().filter_map(|r| r.get("url").cloned()).collect())
+            .unwrap_or_default();
+        let n_chunks = evidence
+            .get("top_chunks")
+            .and_then(|v| v.as_array())
+            .map(|a| a.len())
+            .unwrap_or(0);
+        serde_json::json!({
+            "pass": pass,
+            "ok": evidence.get("ok").and_then(|v| v.as_bool()).unwrap_or(false),
+            "query": query,
+            "refined_by": refined_by,
+            "urls": urls,
+            "top_chunks": n_chunks,
+        })
+    }
+
+    /// Merge a pass-2 evidence payload into pass-1 evidence, tagging every chunk with its `pass`.
+    ///
+    /// Chunks dedupe on (url, start_char) and results on url; at most `max_new_chunks` pass-2
+    /// chunks are added. Returns how many pass-2 chunks were added.
+    fn merge_deep_research_evidence(
+        evidence: &mut serde_json::Value,
+        pass2: &serde_json::Value,
+        max_new_chunks: usize,
+    ) -> usize {
+        let chunk_key = |c: &serde_json::Value| {
+            (
+                c.get("url")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+                c.get("start_char").and_then(|v| v.as_u64()).unwrap_or(0),
+            )
+        };
+        let mut chunks: Vec<serde_json::Value> = evidence
+            .get("top_chunks")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for c in chunks.iter_mut() {
+            c["pass"] = serde_json::json!(1);
+        }
+        let mut seen: std::collections::BTreeSet<(String, u64)> =
+            chunks.iter().map(chunk_key).collect();
+        let mut added = 0usize;
+        for c in pass2
+            .get("top_chunks")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            if added >= max_new_chunks {
+                break;
+            }
+            if seen.insert(chunk_key(c)) {
+                let mut c = c.clone();
+                c["pass"] = serde_json::json!(2);
+                chunks.push(c);
+                added += 1;
+            }
+        }
+        evidence["top_chunks"] = serde_json::json!(chunks);
+
+        let mut results: Vec<serde_json::Value> = evidence
+            .get("results")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let mut seen_urls: std::collections::BTreeSet<String> = results
+            .iter()
+            .filter_map(|r| r.get("url").and_then(|v| v.as_str()).map(str::to_string))
+            .collect();
+        for r in pass2
+            .get("results")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let u = r.get("url").and_then(|v| v.as_str()).unwrap_or("");
+            if seen_urls.insert(u.to_string()) {
+                results.push(r.clone());
+            }
+        }
+        evidence["results"] = serde_json::json!(results);
+        added
+    }
+
     /// Arguments for `webpipe_meta`.
     #[derive(Debug, Deserialize, JsonSchema, Default)]
     struct WebpipeMetaArgs {
@@ -5789,10 +7198,26 @@ mod mcp {
         /// - "usage_reset": reset runtime stats (side effect; idempotent).
         #[serde(default)]
         method: Option<String>,
+        /// method="usage" only: include per-query-key routing summaries (see `webpipe_usage`).
+        #[serde(default)]
+        include_query_keys: Option<bool>,
+        /// method="usage" only: max query keys to return (default: 20; max: 200).
+        #[serde(default)]
+        max_query_keys: Option<usize>,
     }
 
     #[derive(Debug, Deserialize, JsonSchema, Default)]
-    struct WebpipeUsageArgs {}
+    struct WebpipeUsageArgs {
+        /// If true, include per-query-key provider window summaries (default: false).
+        ///
+        /// Only populated when `WEBPIPE_ROUTING_CONTEXT` is `query_key` or `both`. Keys are the
+        /// scrubbed query keys routing uses; most recently seen first.
+        #[serde(default)]
+        include_query_keys: Option<bool>,
+        /// Max query keys to return when `include_query_keys=true` (default: 20; max: 200).
+        #[serde(default)]
+        max_query_keys: Option<usize>,
+    }
 
     #[derive(Debug, Deserialize, JsonSchema, Default)]
     struct WebSeedUrlsArgs {
@@ -5902,6 +7327,11 @@ mod mcp {
         /// Optional extra request headers (some unsafe headers are dropped by default).
         #[serde(default)]
         headers: Option<BTreeMap<String, String>>,
+        /// Optional `Referer` (absolute http(s) URL) for referer-gated sites.
+        ///
+        /// Overrides any `Referer` in `headers`. Part of the cache key.
+        #[serde(default)]
+        referer: Option<String>,
         /// Allow cache reads (default: true).
         #[serde(default)]
         cache_read: Option<bool>,
@@ -5918,6 +7348,25 @@ mod mcp {
         /// Include response headers in output (default: false).
         #[serde(default)]
         include_headers: Option<bool>,
+        /// Expected content type, matched as a case-insensitive prefix (e.g. "application/json").
+        /// If neither the declared nor the sniffed type matches, returns `ok=false` with
+        /// `error.code="content_type_mismatch"` and the actual type, without returning the body.
+        #[serde(default)]
+        expect_content_type: Option<String>,
+        /// Keep `body_text` when the final status is not 2xx (default: false).
+        ///
+        /// Non-2xx responses always return `ok=false` with `error.code="http_status"`.
+        #[serde(default)]
+        include_error_body: Option<bool>,
+        /// `body_sha256` from an earlier call. When the body still hashes to it, the response
+        /// has `changed=false` and no `body_text`; otherwise `changed=true` and the usual output.
+        #[serde(default)]
+        known_sha256: Option<String>,
+        /// Include page metadata (default: false): `metadata = {title, description, site_name,
+        /// image, author, published_time, canonical_url, json_ld[]}` from OpenGraph, Twitter
+        /// cards, `<meta>`/`<link>` tags and JSON-LD. Absent fields are omitted. HTML only.
+        #[serde(default)]
+        include_metadata: Option<bool>,
     }
 
     /// Arguments for `web_extract`.
@@ -5941,12 +7390,25 @@ mod mcp {
         /// For firecrawl fetch_backend, this always errors (firecrawl is network-only).
         #[serde(default)]
         no_network: Option<bool>,
+        /// Optional `Referer` (absolute http(s) URL) for referer-gated sites; part of the cache key.
+        #[serde(default)]
+        referer: Option<String>,
         /// Width for text wrapping (default: 100).
         #[serde(default)]
         width: Option<usize>,
         /// Max chars in output text (default: 20_000).
         #[serde(default)]
         max_chars: Option<usize>,
+        /// Which part of a long text survives `max_chars`: `head` (default), `head_tail` (first 70% +
+        /// last 30%), or `middle_out` (both ends, equal halves). Dropped text is replaced by a
+        /// `[... truncated N chars ...]` marker.
+        #[serde(default)]
+        truncation_strategy: Option<String>,
+        /// HTML extractor: `auto` (default: readability for article-like pages, else the best of
+        /// readability/html_main/html2text), `readability`, `html_main`, or `html2text`.
+        /// Ignored for non-HTML bodies.
+        #[serde(default)]
+        engine: Option<String>,
         /// Optional query: if set, return top matching chunks.
         #[serde(default)]
         query: Option<String>,
@@ -5965,6 +7427,86 @@ mod mcp {
         /// Max links to return (default: 50).
         #[serde(default)]
         max_links: Option<usize>,
+        /// Include declared page alternates (default: false):
+        /// `extract.alternates = { canonical, amp, hreflang: [{lang, url}] }`, resolved to absolute URLs.
+        #[serde(default)]
+        include_alternates: Option<bool>,
+        /// If the page extracts with low confidence and declares a `rel=amphtml` alternate, fetch
+        /// and extract the AMP page instead (default: false). One bounded fetch, kept only when it
+        /// scores higher; recorded in `attempts.amp_alternate` plus warning `amp_alternate_used`.
+        #[serde(default)]
+        prefer_amp: Option<bool>,
+        /// Include schema.org entities (default: false): `extract.entities = [{type, properties,
+        /// sources, conflicts?}]` for Product/Recipe/Article/Event/Organization items found in
+        /// JSON-LD, microdata, or RDFa. The same item seen in several syntaxes is merged; differing
+        /// values are listed in `conflicts` with their source.
+        #[serde(default)]
+        include_entities: Option<bool>,
+        /// Include data tables (default: false): `extract.tables = [{headers, rows, truncated?}]`,
+        /// with colspan/rowspan padded out to a rectangular grid. Layout tables are skipped and
+        /// the page is capped at 10_000 cells. HTML only.
+        #[serde(default)]
+        include_tables: Option<bool>,
+        /// Include page metadata (default: false): `extract.metadata = {title, description,
+        /// site_name, image, author, published_time, canonical_url, json_ld[]}`, as in web_fetch.
+        #[serde(default)]
+        include_metadata: Option<bool>,
+        /// When include_tables=true, also render each table as "markdown" or "csv" into
+        /// `tables[].markdown` / `tables[].csv` (default: "json" = structured only).
+        #[serde(default)]
+        table_format: Option<String>,
+        /// Include dates mentioned in the extracted text (default: false): `extract.dates =
+        /// [{raw, iso, char_offset, ambiguous, alternatives?}]`, normalized to ISO 8601, with
+        /// `char_offset` into the extracted text. Numeric dates that read validly both ways
+        /// (`5/1/24`) come back `ambiguous` with both readings unless `date_order` is set.
+        #[serde(default)]
+        include_dates: Option<bool>,
+        /// Field order for all-numeric dates when include_dates=true: "mdy" (US), "dmy", or
+        /// "auto" (default: flag ambiguous ones instead of guessing).
+        #[serde(default)]
+        date_order: Option<String>,
+        /// Include deterministic keyphrases and entity-ish spans of the extracted text (default:
+        /// false): `extract.keywords = {keyphrases: [{phrase, score, count}], entities: [{text,
+        /// kind, count}], cached}`. Keyphrases are RAKE-scored (no model); entities are URLs, emails
+        /// and capitalized multi-word names. Memoized per doc_id.
+        #[serde(default)]
+        include_keywords: Option<bool>,
+        /// Max keyphrases when include_keywords=true (default: 10; max: 50).
+        #[serde(default)]
+        max_keywords: Option<usize>,
+        /// Tag each chunk with the HTML element it most likely came from (default: false):
+        /// `chunks[].dom_path` such as `article > section:nth-of-type(2) > p`, or null when the
+        /// chunk can't be located. Debugging aid for extraction quality; HTML engines only (no-op
+        /// for PDF/text/markdown content).
+        #[serde(default)]
+        chunk_dom_paths: Option<bool>,
+        /// Add a per-chunk sentence breakdown (default: false):
+        /// `chunks[].sentences = [{text, start_char, end_char}]` (offsets into the extracted text,
+        /// like the chunk's own; at most 50 sentences per chunk).
+        #[serde(default)]
+        sentences: Option<bool>,
+        /// Merge `<noscript>` fallback text into the extraction when the main body is low-signal
+        /// (e.g. a JS app shell), skipping text that is already visible (default: false).
+        #[serde(default)]
+        include_noscript: Option<bool>,
+        /// Fetch same-origin `<iframe src>` documents and append their text to the extraction,
+        /// each under an `[iframe: <url>]` note (default: false). One level deep, one fetch per
+        /// iframe; `extract.iframes[]` reports each frame (`merged` or a `skipped` reason).
+        #[serde(default)]
+        follow_iframes: Option<bool>,
+        /// When follow_iframes=true, also follow iframes on other origins (default: false).
+        /// Frames on localhost/private-network hosts are still skipped unless the page itself is
+        /// served from one.
+        #[serde(default)]
+        allow_cross_origin_iframes: Option<bool>,
+        /// When follow_iframes=true, max iframes to fetch (default: 3, max: 10).
+        #[serde(default)]
+        max_iframes: Option<usize>,
+        /// Keep the extracted text/chunks when the final status is not 2xx (default: false).
+        ///
+        /// Non-2xx responses always return `ok=false` with `error.code="http_status"`.
+        #[serde(default)]
+        include_error_body: Option<bool>,
         #[serde(default)]
         timeout_ms: Option<u64>,
         #[serde(default)]
@@ -5997,6 +7539,14 @@ mod mcp {
         /// Max chars per block in structure (default: 400; max: 2000).
         #[serde(default)]
         max_block_chars: Option<usize>,
+        /// Return the structure split by heading (default: false):
+        /// `extract.sections = [{heading, level, text, start_char, end_char}]`.
+        ///
+        /// Each section runs from its heading to the next same-or-higher-level heading; content
+        /// before the first heading is an intro section (`heading: null`, `level: 0`). Offsets index
+        /// `structure.structure_text`; bounded by max_blocks/max_block_chars.
+        #[serde(default)]
+        sectioned: Option<bool>,
         /// If true, compute semantic chunk scores using embeddings (default: false; requires feature).
         #[serde(default)]
         semantic_rerank: Option<bool>,
@@ -6009,6 +7559,12 @@ mod mcp {
         /// Max semantic chunks to return when semantic_rerank=true (default: 5; max: 50).
         #[serde(default)]
         semantic_top_k: Option<usize>,
+        /// Also return the fetched HTML body as `extract.raw_html` (default: false).
+        ///
+        /// Reuses the bytes already fetched (no extra request); capped at max_chars, with
+        /// `extract.raw_html_chars` / `extract.raw_html_truncated`. Only set for HTML responses.
+        #[serde(default)]
+        include_raw_html: Option<bool>,
     }
 
     /// Arguments for `web_search`.
@@ -6022,13 +7578,16 @@ mod mcp {
         /// Search query (required).
         #[serde(default)]
         query: Option<String>,
-        /// Which provider to use (default: brave). Allowed: auto, brave, tavily, searxng
+        /// Which provider to use (default: brave). Allowed: auto, brave, tavily, searxng, ddg
         #[serde(default)]
         provider: Option<String>,
         /// When provider="auto", choose routing mode:
         /// - "fallback" (default): pick the best available provider (brave-first) and fall back on failure.
-        /// - "merge": query all configured providers and merge/dedup results (bounded).
+        /// - "merge": query all configured providers and merge/dedup results (bounded), ordered by
+        ///   provider agreement, then best rank, then URL (stable across runs).
         /// - "mab": adaptive + deterministic choice based on in-process usage stats.
+        /// - "cost_cascade": try free providers (searxng) first and only pay (brave, tavily) when
+        ///   the free results fail a cheap quality gate; decisions are in `routing_explain`.
         #[serde(default)]
         auto_mode: Option<String>,
         #[serde(default)]
@@ -6042,6 +7601,119 @@ mod mcp {
         /// Some search providers do not apply any default timeout; leaving this unset can hang.
         #[serde(default)]
         timeout_ms: Option<u64>,
+        /// If true, cap results per registrable domain (keeping the highest-ranked ones) so one
+        /// site can't dominate the list (default: false). Dropped results are counted in
+        /// `diversified_count`.
+        #[serde(default)]
+        diversify: Option<bool>,
+        /// Max results per registrable domain when diversifying (default: 2; range: 1..=20).
+        /// Setting this implies `diversify=true`.
+        #[serde(default)]
+        max_per_domain: Option<usize>,
+        /// Restrict the search to one domain (and its subdomains), e.g. "docs.rs". Tavily filters
+        /// natively; other providers get a `site:` operator. Prefer `web_site_search`, which also
+        /// drops off-domain results.
+        #[serde(default)]
+        site: Option<String>,
+        /// Only results from the last "day", "week", "month" or "year". Brave, Tavily, SearXNG
+        /// and ddg all support this.
+        #[serde(default)]
+        freshness: Option<String>,
+        /// Only results published on or after this ISO date ("YYYY-MM-DD"). Brave and Tavily
+        /// only; other providers return a `date_filter_unsupported` warning.
+        #[serde(default)]
+        after: Option<String>,
+        /// Only results published on or before this ISO date ("YYYY-MM-DD"). Brave and Tavily
+        /// only, like `after`.
+        #[serde(default)]
+        before: Option<String>,
+        /// Skip this many results to fetch a later page (default: 0; max: 200). The response
+        /// reports the effective `offset` and `has_more`.
+        #[serde(default)]
+        offset: Option<usize>,
+    }
+
+    /// Arguments for `web_site_search`.
+    #[derive(Debug, Deserialize, JsonSchema, Default)]
+    struct WebSiteSearchArgs {
+        /// Domain to search within (required), e.g. "docs.rs". Subdomains match too; a scheme,
+        /// path or `site:` prefix is stripped.
+        #[serde(default)]
+        domain: Option<String>,
+        /// Search query (required).
+        #[serde(default)]
+        query: Option<String>,
+        /// Max results to return (default: 10; range: 1..=20).
+        #[serde(default)]
+        max_results: Option<usize>,
+        /// Search provider (default: auto). Allowed: auto, brave, tavily, searxng
+        #[serde(default)]
+        provider: Option<String>,
+        /// Search timeout (ms). Default: 20_000; max: 60_000.
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    }
+
+    /// Arguments for `web_summarize`.
+    ///
+    /// Extract + chunk one page (or caller-supplied text), then ask an LLM backend for a short
+    /// bullet summary of those chunks.
+    #[derive(Debug, Deserialize, JsonSchema, Default)]
+    struct WebSummarizeArgs {
+        /// URL to fetch+extract+summarize. Exactly one of `url` / `text` is required.
+        #[serde(default)]
+        url: Option<String>,
+        /// Text to summarize instead of a URL (chunked as plain text; nothing is fetched).
+        #[serde(default)]
+        text: Option<String>,
+        /// Max bullets in the summary (default: 5; range: 1..=10).
+        #[serde(default)]
+        max_bullets: Option<usize>,
+        /// Max chunks sent to the LLM (default: 8; max: 20).
+        #[serde(default)]
+        top_chunks: Option<usize>,
+        /// Max chars per chunk (default: 800; max: 2_000).
+        #[serde(default)]
+        max_chunk_chars: Option<usize>,
+        /// LLM backend (default: auto). Allowed: auto, ollama, openai_compat, perplexity,
+        /// anthropic, or any registered backend. `auto` picks as in web_deep_research.
+        #[serde(default)]
+        llm_backend: Option<String>,
+        /// Model override: Perplexity defaults to "sonar"; other backends fall back to their
+        /// `WEBPIPE_*_MODEL` env var.
+        #[serde(default)]
+        llm_model: Option<String>,
+        /// Max chars across all summary bullets (default: 2_000; max: 20_000).
+        #[serde(default)]
+        max_answer_chars: Option<usize>,
+        /// If true, read the page from cache only and require a localhost LLM backend
+        /// (ollama / openai_compat); errors when none is configured.
+        #[serde(default)]
+        no_network: Option<bool>,
+        /// Timeout (ms) applied to the fetch and the LLM call separately. Default: 30_000;
+        /// max: 120_000.
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    }
+
+    /// Arguments for `web_related`.
+    ///
+    /// Reverse pipeline: fetch+extract `url`, derive a query from its title/top keywords, then
+    /// `web_search` for pages like it (excluding the source's own domain).
+    #[derive(Debug, Deserialize, JsonSchema, Default)]
+    struct WebRelatedArgs {
+        /// Source page URL (required).
+        #[serde(default)]
+        url: Option<String>,
+        /// Max related results to return (default: 10; range: 1..=20).
+        #[serde(default)]
+        max_results: Option<usize>,
+        /// Search provider (default: auto). Allowed: auto, brave, tavily, searxng
+        #[serde(default)]
+        provider: Option<String>,
+        /// Timeout (ms) applied to the fetch and the search separately. Default: 20_000; max: 60_000.
+        #[serde(default)]
+        timeout_ms: Option<u64>,
     }
 
     /// Arguments for `web_perplexity`.
@@ -6111,6 +7783,16 @@ mod mcp {
         /// Max papers to keep after semantic rerank. Default: per_page; max: 50.
         #[serde(default)]
         semantic_top_k: Option<usize>,
+        /// If true, fetch and extract full text (PDF) for the top papers and attach it as
+        /// `papers[].fulltext`. Failures are reported per paper. Default: false.
+        #[serde(default)]
+        include_fulltext: Option<bool>,
+        /// Papers (from the top) that get full text. Default: 3; max: 10.
+        #[serde(default)]
+        fulltext_max_papers: Option<usize>,
+        /// Max chars of full text per paper. Default: 20_000; max: 200_000.
+        #[serde(default)]
+        fulltext_max_chars: Option<usize>,
     }
 
     #[derive(Debug, Deserialize, JsonSchema, Default)]
@@ -6155,6 +7837,13 @@ mod mcp {
         /// Max discussion pages to fetch/extract (default: 2; max: 5).
         #[serde(default)]
         max_discussion_urls: Option<usize>,
+        /// If true (with include_discussions), fetch and extract the discussion pages and attach
+        /// a relevance-ranked snippet per page as `discussions[]`. Failures are per URL. Default: false.
+        #[serde(default)]
+        fetch_discussions: Option<bool>,
+        /// Discussion pages fetched at once when fetch_discussions=true. Default: 2; max: 5.
+        #[serde(default)]
+        discussion_concurrency: Option<usize>,
         /// Timeout per discussion fetch (ms). Default: 20_000.
         #[serde(default)]
         timeout_ms: Option<u64>,
@@ -6175,6 +7864,14 @@ mod mcp {
         /// Max discussion pages to include (default: 2; max: 5). Enrich mode only.
         #[serde(default)]
         max_discussion_urls: Option<usize>,
+        /// Fetch the discussion pages and attach a relevance-ranked snippet per page as
+        /// `discussions[]` (default: false). Enrich mode only.
+        #[serde(default)]
+        fetch_discussions: Option<bool>,
+        /// Discussion pages fetched at once when fetch_discussions=true (default: 2; max: 5).
+        /// Enrich mode only.
+        #[serde(default)]
+        discussion_concurrency: Option<usize>,
 
         // ---- search fields (pass query to search for papers by topic/keyword) ----
         /// Search query. If provided (and id_or_url is absent), search mode: returns papers[].
@@ -6260,6 +7957,19 @@ mod mcp {
         /// Max semantic chunks per doc when semantic_rerank=true (default: 5; max: 50).
         #[serde(default)]
         semantic_top_k: Option<usize>,
+        /// How cached docs and chunks are ranked (default: "lexical").
+        ///
+        /// - "lexical": query-term overlap only; docs without overlap are dropped.
+        /// - "hybrid": `hybrid_weight * semantic + (1 - hybrid_weight) * lexical`, both normalized
+        ///   to 0..1 over the candidates (lexical by the max score, semantic min-max over cosine
+        ///   similarity of OpenRouter embeddings). Docs with no query-term overlap stay eligible,
+        ///   so paraphrases can surface. Falls back to lexical (warning
+        ///   `hybrid_scoring_fell_back_to_lexical`) when embeddings are unavailable.
+        #[serde(default)]
+        scoring: Option<String>,
+        /// Semantic weight for scoring="hybrid" (default: 0.5; 0 = lexical only, 1 = semantic only).
+        #[serde(default)]
+        hybrid_weight: Option<f64>,
         /// If true, return a more compact per-doc shape (default: true).
         ///
         /// This reduces duplication between top-level fields and `extract.*` by keeping:
@@ -6271,6 +7981,67 @@ mod mcp {
         compact: Option<bool>,
     }
 
+    /// Arguments for `web_cache_migrate`.
+    #[derive(Debug, Deserialize, JsonSchema, Default)]
+    struct WebCacheMigrateArgs {
+        /// Max cache entries (meta files) to examine (default: 20_000; max: 200_000).
+        #[serde(default)]
+        max_entries: Option<usize>,
+        /// If true, delete legacy v1 files once their v2 copy exists (default: false).
+        #[serde(default)]
+        remove_legacy: Option<bool>,
+    }
+
+    /// Arguments for `web_compare_backends`.
+    #[derive(Debug, Deserialize, JsonSchema, Default)]
+    struct WebCompareBackendsArgs {
+        /// URLs to extract with both backends (max 20).
+        #[serde(default)]
+        urls: Option<Vec<String>>,
+        /// Per-backend metrics to report: any of "chars", "low_signal", "confidence", "ms"
+        /// (default: all). The winner is always decided on low_signal + confidence.
+        #[serde(default)]
+        metrics: Option<Vec<String>>,
+        /// Per-URL, per-backend timeout (default: 20_000; max: 60_000).
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+        /// Read the local backend from the fetch cache (default: false, so both sides hit the network).
+        #[serde(default)]
+        cache_read: Option<bool>,
+    }
+
+    /// Arguments for `web_diff`.
+    #[derive(Debug, Deserialize, JsonSchema, Default)]
+    struct WebDiffArgs {
+        /// URL to compare (required).
+        #[serde(default)]
+        url: Option<String>,
+        /// Fetch timeout for the fresh copy (default: 20_000; max: 60_000).
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+        /// Max bytes per fetch (default: 5_000_000, the same as web_extract, so its cached copy
+        /// is the "old" side).
+        #[serde(default)]
+        max_bytes: Option<u64>,
+        /// Max chars of extracted text per side (default: 50_000; max: 200_000).
+        #[serde(default)]
+        max_chars: Option<usize>,
+        /// Unchanged lines shown around each change in `diff.unified` (default: 3; max: 10).
+        #[serde(default)]
+        context_lines: Option<usize>,
+        /// Max lines of `diff.unified` (default: 200; max: 2_000).
+        #[serde(default)]
+        max_diff_lines: Option<usize>,
+        /// Store the fresh copy as the new cached version, so the next diff starts from it
+        /// (default: true).
+        #[serde(default)]
+        update_cache: Option<bool>,
+        /// Not supported: the "new" side needs a network fetch. Present so callers get a clean
+        /// error rather than a silent fetch.
+        #[serde(default)]
+        no_network: Option<bool>,
+    }
+
     /// Arguments for `web_search_extract`.
     ///
     /// This is the main “do the job” tool:
@@ -6307,15 +8078,22 @@ mod mcp {
         /// Which search provider to use if `query` is used (default: auto). Allowed: auto, brave, tavily, searxng
         #[serde(default)]
         pub(crate) provider: Option<String>,
-        /// When provider="auto", choose routing mode (default: "fallback"). Allowed: fallback, merge, mab
+        /// When provider="auto", choose routing mode (default: "fallback"). Allowed: fallback, merge, mab, cost_cascade
         #[serde(default)]
         pub(crate) auto_mode: Option<String>,
-        /// How to select `top_chunks` across URLs (default: "score"). Allowed: score, pareto
+        /// How to select `top_chunks` across URLs (default: "score"). Allowed: score, pareto, mmr
         ///
         /// - "score": sort by chunk score (descending)
         /// - "pareto": non-dominated selection across score/cost/warnings (bounded)
+        /// - "mmr": maximal marginal relevance: trade score against similarity to chunks already
+        ///   picked (embeddings when `semantic_rerank=true` and a backend is configured, else
+        ///   word overlap), to avoid near-duplicate chunks
         #[serde(default)]
         pub(crate) selection_mode: Option<String>,
+        /// For `selection_mode="mmr"`: weight of relevance vs diversity in `[0, 1]` (default: 0.7;
+        /// 1 = plain score order, lower = more diverse).
+        #[serde(default)]
+        pub(crate) mmr_lambda: Option<f64>,
         /// Which fetch backend to use (default: local). Allowed: local, firecrawl, render
         ///
         /// - local: reqwest + cache (best default; supports Tor SOCKS via WEBPIPE_ANON_PROXY in anonymous mode)
@@ -6458,6 +8236,10 @@ mod mcp {
         /// - "wide": more breadth (more search + frontier) with shallower per-page extraction
         /// - "deep": fewer pages, deeper per-page extraction, enables agentic discovery
         /// - "smart": balanced but enables agentic discovery
+        ///
+        /// When unset in search mode, the preset is picked from the query intent
+        /// (`request.intent`: factoid, howto, survey, navigational, code); survey queries get "wide",
+        /// everything else "balanced". `request.exploration_auto` reports whether this happened.
         #[serde(default)]
         pub(crate) exploration: Option<String>,
 
@@ -6488,6 +8270,30 @@ mod mcp {
         #[serde(default)]
         pub(crate) agentic_frontier_max: Option<usize>,
 
+        /// When agentic=true, maximum discovery depth (default: unlimited).
+        ///
+        /// Seed URLs (explicit `urls` or search results) are depth 0, links discovered on them are
+        /// depth 1, and so on. Links deeper than this are not added to the frontier.
+        #[serde(default)]
+        pub(crate) agentic_max_depth: Option<usize>,
+
+        /// When agentic=true, a quality floor for the initial URLs (default: off).
+        ///
+        /// Once a round's URLs are processed, if no chunk scores at least this (same scale as
+        /// `top_chunks[].score`), the initial results are treated as weak and another search
+        /// round runs (still bounded by `agentic_max_search_rounds`).
+        #[serde(default)]
+        pub(crate) agentic_min_quality_score: Option<u64>,
+
+        /// When agentic=true, keep up to this many frontier candidates in flight (default: 1 = off, max: 8).
+        ///
+        /// Besides the current pick, the next-best candidates are fetched concurrently into the cache
+        /// so later picks are likely cache hits. Prefetches do not count toward `max_urls`, skip
+        /// auth/challenge URLs, and are capped per host. Requires the local backend with cache
+        /// read+write enabled and `no_network=false`.
+        #[serde(default)]
+        pub(crate) agentic_prefetch: Option<usize>,
+
         /// Max planner (LLM) calls for a single request (default: WEBPIPE_PLANNER_MAX_CALLS or 1).
         #[serde(default)]
         pub(crate) planner_max_calls: Option<usize>,
@@ -6512,6 +8318,58 @@ mod mcp {
         /// Inspired by the minimal_output pattern from github/github-mcp-server.
         #[serde(default)]
         pub(crate) minimal_output: Option<bool>,
+
+        /// Stop fetching further URLs once the accumulated chunks are "good enough" (default: off).
+        ///
+        /// Checked between fetches (or between batches when max_parallel_urls > 1), so in-flight
+        /// URLs always finish. Trades completeness for latency on easy queries; when it triggers,
+        /// the response has `early_exit: true`.
+        #[serde(default)]
+        pub(crate) early_exit: Option<EarlyExitArgs>,
+
+        /// Also surface up to 3 candidate chunks that contrast with the top chunks (default: false).
+        ///
+        /// Returned as `contrasting_chunks[]`, each with a `contrast` explanation. Best-effort lexical
+        /// heuristic (shared terms + negation/antonym cues the top chunk lacks), not stance detection.
+        #[serde(default)]
+        pub(crate) balance: Option<bool>,
+
+        /// Keep extract text/chunks on per-URL results whose final status is not 2xx
+        /// (default: false). Such results are always `ok=false` with `error.code="http_status"`
+        /// and never contribute to `top_chunks`.
+        #[serde(default)]
+        pub(crate) include_error_body: Option<bool>,
+
+        /// Favor recently published pages when ranking `top_chunks` (default: 0 = off, max: 10).
+        ///
+        /// Chunk scores are multiplied by `1 + recency_boost * 0.5^(age_days / recency_half_life_days)`,
+        /// using the page's metadata `published_time`. Pages without a parseable date keep their
+        /// score; each per-URL result reports the `recency_factor` applied.
+        #[serde(default)]
+        pub(crate) recency_boost: Option<f32>,
+
+        /// Half-life in days of the `recency_boost` bonus (default: 30, min: 1).
+        #[serde(default)]
+        pub(crate) recency_half_life_days: Option<f64>,
+
+        /// Hard cap on the serialized JSON payload, in bytes (default: none, min: 1000).
+        ///
+        /// When exceeded, the lowest-value content is dropped deterministically (contrasting
+        /// chunks, then the worst top_chunks, then per-URL detail, then whole per-URL results)
+        /// and `output_truncated` records what went. Envelope fields and `ok` are never trimmed.
+        #[serde(default)]
+        pub(crate) max_output_bytes: Option<usize>,
+    }
+
+    /// Thresholds for `web_search_extract.early_exit`.
+    #[derive(Debug, Clone, Deserialize, JsonSchema, Default)]
+    pub(crate) struct EarlyExitArgs {
+        /// Minimum query-match score a chunk needs to count (default: 1).
+        #[serde(default)]
+        pub(crate) min_top_score: Option<u64>,
+        /// How many chunks must reach `min_top_score` before stopping (default: 1).
+        #[serde(default)]
+        pub(crate) min_chunks: Option<usize>,
     }
 
     /// Arguments for `web_explore_extract`.
@@ -6569,6 +8427,71 @@ mod mcp {
         include_links: Option<bool>,
     }
 
+    /// Arguments for `web_crawl`.
+    ///
+    /// Breadth-first site map: fetch start_url, then its links level by level. Strictly bounded by
+    /// `max_pages`/`max_depth`, one request at a time, and paced per host. `backend="firecrawl"`
+    /// hands the whole crawl to Firecrawl's crawl endpoint instead.
+    #[derive(Debug, Deserialize, JsonSchema, Default)]
+    struct WebCrawlArgs {
+        /// Starting URL (required).
+        #[serde(default)]
+        start_url: Option<String>,
+        /// Crawler: "local" (default; robots-aware BFS) or "firecrawl" (Firecrawl crawl job; needs
+        /// WEBPIPE_FIRECRAWL_API_KEY). Firecrawl applies its own robots/pacing rules, so
+        /// same_domain/respect_robots/per_host_delay_ms/max_links_per_page/cache_* don't apply.
+        #[serde(default)]
+        backend: Option<String>,
+        /// Optional query for chunk scoring; the best chunks across all pages are returned as
+        /// top_chunks.
+        #[serde(default)]
+        query: Option<String>,
+        /// Max chunks returned in top_chunks across all pages (default: 5; max: 50).
+        #[serde(default)]
+        top_chunks: Option<usize>,
+        /// Max chars per chunk (default: 500; max: 5_000).
+        #[serde(default)]
+        max_chunk_chars: Option<usize>,
+        /// Max pages to fetch (default: 10; range: 1..=100). robots-skipped URLs don't count.
+        #[serde(default)]
+        max_pages: Option<usize>,
+        /// Max link depth from start_url (default: 2; max: 5). Depth 0 means only start_url.
+        #[serde(default)]
+        max_depth: Option<usize>,
+        /// If true, only follow internal links: same host or same registrable domain as start_url
+        /// (default: true).
+        #[serde(default)]
+        same_domain: Option<bool>,
+        /// If true, include each page's extracted text (bounded by `max_chars`; default: false).
+        #[serde(default)]
+        include_text: Option<bool>,
+        /// Max chars of extracted text per page (default: 20_000; max: 200_000).
+        #[serde(default)]
+        max_chars: Option<usize>,
+        /// Honor robots.txt (`User-agent: webpipe`, else `*`) including `Crawl-delay` (default: true).
+        #[serde(default)]
+        respect_robots: Option<bool>,
+        /// Minimum delay between requests to the same host, in ms (default: 500; max: 10_000).
+        /// A larger robots `Crawl-delay` wins (capped at 10s).
+        #[serde(default)]
+        per_host_delay_ms: Option<u64>,
+        /// Max links followed from each page, in document order (default: 100; max: 500).
+        #[serde(default)]
+        max_links_per_page: Option<usize>,
+        /// Fetch timeout per page (ms). Default: 20_000; max: 60_000. With backend="firecrawl" it
+        /// bounds the whole crawl instead (default: 60_000; max: 300_000); pages scraped by then
+        /// are returned.
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+        /// Max bytes per page (default: 5_000_000).
+        #[serde(default)]
+        max_bytes: Option<u64>,
+        #[serde(default)]
+        cache_read: Option<bool>,
+        #[serde(default)]
+        cache_write: Option<bool>,
+    }
+
     /// Arguments for `repo_ingest`.
     ///
     /// This is gitingest-like functionality: turn a repo into a bounded text corpus.
@@ -6672,6 +8595,17 @@ mod mcp {
         max_chunk_chars: Option<usize>,
     }
 
+    /// Arguments for `web_sitemap`.
+    #[derive(Debug, Deserialize, JsonSchema, Default)]
+    struct WebSitemapArgs {
+        /// Any URL on the site (required); robots.txt is read at its origin.
+        #[serde(default)]
+        site_url: Option<String>,
+        /// Max page URLs to return (default: 500; max: 10_000).
+        #[serde(default)]
+        max_urls: Option<usize>,
+    }
+
     /// Arguments for `web_deep_research`.
     ///
     /// This is an agentic evidence-gathering tool:
@@ -6706,7 +8640,7 @@ mod mcp {
         /// When provider="auto", choose routing mode (default: "fallback"). Allowed: fallback, merge
         #[serde(default)]
         auto_mode: Option<String>,
-        /// How to select `top_chunks` across URLs (default: "score"). Allowed: score, pareto
+        /// How to select `top_chunks` across URLs (default: "score"). Allowed: score, pareto, mmr
         #[serde(default)]
         selection_mode: Option<String>,
 
@@ -6811,7 +8745,7 @@ mod mcp {
 
         /// Model name for non-Perplexity synthesis backends (e.g. OpenAI-compatible local gateways).
         ///
-        /// - Used by `llm_backend="openai_compat"`.
+        /// - Used by `llm_backend="openai_compat"` and `llm_backend="anthropic"`.
         /// - Ignored by `llm_backend="ollama"` (which uses WEBPIPE_OLLAMA_MODEL).
         #[serde(default)]
         llm_model: Option<String>,
@@ -6843,12 +8777,41 @@ mod mcp {
 
         /// Which LLM backend to use for synthesis.
         ///
-        /// - "auto" (default): Perplexity if configured (and no_network=false), else OpenAI-compatible if configured, else Ollama if enabled.
+        /// - "auto" (default): Perplexity if configured (and no_network=false), else Anthropic if configured (and no_network=false), else OpenAI-compatible if configured, else Ollama if enabled.
         /// - "perplexity": require Perplexity API (network-only).
+        /// - "anthropic": Anthropic Messages API (network-only; WEBPIPE_ANTHROPIC_API_KEY + llm_model or WEBPIPE_ANTHROPIC_MODEL).
         /// - "ollama": use local Ollama (best-effort; defaults to localhost).
         /// - "openai_compat": call an OpenAI-compatible `/v1/chat/completions` endpoint (works well with `axi-gateway`).
+        /// - any other name registered in the server's LLM backend registry.
         #[serde(default)]
         llm_backend: Option<String>,
+
+        /// If true, make one extra bounded LLM call after synthesis that proposes 3-5 follow-up
+        /// research questions (default: false).
+        ///
+        /// Returned as `followups: [{ question, rationale }]`. If the call fails, `followups` is omitted
+        /// (with a `followups_unavailable` warning) rather than failing the tool.
+        #[serde(default)]
+        suggest_followups: Option<bool>,
+
+        /// Evidence rounds to run before synthesis: 1 (default) or 2.
+        ///
+        /// With 2, a refined query is derived from pass-1 evidence (one bounded LLM call on the
+        /// synthesis backend, or top keywords from the pass-1 chunks when no backend is available),
+        /// and a second search/extract round (half the `max_urls` budget) is merged into the
+        /// evidence pack. Per-round provenance is returned as `rounds`. Ignored for `urls=[...]`.
+        #[serde(default)]
+        passes: Option<u8>,
+
+        /// If true, ask the synthesis backend for a structured report (default: false).
+        ///
+        /// Returned as `report: { summary, sections: [{ heading, content, citations: [n] }],
+        /// sources: [{ n, url, title }] }`, where every citation is a `sources[].n`. An invalid reply
+        /// gets one repair call; if that also fails, `report` is omitted (`structured_report_invalid`).
+        /// With `synthesize=false`, or `llm_backend="auto"` and no backend configured, the report is
+        /// built extractively from the top chunks (`report_source: "extractive"`).
+        #[serde(default)]
+        structured_output: Option<bool>,
     }
 
     #[derive(Debug, Clone)]
@@ -6862,6 +8825,83 @@ mod mcp {
         cache_hit: bool,
     }
 
+    impl ChunkCandidate {
+        fn into_chunk(self, contrast: Option<serde_json::Value>) -> webpipe_local::results::Chunk {
+            webpipe_local::results::Chunk {
+                url: self.url,
+                score: self.score,
+                start_char: self.start_char,
+                end_char: self.end_char,
+                text: self.text,
+                contrast,
+            }
+        }
+    }
+
+    /// Samples kept per provider/backend for latency percentiles.
+    const LATENCY_RESERVOIR_CAP: usize = 512;
+
+    /// Bounded uniform sample of call latencies (reservoir sampling, Algorithm R).
+    ///
+    /// Replacement slots come from a hash of the call count rather than an RNG, so a given
+    /// sequence of latencies always yields the same percentiles. `count`, `mean` and `max` are
+    /// exact; `p50`/`p95`/`p99` are estimated from at most [`LATENCY_RESERVOIR_CAP`] samples.
+    #[derive(Debug, Clone, Default)]
+    struct LatencyReservoir {
+        count: u64,
+        sum_ms: u64,
+        max_ms: u64,
+        samples: Vec<u64>,
+    }
+
+    impl LatencyReservoir {
+        fn record(&mut self, ms: u64) {
+            self.count += 1;
+            self.sum_ms = self.sum_ms.saturating_add(ms);
+            self.max_ms = self.max_ms.max(ms);
+            if self.samples.len() < LATENCY_RESERVOIR_CAP {
+                self.samples.push(ms);
+                return;
+            }
+            // splitmix64 finalizer of the call count.
+            let mut z = self.count.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            let j = (z % self.count) as usize;
+            if j < LATENCY_RESERVOIR_CAP {
+                self.samples[j] = ms;
+            }
+        }
+
+        /// Nearest-rank percentile (`q` in 0..=1) of the sample.
+        fn percentile(sorted: &[u64], q: f64) -> Option<u64> {
+            let n = sorted.len();
+            if n == 0 {
+                return None;
+            }
+            let rank = ((q * n as f64).ceil() as usize).clamp(1, n);
+            Some(sorted[rank - 1])
+        }
+    }
+
+    impl serde::Serialize for LatencyReservoir {
+        fn serialize<S: serde::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+            let mut sorted = self.samples.clone();
+            sorted.sort_unstable();
+            serde_json::json!({
+                "count": self.count,
+                "mean": (self.count > 0)
+                    .then(|| ((self.sum_ms as f64 / self.count as f64) * 10.0).round() / 10.0),
+                "p50": Self::percentile(&sorted, 0.50),
+                "p95": Self::percentile(&sorted, 0.95),
+                "p99": Self::percentile(&sorted, 0.99),
+                "max": (self.count > 0).then_some(self.max_ms),
+            })
+            .serialize(ser)
+        }
+    }
+
     #[derive(Debug, Clone, Default, serde::Serialize)]
     struct ProviderUsage {
         calls: u64,
@@ -6869,6 +8909,8 @@ mod mcp {
         cost_units: u64,
         elapsed_ms_sum: u64,
         http_429: u64,
+        /// Call latency in ms: `{count, mean, p50, p95, p99, max}`.
+        latency: LatencyReservoir,
     }
 
     #[derive(Debug, Clone, serde::Serialize)]
@@ -6953,13 +8995,44 @@ mod mcp {
         tool_router: RmcpToolRouter<Self>,
         fetcher: Arc<LocalFetcher>,
         http: reqwest::Client,
+        /// LLM backends selectable by `llm_backend` (builtins + anything registered).
+        llm: Arc<webpipe_local::llm::LlmRegistry>,
         stats: Arc<std::sync::Mutex<UsageStats>>,
+        /// Embedding vectors keyed by `model\ntext` (bounded; see `embed_texts_cached`).
+        embeddings_cache: Arc<std::sync::Mutex<std::collections::HashMap<String, Vec<f32>>>>,
+        /// On-disk embeddings under the fetch cache dir, behind `embeddings_cache`
+        /// (`None` when `WEBPIPE_EMBEDDINGS_CACHE=0`).
+        embeddings_disk: Option<webpipe_local::semantic::EmbeddingCache>,
+        /// In-process embeddings model (`WEBPIPE_EMBED_BACKEND=local`), loaded on first use.
+        #[cfg(feature = "embed-local")]
+        local_embedder: Arc<tokio::sync::OnceCell<Result<Arc<LocalEmbedder>, String>>>,
+        /// `include_keywords` indexes keyed by `doc_id`, with a fingerprint of the text they
+        /// were computed from (bounded; see `keyword_index_cached`).
+        keywords_cache: Arc<
+            std::sync::Mutex<
+                std::collections::HashMap<String, (u64, webpipe_local::keywords::KeywordIndex)>,
+            >,
+        >,
     }
 
+    /// Entries kept in `WebpipeMcp::embeddings_cache` before it is cleared.
+    const EMBEDDINGS_CACHE_MAX_ENTRIES: usize = 4_096;
+    /// Entries kept in `WebpipeMcp::keywords_cache` before it is cleared.
+    const KEYWORDS_CACHE_MAX_ENTRIES: usize = 1_024;
+
     #[tool_router]
     impl WebpipeMcp {
         pub(crate) fn new() -> Result<Self, McpError> {
             let cache_dir = cache_dir_from_env().or_else(|| Some(default_cache_dir()));
+            let embeddings_disk = cache_dir
+                .as_deref()
+                .filter(|_| {
+                    !matches!(
+                        std::env::var("WEBPIPE_EMBEDDINGS_CACHE").ok().as_deref(),
+                        Some("0" | "off" | "false")
+                    )
+                })
+                .map(webpipe_local::semantic::EmbeddingCache::new);
             let fetcher = LocalFetcher::new(cache_dir)
                 .map_err(|e| McpError::internal_error(e.to_string(), None))?;
             let privacy = privacy_mode_from_env();
@@ -6974,17 +9047,61 @@ mod mcp {
                     })?);
                 }
             }
+            let http = httpb
+                .build()
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
             Ok(Self {
                 prompt_router: Self::prompt_router(),
                 tool_router: Self::tool_router(),
                 fetcher: Arc::new(fetcher),
-                http: httpb
-                    .build()
-                    .map_err(|e| McpError::internal_error(e.to_string(), None))?,
+                llm: Arc::new(webpipe_local::llm::LlmRegistry::with_builtins(http.clone())),
+                http,
                 stats: Arc::new(std::sync::Mutex::new(UsageStats::new(now_epoch_s()))),
+                embeddings_cache: Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
+                embeddings_disk,
+                #[cfg(feature = "embed-local")]
+                local_embedder: Arc::new(tokio::sync::OnceCell::new()),
+                keywords_cache: Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
             })
         }
 
+        /// Register (or replace) an LLM backend selectable via `llm_backend=<name>`.
+        ///
+        /// The stdio server only uses the builtins; this is the injection point for embedders.
+        #[allow(dead_code)]
+        pub(crate) fn with_llm_backend(
+            mut self,
+            name: &str,
+            backend: Arc<dyn webpipe_local::llm::LlmBackend>,
+        ) -> Self {
+            Arc::make_mut(&mut self.llm).register(name, backend);
+            self
+        }
+
+        /// Run one completion on a registered backend, recording per-backend LLM stats.
+        async fn llm_complete(
+            &self,
+            backend: &str,
+            prompt: &webpipe_local::llm::Prompt,
+            opts: &webpipe_local::llm::CompletionOpts,
+        ) -> webpipe_core::Result<webpipe_local::llm::Completion> {
+            let Some(b) = self.llm.get(backend) else {
+                return Err(webpipe_core::Error::NotConfigured(format!(
+                    "unknown llm backend: {backend}"
+                )));
+            };
+            let t0 = std::time::Instant::now();
+            let r = b.complete(prompt, opts).await;
+            let elapsed_ms = t0.elapsed().as_millis() as u64;
+            match &r {
+                Ok(_) => self.stats_record_llm_backend(backend, true, elapsed_ms, None),
+                Err(e) => {
+                    self.stats_record_llm_backend(backend, false, elapsed_ms, Some(&e.to_string()))
+                }
+            }
+            r
+        }
+
         fn stats_lock(&self) -> std::sync::MutexGuard<'_, UsageStats> {
             self.stats.lock().unwrap_or_else(|e| e.into_inner())
         }
@@ -7009,6 +9126,7 @@ mod mcp {
             }
             entry.cost_units = entry.cost_units.saturating_add(cost_units);
             entry.elapsed_ms_sum = entry.elapsed_ms_sum.saturating_add(elapsed_ms);
+            entry.latency.record(elapsed_ms);
             if http_429 {
                 entry.http_429 += 1;
             }
@@ -7091,6 +9209,37 @@ mod mcp {
             }
         }
 
+        /// [`Self::stats_record_search_provider_qk`] for one page of a `web_search`. Pages after
+        /// the first only update usage totals: the routing windows already saw this logical
+        /// query, and counting every page would inflate its cost for MAB/merge routing.
+        #[allow(clippy::too_many_arguments)]
+        fn stats_record_search_page(
+            &self,
+            first_page: bool,
+            name: &str,
+            ok: bool,
+            cost_units: u64,
+            elapsed_ms: u64,
+            err: Option<&str>,
+            query_key: Option<&str>,
+        ) {
+            if first_page {
+                return self.stats_record_search_provider_qk(
+                    name, ok, cost_units, elapsed_ms, err, query_key,
+                );
+            }
+            let http_429 = err.is_some_and(|m| is_http_status(m, 429));
+            let mut s = self.stats_lock();
+            Self::stats_record_provider(
+                &mut s.search_providers,
+                name,
+                ok,
+                cost_units,
+                elapsed_ms,
+                http_429,
+            );
+        }
+
         fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
             if a.is_empty() || b.is_empty() || a.len() != b.len() {
                 return 0.0;
@@ -7117,21 +9266,17 @@ mod mcp {
             candidates: &[(usize, usize, String)],
             top_k: usize,
         ) -> webpipe_local::semantic::SemanticRerankResult {
-            let t0 = std::time::Instant::now();
             let top_k = top_k.max(1);
             let q = query.trim();
             if q.is_empty() || candidates.is_empty() {
                 return webpipe_local::semantic::semantic_rerank_chunks(query, candidates, top_k);
             }
 
-            // Best-effort: if OpenRouter embeddings is configured, use it; otherwise fall back
+            // Best-effort: if an embeddings backend is configured, use it; otherwise fall back
             // to the local lexical overlap scorer.
-            let Some(api_key) = Self::openrouter_api_key_from_env() else {
+            if !Self::embeddings_configured() {
                 return webpipe_local::semantic::semantic_rerank_chunks(query, candidates, top_k);
-            };
-
-            let model = Self::openrouter_embeddings_model_from_env();
-            let timeout_ms = Self::openrouter_embeddings_timeout_ms_from_env();
+            }
             let max_inputs = Self::semantic_embeddings_max_inputs_from_env();
 
             // Prefilter (bounded): we only embed up to max_inputs candidates.
@@ -7150,57 +9295,25 @@ mod mcp {
                 let t: String = ch.text.chars().take(1200).collect();
                 inputs.push(t);
             }
-            // Proxy “cost units”: number of embeddings requested (query + each candidate).
-            // We intentionally do not try to guess tokens/$ pricing here.
-            let embedding_units = inputs.len() as u64;
-
-            let client = match webpipe_local::openai_compat::OpenAiCompatClient::new(
-                self.http.clone(),
-                // Client appends `/v1/embeddings`.
-                "https://openrouter.ai/api".to_string(),
-                Some(api_key),
-                model.clone(),
-            ) {
-                Ok(c) => c,
-                Err(_) => {
-                    warnings.push("semantic_embeddings_client_not_configured");
-                    let mut out =
-                        webpipe_local::semantic::semantic_rerank_chunks(query, candidates, top_k);
-                    out.warnings.extend(warnings);
-                    return out;
-                }
-            };
-
-            let embs = match client.embeddings(inputs, timeout_ms).await {
-                Ok(v) => v,
-                Err(_) => {
-                    self.stats_record_llm_backend(
-                        "openrouter_embeddings",
-                        false,
-                        t0.elapsed().as_millis() as u64,
-                        None,
-                    );
-                    warnings.push("semantic_embeddings_failed_fallback_to_lexical");
-                    let mut out =
-                        webpipe_local::semantic::semantic_rerank_chunks(query, candidates, top_k);
-                    out.warnings.extend(warnings);
-                    return out;
-                }
-            };
 
-            if embs.len() != pre.chunks.len() + 1 {
-                self.stats_record_llm_backend(
-                    "openrouter_embeddings",
-                    false,
-                    t0.elapsed().as_millis() as u64,
-                    None,
-                );
-                warnings.push("semantic_embeddings_bad_shape_fallback_to_lexical");
-                let mut out =
-                    webpipe_local::semantic::semantic_rerank_chunks(query, candidates, top_k);
-                out.warnings.extend(warnings);
-                return out;
-            }
+            // Cached vectors (memory, then disk) skip the backend; only misses are requested and
+            // counted as embedding units.
+            let (embs, cache_hits, cache_misses, model) =
+                match self.embed_texts_cached(&inputs).await {
+                    Ok(v) => v,
+                    Err(reason) => {
+                        warnings.push(match reason {
+                            "not_configured" => "semantic_embeddings_client_not_configured",
+                            "bad_shape" => "semantic_embeddings_bad_shape_fallback_to_lexical",
+                            _ => "semantic_embeddings_failed_fallback_to_lexical",
+                        });
+                        let mut out = webpipe_local::semantic::semantic_rerank_chunks(
+                            query, candidates, top_k,
+                        );
+                        out.warnings.extend(warnings);
+                        return out;
+                    }
+                };
 
             let q_emb = &embs[0];
             for (i, ch) in pre.chunks.iter_mut().enumerate() {
@@ -7215,19 +9328,414 @@ mod mcp {
             });
             pre.chunks.truncate(top_k);
 
-            pre.backend = "openrouter_embeddings".to_string();
+            pre.backend = Self::embeddings_backend_label().to_string();
             pre.model_id = Some(model);
+            pre.cache_hits = cache_hits;
+            pre.cache_misses = cache_misses;
             pre.warnings.extend(warnings);
-            self.stats_record_llm_backend_units(
-                "openrouter_embeddings",
-                true,
-                embedding_units,
-                t0.elapsed().as_millis() as u64,
-                None,
-            );
             pre
         }
 
+        /// Why semantic rerank can't run right now, or `None` when it can: the `semantic` feature
+        /// was compiled out, or no embeddings backend is configured.
+        fn semantic_unavailable_reason() -> Option<&'static str> {
+            if !cfg!(feature = "semantic") {
+                Some("feature_disabled")
+            } else if Self::embed_backend_from_env() == "local" && !cfg!(feature = "embed-local") {
+                Some("embed_local_feature_disabled")
+            } else if !Self::embeddings_configured() {
+                Some("backend_not_configured")
+            } else {
+                None
+            }
+        }
+
+        /// Embeddings backend for semantic rerank and hybrid cache search:
+        /// `WEBPIPE_EMBED_BACKEND=local` (in-process model, `embed-local` feature), else
+        /// `openrouter`.
+        fn embed_backend_from_env() -> &'static str {
+            match std::env::var("WEBPIPE_EMBED_BACKEND")
+                .ok()
+                .map(|v| v.trim().to_ascii_lowercase())
+                .as_deref()
+            {
+                Some("local") => "local",
+                _ => "openrouter",
+            }
+        }
+
+        /// `backend` label reported with embedding-scored results.
+        fn embeddings_backend_label() -> &'static str {
+            match Self::embed_backend_from_env() {
+                "local" => "local_embeddings",
+                _ => "openrouter_embeddings",
+            }
+        }
+
+        /// Model id of the local embeddings backend when it is compiled in and configured.
+        fn local_embeddings_model_id() -> Option<String> {
+            #[cfg(feature = "embed-local")]
+            {
+                webpipe_local::embed_local::local_model_dir_from_env()?;
+                webpipe_local::embed_local::local_model_id_from_env()
+            }
+            #[cfg(not(feature = "embed-local"))]
+            {
+                None
+            }
+        }
+
+        /// Whether the selected embeddings backend has what it needs (no IO, no model load).
+        fn embeddings_configured() -> bool {
+            match Self::embed_backend_from_env() {
+                "local" => Self::local_embeddings_model_id().is_some(),
+                _ => Self::openrouter_api_key_from_env().is_some(),
+            }
+        }
+
+        /// Embed with the in-process model (loaded once per server, off the async runtime).
+        #[cfg(feature = "embed-local")]
+        async fn local_embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, &'static str> {
+            let model = self
+                .local_embedder
+                .get_or_init(|| async {
+                    tokio::task::spawn_blocking(LocalEmbedder::from_env)
+                        .await
+                        .map_err(|e| e.to_string())
+                        .and_then(|r| r.map(Arc::new).map_err(|e| e.to_string()))
+                })
+                .await
+                .clone()
+                .map_err(|_| "not_configured")?;
+            tokio::task::spawn_blocking(move || {
+                use webpipe_local::semantic::Embedder;
+                model.embed(&texts)
+            })
+            .await
+            .map_err(|_| "request_failed")?
+            .map_err(|_| "request_failed")
+        }
+
+        /// The semantic rerank entry point for tools (`semantic_rerank=true`).
+        ///
+        /// Never errors. When semantic is unavailable the result is `ok=false` with the
+        /// `semantic_unavailable` warning (and `unavailable_reason`), and callers keep their
+        /// lexical chunk order. Bounded by `WEBPIPE_SEMANTIC_TIMEOUT_MS` (default:
+        /// `default_timeout_ms`; `0` skips with `semantic_rerank_timeout`).
+        async fn semantic_rerank_or_fallback(
+            &self,
+            query: &str,
+            candidates: &[(usize, usize, String)],
+            top_k: usize,
+            default_timeout_ms: u64,
+        ) -> webpipe_local::semantic::SemanticRerankResult {
+            use webpipe_local::semantic::SemanticRerankResult;
+            if query.trim().is_empty() || candidates.is_empty() {
+                return webpipe_local::semantic::semantic_rerank_chunks(query, candidates, top_k);
+            }
+            let timeout_ms = std::env::var("WEBPIPE_SEMANTIC_TIMEOUT_MS")
+                .ok()
+                .and_then(|s| s.trim().parse::<u64>().ok())
+                .unwrap_or(default_timeout_ms);
+            if timeout_ms == 0 {
+                return SemanticRerankResult::skipped("semantic_rerank_timeout");
+            }
+            if let Some(reason) = Self::semantic_unavailable_reason() {
+                return SemanticRerankResult::unavailable(reason);
+            }
+            tokio::time::timeout(
+                std::time::Duration::from_millis(timeout_ms),
+                self.semantic_rerank_chunks_best(query, candidates, top_k),
+            )
+            .await
+            .unwrap_or_else(|_| SemanticRerankResult::skipped("semantic_rerank_timeout"))
+        }
+
+        /// Embed `texts` with the configured embeddings backend (OpenRouter, or the local model
+        /// with `WEBPIPE_EMBED_BACKEND=local`), reusing vectors already in `embeddings_cache` or
+        /// on disk (`embeddings_disk`). Returns `(vectors, cache_hits, cache_misses, model)`;
+        /// `Err` carries a short reason (`not_configured`, `request_failed`, `bad_shape`).
+        async fn embed_texts_cached(
+            &self,
+            texts: &[String],
+        ) -> Result<(Vec<Vec<f32>>, u64, u64, String), &'static str> {
+            let t0 = std::time::Instant::now();
+            let local = Self::embed_backend_from_env() == "local";
+            let (api_key, model) = if local {
+                (
+                    None,
+                    Self::local_embeddings_model_id().ok_or("not_configured")?,
+                )
+            } else {
+                (
+                    Some(Self::openrouter_api_key_from_env().ok_or("not_configured")?),
+                    Self::openrouter_embeddings_model_from_env(),
+                )
+            };
+            let label = Self::embeddings_backend_label();
+            let key = |t: &str| format!("{model}\n{t}");
+            let mut out: Vec<Option<Vec<f32>>> = {
+                let cache = self
+                    .embeddings_cache
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                texts.iter().map(|t| cache.get(&key(t)).cloned()).collect()
+            };
+            if let Some(disk) = self.embeddings_disk.as_ref() {
+                let wanted: Vec<String> = texts
+                    .iter()
+                    .zip(&out)
+                    .filter(|(_, v)| v.is_none())
+                    .map(|(t, _)| t.clone())
+                    .collect();
+                if !wanted.is_empty() {
+                    let mut found = disk.lookup(&model, &wanted).into_iter();
+                    let mut cache = self
+                        .embeddings_cache
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner());
+                    for (t, slot) in texts.iter().zip(out.iter_mut()) {
+                        if slot.is_none() {
+                            if let Some(v) = found.next().flatten() {
+                                if cache.len() < EMBEDDINGS_CACHE_MAX_ENTRIES {
+                                    cache.insert(key(t), v.clone());
+                                }
+                                *slot = Some(v);
+                            }
+                        }
+                    }
+                }
+            }
+            let missing: Vec<String> = texts
+                .iter()
+                .zip(&out)
+                .filter(|(_, v)| v.is_none())
+                .map(|(t, _)| t.clone())
+                .collect();
+            let hits = (texts.len() - missing.len()) as u64;
+            let misses = missing.len() as u64;
+            if !missing.is_empty() {
+                let res = if local {
+                    #[cfg(feature = "embed-local")]
+                    {
+                        self.local_embed(missing.clone()).await
+                    }
+                    #[cfg(not(feature = "embed-local"))]
+                    {
+                        Err("not_configured")
+                    }
+                } else {
+                    let client = webpipe_local::openai_compat::OpenAiCompatClient::new(
+                        self.http.clone(),
+                        Self::openrouter_base_url_from_env(),
+                        api_key,
+                        model.clone(),
+                    )
+                    .map_err(|_| "not_configured")?;
+                    let timeout_ms = Self::openrouter_embeddings_timeout_ms_from_env();
+                    client
+                        .embeddings(missing.clone(), timeout_ms)
+                        .await
+                        .map_err(|_| "request_failed")
+                };
+                let embs = match res {
+                    Ok(v) if v.len() == missing.len() => v,
+                    r => {
+                        self.stats_record_llm_backend(
+                            label,
+                            false,
+                            t0.elapsed().as_millis() as u64,
+                            None,
+                        );
+                        return Err(match r {
+                            Ok(_) => "bad_shape",
+                            Err(reason) => reason,
+                        });
+                    }
+                };
+                self.stats_record_llm_backend_units(
+                    label,
+                    true,
+                    misses,
+                    t0.elapsed().as_millis() as u64,
+                    None,
+                );
+                if let Some(disk) = self.embeddings_disk.as_ref() {
+                    disk.store(&model, &missing, &embs);
+                }
+                let mut cache = self
+                    .embeddings_cache
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                if cache.len() + embs.len() > EMBEDDINGS_CACHE_MAX_ENTRIES {
+                    cache.clear();
+                }
+                let mut fresh = missing.iter().zip(embs);
+                for slot in out.iter_mut().filter(|v| v.is_none()) {
+                    if let Some((t, e)) = fresh.next() {
+                        cache.insert(key(t), e.clone());
+                        *slot = Some(e);
+                    }
+                }
+            }
+            Ok((out.into_iter().flatten().collect(), hits, misses, model))
+        }
+
+        /// Keyphrases/entities for one document, memoized in `keywords_cache` by `doc_id`.
+        /// Recomputed when the text or `top_n` changed. Returns `(index, cache_hit)`.
+        async fn keyword_index_cached(
+            &self,
+            doc_id: &str,
+            text: &str,
+            top_n: usize,
+        ) -> (webpipe_local::keywords::KeywordIndex, bool) {
+            use std::hash::{Hash, Hasher};
+            let mut h = std::collections::hash_map::DefaultHasher::new();
+            (text, top_n).hash(&mut h);
+            let fingerprint = h.finish();
+            {
+                let cache = self
+                    .keywords_cache
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                if let Some((f, idx)) = cache.get(doc_id) {
+                    if *f == fingerprint {
+                        return (idx.clone(), true);
+                    }
+                }
+            }
+            let text = text.to_string();
+            let idx = tokio::task::spawn_blocking(move || {
+                webpipe_local::keywords::keyword_index(&text, top_n)
+            })
+            .await
+            .unwrap_or_default();
+            let mut cache = self
+                .keywords_cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            if cache.len() >= KEYWORDS_CACHE_MAX_ENTRIES {
+                cache.clear();
+            }
+            cache.insert(doc_id.to_string(), (fingerprint, idx.clone()));
+            (idx, false)
+        }
+
+        /// Re-rank `web_cache_search_extract` results with scoring="hybrid" (in place).
+        ///
+        /// Embeds the query plus each doc's leading chunks (bounded by
+        /// `WEBPIPE_SEMANTIC_EMBEDDINGS_MAX_INPUTS`), then scores chunks and docs as
+        /// `w * semantic + (1 - w) * lexical`. A doc's semantic score is its best chunk's. Docs
+        /// that end at 0 are dropped. `Err` is the reason to fall back to lexical.
+        async fn apply_hybrid_cache_scoring(
+            &self,
+            query: &str,
+            docs: &mut Vec<serde_json::Value>,
+            w: f64,
+        ) -> Result<serde_json::Value, &'static str> {
+            const CHUNKS_PER_DOC: usize = 3;
+            if !Self::embeddings_configured() {
+                return Err("embeddings_not_configured");
+            }
+            let max_inputs = Self::semantic_embeddings_max_inputs_from_env();
+            let mut cands: Vec<(usize, usize)> = Vec::new();
+            let mut inputs: Vec<String> = vec![query.to_string()];
+            let mut prefiltered = false;
+            for (di, d) in docs.iter().enumerate() {
+                let chunks = d.get("chunks").and_then(|v| v.as_array());
+                for (ci, c) in chunks.into_iter().flatten().enumerate() {
+                    if ci >= CHUNKS_PER_DOC || inputs.len() >= max_inputs {
+                        prefiltered = true;
+                        break;
+                    }
+                    let t = c.get("text").and_then(|v| v.as_str()).unwrap_or("");
+                    inputs.push(t.chars().take(1200).collect());
+                    cands.push((di, ci));
+                }
+            }
+            if cands.is_empty() {
+                return Ok(serde_json::json!({
+                    "mode": "hybrid",
+                    "requested": "hybrid",
+                    "semantic_weight": w,
+                    "embedded_chunks": 0,
+                }));
+            }
+            let (embs, cache_hits, cache_misses, model) = self.embed_texts_cached(&inputs).await?;
+            let cos: Vec<f64> = embs[1..]
+                .iter()
+                .map(|e| Self::cosine_similarity(&embs[0], e) as f64)
+                .collect();
+            let lo = cos.iter().copied().fold(f64::INFINITY, f64::min);
+            let hi = cos.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let norm = |c: f64| if hi > lo { (c - lo) / (hi - lo) } else { 1.0 };
+            let round4 = |x: f64| (x * 10_000.0).round() / 10_000.0;
+
+            let mut chunk_sem: std::collections::HashMap<(usize, usize), f64> =
+                std::collections::HashMap::new();
+            for (&k, &c) in cands.iter().zip(&cos) {
+                chunk_sem.insert(k, norm(c));
+            }
+            let score_of =
+                |v: &serde_json::Value| v.get("score").and_then(|x| x.as_f64()).unwrap_or(0.0);
+            let max_doc = docs.iter().map(score_of).fold(0.0, f64::max);
+            for (di, d) in docs.iter_mut().enumerate() {
+                let doc_lex = if max_doc > 0.0 {
+                    score_of(d) / max_doc
+                } else {
+                    0.0
+                };
+                let mut doc_sem = 0.0f64;
+                if let Some(chunks) = d.get_mut("chunks").and_then(|v| v.as_array_mut()) {
+                    let max_chunk = chunks.iter().map(score_of).fold(0.0, f64::max);
+                    for (ci, c) in chunks.iter_mut().enumerate() {
+                        // Query-less filler chunks (doc score 0) carry no lexical evidence.
+                        let lex = if doc_lex > 0.0 && max_chunk > 0.0 {
+                            score_of(c) / max_chunk
+                        } else {
+                            0.0
+                        };
+                        let sem = chunk_sem.get(&(di, ci)).copied().unwrap_or(0.0);
+                        doc_sem = doc_sem.max(sem);
+                        c["hybrid_score"] = serde_json::json!(round4(w * sem + (1.0 - w) * lex));
+                    }
+                    chunks.sort_by(|a, b| {
+                        let sa = a["hybrid_score"].as_f64().unwrap_or(0.0);
+                        let sb = b["hybrid_score"].as_f64().unwrap_or(0.0);
+                        sb.partial_cmp(&sa).unwrap_or(std::cmp::Ordering::Equal)
+                    });
+                }
+                d["hybrid"] = serde_json::json!({
+                    "score": round4(w * doc_sem + (1.0 - w) * doc_lex),
+                    "lexical": round4(doc_lex),
+                    "semantic": round4(doc_sem),
+                });
+            }
+            let hybrid_of = |v: &serde_json::Value| v["hybrid"]["score"].as_f64().unwrap_or(0.0);
+            docs.retain(|d| hybrid_of(d) > 0.0);
+            docs.sort_by(|a, b| {
+                hybrid_of(b)
+                    .partial_cmp(&hybrid_of(a))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then_with(|| {
+                        let ua = a.get("url").and_then(|v| v.as_str()).unwrap_or("");
+                        let ub = b.get("url").and_then(|v| v.as_str()).unwrap_or("");
+                        ua.cmp(ub)
+                    })
+            });
+            Ok(serde_json::json!({
+                "mode": "hybrid",
+                "requested": "hybrid",
+                "semantic_weight": w,
+                "backend": Self::embeddings_backend_label(),
+                "model_id": model,
+                "embedded_chunks": cands.len(),
+                "prefiltered": prefiltered,
+                "cache_hits": cache_hits,
+                "cache_misses": cache_misses,
+            }))
+        }
+
         fn stats_set_last_search_outcome_junk_level_qk(
             &self,
             name: &str,
@@ -7349,16 +9857,136 @@ mod mcp {
             );
         }
 
+        /// Fetch and extract the iframes of the page at `page_url` for `follow_iframes`.
+        ///
+        /// Returns a report entry per iframe source (in document order) and the `(url, text)` of
+        /// the frames worth merging. Frames beyond `max_iframes` are reported, not fetched.
+        async fn fetch_iframe_texts(
+            &self,
+            page_url: &str,
+            srcs: &[String],
+            allow_cross_origin: bool,
+            max_iframes: usize,
+            page_req: &FetchRequest,
+            width: usize,
+        ) -> (Vec<serde_json::Value>, Vec<(String, String)>) {
+            let page_origin = reqwest::Url::parse(page_url).ok().map(|u| u.origin());
+            let page_internal = url_is_internal_network(page_url);
+            let mut report = Vec::new();
+            let mut to_fetch = Vec::new();
+            for src in srcs {
+                let same_origin = reqwest::Url::parse(src)
+                    .ok()
+                    .is_some_and(|u| Some(u.origin()) == page_origin);
+                let skipped = if !same_origin && !allow_cross_origin {
+                    Some("cross_origin")
+                } else if !same_origin && !page_internal && url_is_internal_network(src) {
+                    Some("internal_network")
+                } else if to_fetch.len() >= max_iframes {
+                    Some("max_iframes")
+                } else {
+                    None
+                };
+                match skipped {
+                    Some(reason) => report.push(serde_json::json!({
+                        "url": src, "same_origin": same_origin, "skipped": reason
+                    })),
+                    None => {
+                        to_fetch.push((report.len(), src.clone()));
+                        report.push(serde_json::json!({ "url": src, "same_origin": same_origin }));
+                    }
+                }
+            }
+
+            let fetches = to_fetch.into_iter().map(|(i, src)| {
+                let req = FetchRequest {
+                    url: src.clone(),
+                    timeout_ms: page_req.timeout_ms,
+                    max_bytes: page_req.max_bytes,
+                    headers: BTreeMap::from([("Referer".to_string(), page_url.to_string())]),
+                    method: None,
+                    body: None,
+                    cache: page_req.cache.clone(),
+                };
+                async move {
+                    let r = self.fetcher.fetch(&req).await;
+                    let out = match r {
+                        Ok(resp) if (200..300).contains(&resp.status) => {
+                            let ct = resp.content_type.clone();
+                            let final_url = resp.final_url.clone();
+                            let status = resp.status;
+                            let ex = tokio::task::spawn_blocking(move || {
+                                webpipe_local::extract::best_effort_text_from_bytes(
+                                    &resp.bytes,
+                                    ct.as_deref(),
+                                    &final_url,
+                                    width,
+                                    2_000,
+                                )
+                            })
+                            .await
+                            .ok();
+                            let text = ex.map(|e| e.text).unwrap_or_default();
+                            Ok((status, text))
+                        }
+                        Ok(resp) => Err(format!("http_{}", resp.status)),
+                        Err(e) => Err(e.to_string()),
+                    };
+                    (i, src, out)
+                }
+            });
+            let mut frames = Vec::new();
+            for (i, src, out) in futures::future::join_all(fetches).await {
+                match out {
+                    Ok((status, text)) if !text.trim().is_empty() => {
+                        report[i]["status"] = serde_json::json!(status);
+                        report[i]["chars"] = serde_json::json!(text.chars().count());
+                        report[i]["merged"] = serde_json::json!(true);
+                        frames.push((src, text));
+                    }
+                    Ok((status, _)) => {
+                        report[i]["status"] = serde_json::json!(status);
+                        report[i]["skipped"] = serde_json::json!("empty_extraction");
+                    }
+                    Err(e) => {
+                        report[i]["skipped"] = serde_json::json!("fetch_failed");
+                        report[i]["error"] = serde_json::json!(e);
+                    }
+                }
+            }
+            (report, frames)
+        }
+
         fn stats_record_fetch_backend(
             &self,
             name: &str,
             ok: bool,
             elapsed_ms: u64,
             err: Option<&str>,
+        ) {
+            self.stats_record_fetch_backend_units(name, ok, 0, elapsed_ms, err);
+        }
+
+        /// [`Self::stats_record_fetch_backend`] for calls that cost more than one unit (e.g. a
+        /// Firecrawl crawl job billed per page).
+        fn stats_record_fetch_backend_units(
+            &self,
+            name: &str,
+            ok: bool,
+            cost_units: u64,
+            elapsed_ms: u64,
+            err: Option<&str>,
         ) {
             let http_429 = err.is_some_and(|m| is_http_status(m, 429));
             let mut s = self.stats_lock();
-            Self::stats_record_provider(&mut s.fetch_backends, name, ok, 0, elapsed_ms, http_429);
+            Self::stats_record_provider(
+                &mut s.fetch_backends,
+                name,
+                ok,
+                cost_units,
+                elapsed_ms,
+                http_429,
+            );
         }
 
         #[allow(clippy::too_many_arguments)]
@@ -7383,10 +10011,14 @@ mod mcp {
                     timeout_ms: Some(timeout_ms.min(10_000)),
                     max_bytes: Some(max_bytes.min(1_000_000)),
                     headers: BTreeMap::new(),
+                    method: None,
+                    body: None,
                     cache: FetchCachePolicy {
                         read: cache_read || no_network,
                         write: if no_network { false } else { cache_write },
                         ttl_s: cache_ttl_s,
+                        read_non_idempotent: false,
+                        stale_while_revalidate_s: None,
                     },
                 };
                 let t0 = std::time::Instant::now();
@@ -7599,6 +10231,300 @@ mod mcp {
             )
         }
 
+        /// Fetch and extract one paper for `arxiv_search(include_fulltext=true)`.
+        ///
+        /// Prefers the feed's PDF link (else the abs→PDF rewrite). Failures come back as an
+        /// `ok=false` object so one bad paper never fails the search.
+        async fn arxiv_paper_fulltext(
+            &self,
+            paper: &webpipe_local::arxiv::ArxivPaper,
+            max_chars: usize,
+            timeout_ms: u64,
+        ) -> serde_json::Value {
+            let source_url = paper
+                .pdf_url
+                .clone()
+                .or_else(|| {
+                    webpipe_local::rewrite::arxiv_abs_pdf_candidates(&paper.url)
+                        .and_then(|c| c.into_iter().next())
+                })
+                .unwrap_or_else(|| paper.url.clone());
+            let req = FetchRequest {
+                url: source_url.clone(),
+                timeout_ms: Some(timeout_ms),
+                max_bytes: Some(20_000_000),
+                headers: BTreeMap::new(),
+                method: None,
+                body: None,
+                cache: FetchCachePolicy {
+                    read: true,
+                    write: true,
+                    ttl_s: None,
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
+                },
+            };
+            let resp = match self.fetcher.fetch(&req).await {
+                Ok(r) => r,
+                Err(e) => {
+                    return serde_json::json!({
+                        "ok": false,
+                        "source_url": source_url,
+                        "error": error_obj(
+                            ErrorCode::FetchFailed,
+                            e.to_string(),
+                            "Full text could not be fetched; the paper metadata is still usable."
+                        )
+                    });
+                }
+            };
+            let mut out = serde_json::json!({
+                "ok": true,
+                "source_url": source_url,
+                "final_url": resp.final_url,
+                "status": resp.status,
+                "content_type": resp.content_type,
+                "fetch_source": match resp.source {
+                    FetchSource::Cache => "cache",
+                    FetchSource::Network => "network",
+                },
+            });
+            if Self::http_status_is_error(resp.status) {
+                Self::mark_http_status_error(&mut out, resp.status);
+                return out;
+            }
+            let extract_timeout_ms = std::env::var("WEBPIPE_EXTRACT_PIPELINE_TIMEOUT_MS")
+                .ok()
+                .and_then(|s| s.trim().parse::<u64>().ok())
+                .unwrap_or(12_000);
+            let final_url = resp.final_url.clone();
+            let content_type = resp.content_type.clone();
+            let bytes = resp.bytes;
+            let handle = tokio::task::spawn_blocking(move || {
+                webpipe_local::extract::extract_pipeline_from_bytes(
+                    bytes.as_ref(),
+                    content_type.as_deref(),
+                    final_url.as_str(),
+                    webpipe_local::extract::ExtractPipelineCfg {
+                        query: None,
+                        width: 100,
+                        max_chars,
+                        top_chunks: 0,
+                        max_chunk_chars: 500,
+                        include_structure: false,
+                        max_outline_items: 0,
+                        max_blocks: 0,
+                        max_block_chars: 0,
+                        truncation_strategy: webpipe_local::extract::TruncationStrategy::Head,
+                    },
+                )
+            });
+            let pipeline = match tokio::time::timeout(
+                std::time::Duration::from_millis(extract_timeout_ms),
+                handle,
+            )
+            .await
+            {
+                Ok(Ok(p)) => p,
+                Ok(Err(_)) | Err(_) => {
+                    out["ok"] = serde_json::json!(false);
+                    out["error"] = error_obj(
+                        ErrorCode::UnexpectedError,
+                        "full-text extraction failed or timed out",
+                        "Retry, or raise WEBPIPE_EXTRACT_PIPELINE_TIMEOUT_MS for large PDFs.",
+                    );
+                    return out;
+                }
+            };
+            let mut warnings = pipeline.extracted.warnings.clone();
+            if pipeline.text_truncated {
+                warnings.push("text_truncated_by_max_chars");
+            }
+            if pipeline.text_chars == 0 {
+                out["ok"] = serde_json::json!(false);
+                warnings.push("empty_extraction");
+                out["error"] = error_obj(
+                    ErrorCode::UnexpectedError,
+                    "no text could be extracted",
+                    "The document may be scanned or image-only; read the abstract instead.",
+                );
+            }
+            out["engine"] = serde_json::json!(pipeline.extracted.engine);
+            out["text"] = serde_json::json!(pipeline.extracted.text);
+            out["text_chars"] = serde_json::json!(pipeline.text_chars);
+            out["text_truncated"] = serde_json::json!(pipeline.text_truncated);
+            out["warnings"] = serde_json::json!(warnings);
+            out
+        }
+
+        /// Fetch and extract one discussion page for `arxiv_enrich`, keeping the chunk that best
+        /// matches `query` as its snippet. Never fails: errors are reported in the returned object.
+        async fn arxiv_discussion_page(
+            &self,
+            url: String,
+            search_rank: usize,
+            query: String,
+            timeout_ms: u64,
+        ) -> serde_json::Value {
+            let req = FetchRequest {
+                url: url.clone(),
+                timeout_ms: Some(timeout_ms),
+                max_bytes: Some(2_000_000),
+                headers: BTreeMap::new(),
+                method: None,
+                body: None,
+                cache: FetchCachePolicy {
+                    read: true,
+                    write: true,
+                    ttl_s: None,
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
+                },
+            };
+            let resp = match self.fetcher.fetch(&req).await {
+                Ok(r) => r,
+                Err(e) => {
+                    return serde_json::json!({
+                        "ok": false,
+                        "url": url,
+                        "search_rank": search_rank,
+                        "score": 0,
+                        "error": error_obj(
+                            ErrorCode::FetchFailed,
+                            e.to_string(),
+                            "This discussion page could not be fetched; the others are unaffected."
+                        )
+                    });
+                }
+            };
+            let mut out = serde_json::json!({
+                "ok": true,
+                "url": url,
+                "search_rank": search_rank,
+                "final_url": resp.final_url,
+                "status": resp.status,
+                "fetch_source": match resp.source {
+                    FetchSource::Cache => "cache",
+                    FetchSource::Network => "network",
+                },
+                "score": 0,
+            });
+            if Self::http_status_is_error(resp.status) {
+                Self::mark_http_status_error(&mut out, resp.status);
+                return out;
+            }
+            let final_url = resp.final_url.clone();
+            let content_type = resp.content_type.clone();
+            let bytes = resp.bytes;
+            let handle = tokio::task::spawn_blocking(move || {
+                let title = webpipe_local::crawl::page_title(&String::from_utf8_lossy(&bytes));
+                let pipeline = webpipe_local::extract::extract_pipeline_from_bytes(
+                    bytes.as_ref(),
+                    content_type.as_deref(),
+                    final_url.as_str(),
+                    webpipe_local::extract::ExtractPipelineCfg {
+                        query: Some(query.as_str()),
+                        width: 100,
+                        max_chars: 50_000,
+                        top_chunks: 1,
+                        max_chunk_chars: 400,
+                        include_structure: false,
+                        max_outline_items: 0,
+                        max_blocks: 0,
+                        max_block_chars: 0,
+                        truncation_strategy: webpipe_local::extract::TruncationStrategy::Head,
+                    },
+                );
+                (title, pipeline)
+            });
+            let (title, pipeline) =
+                match tokio::time::timeout(std::time::Duration::from_millis(timeout_ms), handle)
+                    .await
+                {
+                    Ok(Ok(r)) => r,
+                    Ok(Err(_)) | Err(_) => {
+                        out["ok"] = serde_json::json!(false);
+                        out["error"] = error_obj(
+                            ErrorCode::UnexpectedError,
+                            "discussion extraction failed or timed out",
+                            "Fetch the URL with web_extract for a closer look.",
+                        );
+                        return out;
+                    }
+                };
+            if pipeline.text_chars == 0 {
+                out["ok"] = serde_json::json!(false);
+                out["warnings"] = serde_json::json!(["empty_extraction"]);
+                out["error"] = error_obj(
+                    ErrorCode::UnexpectedError,
+                    "no text could be extracted",
+                    "The page may be script-rendered; try web_extract with fetch_backend=\"firecrawl\".",
+                );
+                return out;
+            }
+            // No chunk matches the paper: fall back to the page head (scored 0).
+            let (snippet, score) = match pipeline.chunks.first() {
+                Some(c) => (c.text.clone(), c.score),
+                None => (Self::truncate_to_chars(&pipeline.extracted.text, 400).0, 0),
+            };
+            out["title"] = serde_json::json!(title);
+            out["snippet"] = serde_json::json!(snippet);
+            out["score"] = serde_json::json!(score);
+            out
+        }
+
+        /// Fetch the discussion URLs found by `discussion_search` (at most `concurrency` at a time)
+        /// and rank them by snippet relevance to `query`. Returns `(discussions, summary)`.
+        async fn arxiv_fetch_discussions(
+            &self,
+            discussion_search: &serde_json::Value,
+            query: &str,
+            concurrency: usize,
+            timeout_ms: u64,
+        ) -> (Vec<serde_json::Value>, serde_json::Value) {
+            use futures::StreamExt;
+            let urls: Vec<String> = discussion_search["results"]
+                .as_array()
+                .map(|rs| {
+                    rs.iter()
+                        .filter_map(|r| r["url"].as_str())
+                        .map(|u| u.trim().to_string())
+                        .filter(|u| !u.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+            let mut discussions: Vec<serde_json::Value> =
+                futures::stream::iter(urls.into_iter().enumerate().map(|(i, u)| {
+                    self.arxiv_discussion_page(u, i + 1, query.to_string(), timeout_ms)
+                }))
+                .buffer_unordered(concurrency)
+                .collect()
+                .await;
+            // Fetched pages first, then by relevance, then in search order.
+            discussions.sort_by(|a, b| {
+                let key = |d: &serde_json::Value| {
+                    (
+                        d["ok"].as_bool() == Some(true),
+                        d["score"].as_u64().unwrap_or(0),
+                    )
+                };
+                key(b)
+                    .cmp(&key(a))
+                    .then_with(|| a["search_rank"].as_u64().cmp(&b["search_rank"].as_u64()))
+            });
+            let fetched = discussions
+                .iter()
+                .filter(|d| d["ok"].as_bool() == Some(true))
+                .count();
+            let summary = serde_json::json!({
+                "requested": discussions.len(),
+                "fetched": fetched,
+                "failed": discussions.len() - fetched,
+                "concurrency": concurrency,
+            });
+            (discussions, summary)
+        }
+
         fn stats_record_warnings(&self, warnings: &[&'static str]) {
             let mut s = self.stats_lock();
             for &w in warnings {
@@ -7608,6 +10534,50 @@ mod mcp {
             }
         }
 
+        /// `web_fetch`'s `metadata` object (`{}` for PDFs).
+        async fn fetch_metadata_json(
+            resp: &webpipe_core::FetchResponse,
+            is_pdf_like: bool,
+        ) -> serde_json::Value {
+            if is_pdf_like {
+                return serde_json::json!({});
+            }
+            let html = resp.text_lossy().to_string();
+            let final_url = resp.final_url.clone();
+            tokio::task::spawn_blocking(move || page_metadata_json(&html, &final_url))
+                .await
+                .unwrap_or_else(|_| serde_json::json!({}))
+        }
+
+        /// Add `body_sha256` to a `web_fetch` payload, plus `changed` when the caller passed the
+        /// hash it already has; an unchanged body is not sent again.
+        fn apply_body_fingerprint(
+            payload: &mut serde_json::Value,
+            body: &[u8],
+            known_sha256: Option<&str>,
+        ) {
+            let sha = webpipe_local::doc_id::body_sha256(body);
+            if let Some(known) = known_sha256 {
+                let changed = !known.trim().eq_ignore_ascii_case(&sha);
+                payload["changed"] = serde_json::json!(changed);
+                if !changed {
+                    if let Some(o) = payload.as_object_mut() {
+                        o.remove("body_text");
+                    }
+                }
+            }
+            payload["body_sha256"] = serde_json::json!(sha);
+        }
+
+        /// Tag each per-URL result that has a `url` with its stable `doc_id`.
+        fn attach_doc_ids(results: &mut [serde_json::Value]) {
+            for r in results {
+                if let Some(u) = r.get("url").and_then(|v| v.as_str()) {
+                    r["doc_id"] = serde_json::json!(webpipe_local::doc_id::doc_id(u));
+                }
+            }
+        }
+
         fn truncate_to_chars(s: &str, max_chars: usize) -> (String, usize, bool) {
             if max_chars == 0 {
                 return (String::new(), 0, !s.is_empty());
@@ -7639,6 +10609,133 @@ mod mcp {
                 .starts_with("application/pdf")
         }
 
+        /// Default `exploration` preset for a query intent (search mode, preset unset).
+        ///
+        /// Only survey-style queries change the default: they want breadth (`wide`). Factoid,
+        /// how-to, navigational, and code queries keep `balanced` (few URLs, focused chunks).
+        fn exploration_preset_for_intent(intent: webpipe_local::textprep::Intent) -> &'static str {
+            use webpipe_local::textprep::Intent;
+            match intent {
+                Intent::Survey => "wide",
+                Intent::Factoid | Intent::HowTo | Intent::Navigational | Intent::Code => "balanced",
+            }
+        }
+
+        /// Check a response against `web_fetch.expect_content_type` (case-insensitive prefix match).
+        ///
+        /// Matches if either the declared `Content-Type` (mime essence) or the sniffed body type
+        /// starts with `expected`. On mismatch returns `(actual, sniffed)`, where `actual` is the
+        /// declared type (or the sniffed one when nothing was declared).
+        fn content_type_mismatch(
+            expected: &str,
+            declared: Option<&str>,
+            body: &[u8],
+        ) -> Option<(String, Option<&'static str>)> {
+            let expected = expected.trim().to_ascii_lowercase();
+            if expected.is_empty() {
+                return None;
+            }
+            let declared = declared
+                .map(|ct| {
+                    ct.split(';')
+                        .next()
+                        .unwrap_or("")
+                        .trim()
+                        .to_ascii_lowercase()
+                })
+                .filter(|ct| !ct.is_empty());
+            let sniffed = if webpipe_local::extract::bytes_look_like_pdf(body) {
+                Some("application/pdf")
+            } else if webpipe_local::extract::bytes_look_like_html(body) {
+                Some("text/html")
+            } else if matches!(
+                body.strip_prefix(b"\xEF\xBB\xBF")
+                    .unwrap_or(body)
+                    .iter()
+                    .find(|b| !b.is_ascii_whitespace()),
+                Some(b'{') | Some(b'[')
+            ) {
+                Some("application/json")
+            } else {
+                None
+            };
+            let matches = declared
+                .as_deref()
+                .is_some_and(|ct| ct.starts_with(&expected))
+                || sniffed.is_some_and(|ct| ct.starts_with(&expected));
+            if matches {
+                return None;
+            }
+            let actual = declared
+                .or_else(|| sniffed.map(str::to_string))
+                .unwrap_or_else(|| "unknown".to_string());
+            Some((actual, sniffed))
+        }
+
+        fn content_type_mismatch_payload(
+            url: &str,
+            final_url: &str,
+            status: u16,
+            expected: &str,
+            actual: &str,
+            sniffed: Option<&str>,
+        ) -> serde_json::Value {
+            serde_json::json!({
+                "ok": false,
+                "url": url,
+                "final_url": final_url,
+                "status": status,
+                "content_type": actual,
+                "expected_content_type": expected,
+                "sniffed_content_type": sniffed,
+                "error": error_obj(
+                    ErrorCode::ContentTypeMismatch,
+                    format!("expected content type {expected:?}, got {actual:?}"),
+                    "The server returned a different kind of document (often an HTML error or login page). Check status/final_url, or drop expect_content_type to inspect the body."
+                )
+            })
+        }
+
+        /// Final HTTP status that should fail the call: anything non-2xx once redirects were followed.
+        fn http_status_is_error(status: u16) -> bool {
+            !(200..300).contains(&status)
+        }
+
+        /// Flip a fetch/extract payload to `ok:false` with an `http_status` error.
+        ///
+        /// Warnings stay as-is; callers decide which body fields survive (`include_error_body`).
+        fn mark_http_status_error(payload: &mut serde_json::Value, status: u16) {
+            let mut error = error_obj(
+                ErrorCode::HttpStatus,
+                format!("HTTP status {status}"),
+                "The server returned an error page instead of content. Check status/final_url, or set include_error_body=true to inspect the body.",
+            );
+            error["status"] = serde_json::json!(status);
+            error["retryable"] = serde_json::json!(status == 429 || status >= 500);
+            payload["ok"] = serde_json::json!(false);
+            payload["error"] = error;
+        }
+
+        /// Drop page-derived fields from an `extract` object (error pages are not evidence).
+        fn strip_error_body_from_extract(extract: &mut serde_json::Value) {
+            if let Some(ex) = extract.as_object_mut() {
+                for k in [
+                    "text",
+                    "text_preview",
+                    "structure",
+                    "sections",
+                    "semantic",
+                    "links",
+                    "entities",
+                ] {
+                    ex.remove(k);
+                }
+                if ex.contains_key("chunks") {
+                    ex.insert("chunks".to_string(), serde_json::json!([]));
+                }
+            }
+        }
+
         fn url_looks_like_pdf(url: &str) -> bool {
             let u = url.trim();
             if u.is_empty() {
@@ -7656,45 +10753,12 @@ mod mcp {
             }
         }
 
-        fn looks_like_bundle_gunk(s: &str) -> bool {
-            // Common “JS app shell” signatures we saw in the wild (e.g. Next.js hydration payloads).
-            let t = s.trim();
-            if t.is_empty() {
-                return false;
-            }
-            let lc = t.to_ascii_lowercase();
-            // Check for common blocking/challenge messages
-            if lc.contains("please enable javascript")
-                || lc.contains("enable javascript to continue")
-                || lc.contains("javascript is required")
-                || lc.contains("browser does not support javascript")
-                || lc.contains("checking your browser")
-                || lc.contains("cloudflare") && lc.contains("ray id")
-                || lc.contains("verify you are human")
-                || lc.contains("pardon our interruption")
-                || lc.contains("turn on javascript")
-                || lc.contains("cookie") && lc.contains("consent") && t.len() < 1000
-                || lc.contains("403 forbidden") && t.len() < 500
-                || lc.contains("access denied") && t.len() < 500
-            {
-                return true;
-            }
-
-            lc.contains("self.__next_s")
-                || lc.contains("suppresshydrationwarning")
-                || lc.contains("__webpack")
-                || lc.contains("webpackchunk")
-                || lc.contains("window.__nuxt")
-                || lc.contains("(function(a,b,c,d)")
-                || lc.contains(".push([0,{\"suppresshydrationwarning\"")
-        }
-
         fn chunk_is_low_signal(c: &webpipe_local::extract::ScoredChunk) -> bool {
             let t = c.text.trim();
             if t.is_empty() {
                 return true;
             }
-            if Self::looks_like_bundle_gunk(t) {
+            if webpipe_local::extract::looks_like_bundle_gunk(t) {
                 return true;
             }
             // Lightweight “readability” heuristic:
@@ -7760,7 +10824,7 @@ mod mcp {
                     "http_rate_limited" => p += 60,
                     "main_content_low_signal" => p += 25,
                     "chunks_filtered_low_signal" => p += 15,
-                    "body_truncated_by_max_bytes" => p += 12,
+                    "body_truncated_by_max_bytes" | "streaming_capped" => p += 12,
                     "text_truncated_by_max_chars" => p += 8,
                     "retried_due_to_truncation" => p += 3,
                     "truncation_retry_used" => p += 3,
@@ -7811,7 +10875,8 @@ mod mcp {
             let text_chars = extracted_text.chars().count();
             let nonempty = extracted_text.chars().any(|c| !c.is_whitespace());
             let js_challenge = nonempty && looks_like_js_challenge(status, extracted_text, None);
-            let bundle_gunk = nonempty && Self::looks_like_bundle_gunk(extracted_text);
+            let bundle_gunk =
+                nonempty && webpipe_local::extract::looks_like_bundle_gunk(extracted_text);
             let has_empty = warnings
                 .iter()
                 .any(|w| normalize_warning_code(w) == "empty_extraction");
@@ -7943,6 +11008,7 @@ mod mcp {
             }
 
             match selection_mode {
+                "mmr" => Self::select_top_chunks_mmr(candidates, top_k, MMR_DEFAULT_LAMBDA, None),
                 "pareto" => {
                     // Multi-objective selection:
                     // - maximize score
@@ -8030,6 +11096,356 @@ mod mcp {
             }
         }
 
+        /// [`Self::select_top_chunks`] for tool calls. For `mmr` this applies `mmr_lambda` and,
+        /// when `semantic` is on and an embeddings backend is available, scores similarity with
+        /// chunk embeddings (cached like semantic rerank); otherwise it uses word overlap. The
+        /// second value summarizes an `mmr` run for the payload (`None` for other modes).
+        async fn select_top_chunks_with(
+            &self,
+            candidates: Vec<ChunkCandidate>,
+            top_k: usize,
+            selection_mode: &str,
+            mmr_lambda: Option<f64>,
+            semantic: bool,
+        ) -> (Vec<ChunkCandidate>, Option<serde_json::Value>) {
+            if selection_mode != "mmr" {
+                return (
+                    Self::select_top_chunks(candidates, top_k, selection_mode),
+                    None,
+                );
+            }
+            let lambda = mmr_lambda
+                .filter(|l| l.is_finite())
+                .unwrap_or(MMR_DEFAULT_LAMBDA)
+                .clamp(0.0, 1.0);
+            let mut info = serde_json::json!({ "lambda": lambda, "similarity": "lexical" });
+            let mut embeddings = None;
+            if semantic && candidates.len() > 1 && top_k > 1 {
+                if let Some(reason) = Self::semantic_unavailable_reason() {
+                    info["fallback_reason"] = serde_json::json!(reason);
+                } else if candidates.len() > Self::semantic_embeddings_max_inputs_from_env() {
+                    info["fallback_reason"] = serde_json::json!("too_many_candidates");
+                } else {
+                    let inputs: Vec<String> = candidates
+                        .iter()
+                        .map(|c| c.text.chars().take(1200).collect())
+                        .collect();
+                    match self.embed_texts_cached(&inputs).await {
+                        Ok((embs, cache_hits, cache_misses, model)) => {
+                            info["similarity"] = serde_json::json!("embeddings");
+                            info["backend"] = serde_json::json!(Self::embeddings_backend_label());
+                            info["model_id"] = serde_json::json!(model);
+                            info["cache_hits"] = serde_json::json!(cache_hits);
+                            info["cache_misses"] = serde_json::json!(cache_misses);
+                            embeddings = Some(embs);
+                        }
+                        Err(reason) => info["fallback_reason"] = serde_json::json!(reason),
+                    }
+                }
+            }
+            (
+                Self::select_top_chunks_mmr(candidates, top_k, lambda, embeddings),
+                Some(info),
+            )
+        }
+
+        /// `selection_mode="mmr"`: greedy maximal marginal relevance. Each pick maximizes
+        /// `lambda * relevance - (1 - lambda) * max similarity to the chunks already picked`,
+        /// where relevance is the chunk score normalized to `[0, 1]` and similarity is cosine
+        /// over `embeddings` (one per candidate, same order) or, without them, Jaccard overlap
+        /// of content-word sets. `lambda=1` is plain score order.
+        ///
+        /// Deterministic: candidates are first put in `score` order, and ties (in relevance or
+        /// in MMR value) go to the earlier one.
+        fn select_top_chunks_mmr(
+            candidates: Vec<ChunkCandidate>,
+            top_k: usize,
+            lambda: f64,
+            embeddings: Option<Vec<Vec<f32>>>,
+        ) -> Vec<ChunkCandidate> {
+            if candidates.is_empty() || top_k == 0 {
+                return Vec::new();
+            }
+            let lambda = if lambda.is_finite() {
+                lambda.clamp(0.0, 1.0)
+            } else {
+                MMR_DEFAULT_LAMBDA
+            };
+            let embeddings = embeddings.filter(|e| e.len() == candidates.len());
+            let mut order: Vec<usize> = (0..candidates.len()).collect();
+            order.sort_by(|&ia, &ib| {
+                let (a, b) = (&candidates[ia], &candidates[ib]);
+                Self::score_key(b.score)
+                    .cmp(&Self::score_key(a.score))
+                    .then_with(|| a.warning_penalty.cmp(&b.warning_penalty))
+                    .then_with(|| a.url.cmp(&b.url))
+                    .then_with(|| a.start_char.cmp(&b.start_char))
+            });
+
+            let max_score = candidates.iter().map(|c| c.score).max().unwrap_or(0);
+            let relevance = |i: usize| {
+                if max_score == 0 {
+                    0.0
+                } else {
+                    candidates[i].score as f64 / max_score as f64
+                }
+            };
+            let words: Vec<std::collections::BTreeSet<String>> = if embeddings.is_none() {
+                candidates
+                    .iter()
+                    .map(|c| {
+                        c.text
+                            .to_lowercase()
+                            .split(|ch: char| !ch.is_alphanumeric())
+                            .filter(|w| {
+                                w.chars().count() >= 3 && !textprep::stopwords::ENGLISH.contains(w)
+                            })
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let similarity = |i: usize, j: usize| -> f64 {
+                match embeddings.as_ref() {
+                    Some(e) => (Self::cosine_similarity(&e[i], &e[j]) as f64).clamp(0.0, 1.0),
+                    None => {
+                        let union = words[i].union(&words[j]).count();
+                        if union == 0 {
+                            0.0
+                        } else {
+                            words[i].intersection(&words[j]).count() as f64 / union as f64
+                        }
+                    }
+                }
+            };
+
+            // Max similarity of each remaining candidate to the picked set, updated per pick.
+            let mut max_sim = vec![0.0f64; candidates.len()];
+            let mut picked: Vec<usize> = Vec::with_capacity(top_k.min(order.len()));
+            while picked.len() < top_k && !order.is_empty() {
+                let mut best = 0usize;
+                let mut best_v = f64::NEG_INFINITY;
+                for (pos, &i) in order.iter().enumerate() {
+                    let v = lambda * relevance(i) - (1.0 - lambda) * max_sim[i];
+                    if v > best_v {
+                        best_v = v;
+                        best = pos;
+                    }
+                }
+                let i = order.remove(best);
+                for &j in &order {
+                    max_sim[j] = max_sim[j].max(similarity(i, j));
+                }
+                picked.push(i);
+            }
+
+            let mut slots: Vec<Option<ChunkCandidate>> = candidates.into_iter().map(Some).collect();
+            picked.into_iter().filter_map(|i| slots[i].take()).collect()
+        }
+
+        /// Scale chunk scores by their page's recency factor (see `recency_boost`) and record the
+        /// factor on each per-URL result.
+        fn apply_recency_boost(
+            chunks: &mut [ChunkCandidate],
+            per_url: &mut [serde_json::Value],
+            boost: f64,
+            half_life_days: f64,
+        ) {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            let mut factors = std::collections::BTreeMap::<String, f64>::new();
+            for r in per_url.iter_mut() {
+                let Some(u) = r.get("url").and_then(|v| v.as_str()).map(str::to_string) else {
+                    continue;
+                };
+                let published = r
+                    .get("published_time")
+                    .and_then(|v| v.as_str())
+                    .and_then(webpipe_local::published::parse_date_epoch_s);
+                let f =
+                    webpipe_local::published::recency_factor(published, now, boost, half_life_days);
+                r["recency_factor"] = serde_json::json!((f * 1000.0).round() / 1000.0);
+                factors.insert(u, f);
+            }
+            for c in chunks.iter_mut() {
+                if let Some(f) = factors.get(&c.url) {
+                    c.score = ((c.score as f64) * f).round() as u64;
+                }
+            }
+        }
+
+        /// Pick up to `max_k` chunks from `pool` that contrast with the leading `selected` chunks.
+        ///
+        /// "Contrast" is lexical: the candidate shares content terms with a top chunk but differs in
+        /// polarity (negation/hedging cues on one side only) or uses an antonym form of one of its
+        /// terms ("effective" vs "ineffective"). Candidates already in `selected` are skipped.
+        fn select_contrasting_chunks(
+            selected: &[ChunkCandidate],
+            pool: &[ChunkCandidate],
+            max_k: usize,
+        ) -> Vec<(ChunkCandidate, serde_json::Value)> {
+            const CUES: [&str; 30] = [
+                "not",
+                "no",
+                "never",
+                "none",
+                "nor",
+                "cannot",
+                "without",
+                "lack",
+                "lacks",
+                "fail",
+                "fails",
+                "failed",
+                "false",
+                "myth",
+                "however",
+                "although",
+                "despite",
+                "contrary",
+                "unlike",
+                "disputed",
+                "debunked",
+                "refuted",
+                "against",
+                "risk",
+                "risks",
+                "drawback",
+                "drawbacks",
+                "downside",
+                "downsides",
+                "criticism",
+            ];
+            fn words(s: &str) -> Vec<String> {
+                s.to_lowercase()
+                    .split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '’'))
+                    .map(|w| w.trim_matches(|c| c == '\'' || c == '’').to_string())
+                    .filter(|w| !w.is_empty())
+                    .collect()
+            }
+            fn cues_of(ws: &[String]) -> std::collections::BTreeSet<String> {
+                ws.iter()
+                    .filter(|w| {
+                        CUES.contains(&w.as_str()) || w.ends_with("n't") || w.ends_with("n’t")
+                    })
+                    .cloned()
+                    .collect()
+            }
+            fn content_of(ws: &[String]) -> std::collections::BTreeSet<String> {
+                ws.iter()
+                    .filter(|w| {
+                        w.chars().count() >= 3
+                            && !textprep::stopwords::ENGLISH.contains(&w.as_str())
+                            && !CUES.contains(&w.as_str())
+                            && !w.contains('\'')
+                            && !w.contains('’')
+                    })
+                    .cloned()
+                    .collect()
+            }
+            fn antonyms(
+                a: &std::collections::BTreeSet<String>,
+                b: &std::collections::BTreeSet<String>,
+            ) -> Vec<String> {
+                let mut out = Vec::new();
+                for w in a {
+                    for pre in ["un", "in", "im", "non", "dis"] {
+                        if let Some(rest) = w.strip_prefix(pre) {
+                            if rest.chars().count() >= 4 && b.contains(rest) {
+                                out.push(w.clone());
+                            }
+                        }
+                    }
+                }
+                out
+            }
+
+            if max_k == 0 || selected.is_empty() {
+                return Vec::new();
+            }
+            let anchors: Vec<(
+                &ChunkCandidate,
+                std::collections::BTreeSet<String>,
+                std::collections::BTreeSet<String>,
+            )> = selected
+                .iter()
+                .take(3)
+                .map(|c| {
+                    let ws = words(&c.text);
+                    (c, content_of(&ws), cues_of(&ws))
+                })
+                .collect();
+            let picked_keys: std::collections::BTreeSet<(&str, usize, usize)> = selected
+                .iter()
+                .map(|c| (c.url.as_str(), c.start_char, c.end_char))
+                .collect();
+
+            // (shared terms, antonym hits, score, pool index, explanation)
+            let mut scored: Vec<(usize, usize, u64, usize, serde_json::Value)> = Vec::new();
+            for (i, c) in pool.iter().enumerate() {
+                if picked_keys.contains(&(c.url.as_str(), c.start_char, c.end_char)) {
+                    continue;
+                }
+                let ws = words(&c.text);
+                let content = content_of(&ws);
+                let cues = cues_of(&ws);
+                let mut best: Option<(usize, usize, serde_json::Value)> = None;
+                for (a, a_content, a_cues) in &anchors {
+                    let shared = content.intersection(a_content).count();
+                    let union = content.union(a_content).count().max(1);
+                    let overlap = shared as f64 / union as f64;
+                    if shared < 2 || overlap < 0.08 {
+                        continue;
+                    }
+                    let mut hits = antonyms(&content, a_content);
+                    hits.extend(antonyms(a_content, &content));
+                    let polarity_flip = cues.is_empty() != a_cues.is_empty();
+                    if !polarity_flip && hits.is_empty() {
+                        continue;
+                    }
+                    let mut reasons: Vec<String> = if cues.is_empty() {
+                        a_cues.iter().map(|w| format!("top_chunk:{w}")).collect()
+                    } else {
+                        cues.iter().cloned().collect()
+                    };
+                    if !polarity_flip {
+                        reasons.clear();
+                    }
+                    reasons.extend(hits.iter().map(|w| format!("antonym:{w}")));
+                    let explain = serde_json::json!({
+                        "against_url": a.url,
+                        "against_start_char": a.start_char,
+                        "shared_terms": shared,
+                        "overlap": (overlap * 1000.0).round() / 1000.0,
+                        "cues": reasons,
+                    });
+                    if best
+                        .as_ref()
+                        .is_none_or(|(s, h, _)| (shared, hits.len()) > (*s, *h))
+                    {
+                        best = Some((shared, hits.len(), explain));
+                    }
+                }
+                if let Some((shared, hits, explain)) = best {
+                    scored.push((shared, hits, c.score, i, explain));
+                }
+            }
+            scored.sort_by(|a, b| {
+                (b.1, b.0, b.2)
+                    .cmp(&(a.1, a.0, a.2))
+                    .then_with(|| a.3.cmp(&b.3))
+            });
+            scored
+                .into_iter()
+                .take(max_k)
+                .map(|(_, _, _, i, explain)| (pool[i].clone(), explain))
+                .collect()
+        }
+
         fn query_key(query: &str) -> Option<String> {
             let q = query.trim();
             if q.is_empty() {
@@ -8070,6 +11486,14 @@ mod mcp {
                 .unwrap_or_else(|| "openai/gpt-5.2".to_string())
         }
 
+        pub(crate) fn openrouter_base_url_from_env() -> String {
+            std::env::var("WEBPIPE_OPENROUTER_BASE_URL")
+                .ok()
+                .map(|v| v.trim().trim_end_matches('/').to_string())
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| "https://openrouter.ai/api".to_string())
+        }
+
         pub(crate) fn openrouter_embeddings_model_from_env() -> String {
             std::env::var("WEBPIPE_OPENROUTER_EMBEDDINGS_MODEL")
                 .ok()
@@ -8173,6 +11597,83 @@ mod mcp {
                 .min(10)
         }
 
+        /// Tiebreak for equal-scored URLs that doesn't depend on input order: FNV-1a of the
+        /// URL's path and query (so the order is the same whichever host or port serves it),
+        /// then the URL itself. Byte-stable across runs and platforms.
+        fn url_tiebreak(a: &str, b: &str) -> std::cmp::Ordering {
+            fn fnv1a(s: &str) -> u64 {
+                s.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+                    (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+                })
+            }
+            let key = |u: &str| match reqwest::Url::parse(u.trim()) {
+                Ok(p) => match p.query() {
+                    Some(q) => format!("{}?{q}", p.path()),
+                    None => p.path().to_string(),
+                },
+                Err(_) => u.split('#').next().unwrap_or("").trim().to_string(),
+            };
+            fnv1a(&key(a)).cmp(&fnv1a(&key(b))).then_with(|| a.cmp(b))
+        }
+
+        /// `auto_mode="merge"` ordering: dedup by fragmentless URL, then sort by agreement
+        /// (number of providers returning the URL) desc, best per-provider rank asc, canonical
+        /// URL asc. Each URL keeps the entry from its best-ranked provider (ties: provider
+        /// name). The result depends only on what each provider returned, never on which one
+        /// answered first.
+        fn merge_search_results(
+            outs: Vec<(&str, Vec<webpipe_core::SearchResult>)>,
+            max_results: usize,
+        ) -> Vec<webpipe_core::SearchResult> {
+            fn canonicalize_url(url: &str) -> String {
+                if let Ok(mut u) = reqwest::Url::parse(url) {
+                    u.set_fragment(None);
+                    return u.to_string();
+                }
+                url.trim().to_string()
+            }
+
+            // canonical url -> (agreement, best rank, provider of best rank, result)
+            let mut by_url = std::collections::BTreeMap::<
+                String,
+                (usize, usize, &str, webpipe_core::SearchResult),
+            >::new();
+            for (name, results) in outs {
+                let mut seen = std::collections::BTreeSet::<String>::new();
+                for (rank, rr) in results.into_iter().enumerate() {
+                    let key = canonicalize_url(&rr.url);
+                    if !seen.insert(key.clone()) {
+                        continue;
+                    }
+                    match by_url.get_mut(&key) {
+                        Some(e) => {
+                            e.0 += 1;
+                            if (rank, name) < (e.1, e.2) {
+                                e.1 = rank;
+                                e.2 = name;
+                                e.3 = rr;
+                            }
+                        }
+                        None => {
+                            by_url.insert(key, (1, rank, name, rr));
+                        }
+                    }
+                }
+            }
+            // BTreeMap iteration is already canonical-URL order; the stable sort keeps it as
+            // the last tiebreak.
+            let mut merged: Vec<(usize, usize, webpipe_core::SearchResult)> = by_url
+                .into_values()
+                .map(|(agree, rank, _, rr)| (agree, rank, rr))
+                .collect();
+            merged.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+            merged
+                .into_iter()
+                .take(max_results)
+                .map(|(_, _, rr)| rr)
+                .collect()
+        }
+
         fn select_urls_for_hydration(
             urls: Vec<String>,
             max_urls: usize,
@@ -8219,8 +11720,21 @@ mod mcp {
                         })
                         .collect();
 
-                    // Stable rerank: higher score first; tie-break by original order.
-                    scored.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                    // No URL matches the query at all: nothing to rank by, keep the given order.
+                    if scored.iter().all(|(_, s, _)| *s == 0) {
+                        return scored
+                            .into_iter()
+                            .take(max_urls)
+                            .map(|(_, _, u)| u)
+                            .collect();
+                    }
+                    // Higher score first; ties by URL hash, so equal-scored URLs come out the same
+                    // regardless of the order they were given in.
+                    scored.sort_by(|a, b| {
+                        b.1.cmp(&a.1)
+                            .then_with(|| Self::url_tiebreak(&a.2, &b.2))
+                            .then_with(|| a.0.cmp(&b.0))
+                    });
                     scored
                         .into_iter()
                         .take(max_urls)
@@ -8245,7 +11759,14 @@ mod mcp {
             // This collapses three separate meta tools into one with a method param,
             // keeping the individual tools alive as backward-compat delegates.
             match args.method.as_deref().unwrap_or("info") {
-                "usage" => return self.webpipe_usage(Parameters(None)).await,
+                "usage" => {
+                    return self
+                        .webpipe_usage(Parameters(Some(WebpipeUsageArgs {
+                            include_query_keys: args.include_query_keys,
+                            max_query_keys: args.max_query_keys,
+                        })))
+                        .await
+                }
                 "usage_reset" => return self.webpipe_usage_reset().await,
                 _ => {}
             }
@@ -8262,6 +11783,8 @@ mod mcp {
                 has_env("WEBPIPE_SEARXNG_ENDPOINT") || has_env("WEBPIPE_SEARXNG_ENDPOINTS");
             let perplexity_configured =
                 has_env("WEBPIPE_PERPLEXITY_API_KEY") || has_env("PERPLEXITY_API_KEY");
+            let anthropic_configured =
+                has_env("WEBPIPE_ANTHROPIC_API_KEY") || has_env("ANTHROPIC_API_KEY");
             let openrouter_configured =
                 has_env("WEBPIPE_OPENROUTER_API_KEY") || has_env("OPENROUTER_API_KEY");
             let openai_configured = has_env("WEBPIPE_OPENAI_API_KEY") || has_env("OPENAI_API_KEY");
@@ -8273,7 +11796,7 @@ mod mcp {
 
             let local_tools = serde_json::json!({
                 "yt_dlp": webpipe_local::shellout::has("yt-dlp"),
-                "pandoc": webpipe_local::shellout::has("pandoc"),
+                "pandoc": webpipe_local::shellout::has_pandoc(),
                 "ffmpeg": webpipe_local::shellout::has("ffmpeg"),
                 "tesseract": webpipe_local::shellout::has("tesseract"),
                 "pdftotext": webpipe_local::shellout::has("pdftotext"),
@@ -8351,6 +11874,13 @@ mod mcp {
                 "web_seed_search_extract",
                 "web_explore_extract",
                 "web_sitemap_extract",
+                "web_sitemap",
+                "web_crawl",
+                "web_related",
+                "web_site_search",
+                "web_summarize",
+                "web_compare_backends",
+                "web_diff",
                 "repo_ingest",
                 "paper_search",
                 "arxiv",
@@ -8379,11 +11909,17 @@ mod mcp {
                 },
                 "capabilities": {
                     "pdf_extract": true,
-                    "semantic_rerank": true,
+                    "semantic_rerank": Self::semantic_unavailable_reason().is_none(),
+                    "semantic_unavailable_reason": Self::semantic_unavailable_reason(),
                     "embeddings_openai": false,
                     "embeddings_tei": false,
                     "embeddings_openrouter": Self::openrouter_api_key_from_env().is_some(),
-                    "vision_gemini": cfg!(feature = "vision-gemini")
+                    // Selected embeddings backend (`WEBPIPE_EMBED_BACKEND`) and, when the
+                    // `embed-local` feature is built and a model dir is set, the local model.
+                    "embed_backend": Self::embed_backend_from_env(),
+                    "embeddings_local_model": Self::local_embeddings_model_id(),
+                    "vision_gemini": cfg!(feature = "vision-gemini"),
+                    "envelope_format": EnvelopeFormat::from_env().as_str()
                 },
                 "supported": {
                     // Canonical tool surface ordered by "what users reach for first".
@@ -8403,6 +11939,13 @@ mod mcp {
                         "web_seed_search_extract",
                         "web_explore_extract",
                         "web_sitemap_extract",
+                        "web_sitemap",
+                        "web_crawl",
+                        "web_related",
+                        "web_site_search",
+                        "web_summarize",
+                        "web_compare_backends",
+                        "web_diff",
                         "repo_ingest",
                         "paper_search",
                         "arxiv",
@@ -8413,12 +11956,12 @@ mod mcp {
                     "mcp_tool_groups": {
                         "meta": ["webpipe_meta"],
                         "seeds": ["web_seed_urls", "web_seed_search_extract"],
-                        "fetch_extract": ["web_fetch", "web_extract"],
-                        "explore": ["web_explore_extract"],
-                        "sitemap": ["web_sitemap_extract"],
+                        "fetch_extract": ["web_fetch", "web_extract", "web_compare_backends", "web_diff"],
+                        "explore": ["web_explore_extract", "web_crawl"],
+                        "sitemap": ["web_sitemap_extract", "web_sitemap"],
                         "ingest": ["repo_ingest"],
-                        "search": ["web_search", "search_evidence", "web_perplexity", "web_cache_search_extract"],
-                        "research": ["web_deep_research", "paper_search", "arxiv"]
+                        "search": ["web_search", "search_evidence", "web_perplexity", "web_cache_search_extract", "web_related", "web_site_search"],
+                        "research": ["web_deep_research", "web_summarize", "paper_search", "arxiv"]
                     },
                     // Deprecated tool names and their canonical replacements.
                     // These tools remain callable but are excluded from the default visible set.
@@ -8431,17 +11974,19 @@ mod mcp {
                         "arxiv_enrich": "arxiv"
                     },
                     // Values for web_search.provider
-                    "providers": ["auto", "brave", "tavily", "searxng"],
+                    "providers": ["auto", "brave", "tavily", "searxng", "ddg"],
                     // Values for web_search.auto_mode (when provider="auto")
-                    "auto_modes": ["fallback", "merge", "mab"],
+                    "auto_modes": ["fallback", "merge", "mab", "cost_cascade"],
                     // Values for paper_search.backends
                     "paper_backends": ["semantic_scholar", "openalex", "google_scholar_serpapi"],
                     // Values for extraction engines
-                    "extraction_engines": ["html2text", "html_main", "readability", "html_hint", "text", "json", "xml", "markdown", "pdf-extract", "pdf-pdftotext", "pdf-mutool", "pdf-strings", "youtube_transcript", "pandoc", "image", "image_ocr", "media", "media_subtitles", "gemini_vision"],
+                    "extraction_engines": ["html2text", "html_main", "readability", "html_hint", "text", "json", "xml", "markdown", "pdf-extract", "pdf-pdftotext", "pdf-mutool", "pdf-strings", "youtube_transcript", "pandoc", "xlsx", "pptx", "image", "image_ocr", "media", "media_subtitles", "gemini_vision"],
                     // Environment knobs (names only; no values) for opportunistic local tooling + multimodal.
                     "knobs": [
                         "WEBPIPE_SEARXNG_ENDPOINT",
                         "WEBPIPE_SEARXNG_ENDPOINTS",
+                        "WEBPIPE_COST_CASCADE_MIN_RESULTS",
+                        "WEBPIPE_COST_CASCADE_MAX_JUNK_FRAC",
                         "WEBPIPE_ARXIV_ENDPOINT",
                         "WEBPIPE_ARXIV_REWRITE_HOSTS",
                         "WEBPIPE_ARXIV_PDF_FALLBACK_BASE",
@@ -8457,11 +12002,15 @@ mod mcp {
                         "WEBPIPE_SERPAPI_API_KEY",
                         "SERPAPI_API_KEY",
                         "WEBPIPE_PERPLEXITY_ENDPOINT",
+                        "WEBPIPE_EXTRACT_STREAMING",
+                        "WEBPIPE_EXTRACT_STREAMING_MAX_CHARS",
                         "WEBPIPE_PDF_SHELLOUT",
                         "WEBPIPE_PDF_SHELLOUT_MAX_PAGES",
                         "WEBPIPE_YOUTUBE_TRANSCRIPTS",
                         "WEBPIPE_YOUTUBE_LANGS",
                         "WEBPIPE_YOUTUBE_MAX_CHARS",
+                        "WEBPIPE_YOUTUBE_RETRIES",
+                        "WEBPIPE_YOUTUBE_RETRY_BACKOFF_MS",
                         "WEBPIPE_PANDOC",
                         "WEBPIPE_PANDOC_TIMEOUT_MS",
                         "WEBPIPE_PANDOC_MAX_CHARS",
@@ -8489,7 +12038,7 @@ mod mcp {
                         "WEBPIPE_GEMINI_BASE_URL"
                     ],
                     // Values for web_search_extract.selection_mode / web_deep_research.selection_mode
-                    "selection_modes": ["score", "pareto"],
+                    "selection_modes": ["score", "pareto", "mmr"],
                     // Values for web_search_extract.url_selection_mode
                     "url_selection_modes": ["auto", "auto_plus", "preserve", "query_rank"],
                     // Values for web_search_extract.agentic_selector
@@ -8720,6 +12269,7 @@ mod mcp {
                     "web_perplexity": "Perplexity-backed synthesis (requires API key). Returns answer text + citations[].",
                     "web_cache_search_extract": "Cache-only search: scan WEBPIPE_CACHE_DIR -> extract -> top_chunks (no network).",
                     "web_deep_research": "Evidence gatherer + optional synthesis. Prefer include_evidence for auditability.",
+                    "web_summarize": "One page (or text) -> extract + chunk -> LLM bullet summary. Returns summary.bullets[] + the chunks[] sent.",
                     "arxiv": "arXiv papers: search by topic (pass query) or get metadata for a specific paper (pass id_or_url). Returns papers[] or paper{}.",
                     "arxiv_search": "DEPRECATED: use arxiv instead (same capabilities; pass query).",
                     "arxiv_enrich": "DEPRECATED: use arxiv instead (same capabilities; pass id_or_url).",
@@ -8744,6 +12294,12 @@ mod mcp {
                     },
                     "llm": {
                         "perplexity": perplexity_configured,
+                        "anthropic": anthropic_configured,
+                        "anthropic_model": if anthropic_configured {
+                            std::env::var("WEBPIPE_ANTHROPIC_MODEL").ok().filter(|s| !s.trim().is_empty())
+                        } else {
+                            None
+                        },
                         "openrouter": openrouter_configured,
                         "openrouter_model": if openrouter_configured {
                             Some(Self::openrouter_model_from_env())
@@ -8978,6 +12534,8 @@ mod mcp {
                         no_network: Some(no_network),
                         width: Some(width),
                         max_chars: Some(max_chars),
+                        truncation_strategy: None,
+                        engine: None,
                         query: Some(query.clone()),
                         top_chunks: Some(top_chunks),
                         max_chunk_chars: Some(max_chunk_chars),
@@ -8986,14 +12544,34 @@ mod mcp {
                         truncation_retry_max_bytes: None,
                         include_links: Some(false),
                         max_links: Some(0),
+                        include_alternates: None,
+                        prefer_amp: None,
+                        include_entities: None,
+                        include_tables: None,
+                        include_metadata: None,
+                        table_format: None,
+                        include_dates: None,
+                        date_order: None,
+                        include_keywords: None,
+                        max_keywords: None,
+                        chunk_dom_paths: None,
+                        referer: None,
+                        sentences: None,
+                        include_noscript: None,
+                        follow_iframes: None,
+                        allow_cross_origin_iframes: None,
+                        max_iframes: None,
+                        include_error_body: None,
                         include_text: Some(include_text),
                         include_structure: Some(false),
                         max_outline_items: None,
                         max_blocks: None,
                         max_block_chars: None,
+                        sectioned: None,
                         semantic_rerank: Some(false),
                         semantic_auto_fallback: Some(false),
                         semantic_top_k: None,
+                        include_raw_html: None,
                         cache_read: Some(cache_read),
                         cache_write: Some(cache_write),
                         cache_ttl_s,
@@ -9142,8 +12720,11 @@ mod mcp {
         )]
         async fn webpipe_usage(
             &self,
-            _params: Parameters<Option<WebpipeUsageArgs>>,
+            params: Parameters<Option<WebpipeUsageArgs>>,
         ) -> Result<CallToolResult, McpError> {
+            let args = params.0.unwrap_or_default();
+            let include_query_keys = args.include_query_keys.unwrap_or(false);
+            let max_query_keys = args.max_query_keys.unwrap_or(20).clamp(1, 200);
             let kind = "webpipe_usage";
             let t0 = std::time::Instant::now();
             self.stats_inc_tool(kind);
@@ -9199,6 +12780,34 @@ mod mcp {
             for (k, w) in &s.search_windows {
                 search_window_summaries.insert(k.clone(), w.summary());
             }
+            // Most recently seen query keys first (routing eviction uses the same ticks).
+            let by_query_key = include_query_keys.then(|| {
+                let mut keys: Vec<(&String, u64)> = s
+                    .search_windows_by_query_key
+                    .keys()
+                    .map(|k| (k, s.routing_query_last_seen.get(k).copied().unwrap_or(0)))
+                    .collect();
+                keys.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+                keys.into_iter()
+                    .take(max_query_keys)
+                    .map(|(k, tick)| {
+                        let summaries: std::collections::BTreeMap<String, muxer::Summary> = s
+                            .search_windows_by_query_key
+                            .get(k)
+                            .map(|per| {
+                                per.iter()
+                                    .map(|(name, w)| (name.clone(), w.summary()))
+                                    .collect()
+                            })
+                            .unwrap_or_default();
+                        serde_json::json!({
+                            "query_key": k,
+                            "last_seen_tick": tick,
+                            "summaries": summaries
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            });
             let llm_backends = s.llm_backends.clone();
             let fetch_backends = s.fetch_backends.clone();
             let warning_counts = s.warning_counts.clone();
@@ -9221,7 +12830,11 @@ mod mcp {
                         "routing_contexts_in_memory": routing_contexts_in_memory
                     },
                     "llm_backends": llm_backends,
-                    "fetch_backends": fetch_backends
+                    "fetch_backends": fetch_backends,
+                    // Local fetch cache outcomes: fresh / revalidated (304) / stale (SWR) / miss.
+                    "fetch_cache": self.fetcher.cache_status_counts(),
+                    // On-disk embedding vectors (semantic rerank): hits / misses / writes.
+                    "embedding_cache": webpipe_local::semantic::embedding_cache_stats()
                 },
                 "warnings": {
                     "counts": warning_counts
@@ -9243,6 +12856,15 @@ mod mcp {
                     "brave": { "cost_units": brave_units, "estimated_usd": brave_est_usd }
                 }
             });
+            if let Some(rows) = by_query_key {
+                payload["usage"]["search_window"]["by_query_key"] = serde_json::json!(rows);
+                payload["usage"]["search_window"]["by_query_key_truncated"] =
+                    serde_json::json!(routing_contexts_in_memory > max_query_keys);
+                payload["request"] = serde_json::json!({
+                    "include_query_keys": include_query_keys,
+                    "max_query_keys": max_query_keys
+                });
+            }
 
             add_envelope_fields(&mut payload, kind, t0.elapsed().as_millis());
             let md = webpipe_usage_markdown(&payload);
@@ -9273,6 +12895,8 @@ mod mcp {
             let now = now_epoch_s();
             let mut s = self.stats_lock();
             *s = UsageStats::new(now);
+            self.fetcher.reset_cache_status_counts();
+            webpipe_local::semantic::reset_embedding_cache_stats();
 
             let mut payload = serde_json::json!({
                 "ok": true,
@@ -9284,7 +12908,7 @@ mod mcp {
         }
 
         #[tool(
-            description = "Best for: finding academic papers on arXiv by topic, author, or keyword. Not this when you have a specific paper ID — use arxiv_enrich instead. Output: papers[] with title, abstract, authors, categories, pdf_url (bounded by per_page). Supports category filters (cs.AI, cs.CL, etc.) and semantic reranking.",
+            description = "Best for: finding academic papers on arXiv by topic, author, or keyword. Not this when you have a specific paper ID — use arxiv_enrich instead. Output: papers[] with title, abstract, authors, categories, pdf_url (bounded by per_page). Supports category filters (cs.AI, cs.CL, etc.) and semantic reranking; include_fulltext=true attaches extracted full text for the top papers.",
             input_schema = Arc::new(tool_input_schema_draft07::<ArxivSearchArgs>()),
             annotations(title = "arXiv search", read_only_hint = true, open_world_hint = true)
         )]
@@ -9320,6 +12944,9 @@ mod mcp {
 
             let semantic_rerank = args.semantic_rerank.unwrap_or(false);
             let semantic_top_k = args.semantic_top_k.unwrap_or(per_page).clamp(1, 50);
+            let include_fulltext = args.include_fulltext.unwrap_or(false);
+            let fulltext_max_papers = args.fulltext_max_papers.unwrap_or(3).clamp(1, 10);
+            let fulltext_max_chars = args.fulltext_max_chars.unwrap_or(20_000).min(200_000);
 
             let mut resp = webpipe_local::arxiv::arxiv_search(
                 self.http.clone(),
@@ -9345,7 +12972,7 @@ mod mcp {
                     })
                     .collect();
                 let sem = self
-                    .semantic_rerank_chunks_best(&query, &cands, semantic_top_k)
+                    .semantic_rerank_or_fallback(&query, &cands, semantic_top_k, timeout_ms)
                     .await;
                 if sem.ok {
                     let mut by_id = std::collections::BTreeMap::<
@@ -9383,13 +13010,40 @@ mod mcp {
                 semantic = serde_json::to_value(sem).unwrap_or(serde_json::Value::Null);
             }
 
+            let mut papers = serde_json::json!(resp.papers);
+            let mut fulltext_summary = serde_json::Value::Null;
+            if include_fulltext {
+                let picked = &resp.papers[..resp.papers.len().min(fulltext_max_papers)];
+                let outs = futures::future::join_all(
+                    picked
+                        .iter()
+                        .map(|p| self.arxiv_paper_fulltext(p, fulltext_max_chars, timeout_ms)),
+                )
+                .await;
+                let attached = outs
+                    .iter()
+                    .filter(|o| o["ok"].as_bool() == Some(true))
+                    .count();
+                if attached < outs.len() {
+                    resp.warnings.push("arxiv_fulltext_failed");
+                }
+                for (i, o) in outs.into_iter().enumerate() {
+                    papers[i]["fulltext"] = o;
+                }
+                fulltext_summary = serde_json::json!({
+                    "requested": picked.len(),
+                    "attached": attached,
+                    "failed": picked.len() - attached
+                });
+            }
+
             let mut payload = serde_json::json!({
                 "ok": resp.ok,
                 "query": resp.query,
                 "page": resp.page,
                 "per_page": resp.per_page,
                 "total_results": resp.total_results,
-                "papers": resp.papers,
+                "papers": papers,
                 "warnings": resp.warnings,
                 "request": {
                     "query": query,
@@ -9399,12 +13053,18 @@ mod mcp {
                     "per_page": per_page,
                     "timeout_ms": timeout_ms,
                     "semantic_rerank": semantic_rerank,
-                    "semantic_top_k": semantic_top_k
+                    "semantic_top_k": semantic_top_k,
+                    "include_fulltext": include_fulltext,
+                    "fulltext_max_papers": fulltext_max_papers,
+                    "fulltext_max_chars": fulltext_max_chars
                 }
             });
             if !semantic.is_null() {
                 payload["request"]["semantic"] = semantic;
             }
+            if !fulltext_summary.is_null() {
+                payload["fulltext"] = fulltext_summary;
+            }
             add_envelope_fields(&mut payload, "arxiv_search", t0.elapsed().as_millis());
             let md = arxiv_search_markdown(&payload);
             Ok(tool_result_markdown_with_json(payload, md))
@@ -9559,6 +13219,31 @@ mod mcp {
                     }))
                     .await?;
                 payload["discussion_search"] = payload_from_result(&r);
+                if args.fetch_discussions.unwrap_or(false) {
+                    let concurrency = args.discussion_concurrency.unwrap_or(2).clamp(1, 5);
+                    let query = paper
+                        .as_ref()
+                        .map(|p| p.title.clone())
+                        .filter(|t| !t.trim().is_empty())
+                        .unwrap_or_else(|| arxiv_id.clone());
+                    let (discussions, summary) = self
+                        .arxiv_fetch_discussions(
+                            &payload["discussion_search"],
+                            &query,
+                            concurrency,
+                            args.timeout_ms.unwrap_or(20_000),
+                        )
+                        .await;
+                    if summary["failed"].as_u64().unwrap_or(0) > 0 {
+                        let warnings = ["arxiv_discussion_fetch_failed"];
+                        payload["warnings"] = serde_json::json!(warnings);
+                        let codes = warning_codes_from(&warnings);
+                        payload["warning_codes"] = serde_json::json!(codes.clone());
+                        payload["warning_hints"] = warning_hints_from(&codes);
+                    }
+                    payload["discussions"] = serde_json::json!(discussions);
+                    payload["discussion_fetch"] = summary;
+                }
             }
 
             add_envelope_fields(&mut payload, "arxiv_enrich", t0.elapsed().as_millis());
@@ -9632,6 +13317,31 @@ mod mcp {
                         }))
                         .await?;
                     payload["discussion_search"] = payload_from_result(&r);
+                    if args.fetch_discussions.unwrap_or(false) {
+                        let concurrency = args.discussion_concurrency.unwrap_or(2).clamp(1, 5);
+                        let query = paper
+                            .as_ref()
+                            .map(|p| p.title.clone())
+                            .filter(|t| !t.trim().is_empty())
+                            .unwrap_or_else(|| arxiv_id.clone());
+                        let (discussions, summary) = self
+                            .arxiv_fetch_discussions(
+                                &payload["discussion_search"],
+                                &query,
+                                concurrency,
+                                args.timeout_ms.unwrap_or(20_000),
+                            )
+                            .await;
+                        if summary["failed"].as_u64().unwrap_or(0) > 0 {
+                            let warnings = ["arxiv_discussion_fetch_failed"];
+                            payload["warnings"] = serde_json::json!(warnings);
+                            let codes = warning_codes_from(&warnings);
+                            payload["warning_codes"] = serde_json::json!(codes.clone());
+                            payload["warning_hints"] = warning_hints_from(&codes);
+                        }
+                        payload["discussions"] = serde_json::json!(discussions);
+                        payload["discussion_fetch"] = summary;
+                    }
                 }
                 add_envelope_fields(&mut payload, "arxiv", t0.elapsed().as_millis());
                 let md = arxiv_enrich_markdown(&payload);
@@ -9687,7 +13397,7 @@ mod mcp {
                     })
                     .collect();
                 let sem = self
-                    .semantic_rerank_chunks_best(&query, &cands, semantic_top_k)
+                    .semantic_rerank_or_fallback(&query, &cands, semantic_top_k, timeout_ms)
                     .await;
                 if sem.ok {
                     let mut by_id = std::collections::BTreeMap::<
@@ -9964,10 +13674,14 @@ mod mcp {
                     timeout_ms: Some(timeout_ms),
                     max_bytes: Some(max_bytes),
                     headers: BTreeMap::new(),
+                    method: None,
+                    body: None,
                     cache: FetchCachePolicy {
                         read: cache_read || no_network,
                         write: if no_network { false } else { cache_write },
                         ttl_s: cache_ttl_s,
+                        read_non_idempotent: false,
+                        stale_while_revalidate_s: None,
                     },
                 };
 
@@ -10059,6 +13773,7 @@ mod mcp {
                         max_outline_items: 0,
                         max_blocks: 0,
                         max_block_chars: 0,
+                        truncation_strategy: webpipe_local::extract::TruncationStrategy::Head,
                     };
                     let mut p =
                         webpipe_local::extract::extract_pipeline_from_bytes(&[], None, "", cfg);
@@ -10076,6 +13791,7 @@ mod mcp {
                             max_outline_items: 0,
                             max_blocks: 0,
                             max_block_chars: 0,
+                            truncation_strategy: webpipe_local::extract::TruncationStrategy::Head,
                         };
                         webpipe_local::extract::extract_pipeline_from_bytes(
                             bytes2.as_ref(),
@@ -10101,6 +13817,8 @@ mod mcp {
                                 max_outline_items: 0,
                                 max_blocks: 0,
                                 max_block_chars: 0,
+                                truncation_strategy:
+                                    webpipe_local::extract::TruncationStrategy::Head,
                             };
                             webpipe_local::extract::extract_pipeline_from_bytes(&[], None, "", cfg)
                         }),
@@ -10115,6 +13833,8 @@ mod mcp {
                                 max_outline_items: 0,
                                 max_blocks: 0,
                                 max_block_chars: 0,
+                                truncation_strategy:
+                                    webpipe_local::extract::TruncationStrategy::Head,
                             };
                             let mut p = webpipe_local::extract::extract_pipeline_from_bytes(
                                 &[],
@@ -10131,7 +13851,10 @@ mod mcp {
                 let extracted = pipeline.extracted;
                 let mut warnings: Vec<&'static str> = Vec::new();
                 if resp.truncated {
-                    warnings.push("body_truncated_by_max_bytes");
+                    warnings.push(truncation_warning(&resp.timings_ms));
+                }
+                if resp.timings_ms.contains_key("decode_failed") {
+                    warnings.push("decode_failed");
                 }
                 if resp.timings_ms.contains_key("cache_get_timeout")
                     || resp.timings_ms.contains_key("cache_put_timeout")
@@ -10334,6 +14057,555 @@ mod mcp {
             Ok(tool_result(payload))
         }
 
+        #[tool(
+            description = "Site map: breadth-first crawl from start_url following internal links (bounded by max_pages/max_depth; robots-aware; paced per host; cache-aware), or a Firecrawl crawl job with backend=\"firecrawl\". Output: pages[] with url/depth/title/chunk_count (+ text if include_text) and top_chunks (best chunks across pages for query).",
+            input_schema = Arc::new(tool_input_schema_draft07::<WebCrawlArgs>()),
+            annotations(title = "Crawl (BFS)", read_only_hint = true, open_world_hint = true)
+        )]
+        async fn web_crawl(
+            &self,
+            params: Parameters<Option<WebCrawlArgs>>,
+        ) -> Result<CallToolResult, McpError> {
+            let args = params.0.unwrap_or_default();
+            self.stats_inc_tool("web_crawl");
+            let t0 = std::time::Instant::now();
+
+            let start_url = args
+                .start_url
+                .clone()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            let backend = args
+                .backend
+                .as_deref()
+                .unwrap_or("local")
+                .trim()
+                .to_ascii_lowercase();
+            let query = args
+                .query
+                .as_deref()
+                .map(str::trim)
+                .filter(|q| !q.is_empty())
+                .map(str::to_string);
+            let top_chunks = args.top_chunks.unwrap_or(5).min(50);
+            let max_chunk_chars = args.max_chunk_chars.unwrap_or(500).clamp(50, 5_000);
+            let max_pages = args.max_pages.unwrap_or(10).clamp(1, 100);
+            let max_depth = args.max_depth.unwrap_or(2).min(5);
+            let same_domain = args.same_domain.unwrap_or(true);
+            let include_text = args.include_text.unwrap_or(false);
+            let max_chars = args.max_chars.unwrap_or(20_000).min(200_000);
+            let respect_robots = args.respect_robots.unwrap_or(true);
+            let per_host_delay_ms = args.per_host_delay_ms.unwrap_or(500).min(10_000);
+            let max_links_per_page = args.max_links_per_page.unwrap_or(100).min(500);
+            let timeout_ms = if backend == "firecrawl" {
+                args.timeout_ms.unwrap_or(60_000).min(300_000)
+            } else {
+                args.timeout_ms.unwrap_or(20_000).min(60_000)
+            };
+            let max_bytes = args.max_bytes.unwrap_or(5_000_000);
+            let cache_read = args.cache_read.unwrap_or(true);
+            let cache_write = args.cache_write.unwrap_or(true);
+            let request = serde_json::json!({
+                "start_url": start_url,
+                "backend": backend,
+                "query": query,
+                "top_chunks": top_chunks,
+                "max_chunk_chars": max_chunk_chars,
+                "max_pages": max_pages,
+                "max_depth": max_depth,
+                "same_domain": same_domain,
+                "include_text": include_text,
+                "max_chars": max_chars,
+                "respect_robots": respect_robots,
+                "per_host_delay_ms": per_host_delay_ms,
+                "max_links_per_page": max_links_per_page,
+                "timeout_ms": timeout_ms,
+                "max_bytes": max_bytes,
+                "cache_read": cache_read,
+                "cache_write": cache_write
+            });
+            let fail = |error: serde_json::Value| {
+                let mut payload = serde_json::json!({
+                    "ok": false,
+                    "start_url": start_url,
+                    "request": request,
+                    "error": error
+                });
+                add_envelope_fields(&mut payload, "web_crawl", t0.elapsed().as_millis());
+                Ok(tool_result(payload))
+            };
+
+            let start = match reqwest::Url::parse(&start_url) {
+                Ok(u) if matches!(u.scheme(), "http" | "https") => u,
+                _ => {
+                    return fail(error_obj(
+                        ErrorCode::InvalidUrl,
+                        "start_url must be an absolute http(s) URL",
+                        "Pass a URL like https://example.com/docs/.",
+                    ))
+                }
+            };
+            if !matches!(backend.as_str(), "local" | "firecrawl") {
+                return fail(error_obj(
+                    ErrorCode::InvalidParams,
+                    format!("unknown backend: {backend}"),
+                    "Allowed backend values: local, firecrawl.",
+                ));
+            }
+            if backend == "firecrawl" {
+                let fc = match webpipe_local::firecrawl::FirecrawlClient::from_env(
+                    self.http.clone(),
+                ) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        return fail(error_obj(
+                            ErrorCode::NotConfigured,
+                            e.to_string(),
+                            "Set WEBPIPE_FIRECRAWL_API_KEY (or FIRECRAWL_API_KEY) to use backend=\"firecrawl\", or use backend=\"local\".",
+                        ))
+                    }
+                };
+                let fc_t0 = std::time::Instant::now();
+                let crawled = match fc.crawl(&start_url, max_pages, max_depth, timeout_ms).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        let msg = e.to_string();
+                        self.stats_record_fetch_backend(
+                            "firecrawl",
+                            false,
+                            fc_t0.elapsed().as_millis() as u64,
+                            Some(msg.as_str()),
+                        );
+                        return fail(error_obj(
+                            ErrorCode::FetchFailed,
+                            msg,
+                            "The Firecrawl crawl failed. Check the Firecrawl account/limits, or use backend=\"local\".",
+                        ));
+                    }
+                };
+                // Firecrawl bills per page scraped; prefer its own count when it reports one.
+                self.stats_record_fetch_backend_units(
+                    "firecrawl",
+                    true,
+                    crawled.credits_used.unwrap_or(crawled.docs.len() as u64),
+                    fc_t0.elapsed().as_millis() as u64,
+                    None,
+                );
+
+                let mut pages: Vec<serde_json::Value> = Vec::new();
+                let mut candidates: Vec<ChunkCandidate> = Vec::new();
+                for doc in crawled.docs {
+                    let md = doc.markdown;
+                    let url = doc.url;
+                    let (md2, url2, query2) = (md.clone(), url.clone(), query.clone());
+                    let pipe = tokio::task::spawn_blocking(move || {
+                        let cfg = webpipe_local::extract::ExtractPipelineCfg {
+                            query: query2.as_deref(),
+                            width: 100,
+                            max_chars,
+                            top_chunks: 50,
+                            max_chunk_chars,
+                            include_structure: false,
+                            max_outline_items: 0,
+                            max_blocks: 0,
+                            max_block_chars: 0,
+                            truncation_strategy: webpipe_local::extract::TruncationStrategy::Head,
+                        };
+                        webpipe_local::extract::extract_pipeline_from_bytes(
+                            md2.as_bytes(),
+                            Some("text/markdown"),
+                            &url2,
+                            cfg,
+                        )
+                    })
+                    .await;
+                    let Ok(pipe) = pipe else {
+                        continue;
+                    };
+                    let status = doc.status_code.unwrap_or(200);
+                    candidates.extend(pipe.chunks.iter().map(|c| ChunkCandidate {
+                        url: url.clone(),
+                        score: c.score,
+                        start_char: c.start_char,
+                        end_char: c.end_char,
+                        text: c.text.clone(),
+                        warning_penalty: 0,
+                        cache_hit: false,
+                    }));
+                    let mut one = serde_json::json!({
+                        "ok": !Self::http_status_is_error(status),
+                        "url": url,
+                        "status": status,
+                        "content_type": "text/markdown",
+                        "title": doc.title,
+                        "chunk_count": pipe.chunks.len(),
+                        "text_chars": pipe.text_chars,
+                    });
+                    if include_text {
+                        one["text"] = serde_json::json!(pipe.extracted.text);
+                    }
+                    pages.push(one);
+                }
+                let top = Self::select_top_chunks(candidates, top_chunks, "pareto")
+                    .into_iter()
+                    .map(|c| c.into_chunk(None))
+                    .collect::<Vec<_>>();
+                let mut payload = serde_json::json!({
+                    "ok": true,
+                    "start_url": start_url,
+                    "backend": "firecrawl",
+                    "request": request,
+                    "pages": pages,
+                    "top_chunks": top,
+                    "summary": {
+                        "pages": pages.len(),
+                        "pages_ok": pages.iter().filter(|p| p["ok"].as_bool() == Some(true)).count(),
+                        "credits_used": crawled.credits_used,
+                        "stop_reason": if crawled.completed { "firecrawl_completed" } else { "firecrawl_timeout" },
+                    },
+                });
+                if !crawled.completed {
+                    let warnings = ["firecrawl_crawl_incomplete"];
+                    payload["warnings"] = serde_json::json!(warnings);
+                    let codes = warning_codes_from(&warnings);
+                    payload["warning_codes"] = serde_json::json!(codes.clone());
+                    payload["warning_hints"] = warning_hints_from(&codes);
+                }
+                add_envelope_fields(&mut payload, "web_crawl", t0.elapsed().as_millis());
+                return Ok(tool_result(payload));
+            }
+            if privacy_mode_from_env() == PrivacyMode::Anonymous
+                && anon_proxy_from_env().is_none()
+                && !is_localhost_url(&start_url)
+            {
+                return fail(error_obj(
+                    ErrorCode::NotConfigured,
+                    "anonymous mode requires a proxy",
+                    "Set WEBPIPE_ANON_PROXY (recommended for Tor: socks5h://127.0.0.1:9050).",
+                ));
+            }
+
+            const MAX_QUEUED: usize = 1_000;
+            const MAX_SKIPPED_LISTED: usize = 100;
+            fn canon(u: &reqwest::Url) -> String {
+                let mut u = u.clone();
+                u.set_fragment(None);
+                u.to_string()
+            }
+            let cache = FetchCachePolicy {
+                read: cache_read,
+                write: cache_write,
+                ttl_s: None,
+                read_non_idempotent: false,
+                stale_while_revalidate_s: None,
+            };
+            let extract_timeout_ms = std::env::var("WEBPIPE_EXTRACT_PIPELINE_TIMEOUT_MS")
+                .ok()
+                .and_then(|s| s.trim().parse::<u64>().ok())
+                .unwrap_or(12_000);
+
+            let mut queue: std::collections::VecDeque<(String, usize, Option<String>)> =
+                std::collections::VecDeque::new();
+            let mut seen: std::collections::BTreeSet<String> = std::collections::BTreeSet::new();
+            seen.insert(canon(&start));
+            queue.push_back((canon(&start), 0, None));
+            let mut robots: std::collections::HashMap<
+                String,
+                Option<webpipe_local::crawl::RobotsRules>,
+            > = std::collections::HashMap::new();
+            let mut last_fetch: std::collections::HashMap<String, std::time::Instant> =
+                std::collections::HashMap::new();
+            let mut pages: Vec<serde_json::Value> = Vec::new();
+            let mut candidates: Vec<ChunkCandidate> = Vec::new();
+            let mut skipped: Vec<serde_json::Value> = Vec::new();
+            let mut skipped_robots = 0usize;
+            let mut warnings: Vec<&'static str> = Vec::new();
+
+            // Paces `host` and records the request time.
+            async fn pace(
+                last_fetch: &mut std::collections::HashMap<String, std::time::Instant>,
+                host: &str,
+                delay_ms: u64,
+            ) {
+                if let Some(prev) = last_fetch.get(host) {
+                    let due = *prev + std::time::Duration::from_millis(delay_ms);
+                    tokio::time::sleep_until(tokio::time::Instant::from_std(due)).await;
+                }
+                last_fetch.insert(host.to_string(), std::time::Instant::now());
+            }
+
+            while pages.len() < max_pages {
+                let Some((url, depth, found_on)) = queue.pop_front() else {
+                    break;
+                };
+                let Ok(u) = reqwest::Url::parse(&url) else {
+                    continue;
+                };
+                let host = u.host_str().unwrap_or("").to_ascii_lowercase();
+                let origin = u.origin().ascii_serialization();
+
+                let mut delay_ms = per_host_delay_ms;
+                if respect_robots {
+                    if !robots.contains_key(&origin) {
+                        pace(&mut last_fetch, &host, delay_ms).await;
+                        let req = FetchRequest {
+                            url: format!("{origin}/robots.txt"),
+                            timeout_ms: Some(timeout_ms.min(10_000)),
+                            max_bytes: Some(500_000),
+                            headers: BTreeMap::new(),
+                            method: None,
+                            body: None,
+                            cache: cache.clone(),
+                        };
+                        let rules = match self.fetcher.fetch(&req).await {
+                            Ok(r) if r.status == 200 => {
+                                Some(webpipe_local::crawl::RobotsRules::parse(
+                                    &r.text_lossy(),
+                                    "webpipe",
+                                ))
+                            }
+                            // 4xx: no robots.txt, everything allowed.
+                            Ok(r) if (400..500).contains(&r.status) => None,
+                            _ => {
+                                warnings.push("robots_unavailable");
+                                None
+                            }
+                        };
+                        robots.insert(origin.clone(), rules);
+                    }
+                    if let Some(rules) = robots.get(&origin).and_then(|r| r.as_ref()) {
+                        let path = match u.query() {
+                            Some(q) => format!("{}?{q}", u.path()),
+                            None => u.path().to_string(),
+                        };
+                        if !rules.allows(&path) {
+                            skipped_robots += 1;
+                            if skipped.len() < MAX_SKIPPED_LISTED {
+                                skipped.push(serde_json::json!({
+                                    "url": url, "depth": depth, "reason": "robots"
+                                }));
+                            }
+                            continue;
+                        }
+                        if let Some(d) = rules.crawl_delay_s {
+                            delay_ms = delay_ms.max(((d * 1000.0) as u64).min(10_000));
+                        }
+                    }
+                }
+
+                pace(&mut last_fetch, &host, delay_ms).await;
+                let req = FetchRequest {
+                    url: url.clone(),
+                    timeout_ms: Some(timeout_ms),
+                    max_bytes: Some(max_bytes),
+                    headers: match &found_on {
+                        Some(r) => referer_headers(Some(r)),
+                        None => BTreeMap::new(),
+                    },
+                    method: None,
+                    body: None,
+                    cache: cache.clone(),
+                };
+                let resp = match self.fetcher.fetch(&req).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        pages.push(serde_json::json!({
+                            "ok": false,
+                            "url": url,
+                            "depth": depth,
+                            "found_on": found_on,
+                            "error": error_obj(ErrorCode::FetchFailed, e.to_string(), "The page could not be fetched; the crawl continued without it."),
+                        }));
+                        continue;
+                    }
+                };
+                let final_url = resp.final_url.clone();
+                if let Ok(fu) = reqwest::Url::parse(&final_url) {
+                    seen.insert(canon(&fu));
+                }
+
+                let bytes = resp.bytes.clone();
+                let ct = resp.content_type.clone();
+                let fu2 = final_url.clone();
+                let query2 = query.clone();
+                let follow = depth < max_depth;
+                let handle = tokio::task::spawn_blocking(move || {
+                    let cfg = webpipe_local::extract::ExtractPipelineCfg {
+                        query: query2.as_deref(),
+                        width: 100,
+                        max_chars,
+                        top_chunks: 50,
+                        max_chunk_chars,
+                        include_structure: false,
+                        max_outline_items: 0,
+                        max_blocks: 0,
+                        max_block_chars: 0,
+                        truncation_strategy: webpipe_local::extract::TruncationStrategy::Head,
+                    };
+                    let pipe = webpipe_local::extract::extract_pipeline_from_bytes(
+                        bytes.as_ref(),
+                        ct.as_deref(),
+                        &fu2,
+                        cfg,
+                    );
+                    let is_html = webpipe_local::extract::bytes_look_like_html(bytes.as_ref())
+                        || ct
+                            .as_deref()
+                            .unwrap_or("")
+                            .to_ascii_lowercase()
+                            .starts_with("text/html");
+                    let (title, canonical, links) = if is_html {
+                        let html = String::from_utf8_lossy(bytes.as_ref()).to_string();
+                        let links = if follow {
+                            webpipe_local::links::extract_link_candidates(
+                                &html,
+                                Some(&fu2),
+                                max_links_per_page,
+                            )
+                        } else {
+                            Vec::new()
+                        };
+                        (
+                            webpipe_local::crawl::page_title(&html),
+                            webpipe_local::links::extract_alternates(&html, Some(&fu2)).canonical,
+                            links,
+                        )
+                    } else {
+                        (None, None, Vec::new())
+                    };
+                    (pipe, title, canonical, links)
+                });
+                let Ok(Ok((pipe, title, canonical, links))) = tokio::time::timeout(
+                    std::time::Duration::from_millis(extract_timeout_ms),
+                    handle,
+                )
+                .await
+                else {
+                    pages.push(serde_json::json!({
+                        "ok": false,
+                        "url": url,
+                        "depth": depth,
+                        "found_on": found_on,
+                        "final_url": final_url,
+                        "status": resp.status,
+                        "error": error_obj(ErrorCode::UnexpectedError, "extraction timed out", "Raise WEBPIPE_EXTRACT_PIPELINE_TIMEOUT_MS or lower max_bytes."),
+                    }));
+                    continue;
+                };
+
+                // A page whose declared canonical was already crawled is a duplicate: keep the
+                // entry (it was fetched) but don't expand it again.
+                let canonical = canonical
+                    .and_then(|c| reqwest::Url::parse(&c).ok())
+                    .map(|c| canon(&c));
+                let duplicate_of = canonical.as_ref().filter(|c| {
+                    **c != url && pages.iter().any(|p| p["url"].as_str() == Some(c.as_str()))
+                });
+                let duplicate_of = duplicate_of.cloned();
+                if let Some(c) = &canonical {
+                    seen.insert(c.clone());
+                }
+
+                let mut links_queued = 0usize;
+                if duplicate_of.is_none() {
+                    for lc in &links {
+                        let Ok(lu) = reqwest::Url::parse(&lc.url) else {
+                            continue;
+                        };
+                        if !matches!(lu.scheme(), "http" | "https") {
+                            continue;
+                        }
+                        if same_domain
+                            && !webpipe_local::links::link_scope(&start, &lu).is_internal()
+                        {
+                            continue;
+                        }
+                        let k = canon(&lu);
+                        if queue.len() >= MAX_QUEUED || !seen.insert(k.clone()) {
+                            continue;
+                        }
+                        queue.push_back((k, depth + 1, Some(url.clone())));
+                        links_queued += 1;
+                    }
+                }
+
+                if duplicate_of.is_none() {
+                    let cache_hit = resp.source == webpipe_core::FetchSource::Cache;
+                    candidates.extend(pipe.chunks.iter().map(|c| ChunkCandidate {
+                        url: url.clone(),
+                        score: c.score,
+                        start_char: c.start_char,
+                        end_char: c.end_char,
+                        text: c.text.clone(),
+                        warning_penalty: 0,
+                        cache_hit,
+                    }));
+                }
+
+                let mut one = serde_json::json!({
+                    "ok": !Self::http_status_is_error(resp.status),
+                    "url": url,
+                    "depth": depth,
+                    "found_on": found_on,
+                    "final_url": final_url,
+                    "status": resp.status,
+                    "content_type": resp.content_type,
+                    "title": title,
+                    "chunk_count": pipe.chunks.len(),
+                    "text_chars": pipe.text_chars,
+                    "links_found": links.len(),
+                    "links_queued": links_queued,
+                });
+                if let Some(c) = canonical.filter(|c| *c != url) {
+                    one["canonical"] = serde_json::json!(c);
+                }
+                if let Some(d) = duplicate_of {
+                    one["duplicate_of"] = serde_json::json!(d);
+                }
+                if include_text {
+                    one["text"] = serde_json::json!(pipe.extracted.text);
+                }
+                pages.push(one);
+            }
+
+            let stop_reason = if queue.is_empty() {
+                "frontier_exhausted"
+            } else {
+                warnings.push("crawl_truncated_by_max_pages");
+                "max_pages"
+            };
+            let top = Self::select_top_chunks(candidates, top_chunks, "pareto")
+                .into_iter()
+                .map(|c| c.into_chunk(None))
+                .collect::<Vec<_>>();
+            let mut payload = serde_json::json!({
+                "ok": true,
+                "start_url": start_url,
+                "backend": "local",
+                "request": request,
+                "pages": pages,
+                "top_chunks": top,
+                "skipped": skipped,
+                "summary": {
+                    "pages": pages.len(),
+                    "pages_ok": pages.iter().filter(|p| p["ok"].as_bool() == Some(true)).count(),
+                    "skipped_robots": skipped_robots,
+                    "frontier_remaining": queue.len(),
+                    "stop_reason": stop_reason,
+                },
+            });
+            if !warnings.is_empty() {
+                warnings.dedup();
+                payload["warnings"] = serde_json::json!(warnings);
+                let codes = warning_codes_from(&warnings);
+                payload["warning_codes"] = serde_json::json!(codes.clone());
+                payload["warning_hints"] = warning_hints_from(&codes);
+            }
+            add_envelope_fields(&mut payload, "web_crawl", t0.elapsed().as_millis());
+            Ok(tool_result(payload))
+        }
+
         #[tool(
             description = "Repo ingest (gitingest-like): list a GitHub repo tree and fetch a bounded set of files as a combined text corpus (bounded; cache-aware; JSON output)",
             input_schema = Arc::new(tool_input_schema_draft07::<RepoIngestArgs>()),
@@ -10764,10 +15036,14 @@ mod mcp {
                     timeout_ms: Some(timeout_ms),
                     max_bytes: Some(max_file_bytes),
                     headers: BTreeMap::new(),
+                    method: None,
+                    body: None,
                     cache: FetchCachePolicy {
                         read: cache_read,
                         write: cache_write,
                         ttl_s: cache_ttl_s,
+                        read_non_idempotent: false,
+                        stale_while_revalidate_s: None,
                     },
                 };
                 let mut best: Option<webpipe_core::FetchResponse> = None;
@@ -10827,10 +15103,14 @@ mod mcp {
                         timeout_ms: Some(timeout_ms),
                         max_bytes: Some(max_file_bytes),
                         headers,
+                        method: None,
+                        body: None,
                         cache: FetchCachePolicy {
                             read: cache_read,
                             write: cache_write,
                             ttl_s: cache_ttl_s,
+                            read_non_idempotent: false,
+                            stale_while_revalidate_s: None,
                         },
                     };
                     match self.fetcher.fetch(&api_req).await {
@@ -11178,10 +15458,14 @@ mod mcp {
                     timeout_ms: Some(timeout_ms),
                     max_bytes: Some(max_bytes.min(500_000)),
                     headers: BTreeMap::new(),
+                    method: None,
+                    body: None,
                     cache: FetchCachePolicy {
                         read: cache_read || no_network,
                         write: if no_network { false } else { cache_write },
                         ttl_s: cache_ttl_s,
+                        read_non_idempotent: false,
+                        stale_while_revalidate_s: None,
                     },
                 };
                 let t = std::time::Instant::now();
@@ -11265,10 +15549,14 @@ mod mcp {
                     timeout_ms: Some(timeout_ms),
                     max_bytes: Some(max_bytes),
                     headers: BTreeMap::new(),
+                    method: None,
+                    body: None,
                     cache: FetchCachePolicy {
                         read: cache_read || no_network,
                         write: if no_network { false } else { cache_write },
                         ttl_s: cache_ttl_s,
+                        read_non_idempotent: false,
+                        stale_while_revalidate_s: None,
                     },
                 };
                 let t = std::time::Instant::now();
@@ -11436,6 +15724,71 @@ mod mcp {
             Ok(tool_result(payload))
         }
 
+        #[tool(
+            description = "Sitemap discovery for seeding a crawl: read robots.txt, follow its Sitemap: directives (else /sitemap.xml), expand sitemap indexes and .xml.gz files, and return page URLs (bounded; robots.txt Disallow rules respected). Output: urls[] in sitemap order.",
+            input_schema = Arc::new(tool_input_schema_draft07::<WebSitemapArgs>()),
+            annotations(title = "Sitemap URLs", read_only_hint = true, open_world_hint = true)
+        )]
+        async fn web_sitemap(
+            &self,
+            params: Parameters<Option<WebSitemapArgs>>,
+        ) -> Result<CallToolResult, McpError> {
+            let args = params.0.unwrap_or_default();
+            self.stats_inc_tool("web_sitemap");
+            let t0 = std::time::Instant::now();
+            let site_url = args.site_url.unwrap_or_default().trim().to_string();
+            let max_urls = args.max_urls.unwrap_or(500).clamp(1, 10_000);
+            let request = serde_json::json!({ "site_url": site_url, "max_urls": max_urls });
+            let fail = |code: ErrorCode, msg: &str, hint: &str| {
+                let mut payload = serde_json::json!({
+                    "ok": false,
+                    "request": request,
+                    "error": error_obj(code, msg, hint),
+                });
+                add_envelope_fields(&mut payload, "web_sitemap", t0.elapsed().as_millis());
+                Ok(tool_result(payload))
+            };
+
+            if !matches!(reqwest::Url::parse(&site_url), Ok(u) if matches!(u.scheme(), "http" | "https"))
+            {
+                return fail(
+                    ErrorCode::InvalidUrl,
+                    "site_url must be an absolute http(s) URL",
+                    "Pass a site URL like https://docs.example.com/.",
+                );
+            }
+            if privacy_mode_from_env() == PrivacyMode::Anonymous
+                && anon_proxy_from_env().is_none()
+                && !is_localhost_url(&site_url)
+            {
+                return fail(
+                    ErrorCode::NotConfigured,
+                    "anonymous mode requires a proxy",
+                    "Set WEBPIPE_ANON_PROXY (recommended for Tor: socks5h://127.0.0.1:9050).",
+                );
+            }
+
+            let urls =
+                webpipe_local::links::discover_sitemap(&site_url, self.fetcher.as_ref(), max_urls)
+                    .await;
+            let mut payload = serde_json::json!({
+                "ok": true,
+                "request": request,
+                "count": urls.len(),
+                "capped": urls.len() >= max_urls,
+                "urls": urls,
+            });
+            if urls.is_empty() {
+                let warnings = ["sitemap_empty"];
+                let codes = warning_codes_from(&warnings);
+                payload["warnings"] = serde_json::json!(warnings);
+                payload["warning_codes"] = serde_json::json!(codes.clone());
+                payload["warning_hints"] = warning_hints_from(&codes);
+            }
+            add_envelope_fields(&mut payload, "web_sitemap", t0.elapsed().as_millis());
+            Ok(tool_result(payload))
+        }
+
         #[tool(
             description = "Best for: any question requiring web evidence — research, current info, library docs, papers, or any query where you don't know the exact URL. Not this when you already have the URL — use web_extract. Not this for raw bytes/status — use web_fetch.\n\nOutput (full by default): top_chunks[] + results[] + request + search.steps + warning_codes. Set minimal_output=true for a compact response (~10x smaller): top_chunks + warning_codes only.\n\nModes:\n- urls-mode: pass urls=[...] — NO API KEY REQUIRED, works out of the box\n- search-mode: pass query=... — requires a search provider key (WEBPIPE_BRAVE_API_KEY etc.)\n- cache-corpus: pass query=... + no_network=true to search local cache only\n\nPresets (exploration param): balanced (default) | deep (agentic discovery) | smart (balanced+agentic).\nFor full page text: set include_text=true (bounded by max_chars).\nFor JS-heavy pages: set fetch_backend=render (requires Playwright) or fetch_backend=firecrawl.",
             input_schema = Arc::new(tool_input_schema_draft07::<WebSearchExtractArgs>()),
@@ -11554,6 +15907,7 @@ mod mcp {
             let semantic_top_k = args.semantic_top_k.unwrap_or(5).min(50);
             let compact = args.compact.unwrap_or(true);
             let minimal_output = args.minimal_output.unwrap_or(false);
+            let max_output_bytes = args.max_output_bytes.map(|n| n.max(1_000));
             let retry_on_truncation = args.retry_on_truncation.unwrap_or(false);
             let truncation_retry_max_bytes = args.truncation_retry_max_bytes;
             // Default to agentic loop only when we're discovering URLs (search-mode).
@@ -11791,10 +16145,21 @@ mod mcp {
             };
 
             // Width vs depth preset: only fills in defaults when those fields were not explicitly set.
-            let exploration = args
-                .exploration
-                .clone()
-                .unwrap_or_else(|| "balanced".to_string());
+            // Search-mode calls without an explicit preset pick one from the query intent.
+            let query_intent = args
+                .query
+                .as_deref()
+                .filter(|q| !q.trim().is_empty())
+                .map(webpipe_local::textprep::classify_intent);
+            let exploration_auto = args.exploration.is_none() && !user_urls_provided;
+            let exploration = match (args.exploration.clone(), query_intent) {
+                (Some(e), _) => e,
+                (None, Some(intent)) if exploration_auto => {
+                    Self::exploration_preset_for_intent(intent).to_string()
+                }
+                (None, _) => "balanced".to_string(),
+            };
+            let exploration_auto = exploration_auto && query_intent.is_some();
             let mut preset_max_search_rounds: Option<usize> = None;
             match exploration.as_str() {
                 "balanced" => {}
@@ -11892,7 +16257,7 @@ mod mcp {
                 let md = web_search_extract_markdown(&payload);
                 return Ok(tool_result_markdown_with_json(payload, md));
             }
-            if selection_mode.as_str() != "score" && selection_mode.as_str() != "pareto" {
+            if !matches!(selection_mode.as_str(), "score" | "pareto" | "mmr") {
                 let mut payload = serde_json::json!({
                     "ok": false,
                     "provider": requested_provider,
@@ -11903,7 +16268,7 @@ mod mcp {
                     "error": error_obj(
                         ErrorCode::InvalidParams,
                         "unknown selection_mode",
-                        "Allowed selection_mode values: score, pareto"
+                        "Allowed selection_mode values: score, pareto, mmr"
                     ),
                 });
                 add_envelope_fields(&mut payload, "web_search_extract", t0.elapsed().as_millis());
@@ -12079,6 +16444,7 @@ mod mcp {
                             max_block_chars2,
                             include_text,
                             max_scan_entries,
+                            false,
                         )
                     });
                     let r = match tokio::time::timeout(remaining, handle).await {
@@ -12181,8 +16547,15 @@ mod mcp {
                         }));
                     }
 
-                    let selected =
-                        Self::select_top_chunks(all_chunks, top_chunks, selection_mode.as_str());
+                    let (selected, mmr_info) = self
+                        .select_top_chunks_with(
+                            all_chunks,
+                            top_chunks,
+                            selection_mode.as_str(),
+                            args.mmr_lambda,
+                            semantic_rerank,
+                        )
+                        .await;
                     let top_chunks_out: Vec<serde_json::Value> = selected
                         .into_iter()
                         .map(|c| {
@@ -12196,6 +16569,7 @@ mod mcp {
                         })
                         .collect();
 
+                    Self::attach_doc_ids(&mut per_url);
                     let mut payload = serde_json::json!({
                         "ok": true,
                         "mode": "cache",
@@ -12225,6 +16599,10 @@ mod mcp {
                         "results": per_url,
                         "top_chunks": top_chunks_out
                     });
+                    if let Some(info) = mmr_info {
+                        payload["request"]["mmr_lambda"] = info["lambda"].clone();
+                        payload["mmr"] = info;
+                    }
                     add_envelope_fields(
                         &mut payload,
                         "web_search_extract",
@@ -12233,6 +16611,12 @@ mod mcp {
                     if minimal_output {
                         strip_minimal_output(&mut payload);
                     }
+                    if let Some(cap) = max_output_bytes {
+                        if payload["request"].is_object() {
+                            payload["request"]["max_output_bytes"] = serde_json::json!(cap);
+                        }
+                        trim_to_max_output_bytes(&mut payload, cap);
+                    }
                     let md = web_search_extract_markdown(&payload);
                     return Ok(tool_result_markdown_with_json(payload, md));
                 }
@@ -12273,6 +16657,13 @@ mod mcp {
                         language: None,
                         country: None,
                         timeout_ms: Some(timeout_ms),
+                        diversify: None,
+                        max_per_domain: None,
+                        site: None,
+                        freshness: None,
+                        after: None,
+                        before: None,
+                        offset: None,
                     }))
                     .await?;
                 let sv = payload_from_result(&sr);
@@ -12440,6 +16831,47 @@ mod mcp {
                 (q, out)
             };
 
+            // YouTube playlists/channels stand in for their videos: swap each for up to
+            // `max_urls` video URLs (listed by yt-dlp), whose transcripts the fetch path pulls.
+            // Without yt-dlp the collection page is fetched as-is, with a warning.
+            let mut tool_warning_codes: Vec<&'static str> = Vec::new();
+            let urls = if no_network
+                || webpipe_local::youtube::youtube_transcripts_mode_from_env() == "off"
+            {
+                urls
+            } else {
+                let mut out = Vec::with_capacity(urls.len());
+                for u in urls {
+                    let is_collection = reqwest::Url::parse(u.trim())
+                        .is_ok_and(|pu| webpipe_local::youtube::is_youtube_collection(&pu));
+                    if !is_collection {
+                        out.push(u);
+                        continue;
+                    }
+                    let u2 = u.trim().to_string();
+                    let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                    let expanded = tokio::time::timeout(
+                        remaining,
+                        tokio::task::spawn_blocking(move || {
+                            webpipe_local::youtube::expand_playlist(&u2, max_urls)
+                        }),
+                    )
+                    .await;
+                    match expanded {
+                        Ok(Ok(Ok(videos))) => out.extend(videos),
+                        Ok(Ok(Err(code))) if code == "youtube_ytdlp_not_found" => {
+                            tool_warning_codes.push("youtube_ytdlp_not_found");
+                            out.push(u);
+                        }
+                        _ => {
+                            tool_warning_codes.push("youtube_playlist_expand_failed");
+                            out.push(u);
+                        }
+                    }
+                }
+                out
+            };
+
             // Dedup URLs while preserving the original order. This keeps `max_urls`
             // semantics predictable and avoids doing redundant work.
             let urls = {
@@ -12579,7 +17011,11 @@ mod mcp {
                                 (i, s, u.clone())
                             })
                             .collect();
-                        by_url_score.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                        by_url_score.sort_by(|a, b| {
+                            b.1.cmp(&a.1)
+                                .then_with(|| Self::url_tiebreak(&a.2, &b.2))
+                                .then_with(|| a.0.cmp(&b.0))
+                        });
 
                         // Bound prefetch work: refine only the top-N by URL score, capped.
                         // This keeps IO predictable while still considering URLs anywhere in the list.
@@ -12600,10 +17036,14 @@ mod mcp {
                                     timeout_ms: Some(timeout_ms.min(5_000)),
                                     max_bytes: Some(max_bytes.min(200_000)),
                                     headers: BTreeMap::new(),
+                                    method: None,
+                                    body: None,
                                     cache: webpipe_core::FetchCachePolicy {
                                         read: true,
                                         write: cache_write,
                                         ttl_s: cache_ttl_s,
+                                        read_non_idempotent: false,
+                                        stale_while_revalidate_s: None,
                                     },
                                 })
                                 .await
@@ -12644,11 +17084,12 @@ mod mcp {
                             scored.push((i, hint_score2, url_score2, cache_hit, u));
                         }
 
-                        // Sort: hint_score, then url_score, then cache_hit, then original order.
+                        // Sort: hint_score, then url_score, then cache_hit, then URL hash.
                         scored.sort_by(|a, b| {
                             b.1.cmp(&a.1)
                                 .then_with(|| b.2.cmp(&a.2))
                                 .then_with(|| (b.3 as u8).cmp(&(a.3 as u8)))
+                                .then_with(|| Self::url_tiebreak(&a.4, &b.4))
                                 .then_with(|| a.0.cmp(&b.0))
                         });
                         // Prefer URLs with *any* signal. If we have enough, drop zero-signal tail URLs
@@ -12834,7 +17275,37 @@ mod mcp {
                 }
             }
             let frontier_max = args.agentic_frontier_max.unwrap_or(200).clamp(50, 2_000);
+            let agentic_max_depth = args.agentic_max_depth;
+            let min_quality_score = args.agentic_min_quality_score;
+            let balance = args.balance.unwrap_or(false);
+            let include_error_body = args.include_error_body.unwrap_or(false);
+            let recency_boost = args.recency_boost.unwrap_or(0.0).clamp(0.0, 10.0) as f64;
+            let recency_half_life_days = args
+                .recency_half_life_days
+                .unwrap_or(30.0)
+                .clamp(1.0, 36_500.0);
+            // Discovery depth per canonical URL. Seeds (initial/search-round URLs) are absent (=0).
+            let mut url_depths = std::collections::HashMap::<String, usize>::new();
+            // Page each discovered URL was found on; sent as `Referer` when it is fetched.
+            let mut link_referers = std::collections::HashMap::<String, String>::new();
+            let mut depth_skipped: usize = 0;
             let mut stuck_streak: usize = 0;
+            // Speculative prefetch of the next-best frontier candidates into the cache.
+            // Only useful when the pick will read that same cache entry back.
+            let agentic_prefetch = args.agentic_prefetch.unwrap_or(1).clamp(1, 8);
+            let prefetch_enabled = agentic
+                && agentic_prefetch > 1
+                && fetch_backend == "local"
+                && firecrawl_primary.is_none()
+                && !no_network
+                && cache_read
+                && cache_write;
+            // canonical url -> (host, in-flight fetch)
+            let mut prefetch_pending =
+                std::collections::HashMap::<String, (String, tokio::task::JoinHandle<()>)>::new();
+            let mut prefetched = std::collections::HashSet::<String>::new();
+            let mut prefetch_started: usize = 0;
+            let mut prefetch_hits: usize = 0;
 
             let mut deadline_exceeded_partial: bool = false;
             let remaining_ms = || -> u64 {
@@ -12848,8 +17319,30 @@ mod mcp {
                 .unwrap_or(3)
                 .clamp(1, 10)
                 .min(max_urls);
+            let early_exit_cfg: Option<(u64, usize)> = args.early_exit.as_ref().map(|e| {
+                (
+                    e.min_top_score.unwrap_or(1).max(1),
+                    e.min_chunks.unwrap_or(1).clamp(1, 200),
+                )
+            });
+            let mut early_exit = false;
+            // The quality floor is checked once a round's seed URLs are processed, leaving at
+            // least one URL of budget for the search round it may trigger.
+            let quality_floor_due = |processed: usize, seeds: usize| {
+                (processed + seeds.max(1))
+                    .min(max_urls.saturating_sub(1))
+                    .max(processed + 1)
+            };
+            let mut quality_floor_at = quality_floor_due(0, frontier.len());
 
             while per_url.len() < max_urls {
+                if let Some((min_score, min_chunks)) = early_exit_cfg {
+                    let strong = all_chunks.iter().filter(|c| c.score >= min_score).count();
+                    if strong >= min_chunks {
+                        early_exit = true;
+                        break;
+                    }
+                }
                 // Hard-stop the loop when we ran out of wall clock budget. Return partial results.
                 let rem0 = remaining_ms();
                 if rem0 < 800 {
@@ -12944,11 +17437,13 @@ mod mcp {
                                 .collect();
                             let mut idxs = pare::pareto_indices(&metrics)
                                 .unwrap_or_else(|| (0..frontier.len()).collect());
-                            // Prefer higher prior (content-backed), then higher url_score, then stable order.
+                            // Prefer higher prior (content-backed), then higher url_score, then
+                            // URL hash (frontier order shifts with swap_remove; picks must not).
                             idxs.sort_by(|&ia, &ib| {
                                 prior_scores[ib]
                                     .cmp(&prior_scores[ia])
                                     .then_with(|| url_scores[ib].cmp(&url_scores[ia]))
+                                    .then_with(|| Self::url_tiebreak(&frontier[ia], &frontier[ib]))
                                     .then_with(|| ia.cmp(&ib))
                             });
                             let mut best_i = idxs.first().copied().unwrap_or(0);
@@ -12958,10 +17453,13 @@ mod mcp {
 
                             let picked = frontier.swap_remove(best_i);
                             let best_s = url_scores.get(best_i).copied().unwrap_or(0);
+                            let picked_key =
+                                canonicalize_url_no_frag(&picked).unwrap_or_else(|| picked.clone());
                             let trace_obj = serde_json::json!({
-                                "picked_url": canonicalize_url_no_frag(&picked).unwrap_or_else(|| picked.clone()),
+                                "picked_url": picked_key,
                                 "score": best_s,
-                                "prior": *priors.get(&canonicalize_url_no_frag(&picked).unwrap_or_else(|| picked.clone())).unwrap_or(&0),
+                                "prior": *priors.get(&picked_key).unwrap_or(&0),
+                                "depth": url_depths.get(&picked_key).copied().unwrap_or(0),
                                 "selector": "lexical",
                                 "planner_action": "pick_url",
                                 "planner_reason": serde_json::Value::Null,
@@ -12992,7 +17490,7 @@ mod mcp {
                                 "http_rate_limited" => p += 60,
                                 "main_content_low_signal" => p += 25,
                                 "chunks_filtered_low_signal" => p += 15,
-                                "body_truncated_by_max_bytes" => p += 12,
+                                "body_truncated_by_max_bytes" | "streaming_capped" => p += 12,
                                 "text_truncated_by_max_chars" => p += 8,
                                 "retried_due_to_truncation" => p += 3,
                                 "truncation_retry_used" => p += 3,
@@ -13045,19 +17543,41 @@ mod mcp {
                                 cache_ttl_s,
                                 width: Some(width),
                                 max_chars: Some(max_chars),
+                                truncation_strategy: None,
+                                engine: None,
                                 query: Some(query1.clone()).filter(|s| !s.trim().is_empty()),
                                 top_chunks: Some(top_chunks),
                                 max_chunk_chars: Some(max_chunk_chars),
                                 include_text: Some(include_text),
                                 include_links: Some(include_links),
                                 max_links: Some(max_links),
+                              include_alternates: None,
+                              prefer_amp: None,
+                              include_entities: None,
+                              include_tables: None,
+                              include_metadata: None,
+                              table_format: None,
+                              include_dates: None,
+                              date_order: None,
+                              include_keywords: None,
+                              max_keywords: None,
+                              chunk_dom_paths: None,
+                              referer: None,
+                              sentences: None,
+                              include_noscript: None,
+                              follow_iframes: None,
+                              allow_cross_origin_iframes: None,
+                              max_iframes: None,
+                              include_error_body: Some(include_error_body),
                                 include_structure: Some(include_structure),
                                 max_outline_items: Some(max_outline_items),
                                 max_blocks: Some(max_blocks),
                                 max_block_chars: Some(max_block_chars),
+                                sectioned: None,
                                 semantic_rerank: Some(semantic_rerank),
                                 semantic_auto_fallback: Some(false),
                                 semantic_top_k: Some(semantic_top_k),
+                                include_raw_html: None,
                                 retry_on_truncation: Some(retry_on_truncation),
                                 truncation_retry_max_bytes,
                             }))
@@ -13249,6 +17769,9 @@ mod mcp {
                                 }
                             }
                         }
+                        if let Some(pt) = v.get("published_time") {
+                            one["published_time"] = pt.clone();
+                        }
                         if !ok {
                             if let Some(e) = v.get("error") {
                                 one["error"] = e.clone();
@@ -13278,7 +17801,9 @@ mod mcp {
                                 "warnings",
                                 "warning_codes",
                                 "warning_hints",
+                                "error",
                                 "extract",
+                                "published_time",
                             ] {
                                 if let Some(v) = one.get(k) {
                                     out.insert(k.to_string(), v.clone());
@@ -13355,6 +17880,13 @@ mod mcp {
                             language: None,
                             country: None,
                             timeout_ms: Some(timeout_ms_eff),
+                            diversify: None,
+                            max_per_domain: None,
+                            site: None,
+                            freshness: None,
+                            after: None,
+                            before: None,
+                            offset: None,
                         }))
                         .await?;
                     let sv2 = payload_from_result(&sr2);
@@ -13400,6 +17932,7 @@ mod mcp {
                         "frontier_added": added,
                         "frontier_len_after": frontier.len(),
                     }));
+                    quality_floor_at = quality_floor_due(per_url.len(), added);
 
                     // Continue to selection now that we have a frontier again.
                     continue;
@@ -13462,11 +17995,13 @@ mod mcp {
                         .collect();
                     let mut idxs = pare::pareto_indices(&metrics)
                         .unwrap_or_else(|| (0..frontier.len()).collect());
-                    // Prefer higher prior (content-backed), then higher url_score, then stable order.
+                    // Prefer higher prior (content-backed), then higher url_score, then URL hash
+                    // (frontier order shifts with swap_remove; picks must not).
                     idxs.sort_by(|&ia, &ib| {
                         prior_scores[ib]
                             .cmp(&prior_scores[ia])
                             .then_with(|| url_scores[ib].cmp(&url_scores[ia]))
+                            .then_with(|| Self::url_tiebreak(&frontier[ia], &frontier[ib]))
                             .then_with(|| ia.cmp(&ib))
                     });
                     let mut best_i = idxs.first().copied().unwrap_or(0);
@@ -13655,12 +18190,95 @@ Rules:
                         agentic_force_firecrawl_next = true;
                     }
 
+                    // Speculative prefetch: warm the cache for the next-best candidates while this
+                    // pick is processed. Prefetches are not "processed" URLs (no max_urls charge).
+                    let mut prefetch_urls: Vec<String> = Vec::new();
+                    if prefetch_enabled && per_url.len() + 1 < max_urls {
+                        prefetch_pending.retain(|_, (_, h)| !h.is_finished());
+                        let host_of = |u: &str| {
+                            reqwest::Url::parse(u)
+                                .ok()
+                                .and_then(|p| p.host_str().map(|h| h.to_ascii_lowercase()))
+                        };
+                        let mut per_host: std::collections::HashMap<String, usize> =
+                            std::collections::HashMap::new();
+                        for (h, _) in prefetch_pending.values() {
+                            *per_host.entry(h.clone()).or_insert(0) += 1;
+                        }
+                        if let Some(h) = host_of(&frontier[best_i]) {
+                            *per_host.entry(h).or_insert(0) += 1;
+                        }
+                        let mut order: Vec<usize> =
+                            (0..frontier.len()).filter(|&i| i != best_i).collect();
+                        order.sort_by(|&ia, &ib| {
+                            prior_scores[ib]
+                                .cmp(&prior_scores[ia])
+                                .then_with(|| url_scores[ib].cmp(&url_scores[ia]))
+                                .then_with(|| Self::url_tiebreak(&frontier[ia], &frontier[ib]))
+                                .then_with(|| ia.cmp(&ib))
+                        });
+                        for i in order {
+                            if prefetch_pending.len() + 1 >= agentic_prefetch {
+                                break;
+                            }
+                            let u = frontier[i].as_str();
+                            let k = &canon[i];
+                            if prefetched.contains(k) || url_looks_like_auth_or_challenge(u) {
+                                continue;
+                            }
+                            // Rewritten URLs (arXiv, GitHub, gists) are fetched under another key.
+                            if self.maybe_rewrite_arxiv_abs_url(u).0 != u
+                                || self.maybe_rewrite_github_pr_or_commit_url(u).0 != u
+                                || self.maybe_rewrite_github_blob_url(u).0 != u
+                                || self.maybe_rewrite_gist_url(u).0 != u
+                                || webpipe_local::rewrite::github_repo_raw_readme_candidates(u)
+                                    .is_some()
+                            {
+                                continue;
+                            }
+                            let Some(host) = host_of(u) else {
+                                continue;
+                            };
+                            let n = per_host.entry(host.clone()).or_insert(0);
+                            if *n >= AGENTIC_PREFETCH_PER_HOST {
+                                continue;
+                            }
+                            *n += 1;
+                            let req = FetchRequest {
+                                url: u.to_string(),
+                                timeout_ms: Some(timeout_ms_eff),
+                                max_bytes: Some(max_bytes),
+                                headers: referer_headers(link_referers.get(k).map(String::as_str)),
+                                method: None,
+                                body: None,
+                                cache: FetchCachePolicy {
+                                    read: true,
+                                    write: true,
+                                    ttl_s: cache_ttl_s,
+                                    read_non_idempotent: false,
+                                    stale_while_revalidate_s: None,
+                                },
+                            };
+                            let fetcher = self.fetcher.clone();
+                            let handle = tokio::spawn(async move {
+                                let _ = fetcher.fetch(&req).await;
+                            });
+                            prefetch_pending.insert(k.clone(), (host, handle));
+                            prefetched.insert(k.clone());
+                            prefetch_started = prefetch_started.saturating_add(1);
+                            prefetch_urls.push(k.clone());
+                        }
+                    }
+
                     let picked = frontier.swap_remove(best_i);
                     let best_s = url_scores.get(best_i).copied().unwrap_or(0);
-                    let trace_obj = serde_json::json!({
-                        "picked_url": canonicalize_url_no_frag(&picked).unwrap_or_else(|| picked.clone()),
+                    let picked_key =
+                        canonicalize_url_no_frag(&picked).unwrap_or_else(|| picked.clone());
+                    let mut trace_obj = serde_json::json!({
+                        "picked_url": picked_key,
                         "score": best_s,
-                        "prior": *priors.get(&canonicalize_url_no_frag(&picked).unwrap_or_else(|| picked.clone())).unwrap_or(&0),
+                        "prior": *priors.get(&picked_key).unwrap_or(&0),
+                        "depth": url_depths.get(&picked_key).copied().unwrap_or(0),
                         "selector": selector_used,
                         "planner_action": planner_action,
                         "planner_reason": planner_reason,
@@ -13668,6 +18286,9 @@ Rules:
                         "force_firecrawl": planner_force_firecrawl,
                         "frontier_len_before": frontier.len() + 1
                     });
+                    if prefetch_enabled {
+                        trace_obj["prefetched"] = serde_json::json!(prefetch_urls);
+                    }
                     agentic_trace.push(trace_obj);
                     picked
                 } else {
@@ -13676,6 +18297,13 @@ Rules:
 
                 let url_owned = next_url;
                 let url = &url_owned;
+                let url_key = canonicalize_url_no_frag(url).unwrap_or_else(|| url.clone());
+                let url_depth = url_depths.get(&url_key).copied().unwrap_or(0);
+                let url_referer = link_referers.get(&url_key).cloned();
+                // A prefetch still in flight for this pick: wait for it instead of racing it.
+                if let Some((_, h)) = prefetch_pending.remove(&url_key) {
+                    let _ = h.await;
+                }
                 let per_t0 = std::time::Instant::now();
                 let mut attempts: serde_json::Value = serde_json::Value::Null;
                 let use_firecrawl_agentic = !firecrawl_disabled
@@ -13685,6 +18313,8 @@ Rules:
                     agentic_force_firecrawl_next = false;
                 }
                 let mut used_firecrawl_agentic = false;
+                let mut streaming_capped = false;
+                let mut decode_failed = false;
                 let (
                     raw_text,
                     raw_bytes,
@@ -13783,11 +18413,15 @@ Rules:
                         url: fetch_url.clone(),
                         timeout_ms: Some(timeout_ms_eff),
                         max_bytes: Some(max_bytes),
-                        headers: BTreeMap::new(),
+                        headers: referer_headers(url_referer.as_deref()),
+                        method: None,
+                        body: None,
                         cache: FetchCachePolicy {
                             read: cache_read || no_network,
                             write: if no_network { false } else { cache_write },
                             ttl_s: cache_ttl_s,
+                            read_non_idempotent: false,
+                            stale_while_revalidate_s: None,
                         },
                     };
                     let github_repo_attempts0 = github_repo_attempts;
@@ -13926,9 +18560,13 @@ Rules:
                             status: pr.status.unwrap_or(200),
                             content_type: Some("text/html".to_string()),
                             headers: BTreeMap::new(),
+                            wire_bytes: pr.html.len() as u64,
                             bytes: pr.html.into_bytes(),
                             truncated: false,
                             source: webpipe_core::FetchSource::Network,
+                            cache_status: None,
+                            body_path: None,
+                            revalidating: false,
                             timings_ms: {
                                 let mut m = BTreeMap::new();
                                 m.insert("playwright_render".to_string(), pr.elapsed_ms as u128);
@@ -14023,6 +18661,7 @@ Rules:
                         && !no_network
                         && retry_on_truncation
                         && fetched.truncated
+                        && !fetched.timings_ms.contains_key("streaming_capped")
                         && max_bytes > 0
                     {
                         let retry_cap_default = max_bytes.saturating_mul(2);
@@ -14046,11 +18685,15 @@ Rules:
                                 url: fetch_url0.clone(),
                                 timeout_ms: Some(timeout_ms_eff),
                                 max_bytes: Some(retry_cap),
-                                headers: BTreeMap::new(),
+                                headers: req.headers.clone(),
+                                method: None,
+                                body: None,
                                 cache: FetchCachePolicy {
                                     read: cache_read,
                                     write: cache_write,
                                     ttl_s: cache_ttl_s,
+                                    read_non_idempotent: false,
+                                    stale_while_revalidate_s: None,
                                 },
                             };
                             if let Ok(r2) = self.fetcher.fetch(&req2).await {
@@ -14145,6 +18788,8 @@ Rules:
                     let mut cache_io_timed_out = cache_io_disabled
                         || fetched.timings_ms.contains_key("cache_get_timeout")
                         || fetched.timings_ms.contains_key("cache_put_timeout");
+                    streaming_capped = fetched.timings_ms.contains_key("streaming_capped");
+                    decode_failed = fetched.timings_ms.contains_key("decode_failed");
 
                     // Attempt local extraction first; if it's empty and fallback is enabled + configured,
                     // retry *just this URL* via Firecrawl.
@@ -14249,10 +18894,14 @@ Rules:
                                     timeout_ms: Some(timeout_ms_eff),
                                     max_bytes: Some(max_bytes),
                                     headers: BTreeMap::new(),
+                                    method: None,
+                                    body: None,
                                     cache: FetchCachePolicy {
                                         read: cache_read,
                                         write: cache_write,
                                         ttl_s: cache_ttl_s,
+                                        read_non_idempotent: false,
+                                        stale_while_revalidate_s: None,
                                     },
                                 };
                                 match self.fetcher.fetch(&fb_req).await {
@@ -14332,6 +18981,10 @@ Rules:
                                                 || fetched
                                                     .timings_ms
                                                     .contains_key("cache_put_timeout");
+                                            streaming_capped =
+                                                fetched.timings_ms.contains_key("streaming_capped");
+                                            decode_failed =
+                                                fetched.timings_ms.contains_key("decode_failed");
                                             break;
                                         } else {
                                             let mut a =
@@ -14385,8 +19038,11 @@ Rules:
                             };
                             if let Some(u) = next {
                                 if let Some(k) = canonicalize_url_no_frag(u.as_str()) {
-                                    if seen_frontier.insert(k) {
-                                        // Prioritize the redirect target.
+                                    if seen_frontier.insert(k.clone()) {
+                                        // Prioritize the redirect target (same depth: not a new hop).
+                                        if url_depth > 0 {
+                                            url_depths.insert(k, url_depth);
+                                        }
                                         frontier.insert(0, u.to_string());
                                     }
                                 }
@@ -14399,7 +19055,9 @@ Rules:
                     let local_empty_extraction = local_text_chars == 0 && local_bytes_len > 0;
                     let local_low_signal = !local_empty_extraction
                         && local_text_chars > 0
-                        && Self::looks_like_bundle_gunk(&local_extracted_obj.text);
+                        && webpipe_local::extract::looks_like_bundle_gunk(
+                            &local_extracted_obj.text,
+                        );
 
                     if let Some(render_tuple) = {
                         let wants_render = (local_empty_extraction
@@ -14756,6 +19414,7 @@ Rules:
                             max_outline_items: 0,
                             max_blocks: 0,
                             max_block_chars: 0,
+                            truncation_strategy: webpipe_local::extract::TruncationStrategy::Head,
                         };
                         let mut p =
                             webpipe_local::extract::extract_pipeline_from_bytes(&[], None, "", cfg);
@@ -14778,6 +19437,8 @@ Rules:
                                     max_outline_items,
                                     max_blocks,
                                     max_block_chars,
+                                    truncation_strategy:
+                                        webpipe_local::extract::TruncationStrategy::Head,
                                 },
                             )
                         });
@@ -14805,6 +19466,8 @@ Rules:
                                     max_outline_items: 0,
                                     max_blocks: 0,
                                     max_block_chars: 0,
+                                    truncation_strategy:
+                                        webpipe_local::extract::TruncationStrategy::Head,
                                 };
                                 let mut p = webpipe_local::extract::extract_pipeline_from_bytes(
                                     &[],
@@ -14882,6 +19545,8 @@ Rules:
                                             max_outline_items,
                                             max_blocks,
                                             max_block_chars,
+                                            truncation_strategy:
+                                                webpipe_local::extract::TruncationStrategy::Head,
                                         },
                                     )
                                 })
@@ -14979,7 +19644,14 @@ Rules:
 
                 let mut warnings: Vec<&'static str> = Vec::new();
                 if truncated {
-                    warnings.push("body_truncated_by_max_bytes");
+                    warnings.push(if streaming_capped {
+                        "streaming_capped"
+                    } else {
+                        "body_truncated_by_max_bytes"
+                    });
+                }
+                if decode_failed {
+                    warnings.push("decode_failed");
                 }
                 let cache_io_disabled = std::env::var("WEBPIPE_CACHE_IO_TIMEOUT_MS")
                     .ok()
@@ -14993,6 +19665,7 @@ Rules:
                 if status_bad {
                     warnings.push("http_status_error");
                 }
+                let status_error = Self::http_status_is_error(status);
                 if status == 429 {
                     warnings.push("http_rate_limited");
                 }
@@ -15104,10 +19777,19 @@ Rules:
                     soft_junk_urls = soft_junk_urls.saturating_add(1);
                 }
 
+                // Graded counterpart of the low-signal warnings above (0 = unusable, 1 = clean prose).
+                let extraction_confidence = webpipe_local::extract::extraction_confidence(
+                    &extracted_obj,
+                    structure_opt.as_ref(),
+                );
+
                 // Collect chunk candidates with per-URL provenance signals for selection.
                 let warning_penalty = Self::warning_penalty(&warnings);
                 let cache_hit = fetch_source == "cache";
-                if !status_bad {
+                if cache_hit && prefetched.contains(&url_key) {
+                    prefetch_hits = prefetch_hits.saturating_add(1);
+                }
+                if !status_error {
                     for c in &chunks {
                         all_chunks.push(ChunkCandidate {
                             url: url.clone(),
@@ -15150,39 +19832,6 @@ Rules:
                     .join(" ");
                 let text_preview_truncated = preview_base.chars().count() > cap;
 
-                let mut one = serde_json::json!({
-                    "url": url,
-                    "ok": true,
-                    "fetch_backend": if used_firecrawl_fallback || used_firecrawl_agentic {
-                        "firecrawl"
-                    } else if used_render_fallback {
-                        "render"
-                    } else {
-                        fetch_backend.as_str()
-                    },
-                    "final_url": final_url,
-                    "status": status,
-                    "content_type": content_type,
-                    "bytes": bytes_len,
-                    "truncated": truncated,
-                    "fetch_source": fetch_source,
-                    // Always include attempts (null or object) so compact mode stays diagnosable.
-                    "attempts": attempts,
-                    "extract": {
-                        "engine": extracted_obj.engine,
-                        "width": width,
-                        "max_chars": max_chars,
-                        "text_chars": text_chars,
-                        "text_truncated": text_clipped,
-                        "text_preview": text_preview,
-                        "text_preview_source": text_preview_source,
-                        "text_preview_truncated": text_preview_truncated,
-                        "top_chunks": top_chunks,
-                        "max_chunk_chars": max_chunk_chars,
-                        "chunks": chunks
-                    },
-                    "elapsed_ms": per_t0.elapsed().as_millis()
-                });
                 // Deterministic quality scorecard (tail-risk detector).
                 // Additive: safe for existing consumers.
                 let quality = Self::quality_scorecard(
@@ -15192,7 +19841,72 @@ Rules:
                     extracted_obj.engine,
                     &warnings,
                 );
-                one["extract"]["quality"] = quality;
+                let published_time = if !is_pdf_like && extracted_obj.engine.starts_with("html") {
+                    webpipe_local::published::published_time(&raw_text)
+                } else {
+                    None
+                };
+                let mut one = serde_json::json!(webpipe_local::results::PerUrlResult {
+                    url: url.clone(),
+                    ok: true,
+                    fetch_backend: if used_firecrawl_fallback || used_firecrawl_agentic {
+                        "firecrawl"
+                    } else if used_render_fallback {
+                        "render"
+                    } else {
+                        fetch_backend.as_str()
+                    }
+                    .to_string(),
+                    final_url: final_url.clone(),
+                    status,
+                    content_type: content_type.clone(),
+                    bytes: bytes_len,
+                    truncated,
+                    fetch_source: fetch_source.to_string(),
+                    // Always include attempts (null or object) so compact mode stays diagnosable.
+                    attempts: attempts.clone(),
+                    extract: webpipe_local::results::ExtractResult {
+                        engine: extracted_obj.engine.to_string(),
+                        width,
+                        max_chars,
+                        text_chars,
+                        text_truncated: text_clipped,
+                        confidence: webpipe_local::results::ExtractResult::rounded_confidence(
+                            extraction_confidence,
+                        ),
+                        top_chunks,
+                        max_chunk_chars,
+                        chunks: chunks.clone(),
+                        text: include_text.then(|| text.clone()),
+                        text_preview: Some(text_preview),
+                        text_preview_source: Some(text_preview_source.to_string()),
+                        text_preview_truncated: Some(text_preview_truncated),
+                        quality: Some(quality),
+                        structure: if include_structure {
+                            structure_opt.clone()
+                        } else {
+                            None
+                        },
+                    },
+                    published_time,
+                    elapsed_ms: per_t0.elapsed().as_millis(),
+                });
+                if semantic_rerank && !query.trim().is_empty() {
+                    let cands: Vec<(usize, usize, String)> = chunks
+                        .iter()
+                        .map(|c| (c.start_char, c.end_char, c.text.clone()))
+                        .collect();
+                    let sem = self
+                        .semantic_rerank_or_fallback(
+                            &query,
+                            &cands,
+                            semantic_top_k,
+                            timeout_ms.saturating_div(2).clamp(2_000, 8_000),
+                        )
+                        .await;
+                    warnings.extend(sem.warnings.iter().copied());
+                    one["extract"]["semantic"] = serde_json::json!(sem);
+                }
                 if !warnings.is_empty() {
                     one["warnings"] = serde_json::json!(warnings);
                     let codes = warning_codes_from(&warnings);
@@ -15200,9 +19914,6 @@ Rules:
                     one["warning_hints"] = warning_hints_from(&codes);
                     self.stats_record_warnings(&warnings);
                 }
-                if include_text {
-                    one["extract"]["text"] = serde_json::json!(text);
-                }
                 if include_links {
                     if used_firecrawl_fallback {
                         one["extract"]["links"] = serde_json::json!([]);
@@ -15210,21 +19921,12 @@ Rules:
                         one["extract"]["links"] = serde_json::json!(links);
                     }
                 }
-                if include_structure {
-                    if let Some(s) = structure_opt.as_ref() {
-                        one["extract"]["structure"] = serde_json::json!(s);
+                if status_error {
+                    Self::mark_http_status_error(&mut one, status);
+                    if !include_error_body {
+                        Self::strip_error_body_from_extract(&mut one["extract"]);
                     }
                 }
-                if semantic_rerank && !query.trim().is_empty() {
-                    let cands: Vec<(usize, usize, String)> = chunks
-                        .iter()
-                        .map(|c| (c.start_char, c.end_char, c.text.clone()))
-                        .collect();
-                    let sem = self
-                        .semantic_rerank_chunks_best(&query, &cands, semantic_top_k)
-                        .await;
-                    one["extract"]["semantic"] = serde_json::json!(sem);
-                }
                 if compact {
                     // Reduce duplication: `top_chunks` already carries the evidence text,
                     // so per-URL compact mode drops `extract.chunks` (and some knob echoes).
@@ -15251,7 +19953,9 @@ Rules:
                         "warnings",
                         "warning_codes",
                         "warning_hints",
+                        "error",
                         "extract",
+                        "published_time",
                     ] {
                         if let Some(v) = one.get(k) {
                             out.insert(k.to_string(), v.clone());
@@ -15332,12 +20036,18 @@ Rules:
                     // Propagate content relevance to discovered links. This is the “loop”:
                     // good pages contribute candidate URLs more strongly than weak pages.
                     let parent_relevance = chunks.iter().map(|c| c.score).max().unwrap_or(0);
+                    let child_depth = url_depth + 1;
                     let mut added = 0usize;
+                    let mut too_deep = 0usize;
                     for cand in discovered {
                         let u = cand.url;
                         if frontier.len() >= frontier_max {
                             break;
                         }
+                        if agentic_max_depth.is_some_and(|d| child_depth > d) {
+                            too_deep += 1;
+                            continue;
+                        }
                         // Drop obvious auth/challenge/tracking/homepage URLs from discovery.
                         if url_looks_like_auth_or_challenge(&u)
                             || url_looks_like_promo_or_tracking(&u)
@@ -15406,6 +20116,8 @@ Rules:
                                 };
                                 let prior_add = base_parent.saturating_mul(hits.min(10));
                                 *entry = (*entry).max(prior_add);
+                                link_referers.insert(k.clone(), final_url.clone());
+                                url_depths.insert(k, child_depth);
                                 frontier.push(u);
                                 added += 1;
                             }
@@ -15418,8 +20130,15 @@ Rules:
                                 "frontier_len_after".to_string(),
                                 serde_json::json!(frontier.len()),
                             );
+                            if too_deep > 0 {
+                                obj.insert(
+                                    "depth_skipped".to_string(),
+                                    serde_json::json!(too_deep),
+                                );
+                            }
                         }
                     }
+                    depth_skipped += too_deep;
                 }
 
                 // If we're stuck, proactively broaden the frontier via another search round.
@@ -15437,6 +20156,34 @@ Rules:
                     }));
                     stuck_streak = 0;
                 }
+
+                // Weak initial results: if nothing from this round's seeds clears the quality
+                // floor, broaden via another search round rather than returning junk.
+                if let Some(floor) = min_quality_score {
+                    if agentic
+                        && !no_network
+                        && !query.trim().is_empty()
+                        && !frontier.is_empty()
+                        && search_rounds < max_search_rounds
+                        && per_url.len() >= quality_floor_at
+                        && per_url.len() < max_urls
+                    {
+                        let best_score = all_chunks.iter().map(|c| c.score).max().unwrap_or(0);
+                        if best_score < floor {
+                            frontier.clear();
+                            agentic_trace.push(serde_json::json!({
+                                "quality_floor": true,
+                                "min_quality_score": floor,
+                                "best_score": best_score,
+                                "urls_processed": per_url.len(),
+                                "action": "search_more",
+                            }));
+                        } else {
+                            // Met: no more checks unless a later round resets this.
+                            quality_floor_at = usize::MAX;
+                        }
+                    }
+                }
             }
 
             // Filter out redirect sources if their target was also fetched.
@@ -15494,20 +20241,34 @@ Rules:
                 all_chunks.retain(|c| !redirect_urls_to_drop.contains(&c.url));
             }
 
-            let selected = Self::select_top_chunks(all_chunks, top_chunks, selection_mode.as_str());
+            if recency_boost > 0.0 {
+                Self::apply_recency_boost(
+                    &mut all_chunks,
+                    &mut per_url,
+                    recency_boost,
+                    recency_half_life_days,
+                );
+            }
+            let balance_pool = balance.then(|| all_chunks.clone());
+            let (selected, mmr_info) = self
+                .select_top_chunks_with(
+                    all_chunks,
+                    top_chunks,
+                    selection_mode.as_str(),
+                    args.mmr_lambda,
+                    semantic_rerank,
+                )
+                .await;
             let max_selected_score = selected.iter().map(|c| c.score).max().unwrap_or(0);
-            let top_chunks_out: Vec<serde_json::Value> = selected
-                .into_iter()
-                .map(|c| {
-                    serde_json::json!({
-                        "url": c.url,
-                        "score": c.score,
-                        "start_char": c.start_char,
-                        "end_char": c.end_char,
-                        "text": c.text
-                    })
-                })
-                .collect();
+            let contrasting_chunks_out: Option<Vec<webpipe_local::results::Chunk>> = balance_pool
+                .map(|pool| {
+                    Self::select_contrasting_chunks(&selected, &pool, top_chunks.min(3))
+                        .into_iter()
+                        .map(|(c, contrast)| c.into_chunk(Some(contrast)))
+                        .collect()
+                });
+            let top_chunks_out: Vec<webpipe_local::results::Chunk> =
+                selected.into_iter().map(|c| c.into_chunk(None)).collect();
 
             let mode = if search_steps.is_empty() {
                 "urls"
@@ -15520,55 +20281,86 @@ Rules:
                 requested_provider.clone()
             };
 
-            let mut payload = serde_json::json!({
-                "ok": true,
-                "mode": mode,
-                "provider": provider_out,
-                "query": query,
-                "request": {
-                    "provider": requested_provider,
-                        "auto_mode": requested_auto_mode,
-                    "selection_mode": selection_mode,
-                    "exploration": exploration,
-                    "fetch_backend": fetch_backend,
-                    "max_results": max_results,
-                    "max_urls": max_urls,
-                    "max_parallel_urls": max_parallel_urls,
-                    "url_selection_mode": url_selection_mode,
-                    "timeout_ms": timeout_ms,
-                    "deadline_ms": deadline_ms,
-                    "max_bytes": max_bytes,
-                    "retry_on_truncation": retry_on_truncation,
-                    "truncation_retry_max_bytes": truncation_retry_max_bytes,
-                    "width": width,
-                    "max_chars": max_chars,
-                    "top_chunks": top_chunks,
-                    "max_chunk_chars": max_chunk_chars,
-                    "include_links": include_links,
-                    "max_links": max_links,
-                    "include_text": include_text,
-                    "domains_allow": domains_allow,
-                    "domains_deny": domains_deny,
-                    "agentic": agentic,
-                    "agentic_max_search_rounds": max_search_rounds,
-                    "agentic_frontier_max": frontier_max,
-                    "planner_max_calls": planner_max_calls,
-                        "no_network": no_network,
-                    "firecrawl_fallback_on_empty_extraction": firecrawl_fallback_on_empty_extraction,
-                    "firecrawl_fallback_on_low_signal": firecrawl_fallback_on_low_signal,
-                    "render_fallback_on_empty_extraction": render_fallback_on_empty_extraction,
-                    "render_fallback_on_low_signal": render_fallback_on_low_signal,
-                    "cache": { "read": cache_read_effective, "write": cache_write_effective, "ttl_s": cache_ttl_s },
-                    "compact": compact
-                },
-                "url_count_in": urls.len(),
-                "url_count_used": per_url.len(),
-                "results": per_url,
-                "top_chunks": top_chunks_out
+            Self::attach_doc_ids(&mut per_url);
+            let request = serde_json::json!({
+                        "provider": requested_provider,
+                            "auto_mode": requested_auto_mode,
+                        "selection_mode": selection_mode,
+                        "exploration": exploration,
+                        "exploration_auto": exploration_auto,
+                        "intent": query_intent.map(|i| i.as_str()),
+                        "fetch_backend": fetch_backend,
+                        "max_results": max_results,
+                        "max_urls": max_urls,
+                        "max_parallel_urls": max_parallel_urls,
+                        "url_selection_mode": url_selection_mode,
+                        "timeout_ms": timeout_ms,
+                        "deadline_ms": deadline_ms,
+                        "max_bytes": max_bytes,
+                        "retry_on_truncation": retry_on_truncation,
+                        "truncation_retry_max_bytes": truncation_retry_max_bytes,
+                        "width": width,
+                        "max_chars": max_chars,
+                        "top_chunks": top_chunks,
+                        "max_chunk_chars": max_chunk_chars,
+                        "include_links": include_links,
+                        "max_links": max_links,
+                        "include_text": include_text,
+                        "domains_allow": domains_allow,
+                        "domains_deny": domains_deny,
+                        "agentic": agentic,
+                        "agentic_max_search_rounds": max_search_rounds,
+                        "agentic_frontier_max": frontier_max,
+                        "agentic_max_depth": agentic_max_depth,
+                        "agentic_min_quality_score": min_quality_score,
+                        "agentic_prefetch": agentic_prefetch,
+                        "planner_max_calls": planner_max_calls,
+                            "no_network": no_network,
+                        "firecrawl_fallback_on_empty_extraction": firecrawl_fallback_on_empty_extraction,
+                        "firecrawl_fallback_on_low_signal": firecrawl_fallback_on_low_signal,
+                        "render_fallback_on_empty_extraction": render_fallback_on_empty_extraction,
+                        "render_fallback_on_low_signal": render_fallback_on_low_signal,
+                        "cache": { "read": cache_read_effective, "write": cache_write_effective, "ttl_s": cache_ttl_s },
+                        "compact": compact,
+                        "early_exit": early_exit_cfg.map(|(min_top_score, min_chunks)| serde_json::json!({
+                            "min_top_score": min_top_score,
+                            "min_chunks": min_chunks
+                        })),
+                        "balance": balance,
+                        "include_error_body": include_error_body,
+                        "recency_boost": recency_boost,
+                        "recency_half_life_days": recency_half_life_days,
+                        "max_output_bytes": max_output_bytes
+
+            });
+            let mut payload = serde_json::json!(webpipe_local::results::SearchExtractResult {
+                ok: true,
+                mode: mode.to_string(),
+                provider: provider_out,
+                query: query.clone(),
+                request,
+                url_count_in: urls.len(),
+                url_count_used: per_url.len(),
+                results: per_url.clone(),
+                top_chunks: top_chunks_out,
             });
             if !search_steps.is_empty() {
                 payload["search"] = serde_json::json!({ "steps": search_steps });
             }
+            if early_exit_cfg.is_some() {
+                payload["early_exit"] = serde_json::json!(early_exit);
+            }
+            if let Some(contrasting) = contrasting_chunks_out {
+                payload["contrasting_chunks"] = serde_json::json!(contrasting);
+                payload["balance"] = serde_json::json!({
+                    "method": "lexical_contrast",
+                    "best_effort": true
+                });
+            }
+            if let Some(info) = mmr_info {
+                payload["request"]["mmr_lambda"] = info["lambda"].clone();
+                payload["mmr"] = info;
+            }
             if let Some(ref k) = query_key {
                 payload["query_key"] = serde_json::json!(k);
             }
@@ -15586,6 +20378,12 @@ Rules:
                     "Install Playwright (Node) + browsers, or set fetch_backend=\"local\" for non-JS pages."
                 );
             }
+            if payload["ok"].as_bool() == Some(true) {
+                let n_top = payload["top_chunks"].as_array().map_or(0, |a| a.len());
+                if let Some(d) = search_extract_diagnostics(&per_url, n_top) {
+                    payload["diagnostics"] = d;
+                }
+            }
             // E2E/debugging: always surface which provider actually supplied results, even when
             // the requested provider was "auto" or "merge".
             //
@@ -15599,11 +20397,15 @@ Rules:
             if agentic {
                 if compact {
                     let mut stuck_events = 0usize;
+                    let mut quality_floor_events = 0usize;
                     let mut frontier_added_total = 0usize;
                     for t in &agentic_trace {
                         if t.get("stuck").and_then(|v| v.as_bool()) == Some(true) {
                             stuck_events = stuck_events.saturating_add(1);
                         }
+                        if t.get("quality_floor").and_then(|v| v.as_bool()) == Some(true) {
+                            quality_floor_events = quality_floor_events.saturating_add(1);
+                        }
                         frontier_added_total = frontier_added_total.saturating_add(
                             t.get("frontier_added")
                                 .and_then(|v| v.as_u64())
@@ -15615,17 +20417,30 @@ Rules:
                         "trace_len": agentic_trace.len(),
                         "search_rounds": search_rounds,
                         "stuck_events": stuck_events,
+                        "quality_floor_events": quality_floor_events,
                         "frontier_added_total": frontier_added_total,
                         "frontier_len_final": frontier.len(),
                         "urls_fetched": per_url.len(),
+                        "depth_skipped": depth_skipped,
                     });
                 } else {
                     payload["agentic"] = serde_json::json!({
                         "enabled": true,
+                        "depth_skipped": depth_skipped,
                         "trace": agentic_trace
                     });
                 }
+                if agentic_prefetch > 1 {
+                    payload["agentic"]["prefetch"] = serde_json::json!({
+                        "requested": agentic_prefetch,
+                        "enabled": prefetch_enabled,
+                        "started": prefetch_started,
+                        "hits": prefetch_hits,
+                    });
+                }
             }
+            // Unpicked prefetches only warm the cache; let them finish in the background.
+            drop(prefetch_pending);
 
             // Tool-level warning: none of the selected chunks appears to match the query (beyond
             // the query-less fallback chunks, which carry score=1).
@@ -15640,7 +20455,10 @@ Rules:
             }
 
             if deadline_exceeded_partial {
-                // Tool-level warning (not tied to a specific URL). Keep it compact; merge logic later
+                tool_warning_codes.push("deadline_exceeded_partial");
+            }
+            if !tool_warning_codes.is_empty() {
+                // Tool-level warnings (not tied to a specific URL). Keep it compact; merge logic later
                 // will combine this with per-URL warnings.
                 // Merge with any existing tool-level warning codes.
                 let mut merged = std::collections::BTreeSet::<String>::new();
@@ -15652,7 +20470,7 @@ Rules:
                         }
                     }
                 }
-                merged.insert("deadline_exceeded_partial".to_string());
+                merged.extend(tool_warning_codes.iter().map(|c| c.to_string()));
                 let merged_vec = merged.into_iter().collect::<Vec<_>>();
                 payload["warning_codes"] = serde_json::json!(merged_vec.clone());
                 let codes_ref = merged_vec
@@ -15729,7 +20547,7 @@ Rules:
                         "all_chunks_low_signal" => 3,
                         "main_content_low_signal" => 4,
                         "chunks_filtered_low_signal" => 5,
-                        "body_truncated_by_max_bytes" => 6,
+                        "body_truncated_by_max_bytes" | "streaming_capped" => 6,
                         _ => 9,
                     }
                 }
@@ -15798,6 +20616,9 @@ Rules:
             if minimal_output {
                 strip_minimal_output(&mut payload);
             }
+            if let Some(cap) = max_output_bytes {
+                trim_to_max_output_bytes(&mut payload, cap);
+            }
             let md = web_search_extract_markdown(&payload);
             Ok(tool_result_markdown_with_json(payload, md))
         }
@@ -15870,6 +20691,32 @@ Rules:
             let semantic_rerank = args.semantic_rerank.unwrap_or(false);
             let semantic_top_k = args.semantic_top_k.unwrap_or(5).min(50);
             let compact = args.compact.unwrap_or(true);
+            let scoring = args
+                .scoring
+                .as_deref()
+                .map(|s| s.trim().to_ascii_lowercase())
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "lexical".to_string());
+            if !matches!(scoring.as_str(), "lexical" | "hybrid") {
+                let mut payload = serde_json::json!({
+                    "ok": false,
+                    "query": query,
+                    "error": error_obj(
+                        ErrorCode::InvalidParams,
+                        format!("unknown scoring: {scoring}"),
+                        "Use scoring=\"lexical\" (default) or scoring=\"hybrid\"."
+                    ),
+                });
+                add_envelope_fields(
+                    &mut payload,
+                    "web_cache_search_extract",
+                    t0.elapsed().as_millis(),
+                );
+                let md = web_cache_search_extract_markdown(&payload);
+                return Ok(tool_result_markdown_with_json(payload, md));
+            }
+            let hybrid = scoring == "hybrid";
+            let hybrid_weight = args.hybrid_weight.unwrap_or(0.5).clamp(0.0, 1.0);
 
             let cache_dir_s = cache_dir.to_string_lossy().to_string();
             let query_s = query.clone();
@@ -15932,6 +20779,7 @@ Rules:
                     max_block_chars,
                     include_text,
                     max_scan_entries,
+                    hybrid,
                 )
             });
             let r = match tokio::time::timeout(
@@ -16155,9 +21003,39 @@ Rules:
                 }
             }
 
+            let mut hybrid_warnings: Vec<&'static str> = Vec::new();
+            let scoring_meta = if hybrid {
+                let meta = match results_json.as_array_mut() {
+                    Some(arr) => {
+                        self.apply_hybrid_cache_scoring(&query, arr, hybrid_weight)
+                            .await
+                    }
+                    None => Err("no_results"),
+                };
+                match meta {
+                    Ok(m) => Some(m),
+                    Err(reason) => {
+                        // Lexical fallback: the same docs lexical mode would have returned.
+                        if let Some(arr) = results_json.as_array_mut() {
+                            arr.retain(|d| {
+                                d.get("score").and_then(|v| v.as_u64()).unwrap_or(0) > 0
+                            });
+                        }
+                        hybrid_warnings.push("hybrid_scoring_fell_back_to_lexical");
+                        Some(serde_json::json!({
+                            "mode": "lexical",
+                            "requested": "hybrid",
+                            "fallback_reason": reason,
+                        }))
+                    }
+                }
+            } else {
+                None
+            };
+
             if semantic_rerank {
                 if let Some(arr) = results_json.as_array_mut() {
-                    let use_embeddings = Self::openrouter_api_key_from_env().is_some();
+                    let unavailable = Self::semantic_unavailable_reason();
                     let max_embed_docs = Self::semantic_embeddings_max_docs_from_env();
                     for (doc_i, one) in arr.iter_mut().enumerate() {
                         let chunks = one
@@ -16178,10 +21056,15 @@ Rules:
                                 .to_string();
                             cands.push((sc, ec, txt));
                         }
-                        let sem = if use_embeddings && doc_i < max_embed_docs && !cands.is_empty() {
+                        let sem = if doc_i < max_embed_docs || unavailable.is_some() {
                             serde_json::json!(
-                                self.semantic_rerank_chunks_best(&query, &cands, semantic_top_k)
-                                    .await
+                                self.semantic_rerank_or_fallback(
+                                    &query,
+                                    &cands,
+                                    semantic_top_k,
+                                    8_000
+                                )
+                                .await
                             )
                         } else {
                             serde_json::json!(webpipe_local::semantic::semantic_rerank_chunks(
@@ -16291,6 +21174,7 @@ Rules:
                             "warning_codes",
                             "warning_hints",
                             "semantic",
+                            "hybrid",
                             "extract",
                         ] {
                             if let Some(v) = one.get(k) {
@@ -16325,9 +21209,14 @@ Rules:
                     "max_block_chars": max_block_chars,
                     "semantic_rerank": semantic_rerank,
                     "semantic_top_k": semantic_top_k,
+                    "scoring": scoring,
+                    "hybrid_weight": hybrid_weight,
                     "compact": compact
                 }
             });
+            if let Some(m) = scoring_meta {
+                payload["scoring"] = m;
+            }
             if let Some((before, after)) = dedup_stats {
                 payload["dedup"] = serde_json::json!({
                     "before": before,
@@ -16335,27 +21224,21 @@ Rules:
                     "dropped": before.saturating_sub(after)
                 });
             }
-            if semantic_rerank {
-                let sem_probe = webpipe_local::semantic::semantic_rerank_chunks(&query, &[], 1);
-                let codes: Vec<&'static str> = sem_probe
-                    .warnings
-                    .iter()
-                    .copied()
-                    .map(normalize_warning_code)
-                    .collect();
-                if !codes.is_empty() && !sem_probe.ok {
+            let mut top_warnings = r.warnings.clone();
+            top_warnings.extend(hybrid_warnings);
+            if semantic_rerank && !query.trim().is_empty() {
+                if let Some(reason) = Self::semantic_unavailable_reason() {
+                    top_warnings.push("semantic_unavailable");
                     payload["semantic_status"] = serde_json::json!({
                         "ok": false,
-                        "backend": sem_probe.backend,
-                        "model_id": sem_probe.model_id,
-                        "warning_codes": codes.clone(),
-                        "warning_hints": warning_hints_from(&codes),
+                        "backend": "none",
+                        "unavailable_reason": reason,
                     });
                 }
             }
-            if !r.warnings.is_empty() {
-                payload["warnings"] = serde_json::json!(r.warnings);
-                let codes = warning_codes_from(&r.warnings);
+            if !top_warnings.is_empty() {
+                payload["warnings"] = serde_json::json!(top_warnings);
+                let codes = warning_codes_from(&top_warnings);
                 payload["warning_codes"] = serde_json::json!(codes.clone());
                 payload["warning_hints"] = warning_hints_from(&codes);
             }
@@ -16368,6 +21251,658 @@ Rules:
             Ok(tool_result_markdown_with_json(payload, md))
         }
 
+        /// One bounded auxiliary LLM call on a synthesis backend (follow-ups, query refinement).
+        ///
+        /// Best-effort: returns None on any backend failure; usage is still recorded in stats.
+        #[allow(clippy::too_many_arguments)]
+        async fn deep_research_side_call(
+            &self,
+            backend: &str,
+            model: &str,
+            llm_model: Option<String>,
+            sys: &str,
+            user: String,
+            max_tokens: u64,
+            timeout_ms: u64,
+            no_network: bool,
+        ) -> Option<String> {
+            if no_network && self.llm.get(backend)?.network_only() {
+                return None;
+            }
+            let mut opts = deep_research_llm_opts(backend, model, llm_model, timeout_ms);
+            opts.max_tokens = Some(max_tokens);
+            opts.temperature = Some(0.2);
+            if backend == "perplexity" {
+                opts.search_mode = Some("off".to_string());
+            }
+            let prompt = webpipe_local::llm::Prompt {
+                system: sys.to_string(),
+                user,
+            };
+            self.llm_complete(backend, &prompt, &opts)
+                .await
+                .ok()
+                .map(|c| c.text)
+        }
+
+        /// One bounded LLM call (same backend as synthesis) proposing follow-up research questions.
+        ///
+        /// Best-effort: returns None on any backend or parse failure so `web_deep_research` can omit
+        /// `followups` without failing.
+        #[allow(clippy::too_many_arguments)]
+        async fn deep_research_followups(
+            &self,
+            backend: &str,
+            model: &str,
+            llm_model: Option<String>,
+            query: &str,
+            answer: &str,
+            evidence_pack: &serde_json::Value,
+            timeout_ms: u64,
+            no_network: bool,
+        ) -> Option<Vec<serde_json::Value>> {
+            let sys = "You suggest follow-up research questions. Reply with ONLY a JSON array of 3-5 objects shaped like {\"question\": string, \"rationale\": string}. Each rationale names the gap in the answer or evidence that the question addresses.";
+            let user = serde_json::json!({
+                "question": query,
+                "answer": answer,
+                "evidence": evidence_pack,
+            })
+            .to_string();
+            let (user, _n_user, _user_clipped) = Self::truncate_to_chars(&user, 20_000);
+            let raw = self
+                .deep_research_side_call(
+                    backend, model, llm_model, sys, user, 800, timeout_ms, no_network,
+                )
+                .await?;
+            let items = parse_deep_research_followups(&raw);
+            (!items.is_empty()).then_some(items)
+        }
+
+        /// One bounded LLM call proposing the pass-2 search query from pass-1 chunks.
+        ///
+        /// Best-effort: None on backend or parse failure (the caller falls back to keywords).
+        #[allow(clippy::too_many_arguments)]
+        async fn deep_research_refine_query(
+            &self,
+            backend: &str,
+            model: &str,
+            llm_model: Option<String>,
+            query: &str,
+            pass1_chunks: &[serde_json::Value],
+            timeout_ms: u64,
+            no_network: bool,
+        ) -> Option<String> {
+            let sys = "You refine web search queries. Given a research question and first-round evidence chunks, reply with ONLY a JSON object {\"query\": string}: one web search query that targets what the evidence is still missing or leaves unresolved.";
+            let user = serde_json::json!({
+                "question": query,
+                "evidence": pass1_chunks,
+            })
+            .to_string();
+            let (user, _n_user, _user_clipped) = Self::truncate_to_chars(&user, 12_000);
+            let raw = self
+                .deep_research_side_call(
+                    backend, model, llm_model, sys, user, 200, timeout_ms, no_network,
+                )
+                .await?;
+            parse_deep_research_refined_query(&raw)
+                .filter(|q| !q.eq_ignore_ascii_case(query.trim()))
+        }
+
+        /// Validate a structured-report synthesis reply, with one repair call on failure.
+        ///
+        /// Returns the validated report (None when the repair also fails) and whether a repair
+        /// call was made.
+        #[allow(clippy::too_many_arguments)]
+        async fn deep_research_report_from_reply(
+            &self,
+            backend: &str,
+            model: &str,
+            llm_model: Option<String>,
+            reply: &str,
+            sources: &[serde_json::Value],
+            max_tokens: Option<u64>,
+            timeout_ms: u64,
+            no_network: bool,
+        ) -> (Option<serde_json::Value>, bool) {
+            let err = match validate_deep_research_report(reply, sources) {
+                Ok(r) => return (Some(r), false),
+                Err(e) => e,
+            };
+            let sys = format!(
+                "You repair malformed research reports. Reply with ONLY the corrected JSON object. {DEEP_RESEARCH_REPORT_SCHEMA}"
+            );
+            let (reply, _n, _clipped) = Self::truncate_to_chars(reply, 12_000);
+            let user = serde_json::json!({
+                "error": err,
+                "reply": reply,
+                "sources": sources,
+            })
+            .to_string();
+            let repaired = self
+                .deep_research_side_call(
+                    backend,
+                    model,
+                    llm_model,
+                    &sys,
+                    user,
+                    max_tokens.unwrap_or(2_000),
+                    timeout_ms,
+                    no_network,
+                )
+                .await;
+            let report = repaired.and_then(|r| validate_deep_research_report(&r, sources).ok());
+            (report, true)
+        }
+
+        #[tool(
+            description = "Maintenance: re-key legacy v1 cache entries in WEBPIPE_CACHE_DIR into the current (v2) key space in bulk (no network). Output: counts (scanned, legacy_found, migrated, already_migrated, removed_legacy, skipped, not_legacy).",
+            input_schema = Arc::new(tool_input_schema_draft07::<WebCacheMigrateArgs>()),
+            annotations(
+                title = "Cache migrate",
+                read_only_hint = false,
+                destructive_hint = true,
+                idempotent_hint = true,
+                open_world_hint = false
+            )
+        )]
+        async fn web_cache_migrate(
+            &self,
+            params: Parameters<Option<WebCacheMigrateArgs>>,
+        ) -> Result<CallToolResult, McpError> {
+            let args = params.0.unwrap_or_default();
+            self.stats_inc_tool("web_cache_migrate");
+            let t0 = std::time::Instant::now();
+            let max_entries = args.max_entries.unwrap_or(20_000).clamp(1, 200_000);
+            let remove_legacy = args.remove_legacy.unwrap_or(false);
+            let request = serde_json::json!({
+                "max_entries": max_entries,
+                "remove_legacy": remove_legacy
+            });
+
+            let Some(cache_dir) = cache_dir_from_env() else {
+                let mut payload = serde_json::json!({
+                    "ok": false,
+                    "request": request,
+                    "error": error_obj(
+                        ErrorCode::NotConfigured,
+                        "WEBPIPE_CACHE_DIR is not set",
+                        "Set WEBPIPE_CACHE_DIR to the cache you want to migrate."
+                    ),
+                });
+                add_envelope_fields(&mut payload, "web_cache_migrate", t0.elapsed().as_millis());
+                return Ok(tool_result(payload));
+            };
+
+            let cache = webpipe_local::FsCache::new(cache_dir.clone());
+            let report = match tokio::task::spawn_blocking(move || {
+                cache.migrate_all(max_entries, remove_legacy)
+            })
+            .await
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    let mut payload = serde_json::json!({
+                        "ok": false,
+                        "cache_dir": cache_dir.to_string_lossy(),
+                        "request": request,
+                        "error": error_obj(
+                            ErrorCode::CacheError,
+                            format!("cache migration task failed: {e}"),
+                            "Retry; if it persists, check WEBPIPE_CACHE_DIR permissions."
+                        ),
+                    });
+                    add_envelope_fields(
+                        &mut payload,
+                        "web_cache_migrate",
+                        t0.elapsed().as_millis(),
+                    );
+                    return Ok(tool_result(payload));
+                }
+            };
+
+            let mut payload = serde_json::json!({
+                "ok": true,
+                "cache_dir": cache_dir.to_string_lossy(),
+                "request": request,
+                "counts": report,
+            });
+            if report.truncated {
+                let ws = ["cache_migrate_truncated"];
+                payload["warnings"] = serde_json::json!(ws);
+                let codes = warning_codes_from(&ws);
+                payload["warning_codes"] = serde_json::json!(codes.clone());
+                payload["warning_hints"] = warning_hints_from(&codes);
+            }
+            add_envelope_fields(&mut payload, "web_cache_migrate", t0.elapsed().as_millis());
+            Ok(tool_result(payload))
+        }
+
+        /// One backend's side of a `web_compare_backends` row, from its extraction pipeline output.
+        fn compare_backend_side(
+            r: &webpipe_local::extract::ExtractPipelineResult,
+            ms: u128,
+        ) -> serde_json::Value {
+            let confidence =
+                webpipe_local::extract::extraction_confidence(&r.extracted, r.structure.as_ref());
+            serde_json::json!({
+                "ok": true,
+                "engine": r.extracted.engine,
+                "chars": r.text_chars,
+                "low_signal": confidence < webpipe_local::extract::EXTRACTION_CONFIDENCE_LOW,
+                "confidence": webpipe_local::results::ExtractResult::rounded_confidence(confidence),
+                "ms": ms
+            })
+        }
+
+        /// `winner` for one `web_compare_backends` row: a failed backend loses, then a
+        /// non-low-signal extraction beats a low-signal one, then higher confidence wins
+        /// (within 0.05 is a tie).
+        fn compare_backends_winner(
+            local: &serde_json::Value,
+            firecrawl: &serde_json::Value,
+        ) -> &'static str {
+            let ok = |v: &serde_json::Value| v["ok"].as_bool().unwrap_or(false);
+            match (ok(local), ok(firecrawl)) {
+                (false, false) => return "none",
+                (true, false) => return "local",
+                (false, true) => return "firecrawl",
+                (true, true) => {}
+            }
+            let low = |v: &serde_json::Value| v["low_signal"].as_bool().unwrap_or(true);
+            match (low(local), low(firecrawl)) {
+                (false, true) => return "local",
+                (true, false) => return "firecrawl",
+                _ => {}
+            }
+            let conf = |v: &serde_json::Value| v["confidence"].as_f64().unwrap_or(0.0);
+            let d = conf(local) - conf(firecrawl);
+            if d > 0.05 {
+                "local"
+            } else if d < -0.05 {
+                "firecrawl"
+            } else {
+                "tie"
+            }
+        }
+
+        #[tool(
+            description = "Diagnostics: extract each URL with both the local backend and Firecrawl (when WEBPIPE_FIRECRAWL_API_KEY is set) and compare extraction quality. Output: per-URL {local, firecrawl, winner} with chars/low_signal/confidence/ms per backend, plus an aggregate win-rate. Without a Firecrawl key, only the local side is reported.",
+            input_schema = Arc::new(tool_input_schema_draft07::<WebCompareBackendsArgs>()),
+            annotations(
+                title = "Compare backends",
+                read_only_hint = true,
+                open_world_hint = true
+            )
+        )]
+        async fn web_compare_backends(
+            &self,
+            params: Parameters<Option<WebCompareBackendsArgs>>,
+        ) -> Result<CallToolResult, McpError> {
+            const METRICS: [&str; 4] = ["chars", "low_signal", "confidence", "ms"];
+            let args = params.0.unwrap_or_default();
+            self.stats_inc_tool("web_compare_backends");
+            let t0 = std::time::Instant::now();
+            let urls: Vec<String> = args
+                .urls
+                .unwrap_or_default()
+                .into_iter()
+                .map(|u| u.trim().to_string())
+                .filter(|u| !u.is_empty())
+                .collect();
+            let metrics: Vec<String> = match args.metrics {
+                Some(ms) if !ms.is_empty() => ms
+                    .into_iter()
+                    .map(|m| m.trim().to_ascii_lowercase())
+                    .collect(),
+                _ => METRICS.iter().map(|m| m.to_string()).collect(),
+            };
+            let timeout_ms = args.timeout_ms.unwrap_or(20_000).clamp(1_000, 60_000);
+            let cache_read = args.cache_read.unwrap_or(false);
+            let request = serde_json::json!({
+                "urls": urls,
+                "metrics": metrics,
+                "timeout_ms": timeout_ms,
+                "cache_read": cache_read
+            });
+
+            let invalid = if urls.is_empty() {
+                Some("urls must contain at least one URL".to_string())
+            } else if urls.len() > 20 {
+                Some(format!("too many urls: {} (max 20)", urls.len()))
+            } else {
+                metrics
+                    .iter()
+                    .find(|m| !METRICS.contains(&m.as_str()))
+                    .map(|m| format!("unknown metric: {m}"))
+            };
+            if let Some(msg) = invalid {
+                let mut payload = serde_json::json!({
+                    "ok": false,
+                    "request": request,
+                    "error": error_obj(
+                        ErrorCode::InvalidParams,
+                        msg,
+                        "Pass 1-20 urls; metrics may be any of chars, low_signal, confidence, ms."
+                    ),
+                });
+                add_envelope_fields(
+                    &mut payload,
+                    "web_compare_backends",
+                    t0.elapsed().as_millis(),
+                );
+                return Ok(tool_result(payload));
+            }
+
+            let firecrawl =
+                webpipe_local::firecrawl::FirecrawlClient::from_env(self.http.clone()).ok();
+            let cfg = || webpipe_local::extract::ExtractPipelineCfg {
+                query: None,
+                width: 100,
+                max_chars: 20_000,
+                top_chunks: 1,
+                max_chunk_chars: 500,
+                include_structure: true,
+                max_outline_items: 25,
+                max_blocks: 40,
+                max_block_chars: 400,
+                truncation_strategy: webpipe_local::extract::TruncationStrategy::Head,
+            };
+
+            let rows = futures::future::join_all(urls.iter().map(|url| async {
+                let local = async {
+                    let t = std::time::Instant::now();
+                    let req = FetchRequest {
+                        url: url.clone(),
+                        timeout_ms: Some(timeout_ms),
+                        max_bytes: Some(5_000_000),
+                        headers: BTreeMap::new(),
+                        method: None,
+                        body: None,
+                        cache: FetchCachePolicy {
+                            read: cache_read,
+                            write: true,
+                            ttl_s: None,
+                            read_non_idempotent: false,
+                            stale_while_revalidate_s: None,
+                        },
+                    };
+                    match self.fetcher.fetch(&req).await {
+                        Ok(resp) if Self::http_status_is_error(resp.status) => {
+                            let mut v = serde_json::json!({ "ok": true });
+                            Self::mark_http_status_error(&mut v, resp.status);
+                            v
+                        }
+                        Ok(resp) => {
+                            let r = webpipe_local::extract::extract_pipeline_from_bytes(
+                                &resp.bytes,
+                                resp.content_type.as_deref(),
+                                &resp.final_url,
+                                cfg(),
+                            );
+                            Self::compare_backend_side(&r, t.elapsed().as_millis())
+                        }
+                        Err(e) => serde_json::json!({
+                            "ok": false,
+                            "error": error_obj(
+                                ErrorCode::FetchFailed,
+                                e.to_string(),
+                                "The local fetch failed; check the URL or raise timeout_ms."
+                            )
+                        }),
+                    }
+                };
+                let remote = async {
+                    let fc = firecrawl.as_ref()?;
+                    let t = std::time::Instant::now();
+                    Some(match fc.fetch_markdown(url, timeout_ms, None).await {
+                        Ok(r) => {
+                            let p = webpipe_local::extract::extract_pipeline_from_bytes(
+                                r.markdown.as_bytes(),
+                                Some("text/markdown"),
+                                url,
+                                cfg(),
+                            );
+                            Self::compare_backend_side(&p, t.elapsed().as_millis())
+                        }
+                        Err(e) => serde_json::json!({
+                            "ok": false,
+                            "error": error_obj(
+                                ErrorCode::FetchFailed,
+                                e.to_string(),
+                                "The Firecrawl scrape failed; check the API key/quota or raise timeout_ms."
+                            )
+                        }),
+                    })
+                };
+                let (local, remote) = tokio::join!(local, remote);
+                (local, remote)
+            }))
+            .await;
+
+            let keep = |mut v: serde_json::Value| {
+                if let Some(m) = v.as_object_mut() {
+                    m.retain(|k, _| {
+                        matches!(k.as_str(), "ok" | "engine" | "error") || metrics.contains(k)
+                    });
+                }
+                v
+            };
+            let (mut local_wins, mut firecrawl_wins, mut ties) = (0usize, 0usize, 0usize);
+            let results: Vec<serde_json::Value> = urls
+                .iter()
+                .zip(rows)
+                .map(|(url, (local, remote))| {
+                    let winner = remote.as_ref().map(|fc| {
+                        let w = Self::compare_backends_winner(&local, fc);
+                        match w {
+                            "local" => local_wins += 1,
+                            "firecrawl" => firecrawl_wins += 1,
+                            "tie" => ties += 1,
+                            _ => {}
+                        }
+                        w
+                    });
+                    serde_json::json!({
+                        "url": url,
+                        "local": keep(local),
+                        "firecrawl": remote.map(keep),
+                        "winner": winner
+                    })
+                })
+                .collect();
+
+            // Rows where both backends failed are not comparisons.
+            let compared = local_wins + firecrawl_wins + ties;
+            let rate = |n: usize| {
+                (compared > 0).then(|| ((n as f64 / compared as f64) * 100.0).round() / 100.0)
+            };
+            let mut payload = serde_json::json!({
+                "ok": true,
+                "request": request,
+                "firecrawl_configured": firecrawl.is_some(),
+                "results": results,
+                "aggregate": {
+                    "urls": urls.len(),
+                    "compared": compared,
+                    "local_wins": local_wins,
+                    "firecrawl_wins": firecrawl_wins,
+                    "ties": ties,
+                    "local_win_rate": rate(local_wins),
+                    "firecrawl_win_rate": rate(firecrawl_wins)
+                }
+            });
+            if firecrawl.is_none() {
+                let ws = ["firecrawl_not_configured"];
+                payload["warnings"] = serde_json::json!(ws);
+                let codes = warning_codes_from(&ws);
+                payload["warning_codes"] = serde_json::json!(codes.clone());
+                payload["warning_hints"] = warning_hints_from(&codes);
+            }
+            add_envelope_fields(
+                &mut payload,
+                "web_compare_backends",
+                t0.elapsed().as_millis(),
+            );
+            Ok(tool_result(payload))
+        }
+
+        #[tool(
+            description = "Change monitoring: extract a URL's cached copy (old) and a fresh fetch (new), then diff the texts line by line. Output: diff{added, removed, unchanged, similarity, unified (bounded), truncated}; first_seen=true (no diff) when nothing was cached yet. Needs the network (no_network=true is an error).",
+            input_schema = Arc::new(tool_input_schema_draft07::<WebDiffArgs>()),
+            annotations(title = "Page diff", read_only_hint = true, open_world_hint = true)
+        )]
+        async fn web_diff(
+            &self,
+            params: Parameters<Option<WebDiffArgs>>,
+        ) -> Result<CallToolResult, McpError> {
+            let args = params.0.unwrap_or_default();
+            self.stats_inc_tool("web_diff");
+            let t0 = std::time::Instant::now();
+            let url = args.url.unwrap_or_default().trim().to_string();
+            let timeout_ms = args.timeout_ms.unwrap_or(20_000).min(60_000);
+            let max_bytes = args.max_bytes.unwrap_or(5_000_000);
+            let max_chars = args.max_chars.unwrap_or(50_000).min(200_000);
+            let context_lines = args.context_lines.unwrap_or(3).min(10);
+            let max_diff_lines = args.max_diff_lines.unwrap_or(200).clamp(1, 2_000);
+            let update_cache = args.update_cache.unwrap_or(true);
+            let no_network = args.no_network.unwrap_or(false);
+            let request = serde_json::json!({
+                "url": url,
+                "timeout_ms": timeout_ms,
+                "max_bytes": max_bytes,
+                "max_chars": max_chars,
+                "context_lines": context_lines,
+                "max_diff_lines": max_diff_lines,
+                "update_cache": update_cache,
+                "no_network": no_network
+            });
+            let fail = |code: ErrorCode, msg: String, hint: &str| {
+                let mut payload = serde_json::json!({
+                    "ok": false,
+                    "url": url,
+                    "request": request,
+                    "error": error_obj(code, msg, hint),
+                });
+                add_envelope_fields(&mut payload, "web_diff", t0.elapsed().as_millis());
+                Ok(tool_result(payload))
+            };
+
+            if !matches!(reqwest::Url::parse(&url), Ok(u) if matches!(u.scheme(), "http" | "https"))
+            {
+                return fail(
+                    ErrorCode::InvalidUrl,
+                    "url must be an absolute http(s) URL".to_string(),
+                    "Pass a URL like https://example.com/docs/changelog.",
+                );
+            }
+            if no_network {
+                return fail(
+                    ErrorCode::NotSupported,
+                    "web_diff needs a fresh network fetch for the new side".to_string(),
+                    "Set no_network=false. To read only the cached copy, use web_extract with no_network=true.",
+                );
+            }
+
+            let req = |read: bool, write: bool| FetchRequest {
+                url: url.clone(),
+                timeout_ms: Some(timeout_ms),
+                max_bytes: Some(max_bytes),
+                headers: BTreeMap::new(),
+                method: None,
+                body: None,
+                cache: FetchCachePolicy {
+                    read,
+                    write,
+                    ttl_s: None,
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
+                },
+            };
+            // Read the old side before the fresh fetch overwrites it.
+            let old = self.fetcher.cache_get(&req(true, false)).ok().flatten();
+            let new = match self.fetcher.fetch(&req(false, update_cache)).await {
+                Ok(r) => r,
+                Err(e) => {
+                    return fail(
+                        ErrorCode::FetchFailed,
+                        e.to_string(),
+                        "The fresh fetch failed; check the URL or raise timeout_ms.",
+                    )
+                }
+            };
+            let text_of = |r: &webpipe_core::FetchResponse| {
+                webpipe_local::extract::extract_pipeline_from_bytes(
+                    &r.bytes,
+                    r.content_type.as_deref(),
+                    &r.final_url,
+                    webpipe_local::extract::ExtractPipelineCfg {
+                        query: None,
+                        width: 100,
+                        max_chars,
+                        top_chunks: 1,
+                        max_chunk_chars: 500,
+                        include_structure: false,
+                        max_outline_items: 0,
+                        max_blocks: 0,
+                        max_block_chars: 0,
+                        truncation_strategy: webpipe_local::extract::TruncationStrategy::Head,
+                    },
+                )
+                .extracted
+                .text
+            };
+            let side = |r: &webpipe_core::FetchResponse, text: &str| {
+                serde_json::json!({
+                    "status": r.status,
+                    "final_url": r.final_url,
+                    "content_type": r.content_type,
+                    "text_chars": text.chars().count()
+                })
+            };
+            let new_text = text_of(&new);
+
+            let mut payload = serde_json::json!({
+                "ok": true,
+                "url": url,
+                "request": request,
+                "new": side(&new, &new_text),
+            });
+            if Self::http_status_is_error(new.status) {
+                Self::mark_http_status_error(&mut payload, new.status);
+            }
+            match old {
+                Some(old) => {
+                    let old_text = text_of(&old);
+                    let d = webpipe_local::compare::text_diff(&old_text, &new_text);
+                    let (unified, truncated) = d.unified(context_lines, max_diff_lines);
+                    payload["first_seen"] = serde_json::json!(false);
+                    payload["old"] = side(&old, &old_text);
+                    payload["changed"] = serde_json::json!(d.added + d.removed > 0);
+                    payload["diff"] = serde_json::json!({
+                        "added": d.added,
+                        "removed": d.removed,
+                        "unchanged": d.unchanged,
+                        "similarity": (d.similarity * 1000.0).round() / 1000.0,
+                        "unified": unified,
+                        "truncated": truncated
+                    });
+                }
+                None => {
+                    let warnings = ["diff_first_seen"];
+                    let codes = warning_codes_from(&warnings);
+                    payload["first_seen"] = serde_json::json!(true);
+                    payload["old"] = serde_json::Value::Null;
+                    payload["changed"] = serde_json::Value::Null;
+                    payload["diff"] = serde_json::Value::Null;
+                    payload["warnings"] = serde_json::json!(warnings);
+                    payload["warning_codes"] = serde_json::json!(codes.clone());
+                    payload["warning_hints"] = warning_hints_from(&codes);
+                }
+            }
+            add_envelope_fields(&mut payload, "web_diff", t0.elapsed().as_millis());
+            Ok(tool_result(payload))
+        }
+
         #[tool(
             description = "Best for: multi-source research questions that require gathering and synthesizing evidence across several pages. Not this for single-URL extraction — use web_extract. Not this when you want inspectable evidence without LLM synthesis — use search_evidence with synthesize=false. Output (synthesize=false): top_chunks[] + evidence[]. Output (synthesize=true): answer text + citations (non-deterministic; not reproducible from cache).",
             input_schema = Arc::new(tool_input_schema_draft07::<WebDeepResearchArgs>()),
@@ -16519,7 +22054,7 @@ Rules:
                 .unwrap_or_else(|| "sonar-deep-research".to_string());
             let fetch_backend = args.fetch_backend.unwrap_or_else(|| "local".to_string());
             let no_network = args.no_network.unwrap_or(false);
-            if selection_mode.as_str() != "score" && selection_mode.as_str() != "pareto" {
+            if !matches!(selection_mode.as_str(), "score" | "pareto" | "mmr") {
                 let mut payload = serde_json::json!({
                     "ok": false,
                     "query": query,
@@ -16527,7 +22062,7 @@ Rules:
                     "error": error_obj(
                         ErrorCode::InvalidParams,
                         "unknown selection_mode",
-                        "Allowed selection_mode values: score, pareto"
+                        "Allowed selection_mode values: score, pareto, mmr"
                     ),
                 });
                 add_envelope_fields(&mut payload, "web_deep_research", t0.elapsed().as_millis());
@@ -16545,6 +22080,9 @@ Rules:
                 .clone()
                 .unwrap_or_else(|| "auto".to_string());
             let llm_model = args.llm_model.clone();
+            let suggest_followups = args.suggest_followups.unwrap_or(false);
+            let passes = args.passes.unwrap_or(1).clamp(1, 2);
+            let structured_output = args.structured_output.unwrap_or(false);
 
             // 1) Gather evidence with our own bounded pipeline (so even if Perplexity is flaky, we can inspect what we fed it).
             let firecrawl_fallback_on_empty_extraction = fetch_backend == "local"
@@ -16632,7 +22170,7 @@ Rules:
                 }))
                 .await?;
 
-            let evidence = payload_from_result(&evidence_r);
+            let mut evidence = payload_from_result(&evidence_r);
             if evidence.get("ok").and_then(|v| v.as_bool()) != Some(true) {
                 let mut payload = serde_json::json!({
                     "ok": false,
@@ -16661,6 +22199,98 @@ Rules:
 
             // Note: we do not run a second hidden evidence pass here. If Firecrawl is available and
             // fetch_backend="local", the per-URL fallback is handled inside web_search_extract.
+            // The only second round is the explicit, bounded passes=2 below.
+            let mut pass_warnings: Vec<&'static str> = Vec::new();
+            let mut rounds: Option<Vec<serde_json::Value>> = None;
+            if passes == 2 {
+                let pass1_chunks: Vec<serde_json::Value> = evidence
+                    .get("top_chunks")
+                    .and_then(|v| v.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                let mut rs = vec![deep_research_round_summary(
+                    1, &query, "original", &evidence,
+                )];
+                if args.urls.is_some() {
+                    // urls-mode has no search step to refine.
+                    pass_warnings.push("deep_research_pass2_skipped");
+                } else {
+                    let refine_backend = match llm_backend.as_str() {
+                        "auto" => deep_research_auto_llm_backend(no_network),
+                        name if self.llm.get(name).is_some() => name,
+                        _ => "none",
+                    };
+                    let llm_refined = if refine_backend == "none" {
+                        None
+                    } else {
+                        self.deep_research_refine_query(
+                            refine_backend,
+                            &model,
+                            llm_model.clone(),
+                            &query,
+                            &pass1_chunks,
+                            timeout_ms,
+                            no_network,
+                        )
+                        .await
+                    };
+                    let (refined, refined_by) = match llm_refined {
+                        Some(q) => (Some(q), refine_backend),
+                        None => (
+                            deep_research_offline_refined_query(&query, &pass1_chunks),
+                            "keywords",
+                        ),
+                    };
+                    match refined {
+                        None => pass_warnings.push("deep_research_pass2_skipped"),
+                        Some(q2) => {
+                            let r2 = self
+                                .web_search_extract(p(WebSearchExtractArgs {
+                                    query: Some(q2.clone()),
+                                    provider: Some(provider.clone()),
+                                    auto_mode: Some(auto_mode.clone()),
+                                    selection_mode: Some(selection_mode.clone()),
+                                    url_selection_mode: Some("query_rank".to_string()),
+                                    fetch_backend: Some(fetch_backend.clone()),
+                                    no_network: Some(no_network),
+                                    firecrawl_fallback_on_empty_extraction: Some(
+                                        firecrawl_fallback_on_empty_extraction,
+                                    ),
+                                    firecrawl_fallback_on_low_signal: Some(false),
+                                    render_fallback_on_empty_extraction: Some(false),
+                                    render_fallback_on_low_signal: Some(false),
+                                    max_results: Some(max_results),
+                                    // Bound total cost: pass 2 gets half the URL budget.
+                                    max_urls: Some(max_urls.div_ceil(2)),
+                                    timeout_ms: Some(timeout_ms),
+                                    max_bytes: Some(max_bytes),
+                                    width: Some(width),
+                                    max_chars: Some(max_chars),
+                                    top_chunks: Some(top_chunks),
+                                    max_chunk_chars: Some(max_chunk_chars),
+                                    include_links: Some(include_links),
+                                    include_structure: Some(false),
+                                    max_links: Some(max_links),
+                                    include_text: Some(false),
+                                    cache_read: Some(true),
+                                    cache_write: Some(true),
+                                    agentic: Some(false),
+                                    compact: Some(true),
+                                    ..Default::default()
+                                }))
+                                .await?;
+                            let evidence2 = payload_from_result(&r2);
+                            rs.push(deep_research_round_summary(2, &q2, refined_by, &evidence2));
+                            if evidence2.get("ok").and_then(|v| v.as_bool()) == Some(true) {
+                                merge_deep_research_evidence(&mut evidence, &evidence2, top_chunks);
+                            } else {
+                                pass_warnings.push("deep_research_pass2_failed");
+                            }
+                        }
+                    }
+                }
+                rounds = Some(rs);
+            }
 
             // Build a compact evidence pack for the LLM: keep only URLs + top chunks, never full HTML.
             let mut compact_results = Vec::new();
@@ -16926,17 +22556,36 @@ Rules:
             evidence_for_llm["papers"] = papers_block;
             evidence_for_llm["arxiv"] = arxiv_block;
             let evidence_pack = evidence_for_llm.clone();
+            let report_sources = if structured_output {
+                deep_research_report_sources(&evidence_pack)
+            } else {
+                Vec::new()
+            };
 
             // Keep prompt bounded: pass only URLs + top chunks, never full HTML.
-            let sys = "You are a careful research assistant. Use the provided evidence pack. Cite sources by URL. If evidence is insufficient, say so.";
-            let user = serde_json::json!({
+            let sys = if structured_output {
+                format!("You are a careful research assistant. Use the provided evidence pack. Reply with ONLY a JSON research report. {DEEP_RESEARCH_REPORT_SCHEMA} If evidence is insufficient, say so in the summary.")
+            } else {
+                "You are a careful research assistant. Use the provided evidence pack. Cite sources by URL. If evidence is insufficient, say so.".to_string()
+            };
+            let mut user = serde_json::json!({
                 "question": query,
                 "evidence": evidence_for_llm,
-            })
-            .to_string();
-            let (user, _n_user, _user_clipped) = Self::truncate_to_chars(&user, 20_000);
+            });
+            if structured_output {
+                user["sources"] = serde_json::json!(report_sources);
+            }
+            let (user, _n_user, _user_clipped) = Self::truncate_to_chars(&user.to_string(), 20_000);
 
-            if !synthesize {
+            // A structured report can still be produced extractively when auto finds no backend.
+            let extractive_fallback = synthesize
+                && structured_output
+                && llm_backend == "auto"
+                && deep_research_auto_llm_backend(no_network) == "none";
+            if !synthesize || extractive_fallback {
+                if extractive_fallback {
+                    pass_warnings.push("structured_report_extractive");
+                }
                 let mut payload = serde_json::json!({
                     "ok": true,
                     "query": query,
@@ -16963,14 +22612,30 @@ Rules:
                         "papers_max_papers": args.papers_max_papers.unwrap_or(5).clamp(1, 20),
                         "arxiv_mode": arxiv_mode,
                         "arxiv_max_papers": args.arxiv_max_papers.unwrap_or(3).clamp(1, 10),
-                        "synthesize": false,
+                        "synthesize": synthesize,
+                        "passes": passes,
+                        "structured_output": structured_output,
                     },
                     "answer": {
                         "text": "",
                         "skipped": true,
-                        "reason": "synthesize=false",
+                        "reason": if synthesize { "no LLM backend configured" } else { "synthesize=false" },
                     },
                 });
+                if structured_output {
+                    payload["report"] =
+                        deep_research_extractive_report(&evidence_pack, &report_sources);
+                    payload["report_source"] = serde_json::json!("extractive");
+                }
+                if let Some(rs) = rounds {
+                    payload["rounds"] = serde_json::json!(rs);
+                }
+                if !pass_warnings.is_empty() {
+                    payload["warnings"] = serde_json::json!(pass_warnings);
+                    let codes = warning_codes_from(&pass_warnings);
+                    payload["warning_codes"] = serde_json::json!(codes.clone());
+                    payload["warning_hints"] = warning_hints_from(&codes);
+                }
                 if include_evidence {
                     payload["evidence"] = evidence_for_llm;
                 }
@@ -16981,14 +22646,14 @@ Rules:
             // LLM backend selection.
             // - In no_network mode, network-backed providers must not be called.
             // - “Local network” (localhost only) is allowed for explicit local backends.
-            if no_network && llm_backend.as_str() == "perplexity" {
+            if no_network && self.llm.get(&llm_backend).is_some_and(|b| b.network_only()) {
                 let mut payload = serde_json::json!({
                     "ok": false,
                     "query": query,
                     "model": model,
                     "error": error_obj(
                         ErrorCode::NotSupported,
-                        "llm_backend=perplexity cannot be used with no_network=true",
+                        format!("llm_backend={llm_backend} cannot be used with no_network=true"),
                         "Use llm_backend=\"ollama\" or \"openai_compat\" for local synthesis, or set no_network=false."
                     ),
                 });
@@ -17000,27 +22665,9 @@ Rules:
             }
 
             let selected_backend: &str = match llm_backend.as_str() {
-                "perplexity" => "perplexity",
-                "ollama" => "ollama",
-                "openai_compat" => "openai_compat",
-                "auto" => {
-                    // Prefer Perplexity when it's available and we're not in strict offline mode.
-                    if !no_network
-                        && (has_env("WEBPIPE_PERPLEXITY_API_KEY") || has_env("PERPLEXITY_API_KEY"))
-                    {
-                        "perplexity"
-                    } else if has_env("WEBPIPE_OPENAI_COMPAT_BASE_URL") {
-                        "openai_compat"
-                    } else if has_env("WEBPIPE_OLLAMA_ENABLE")
-                        && std::env::var("WEBPIPE_OLLAMA_ENABLE")
-                            .ok()
-                            .is_some_and(|v| v.trim().eq_ignore_ascii_case("true"))
-                    {
-                        "ollama"
-                    } else {
-                        "none"
-                    }
-                }
+                // Prefer Perplexity when it's available and we're not in strict offline mode.
+                "auto" => deep_research_auto_llm_backend(no_network),
+                name if self.llm.get(name).is_some() => name,
                 other => {
                     let mut payload = serde_json::json!({
                         "ok": false,
@@ -17029,7 +22676,10 @@ Rules:
                         "error": error_obj(
                             ErrorCode::InvalidParams,
                             format!("unknown llm_backend: {other}"),
-                            "Allowed llm_backend values: auto, perplexity, ollama, openai_compat"
+                            format!(
+                                "Allowed llm_backend values: auto, {}",
+                                self.llm.names().join(", ")
+                            )
                         ),
                     });
                     if include_evidence {
@@ -17053,7 +22703,7 @@ Rules:
                     "error": error_obj(
                         ErrorCode::NotConfigured,
                         "no LLM backend configured for synthesis",
-                        "Configure WEBPIPE_PERPLEXITY_API_KEY, or set WEBPIPE_OPENAI_COMPAT_BASE_URL (and llm_model / WEBPIPE_OPENAI_COMPAT_MODEL), or enable Ollama via WEBPIPE_OLLAMA_ENABLE=true."
+                        "Configure WEBPIPE_PERPLEXITY_API_KEY, or WEBPIPE_ANTHROPIC_API_KEY + WEBPIPE_ANTHROPIC_MODEL, or set WEBPIPE_OPENAI_COMPAT_BASE_URL (and llm_model / WEBPIPE_OPENAI_COMPAT_MODEL), or enable Ollama via WEBPIPE_OLLAMA_ENABLE=true."
                     ),
                     "request": { "llm_backend": llm_backend },
                 });
@@ -17065,7 +22715,7 @@ Rules:
             }
 
             // 2) Synthesize via selected backend.
-            let mut deep_warnings: Vec<&'static str> = Vec::new();
+            let mut deep_warnings: Vec<&'static str> = pass_warnings;
             let mut citations: Vec<String> = Vec::new();
             if let Some(arr) = evidence.get("top_chunks").and_then(|v| v.as_array()) {
                 for c in arr {
@@ -17077,27 +22727,41 @@ Rules:
                 }
             }
 
-            if selected_backend == "ollama" {
-                let llm_t0 = std::time::Instant::now();
-                let ollama = match webpipe_local::ollama::OllamaClient::from_env(self.http.clone())
-                {
-                    Ok(c) => c,
+            let prompt = webpipe_local::llm::Prompt { system: sys, user };
+            let mut opts =
+                deep_research_llm_opts(selected_backend, &model, llm_model.clone(), timeout_ms);
+            opts.max_tokens = max_tokens;
+            opts.temperature = temperature;
+            opts.top_p = top_p;
+
+            // Registry backends (ollama, openai_compat, embedder-provided). Perplexity keeps its own
+            // path below for provider-side browsing controls and provider citations.
+            if selected_backend != "perplexity" {
+                let name = selected_backend;
+                let configured = self
+                    .llm
+                    .get(name)
+                    .map(|b| b.check_configured(&opts).map(|_| b))
+                    .unwrap_or_else(|| {
+                        Err(webpipe_core::Error::NotConfigured(format!(
+                            "unknown llm backend: {name}"
+                        )))
+                    });
+                let backend = match configured {
+                    Ok(b) => b,
                     Err(e) => {
-                        self.stats_record_llm_backend(
-                            "ollama",
-                            false,
-                            llm_t0.elapsed().as_millis() as u64,
-                            Some(&e.to_string()),
-                        );
+                        self.stats_record_llm_backend(name, false, 0, Some(&e.to_string()));
+                        let hint = match name {
+                            "ollama" => "Enable Ollama synthesis by setting WEBPIPE_OLLAMA_ENABLE=true (and ensure Ollama is running).",
+                            "openai_compat" => "Set WEBPIPE_OPENAI_COMPAT_BASE_URL and (llm_model or WEBPIPE_OPENAI_COMPAT_MODEL).",
+                            "anthropic" => "Set WEBPIPE_ANTHROPIC_API_KEY (or ANTHROPIC_API_KEY) and (llm_model or WEBPIPE_ANTHROPIC_MODEL).",
+                            _ => "Check the configuration of the registered LLM backend.",
+                        };
                         let mut payload = serde_json::json!({
                             "ok": false,
                             "query": query,
                             "model": model,
-                            "error": error_obj(
-                                ErrorCode::NotConfigured,
-                                e.to_string(),
-                                "Enable Ollama synthesis by setting WEBPIPE_OLLAMA_ENABLE=true (and ensure Ollama is running)."
-                            ),
+                            "error": error_obj(ErrorCode::NotConfigured, e.to_string(), hint),
                             "request": { "llm_backend": llm_backend },
                         });
                         if include_evidence {
@@ -17114,51 +22778,65 @@ Rules:
                 };
 
                 if no_network {
-                    // Best-effort safety: require localhost base URL when no_network=true.
-                    let b = ollama.base_url().to_string();
-                    let ok_local =
-                        b.contains("127.0.0.1") || b.contains("localhost") || b.contains("[::1]");
-                    if !ok_local {
-                        let mut payload = serde_json::json!({
-                            "ok": false,
-                            "query": query,
-                            "model": model,
-                            "error": error_obj(
-                                ErrorCode::NotSupported,
-                                "no_network=true requires Ollama to be localhost",
-                                "Set WEBPIPE_OLLAMA_BASE_URL to http://127.0.0.1:11434 (or set no_network=false)."
-                            ),
-                            "request": { "llm_backend": llm_backend, "ollama_base_url": b },
-                        });
-                        if include_evidence {
-                            payload["evidence"] = evidence;
-                            payload["evidence_pack"] = evidence_pack.clone();
+                    // Best-effort safety: require a localhost endpoint when no_network=true.
+                    if let Some(b) = backend.endpoint(&opts) {
+                        if !llm_endpoint_is_localhost(&b) {
+                            let (msg, hint) = match name {
+                                "ollama" => (
+                                    "no_network=true requires Ollama to be localhost".to_string(),
+                                    "Set WEBPIPE_OLLAMA_BASE_URL to http://127.0.0.1:11434 (or set no_network=false).",
+                                ),
+                                "openai_compat" => (
+                                    "no_network=true requires openai_compat base URL to be localhost".to_string(),
+                                    "Set WEBPIPE_OPENAI_COMPAT_BASE_URL to http://127.0.0.1:<port> (or set no_network=false).",
+                                ),
+                                _ => (
+                                    format!("no_network=true requires llm_backend={name} to be localhost"),
+                                    "Point the backend at a localhost endpoint (or set no_network=false).",
+                                ),
+                            };
+                            let mut payload = serde_json::json!({
+                                "ok": false,
+                                "query": query,
+                                "model": model,
+                                "error": error_obj(ErrorCode::NotSupported, msg, hint),
+                                "request": { "llm_backend": llm_backend },
+                            });
+                            payload["request"][format!("{name}_base_url")] = serde_json::json!(b);
+                            if include_evidence {
+                                payload["evidence"] = evidence;
+                                payload["evidence_pack"] = evidence_pack.clone();
+                            }
+                            add_envelope_fields(
+                                &mut payload,
+                                "web_deep_research",
+                                t0.elapsed().as_millis(),
+                            );
+                            return Ok(tool_result(payload));
                         }
-                        add_envelope_fields(
-                            &mut payload,
-                            "web_deep_research",
-                            t0.elapsed().as_millis(),
-                        );
-                        return Ok(tool_result(payload));
                     }
                 }
 
-                deep_warnings.push("llm_ollama_used");
-                let answer = match ollama.chat(sys, &user, timeout_ms).await {
-                    Ok(s) => s,
+                match name {
+                    "ollama" => deep_warnings.push("llm_ollama_used"),
+                    "openai_compat" => deep_warnings.push("llm_openai_compat_used"),
+                    _ => {}
+                }
+                let (answer, usage) = match self.llm_complete(name, &prompt, &opts).await {
+                    Ok(c) => (c.text, c.usage),
                     Err(e) => {
-                        self.stats_record_llm_backend(
-                            "ollama",
-                            false,
-                            llm_t0.elapsed().as_millis() as u64,
-                            Some(&e.to_string()),
-                        );
+                        let hint = match name {
+                            "ollama" => "Ollama synthesis failed. Check that Ollama is running and the model name exists.",
+                            "openai_compat" => "OpenAI-compatible synthesis failed. Check the base URL, model name, and auth.",
+                            "anthropic" => "Anthropic synthesis failed. Check the API key and model name (or retry later).",
+                            _ => "LLM synthesis failed. Retry, or pick a different llm_backend.",
+                        };
                         let mut payload = serde_json::json!({
                             "ok": false,
                             "query": query,
                             "model": model,
-                            "error": error_obj(ErrorCode::ProviderUnavailable, e.to_string(), "Ollama synthesis failed. Check that Ollama is running and the model name exists."),
-                            "request": { "llm_backend": "ollama" },
+                            "error": error_obj(ErrorCode::ProviderUnavailable, e.to_string(), hint),
+                            "request": { "llm_backend": name },
                         });
                         if include_evidence {
                             payload["evidence"] = evidence;
@@ -17172,156 +22850,77 @@ Rules:
                         return Ok(tool_result(payload));
                     }
                 };
-                self.stats_record_llm_backend(
-                    "ollama",
-                    true,
-                    llm_t0.elapsed().as_millis() as u64,
-                    None,
-                );
+                let report = if structured_output {
+                    let (r, repaired) = self
+                        .deep_research_report_from_reply(
+                            name,
+                            &model,
+                            llm_model.clone(),
+                            &answer,
+                            &report_sources,
+                            max_tokens,
+                            timeout_ms,
+                            no_network,
+                        )
+                        .await;
+                    match (&r, repaired) {
+                        (None, _) => deep_warnings.push("structured_report_invalid"),
+                        (Some(_), true) => deep_warnings.push("structured_report_repaired"),
+                        (Some(_), false) => {}
+                    }
+                    r
+                } else {
+                    None
+                };
+                let answer = report
+                    .as_ref()
+                    .map(deep_research_report_text)
+                    .unwrap_or(answer);
                 let (answer, _n, clipped) = Self::truncate_to_chars(&answer, max_answer_chars);
+                let followups = if suggest_followups {
+                    self.deep_research_followups(
+                        name,
+                        &model,
+                        llm_model.clone(),
+                        &query,
+                        &answer,
+                        &evidence_pack,
+                        timeout_ms,
+                        no_network,
+                    )
+                    .await
+                } else {
+                    None
+                };
+                if suggest_followups && followups.is_none() {
+                    deep_warnings.push("followups_unavailable");
+                }
                 let mut payload = serde_json::json!({
                     "ok": true,
-                    "provider": "ollama",
+                    "provider": name,
                     "query": query,
-                    "request": { "llm_backend": llm_backend, "timeout_ms": timeout_ms, "no_network": no_network },
+                    "request": { "llm_backend": llm_backend, "timeout_ms": timeout_ms, "no_network": no_network, "suggest_followups": suggest_followups, "passes": passes, "structured_output": structured_output },
                     "answer": { "text": answer, "truncated": clipped, "citations": citations },
                 });
-                if !deep_warnings.is_empty() {
-                    payload["warnings"] = serde_json::json!(deep_warnings);
-                    let codes = warning_codes_from(&deep_warnings);
-                    payload["warning_codes"] = serde_json::json!(codes.clone());
-                    payload["warning_hints"] = warning_hints_from(&codes);
-                    self.stats_record_warnings(&deep_warnings);
+                if let Some(u) = usage {
+                    payload["usage"] = u;
                 }
-                if include_evidence {
-                    payload["evidence"] = evidence;
-                    payload["evidence_pack"] = evidence_pack.clone();
+                if let Some(r) = report {
+                    payload["report"] = r;
+                    payload["report_source"] = serde_json::json!("llm");
                 }
-                if let Some(now) = args.now_epoch_s {
-                    payload["generated_at_epoch_s"] = serde_json::json!(now);
+                if let Some(f) = followups {
+                    payload["followups"] = serde_json::json!(f);
                 }
-                add_envelope_fields(&mut payload, "web_deep_research", t0.elapsed().as_millis());
-                let md = web_deep_research_markdown(&payload);
-                return Ok(tool_result_markdown_with_json(payload, md));
-            }
-
-            if selected_backend == "openai_compat" {
-                let llm_t0 = std::time::Instant::now();
-                let client = match webpipe_local::openai_compat::OpenAiCompatClient::from_env(
-                    self.http.clone(),
-                    llm_model.clone(),
-                ) {
-                    Ok(c) => c,
-                    Err(e) => {
-                        self.stats_record_llm_backend(
-                            "openai_compat",
-                            false,
-                            llm_t0.elapsed().as_millis() as u64,
-                            Some(&e.to_string()),
-                        );
-                        let mut payload = serde_json::json!({
-                            "ok": false,
-                            "query": query,
-                            "model": model,
-                            "error": error_obj(
-                                ErrorCode::NotConfigured,
-                                e.to_string(),
-                                "Set WEBPIPE_OPENAI_COMPAT_BASE_URL and (llm_model or WEBPIPE_OPENAI_COMPAT_MODEL)."
-                            ),
-                            "request": { "llm_backend": llm_backend },
-                        });
-                        if include_evidence {
-                            payload["evidence"] = evidence;
-                            payload["evidence_pack"] = evidence_pack.clone();
-                        }
-                        add_envelope_fields(
-                            &mut payload,
-                            "web_deep_research",
-                            t0.elapsed().as_millis(),
-                        );
-                        return Ok(tool_result(payload));
-                    }
-                };
-
-                if no_network {
-                    let b = client.base_url().to_string();
-                    let ok_local =
-                        b.contains("127.0.0.1") || b.contains("localhost") || b.contains("[::1]");
-                    if !ok_local {
-                        let mut payload = serde_json::json!({
-                            "ok": false,
-                            "query": query,
-                            "model": model,
-                            "error": error_obj(
-                                ErrorCode::NotSupported,
-                                "no_network=true requires openai_compat base URL to be localhost",
-                                "Set WEBPIPE_OPENAI_COMPAT_BASE_URL to http://127.0.0.1:<port> (or set no_network=false)."
-                            ),
-                            "request": { "llm_backend": llm_backend, "openai_compat_base_url": b },
-                        });
-                        if include_evidence {
-                            payload["evidence"] = evidence;
-                            payload["evidence_pack"] = evidence_pack.clone();
-                        }
-                        add_envelope_fields(
-                            &mut payload,
-                            "web_deep_research",
-                            t0.elapsed().as_millis(),
-                        );
-                        return Ok(tool_result(payload));
-                    }
+                if let Some(rs) = rounds {
+                    payload["rounds"] = serde_json::json!(rs);
                 }
-
-                deep_warnings.push("llm_openai_compat_used");
-                let answer = match client
-                    .chat(sys, &user, timeout_ms, max_tokens, temperature, top_p)
-                    .await
-                {
-                    Ok(s) => s,
-                    Err(e) => {
-                        self.stats_record_llm_backend(
-                            "openai_compat",
-                            false,
-                            llm_t0.elapsed().as_millis() as u64,
-                            Some(&e.to_string()),
-                        );
-                        let mut payload = serde_json::json!({
-                            "ok": false,
-                            "query": query,
-                            "model": model,
-                            "error": error_obj(ErrorCode::ProviderUnavailable, e.to_string(), "OpenAI-compatible synthesis failed. Check the base URL, model name, and auth."),
-                            "request": { "llm_backend": "openai_compat" },
-                        });
-                        if include_evidence {
-                            payload["evidence"] = evidence;
-                        }
-                        add_envelope_fields(
-                            &mut payload,
-                            "web_deep_research",
-                            t0.elapsed().as_millis(),
-                        );
-                        return Ok(tool_result(payload));
-                    }
-                };
-                self.stats_record_llm_backend(
-                    "openai_compat",
-                    true,
-                    llm_t0.elapsed().as_millis() as u64,
-                    None,
-                );
-                let (answer, _n, clipped) = Self::truncate_to_chars(&answer, max_answer_chars);
-                let mut payload = serde_json::json!({
-                    "ok": true,
-                    "provider": "openai_compat",
-                    "query": query,
-                    "request": { "llm_backend": llm_backend, "timeout_ms": timeout_ms, "no_network": no_network },
-                    "answer": { "text": answer, "truncated": clipped, "citations": citations },
-                });
                 if !deep_warnings.is_empty() {
                     payload["warnings"] = serde_json::json!(deep_warnings);
                     let codes = warning_codes_from(&deep_warnings);
                     payload["warning_codes"] = serde_json::json!(codes.clone());
                     payload["warning_hints"] = warning_hints_from(&codes);
+                    self.stats_record_warnings(&deep_warnings);
                 }
                 if include_evidence {
                     payload["evidence"] = evidence;
@@ -17336,10 +22935,17 @@ Rules:
             }
 
             // Otherwise: Perplexity API path (existing behavior).
-            let pplx = match webpipe_local::perplexity::PerplexityClient::from_env(
-                self.http.clone(),
-            ) {
-                Ok(c) => c,
+            let pplx_configured = self
+                .llm
+                .get("perplexity")
+                .map(|b| b.check_configured(&opts))
+                .unwrap_or_else(|| {
+                    Err(webpipe_core::Error::NotConfigured(
+                        "perplexity backend is not registered".to_string(),
+                    ))
+                });
+            match pplx_configured {
+                Ok(()) => {}
                 Err(e) => {
                     let mut payload = serde_json::json!({
                         "ok": false,
@@ -17359,7 +22965,7 @@ Rules:
                     );
                     return Ok(tool_result(payload));
                 }
-            };
+            }
 
             // By default, disable provider-side browsing. We already gather an evidence pack ourselves;
             // letting the provider browse makes results less inspectable and can be much more expensive.
@@ -17370,26 +22976,10 @@ Rules:
                 effective_search_mode = Some("off".to_string());
             }
 
-            let req = webpipe_local::perplexity::ChatCompletionsRequest {
-                model: model.clone(),
-                messages: vec![
-                    webpipe_local::perplexity::Message {
-                        role: "system".to_string(),
-                        content: sys.to_string(),
-                    },
-                    webpipe_local::perplexity::Message {
-                        role: "user".to_string(),
-                        content: user,
-                    },
-                ],
-                max_tokens,
-                temperature,
-                top_p,
-                search_mode: effective_search_mode.clone(),
-                reasoning_effort: reasoning_effort.clone(),
-            };
+            opts.search_mode = effective_search_mode.clone();
+            opts.reasoning_effort = reasoning_effort.clone();
 
-            let resp = match pplx.chat_completions(req.clone()).await {
+            let resp = match self.llm_complete("perplexity", &prompt, &opts).await {
                 Ok(r) => r,
                 Err(e) => {
                     // Retry once if we set the default `search_mode="off"` and the provider rejected it.
@@ -17434,9 +23024,8 @@ Rules:
                             );
                             return Ok(tool_result(payload));
                         }
-                        let mut req2 = req;
-                        req2.search_mode = None;
-                        if let Ok(r2) = pplx.chat_completions(req2).await {
+                        opts.search_mode = None;
+                        if let Ok(r2) = self.llm_complete("perplexity", &prompt, &opts).await {
                             r2
                         } else {
                             let mut payload = serde_json::json!({
@@ -17491,12 +23080,51 @@ Rules:
                 }
             };
 
-            let answer_text = resp
-                .choices
-                .first()
-                .map(|c| c.message.content.clone())
-                .unwrap_or_default();
-            let (answer, _n, clipped) = Self::truncate_to_chars(&answer_text, max_answer_chars);
+            let report = if structured_output {
+                let (r, repaired) = self
+                    .deep_research_report_from_reply(
+                        "perplexity",
+                        &model,
+                        llm_model.clone(),
+                        &resp.text,
+                        &report_sources,
+                        max_tokens,
+                        timeout_ms,
+                        no_network,
+                    )
+                    .await;
+                match (&r, repaired) {
+                    (None, _) => deep_warnings.push("structured_report_invalid"),
+                    (Some(_), true) => deep_warnings.push("structured_report_repaired"),
+                    (Some(_), false) => {}
+                }
+                r
+            } else {
+                None
+            };
+            let answer = report
+                .as_ref()
+                .map(deep_research_report_text)
+                .unwrap_or(resp.text);
+            let (answer, _n, clipped) = Self::truncate_to_chars(&answer, max_answer_chars);
+            let followups = if suggest_followups {
+                self.deep_research_followups(
+                    "perplexity",
+                    &model,
+                    llm_model.clone(),
+                    &query,
+                    &answer,
+                    &evidence_pack,
+                    timeout_ms,
+                    no_network,
+                )
+                .await
+            } else {
+                None
+            };
+            if suggest_followups && followups.is_none() {
+                deep_warnings.push("followups_unavailable");
+            }
 
             let mut payload = serde_json::json!({
                 "ok": true,
@@ -17524,7 +23152,10 @@ Rules:
                     "max_answer_chars": max_answer_chars,
                     "max_tokens": max_tokens,
                     "temperature": temperature,
-                    "top_p": top_p
+                    "top_p": top_p,
+                    "suggest_followups": suggest_followups,
+                    "passes": passes,
+                    "structured_output": structured_output
                 },
                 "answer": {
                     "text": answer,
@@ -17534,6 +23165,16 @@ Rules:
                 "usage": resp.usage,
                 "timings_ms": resp.timings_ms
             });
+            if let Some(r) = report {
+                payload["report"] = r;
+                payload["report_source"] = serde_json::json!("llm");
+            }
+            if let Some(f) = followups {
+                payload["followups"] = serde_json::json!(f);
+            }
+            if let Some(rs) = rounds {
+                payload["rounds"] = serde_json::json!(rs);
+            }
             if !deep_warnings.is_empty() {
                 payload["warnings"] = serde_json::json!(deep_warnings);
                 let codes = warning_codes_from(&deep_warnings);
@@ -17565,9 +23206,16 @@ Rules:
             self.stats_inc_tool("web_fetch");
             let include_headers = args.include_headers.unwrap_or(false);
             let include_text = args.include_text.unwrap_or(false);
+            let include_metadata = args.include_metadata.unwrap_or(false);
+            let include_error_body = args.include_error_body.unwrap_or(false);
             let max_text_chars = args.max_text_chars.unwrap_or(20_000).min(200_000);
             let fetch_backend = args.fetch_backend.unwrap_or_else(|| "local".to_string());
             let url = args.url.clone().unwrap_or_default();
+            let expect_content_type = args
+                .expect_content_type
+                .clone()
+                .filter(|ct| !ct.trim().is_empty());
+            let known_sha256 = args.known_sha256.clone().filter(|h| !h.trim().is_empty());
             let privacy = privacy_mode_from_env();
 
             // Offline-only mode: never allow non-localhost fetches. (But keep invalid_params
@@ -17668,7 +23316,9 @@ Rules:
                         "cache": { "read": cache_read_effective, "write": cache_write_effective, "ttl_s": args.cache_ttl_s },
                         "include_text": include_text,
                         "max_text_chars": max_text_chars,
-                        "include_headers": include_headers
+                        "include_headers": include_headers,
+                        "include_metadata": include_metadata,
+                        "include_metadata": include_metadata
                     }
                 });
                 add_envelope_fields(&mut payload, "web_fetch", t0.elapsed().as_millis());
@@ -17767,6 +23417,24 @@ Rules:
                         return Ok(tool_result_markdown_with_json(payload, md));
                     }
                 };
+                if let Some(expected) = expect_content_type.as_deref() {
+                    if let Some((actual, sniffed)) =
+                        Self::content_type_mismatch(expected, Some("text/markdown"), b"")
+                    {
+                        let mut payload = Self::content_type_mismatch_payload(
+                            &url, &url, 200, expected, &actual, sniffed,
+                        );
+                        payload["fetch_backend"] = serde_json::json!("firecrawl");
+                        payload["request"] = serde_json::json!({
+                            "fetch_backend": "firecrawl",
+                            "timeout_ms": timeout_ms,
+                            "expect_content_type": expected
+                        });
+                        add_envelope_fields(&mut payload, "web_fetch", t0.elapsed().as_millis());
+                        let md = web_fetch_markdown(&payload);
+                        return Ok(tool_result_markdown_with_json(payload, md));
+                    }
+                }
                 let bytes_est = Self::approx_bytes_len(&r.markdown);
                 let cleaned = clean_text_for_output(r.markdown);
                 let (text, n, text_clipped) = Self::truncate_to_chars(&cleaned, max_text_chars);
@@ -17800,7 +23468,9 @@ Rules:
                     "cache_ttl_s": args.cache_ttl_s,
                     "include_text": include_text,
                     "max_text_chars": max_text_chars,
-                    "include_headers": include_headers
+                    "include_headers": include_headers,
+                    "include_metadata": include_metadata,
+                    "include_metadata": include_metadata
                 });
                 if include_text {
                     payload["body_text"] = serde_json::json!(text);
@@ -17809,6 +23479,14 @@ Rules:
                     payload["headers"] = serde_json::json!({});
                     warnings.push("headers_unavailable_for_firecrawl");
                 }
+                if include_metadata {
+                    payload["metadata"] = serde_json::json!({});
+                }
+                Self::apply_body_fingerprint(
+                    &mut payload,
+                    cleaned.as_bytes(),
+                    known_sha256.as_deref(),
+                );
                 if !warnings.is_empty() {
                     payload["warnings"] = serde_json::json!(warnings);
                     let codes = warning_codes_from(&warnings);
@@ -17830,6 +23508,8 @@ Rules:
                 timeout_ms: args.timeout_ms.or(Some(15_000)),
                 max_bytes: args.max_bytes.or(Some(5_000_000)),
                 headers: BTreeMap::new(), // filled below (after filtering)
+                method: None,
+                body: None,
                 cache: FetchCachePolicy {
                     read: args.cache_read.unwrap_or(true) || no_network,
                     write: if no_network {
@@ -17838,6 +23518,8 @@ Rules:
                         args.cache_write.unwrap_or(true)
                     },
                     ttl_s: args.cache_ttl_s,
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
                 },
             };
             // Filter user-provided request headers at the boundary so they don't affect:
@@ -17873,6 +23555,24 @@ Rules:
             }
             dropped_request_headers.sort();
             dropped_request_headers.dedup();
+            if let Some(raw) = args.referer.as_deref().filter(|s| !s.trim().is_empty()) {
+                let Some(referer) = parse_referer(raw) else {
+                    let mut payload = serde_json::json!({
+                        "ok": false,
+                        "url": req.url,
+                        "error": error_obj(
+                            ErrorCode::InvalidParams,
+                            "referer must be an absolute http(s) URL",
+                            "Pass the page the link was found on, e.g. https://example.com/index.html."
+                        ),
+                        "request": { "fetch_backend": fetch_backend, "referer": raw }
+                    });
+                    add_envelope_fields(&mut payload, "web_fetch", t0.elapsed().as_millis());
+                    let md = web_fetch_markdown(&payload);
+                    return Ok(tool_result_markdown_with_json(payload, md));
+                };
+                set_referer_header(&mut filtered_headers, referer);
+            }
             let req = FetchRequest {
                 headers: filtered_headers,
                 ..req
@@ -17881,6 +23581,36 @@ Rules:
             if no_network && !url_is_localhost(&req.url) {
                 match self.fetcher.cache_get(&req) {
                     Ok(Some(resp)) => {
+                        if let Some(expected) = expect_content_type.as_deref() {
+                            if let Some((actual, sniffed)) = Self::content_type_mismatch(
+                                expected,
+                                resp.content_type.as_deref(),
+                                &resp.bytes,
+                            ) {
+                                let mut payload = Self::content_type_mismatch_payload(
+                                    &url,
+                                    &resp.final_url,
+                                    resp.status,
+                                    expected,
+                                    &actual,
+                                    sniffed,
+                                );
+                                payload["fetch_backend"] = serde_json::json!("local");
+                                payload["source"] = serde_json::json!("cache");
+                                payload["request"] = serde_json::json!({
+                                    "fetch_backend": "local",
+                                    "no_network": true,
+                                    "expect_content_type": expected
+                                });
+                                add_envelope_fields(
+                                    &mut payload,
+                                    "web_fetch",
+                                    t0.elapsed().as_millis(),
+                                );
+                                let md = web_fetch_markdown(&payload);
+                                return Ok(tool_result_markdown_with_json(payload, md));
+                            }
+                        }
                         let is_pdf_like = Self::content_type_is_pdf(resp.content_type.as_deref())
                             || Self::url_looks_like_pdf(resp.final_url.as_str())
                             || webpipe_local::extract::bytes_look_like_pdf(&resp.bytes);
@@ -17893,7 +23623,10 @@ Rules:
                         };
                         let mut warnings: Vec<&'static str> = Vec::new();
                         if resp.truncated {
-                            warnings.push("body_truncated_by_max_bytes");
+                            warnings.push(truncation_warning(&resp.timings_ms));
+                        }
+                        if resp.timings_ms.contains_key("decode_failed") {
+                            warnings.push("decode_failed");
                         }
                         let status_bad = resp.status >= 400;
                         if status_bad {
@@ -17923,6 +23656,9 @@ Rules:
                             "status": resp.status,
                             "content_type": resp.content_type,
                             "bytes": resp.bytes.len(),
+                            "wire_bytes": resp.wire_bytes,
+                            "decoded_bytes": resp.bytes.len(),
+                            "filename": resp.filename(),
                             "truncated": resp.truncated,
                             "source": "cache",
                             "text_chars": n,
@@ -17936,7 +23672,11 @@ Rules:
                                 "cache": { "read": true, "write": false, "ttl_s": req.cache.ttl_s },
                                 "include_text": include_text,
                                 "max_text_chars": max_text_chars,
-                                "include_headers": include_headers
+                                "include_headers": include_headers,
+                                "include_metadata": include_metadata,
+                                "expect_content_type": expect_content_type,
+                                "include_error_body": include_error_body,
+                                "known_sha256": known_sha256
                             },
                             "warnings": warnings
                         });
@@ -17945,6 +23685,9 @@ Rules:
                             payload["request"]["dropped_request_headers"] =
                                 serde_json::json!(dropped_request_headers);
                         }
+                        if let Some(r) = req.headers.get("Referer") {
+                            payload["request"]["referer"] = serde_json::json!(r);
+                        }
                         if !warnings.is_empty() {
                             let codes = warning_codes_from(&warnings);
                             payload["warning_codes"] = serde_json::json!(codes.clone());
@@ -17957,6 +23700,23 @@ Rules:
                         if include_headers {
                             payload["headers"] = serde_json::json!(resp.headers);
                         }
+                        if include_metadata {
+                            payload["metadata"] =
+                                Self::fetch_metadata_json(&resp, is_pdf_like).await;
+                        }
+                        if Self::http_status_is_error(resp.status) {
+                            Self::mark_http_status_error(&mut payload, resp.status);
+                            if !include_error_body {
+                                if let Some(o) = payload.as_object_mut() {
+                                    o.remove("body_text");
+                                }
+                            }
+                        }
+                        Self::apply_body_fingerprint(
+                            &mut payload,
+                            &resp.bytes,
+                            known_sha256.as_deref(),
+                        );
                         let md = web_fetch_markdown(&payload);
                         return Ok(tool_result_markdown_with_json(payload, md));
                     }
@@ -18039,19 +23799,54 @@ Rules:
                             "cache": { "read": req.cache.read, "write": req.cache.write, "ttl_s": req.cache.ttl_s },
                             "include_text": include_text,
                             "max_text_chars": max_text_chars,
-                            "include_headers": include_headers
+                            "include_headers": include_headers,
+                            "include_metadata": include_metadata,
+                            "include_metadata": include_metadata
                         }
                     });
                     if !dropped_request_headers.is_empty() {
                         payload["request"]["dropped_request_headers"] =
                             serde_json::json!(dropped_request_headers);
                     }
+                    if let Some(r) = req.headers.get("Referer") {
+                        payload["request"]["referer"] = serde_json::json!(r);
+                    }
                     add_envelope_fields(&mut payload, "web_fetch", t0.elapsed().as_millis());
                     let md = web_fetch_markdown(&payload);
                     return Ok(tool_result_markdown_with_json(payload, md));
                 }
             };
 
+            if let Some(expected) = expect_content_type.as_deref() {
+                if let Some((actual, sniffed)) =
+                    Self::content_type_mismatch(expected, resp.content_type.as_deref(), &resp.bytes)
+                {
+                    let mut payload = Self::content_type_mismatch_payload(
+                        &url,
+                        &resp.final_url,
+                        resp.status,
+                        expected,
+                        &actual,
+                        sniffed,
+                    );
+                    payload["fetch_backend"] = serde_json::json!("local");
+                    payload["source"] = serde_json::json!(match resp.source {
+                        FetchSource::Cache => "cache",
+                        FetchSource::Network => "network",
+                    });
+                    payload["request"] = serde_json::json!({
+                        "fetch_backend": "local",
+                        "no_network": no_network,
+                        "timeout_ms": req.timeout_ms,
+                        "max_bytes": req.max_bytes,
+                        "expect_content_type": expected
+                    });
+                    add_envelope_fields(&mut payload, "web_fetch", t0.elapsed().as_millis());
+                    let md = web_fetch_markdown(&payload);
+                    return Ok(tool_result_markdown_with_json(payload, md));
+                }
+            }
+
             let is_pdf_like = Self::content_type_is_pdf(resp.content_type.as_deref())
                 || Self::url_looks_like_pdf(resp.final_url.as_str())
                 || webpipe_local::extract::bytes_look_like_pdf(&resp.bytes);
@@ -18064,7 +23859,10 @@ Rules:
             };
             let mut warnings: Vec<&'static str> = Vec::new();
             if resp.truncated {
-                warnings.push("body_truncated_by_max_bytes");
+                warnings.push(truncation_warning(&resp.timings_ms));
+            }
+            if resp.timings_ms.contains_key("decode_failed") {
+                warnings.push("decode_failed");
             }
             if resp.timings_ms.contains_key("cache_get_timeout")
                 || resp.timings_ms.contains_key("cache_put_timeout")
@@ -18096,11 +23894,16 @@ Rules:
                 "status": resp.status,
                 "content_type": resp.content_type,
                 "bytes": resp.bytes.len(),
+                "wire_bytes": resp.wire_bytes,
+                "decoded_bytes": resp.bytes.len(),
+                "filename": resp.filename(),
                 "truncated": resp.truncated,
                 "source": match resp.source {
                     FetchSource::Cache => "cache",
                     FetchSource::Network => "network",
                 },
+                "cache_status": resp.cache_status,
+                "revalidating": resp.revalidating,
                 "text_chars": n,
                 "text_truncated": text_clipped,
                 "timings_ms": {
@@ -18122,12 +23925,19 @@ Rules:
                 "cache": { "read": req.cache.read, "write": req.cache.write, "ttl_s": req.cache.ttl_s },
                 "include_text": include_text,
                 "max_text_chars": max_text_chars,
-                "include_headers": include_headers
+                "include_headers": include_headers,
+                "include_metadata": include_metadata,
+                "expect_content_type": expect_content_type,
+                "include_error_body": include_error_body,
+                "known_sha256": known_sha256
             });
             if !dropped_request_headers.is_empty() {
                 payload["request"]["dropped_request_headers"] =
                     serde_json::json!(dropped_request_headers);
             }
+            if let Some(r) = req.headers.get("Referer") {
+                payload["request"]["referer"] = serde_json::json!(r);
+            }
 
             if include_text {
                 payload["body_text"] = serde_json::json!(text);
@@ -18136,6 +23946,9 @@ Rules:
             if include_headers {
                 payload["headers"] = serde_json::json!(resp.headers);
             }
+            if include_metadata {
+                payload["metadata"] = Self::fetch_metadata_json(&resp, is_pdf_like).await;
+            }
             if !warnings.is_empty() {
                 payload["warnings"] = serde_json::json!(warnings);
                 let codes = warning_codes_from(&warnings);
@@ -18144,6 +23957,15 @@ Rules:
                 self.stats_record_warnings(&warnings);
             }
             self.stats_record_fetch_backend("local", true, t0.elapsed().as_millis() as u64, None);
+            if Self::http_status_is_error(resp.status) {
+                Self::mark_http_status_error(&mut payload, resp.status);
+                if !include_error_body {
+                    if let Some(o) = payload.as_object_mut() {
+                        o.remove("body_text");
+                    }
+                }
+            }
+            Self::apply_body_fingerprint(&mut payload, &resp.bytes, known_sha256.as_deref());
 
             let md = web_fetch_markdown(&payload);
             Ok(tool_result_markdown_with_json(payload, md))
@@ -18162,7 +23984,7 @@ Rules:
         }
 
         #[tool(
-            description = "Best for: getting a list of relevant URLs and snippets for a query. Not this when you also need to extract page content — use search_evidence instead. Output: results[] with url/title/snippet, provider, and selection metadata. Providers: brave, tavily, searxng, auto (picks best configured).",
+            description = "Best for: getting a list of relevant URLs and snippets for a query. Not this when you also need to extract page content — use search_evidence instead. Output: results[] with url/title/snippet, provider, and selection metadata. Providers: brave, tavily, searxng, ddg (keyless DuckDuckGo HTML), auto (picks best configured).",
             input_schema = Arc::new(tool_input_schema_draft07::<WebSearchArgs>()),
             annotations(title = "Web search", read_only_hint = true, open_world_hint = true)
         )]
@@ -18173,6 +23995,8 @@ Rules:
             let args = params.0.unwrap_or_default();
             let max_results = args.max_results.unwrap_or(10).clamp(1, 20);
             let timeout_ms = args.timeout_ms.unwrap_or(20_000).min(60_000);
+            let max_per_domain = (args.diversify.unwrap_or(false) || args.max_per_domain.is_some())
+                .then(|| args.max_per_domain.unwrap_or(2).clamp(1, 20));
 
             let provider_name = args.provider.clone().unwrap_or_else(|| "brave".to_string());
             let auto_mode = args.auto_mode.unwrap_or_else(|| "fallback".to_string());
@@ -18263,13 +24087,21 @@ Rules:
                 language: language.clone(),
                 country: country.clone(),
                 timeout_ms: Some(timeout_ms),
+                site: args.site.clone(),
+                freshness: args.freshness.clone(),
+                after: args.after.clone(),
+                before: args.before.clone(),
+                offset: args.offset.map(|o| o.min(200)).filter(|o| *o > 0),
             };
+            // Later pages of a query only count toward usage totals, not the routing windows.
+            let first_page = q.offset.is_none();
             let qk = Self::query_key(&query);
 
             if provider_name.as_str() != "auto"
                 && provider_name.as_str() != "brave"
                 && provider_name.as_str() != "tavily"
                 && provider_name.as_str() != "searxng"
+                && provider_name.as_str() != "ddg"
             {
                 let mut payload = serde_json::json!({
                     "ok": false,
@@ -18287,7 +24119,7 @@ Rules:
                     "error": error_obj(
                         ErrorCode::InvalidParams,
                         format!("unknown provider: {}", provider_name),
-                        "provider must be one of: auto, brave, tavily, searxng"
+                        "provider must be one of: auto, brave, tavily, searxng, ddg"
                     )
                 });
                 add_envelope_fields(&mut payload, "web_search", t0.elapsed().as_millis());
@@ -18299,6 +24131,7 @@ Rules:
                 && auto_mode.as_str() != "fallback"
                 && auto_mode.as_str() != "merge"
                 && auto_mode.as_str() != "mab"
+                && auto_mode.as_str() != "cost_cascade"
             {
                 let mut payload = serde_json::json!({
                     "ok": false,
@@ -18316,7 +24149,7 @@ Rules:
                     "error": error_obj(
                         ErrorCode::InvalidParams,
                         "unknown auto_mode",
-                        "When provider=\"auto\", auto_mode must be one of: fallback, merge, mab"
+                        "When provider=\"auto\", auto_mode must be one of: fallback, merge, mab, cost_cascade"
                     )
                 });
                 add_envelope_fields(&mut payload, "web_search", t0.elapsed().as_millis());
@@ -18328,15 +24161,8 @@ Rules:
                 "auto" => {
                     if auto_mode.as_str() == "merge" {
                         // Merge mode: query all configured providers and dedup by URL.
-                        // ok=true if at least one provider succeeds.
-                        fn canonicalize_url(url: &str) -> String {
-                            if let Ok(mut u) = reqwest::Url::parse(url) {
-                                u.set_fragment(None);
-                                return u.to_string();
-                            }
-                            url.trim().to_string()
-                        }
-
+                        // ok=true if at least one provider succeeds. Result order is
+                        // deterministic (see `merge_search_results`).
                         let brave_env =
                             has_env("WEBPIPE_BRAVE_API_KEY") || has_env("BRAVE_SEARCH_API_KEY");
                         let tavily_env =
@@ -18376,6 +24202,8 @@ Rules:
                             elapsed_ms: u64,
                             results: Vec<webpipe_core::SearchResult>,
                             error: Option<String>,
+                            warnings: Vec<String>,
+                            has_more: bool,
                         }
 
                         let brave_fut = async {
@@ -18393,6 +24221,8 @@ Rules:
                                         cost_units: r.cost_units,
                                         elapsed_ms: pt0.elapsed().as_millis() as u64,
                                         results: r.results,
+                                        warnings: r.warnings,
+                                        has_more: r.has_more,
                                         error: None,
                                     }),
                                     Err(e) => Some(ProviderOutcome {
@@ -18401,6 +24231,8 @@ Rules:
                                         cost_units: 0,
                                         elapsed_ms: pt0.elapsed().as_millis() as u64,
                                         results: Vec::new(),
+                                        warnings: Vec::new(),
+                                        has_more: false,
                                         error: Some(e.to_string()),
                                     }),
                                 },
@@ -18410,6 +24242,8 @@ Rules:
                                     cost_units: 0,
                                     elapsed_ms: pt0.elapsed().as_millis() as u64,
                                     results: Vec::new(),
+                                    warnings: Vec::new(),
+                                    has_more: false,
                                     error: Some(e.to_string()),
                                 }),
                             }
@@ -18430,6 +24264,8 @@ Rules:
                                         cost_units: r.cost_units,
                                         elapsed_ms: pt0.elapsed().as_millis() as u64,
                                         results: r.results,
+                                        warnings: r.warnings,
+                                        has_more: r.has_more,
                                         error: None,
                                     }),
                                     Err(e) => Some(ProviderOutcome {
@@ -18438,6 +24274,8 @@ Rules:
                                         cost_units: 0,
                                         elapsed_ms: pt0.elapsed().as_millis() as u64,
                                         results: Vec::new(),
+                                        warnings: Vec::new(),
+                                        has_more: false,
                                         error: Some(e.to_string()),
                                     }),
                                 },
@@ -18447,6 +24285,8 @@ Rules:
                                     cost_units: 0,
                                     elapsed_ms: pt0.elapsed().as_millis() as u64,
                                     results: Vec::new(),
+                                    warnings: Vec::new(),
+                                    has_more: false,
                                     error: Some(e.to_string()),
                                 }),
                             }
@@ -18467,6 +24307,8 @@ Rules:
                                         cost_units: r.cost_units,
                                         elapsed_ms: pt0.elapsed().as_millis() as u64,
                                         results: r.results,
+                                        warnings: r.warnings,
+                                        has_more: r.has_more,
                                         error: None,
                                     }),
                                     Err(e) => Some(ProviderOutcome {
@@ -18475,6 +24317,8 @@ Rules:
                                         cost_units: 0,
                                         elapsed_ms: pt0.elapsed().as_millis() as u64,
                                         results: Vec::new(),
+                                        warnings: Vec::new(),
+                                        has_more: false,
                                         error: Some(e.to_string()),
                                     }),
                                 },
@@ -18484,6 +24328,8 @@ Rules:
                                     cost_units: 0,
                                     elapsed_ms: pt0.elapsed().as_millis() as u64,
                                     results: Vec::new(),
+                                    warnings: Vec::new(),
+                                    has_more: false,
                                     error: Some(e.to_string()),
                                 }),
                             }
@@ -18493,8 +24339,6 @@ Rules:
                             tokio::join!(brave_fut, searxng_fut, tavily_fut);
 
                         let mut providers = Vec::new();
-                        let mut merged: Vec<webpipe_core::SearchResult> = Vec::new();
-                        let mut seen = std::collections::BTreeSet::<String>::new();
                         let mut cost_units_total: u64 = 0;
 
                         // Stable provider ordering for deterministic JSON.
@@ -18502,9 +24346,16 @@ Rules:
                             .into_iter()
                             .flatten()
                             .collect();
+                        let mut provider_warnings: Vec<String> = Vec::new();
+                        let has_more = outs.iter().any(|o| o.ok && o.has_more);
                         for o in &outs {
                             if o.ok {
                                 cost_units_total = cost_units_total.saturating_add(o.cost_units);
+                                for w in &o.warnings {
+                                    if !provider_warnings.contains(w) {
+                                        provider_warnings.push(w.clone());
+                                    }
+                                }
                             }
                             let mut row = serde_json::json!({
                                 "name": o.name,
@@ -18518,7 +24369,8 @@ Rules:
                             }
                             providers.push(row);
 
-                            self.stats_record_search_provider_qk(
+                            self.stats_record_search_page(
+                                first_page,
                                 o.name,
                                 o.ok,
                                 if o.ok { o.cost_units } else { 0 },
@@ -18529,23 +24381,13 @@ Rules:
                         }
 
                         // Merge results from all providers, bounded by max_results.
-                        for o in outs {
-                            if !o.ok {
-                                continue;
-                            }
-                            for rr in o.results {
-                                let key = canonicalize_url(&rr.url);
-                                if seen.insert(key) {
-                                    merged.push(rr);
-                                }
-                                if merged.len() >= max_results {
-                                    break;
-                                }
-                            }
-                            if merged.len() >= max_results {
-                                break;
-                            }
-                        }
+                        let merged = Self::merge_search_results(
+                            outs.into_iter()
+                                .filter(|o| o.ok)
+                                .map(|o| (o.name, o.results))
+                                .collect(),
+                            max_results,
+                        );
 
                         let ok_any = providers
                             .iter()
@@ -18581,7 +24423,7 @@ Rules:
                             "backend_provider": "merge",
                             "query": query.clone(),
                             "max_results": max_results,
-                            "request": { "provider": "auto", "auto_mode": "merge", "query": q.query, "max_results": max_results, "language": q.language, "country": q.country },
+                            "request": { "provider": "auto", "auto_mode": "merge", "query": q.query, "max_results": max_results, "language": q.language, "country": q.country, "freshness": q.freshness, "after": q.after, "before": q.before, "offset": q.offset },
                             "selection": { "requested_provider": "auto", "auto_mode": "merge", "selected_provider": "merge" },
                             "providers": providers,
                             "cost_units": cost_units_total,
@@ -18613,6 +24455,274 @@ Rules:
                             payload["warning_codes"] = serde_json::json!(codes.clone());
                             payload["warning_hints"] = warning_hints_from(&codes);
                         }
+                        merge_search_warnings(&mut payload, &provider_warnings);
+                        set_search_page(&mut payload, q.offset.unwrap_or(0), has_more);
+                        if let Some(cap) = max_per_domain {
+                            let dropped = diversify_search_results(&mut payload, cap);
+                            payload["request"]["max_per_domain"] = serde_json::json!(cap);
+                            payload["diversified_count"] = serde_json::json!(dropped);
+                        }
+                        add_envelope_fields(&mut payload, "web_search", t0.elapsed().as_millis());
+                        let md = web_search_markdown(&payload);
+                        return Ok(tool_result_markdown_with_json(payload, md));
+                    }
+
+                    if auto_mode.as_str() == "cost_cascade" {
+                        // Cost cascade: free tier first, paid tier only when the free results
+                        // fail a cheap quality gate (too few results, or mostly auth walls).
+                        //
+                        // Gate knobs (env):
+                        // - WEBPIPE_COST_CASCADE_MIN_RESULTS (default: 3; capped at max_results)
+                        // - WEBPIPE_COST_CASCADE_MAX_JUNK_FRAC (default: 0.5)
+                        let min_results = std::env::var("WEBPIPE_COST_CASCADE_MIN_RESULTS")
+                            .ok()
+                            .and_then(|v| v.trim().parse::<usize>().ok())
+                            .unwrap_or(3)
+                            .clamp(1, max_results);
+                        let max_junk_frac = std::env::var("WEBPIPE_COST_CASCADE_MAX_JUNK_FRAC")
+                            .ok()
+                            .and_then(|v| v.trim().parse::<f64>().ok())
+                            .filter(|x| x.is_finite())
+                            .unwrap_or(0.5)
+                            .clamp(0.0, 1.0);
+
+                        let searxng_env = has_env("WEBPIPE_SEARXNG_ENDPOINT")
+                            || has_env("WEBPIPE_SEARXNG_ENDPOINTS");
+                        let brave_env =
+                            has_env("WEBPIPE_BRAVE_API_KEY") || has_env("BRAVE_SEARCH_API_KEY");
+                        let tavily_env =
+                            has_env("WEBPIPE_TAVILY_API_KEY") || has_env("TAVILY_API_KEY");
+                        // Stable order: free before paid, cheaper paid before pricier paid.
+                        let chain: Vec<(&'static str, &'static str)> = [
+                            ("searxng", "free", searxng_env),
+                            ("brave", "paid", brave_env),
+                            ("tavily", "paid", tavily_env),
+                        ]
+                        .into_iter()
+                        .filter(|(_, _, configured)| *configured)
+                        .map(|(name, tier, _)| (name, tier))
+                        .collect();
+                        let gate = serde_json::json!({
+                            "min_results": min_results,
+                            "max_junk_frac": max_junk_frac
+                        });
+                        let request = serde_json::json!({ "provider": "auto", "auto_mode": "cost_cascade", "query": query.clone(), "query_key": qk.clone(), "max_results": max_results, "language": language, "country": country });
+                        if chain.is_empty() {
+                            let mut payload = serde_json::json!({
+                                "ok": false,
+                                "provider": "auto",
+                                "query": query.clone(),
+                                "max_results": max_results,
+                                "selection": { "requested_provider": "auto", "auto_mode": "cost_cascade", "selected_provider": "none" },
+                                "request": request,
+                                "routing_explain": { "mode": "cost_cascade", "gate": gate, "steps": [] },
+                                "error": error_obj(
+                                    ErrorCode::NotConfigured,
+                                    "no web search providers configured",
+                                    "Set WEBPIPE_SEARXNG_ENDPOINT(S) for the free tier and/or WEBPIPE_BRAVE_API_KEY / WEBPIPE_TAVILY_API_KEY for the paid tier."
+                                )
+                            });
+                            add_envelope_fields(
+                                &mut payload,
+                                "web_search",
+                                t0.elapsed().as_millis(),
+                            );
+                            let md = web_search_markdown(&payload);
+                            return Ok(tool_result_markdown_with_json(payload, md));
+                        }
+
+                        let mut steps: Vec<serde_json::Value> = Vec::new();
+                        let mut providers: Vec<serde_json::Value> = Vec::new();
+                        let mut cost_units_total: u64 = 0;
+                        // Best below-gate free answer, returned if nothing paid is configured/works.
+                        let mut fallback: Option<(&'static str, webpipe_core::SearchResponse)> =
+                            None;
+                        let mut accepted: Option<(&'static str, webpipe_core::SearchResponse)> =
+                            None;
+                        for (i, (name, tier)) in chain.iter().copied().enumerate() {
+                            let pt0 = std::time::Instant::now();
+                            let r = match name {
+                                "searxng" => {
+                                    match webpipe_local::search::SearxngSearchProvider::from_env(
+                                        client.clone(),
+                                    ) {
+                                        Ok(p) => p.search(&q).await,
+                                        Err(e) => Err(e),
+                                    }
+                                }
+                                "brave" => {
+                                    match webpipe_local::search::BraveSearchProvider::from_env(
+                                        client.clone(),
+                                    ) {
+                                        Ok(p) => p.search(&q).await,
+                                        Err(e) => Err(e),
+                                    }
+                                }
+                                _ => match webpipe_local::search::TavilySearchProvider::from_env(
+                                    client.clone(),
+                                ) {
+                                    Ok(p) => p.search(&q).await,
+                                    Err(e) => Err(e),
+                                },
+                            };
+                            let elapsed_ms = pt0.elapsed().as_millis() as u64;
+                            let has_next = i + 1 < chain.len();
+                            match r {
+                                Ok(r) => {
+                                    self.stats_record_search_page(
+                                        first_page,
+                                        name,
+                                        true,
+                                        r.cost_units,
+                                        elapsed_ms,
+                                        None,
+                                        qk.as_deref(),
+                                    );
+                                    cost_units_total =
+                                        cost_units_total.saturating_add(r.cost_units);
+                                    providers.push(serde_json::json!({"name":name,"ok":true,"cost_units":r.cost_units,"elapsed_ms":elapsed_ms}));
+                                    let n = r.results.len();
+                                    let junk = r
+                                        .results
+                                        .iter()
+                                        .filter(|x| url_looks_like_auth_or_challenge(&x.url))
+                                        .count();
+                                    let junk_frac =
+                                        if n == 0 { 1.0 } else { junk as f64 / n as f64 };
+                                    // The gate only guards spending: paid results are accepted as-is.
+                                    let passes = tier == "paid"
+                                        || (n >= min_results && junk_frac <= max_junk_frac);
+                                    let reason = if tier == "paid" {
+                                        "paid_tier"
+                                    } else if n == 0 {
+                                        "empty_results"
+                                    } else if n < min_results {
+                                        "too_few_results"
+                                    } else if junk_frac > max_junk_frac {
+                                        "mostly_auth_wall_urls"
+                                    } else {
+                                        "quality_gate_passed"
+                                    };
+                                    let decision = if passes {
+                                        "accept"
+                                    } else if has_next {
+                                        "escalate"
+                                    } else {
+                                        "accept_below_gate"
+                                    };
+                                    steps.push(serde_json::json!({
+                                        "provider": name,
+                                        "tier": tier,
+                                        "ok": true,
+                                        "results": n,
+                                        "auth_wall_results": junk,
+                                        "decision": decision,
+                                        "reason": reason,
+                                        "elapsed_ms": elapsed_ms
+                                    }));
+                                    if passes {
+                                        accepted = Some((name, r));
+                                        break;
+                                    }
+                                    if fallback.as_ref().map(|(_, f)| f.results.len()).unwrap_or(0)
+                                        <= n
+                                    {
+                                        fallback = Some((name, r));
+                                    }
+                                }
+                                Err(e) => {
+                                    let msg = e.to_string();
+                                    self.stats_record_search_page(
+                                        first_page,
+                                        name,
+                                        false,
+                                        0,
+                                        elapsed_ms,
+                                        Some(&msg),
+                                        qk.as_deref(),
+                                    );
+                                    providers.push(serde_json::json!({"name":name,"ok":false,"error":msg.clone(),"elapsed_ms":elapsed_ms}));
+                                    steps.push(serde_json::json!({
+                                        "provider": name,
+                                        "tier": tier,
+                                        "ok": false,
+                                        "decision": if has_next { "escalate" } else { "give_up" },
+                                        "reason": "provider_error",
+                                        "error": msg,
+                                        "elapsed_ms": elapsed_ms
+                                    }));
+                                }
+                            }
+                        }
+
+                        let below_gate = accepted.is_none() && fallback.is_some();
+                        let Some((backend, r)) = accepted.or(fallback) else {
+                            let mut payload = serde_json::json!({
+                                "ok": false,
+                                "provider": "auto",
+                                "query": query.clone(),
+                                "max_results": max_results,
+                                "selection": { "requested_provider": "auto", "auto_mode": "cost_cascade", "selected_provider": "none" },
+                                "request": request,
+                                "providers": providers,
+                                "routing_explain": { "mode": "cost_cascade", "gate": gate, "steps": steps },
+                                "error": error_obj(
+                                    ErrorCode::SearchFailed,
+                                    "all configured providers failed",
+                                    "Inspect `routing_explain.steps` for per-provider errors; retry later or switch provider."
+                                )
+                            });
+                            add_envelope_fields(
+                                &mut payload,
+                                "web_search",
+                                t0.elapsed().as_millis(),
+                            );
+                            let md = web_search_markdown(&payload);
+                            return Ok(tool_result_markdown_with_json(payload, md));
+                        };
+                        let paid_used = steps.iter().any(|s| {
+                            s["tier"].as_str() == Some("paid") && s["ok"].as_bool() == Some(true)
+                        });
+                        let mut payload = serde_json::json!({
+                            "ok": true,
+                            "provider": "auto",
+                            "backend_provider": backend,
+                            "query": query.clone(),
+                            "query_key": qk.clone(),
+                            "max_results": max_results,
+                            "request": request,
+                            "selection": { "requested_provider": "auto", "auto_mode": "cost_cascade", "selected_provider": backend },
+                            "providers": providers,
+                            "routing_explain": {
+                                "mode": "cost_cascade",
+                                "gate": gate,
+                                "steps": steps,
+                                "paid_used": paid_used
+                            },
+                            "cost_units": cost_units_total,
+                            "timings_ms": { "total": t0.elapsed().as_millis() },
+                            "results": r.results
+                        });
+                        let mut ws: Vec<&'static str> = Vec::new();
+                        if below_gate {
+                            ws.push("cost_cascade_below_quality_gate");
+                        }
+                        if backend == "tavily" {
+                            ws.push("tavily_used");
+                        }
+                        if !ws.is_empty() {
+                            payload["warnings"] = serde_json::json!(ws);
+                            let codes = warning_codes_from(&ws);
+                            payload["warning_codes"] = serde_json::json!(codes.clone());
+                            payload["warning_hints"] = warning_hints_from(&codes);
+                        }
+                        merge_search_warnings(&mut payload, &r.warnings);
+                        set_search_page(&mut payload, r.offset, r.has_more);
+                        if let Some(cap) = max_per_domain {
+                            let dropped = diversify_search_results(&mut payload, cap);
+                            payload["request"]["max_per_domain"] = serde_json::json!(cap);
+                            payload["diversified_count"] = serde_json::json!(dropped);
+                        }
                         add_envelope_fields(&mut payload, "web_search", t0.elapsed().as_millis());
                         let md = web_search_markdown(&payload);
                         return Ok(tool_result_markdown_with_json(payload, md));
@@ -18740,6 +24850,8 @@ Rules:
                         }
 
                         let cfg = muxer::MabConfig {
+                            strategy: muxer::MabStrategy::from_env(),
+                            seed: muxer::mab_seed_from_env(),
                             exploration_c,
                             cost_weight: cost_w,
                             latency_weight: lat_w,
@@ -18766,7 +24878,8 @@ Rules:
                                         Ok(p) => p,
                                         Err(e) => {
                                             let msg = e.to_string();
-                                            self.stats_record_search_provider_qk(
+                                            self.stats_record_search_page(
+                                                first_page,
                                                 "tavily",
                                                 false,
                                                 0,
@@ -18780,7 +24893,7 @@ Rules:
                                                 "backend_provider": "tavily",
                                                 "query": query.clone(),
                                                 "max_results": max_results,
-                                                "selection": { "requested_provider": "auto", "auto_mode": "mab", "selected_provider": "tavily", "mab": { "candidates": debug_rows, "frontier": frontier_names, "routing_context_used": routing_context_used, "routing_query_key": qk } },
+                                                "selection": { "requested_provider": "auto", "auto_mode": "mab", "selected_provider": "tavily", "mab": { "strategy": sel.strategy, "candidates": debug_rows, "frontier": frontier_names, "routing_context_used": routing_context_used, "routing_query_key": qk } },
                                                 "request": { "provider": "auto", "auto_mode": "mab", "query": query.clone(), "max_results": max_results, "language": language, "country": country },
                                                 "error": error_obj(ErrorCode::NotConfigured, msg, "Tavily was selected but is not configured. Set WEBPIPE_TAVILY_API_KEY (or TAVILY_API_KEY), or use provider=brave.")
                                             });
@@ -18795,7 +24908,8 @@ Rules:
                                     };
                                 match provider.search(&q).await {
                                     Ok(r) => {
-                                        self.stats_record_search_provider_qk(
+                                        self.stats_record_search_page(
+                                            first_page,
                                             "tavily",
                                             true,
                                             r.cost_units,
@@ -18807,7 +24921,8 @@ Rules:
                                     }
                                     Err(e) => {
                                         let msg = e.to_string();
-                                        self.stats_record_search_provider_qk(
+                                        self.stats_record_search_page(
+                                            first_page,
                                             "tavily",
                                             false,
                                             0,
@@ -18826,7 +24941,7 @@ Rules:
                                             "backend_provider": "tavily",
                                             "query": query.clone(),
                                             "max_results": max_results,
-                                            "selection": { "requested_provider": "auto", "auto_mode": "mab", "selected_provider": "tavily", "mab": { "candidates": debug_rows, "frontier": frontier_names, "routing_context_used": routing_context_used, "routing_query_key": qk } },
+                                            "selection": { "requested_provider": "auto", "auto_mode": "mab", "selected_provider": "tavily", "mab": { "strategy": sel.strategy, "candidates": debug_rows, "frontier": frontier_names, "routing_context_used": routing_context_used, "routing_query_key": qk } },
                                             "request": { "provider": "auto", "auto_mode": "mab", "query": query.clone(), "max_results": max_results, "language": language, "country": country },
                                             "error": error_obj(ErrorCode::SearchFailed, msg, hint)
                                         });
@@ -18873,7 +24988,8 @@ Rules:
 
                                 match run.await {
                                     Ok(r) => {
-                                        self.stats_record_search_provider_qk(
+                                        self.stats_record_search_page(
+                                            first_page,
                                             &stats_key,
                                             true,
                                             r.cost_units,
@@ -18885,7 +25001,8 @@ Rules:
                                     }
                                     Err(e) => {
                                         let msg = e.to_string();
-                                        self.stats_record_search_provider_qk(
+                                        self.stats_record_search_page(
+                                            first_page,
                                             &stats_key,
                                             false,
                                             0,
@@ -18904,7 +25021,7 @@ Rules:
                                             "backend_provider": "searxng",
                                             "query": query.clone(),
                                             "max_results": max_results,
-                                            "selection": { "requested_provider": "auto", "auto_mode": "mab", "selected_provider": "searxng", "mab": { "candidates": debug_rows, "frontier": frontier_names, "routing_context_used": routing_context_used, "routing_query_key": qk } },
+                                            "selection": { "requested_provider": "auto", "auto_mode": "mab", "selected_provider": "searxng", "mab": { "strategy": sel.strategy, "candidates": debug_rows, "frontier": frontier_names, "routing_context_used": routing_context_used, "routing_query_key": qk } },
                                             "request": { "provider": "auto", "auto_mode": "mab", "query": query.clone(), "max_results": max_results, "language": language, "country": country },
                                             "error": error_obj(ErrorCode::SearchFailed, msg, hint)
                                         });
@@ -18930,7 +25047,8 @@ Rules:
                                         Ok(p) => p,
                                         Err(e) => {
                                             let msg = e.to_string();
-                                            self.stats_record_search_provider_qk(
+                                            self.stats_record_search_page(
+                                                first_page,
                                                 "brave",
                                                 false,
                                                 0,
@@ -18944,7 +25062,7 @@ Rules:
                                                 "backend_provider": "brave",
                                                 "query": query.clone(),
                                                 "max_results": max_results,
-                                                "selection": { "requested_provider": "auto", "auto_mode": "mab", "selected_provider": "brave", "mab": { "candidates": debug_rows, "frontier": frontier_names, "routing_context_used": routing_context_used, "routing_query_key": qk } },
+                                                "selection": { "requested_provider": "auto", "auto_mode": "mab", "selected_provider": "brave", "mab": { "strategy": sel.strategy, "candidates": debug_rows, "frontier": frontier_names, "routing_context_used": routing_context_used, "routing_query_key": qk } },
                                                 "request": { "provider": "auto", "auto_mode": "mab", "query": query.clone(), "max_results": max_results, "language": language, "country": country },
                                                 "error": error_obj(ErrorCode::NotConfigured, msg, "Brave was selected but is not configured. Set WEBPIPE_BRAVE_API_KEY (or BRAVE_SEARCH_API_KEY), or use provider=tavily.")
                                             });
@@ -18959,7 +25077,8 @@ Rules:
                                     };
                                 match provider.search(&q).await {
                                     Ok(r) => {
-                                        self.stats_record_search_provider_qk(
+                                        self.stats_record_search_page(
+                                            first_page,
                                             "brave",
                                             true,
                                             r.cost_units,
@@ -18971,7 +25090,8 @@ Rules:
                                     }
                                     Err(e) => {
                                         let msg = e.to_string();
-                                        self.stats_record_search_provider_qk(
+                                        self.stats_record_search_page(
+                                            first_page,
                                             "brave",
                                             false,
                                             0,
@@ -18990,7 +25110,7 @@ Rules:
                                             "backend_provider": "brave",
                                             "query": query.clone(),
                                             "max_results": max_results,
-                                            "selection": { "requested_provider": "auto", "auto_mode": "mab", "selected_provider": "brave", "mab": { "candidates": debug_rows, "frontier": frontier_names, "routing_context_used": routing_context_used, "routing_query_key": qk } },
+                                            "selection": { "requested_provider": "auto", "auto_mode": "mab", "selected_provider": "brave", "mab": { "strategy": sel.strategy, "candidates": debug_rows, "frontier": frontier_names, "routing_context_used": routing_context_used, "routing_query_key": qk } },
                                             "request": { "provider": "auto", "auto_mode": "mab", "query": query.clone(), "max_results": max_results, "language": language, "country": country },
                                             "error": error_obj(ErrorCode::SearchFailed, msg, hint)
                                         });
@@ -19013,8 +25133,8 @@ Rules:
                             "query": query.clone(),
                             "query_key": Self::query_key(&query),
                             "max_results": max_results,
-                            "request": { "provider": "auto", "auto_mode": "mab", "query": q.query, "query_key": Self::query_key(&q.query), "max_results": max_results, "language": q.language, "country": q.country },
-                            "selection": { "requested_provider": "auto", "selected_provider": backend_provider, "auto_mode": "mab", "mab": { "candidates": debug_rows, "frontier": frontier_names, "routing_context_used": routing_context_used, "routing_query_key": qk, "exploration_c": exploration_c, "cost_weight": cost_w, "latency_weight": lat_w, "junk_weight": junk_w, "hard_junk_weight": hard_junk_w, "constraints": { "max_junk_rate": max_junk_rate, "max_hard_junk_rate": max_hard_junk_rate, "max_http_429_rate": max_http_429_rate, "max_mean_cost_units": max_mean_cost_units } } },
+                            "request": { "provider": "auto", "auto_mode": "mab", "query": q.query, "query_key": Self::query_key(&q.query), "max_results": max_results, "language": q.language, "country": q.country, "freshness": q.freshness, "after": q.after, "before": q.before, "offset": q.offset },
+                            "selection": { "requested_provider": "auto", "selected_provider": backend_provider, "auto_mode": "mab", "mab": { "strategy": sel.strategy, "candidates": debug_rows, "frontier": frontier_names, "routing_context_used": routing_context_used, "routing_query_key": qk, "exploration_c": exploration_c, "cost_weight": cost_w, "latency_weight": lat_w, "junk_weight": junk_w, "hard_junk_weight": hard_junk_w, "constraints": { "max_junk_rate": max_junk_rate, "max_hard_junk_rate": max_hard_junk_rate, "max_http_429_rate": max_http_429_rate, "max_mean_cost_units": max_mean_cost_units } } },
                             "cost_units": out.cost_units,
                             "timings_ms": { "total": t0.elapsed().as_millis() },
                             "results": out.results
@@ -19028,6 +25148,13 @@ Rules:
                             payload["warning_codes"] = serde_json::json!(codes.clone());
                             payload["warning_hints"] = warning_hints_from(&codes);
                         }
+                        merge_search_warnings(&mut payload, &out.warnings);
+                        set_search_page(&mut payload, out.offset, out.has_more);
+                        if let Some(cap) = max_per_domain {
+                            let dropped = diversify_search_results(&mut payload, cap);
+                            payload["request"]["max_per_domain"] = serde_json::json!(cap);
+                            payload["diversified_count"] = serde_json::json!(dropped);
+                        }
                         add_envelope_fields(&mut payload, "web_search", t0.elapsed().as_millis());
                         let md = web_search_markdown(&payload);
                         return Ok(tool_result_markdown_with_json(payload, md));
@@ -19133,6 +25260,8 @@ Rules:
                         self.snapshot_search_summaries_for_query_key(qk.as_deref());
 
                     let cfg = muxer::MabConfig {
+                        strategy: muxer::MabStrategy::from_env(),
+                        seed: muxer::mab_seed_from_env(),
                         exploration_c: env_f64("WEBPIPE_MAB_EXPLORATION_C").unwrap_or(0.7),
                         cost_weight: env_f64("WEBPIPE_MAB_COST_WEIGHT").unwrap_or(0.0),
                         latency_weight: env_f64("WEBPIPE_MAB_LATENCY_WEIGHT").unwrap_or(0.0),
@@ -19173,7 +25302,8 @@ Rules:
                                 Ok(p) => match p.search(&q).await {
                                     Ok(r) => {
                                         attempts.push(serde_json::json!({"name":"brave","ok":true,"cost_units":r.cost_units,"elapsed_ms":pt0.elapsed().as_millis()}));
-                                        self.stats_record_search_provider_qk(
+                                        self.stats_record_search_page(
+                                            first_page,
                                             "brave",
                                             true,
                                             r.cost_units,
@@ -19188,8 +25318,8 @@ Rules:
                                             "query": query.clone(),
                                             "query_key": Self::query_key(&query),
                                             "max_results": max_results,
-                                            "request": { "provider": "auto", "auto_mode": auto_mode, "query": q.query, "query_key": Self::query_key(&q.query), "max_results": max_results, "language": q.language, "country": q.country },
-                                            "selection": { "requested_provider": "auto", "selected_provider": "brave", "auto_mode": auto_mode, "mab": { "candidates": debug_rows0, "frontier": frontier0, "routing_context_used": routing_context_used, "routing_query_key": qk, "attempted_chain": attempted_chain } },
+                                            "request": { "provider": "auto", "auto_mode": auto_mode, "query": q.query, "query_key": Self::query_key(&q.query), "max_results": max_results, "language": q.language, "country": q.country, "freshness": q.freshness, "after": q.after, "before": q.before, "offset": q.offset },
+                                            "selection": { "requested_provider": "auto", "selected_provider": "brave", "auto_mode": auto_mode, "mab": { "strategy": sel0.strategy, "candidates": debug_rows0, "frontier": frontier0, "routing_context_used": routing_context_used, "routing_query_key": qk, "attempted_chain": attempted_chain } },
                                             "providers": attempts,
                                             "cost_units": r.cost_units,
                                             "timings_ms": { "total": t0.elapsed().as_millis() },
@@ -19208,6 +25338,16 @@ Rules:
                                                 serde_json::json!(codes.clone());
                                             payload["warning_hints"] = warning_hints_from(&codes);
                                         }
+                                        merge_search_warnings(&mut payload, &r.warnings);
+                                        set_search_page(&mut payload, r.offset, r.has_more);
+                                        if let Some(cap) = max_per_domain {
+                                            let dropped =
+                                                diversify_search_results(&mut payload, cap);
+                                            payload["request"]["max_per_domain"] =
+                                                serde_json::json!(cap);
+                                            payload["diversified_count"] =
+                                                serde_json::json!(dropped);
+                                        }
                                         add_envelope_fields(
                                             &mut payload,
                                             "web_search",
@@ -19220,7 +25360,8 @@ Rules:
                                         let elapsed_ms = pt0.elapsed().as_millis() as u64;
                                         let http_429 = is_http_status(&msg, 429);
                                         attempts.push(serde_json::json!({"name":"brave","ok":false,"error":msg.clone(),"elapsed_ms":elapsed_ms}));
-                                        self.stats_record_search_provider_qk(
+                                        self.stats_record_search_page(
+                                            first_page,
                                             "brave",
                                             false,
                                             0,
@@ -19241,7 +25382,8 @@ Rules:
                                     let elapsed_ms = pt0.elapsed().as_millis() as u64;
                                     let http_429 = is_http_status(&msg, 429);
                                     attempts.push(serde_json::json!({"name":"brave","ok":false,"error":msg.clone(),"elapsed_ms":elapsed_ms}));
-                                    self.stats_record_search_provider_qk(
+                                    self.stats_record_search_page(
+                                        first_page,
                                         "brave",
                                         false,
                                         0,
@@ -19297,7 +25439,8 @@ Rules:
                                                 serde_json::json!(format!("searxng#{i}"));
                                         }
                                         attempts.push(entry);
-                                        self.stats_record_search_provider_qk(
+                                        self.stats_record_search_page(
+                                            first_page,
                                             &stats_key,
                                             true,
                                             r.cost_units,
@@ -19312,8 +25455,8 @@ Rules:
                                             "query": query.clone(),
                                             "query_key": Self::query_key(&query),
                                             "max_results": max_results,
-                                            "request": { "provider": "auto", "auto_mode": auto_mode, "query": q.query, "query_key": Self::query_key(&q.query), "max_results": max_results, "language": q.language, "country": q.country },
-                                            "selection": { "requested_provider": "auto", "selected_provider": "searxng", "auto_mode": auto_mode, "mab": { "candidates": debug_rows0, "frontier": frontier0, "routing_context_used": routing_context_used, "routing_query_key": qk, "attempted_chain": attempted_chain } },
+                                            "request": { "provider": "auto", "auto_mode": auto_mode, "query": q.query, "query_key": Self::query_key(&q.query), "max_results": max_results, "language": q.language, "country": q.country, "freshness": q.freshness, "after": q.after, "before": q.before, "offset": q.offset },
+                                            "selection": { "requested_provider": "auto", "selected_provider": "searxng", "auto_mode": auto_mode, "mab": { "strategy": sel0.strategy, "candidates": debug_rows0, "frontier": frontier0, "routing_context_used": routing_context_used, "routing_query_key": qk, "attempted_chain": attempted_chain } },
                                             "providers": attempts,
                                             "cost_units": r.cost_units,
                                             "timings_ms": { "total": t0.elapsed().as_millis() },
@@ -19336,6 +25479,16 @@ Rules:
                                                 serde_json::json!(codes.clone());
                                             payload["warning_hints"] = warning_hints_from(&codes);
                                         }
+                                        merge_search_warnings(&mut payload, &r.warnings);
+                                        set_search_page(&mut payload, r.offset, r.has_more);
+                                        if let Some(cap) = max_per_domain {
+                                            let dropped =
+                                                diversify_search_results(&mut payload, cap);
+                                            payload["request"]["max_per_domain"] =
+                                                serde_json::json!(cap);
+                                            payload["diversified_count"] =
+                                                serde_json::json!(dropped);
+                                        }
                                         add_envelope_fields(
                                             &mut payload,
                                             "web_search",
@@ -19353,7 +25506,8 @@ Rules:
                                                 serde_json::json!(format!("searxng#{i}"));
                                         }
                                         attempts.push(entry);
-                                        self.stats_record_search_provider_qk(
+                                        self.stats_record_search_page(
+                                            first_page,
                                             &stats_key,
                                             false,
                                             0,
@@ -19376,7 +25530,8 @@ Rules:
                                     Ok(p) => match p.search(&q).await {
                                         Ok(r) => {
                                             attempts.push(serde_json::json!({"name":"tavily","ok":true,"cost_units":r.cost_units,"elapsed_ms":pt0.elapsed().as_millis()}));
-                                            self.stats_record_search_provider_qk(
+                                            self.stats_record_search_page(
+                                                first_page,
                                                 "tavily",
                                                 true,
                                                 r.cost_units,
@@ -19396,8 +25551,8 @@ Rules:
                                                 "query": query.clone(),
                                                 "query_key": Self::query_key(&query),
                                                 "max_results": max_results,
-                                                "request": { "provider": "auto", "auto_mode": auto_mode, "query": q.query, "query_key": Self::query_key(&q.query), "max_results": max_results, "language": q.language, "country": q.country },
-                                                "selection": { "requested_provider": "auto", "selected_provider": "tavily", "auto_mode": auto_mode, "mab": { "candidates": debug_rows0, "frontier": frontier0, "routing_context_used": routing_context_used, "routing_query_key": qk, "attempted_chain": attempted_chain } },
+                                                "request": { "provider": "auto", "auto_mode": auto_mode, "query": q.query, "query_key": Self::query_key(&q.query), "max_results": max_results, "language": q.language, "country": q.country, "freshness": q.freshness, "after": q.after, "before": q.before, "offset": q.offset },
+                                                "selection": { "requested_provider": "auto", "selected_provider": "tavily", "auto_mode": auto_mode, "mab": { "strategy": sel0.strategy, "candidates": debug_rows0, "frontier": frontier0, "routing_context_used": routing_context_used, "routing_query_key": qk, "attempted_chain": attempted_chain } },
                                                 "providers": attempts,
                                                 "warnings": ws,
                                                 "cost_units": r.cost_units,
@@ -19408,6 +25563,16 @@ Rules:
                                             payload["warning_codes"] =
                                                 serde_json::json!(codes.clone());
                                             payload["warning_hints"] = warning_hints_from(&codes);
+                                            merge_search_warnings(&mut payload, &r.warnings);
+                                            set_search_page(&mut payload, r.offset, r.has_more);
+                                            if let Some(cap) = max_per_domain {
+                                                let dropped =
+                                                    diversify_search_results(&mut payload, cap);
+                                                payload["request"]["max_per_domain"] =
+                                                    serde_json::json!(cap);
+                                                payload["diversified_count"] =
+                                                    serde_json::json!(dropped);
+                                            }
                                             add_envelope_fields(
                                                 &mut payload,
                                                 "web_search",
@@ -19420,7 +25585,8 @@ Rules:
                                             let elapsed_ms = pt0.elapsed().as_millis() as u64;
                                             let http_429 = is_http_status(&msg, 429);
                                             attempts.push(serde_json::json!({"name":"tavily","ok":false,"error":msg.clone(),"elapsed_ms":elapsed_ms}));
-                                            self.stats_record_search_provider_qk(
+                                            self.stats_record_search_page(
+                                                first_page,
                                                 "tavily",
                                                 false,
                                                 0,
@@ -19441,7 +25607,8 @@ Rules:
                                         let elapsed_ms = pt0.elapsed().as_millis() as u64;
                                         let http_429 = is_http_status(&msg, 429);
                                         attempts.push(serde_json::json!({"name":"tavily","ok":false,"error":msg.clone(),"elapsed_ms":elapsed_ms}));
-                                        self.stats_record_search_provider_qk(
+                                        self.stats_record_search_page(
+                                            first_page,
                                             "tavily",
                                             false,
                                             0,
@@ -19488,8 +25655,8 @@ Rules:
                         "query": query.clone(),
                         "query_key": Self::query_key(&query),
                         "max_results": max_results,
-                        "request": { "provider": "auto", "auto_mode": auto_mode, "query": q.query, "query_key": Self::query_key(&q.query), "max_results": max_results, "language": q.language, "country": q.country },
-                        "selection": { "requested_provider": "auto", "auto_mode": auto_mode, "selected_provider": "none", "mab": { "candidates": debug_rows0, "frontier": frontier0, "routing_context_used": routing_context_used, "routing_query_key": qk, "attempted_chain": attempted_chain } },
+                        "request": { "provider": "auto", "auto_mode": auto_mode, "query": q.query, "query_key": Self::query_key(&q.query), "max_results": max_results, "language": q.language, "country": q.country, "freshness": q.freshness, "after": q.after, "before": q.before, "offset": q.offset },
+                        "selection": { "requested_provider": "auto", "auto_mode": auto_mode, "selected_provider": "none", "mab": { "strategy": sel0.strategy, "candidates": debug_rows0, "frontier": frontier0, "routing_context_used": routing_context_used, "routing_query_key": qk, "attempted_chain": attempted_chain } },
                         "providers": attempts,
                         "error": error_obj(
                             ErrorCode::SearchFailed,
@@ -19507,7 +25674,8 @@ Rules:
                     ) {
                         Ok(p) => p,
                         Err(WebpipeError::NotConfigured(msg)) => {
-                            self.stats_record_search_provider_qk(
+                            self.stats_record_search_page(
+                                first_page,
                                 "brave",
                                 false,
                                 0,
@@ -19538,7 +25706,8 @@ Rules:
                     };
                     match provider.search(&q).await {
                         Ok(r) => {
-                            self.stats_record_search_provider_qk(
+                            self.stats_record_search_page(
+                                first_page,
                                 "brave",
                                 true,
                                 r.cost_units,
@@ -19550,7 +25719,8 @@ Rules:
                         }
                         Err(e) => {
                             let msg = e.to_string();
-                            self.stats_record_search_provider_qk(
+                            self.stats_record_search_page(
+                                first_page,
                                 "brave",
                                 false,
                                 0,
@@ -19591,7 +25761,8 @@ Rules:
                     ) {
                         Ok(p) => p,
                         Err(WebpipeError::NotConfigured(msg)) => {
-                            self.stats_record_search_provider_qk(
+                            self.stats_record_search_page(
+                                first_page,
                                 "tavily",
                                 false,
                                 0,
@@ -19622,7 +25793,8 @@ Rules:
                     };
                     match provider.search(&q).await {
                         Ok(r) => {
-                            self.stats_record_search_provider_qk(
+                            self.stats_record_search_page(
+                                first_page,
                                 "tavily",
                                 true,
                                 r.cost_units,
@@ -19636,7 +25808,8 @@ Rules:
                             // In this environment, Tavily can return HTTP 433 even when configured.
                             // Prefer a bounded fallback to Brave if available.
                             let msg = e.to_string();
-                            self.stats_record_search_provider_qk(
+                            self.stats_record_search_page(
+                                first_page,
                                 "tavily",
                                 false,
                                 0,
@@ -19652,7 +25825,8 @@ Rules:
                                 {
                                     let r = match brave.search(&q).await {
                                         Ok(r) => {
-                                            self.stats_record_search_provider_qk(
+                                            self.stats_record_search_page(
+                                                first_page,
                                                 "brave",
                                                 true,
                                                 r.cost_units,
@@ -19664,7 +25838,8 @@ Rules:
                                         }
                                         Err(e2) => {
                                             let msg2 = e2.to_string();
-                                            self.stats_record_search_provider_qk(
+                                            self.stats_record_search_page(
+                                                first_page,
                                                 "brave",
                                                 false,
                                                 0,
@@ -19715,6 +25890,14 @@ Rules:
                                         "timings_ms": r.timings_ms,
                                         "results": r.results,
                                     });
+                                    merge_search_warnings(&mut payload, &r.warnings);
+                                    set_search_page(&mut payload, r.offset, r.has_more);
+                                    if let Some(cap) = max_per_domain {
+                                        let dropped = diversify_search_results(&mut payload, cap);
+                                        payload["request"]["max_per_domain"] =
+                                            serde_json::json!(cap);
+                                        payload["diversified_count"] = serde_json::json!(dropped);
+                                    }
                                     add_envelope_fields(
                                         &mut payload,
                                         "web_search",
@@ -19775,7 +25958,8 @@ Rules:
                     ) {
                         Ok(p) => p,
                         Err(WebpipeError::NotConfigured(msg)) => {
-                            self.stats_record_search_provider_qk(
+                            self.stats_record_search_page(
+                                first_page,
                                 "searxng",
                                 false,
                                 0,
@@ -19806,7 +25990,8 @@ Rules:
                     };
                     match provider.search(&q).await {
                         Ok(r) => {
-                            self.stats_record_search_provider_qk(
+                            self.stats_record_search_page(
+                                first_page,
                                 "searxng",
                                 true,
                                 r.cost_units,
@@ -19818,7 +26003,8 @@ Rules:
                         }
                         Err(e) => {
                             let msg = e.to_string();
-                            self.stats_record_search_provider_qk(
+                            self.stats_record_search_page(
+                                first_page,
                                 "searxng",
                                 false,
                                 0,
@@ -19852,6 +26038,61 @@ Rules:
                         }
                     }
                 }
+                "ddg" => {
+                    let pt0 = std::time::Instant::now();
+                    let provider =
+                        webpipe_local::search::DuckDuckGoProvider::from_env(client.clone())
+                            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                    match provider.search(&q).await {
+                        Ok(r) => {
+                            self.stats_record_search_page(
+                                first_page,
+                                "ddg",
+                                true,
+                                r.cost_units,
+                                pt0.elapsed().as_millis() as u64,
+                                None,
+                                qk.as_deref(),
+                            );
+                            r
+                        }
+                        Err(e) => {
+                            let msg = e.to_string();
+                            self.stats_record_search_page(
+                                first_page,
+                                "ddg",
+                                false,
+                                0,
+                                pt0.elapsed().as_millis() as u64,
+                                Some(&msg),
+                                qk.as_deref(),
+                            );
+                            let hint = search_failed_hint(
+                                "ddg",
+                                &msg,
+                                "DuckDuckGo search failed (keyless HTML scrape; it may be rate-limited or its layout may have changed). Retry later or switch provider.",
+                            );
+                            let mut payload = serde_json::json!({
+                                "ok": false,
+                                "provider": "ddg",
+                                "query": query.clone(),
+                                "max_results": max_results,
+                                "request": { "provider": "ddg", "auto_mode": auto_mode, "query": query.clone(), "max_results": max_results, "language": language, "country": country },
+                                "error": error_obj(
+                                    ErrorCode::SearchFailed,
+                                    msg,
+                                    hint
+                                )
+                            });
+                            add_envelope_fields(
+                                &mut payload,
+                                "web_search",
+                                t0.elapsed().as_millis(),
+                            );
+                            return Ok(tool_result(payload));
+                        }
+                    }
+                }
                 other => {
                     let mut payload = serde_json::json!({
                         "ok": false,
@@ -19862,7 +26103,7 @@ Rules:
                         "error": error_obj(
                             ErrorCode::InvalidParams,
                             format!("unknown provider: {other}"),
-                            "provider must be one of: auto, brave, tavily, searxng"
+                            "provider must be one of: auto, brave, tavily, searxng, ddg"
                         )
                     });
                     add_envelope_fields(&mut payload, "web_search", t0.elapsed().as_millis());
@@ -19878,7 +26119,7 @@ Rules:
                 "query": query,
                 "query_key": Self::query_key(&query),
                 "max_results": max_results,
-                "request": { "provider": provider_name, "auto_mode": auto_mode, "query": q.query, "query_key": Self::query_key(&q.query), "max_results": max_results, "language": q.language, "country": q.country },
+                "request": { "provider": provider_name, "auto_mode": auto_mode, "query": q.query, "query_key": Self::query_key(&q.query), "max_results": max_results, "language": q.language, "country": q.country, "freshness": q.freshness, "after": q.after, "before": q.before, "offset": q.offset },
                 "cost_units": resp.cost_units,
                 "timings_ms": resp.timings_ms,
                 "results": resp.results,
@@ -19890,12 +26131,519 @@ Rules:
                     "auto_mode": auto_mode
                 });
             }
+            merge_search_warnings(&mut payload, &resp.warnings);
+            set_search_page(&mut payload, resp.offset, resp.has_more);
+            if let Some(cap) = max_per_domain {
+                let dropped = diversify_search_results(&mut payload, cap);
+                payload["request"]["max_per_domain"] = serde_json::json!(cap);
+                payload["diversified_count"] = serde_json::json!(dropped);
+            }
             add_envelope_fields(&mut payload, "web_search", t0.elapsed().as_millis());
 
             let md = web_search_markdown(&payload);
             Ok(tool_result_markdown_with_json(payload, md))
         }
 
+        #[tool(
+            description = "Best for: \"find similar pages\" when you already have a document. Fetches+extracts url, derives a query from its title/top keywords, runs web_search, and drops results from the source's own domain. Requires a search provider (like web_search). Output: derived_query + results[] with url/title/snippet.",
+            input_schema = Arc::new(tool_input_schema_draft07::<WebRelatedArgs>()),
+            annotations(title = "Related pages", read_only_hint = true, open_world_hint = true)
+        )]
+        async fn web_related(
+            &self,
+            params: Parameters<Option<WebRelatedArgs>>,
+        ) -> Result<CallToolResult, McpError> {
+            let args = params.0.unwrap_or_default();
+            let t0 = std::time::Instant::now();
+            self.stats_inc_tool("web_related");
+            let url = args.url.clone().unwrap_or_default().trim().to_string();
+            let max_results = args.max_results.unwrap_or(10).clamp(1, 20);
+            let provider = args.provider.clone().unwrap_or_else(|| "auto".to_string());
+            let timeout_ms = args.timeout_ms.unwrap_or(20_000).min(60_000);
+            let request = serde_json::json!({
+                "url": url,
+                "max_results": max_results,
+                "provider": provider,
+                "timeout_ms": timeout_ms
+            });
+            let fail = |error: serde_json::Value, t0: std::time::Instant| {
+                let mut payload = serde_json::json!({
+                    "ok": false,
+                    "url": url,
+                    "request": request,
+                    "error": error
+                });
+                add_envelope_fields(&mut payload, "web_related", t0.elapsed().as_millis());
+                let md = web_related_markdown(&payload);
+                Ok(tool_result_markdown_with_json(payload, md))
+            };
+
+            let source_host = match reqwest::Url::parse(&url) {
+                Ok(u) if matches!(u.scheme(), "http" | "https") => {
+                    u.host_str().unwrap_or("").to_string()
+                }
+                _ => {
+                    return fail(
+                        error_obj(
+                            ErrorCode::InvalidUrl,
+                            "url must be an absolute http(s) URL",
+                            "Pass the page to find related pages for, e.g. url=\"https://example.com/post\".",
+                        ),
+                        t0,
+                    );
+                }
+            };
+
+            let er = self
+                .web_extract(p(WebExtractArgs {
+                    url: Some(url.clone()),
+                    timeout_ms: Some(timeout_ms),
+                    include_text: Some(true),
+                    include_structure: Some(true),
+                    max_outline_items: Some(1),
+                    ..Default::default()
+                }))
+                .await?;
+            let ev = payload_from_result(&er);
+            if ev["ok"].as_bool() != Some(true) {
+                return fail(ev["error"].clone(), t0);
+            }
+            let final_url = ev["final_url"].as_str().unwrap_or(&url).to_string();
+            let title = ev["extract"]["structure"]["title"]
+                .as_str()
+                .or_else(|| ev["extract"]["structure"]["outline"][0].as_str())
+                .map(|s| s.to_string());
+            let text = ev["extract"]["text"].as_str().unwrap_or("");
+            let (query, title_terms, keywords) = related_query_from_page(title.as_deref(), text);
+            let derived = serde_json::json!({
+                "query": query,
+                "title": title,
+                "title_terms": title_terms,
+                "keywords": keywords
+            });
+            if query.is_empty() {
+                return fail(
+                    error_obj(
+                        ErrorCode::NotSupported,
+                        "could not derive a query from the page (no title or content terms)",
+                        "Use web_search with your own query, or try a page with readable text.",
+                    ),
+                    t0,
+                );
+            }
+
+            // Over-fetch a little so dropping same-domain hits still leaves max_results.
+            let sr = self
+                .web_search(p(WebSearchArgs {
+                    query: Some(query.clone()),
+                    provider: Some(provider.clone()),
+                    max_results: Some((max_results * 2).min(20)),
+                    timeout_ms: Some(timeout_ms),
+                    ..Default::default()
+                }))
+                .await?;
+            let mut payload = payload_from_result(&sr);
+            let source_domain = registrable_domain(&source_host);
+            let mut excluded = 0usize;
+            if let Some(results) = payload.get_mut("results").and_then(|v| v.as_array_mut()) {
+                let before = results.len();
+                results.retain(|r| {
+                    let host = r["url"]
+                        .as_str()
+                        .and_then(|u| reqwest::Url::parse(u).ok())
+                        .and_then(|u| u.host_str().map(registrable_domain));
+                    host.as_deref() != Some(source_domain.as_str())
+                });
+                excluded = before - results.len();
+                results.truncate(max_results);
+            }
+            payload["url"] = serde_json::json!(url);
+            payload["final_url"] = serde_json::json!(final_url);
+            payload["source_domain"] = serde_json::json!(source_domain);
+            payload["derived_query"] = derived;
+            payload["excluded_count"] = serde_json::json!(excluded);
+            payload["request"] = request;
+            add_envelope_fields(&mut payload, "web_related", t0.elapsed().as_millis());
+            let md = web_related_markdown(&payload);
+            Ok(tool_result_markdown_with_json(payload, md))
+        }
+
+        #[tool(
+            description = "Best for: searching within one site (e.g. a project's docs). Scopes web_search to `domain` (Tavily's native domain filter, else a `site:` operator) and drops any result not on that domain or its subdomains. Output: results[] (all on-domain), site_filter, filtered_count.",
+            input_schema = Arc::new(tool_input_schema_draft07::<WebSiteSearchArgs>()),
+            annotations(title = "Site search", read_only_hint = true, open_world_hint = true)
+        )]
+        async fn web_site_search(
+            &self,
+            params: Parameters<Option<WebSiteSearchArgs>>,
+        ) -> Result<CallToolResult, McpError> {
+            let args = params.0.unwrap_or_default();
+            let t0 = std::time::Instant::now();
+            self.stats_inc_tool("web_site_search");
+            let raw_domain = args.domain.clone().unwrap_or_default();
+            let query = args.query.clone().unwrap_or_default().trim().to_string();
+            let max_results = args.max_results.unwrap_or(10).clamp(1, 20);
+            let provider = args.provider.clone().unwrap_or_else(|| "auto".to_string());
+            let timeout_ms = args.timeout_ms.unwrap_or(20_000).min(60_000);
+            let domain = site_search_domain(&raw_domain);
+            let request = serde_json::json!({
+                "domain": domain.clone().unwrap_or(raw_domain),
+                "query": query,
+                "max_results": max_results,
+                "provider": provider,
+                "timeout_ms": timeout_ms
+            });
+            let fail = |error: serde_json::Value, t0: std::time::Instant| {
+                let mut payload = serde_json::json!({
+                    "ok": false,
+                    "query": query,
+                    "request": request,
+                    "error": error
+                });
+                add_envelope_fields(&mut payload, "web_site_search", t0.elapsed().as_millis());
+                let md = web_search_markdown(&payload);
+                Ok(tool_result_markdown_with_json(payload, md))
+            };
+            let Some(domain) = domain else {
+                return fail(
+                    error_obj(
+                        ErrorCode::InvalidParams,
+                        "domain must be a hostname like \"docs.rs\"",
+                        "Pass the site to search within, e.g. domain=\"docs.rs\" (a URL is fine too).",
+                    ),
+                    t0,
+                );
+            };
+            if query.is_empty() {
+                return fail(
+                    error_obj(
+                        ErrorCode::InvalidParams,
+                        "query must be non-empty",
+                        "Pass a non-empty search query.",
+                    ),
+                    t0,
+                );
+            }
+
+            // Over-fetch a little so dropping off-domain hits still leaves max_results.
+            let sr = self
+                .web_search(p(WebSearchArgs {
+                    query: Some(query.clone()),
+                    provider: Some(provider.clone()),
+                    max_results: Some((max_results * 2).min(20)),
+                    timeout_ms: Some(timeout_ms),
+                    site: Some(domain.clone()),
+                    ..Default::default()
+                }))
+                .await?;
+            let mut payload = payload_from_result(&sr);
+            let used = payload["backend_provider"]
+                .as_str()
+                .or_else(|| payload["provider"].as_str())
+                .unwrap_or("")
+                .to_string();
+            let mut filtered = 0usize;
+            if let Some(results) = payload.get_mut("results").and_then(|v| v.as_array_mut()) {
+                let before = results.len();
+                results.retain(|r| r["url"].as_str().is_some_and(|u| url_on_domain(u, &domain)));
+                filtered = before - results.len();
+                results.truncate(max_results);
+            }
+            payload["domain"] = serde_json::json!(domain);
+            payload["site_filter"] = serde_json::json!(if used == "tavily" {
+                "native"
+            } else {
+                "query_operator"
+            });
+            payload["filtered_count"] = serde_json::json!(filtered);
+            payload["request"] = request;
+            add_envelope_fields(&mut payload, "web_site_search", t0.elapsed().as_millis());
+            let md = web_search_markdown(&payload);
+            Ok(tool_result_markdown_with_json(payload, md))
+        }
+
+        #[tool(
+            description = "Best for: a short bullet summary of one page (or of text you pass). Extracts + chunks locally, then asks the configured LLM backend (llm_backend: ollama, openai_compat, perplexity, ...) for at most max_bullets bullets grounded in those chunks. Lighter than web_deep_research (no search, one LLM call). Output: summary.bullets[], chunks[] (the excerpts sent), provider.",
+            input_schema = Arc::new(tool_input_schema_draft07::<WebSummarizeArgs>()),
+            annotations(title = "Summarize page", read_only_hint = true, open_world_hint = true)
+        )]
+        async fn web_summarize(
+            &self,
+            params: Parameters<Option<WebSummarizeArgs>>,
+        ) -> Result<CallToolResult, McpError> {
+            let args = params.0.unwrap_or_default();
+            let t0 = std::time::Instant::now();
+            self.stats_inc_tool("web_summarize");
+            let url = args
+                .url
+                .as_deref()
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string);
+            let text = args.text.clone().filter(|s| !s.trim().is_empty());
+            let max_bullets = args.max_bullets.unwrap_or(5).clamp(1, 10);
+            let top_chunks = args.top_chunks.unwrap_or(8).clamp(1, 20);
+            let max_chunk_chars = args.max_chunk_chars.unwrap_or(800).clamp(50, 2_000);
+            let llm_backend = args
+                .llm_backend
+                .as_deref()
+                .map(|s| s.trim().to_ascii_lowercase())
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "auto".to_string());
+            let max_answer_chars = args.max_answer_chars.unwrap_or(2_000).clamp(1, 20_000);
+            let no_network = args.no_network.unwrap_or(false);
+            let timeout_ms = args.timeout_ms.unwrap_or(30_000).min(120_000);
+            let request = serde_json::json!({
+                "url": url,
+                "text_chars": text.as_ref().map(|t| t.chars().count()),
+                "max_bullets": max_bullets,
+                "top_chunks": top_chunks,
+                "max_chunk_chars": max_chunk_chars,
+                "llm_backend": llm_backend,
+                "max_answer_chars": max_answer_chars,
+                "no_network": no_network,
+                "timeout_ms": timeout_ms
+            });
+            let fail = |error: serde_json::Value, t0: std::time::Instant| {
+                let mut payload = serde_json::json!({
+                    "ok": false,
+                    "url": url,
+                    "request": request,
+                    "error": error
+                });
+                add_envelope_fields(&mut payload, "web_summarize", t0.elapsed().as_millis());
+                let md = web_summarize_markdown(&payload);
+                Ok(tool_result_markdown_with_json(payload, md))
+            };
+            if url.is_some() == text.is_some() {
+                return fail(
+                    error_obj(
+                        ErrorCode::InvalidParams,
+                        "pass exactly one of url or text",
+                        "Pass url=\"https://...\" to summarize a page, or text=\"...\" to summarize text you already have.",
+                    ),
+                    t0,
+                );
+            }
+
+            // Pick and vet the backend before fetching anything.
+            let selected: &str = match llm_backend.as_str() {
+                "auto" => deep_research_auto_llm_backend(no_network),
+                name if self.llm.get(name).is_some() => name,
+                other => {
+                    return fail(
+                        error_obj(
+                            ErrorCode::InvalidParams,
+                            format!("unknown llm_backend: {other}"),
+                            format!(
+                                "Allowed llm_backend values: auto, {}",
+                                self.llm.names().join(", ")
+                            ),
+                        ),
+                        t0,
+                    );
+                }
+            };
+            let Some(backend) = self.llm.get(selected) else {
+                let (msg, hint) = if no_network {
+                    (
+                        "no local LLM backend configured (no_network=true)",
+                        "Set WEBPIPE_OPENAI_COMPAT_BASE_URL to a localhost server (plus WEBPIPE_OPENAI_COMPAT_MODEL), or WEBPIPE_OLLAMA_ENABLE=true.",
+                    )
+                } else {
+                    (
+                        "no LLM backend configured for summarization",
+                        "Configure WEBPIPE_PERPLEXITY_API_KEY, or WEBPIPE_ANTHROPIC_API_KEY + WEBPIPE_ANTHROPIC_MODEL, or WEBPIPE_OPENAI_COMPAT_BASE_URL, or enable Ollama via WEBPIPE_OLLAMA_ENABLE=true.",
+                    )
+                };
+                return fail(error_obj(ErrorCode::NotConfigured, msg, hint), t0);
+            };
+            if no_network && backend.network_only() {
+                return fail(
+                    error_obj(
+                        ErrorCode::NotSupported,
+                        format!("llm_backend={selected} cannot be used with no_network=true"),
+                        "Use llm_backend=\"ollama\" or \"openai_compat\" with a localhost endpoint, or set no_network=false.",
+                    ),
+                    t0,
+                );
+            }
+            let opts = webpipe_local::llm::CompletionOpts {
+                timeout_ms,
+                model: if selected == "perplexity" {
+                    Some(
+                        args.llm_model
+                            .clone()
+                            .unwrap_or_else(|| "sonar".to_string()),
+                    )
+                } else {
+                    args.llm_model.clone()
+                },
+                ..Default::default()
+            };
+            if let Err(e) = backend.check_configured(&opts) {
+                self.stats_record_llm_backend(selected, false, 0, Some(&e.to_string()));
+                return fail(
+                    error_obj(
+                        ErrorCode::NotConfigured,
+                        e.to_string(),
+                        format!("Configure llm_backend={selected}, or pick another llm_backend."),
+                    ),
+                    t0,
+                );
+            }
+            if no_network {
+                if let Some(ep) = backend
+                    .endpoint(&opts)
+                    .filter(|ep| !llm_endpoint_is_localhost(ep))
+                {
+                    return fail(
+                        error_obj(
+                            ErrorCode::NotSupported,
+                            format!("no_network=true requires llm_backend={selected} to be localhost (got {ep})"),
+                            "Point the backend at a localhost endpoint (or set no_network=false).",
+                        ),
+                        t0,
+                    );
+                }
+            }
+
+            // Chunks: the page's default (query-less) selection, or the same over plain text.
+            let (chunks, final_url): (Vec<serde_json::Value>, Option<String>) = match (&url, &text)
+            {
+                (Some(u), _) => {
+                    let er = self
+                        .web_extract(p(WebExtractArgs {
+                            url: Some(u.clone()),
+                            no_network: Some(no_network),
+                            timeout_ms: Some(timeout_ms),
+                            top_chunks: Some(top_chunks),
+                            max_chunk_chars: Some(max_chunk_chars),
+                            include_text: Some(false),
+                            ..Default::default()
+                        }))
+                        .await?;
+                    let ev = payload_from_result(&er);
+                    if ev["ok"].as_bool() != Some(true) {
+                        return fail(ev["error"].clone(), t0);
+                    }
+                    (
+                        ev["extract"]["chunks"]
+                            .as_array()
+                            .cloned()
+                            .unwrap_or_default(),
+                        ev["final_url"].as_str().map(|s| s.to_string()),
+                    )
+                }
+                (None, Some(t)) => {
+                    let pipeline = webpipe_local::extract::extract_pipeline_from_bytes(
+                        t.as_bytes(),
+                        Some("text/plain"),
+                        "",
+                        webpipe_local::extract::ExtractPipelineCfg {
+                            query: None,
+                            width: 100,
+                            max_chars: 200_000,
+                            top_chunks,
+                            max_chunk_chars,
+                            include_structure: false,
+                            max_outline_items: 0,
+                            max_blocks: 0,
+                            max_block_chars: 0,
+                            truncation_strategy: webpipe_local::extract::TruncationStrategy::Head,
+                        },
+                    );
+                    (
+                        pipeline
+                            .chunks
+                            .iter()
+                            .filter_map(|c| serde_json::to_value(c).ok())
+                            .collect(),
+                        None,
+                    )
+                }
+                (None, None) => unreachable!("validated above"),
+            };
+            let chunks: Vec<serde_json::Value> = chunks
+                .into_iter()
+                .filter(|c| c["text"].as_str().is_some_and(|s| !s.trim().is_empty()))
+                .take(top_chunks)
+                .collect();
+            let chunk_texts: Vec<String> = chunks
+                .iter()
+                .filter_map(|c| c["text"].as_str().map(|s| s.to_string()))
+                .collect();
+            if chunk_texts.is_empty() {
+                return fail(
+                    error_obj(
+                        ErrorCode::NotSupported,
+                        "no extractable text to summarize",
+                        "Try web_extract with include_text=true to inspect the page, or pass text directly.",
+                    ),
+                    t0,
+                );
+            }
+
+            let source = final_url
+                .as_deref()
+                .or(url.as_deref())
+                .unwrap_or("(caller-provided text)");
+            let prompt = summarize_prompt(source, &chunk_texts, max_bullets);
+            let completion = match self.llm_complete(selected, &prompt, &opts).await {
+                Ok(c) => c,
+                Err(e) => {
+                    return fail(
+                        error_obj(
+                            ErrorCode::ProviderUnavailable,
+                            e.to_string(),
+                            "Summarization failed. Check the backend is reachable and the model exists, or pick a different llm_backend.",
+                        ),
+                        t0,
+                    );
+                }
+            };
+            let (bullets, truncated) =
+                summary_bullets(&completion.text, max_bullets, max_answer_chars);
+            if bullets.is_empty() {
+                return fail(
+                    error_obj(
+                        ErrorCode::ProviderUnavailable,
+                        "LLM returned an empty summary",
+                        "Retry, or pick a different llm_backend / llm_model.",
+                    ),
+                    t0,
+                );
+            }
+            let used: Vec<serde_json::Value> = chunks
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    serde_json::json!({
+                        "index": i + 1,
+                        "text": c["text"],
+                        "start_char": c["start_char"],
+                        "end_char": c["end_char"]
+                    })
+                })
+                .collect();
+            let mut payload = serde_json::json!({
+                "ok": true,
+                "url": url,
+                "final_url": final_url,
+                "provider": selected,
+                "model": opts.model,
+                "summary": { "bullets": bullets, "truncated": truncated },
+                "chunks": used,
+                "request": request
+            });
+            if let Some(u) = completion.usage {
+                payload["usage"] = u;
+            }
+            add_envelope_fields(&mut payload, "web_summarize", t0.elapsed().as_millis());
+            let md = web_summarize_markdown(&payload);
+            Ok(tool_result_markdown_with_json(payload, md))
+        }
+
         #[tool(
             description = "Best for: fast single-turn Q&A with live web citations via Perplexity Sonar. Not this for multi-source evidence with per-URL control — use search_evidence instead. Only visible when WEBPIPE_PERPLEXITY_API_KEY is configured. Output: answer text + citations[].",
             input_schema = Arc::new(tool_input_schema_draft07::<WebPerplexityArgs>()),
@@ -20056,11 +26804,45 @@ Rules:
             let max_chunk_chars = args.max_chunk_chars.unwrap_or(500).min(5_000);
             let include_links = args.include_links.unwrap_or(false);
             let max_links = args.max_links.unwrap_or(50).min(500);
+            let include_alternates = args.include_alternates.unwrap_or(false);
+            let prefer_amp = args.prefer_amp.unwrap_or(false);
+            let include_entities = args.include_entities.unwrap_or(false);
+            let include_tables = args.include_tables.unwrap_or(false);
+            let include_metadata = args.include_metadata.unwrap_or(false);
+            let table_format = match args
+                .table_format
+                .as_deref()
+                .map(|s| s.trim().to_ascii_lowercase())
+                .as_deref()
+            {
+                Some("markdown" | "md") => "markdown",
+                Some("csv") => "csv",
+                _ => "json",
+            };
+            let include_dates = args.include_dates.unwrap_or(false);
+            let date_order =
+                webpipe_local::textprep::DateOrder::parse(args.date_order.as_deref().unwrap_or(""));
+            let include_keywords = args.include_keywords.unwrap_or(false);
+            let max_keywords = args
+                .max_keywords
+                .unwrap_or(10)
+                .clamp(1, webpipe_local::keywords::MAX_KEYPHRASES);
+            let chunk_dom_paths = args.chunk_dom_paths.unwrap_or(false);
+            let sentences = args.sentences.unwrap_or(false);
+            let include_noscript = args.include_noscript.unwrap_or(false);
+            let follow_iframes = args.follow_iframes.unwrap_or(false);
+            let allow_cross_origin_iframes = args.allow_cross_origin_iframes.unwrap_or(false);
+            let max_iframes = args.max_iframes.unwrap_or(3).min(10);
+            let include_error_body = args.include_error_body.unwrap_or(false);
+            let include_raw_html = args.include_raw_html.unwrap_or(false);
             // Default behavior: return full extracted text when no query is provided (users asked for “extract”),
             // but keep it off when query is provided (callers usually want bounded chunks).
             let include_text = args.include_text.unwrap_or(args.query.is_none());
             // Default to structure output for higher-quality chunk selection and better debugging.
             let include_structure = args.include_structure.unwrap_or(true);
+            let sectioned = args.sectioned.unwrap_or(false);
+            // Sections are derived from the structure, so compute it even when it is not returned.
+            let structure_wanted = include_structure || sectioned;
             let max_outline_items = args.max_outline_items.unwrap_or(25).min(200);
             let max_blocks = args.max_blocks.unwrap_or(40).min(200);
             let max_block_chars = args.max_block_chars.unwrap_or(400).min(2000);
@@ -20104,7 +26886,20 @@ Rules:
                         "include_text": include_text,
                         "include_links": include_links,
                         "max_links": max_links,
+                        "include_alternates": include_alternates,
+                        "include_entities": include_entities,
+                        "include_tables": include_tables,
+                        "include_metadata": include_metadata,
+                        "table_format": table_format,
+                        "include_dates": include_dates,
+                        "date_order": date_order.as_str(),
+                        "sentences": sentences,
+                        "include_noscript": include_noscript,
+                        "follow_iframes": follow_iframes,
+                        "allow_cross_origin_iframes": allow_cross_origin_iframes,
+                        "max_iframes": max_iframes,
                         "include_structure": include_structure,
+                        "sectioned": sectioned,
                         "max_outline_items": max_outline_items,
                         "max_blocks": max_blocks,
                         "max_block_chars": max_block_chars
@@ -20132,6 +26927,69 @@ Rules:
                 let md = web_extract_markdown(&payload);
                 return Ok(tool_result_markdown_with_json(payload, md));
             }
+            let truncation_strategy = match args.truncation_strategy.as_deref() {
+                None => webpipe_local::extract::TruncationStrategy::Head,
+                Some(raw) => match webpipe_local::extract::TruncationStrategy::parse(raw) {
+                    Some(t) => t,
+                    None => {
+                        let mut payload = serde_json::json!({
+                            "ok": false,
+                            "url": url,
+                            "error": error_obj(
+                                ErrorCode::InvalidParams,
+                                "unknown truncation_strategy",
+                                "Allowed truncation_strategy values: head, head_tail, middle_out"
+                            ),
+                            "request": { "fetch_backend": fetch_backend, "truncation_strategy": raw }
+                        });
+                        add_envelope_fields(&mut payload, "web_extract", t0.elapsed().as_millis());
+                        let md = web_extract_markdown(&payload);
+                        return Ok(tool_result_markdown_with_json(payload, md));
+                    }
+                },
+            };
+            let html_engine = match args.engine.as_deref() {
+                None => webpipe_local::extract::HtmlEngine::Auto,
+                Some(raw) => match webpipe_local::extract::HtmlEngine::parse(raw) {
+                    Some(e) => e,
+                    None => {
+                        let mut payload = serde_json::json!({
+                            "ok": false,
+                            "url": url,
+                            "error": error_obj(
+                                ErrorCode::InvalidParams,
+                                "unknown engine",
+                                "Allowed engine values: auto, readability, html_main, html2text"
+                            ),
+                            "request": { "fetch_backend": fetch_backend, "engine": raw }
+                        });
+                        add_envelope_fields(&mut payload, "web_extract", t0.elapsed().as_millis());
+                        let md = web_extract_markdown(&payload);
+                        return Ok(tool_result_markdown_with_json(payload, md));
+                    }
+                },
+            };
+            let referer = match args.referer.as_deref().filter(|s| !s.trim().is_empty()) {
+                None => None,
+                Some(raw) => match parse_referer(raw) {
+                    Some(r) => Some(r),
+                    None => {
+                        let mut payload = serde_json::json!({
+                            "ok": false,
+                            "url": url,
+                            "error": error_obj(
+                                ErrorCode::InvalidParams,
+                                "referer must be an absolute http(s) URL",
+                                "Pass the page the link was found on, e.g. https://example.com/index.html."
+                            ),
+                            "request": { "fetch_backend": fetch_backend, "referer": raw }
+                        });
+                        add_envelope_fields(&mut payload, "web_extract", t0.elapsed().as_millis());
+                        let md = web_extract_markdown(&payload);
+                        return Ok(tool_result_markdown_with_json(payload, md));
+                    }
+                },
+            };
 
             if no_network && fetch_backend == "firecrawl" {
                 let mut payload = serde_json::json!({
@@ -20188,7 +27046,20 @@ Rules:
                             "include_text": include_text,
                             "include_links": include_links,
                             "max_links": max_links,
+                            "include_alternates": include_alternates,
+                            "include_entities": include_entities,
+                            "include_tables": include_tables,
+                            "include_metadata": include_metadata,
+                            "table_format": table_format,
+                            "include_dates": include_dates,
+                            "date_order": date_order.as_str(),
+                            "sentences": sentences,
+                            "include_noscript": include_noscript,
+                            "follow_iframes": follow_iframes,
+                            "allow_cross_origin_iframes": allow_cross_origin_iframes,
+                            "max_iframes": max_iframes,
                             "include_structure": include_structure,
+                            "sectioned": sectioned,
                             "max_outline_items": max_outline_items,
                             "max_blocks": max_blocks,
                             "max_block_chars": max_block_chars
@@ -20277,10 +27148,11 @@ Rules:
                         max_chars,
                         top_chunks,
                         max_chunk_chars,
-                        include_structure,
+                        include_structure: structure_wanted,
                         max_outline_items,
                         max_blocks,
                         max_block_chars,
+                        truncation_strategy,
                     },
                 );
                 let extracted = pipeline.extracted;
@@ -20296,6 +27168,7 @@ Rules:
                     "ok": true,
                     "fetch_backend": "firecrawl",
                     "url": url,
+                    "doc_id": webpipe_local::doc_id::doc_id(&url),
                     "final_url": url,
                     "status": 200,
                     "content_type": "text/markdown",
@@ -20317,7 +27190,22 @@ Rules:
                     "include_text": include_text,
                     "include_links": include_links,
                     "max_links": max_links,
+                    "include_alternates": include_alternates,
+                    "include_entities": include_entities,
+                    "include_tables": include_tables,
+                    "include_metadata": include_metadata,
+                    "table_format": table_format,
+                    "include_dates": include_dates,
+                    "date_order": date_order.as_str(),
+                    "include_keywords": include_keywords,
+                    "max_keywords": max_keywords,
+                    "sentences": sentences,
+                    "include_noscript": include_noscript,
+                    "follow_iframes": follow_iframes,
+                    "allow_cross_origin_iframes": allow_cross_origin_iframes,
+                    "max_iframes": max_iframes,
                     "include_structure": include_structure,
+                    "sectioned": sectioned,
                     "max_outline_items": max_outline_items,
                     "max_blocks": max_blocks,
                     "max_block_chars": max_block_chars
@@ -20354,6 +27242,13 @@ Rules:
                         payload["extract"]["structure"] = serde_json::json!(s);
                     }
                 }
+                if sectioned {
+                    payload["extract"]["sections"] = serde_json::json!(pipeline
+                        .structure
+                        .as_ref()
+                        .map(webpipe_local::extract::structure_sections)
+                        .unwrap_or_default());
+                }
                 if let Some(q) = args.query.as_deref() {
                     if !q.trim().is_empty() {
                         payload["extract"]["chunks"] = serde_json::json!(pipeline.chunks);
@@ -20371,62 +27266,17 @@ Rules:
                         .iter()
                         .map(|c| (c.start_char, c.end_char, c.text.clone()))
                         .collect();
-                    let q0 = args.query.clone().unwrap_or_default();
-                    let semantic_timeout_ms = std::env::var("WEBPIPE_SEMANTIC_TIMEOUT_MS")
-                        .ok()
-                        .and_then(|s| s.trim().parse::<u64>().ok())
-                        .unwrap_or(
+                    let sem = self
+                        .semantic_rerank_or_fallback(
+                            args.query.as_deref().unwrap_or_default(),
+                            &cands,
+                            semantic_top_k,
                             args.timeout_ms
                                 .unwrap_or(20_000)
                                 .saturating_div(2)
                                 .clamp(2_000, 8_000),
-                        );
-                    let sem = if semantic_timeout_ms == 0 {
-                        webpipe_local::semantic::SemanticRerankResult {
-                            ok: false,
-                            backend: "unknown".to_string(),
-                            model_id: None,
-                            cache_hits: 0,
-                            cache_misses: 0,
-                            chunks: Vec::new(),
-                            warnings: vec!["semantic_rerank_timeout"],
-                        }
-                    } else {
-                        let handle = tokio::task::spawn_blocking(move || {
-                            webpipe_local::semantic::semantic_rerank_chunks(
-                                &q0,
-                                &cands,
-                                semantic_top_k,
-                            )
-                        });
-                        match tokio::time::timeout(
-                            std::time::Duration::from_millis(semantic_timeout_ms),
-                            handle,
                         )
-                        .await
-                        {
-                            Ok(join) => join.unwrap_or_else(|_| {
-                                webpipe_local::semantic::SemanticRerankResult {
-                                    ok: false,
-                                    backend: "unknown".to_string(),
-                                    model_id: None,
-                                    cache_hits: 0,
-                                    cache_misses: 0,
-                                    chunks: Vec::new(),
-                                    warnings: vec!["semantic_rerank_task_failed"],
-                                }
-                            }),
-                            Err(_) => webpipe_local::semantic::SemanticRerankResult {
-                                ok: false,
-                                backend: "unknown".to_string(),
-                                model_id: None,
-                                cache_hits: 0,
-                                cache_misses: 0,
-                                chunks: Vec::new(),
-                                warnings: vec!["semantic_rerank_timeout"],
-                            },
-                        }
-                    };
+                        .await;
                     for w in &sem.warnings {
                         warnings.push(*w);
                     }
@@ -20437,6 +27287,40 @@ Rules:
                     payload["extract"]["max_links"] = serde_json::json!(max_links);
                     warnings.push("links_unavailable_for_firecrawl");
                 }
+                if sentences {
+                    add_chunk_sentences(&mut payload["extract"]);
+                }
+                if include_alternates {
+                    // Firecrawl returns markdown only; the <head> link tags are gone.
+                    payload["extract"]["alternates"] =
+                        serde_json::json!(webpipe_local::links::PageAlternates::default());
+                }
+                if include_entities {
+                    // Same for JSON-LD/microdata/RDFa markup.
+                    payload["extract"]["entities"] = serde_json::json!([]);
+                }
+                if include_tables {
+                    payload["extract"]["tables"] = serde_json::json!([]);
+                }
+                if include_metadata {
+                    payload["extract"]["metadata"] = serde_json::json!({});
+                }
+                if include_dates {
+                    payload["extract"]["dates"] = serde_json::json!(
+                        webpipe_local::textprep::extract_dates_with(&text, date_order)
+                    );
+                }
+                if include_keywords {
+                    let (idx, cached) = self
+                        .keyword_index_cached(
+                            &webpipe_local::doc_id::doc_id(&url),
+                            &text,
+                            max_keywords,
+                        )
+                        .await;
+                    payload["extract"]["keywords"] = serde_json::json!(idx);
+                    payload["extract"]["keywords"]["cached"] = serde_json::json!(cached);
+                }
                 if !warnings.is_empty() {
                     payload["warnings"] = serde_json::json!(warnings);
                     let codes = warning_codes_from(&warnings);
@@ -20508,7 +27392,9 @@ Rules:
                 url: fetch_url.clone(),
                 timeout_ms: args.timeout_ms.or(Some(20_000)),
                 max_bytes: args.max_bytes.or(Some(5_000_000)),
-                headers: BTreeMap::new(),
+                headers: referer_headers(referer.as_deref()),
+                method: None,
+                body: None,
                 cache: FetchCachePolicy {
                     read: args.cache_read.unwrap_or(true) || no_network,
                     write: if no_network {
@@ -20517,6 +27403,8 @@ Rules:
                         args.cache_write.unwrap_or(true)
                     },
                     ttl_s: args.cache_ttl_s,
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
                 },
             };
 
@@ -20647,9 +27535,13 @@ Rules:
                     status: pr.status.unwrap_or(200),
                     content_type: Some("text/html".to_string()),
                     headers: BTreeMap::new(),
+                    wire_bytes: pr.html.len() as u64,
                     bytes: pr.html.into_bytes(),
                     truncated: false,
                     source: webpipe_core::FetchSource::Network,
+                    cache_status: None,
+                    body_path: None,
+                    revalidating: false,
                     timings_ms: {
                         let mut m = BTreeMap::new();
                         m.insert("playwright_render".to_string(), pr.elapsed_ms as u128);
@@ -20772,7 +27664,12 @@ Rules:
                         || Self::url_looks_like_pdf(r.final_url.as_str())
                         || webpipe_local::extract::bytes_look_like_pdf(&r.bytes)
                 });
-                if retry_on_truncation && r.truncated && !r.bytes.is_empty() {
+                // A bigger max_bytes can't finish an open-ended stream.
+                if retry_on_truncation
+                    && r.truncated
+                    && !r.bytes.is_empty()
+                    && !r.timings_ms.contains_key("streaming_capped")
+                {
                     let from = req.max_bytes.unwrap_or(0);
                     let to = (from.saturating_mul(5))
                         .max(1_000_000)
@@ -20831,8 +27728,12 @@ Rules:
                 content_type: resp_content_type,
                 headers: _resp_headers,
                 bytes: resp_bytes0,
+                wire_bytes: _resp_wire_bytes,
                 truncated: resp_body_truncated,
                 source: _resp_source,
+                cache_status: _resp_cache_status,
+                body_path: _resp_body_path,
+                revalidating: _resp_revalidating,
                 timings_ms: resp_timings_ms,
             } = resp;
             let resp_bytes = std::sync::Arc::new(resp_bytes0);
@@ -20884,6 +27785,18 @@ Rules:
                             "include_text": include_text,
                             "include_links": include_links,
                             "max_links": max_links,
+                            "include_alternates": include_alternates,
+                            "include_entities": include_entities,
+                            "include_tables": include_tables,
+                            "include_metadata": include_metadata,
+                            "table_format": table_format,
+                            "include_dates": include_dates,
+                            "date_order": date_order.as_str(),
+                            "sentences": sentences,
+                            "include_noscript": include_noscript,
+                            "follow_iframes": follow_iframes,
+                            "allow_cross_origin_iframes": allow_cross_origin_iframes,
+                            "max_iframes": max_iframes,
                             "include_structure": include_structure
                         },
                         "warnings": ["extract_pipeline_timeout"],
@@ -20895,13 +27808,15 @@ Rules:
                     return Ok(tool_result_markdown_with_json(payload, md));
                 }
                 let handle = tokio::task::spawn_blocking(move || {
-                    let extracted0 = webpipe_local::extract::best_effort_text_from_bytes(
-                        &bytes,
-                        ct.as_deref(),
-                        final_url.as_str(),
-                        width,
-                        500,
-                    );
+                    let extracted0 =
+                        webpipe_local::extract::best_effort_text_from_bytes_with_engine(
+                            &bytes,
+                            ct.as_deref(),
+                            final_url.as_str(),
+                            width,
+                            500,
+                            html_engine,
+                        );
                     webpipe_local::extract::extract_pipeline_from_extracted(
                         &bytes,
                         ct.as_deref(),
@@ -20913,10 +27828,11 @@ Rules:
                             max_chars,
                             top_chunks,
                             max_chunk_chars,
-                            include_structure,
+                            include_structure: structure_wanted,
                             max_outline_items,
                             max_blocks,
                             max_block_chars,
+                            truncation_strategy,
                         },
                     )
                 });
@@ -20954,6 +27870,18 @@ Rules:
                                 "include_text": include_text,
                                 "include_links": include_links,
                                 "max_links": max_links,
+                                "include_alternates": include_alternates,
+                                "include_entities": include_entities,
+                                "include_tables": include_tables,
+                                "include_metadata": include_metadata,
+                                "table_format": table_format,
+                                "include_dates": include_dates,
+                                "date_order": date_order.as_str(),
+                                "sentences": sentences,
+                                "include_noscript": include_noscript,
+                                "follow_iframes": follow_iframes,
+                                "allow_cross_origin_iframes": allow_cross_origin_iframes,
+                                "max_iframes": max_iframes,
                                 "include_structure": include_structure
                             },
                             "warnings": ["extract_pipeline_timeout"],
@@ -21056,10 +27984,14 @@ Rules:
                             timeout_ms: req.timeout_ms,
                             max_bytes: req.max_bytes,
                             headers: BTreeMap::new(),
+                            method: None,
+                            body: None,
                             cache: FetchCachePolicy {
                                 read: req.cache.read,
                                 write: req.cache.write,
                                 ttl_s: req.cache.ttl_s,
+                                read_non_idempotent: false,
+                                stale_while_revalidate_s: None,
                             },
                         };
                         match self.fetcher.fetch(&fb_req).await {
@@ -21071,8 +28003,12 @@ Rules:
                                     content_type: fb_content_type,
                                     headers: _fb_headers,
                                     bytes: fb_bytes0,
+                                    wire_bytes: _fb_wire_bytes,
                                     truncated: fb_body_truncated,
                                     source: _fb_source,
+                                    cache_status: _fb_cache_status,
+                                    body_path: _fb_body_path,
+                                    revalidating: _fb_revalidating,
                                     timings_ms: fb_timings_ms,
                                 } = resp2;
 
@@ -21099,12 +28035,13 @@ Rules:
                                 } else {
                                     let handle = tokio::task::spawn_blocking(move || {
                                         let extracted0 =
-                                            webpipe_local::extract::best_effort_text_from_bytes(
+                                            webpipe_local::extract::best_effort_text_from_bytes_with_engine(
                                                 &bytes2,
                                                 ct2.as_deref(),
                                                 final_url2.as_str(),
                                                 width,
                                                 500,
+                                                html_engine,
                                             );
                                         webpipe_local::extract::extract_pipeline_from_extracted(
                                             &bytes2,
@@ -21117,10 +28054,11 @@ Rules:
                                                 max_chars,
                                                 top_chunks,
                                                 max_chunk_chars,
-                                                include_structure,
+                                                include_structure: structure_wanted,
                                                 max_outline_items,
                                                 max_blocks,
                                                 max_block_chars,
+                                                truncation_strategy,
                                             },
                                         )
                                     });
@@ -21219,6 +28157,135 @@ Rules:
                 }
             }
 
+            // Opt-in AMP substitution: JS-shell pages often declare a static `rel=amphtml` twin.
+            // One bounded fetch of the declared URL (the AMP page's own alternates are never
+            // followed), kept only when it extracts with higher confidence.
+            let mut amp_alternate_used = false;
+            if prefer_amp && fetch_backend == "local" && !no_network {
+                let html_like0 = webpipe_local::extract::bytes_look_like_html(resp_bytes.as_ref())
+                    || resp_content_type.as_deref().is_some_and(|ct| {
+                        let ct = ct.split(';').next().unwrap_or("").trim();
+                        ct.eq_ignore_ascii_case("text/html")
+                            || ct.eq_ignore_ascii_case("application/xhtml+xml")
+                    });
+                let confidence0 = webpipe_local::extract::extraction_confidence(
+                    &pipeline.extracted,
+                    pipeline.structure.as_ref(),
+                );
+                let amp_url = if html_like0
+                    && confidence0 < webpipe_local::extract::EXTRACTION_CONFIDENCE_LOW
+                {
+                    webpipe_local::links::extract_alternates(
+                        &String::from_utf8_lossy(resp_bytes.as_ref()),
+                        Some(resp_final_url.as_str()),
+                    )
+                    .amp
+                    .filter(|u| u != &resp_final_url && u != &resp_url)
+                } else {
+                    None
+                };
+                if let Some(amp_url) = amp_url {
+                    let t_amp0 = std::time::Instant::now();
+                    let amp_req = FetchRequest {
+                        url: amp_url.clone(),
+                        timeout_ms: req.timeout_ms,
+                        max_bytes: req.max_bytes,
+                        headers: BTreeMap::new(),
+                        method: None,
+                        body: None,
+                        cache: req.cache.clone(),
+                    };
+                    let attempt = match self.fetcher.fetch(&amp_req).await {
+                        Ok(amp_resp) => {
+                            let amp_bytes = std::sync::Arc::new(amp_resp.bytes);
+                            let bytes2 = amp_bytes.clone();
+                            let ct2 = amp_resp.content_type.clone();
+                            let final_url2 = amp_resp.final_url.clone();
+                            let query2 = args.query.clone();
+                            let amp_pipeline = tokio::task::spawn_blocking(move || {
+                                let extracted0 =
+                                    webpipe_local::extract::best_effort_text_from_bytes_with_engine(
+                                        &bytes2,
+                                        ct2.as_deref(),
+                                        final_url2.as_str(),
+                                        width,
+                                        500,
+                                        html_engine,
+                                    );
+                                webpipe_local::extract::extract_pipeline_from_extracted(
+                                    &bytes2,
+                                    ct2.as_deref(),
+                                    final_url2.as_str(),
+                                    extracted0,
+                                    webpipe_local::extract::ExtractPipelineCfg {
+                                        query: query2.as_deref(),
+                                        width,
+                                        max_chars,
+                                        top_chunks,
+                                        max_chunk_chars,
+                                        include_structure: structure_wanted,
+                                        max_outline_items,
+                                        max_blocks,
+                                        max_block_chars,
+                                        truncation_strategy,
+                                    },
+                                )
+                            })
+                            .await
+                            .ok();
+                            let amp_confidence = amp_pipeline
+                                .as_ref()
+                                .map(|p| {
+                                    webpipe_local::extract::extraction_confidence(
+                                        &p.extracted,
+                                        p.structure.as_ref(),
+                                    )
+                                })
+                                .unwrap_or(0.0);
+                            let mut attempt = serde_json::json!({
+                                "kind": "amp_alternate",
+                                "from": resp_final_url,
+                                "to": amp_resp.final_url,
+                                "status": amp_resp.status,
+                                "confidence_before": webpipe_local::results::ExtractResult::rounded_confidence(confidence0),
+                                "confidence_after": webpipe_local::results::ExtractResult::rounded_confidence(amp_confidence),
+                                "elapsed_ms": t_amp0.elapsed().as_millis(),
+                            });
+                            match amp_pipeline {
+                                Some(p)
+                                    if amp_resp.status < 400 && amp_confidence > confidence0 =>
+                                {
+                                    attempt["ok"] = serde_json::json!(true);
+                                    amp_alternate_used = true;
+                                    resp_url = amp_resp.url;
+                                    resp_final_url = amp_resp.final_url;
+                                    resp_status = amp_resp.status;
+                                    resp_content_type = amp_resp.content_type;
+                                    resp_body_truncated = amp_resp.truncated;
+                                    resp_timings_ms = amp_resp.timings_ms;
+                                    resp_bytes = amp_bytes;
+                                    pipeline = p;
+                                }
+                                _ => {
+                                    attempt["ok"] = serde_json::json!(false);
+                                    attempt["error"] = serde_json::json!("fallback_not_better");
+                                }
+                            }
+                            attempt
+                        }
+                        Err(e) => serde_json::json!({
+                            "kind": "amp_alternate",
+                            "ok": false,
+                            "from": resp_final_url,
+                            "to": amp_url,
+                            "elapsed_ms": t_amp0.elapsed().as_millis(),
+                            "error": e.to_string()
+                        }),
+                    };
+                    attempts_map.insert("amp_alternate".to_string(), attempt);
+                }
+            }
+
             // Opportunistic multimodal: if we fetched an image and local extraction produced no text,
             // optionally call Gemini Flash (feature-gated, opt-in via WEBPIPE_VISION).
             #[cfg(feature = "vision-gemini")]
@@ -21271,10 +28338,11 @@ Rules:
                                         max_chars,
                                         top_chunks,
                                         max_chunk_chars,
-                                        include_structure,
+                                        include_structure: structure_wanted,
                                         max_outline_items,
                                         max_blocks,
                                         max_block_chars,
+                                        truncation_strategy,
                                     },
                                 )
                             })
@@ -21298,6 +28366,119 @@ Rules:
                 }
             }
 
+            // Optional: recover JS-shell pages whose real content sits in <noscript> fallbacks.
+            // Only when the main body is low-signal, and never duplicating visible text.
+            if include_noscript
+                && (pipeline.extracted.engine.starts_with("html_")
+                    || pipeline.extracted.engine == "html2text"
+                    || pipeline.extracted.engine == "unknown")
+                && (pipeline.text_chars <= 200
+                    || webpipe_local::extract::looks_like_bundle_gunk(&pipeline.extracted.text))
+            {
+                let bytes = resp_bytes.clone();
+                let ct = resp_content_type.clone();
+                let final_url = resp_final_url.clone();
+                let query = args.query.clone();
+                let base = pipeline.extracted.clone();
+                let merged = tokio::task::spawn_blocking(move || {
+                    let html = String::from_utf8_lossy(bytes.as_ref());
+                    let ns = webpipe_local::extract::html_noscript_text(&html, width);
+                    let text = webpipe_local::extract::merge_noscript_text(&base.text, &ns)?;
+                    let mut warnings = base.warnings;
+                    warnings.push("noscript_merged");
+                    let ex = webpipe_local::extract::ExtractedText {
+                        engine: "html_noscript",
+                        text,
+                        warnings,
+                    };
+                    Some(webpipe_local::extract::extract_pipeline_from_extracted(
+                        &bytes,
+                        ct.as_deref(),
+                        final_url.as_str(),
+                        ex,
+                        webpipe_local::extract::ExtractPipelineCfg {
+                            query: query.as_deref(),
+                            width,
+                            max_chars,
+                            top_chunks,
+                            max_chunk_chars,
+                            include_structure: structure_wanted,
+                            max_outline_items,
+                            max_blocks,
+                            max_block_chars,
+                            truncation_strategy,
+                        },
+                    ))
+                })
+                .await
+                .ok()
+                .flatten();
+                if let Some(p2) = merged {
+                    pipeline = p2;
+                }
+            }
+
+            // Optional: inline iframe documents (docs sites often render the real page in one).
+            let mut iframes_report: Option<Vec<serde_json::Value>> = None;
+            if follow_iframes
+                && (pipeline.extracted.engine.starts_with("html")
+                    || pipeline.extracted.engine == "unknown")
+            {
+                let html = String::from_utf8_lossy(resp_bytes.as_ref()).to_string();
+                let srcs = webpipe_local::links::extract_iframe_sources(
+                    &html,
+                    Some(resp_final_url.as_str()),
+                );
+                let (report, frames) = self
+                    .fetch_iframe_texts(
+                        resp_final_url.as_str(),
+                        &srcs,
+                        allow_cross_origin_iframes,
+                        max_iframes,
+                        &req,
+                        width,
+                    )
+                    .await;
+                if !frames.is_empty() {
+                    let mut text = pipeline.extracted.text.trim_end().to_string();
+                    for (src, t) in &frames {
+                        text.push_str(&format!("\n\n[iframe: {src}]\n{}", t.trim()));
+                    }
+                    let mut ex = pipeline.extracted.clone();
+                    ex.text = text;
+                    ex.warnings.push("iframes_merged");
+                    let bytes = resp_bytes.clone();
+                    let ct = resp_content_type.clone();
+                    let final_url = resp_final_url.clone();
+                    let query = args.query.clone();
+                    if let Ok(p2) = tokio::task::spawn_blocking(move || {
+                        webpipe_local::extract::extract_pipeline_from_extracted(
+                            &bytes,
+                            ct.as_deref(),
+                            final_url.as_str(),
+                            ex,
+                            webpipe_local::extract::ExtractPipelineCfg {
+                                query: query.as_deref(),
+                                width,
+                                max_chars,
+                                top_chunks,
+                                max_chunk_chars,
+                                include_structure: structure_wanted,
+                                max_outline_items,
+                                max_blocks,
+                                max_block_chars,
+                                truncation_strategy,
+                            },
+                        )
+                    })
+                    .await
+                    {
+                        pipeline = p2;
+                    }
+                }
+                iframes_report = Some(report);
+            }
+
             let extracted = pipeline.extracted;
             let text = extracted.text.clone();
             let n = pipeline.text_chars;
@@ -21309,7 +28490,10 @@ Rules:
                 || extracted.engine.starts_with("pdf-");
             let mut warnings: Vec<&'static str> = Vec::new();
             if resp_body_truncated {
-                warnings.push("body_truncated_by_max_bytes");
+                warnings.push(truncation_warning(&resp_timings_ms));
+            }
+            if resp_timings_ms.contains_key("decode_failed") {
+                warnings.push("decode_failed");
             }
             if resp_timings_ms.contains_key("cache_get_timeout")
                 || resp_timings_ms.contains_key("cache_put_timeout")
@@ -21394,7 +28578,7 @@ Rules:
             // Also treat “tiny but JS-shaped” extraction as low-signal, even if structure exists.
             if (extracted.engine.starts_with("html_") || extracted.engine == "html2text")
                 && n <= 200
-                && Self::looks_like_bundle_gunk(&text)
+                && webpipe_local::extract::looks_like_bundle_gunk(&text)
             {
                 warnings.push("main_content_low_signal");
             }
@@ -21404,6 +28588,9 @@ Rules:
             if let Some(w) = pdf_html_fallback_used_warning {
                 warnings.push(w);
             }
+            if amp_alternate_used {
+                warnings.push("amp_alternate_used");
+            }
             if gh_issue_rewrote {
                 warnings.push("github_issue_rewritten_to_api");
             }
@@ -21429,6 +28616,7 @@ Rules:
                 "ok": true,
                 "fetch_backend": fetch_backend,
                 "url": url,
+                "doc_id": webpipe_local::doc_id::doc_id(&url),
                 "fetched_url": resp_url,
                 "final_url": resp_final_url,
                 "status": resp_status,
@@ -21440,6 +28628,13 @@ Rules:
                 }
             });
             add_envelope_fields(&mut payload, "web_extract", t0.elapsed().as_millis());
+            if html_like {
+                if let Some(pt) = webpipe_local::published::published_time(
+                    &String::from_utf8_lossy(resp_bytes.as_ref()),
+                ) {
+                    payload["published_time"] = serde_json::json!(pt);
+                }
+            }
 
             payload["request"] = serde_json::json!({
                 "fetch_backend": fetch_backend,
@@ -21451,11 +28646,33 @@ Rules:
                 "cache": { "read": req.cache.read, "write": req.cache.write, "ttl_s": req.cache.ttl_s },
                 "width": width,
                 "max_chars": max_chars,
+                "truncation_strategy": truncation_strategy.as_str(),
+                "engine": html_engine.as_str(),
                 "query": args.query,
                 "include_text": include_text,
                 "include_links": include_links,
                 "max_links": max_links,
+                "include_alternates": include_alternates,
+                "prefer_amp": prefer_amp,
+                "include_entities": include_entities,
+                "include_tables": include_tables,
+                "include_metadata": include_metadata,
+                "table_format": table_format,
+                "include_dates": include_dates,
+                "date_order": date_order.as_str(),
+                "include_keywords": include_keywords,
+                "max_keywords": max_keywords,
+                "chunk_dom_paths": chunk_dom_paths,
+                "referer": referer,
+                "sentences": sentences,
+                "include_noscript": include_noscript,
+                "follow_iframes": follow_iframes,
+                "allow_cross_origin_iframes": allow_cross_origin_iframes,
+                "max_iframes": max_iframes,
+                "include_error_body": include_error_body,
+                "include_raw_html": include_raw_html,
                 "include_structure": include_structure,
+                "sectioned": sectioned,
                 "max_outline_items": max_outline_items,
                 "max_blocks": max_blocks,
                 "max_block_chars": max_block_chars,
@@ -21478,19 +28695,10 @@ Rules:
             }
 
             // Canonical extract object (no legacy mirrors).
-            payload["extract"] = serde_json::json!({
-                "engine": extracted.engine,
-                "width": width,
-                "max_chars": max_chars,
-                "text_chars": n,
-                "text_truncated": clipped,
-                "top_chunks": top_chunks,
-                "max_chunk_chars": max_chunk_chars,
-                "chunks": pipeline.chunks
-            });
-            if include_text {
-                payload["extract"]["text"] = serde_json::json!(text);
-            }
+            let confidence = webpipe_local::extract::extraction_confidence(
+                &extracted,
+                pipeline.structure.as_ref(),
+            );
             // Deterministic quality scorecard (tail-risk detector).
             // Additive: safe for existing consumers.
             let quality = Self::quality_scorecard(
@@ -21500,16 +28708,46 @@ Rules:
                 extracted.engine,
                 &warnings,
             );
-            payload["extract"]["quality"] = quality;
+            payload["extract"] = serde_json::json!(webpipe_local::results::ExtractResult {
+                engine: extracted.engine.to_string(),
+                width,
+                max_chars,
+                text_chars: n,
+                text_truncated: clipped,
+                confidence: webpipe_local::results::ExtractResult::rounded_confidence(confidence),
+                top_chunks,
+                max_chunk_chars,
+                chunks: pipeline.chunks.clone(),
+                text: include_text.then(|| text.clone()),
+                text_preview: None,
+                text_preview_source: None,
+                text_preview_truncated: None,
+                quality: Some(quality),
+                structure: if include_structure {
+                    pipeline.structure.clone()
+                } else {
+                    None
+                },
+            });
 
             if let Some(vm) = vision_model.as_ref() {
                 payload["extract"]["vision_model"] = serde_json::json!(vm);
             }
-
-            if include_structure {
-                if let Some(s) = pipeline.structure.as_ref() {
-                    payload["extract"]["structure"] = serde_json::json!(s);
-                }
+            if include_raw_html && html_like {
+                let (raw, raw_chars, raw_truncated) = Self::truncate_to_chars(
+                    &String::from_utf8_lossy(resp_bytes.as_ref()),
+                    max_chars,
+                );
+                payload["extract"]["raw_html"] = serde_json::json!(raw);
+                payload["extract"]["raw_html_chars"] = serde_json::json!(raw_chars);
+                payload["extract"]["raw_html_truncated"] = serde_json::json!(raw_truncated);
+            }
+            if sectioned {
+                payload["extract"]["sections"] = serde_json::json!(pipeline
+                    .structure
+                    .as_ref()
+                    .map(webpipe_local::extract::structure_sections)
+                    .unwrap_or_default());
             }
 
             if let Some(q) = payload["request"]["query"].as_str().map(|s| s.to_string()) {
@@ -21520,68 +28758,23 @@ Rules:
                         .iter()
                         .map(|c| (c.start_char, c.end_char, c.text.clone()))
                         .collect();
-                    let q0 = q.clone();
-                    let semantic_timeout_ms = std::env::var("WEBPIPE_SEMANTIC_TIMEOUT_MS")
-                        .ok()
-                        .and_then(|s| s.trim().parse::<u64>().ok())
-                        .unwrap_or(
+                    let sem = self
+                        .semantic_rerank_or_fallback(
+                            &q,
+                            &cands,
+                            semantic_top_k,
                             args.timeout_ms
                                 .unwrap_or(20_000)
                                 .saturating_div(2)
                                 .clamp(2_000, 8_000),
-                        );
-                    let sem = if semantic_timeout_ms == 0 {
-                        webpipe_local::semantic::SemanticRerankResult {
-                            ok: false,
-                            backend: "unknown".to_string(),
-                            model_id: None,
-                            cache_hits: 0,
-                            cache_misses: 0,
-                            chunks: Vec::new(),
-                            warnings: vec!["semantic_rerank_timeout"],
-                        }
-                    } else {
-                        let handle = tokio::task::spawn_blocking(move || {
-                            webpipe_local::semantic::semantic_rerank_chunks(
-                                &q0,
-                                &cands,
-                                semantic_top_k,
-                            )
-                        });
-                        match tokio::time::timeout(
-                            std::time::Duration::from_millis(semantic_timeout_ms),
-                            handle,
                         )
-                        .await
-                        {
-                            Ok(join) => join.unwrap_or_else(|_| {
-                                webpipe_local::semantic::SemanticRerankResult {
-                                    ok: false,
-                                    backend: "unknown".to_string(),
-                                    model_id: None,
-                                    cache_hits: 0,
-                                    cache_misses: 0,
-                                    chunks: Vec::new(),
-                                    warnings: vec!["semantic_rerank_task_failed"],
-                                }
-                            }),
-                            Err(_) => webpipe_local::semantic::SemanticRerankResult {
-                                ok: false,
-                                backend: "unknown".to_string(),
-                                model_id: None,
-                                cache_hits: 0,
-                                cache_misses: 0,
-                                chunks: Vec::new(),
-                                warnings: vec!["semantic_rerank_timeout"],
-                            },
-                        }
-                    };
+                        .await;
                     for w in &sem.warnings {
                         warnings.push(*w);
                     }
                     payload["extract"]["semantic"] = serde_json::json!(sem);
                 } else if auto_ok
-                    && Self::openrouter_api_key_from_env().is_some()
+                    && Self::semantic_unavailable_reason().is_none()
                     && n >= 1500
                     && !pipeline.chunks.is_empty()
                     && pipeline.chunks.iter().all(|c| c.score <= 1)
@@ -21652,6 +28845,114 @@ Rules:
                 }
             }
 
+            if sentences {
+                add_chunk_sentences(&mut payload["extract"]);
+            }
+            if include_alternates {
+                let alternates = if is_pdf_like {
+                    webpipe_local::links::PageAlternates::default()
+                } else {
+                    let bytes = resp_bytes.clone();
+                    let base_url = payload["final_url"].as_str().unwrap_or("").to_string();
+                    tokio::task::spawn_blocking(move || {
+                        let html = String::from_utf8_lossy(bytes.as_ref()).to_string();
+                        webpipe_local::links::extract_alternates(&html, Some(base_url.as_str()))
+                    })
+                    .await
+                    .unwrap_or_default()
+                };
+                payload["extract"]["alternates"] = serde_json::json!(alternates);
+            }
+            if include_entities {
+                let entities = if is_pdf_like {
+                    Vec::new()
+                } else {
+                    let bytes = resp_bytes.clone();
+                    tokio::task::spawn_blocking(move || {
+                        let html = String::from_utf8_lossy(bytes.as_ref()).to_string();
+                        webpipe_local::entities::extract_entities(&html)
+                    })
+                    .await
+                    .unwrap_or_default()
+                };
+                payload["extract"]["entities"] = serde_json::json!(entities);
+            }
+            if include_tables {
+                let tables = if is_pdf_like {
+                    Vec::new()
+                } else {
+                    let bytes = resp_bytes.clone();
+                    tokio::task::spawn_blocking(move || {
+                        let html = String::from_utf8_lossy(bytes.as_ref()).to_string();
+                        webpipe_local::extract::extract_tables(&html)
+                    })
+                    .await
+                    .unwrap_or_default()
+                };
+                payload["extract"]["tables"] = tables_json(&tables, table_format);
+            }
+            if include_metadata {
+                let metadata = if is_pdf_like {
+                    serde_json::json!({})
+                } else {
+                    let bytes = resp_bytes.clone();
+                    let final_url = payload["final_url"].as_str().unwrap_or("").to_string();
+                    tokio::task::spawn_blocking(move || {
+                        let html = String::from_utf8_lossy(bytes.as_ref()).to_string();
+                        page_metadata_json(&html, &final_url)
+                    })
+                    .await
+                    .unwrap_or_else(|_| serde_json::json!({}))
+                };
+                payload["extract"]["metadata"] = metadata;
+            }
+            if let Some(report) = iframes_report {
+                payload["extract"]["iframes"] = serde_json::json!(report);
+            }
+            if include_dates {
+                let text = text.clone();
+                let dates = tokio::task::spawn_blocking(move || {
+                    webpipe_local::textprep::extract_dates_with(&text, date_order)
+                })
+                .await
+                .unwrap_or_default();
+                payload["extract"]["dates"] = serde_json::json!(dates);
+            }
+            if include_keywords {
+                let (idx, cached) = self
+                    .keyword_index_cached(&webpipe_local::doc_id::doc_id(&url), &text, max_keywords)
+                    .await;
+                payload["extract"]["keywords"] = serde_json::json!(idx);
+                payload["extract"]["keywords"]["cached"] = serde_json::json!(cached);
+            }
+            if chunk_dom_paths
+                && payload["extract"]["engine"]
+                    .as_str()
+                    .is_some_and(|e| e.starts_with("html"))
+            {
+                let bytes = resp_bytes.clone();
+                let texts: Vec<String> = payload["extract"]["chunks"]
+                    .as_array()
+                    .map(|cs| {
+                        cs.iter()
+                            .map(|c| c["text"].as_str().unwrap_or("").to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let paths = tokio::task::spawn_blocking(move || {
+                    let html = String::from_utf8_lossy(bytes.as_ref()).to_string();
+                    let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+                    webpipe_local::dom_paths::chunk_dom_paths(&html, &texts)
+                })
+                .await
+                .unwrap_or_default();
+                if let Some(cs) = payload["extract"]["chunks"].as_array_mut() {
+                    for (c, path) in cs.iter_mut().zip(paths) {
+                        c["dom_path"] = serde_json::json!(path);
+                    }
+                }
+            }
+
             // Include any late-added warnings (e.g. links_timeout) in the final envelope.
             if !warnings.is_empty() {
                 payload["warnings"] = serde_json::json!(warnings);
@@ -21671,6 +28972,12 @@ Rules:
             } else {
                 serde_json::Value::Object(attempts_map)
             };
+            if Self::http_status_is_error(resp_status) {
+                Self::mark_http_status_error(&mut payload, resp_status);
+                if !include_error_body {
+                    Self::strip_error_body_from_extract(&mut payload["extract"]);
+                }
+            }
             // Mirror extract.chunks as top_chunks at the top level for API consistency with
             // search_evidence, which returns top_chunks[] at the response root.
             // Agents can write `response.top_chunks` consistently for both tools.
@@ -21742,6 +29049,33 @@ Rules:
         include_evidence: Option<bool>,
     }
 
+    #[derive(Debug, Deserialize, JsonSchema)]
+    struct PromptResearchQuestionArgs {
+        /// Research question.
+        question: String,
+        /// Width vs depth preset for `web_deep_research` (default: balanced). Allowed: balanced,
+        /// wide, deep.
+        #[serde(default)]
+        depth: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    struct PromptCompareSourcesArgs {
+        /// What the sources should be compared on.
+        question: String,
+        /// Source URLs, separated by whitespace or commas (2..=5 are used).
+        urls: String,
+    }
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    struct PromptSummarizeUrlArgs {
+        /// Page to summarize.
+        url: String,
+        /// Optional focus for the summary (e.g. "pricing", "breaking changes").
+        #[serde(default)]
+        focus: Option<String>,
+    }
+
     #[prompt_router]
     impl WebpipeMcp {
         #[prompt(
@@ -21813,6 +29147,100 @@ Rules:
                 format!("{sys}\n\n{user}"),
             )])
         }
+
+        #[prompt(
+            name = "research_question",
+            description = "Answer a research question with `web_deep_research` (search + evidence + cited synthesis)."
+        )]
+        async fn prompt_research_question(
+            &self,
+            Parameters(args): Parameters<PromptResearchQuestionArgs>,
+        ) -> Result<Vec<PromptMessage>, McpError> {
+            let depth = match args.depth.as_deref().map(str::trim) {
+                Some("wide") => "wide",
+                Some("deep") => "deep",
+                _ => "balanced",
+            };
+            let sys = "You are a careful research assistant. Use webpipe tools to gather evidence, then answer.\n\nRules:\n- Cite sources by URL.\n- Keep bounds small unless the user asks for exhaustive coverage.\n- If evidence is insufficient, say so and list the missing evidence.";
+            let user = format!(
+                "Research question:\n{}\n\nCall `web_deep_research` with query=<the question>, depth=\"{depth}\", include_evidence=true, max_results=5, max_urls=3, top_chunks=5.\nIf `web_deep_research` is not available (or no LLM backend is configured), call `search_evidence` with the same bounds and write the answer yourself from its top chunks.\n\nReturn a concise answer, then a Sources list.",
+                args.question.trim()
+            );
+            Ok(vec![PromptMessage::new_text(
+                PromptMessageRole::User,
+                format!("{sys}\n\n{user}"),
+            )])
+        }
+
+        #[prompt(
+            name = "compare_sources",
+            description = "Compare what 2–5 given URLs say about a question, using `search_evidence` over exactly those URLs."
+        )]
+        async fn prompt_compare_sources(
+            &self,
+            Parameters(args): Parameters<PromptCompareSourcesArgs>,
+        ) -> Result<Vec<PromptMessage>, McpError> {
+            let mut urls: Vec<&str> = Vec::new();
+            for u in args
+                .urls
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .map(str::trim)
+                .filter(|u| !u.is_empty())
+            {
+                if !urls.contains(&u) && urls.len() < 5 {
+                    urls.push(u);
+                }
+            }
+            if urls.len() < 2 {
+                return Err(McpError::invalid_params(
+                    "compare_sources needs at least 2 distinct urls",
+                    None,
+                ));
+            }
+            let url_list = urls
+                .iter()
+                .map(|u| format!("- {u}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let sys = "You are a careful assistant comparing sources. Only use what the sources say.\n\nRules:\n- Attribute every claim to its source URL.\n- Call out agreements, disagreements, and claims only one source makes.\n- Do not fill gaps from memory; say what no source covers.";
+            let user = format!(
+                "Question:\n{}\n\nSources:\n{url_list}\n\nCall `search_evidence` with query=<the question>, urls=[the sources above], url_selection_mode=\"preserve\", max_urls={}, top_chunks=10, max_chunk_chars=500.\n\nReturn a short comparison table (one row per point of comparison, one column per source), then a one-paragraph verdict.",
+                args.question.trim(),
+                urls.len()
+            );
+            Ok(vec![PromptMessage::new_text(
+                PromptMessageRole::User,
+                format!("{sys}\n\n{user}"),
+            )])
+        }
+
+        #[prompt(
+            name = "summarize_url",
+            description = "Summarize one page with `web_summarize` (extract + chunk + bounded bullet summary)."
+        )]
+        async fn prompt_summarize_url(
+            &self,
+            Parameters(args): Parameters<PromptSummarizeUrlArgs>,
+        ) -> Result<Vec<PromptMessage>, McpError> {
+            let focus = args
+                .focus
+                .as_deref()
+                .map(str::trim)
+                .filter(|f| !f.is_empty());
+            let sys = "You are a careful assistant. Summarize only what the page says.\n\nRules:\n- Keep the summary short and factual.\n- Cite the URL.\n- If the page could not be fetched or extracted, say so instead of guessing.";
+            let user = format!(
+                "URL:\n{}\n\nCall `web_summarize` with url=<the URL>, max_bullets=5, top_chunks=8.\nIf `web_summarize` is not available (or no LLM backend is configured), call `web_extract` with include_text=true, max_chars=20000 and summarize its text yourself.\n{}",
+                args.url.trim(),
+                match focus {
+                    Some(f) => format!("\nFocus the summary on: {f}"),
+                    None => String::new(),
+                }
+            );
+            Ok(vec![PromptMessage::new_text(
+                PromptMessageRole::User,
+                format!("{sys}\n\n{user}"),
+            )])
+        }
     }
 
     impl rmcp::ServerHandler for WebpipeMcp {
@@ -21911,7 +29339,7 @@ Rules:
             _request: Option<PaginatedRequestParam>,
             _context: RequestContext<RoleServer>,
         ) -> Result<ListResourcesResult, McpError> {
-            let resources: Vec<Resource> = vec![
+            let mut resources: Vec<Resource> = vec![
                 Annotated::new(
                     RawResource {
                         uri: "webpipe://meta".to_string(),
@@ -21945,6 +29373,39 @@ Rules:
                     None,
                 ),
             ];
+            let max = cache_resources_max_from_env();
+            if max > 0 {
+                let cache = webpipe_local::FsCache::new(
+                    cache_dir_from_env().unwrap_or_else(default_cache_dir),
+                );
+                let entries = tokio::task::spawn_blocking(move || cache.recent_entries(max))
+                    .await
+                    .unwrap_or_default();
+                resources.extend(entries.into_iter().map(|e| {
+                    Annotated::new(
+                        RawResource {
+                            uri: format!("{CACHE_RESOURCE_SCHEME}{}", e.key),
+                            name: e.final_url.clone(),
+                            title: None,
+                            description: Some(format!(
+                                "Cached response (HTTP {}) for {}, fetched at epoch {}s.",
+                                e.status, e.url, e.fetched_at_epoch_s
+                            )),
+                            // Drop parameters (`; charset=...`): mime only.
+                            mime_type: e
+                                .content_type
+                                .as_deref()
+                                .and_then(|ct| ct.split(';').next())
+                                .map(|ct| ct.trim().to_ascii_lowercase())
+                                .filter(|ct| !ct.is_empty()),
+                            size: u32::try_from(e.bytes).ok(),
+                            icons: None,
+                            meta: None,
+                        },
+                        None,
+                    )
+                }));
+            }
             Ok(ListResourcesResult::with_all_items(resources))
         }
 
@@ -21977,11 +29438,45 @@ Rules:
                     let s = serde_json::to_string(&v).unwrap_or_default();
                     (s, "application/json".to_string())
                 }
+                _ if uri.starts_with(CACHE_RESOURCE_SCHEME) => {
+                    let key = uri[CACHE_RESOURCE_SCHEME.len()..].to_string();
+                    let cache = webpipe_local::FsCache::new(
+                        cache_dir_from_env().unwrap_or_else(default_cache_dir),
+                    );
+                    let entry = tokio::task::spawn_blocking(move || cache.read_by_key(&key))
+                        .await
+                        .map_err(|e| McpError::internal_error(e.to_string(), None))?
+                        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                    let Some((info, body)) = entry else {
+                        return Err(McpError::resource_not_found(
+                            format!("no cached document for {uri}"),
+                            Some(serde_json::json!({
+                                "hint": "The entry may have been evicted or purged; list resources again."
+                            })),
+                        ));
+                    };
+                    // Bodies are stored content-decoded; binary ones (PDF, images) aren't text.
+                    let text = String::from_utf8(body).map_err(|_| {
+                        McpError::invalid_params(
+                            format!("cached document is not UTF-8 text: {uri}"),
+                            Some(serde_json::json!({
+                                "url": info.final_url,
+                                "content_type": info.content_type,
+                                "hint": "Use web_extract on the URL to get extracted text for binary documents."
+                            })),
+                        )
+                    })?;
+                    let mime = info
+                        .content_type
+                        .unwrap_or_else(|| "text/plain".to_string());
+                    (text, mime)
+                }
                 _ => {
                     return Err(McpError::resource_not_found(
                         format!("unknown resource uri: {uri}"),
                         Some(serde_json::json!({
-                            "known_uris": ["webpipe://meta", "webpipe://usage"]
+                            "known_uris": ["webpipe://meta", "webpipe://usage"],
+                            "cached_documents": format!("{CACHE_RESOURCE_SCHEME}<key> (see resources/list)")
                         })),
                     ));
                 }
@@ -22164,6 +29659,10 @@ Rules:
             fn set(&self, k: &str, v: &str) {
                 std::env::set_var(k, v);
             }
+
+            fn remove(&self, k: &str) {
+                std::env::remove_var(k);
+            }
         }
 
         impl Drop for EnvGuard {
@@ -22305,6 +29804,75 @@ Rules:
             assert_eq!(sel.chosen, "tavily");
         }
 
+        #[test]
+        fn mab_thompson_sampling_is_seeded_and_favors_the_reliable_arm() {
+            let arms = vec!["brave".to_string(), "tavily".to_string()];
+            let mut summaries = std::collections::BTreeMap::new();
+            summaries.insert(
+                "brave".to_string(),
+                muxer::Summary {
+                    calls: 20,
+                    ok: 4,
+                    ..Default::default()
+                },
+            );
+            summaries.insert(
+                "tavily".to_string(),
+                muxer::Summary {
+                    calls: 20,
+                    ok: 19,
+                    ..Default::default()
+                },
+            );
+            let cfg = |seed| muxer::MabConfig {
+                strategy: muxer::MabStrategy::Thompson,
+                seed,
+                ..muxer::MabConfig::default()
+            };
+
+            // Same stats + seed => same draws.
+            let a = muxer::select_mab(&arms, &summaries, &cfg(7));
+            let b = muxer::select_mab(&arms, &summaries, &cfg(7));
+            assert_eq!(a.strategy, muxer::MabStrategy::Thompson);
+            let scores =
+                |s: &muxer::MabSelection| s.candidates.iter().map(|r| r.score).collect::<Vec<_>>();
+            assert_eq!(scores(&a), scores(&b));
+            assert!(scores(&a).iter().all(|x| (0.0..=1.0).contains(x)));
+
+            // Beta(5, 17) vs Beta(20, 2): the reliable arm wins nearly every seed.
+            let tavily_wins = (0..200u64)
+                .filter(|&seed| muxer::select_mab(&arms, &summaries, &cfg(seed)).chosen == "tavily")
+                .count();
+            assert!(tavily_wins >= 195, "tavily_wins={tavily_wins}");
+
+            // Unexplored arms are Beta(1, 1): each gets picked some of the time.
+            let fresh = std::collections::BTreeMap::new();
+            let brave_wins = (0..200u64)
+                .filter(|&seed| muxer::select_mab(&arms, &fresh, &cfg(seed)).chosen == "brave")
+                .count();
+            assert!((40..=160).contains(&brave_wins), "brave_wins={brave_wins}");
+
+            // Penalties still apply on top of the draw.
+            let mut costly = summaries.clone();
+            costly.get_mut("tavily").unwrap().cost_units = 200;
+            let sel = muxer::select_mab(
+                &arms,
+                &costly,
+                &muxer::MabConfig {
+                    cost_weight: 1.0,
+                    ..cfg(7)
+                },
+            );
+            assert_eq!(sel.chosen, "brave");
+
+            let ucb = muxer::select_mab(&arms, &summaries, &muxer::MabConfig::default());
+            assert_eq!(ucb.strategy, muxer::MabStrategy::Ucb);
+            assert_eq!(
+                serde_json::to_value(&sel).unwrap()["strategy"].as_str(),
+                Some("thompson")
+            );
+        }
+
         #[test]
         fn routing_context_query_key_is_bounded_by_max_contexts() {
             let env = EnvGuard::new(&[
@@ -22325,6 +29893,249 @@ Rules:
             assert!(s.search_windows_by_query_key.len() <= 1);
         }
 
+        #[test]
+        fn later_search_pages_count_usage_but_not_routing_windows() {
+            let env = EnvGuard::new(&["WEBPIPE_ROUTING_CONTEXT", "WEBPIPE_ROUTING_WINDOW"]);
+            env.set("WEBPIPE_ROUTING_CONTEXT", "both");
+            env.set("WEBPIPE_ROUTING_WINDOW", "10");
+
+            let mcp = WebpipeMcp::new().expect("mcp new");
+            mcp.stats_record_search_page(true, "brave", true, 1, 10, None, Some("qk1"));
+            mcp.stats_record_search_page(false, "brave", true, 1, 10, None, Some("qk1"));
+            mcp.stats_record_search_page(false, "brave", true, 1, 10, None, Some("qk1"));
+
+            let s = mcp.stats_lock();
+            let usage = &s.search_providers["brave"];
+            assert_eq!((usage.calls, usage.cost_units), (3, 3));
+            assert_eq!(s.search_windows["brave"].summary().calls, 1);
+            assert_eq!(
+                s.search_windows_by_query_key["qk1"]["brave"]
+                    .summary()
+                    .calls,
+                1
+            );
+        }
+
+        #[test]
+        fn latency_reservoir_percentiles_track_a_known_distribution() {
+            // Uniform 1..=10_000 ms fed in ascending order (the worst case for a naive
+            // "keep the first N" sample).
+            let mut r = LatencyReservoir::default();
+            for ms in 1..=10_000u64 {
+                r.record(ms);
+            }
+            assert_eq!(r.samples.len(), LATENCY_RESERVOIR_CAP);
+            let v = serde_json::to_value(&r).unwrap();
+            assert_eq!(v["count"].as_u64(), Some(10_000));
+            assert_eq!(v["mean"].as_f64(), Some(5_000.5));
+            assert_eq!(v["max"].as_u64(), Some(10_000));
+            for (k, want, tol) in [
+                ("p50", 5_000.0, 0.06),
+                ("p95", 9_500.0, 0.03),
+                ("p99", 9_900.0, 0.02),
+            ] {
+                let got = v[k].as_u64().unwrap() as f64;
+                assert!((got - want).abs() <= want * tol, "{k}={got} want≈{want}");
+            }
+
+            // Small samples are exact.
+            let mut small = LatencyReservoir::default();
+            for ms in [40, 10, 30, 20, 100] {
+                small.record(ms);
+            }
+            let v = serde_json::to_value(&small).unwrap();
+            assert_eq!(
+                (v["p50"].as_u64(), v["p95"].as_u64(), v["max"].as_u64()),
+                (Some(30), Some(100), Some(100))
+            );
+            let empty = serde_json::to_value(LatencyReservoir::default()).unwrap();
+            assert_eq!(empty["count"].as_u64(), Some(0));
+            assert!(empty["p50"].is_null() && empty["mean"].is_null());
+        }
+
+        #[tokio::test]
+        async fn webpipe_usage_reports_backend_latency_percentiles_until_reset() {
+            let svc = WebpipeMcp::new().expect("new");
+            for ms in 1..=200u64 {
+                svc.stats_record_fetch_backend("local", true, ms, None);
+            }
+            svc.stats_record_search_provider_qk("brave", true, 1, 75, None, None);
+
+            let usage = |svc: &WebpipeMcp| {
+                let svc = svc.clone();
+                async move {
+                    let r = svc
+                        .webpipe_usage(Parameters(Some(WebpipeUsageArgs::default())))
+                        .await
+                        .expect("usage");
+                    payload_from_call_tool_result(&r)
+                }
+            };
+            let v = usage(&svc).await;
+            let l = &v["usage"]["fetch_backends"]["local"]["latency"];
+            assert_eq!(l["count"].as_u64(), Some(200), "{v}");
+            assert_eq!(l["p50"].as_u64(), Some(100));
+            assert_eq!(l["p95"].as_u64(), Some(190));
+            assert_eq!(l["p99"].as_u64(), Some(198));
+            assert_eq!(l["max"].as_u64(), Some(200));
+            assert_eq!(l["mean"].as_f64(), Some(100.5));
+            assert_eq!(
+                v["usage"]["search_providers"]["brave"]["latency"]["p99"].as_u64(),
+                Some(75)
+            );
+
+            svc.webpipe_usage_reset().await.expect("reset");
+            let v = usage(&svc).await;
+            assert!(v["usage"]["fetch_backends"].get("local").is_none(), "{v}");
+        }
+
+        #[tokio::test]
+        async fn webpipe_usage_counts_fetch_cache_statuses() {
+            let env = EnvGuard::new(&["WEBPIPE_CACHE_DIR", "WEBPIPE_RESPECT_CACHE_CONTROL"]);
+            env.remove("WEBPIPE_RESPECT_CACHE_CONTROL");
+            use axum::{routing::get, Router};
+            let app = Router::new().route("/", get(|| async { "cached body" }));
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+
+            let svc = WebpipeMcp::new().expect("new");
+            let mut statuses = Vec::new();
+            for _ in 0..2 {
+                let r = svc
+                    .web_fetch(p(WebFetchArgs {
+                        url: Some(format!("http://{addr}/")),
+                        fetch_backend: Some("local".to_string()),
+                        ..Default::default()
+                    }))
+                    .await
+                    .unwrap();
+                statuses.push(payload_from_call_tool_result(&r)["cache_status"].clone());
+            }
+            assert_eq!(
+                statuses,
+                vec![serde_json::json!("miss"), serde_json::json!("fresh")]
+            );
+
+            let v =
+                payload_from_call_tool_result(&svc.webpipe_usage(Parameters(None)).await.unwrap());
+            assert_eq!(
+                v["usage"]["fetch_cache"],
+                serde_json::json!({"fresh": 1, "miss": 1, "revalidated": 0, "stale": 0})
+            );
+            svc.webpipe_usage_reset().await.unwrap();
+            let v =
+                payload_from_call_tool_result(&svc.webpipe_usage(Parameters(None)).await.unwrap());
+            assert_eq!(v["usage"]["fetch_cache"]["fresh"].as_u64(), Some(0));
+        }
+
+        #[tokio::test]
+        async fn webpipe_usage_include_query_keys_reports_bounded_per_key_summaries() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.extend_from_slice(&[
+                "WEBPIPE_ROUTING_CONTEXT",
+                "WEBPIPE_ROUTING_MAX_CONTEXTS",
+                "WEBPIPE_ROUTING_WINDOW",
+            ]);
+            let env = EnvGuard::new(&keys);
+            env.set("WEBPIPE_ROUTING_CONTEXT", "query_key");
+
+            use axum::{routing::get, Json, Router};
+            let app = Router::new().route(
+                "/search",
+                get(|| async {
+                    Json(serde_json::json!({ "results": [
+                        {"url": "https://example.com/a", "title": "A", "content": "a"}
+                    ]}))
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set("WEBPIPE_SEARXNG_ENDPOINT", &format!("http://{addr}"));
+
+            let svc = WebpipeMcp::new().expect("new");
+            for q in [
+                "Rust Ownership",
+                "rust ownership",
+                "tokio runtime",
+                "serde derive",
+            ] {
+                let r = svc
+                    .web_search(p(WebSearchArgs {
+                        query: Some(q.to_string()),
+                        provider: Some("searxng".to_string()),
+                        max_results: Some(1),
+                        ..Default::default()
+                    }))
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    payload_from_call_tool_result(&r)["ok"].as_bool(),
+                    Some(true)
+                );
+            }
+
+            // Off by default.
+            let v =
+                payload_from_call_tool_result(&svc.webpipe_usage(Parameters(None)).await.unwrap());
+            assert!(v["usage"]["search_window"].get("by_query_key").is_none());
+
+            let v = payload_from_call_tool_result(
+                &svc.webpipe_usage(Parameters(Some(WebpipeUsageArgs {
+                    include_query_keys: Some(true),
+                    max_query_keys: Some(2),
+                })))
+                .await
+                .unwrap(),
+            );
+            let sw = &v["usage"]["search_window"];
+            assert_eq!(sw["routing_contexts_in_memory"].as_u64(), Some(3));
+            assert_eq!(sw["by_query_key_truncated"].as_bool(), Some(true));
+            let rows = sw["by_query_key"].as_array().expect("by_query_key");
+            let keys: Vec<&str> = rows
+                .iter()
+                .filter_map(|r| r["query_key"].as_str())
+                .collect();
+            // Most recent first, using the scrubbed key.
+            assert_eq!(
+                keys,
+                vec![
+                    WebpipeMcp::query_key("serde derive").unwrap().as_str(),
+                    WebpipeMcp::query_key("tokio runtime").unwrap().as_str(),
+                ]
+            );
+            assert_eq!(rows[0]["summaries"]["searxng"]["calls"].as_u64(), Some(1));
+
+            // Both spellings scrub to one key, so its window holds two calls.
+            let v = payload_from_call_tool_result(
+                &svc.webpipe_meta(Parameters(Some(WebpipeMetaArgs {
+                    method: Some("usage".to_string()),
+                    include_query_keys: Some(true),
+                    max_query_keys: Some(10),
+                })))
+                .await
+                .unwrap(),
+            );
+            let rows = v["usage"]["search_window"]["by_query_key"]
+                .as_array()
+                .expect("by_query_key");
+            assert_eq!(rows.len(), 3);
+            let own = WebpipeMcp::query_key("rust ownership").unwrap();
+            let row = rows
+                .iter()
+                .find(|r| r["query_key"].as_str() == Some(own.as_str()))
+                .expect("ownership key");
+            assert_eq!(row["summaries"]["searxng"]["calls"].as_u64(), Some(2));
+        }
+
         #[test]
         fn compute_search_junk_label_hard_always_true() {
             let env = EnvGuard::new(&[
@@ -22450,6 +30261,25 @@ Rules:
             );
         }
 
+        #[test]
+        fn summary_bullets_strips_markers_and_bounds_count_and_chars() {
+            let reply =
+                "Here is a summary:\n- First point.\n* Second point.\n2. Third point.\n\n• Fourth";
+            let (b, truncated) = summary_bullets(reply, 3, 1_000);
+            assert_eq!(b, vec!["First point.", "Second point.", "Third point."]);
+            assert!(truncated);
+
+            // The char budget spans all bullets; the last kept one is clipped.
+            let (b, truncated) = summary_bullets(reply, 10, 20);
+            assert_eq!(b, vec!["First point.", "Second p"]);
+            assert!(truncated);
+
+            // A reply without list markers falls back to its lines.
+            let (b, truncated) = summary_bullets("One line.\n\nAnother line.", 5, 1_000);
+            assert_eq!(b, vec!["One line.", "Another line."]);
+            assert!(!truncated);
+        }
+
         #[test]
         fn truncate_to_chars_is_utf8_safe_and_consistent() {
             let s = "aé🙂中";
@@ -22478,6 +30308,69 @@ Rules:
             );
         }
 
+        #[test]
+        fn mmr_selection_skips_near_duplicates_for_a_less_similar_chunk() {
+            let cand = |start: usize, score: u64, text: &str| ChunkCandidate {
+                url: "https://example.com/a".to_string(),
+                score,
+                start_char: start,
+                end_char: start + text.len(),
+                text: text.to_string(),
+                warning_penalty: 0,
+                cache_hit: false,
+            };
+            let cands = vec![
+                cand(
+                    0,
+                    10,
+                    "rust borrow checker rejects aliasing mutable references",
+                ),
+                cand(
+                    100,
+                    9,
+                    "the rust borrow checker rejects aliasing mutable references",
+                ),
+                cand(200, 7, "lifetimes annotate how long references stay valid"),
+            ];
+            let starts =
+                |v: Vec<ChunkCandidate>| v.iter().map(|c| c.start_char).collect::<Vec<_>>();
+
+            assert_eq!(
+                starts(WebpipeMcp::select_top_chunks(cands.clone(), 2, "score")),
+                [0, 100]
+            );
+            assert_eq!(
+                starts(WebpipeMcp::select_top_chunks_mmr(
+                    cands.clone(),
+                    2,
+                    0.5,
+                    None
+                )),
+                [0, 200]
+            );
+            // Default lambda via the plain `select_top_chunks` entry point.
+            assert_eq!(
+                starts(WebpipeMcp::select_top_chunks(cands.clone(), 2, "mmr")),
+                [0, 200]
+            );
+            assert_eq!(
+                starts(WebpipeMcp::select_top_chunks_mmr(
+                    cands.clone(),
+                    2,
+                    1.0,
+                    None
+                )),
+                [0, 100]
+            );
+
+            // With embeddings, similarity comes from the vectors, not the words.
+            let embs = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.05]];
+            assert_eq!(
+                starts(WebpipeMcp::select_top_chunks_mmr(cands, 2, 0.5, Some(embs))),
+                [0, 100]
+            );
+        }
+
         proptest! {
             #[test]
             fn query_key_never_panics_for_arbitrary_unicode(s in any::<String>()) {
@@ -22534,6 +30427,88 @@ Rules:
                 prop_assert_eq!(sa, sb);
             }
 
+            #[test]
+            fn url_tiebreak_ignores_host_port_and_fragment(
+                a in "[a-z]{1,8}",
+                b in "[a-z]{1,8}",
+                port in 1024u16..65535,
+            ) {
+                let ord = WebpipeMcp::url_tiebreak(
+                    &format!("http://127.0.0.1:{port}/{a}#x"),
+                    &format!("http://127.0.0.1:{port}/{b}"),
+                );
+                let ord2 = WebpipeMcp::url_tiebreak(
+                    &format!("https://example.com/{a}"),
+                    &format!("https://example.com/{b}#y"),
+                );
+                // Equal paths fall through to the full-URL comparison, which does see the host.
+                if a != b {
+                    prop_assert_eq!(ord, ord2);
+                }
+            }
+
+            #[test]
+            fn mmr_selection_is_bounded_deterministic_and_score_order_at_lambda_one(
+                scores in prop::collection::vec(0u64..20, 0..30),
+                texts in prop::collection::vec("[a-d]{3}( [a-d]{3}){0,4}", 0..30),
+                top_k in 1usize..10,
+                lambda in 0.0f64..1.0,
+                rot in 0usize..30,
+            ) {
+                let n = scores.len().min(texts.len());
+                let cands: Vec<ChunkCandidate> = (0..n)
+                    .map(|i| ChunkCandidate {
+                        url: format!("https://example.com/{}", i % 3),
+                        score: scores[i],
+                        start_char: i,
+                        end_char: i + 1,
+                        text: texts[i].clone(),
+                        warning_penalty: 0,
+                        cache_hit: false,
+                    })
+                    .collect();
+                let sig = |v: Vec<ChunkCandidate>| -> Vec<(String, usize)> {
+                    v.into_iter().map(|c| (c.url, c.start_char)).collect()
+                };
+                let a = sig(WebpipeMcp::select_top_chunks_mmr(cands.clone(), top_k, lambda, None));
+                let mut rotated = cands.clone();
+                if n > 0 {
+                    rotated.rotate_left(rot % n);
+                }
+                prop_assert_eq!(&a, &sig(WebpipeMcp::select_top_chunks_mmr(rotated, top_k, lambda, None)));
+                prop_assert_eq!(a.len(), top_k.min(n));
+
+                prop_assert_eq!(
+                    sig(WebpipeMcp::select_top_chunks_mmr(cands.clone(), top_k, 1.0, None)),
+                    sig(WebpipeMcp::select_top_chunks(cands, top_k, "score"))
+                );
+            }
+
+            #[test]
+            fn query_rank_url_selection_ignores_input_order_for_equal_scores(
+                paths in prop::collection::vec("[a-z]{1,8}", 1..20),
+                rot in 0usize..20,
+                max_urls in 1usize..10,
+            ) {
+                // Every URL matches the one query token, so all scores tie.
+                let urls: Vec<String> = paths
+                    .iter()
+                    .map(|p| format!("https://example.com/rust/{p}"))
+                    .collect();
+                let mut shuffled = urls.clone();
+                shuffled.reverse();
+                let k = rot % shuffled.len();
+                shuffled.rotate_left(k);
+
+                let sel = |u: Vec<String>| {
+                    WebpipeMcp::select_urls_for_hydration(u, max_urls, "rust", "query_rank")
+                };
+                let a = sel(urls.clone());
+                prop_assert_eq!(&a, &sel(urls.clone()));
+                prop_assert_eq!(&a, &sel(shuffled));
+                prop_assert!(a.len() <= max_urls);
+            }
+
             #[test]
             fn score_selection_is_sorted_and_deterministic(
                 urls in prop::collection::vec(any::<String>(), 0..30),
@@ -22774,6 +30749,9 @@ Rules:
                     include_evidence: Some(true),
                     now_epoch_s: Some(1700000000),
                     llm_backend: None,
+                    suggest_followups: None,
+                    passes: None,
+                    structured_output: None,
                 })))
                 .await
                 .expect("call");
@@ -22817,10 +30795,14 @@ Rules:
                 timeout_ms: Some(2_000),
                 max_bytes: Some(200_000),
                 headers: BTreeMap::new(),
+                method: None,
+                body: None,
                 cache: FetchCachePolicy {
                     read: true,
                     write: true,
                     ttl_s: Some(60),
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
                 },
             };
             cache
@@ -22833,8 +30815,12 @@ Rules:
                         content_type: Some("text/html".to_string()),
                         headers: BTreeMap::new(),
                         bytes: html.as_bytes().to_vec(),
+                        wire_bytes: html.as_bytes().to_vec().len() as u64,
                         truncated: false,
                         source: FetchSource::Network,
+                        cache_status: None,
+                        body_path: None,
+                        revalidating: false,
                         timings_ms: BTreeMap::new(),
                     },
                 )
@@ -22905,6 +30891,9 @@ Rules:
                     include_evidence: Some(true),
                     now_epoch_s: Some(1700000000),
                     llm_backend: Some("ollama".to_string()),
+                    suggest_followups: None,
+                    passes: None,
+                    structured_output: None,
                 })))
                 .await
                 .expect("call");
@@ -22953,10 +30942,14 @@ Rules:
                 timeout_ms: Some(2_000),
                 max_bytes: Some(200_000),
                 headers: BTreeMap::new(),
+                method: None,
+                body: None,
                 cache: FetchCachePolicy {
                     read: true,
                     write: true,
                     ttl_s: Some(60),
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
                 },
             };
             cache
@@ -22969,8 +30962,12 @@ Rules:
                         content_type: Some("text/html".to_string()),
                         headers: BTreeMap::new(),
                         bytes: html.as_bytes().to_vec(),
+                        wire_bytes: html.as_bytes().to_vec().len() as u64,
                         truncated: false,
                         source: FetchSource::Network,
+                        cache_status: None,
+                        body_path: None,
+                        revalidating: false,
                         timings_ms: BTreeMap::new(),
                     },
                 )
@@ -23042,6 +31039,9 @@ Rules:
                     include_evidence: Some(true),
                     now_epoch_s: Some(1700000000),
                     llm_backend: Some("openai_compat".to_string()),
+                    suggest_followups: None,
+                    passes: None,
+                    structured_output: None,
                 })))
                 .await
                 .expect("call");
@@ -23056,166 +31056,1101 @@ Rules:
                 .contains("OpenAI-compat synthesis ok"));
         }
 
+        #[test]
+        fn parse_deep_research_followups_is_bounded_and_lenient() {
+            let raw = "Here you go:\n```json\n[\n{\"question\":\"Q1\",\"rationale\":\"R1\"},\n{\"question\":\"  \"},\n{\"question\":\"Q2\"},\n{\"question\":\"Q3\",\"rationale\":\"R3\"},\n{\"question\":\"Q4\",\"rationale\":\"R4\"},\n{\"question\":\"Q5\",\"rationale\":\"R5\"},\n{\"question\":\"Q6\",\"rationale\":\"R6\"}\n]\n```";
+            let got = parse_deep_research_followups(raw);
+            assert_eq!(got.len(), DEEP_RESEARCH_MAX_FOLLOWUPS);
+            assert_eq!(got[0]["question"].as_str(), Some("Q1"));
+            assert_eq!(got[0]["rationale"].as_str(), Some("R1"));
+            assert_eq!(got[1]["question"].as_str(), Some("Q2"));
+            assert_eq!(got[1]["rationale"].as_str(), Some(""));
+
+            let wrapped = r#"{"followups":[{"question":"A","rationale":"B"}]}"#;
+            assert_eq!(parse_deep_research_followups(wrapped).len(), 1);
+            assert!(parse_deep_research_followups("no json here").is_empty());
+        }
+
         #[tokio::test]
-        async fn web_deep_research_openai_compat_rejects_non_localhost_in_no_network_mode() {
+        async fn web_deep_research_suggest_followups_returns_bounded_followups() {
             let mut keys = Vec::new();
             keys.extend_from_slice(&SEARCH_ENV_KEYS);
             keys.extend_from_slice(&PERPLEXITY_ENV_KEYS);
             keys.extend_from_slice(&[
+                "WEBPIPE_CACHE_DIR",
                 "WEBPIPE_OPENAI_COMPAT_BASE_URL",
+                "WEBPIPE_OPENAI_COMPAT_API_KEY",
                 "WEBPIPE_OPENAI_COMPAT_MODEL",
             ]);
             let env = EnvGuard::new(&keys);
-            env.set("WEBPIPE_OPENAI_COMPAT_BASE_URL", "https://example.com");
-            env.set("WEBPIPE_OPENAI_COMPAT_MODEL", "x");
+
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+
+            let url = "http://example.invalid/fixture".to_string();
+            let html = "<html><body><h1>Hello</h1><p>cached world</p></body></html>";
+            let cache = webpipe_local::FsCache::new(tmp.path().to_path_buf());
+            let req = FetchRequest {
+                url: url.clone(),
+                timeout_ms: Some(2_000),
+                max_bytes: Some(200_000),
+                headers: BTreeMap::new(),
+                method: None,
+                body: None,
+                cache: FetchCachePolicy {
+                    read: true,
+                    write: true,
+                    ttl_s: Some(60),
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
+                },
+            };
+            cache
+                .put(
+                    &req,
+                    &webpipe_core::FetchResponse {
+                        url: url.clone(),
+                        final_url: url.clone(),
+                        status: 200,
+                        content_type: Some("text/html".to_string()),
+                        headers: BTreeMap::new(),
+                        bytes: html.as_bytes().to_vec(),
+                        wire_bytes: html.as_bytes().to_vec().len() as u64,
+                        truncated: false,
+                        source: FetchSource::Network,
+                        cache_status: None,
+                        body_path: None,
+                        revalidating: false,
+                        timings_ms: BTreeMap::new(),
+                    },
+                )
+                .expect("cache put");
+
+            // Stub: answer the synthesis call normally; answer the follow-up call with 7 items
+            // (more than the cap) so we can assert bounding.
+            use axum::{routing::post, Json, Router};
+            use std::net::SocketAddr;
+            let app = Router::new().route(
+                "/v1/chat/completions",
+                post(|body: Json<serde_json::Value>| async move {
+                    let sys = body.0["messages"][0]["content"].as_str().unwrap_or("");
+                    let content = if sys.contains("follow-up") {
+                        let items: Vec<serde_json::Value> = (1..=7)
+                            .map(|i| serde_json::json!({"question": format!("Q{i}?"), "rationale": format!("gap {i}")}))
+                            .collect();
+                        serde_json::Value::Array(items).to_string()
+                    } else {
+                        "Synthesis ok.".to_string()
+                    };
+                    Json(serde_json::json!({
+                        "choices": [ { "message": { "role": "assistant", "content": content } } ]
+                    }))
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set("WEBPIPE_OPENAI_COMPAT_BASE_URL", &format!("http://{addr}"));
+            env.set("WEBPIPE_OPENAI_COMPAT_MODEL", "local/test");
 
             let svc = WebpipeMcp::new().expect("new");
             let r = svc
-                .web_deep_research(Parameters(Some(WebDeepResearchArgs {
-                    query: "q".to_string(),
-                    audit: None,
-                    synthesize: None,
-                    urls: Some(vec!["http://example.invalid/fixture".to_string()]),
-                    provider: Some("auto".to_string()),
-                    auto_mode: Some("fallback".to_string()),
-                    selection_mode: None,
-                    exploration: None,
+                .web_deep_research(p(WebDeepResearchArgs {
+                    query: "test question".to_string(),
+                    urls: Some(vec![url]),
                     fetch_backend: Some("local".to_string()),
                     no_network: Some(true),
-                    max_results: Some(1),
                     max_urls: Some(1),
-                    timeout_ms: Some(2_000),
+                    timeout_ms: Some(5_000),
                     max_bytes: Some(200_000),
-                    width: Some(80),
-                    max_chars: Some(2_000),
-                    top_chunks: Some(1),
+                    max_chars: Some(5_000),
+                    top_chunks: Some(2),
                     max_chunk_chars: Some(200),
-                    include_links: Some(false),
-                    max_links: Some(10),
-                    arxiv_mode: None,
-                    arxiv_max_papers: None,
-                    arxiv_categories: None,
-                    arxiv_years: None,
-                    arxiv_timeout_ms: None,
-                    papers_mode: None,
-                    papers_backends: None,
-                    papers_max_papers: None,
-                    papers_years: None,
-                    papers_timeout_ms: None,
-                    papers_include_abstract: None,
-                    model: Some("sonar-deep-research".to_string()),
-                    llm_model: Some("x".to_string()),
-                    search_mode: None,
-                    reasoning_effort: None,
-                    max_tokens: None,
-                    temperature: None,
-                    top_p: None,
-                    max_answer_chars: Some(500),
                     include_evidence: Some(false),
-                    now_epoch_s: Some(1700000000),
                     llm_backend: Some("openai_compat".to_string()),
-                })))
+                    suggest_followups: Some(true),
+                    ..Default::default()
+                }))
                 .await
                 .expect("call");
 
             let v = payload_from_call_tool_result(&r);
-            assert_eq!(v["ok"].as_bool(), Some(false));
-            assert_eq!(
-                v["error"]["code"].as_str(),
-                Some(ErrorCode::NotSupported.as_str())
-            );
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["answer"]["text"].as_str(), Some("Synthesis ok."));
+            let fs = v["followups"].as_array().expect("followups");
+            assert_eq!(fs.len(), DEEP_RESEARCH_MAX_FOLLOWUPS);
+            assert_eq!(fs[0]["question"].as_str(), Some("Q1?"));
+            assert_eq!(fs[0]["rationale"].as_str(), Some("gap 1"));
         }
 
         #[tokio::test]
-        async fn web_deep_research_audit_rejects_search_mode() {
+        async fn web_deep_research_suggest_followups_omitted_on_llm_failure() {
             let mut keys = Vec::new();
             keys.extend_from_slice(&SEARCH_ENV_KEYS);
             keys.extend_from_slice(&PERPLEXITY_ENV_KEYS);
-            let _env = EnvGuard::new(&keys);
+            keys.extend_from_slice(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_OPENAI_COMPAT_BASE_URL",
+                "WEBPIPE_OPENAI_COMPAT_API_KEY",
+                "WEBPIPE_OPENAI_COMPAT_MODEL",
+            ]);
+            let env = EnvGuard::new(&keys);
+
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+
+            let url = "http://example.invalid/fixture".to_string();
+            let cache = webpipe_local::FsCache::new(tmp.path().to_path_buf());
+            let req = FetchRequest {
+                url: url.clone(),
+                timeout_ms: Some(2_000),
+                max_bytes: Some(200_000),
+                headers: BTreeMap::new(),
+                method: None,
+                body: None,
+                cache: FetchCachePolicy {
+                    read: true,
+                    write: true,
+                    ttl_s: Some(60),
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
+                },
+            };
+            cache
+                .put(
+                    &req,
+                    &webpipe_core::FetchResponse {
+                        url: url.clone(),
+                        final_url: url.clone(),
+                        status: 200,
+                        content_type: Some("text/html".to_string()),
+                        headers: BTreeMap::new(),
+                        bytes: b"<html><body><p>cached world</p></body></html>".to_vec(),
+                        wire_bytes: b"<html><body><p>cached world</p></body></html>"
+                            .to_vec()
+                            .len() as u64,
+                        truncated: false,
+                        source: FetchSource::Network,
+                        cache_status: None,
+                        body_path: None,
+                        revalidating: false,
+                        timings_ms: BTreeMap::new(),
+                    },
+                )
+                .expect("cache put");
+
+            // Stub: synthesis succeeds, follow-up call returns HTTP 500.
+            use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+            use std::net::SocketAddr;
+            let app = Router::new().route(
+                "/v1/chat/completions",
+                post(|body: Json<serde_json::Value>| async move {
+                    let sys = body.0["messages"][0]["content"].as_str().unwrap_or("");
+                    if sys.contains("follow-up") {
+                        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                    }
+                    Json(serde_json::json!({
+                        "choices": [ { "message": { "role": "assistant", "content": "Synthesis ok." } } ]
+                    }))
+                    .into_response()
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set("WEBPIPE_OPENAI_COMPAT_BASE_URL", &format!("http://{addr}"));
+            env.set("WEBPIPE_OPENAI_COMPAT_MODEL", "local/test");
 
             let svc = WebpipeMcp::new().expect("new");
             let r = svc
-                .web_deep_research(Parameters(Some(WebDeepResearchArgs {
-                    query: "q".to_string(),
-                    audit: Some(true),
-                    synthesize: Some(false),
-                    urls: Some(vec!["http://example.invalid/fixture".to_string()]),
-                    provider: Some("auto".to_string()),
-                    auto_mode: Some("fallback".to_string()),
-                    selection_mode: Some("score".to_string()),
-                    exploration: Some("balanced".to_string()),
-                    fetch_backend: Some("local".to_string()),
+                .web_deep_research(p(WebDeepResearchArgs {
+                    query: "test question".to_string(),
+                    urls: Some(vec![url]),
                     no_network: Some(true),
-                    max_results: Some(1),
                     max_urls: Some(1),
-                    timeout_ms: Some(2_000),
-                    max_bytes: Some(200_000),
-                    width: Some(80),
-                    max_chars: Some(2_000),
-                    top_chunks: Some(1),
-                    max_chunk_chars: Some(200),
-                    include_links: Some(false),
-                    max_links: Some(10),
-                    arxiv_mode: Some("off".to_string()),
-                    arxiv_max_papers: None,
-                    arxiv_categories: None,
-                    arxiv_years: None,
-                    arxiv_timeout_ms: None,
-                    papers_mode: None,
-                    papers_backends: None,
-                    papers_max_papers: None,
-                    papers_years: None,
-                    papers_timeout_ms: None,
-                    papers_include_abstract: None,
-                    model: Some("sonar-deep-research".to_string()),
-                    llm_model: None,
-                    search_mode: Some("on".to_string()),
-                    reasoning_effort: None,
-                    max_tokens: None,
-                    temperature: None,
-                    top_p: None,
-                    max_answer_chars: Some(500),
-                    include_evidence: Some(true),
-                    now_epoch_s: Some(1700000000),
-                    llm_backend: Some("auto".to_string()),
-                })))
+                    timeout_ms: Some(5_000),
+                    include_evidence: Some(false),
+                    llm_backend: Some("openai_compat".to_string()),
+                    suggest_followups: Some(true),
+                    ..Default::default()
+                }))
                 .await
                 .expect("call");
+
             let v = payload_from_call_tool_result(&r);
-            assert_eq!(v["ok"].as_bool(), Some(false));
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert!(v.get("followups").is_none());
+            let codes = v["warning_codes"].as_array().expect("warning_codes");
+            assert!(codes
+                .iter()
+                .any(|c| c.as_str() == Some("followups_unavailable")));
+        }
+
+        #[test]
+        fn deep_research_refined_query_parses_llm_reply_and_falls_back_to_keywords() {
             assert_eq!(
-                v["error"]["code"].as_str(),
-                Some(ErrorCode::NotSupported.as_str())
+                parse_deep_research_refined_query("```json\n{\"query\": \"rust  lifetimes\"}\n```")
+                    .as_deref(),
+                Some("rust lifetimes")
+            );
+            assert_eq!(
+                parse_deep_research_refined_query("rust lifetimes elision\nmore prose").as_deref(),
+                Some("rust lifetimes elision")
             );
+            assert!(parse_deep_research_refined_query("  ").is_none());
+
+            let chunks = vec![serde_json::json!({
+                "text": "The borrow checker enforces borrowing rules. Borrow checker errors mention lifetimes."
+            })];
+            let q = deep_research_offline_refined_query("rust ownership", &chunks).unwrap();
+            assert!(q.starts_with("rust ownership "), "q={q:?}");
+            assert_eq!(q.split_whitespace().nth(2), Some("borrow"), "q={q:?}");
+            assert!(!q["rust ownership".len()..].contains("ownership"));
+            assert!(deep_research_offline_refined_query("q", &[]).is_none());
         }
 
         #[tokio::test]
-        async fn web_deep_research_audit_requires_include_evidence() {
+        async fn web_deep_research_two_passes_refines_query_from_pass1_evidence() {
             let mut keys = Vec::new();
             keys.extend_from_slice(&SEARCH_ENV_KEYS);
             keys.extend_from_slice(&PERPLEXITY_ENV_KEYS);
-            let _env = EnvGuard::new(&keys);
+            keys.extend_from_slice(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_OPENAI_COMPAT_BASE_URL",
+                "WEBPIPE_OPENAI_COMPAT_API_KEY",
+                "WEBPIPE_OPENAI_COMPAT_MODEL",
+            ]);
+            let env = EnvGuard::new(&keys);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
 
-            let svc = WebpipeMcp::new().expect("new");
-            let r = svc
-                .web_deep_research(Parameters(Some(WebDeepResearchArgs {
-                    query: "q".to_string(),
-                    audit: Some(true),
-                    synthesize: Some(false),
-                    urls: Some(vec!["http://example.invalid/fixture".to_string()]),
-                    provider: Some("auto".to_string()),
-                    auto_mode: Some("fallback".to_string()),
-                    selection_mode: Some("score".to_string()),
-                    exploration: Some("balanced".to_string()),
-                    fetch_backend: Some("local".to_string()),
-                    no_network: Some(true),
-                    max_results: Some(1),
-                    max_urls: Some(1),
-                    timeout_ms: Some(2_000),
-                    max_bytes: Some(200_000),
-                    width: Some(80),
-                    max_chars: Some(2_000),
-                    top_chunks: Some(1),
+            use axum::{
+                extract::Query,
+                routing::{get, post},
+                Json, Router,
+            };
+            use std::collections::HashMap;
+            use std::net::SocketAddr;
+            use std::sync::{Arc, Mutex};
+
+            let searches = Arc::new(Mutex::new(Vec::<String>::new()));
+            let searches2 = searches.clone();
+            let refine_prompt = Arc::new(Mutex::new(String::new()));
+            let refine_prompt2 = refine_prompt.clone();
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            let page = |body: &'static str| {
+                move || async move { ([(axum::http::header::CONTENT_TYPE, "text/html")], body) }
+            };
+            let app = Router::new()
+                .route(
+                    "/a",
+                    get(page(
+                        "<html><body><main><h1>Rust ownership</h1><p>Rust ownership means each \
+                         value has one owner. The borrow checker enforces ownership and borrowing \
+                         rules at compile time.</p></main></body></html>",
+                    )),
+                )
+                .route(
+                    "/b",
+                    get(page(
+                        "<html><body><main><h1>Lifetimes</h1><p>Lifetimes tell the borrow \
+                         checker how long references stay valid; elision rules cover the common \
+                         ownership cases.</p></main></body></html>",
+                    )),
+                )
+                .route(
+                    "/search",
+                    get(move |q: Query<HashMap<String, String>>| {
+                        let searches = searches2.clone();
+                        async move {
+                            let q = q.get("q").cloned().unwrap_or_default();
+                            searches.lock().unwrap().push(q.clone());
+                            let path = if q.contains("lifetimes") { "b" } else { "a" };
+                            Json(serde_json::json!({ "results": [
+                                {"url": format!("http://{addr}/{path}"), "title": path, "content": "rust"}
+                            ]}))
+                        }
+                    }),
+                )
+                .route(
+                    "/v1/chat/completions",
+                    post(move |body: Json<serde_json::Value>| {
+                        let refine_prompt = refine_prompt2.clone();
+                        async move {
+                            let sys = body.0["messages"][0]["content"].as_str().unwrap_or("");
+                            let content = if sys.contains("refine") {
+                                *refine_prompt.lock().unwrap() = body.0["messages"][1]["content"]
+                                    .as_str()
+                                    .unwrap_or("")
+                                    .to_string();
+                                r#"{"query": "rust borrow checker lifetimes"}"#.to_string()
+                            } else {
+                                "Synthesis ok.".to_string()
+                            };
+                            Json(serde_json::json!({
+                                "choices": [ { "message": { "role": "assistant", "content": content } } ]
+                            }))
+                        }
+                    }),
+                );
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set("WEBPIPE_SEARXNG_ENDPOINT", &format!("http://{addr}"));
+            env.set("WEBPIPE_OPENAI_COMPAT_BASE_URL", &format!("http://{addr}"));
+            env.set("WEBPIPE_OPENAI_COMPAT_MODEL", "local/test");
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_deep_research(p(WebDeepResearchArgs {
+                    query: "rust ownership".to_string(),
+                    provider: Some("searxng".to_string()),
+                    fetch_backend: Some("local".to_string()),
+                    max_results: Some(3),
+                    max_urls: Some(1),
+                    timeout_ms: Some(5_000),
+                    top_chunks: Some(2),
+                    max_chunk_chars: Some(300),
+                    papers_mode: Some("off".to_string()),
+                    arxiv_mode: Some("off".to_string()),
+                    llm_backend: Some("openai_compat".to_string()),
+                    passes: Some(2),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["passes"].as_u64(), Some(2));
+
+            // Pass 2 searched for the LLM-refined query, which saw pass-1 chunk text.
+            assert_eq!(
+                *searches.lock().unwrap(),
+                vec!["rust ownership", "rust borrow checker lifetimes"]
+            );
+            assert!(refine_prompt
+                .lock()
+                .unwrap()
+                .contains("borrow checker enforces"));
+            let rounds = v["rounds"].as_array().expect("rounds");
+            assert_eq!(rounds.len(), 2);
+            assert_eq!(rounds[0]["refined_by"].as_str(), Some("original"));
+            assert_eq!(
+                rounds[1]["query"].as_str(),
+                Some("rust borrow checker lifetimes")
+            );
+            assert_eq!(rounds[1]["refined_by"].as_str(), Some("openai_compat"));
+
+            // The answer cites evidence from both rounds, and chunks carry their pass.
+            let cites: Vec<&str> = v["answer"]["citations"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|c| c.as_str())
+                .collect();
+            assert_eq!(
+                cites,
+                vec![format!("http://{addr}/a"), format!("http://{addr}/b")]
+            );
+            let passes: Vec<u64> = v["evidence"]["top_chunks"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|c| c["pass"].as_u64())
+                .collect();
+            assert!(
+                passes.contains(&1) && passes.contains(&2),
+                "passes={passes:?}"
+            );
+        }
+
+        #[tokio::test]
+        async fn web_deep_research_synthesizes_through_registered_llm_backend() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.extend_from_slice(&PERPLEXITY_ENV_KEYS);
+            keys.extend_from_slice(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_OPENAI_COMPAT_BASE_URL",
+                "WEBPIPE_OLLAMA_ENABLE",
+            ]);
+            let env = EnvGuard::new(&keys);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+
+            let url = "http://example.invalid/registry".to_string();
+            let html =
+                "<html><body><h1>Registry</h1><p>pluggable backend evidence</p></body></html>";
+            let cache = webpipe_local::FsCache::new(tmp.path().to_path_buf());
+            let req = FetchRequest {
+                url: url.clone(),
+                timeout_ms: Some(2_000),
+                max_bytes: Some(200_000),
+                headers: BTreeMap::new(),
+                method: None,
+                body: None,
+                cache: FetchCachePolicy {
+                    read: true,
+                    write: true,
+                    ttl_s: Some(60),
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
+                },
+            };
+            cache
+                .put(
+                    &req,
+                    &webpipe_core::FetchResponse {
+                        url: url.clone(),
+                        final_url: url.clone(),
+                        status: 200,
+                        content_type: Some("text/html".to_string()),
+                        headers: BTreeMap::new(),
+                        bytes: html.as_bytes().to_vec(),
+                        wire_bytes: html.len() as u64,
+                        truncated: false,
+                        source: FetchSource::Network,
+                        cache_status: None,
+                        body_path: None,
+                        revalidating: false,
+                        timings_ms: BTreeMap::new(),
+                    },
+                )
+                .expect("cache put");
+
+            use std::sync::Mutex;
+            use webpipe_local::llm::{Completion, CompletionOpts, LlmBackend, Prompt};
+            #[derive(Default)]
+            struct Stub {
+                prompts: Mutex<Vec<(String, String, Option<String>)>>,
+            }
+            #[async_trait::async_trait]
+            impl LlmBackend for Stub {
+                async fn complete(
+                    &self,
+                    prompt: &Prompt,
+                    opts: &CompletionOpts,
+                ) -> webpipe_core::Result<Completion> {
+                    self.prompts.lock().unwrap().push((
+                        prompt.system.clone(),
+                        prompt.user.clone(),
+                        opts.model.clone(),
+                    ));
+                    let text = if prompt.system.contains("follow-up") {
+                        r#"[{"question":"Next?","rationale":"gap"}]"#.to_string()
+                    } else {
+                        "Stub synthesis.".to_string()
+                    };
+                    Ok(Completion {
+                        text,
+                        ..Default::default()
+                    })
+                }
+            }
+            let stub = Arc::new(Stub::default());
+            let svc = WebpipeMcp::new()
+                .expect("new")
+                .with_llm_backend("stub", stub.clone());
+
+            let r = svc
+                .web_deep_research(p(WebDeepResearchArgs {
+                    query: "pluggable backend evidence".to_string(),
+                    urls: Some(vec![url.clone()]),
+                    fetch_backend: Some("local".to_string()),
+                    no_network: Some(true),
+                    max_urls: Some(1),
+                    max_bytes: Some(200_000),
+                    top_chunks: Some(2),
+                    include_evidence: Some(false),
+                    llm_backend: Some("stub".to_string()),
+                    llm_model: Some("stub-model".to_string()),
+                    suggest_followups: Some(true),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["provider"].as_str(), Some("stub"));
+            assert_eq!(v["answer"]["text"].as_str(), Some("Stub synthesis."));
+            assert_eq!(v["answer"]["citations"][0].as_str(), Some(url.as_str()));
+            assert_eq!(v["followups"][0]["question"].as_str(), Some("Next?"));
+
+            // Synthesis + follow-up both went through the stub with the evidence pack and llm_model.
+            let prompts = stub.prompts.lock().unwrap().clone();
+            assert_eq!(prompts.len(), 2);
+            assert!(prompts[0].1.contains("pluggable backend evidence"));
+            assert!(prompts.iter().all(|p| p.2.as_deref() == Some("stub-model")));
+            // Usage accounting is recorded once per call, under the registered name.
+            let calls = svc
+                .stats_lock()
+                .llm_backends
+                .get("stub")
+                .map(|u| (u.calls, u.ok));
+            assert_eq!(calls, Some((2, 2)));
+
+            // Unknown names are rejected with the registry's names in the hint.
+            let r = svc
+                .web_deep_research(p(WebDeepResearchArgs {
+                    query: "q".to_string(),
+                    urls: Some(vec![url]),
+                    no_network: Some(true),
+                    llm_backend: Some("nope".to_string()),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["error"]["code"].as_str(), Some("invalid_params"));
+            assert!(v["error"]["hint"].as_str().unwrap().contains("stub"));
+        }
+
+        #[tokio::test]
+        async fn web_deep_research_structured_output_validates_report_citations() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.extend_from_slice(&PERPLEXITY_ENV_KEYS);
+            keys.extend_from_slice(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_OPENAI_COMPAT_BASE_URL",
+                "WEBPIPE_OLLAMA_ENABLE",
+                "WEBPIPE_ANTHROPIC_API_KEY",
+                "ANTHROPIC_API_KEY",
+            ]);
+            let env = EnvGuard::new(&keys);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+
+            let url = "http://example.invalid/report".to_string();
+            let html = "<html><body><h1>Report</h1><p>Structured reports cite numbered sources. Each section lists its citations.</p></body></html>";
+            let cache = webpipe_local::FsCache::new(tmp.path().to_path_buf());
+            let req = FetchRequest {
+                url: url.clone(),
+                timeout_ms: Some(2_000),
+                max_bytes: Some(200_000),
+                headers: BTreeMap::new(),
+                method: None,
+                body: None,
+                cache: FetchCachePolicy {
+                    read: true,
+                    write: true,
+                    ttl_s: Some(60),
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
+                },
+            };
+            cache
+                .put(
+                    &req,
+                    &webpipe_core::FetchResponse {
+                        url: url.clone(),
+                        final_url: url.clone(),
+                        status: 200,
+                        content_type: Some("text/html".to_string()),
+                        headers: BTreeMap::new(),
+                        bytes: html.as_bytes().to_vec(),
+                        wire_bytes: html.len() as u64,
+                        truncated: false,
+                        source: FetchSource::Network,
+                        cache_status: None,
+                        body_path: None,
+                        revalidating: false,
+                        timings_ms: BTreeMap::new(),
+                    },
+                )
+                .expect("cache put");
+
+            use std::sync::Mutex;
+            use webpipe_local::llm::{Completion, CompletionOpts, LlmBackend, Prompt};
+            /// Replies with `synthesis` to the report prompt and `repair` to the repair prompt.
+            struct Stub {
+                synthesis: String,
+                repair: String,
+                prompts: Mutex<Vec<(String, String)>>,
+            }
+            #[async_trait::async_trait]
+            impl LlmBackend for Stub {
+                async fn complete(
+                    &self,
+                    prompt: &Prompt,
+                    _opts: &CompletionOpts,
+                ) -> webpipe_core::Result<Completion> {
+                    self.prompts
+                        .lock()
+                        .unwrap()
+                        .push((prompt.system.clone(), prompt.user.clone()));
+                    let text = if prompt.system.contains("repair") {
+                        self.repair.clone()
+                    } else {
+                        self.synthesis.clone()
+                    };
+                    Ok(Completion {
+                        text,
+                        ..Default::default()
+                    })
+                }
+            }
+            let good = format!(
+                r#"Here you go: {{"summary":"Reports cite sources [1].","sections":[{{"heading":"Citations","content":"Each section lists its citations.","citations":[1]}}],"sources":[{{"n":1,"url":"{url}","title":"Report"}}]}}"#
+            );
+            let bad_citation =
+                r#"{"summary":"s","sections":[{"heading":"h","content":"c","citations":[7]}]}"#;
+            let args = |backend: &str, synthesize: bool| WebDeepResearchArgs {
+                query: "structured reports citations".to_string(),
+                urls: Some(vec![url.clone()]),
+                fetch_backend: Some("local".to_string()),
+                no_network: Some(true),
+                max_urls: Some(1),
+                max_bytes: Some(200_000),
+                top_chunks: Some(2),
+                include_evidence: Some(false),
+                llm_backend: Some(backend.to_string()),
+                synthesize: Some(synthesize),
+                structured_output: Some(true),
+                ..Default::default()
+            };
+
+            // A valid reply is validated as-is; sources are the server's numbered evidence URLs.
+            let stub = Arc::new(Stub {
+                synthesis: good.clone(),
+                repair: String::new(),
+                prompts: Mutex::new(Vec::new()),
+            });
+            let svc = WebpipeMcp::new()
+                .expect("new")
+                .with_llm_backend("stub", stub.clone());
+            let v = payload_from_call_tool_result(
+                &svc.web_deep_research(p(args("stub", true)))
+                    .await
+                    .expect("call"),
+            );
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["structured_output"].as_bool(), Some(true));
+            assert_eq!(v["report_source"].as_str(), Some("llm"));
+            assert_eq!(
+                v["report"]["summary"].as_str(),
+                Some("Reports cite sources [1].")
+            );
+            assert_eq!(
+                v["report"]["sections"][0]["citations"],
+                serde_json::json!([1])
+            );
+            assert_eq!(v["report"]["sources"][0]["n"].as_u64(), Some(1));
+            assert_eq!(
+                v["report"]["sources"][0]["url"].as_str(),
+                Some(url.as_str())
+            );
+            assert!(v["answer"]["text"]
+                .as_str()
+                .unwrap()
+                .contains("## Citations"));
+            {
+                let prompts = stub.prompts.lock().unwrap();
+                assert_eq!(prompts.len(), 1);
+                assert!(prompts[0].0.contains("\"citations\""));
+                assert!(prompts[0].1.contains(&format!(r#""url":"{url}""#)));
+            }
+
+            // A citation to an unknown source triggers exactly one repair call.
+            let stub = Arc::new(Stub {
+                synthesis: bad_citation.to_string(),
+                repair: good.clone(),
+                prompts: Mutex::new(Vec::new()),
+            });
+            let svc = WebpipeMcp::new()
+                .expect("new")
+                .with_llm_backend("stub", stub.clone());
+            let v = payload_from_call_tool_result(
+                &svc.web_deep_research(p(args("stub", true)))
+                    .await
+                    .expect("call"),
+            );
+            assert_eq!(
+                v["report"]["sections"][0]["citations"],
+                serde_json::json!([1])
+            );
+            assert!(v["warning_codes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|c| c == "structured_report_repaired"));
+            {
+                let prompts = stub.prompts.lock().unwrap();
+                assert_eq!(prompts.len(), 2);
+                assert!(prompts[1].1.contains("not a listed source number"));
+            }
+
+            // If the repair is invalid too, the report is omitted and the raw reply kept.
+            let stub = Arc::new(Stub {
+                synthesis: bad_citation.to_string(),
+                repair: "still not json".to_string(),
+                prompts: Mutex::new(Vec::new()),
+            });
+            let svc = WebpipeMcp::new()
+                .expect("new")
+                .with_llm_backend("stub", stub.clone());
+            let v = payload_from_call_tool_result(
+                &svc.web_deep_research(p(args("stub", true)))
+                    .await
+                    .expect("call"),
+            );
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert!(v.get("report").is_none(), "payload={v}");
+            assert_eq!(v["answer"]["text"].as_str(), Some(bad_citation));
+            assert!(v["warning_codes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|c| c == "structured_report_invalid"));
+
+            // Offline (auto, nothing configured) and synthesize=false build the same shape extractively.
+            for (backend, synthesize) in [("auto", true), ("stub", false)] {
+                let v = payload_from_call_tool_result(
+                    &svc.web_deep_research(p(args(backend, synthesize)))
+                        .await
+                        .expect("call"),
+                );
+                assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+                assert_eq!(v["report_source"].as_str(), Some("extractive"));
+                let report = &v["report"];
+                assert!(report["summary"]
+                    .as_str()
+                    .unwrap()
+                    .contains("Structured reports cite numbered sources. [1]"));
+                assert_eq!(report["sources"][0]["url"].as_str(), Some(url.as_str()));
+                let sections = report["sections"].as_array().unwrap();
+                assert_eq!(sections.len(), 1);
+                assert_eq!(sections[0]["citations"], serde_json::json!([1]));
+                // The extractive shape passes the same validation as an LLM reply.
+                let sources = report["sources"].as_array().unwrap().clone();
+                assert!(validate_deep_research_report(&report.to_string(), &sources).is_ok());
+            }
+        }
+
+        #[tokio::test]
+        async fn web_deep_research_anthropic_backend_calls_messages_api() {
+            use axum::{http::HeaderMap, routing::post, Json, Router};
+            use std::sync::Mutex;
+
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.extend_from_slice(&PERPLEXITY_ENV_KEYS);
+            keys.extend_from_slice(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_ANTHROPIC_API_KEY",
+                "ANTHROPIC_API_KEY",
+                "WEBPIPE_ANTHROPIC_MODEL",
+                "WEBPIPE_ANTHROPIC_BASE_URL",
+            ]);
+            let env = EnvGuard::new(&keys);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+
+            let url = "http://example.invalid/claude".to_string();
+            let html = "<html><body><h1>Claude</h1><p>messages api evidence</p></body></html>";
+            let cache = webpipe_local::FsCache::new(tmp.path().to_path_buf());
+            let req = FetchRequest {
+                url: url.clone(),
+                timeout_ms: Some(2_000),
+                max_bytes: Some(200_000),
+                headers: BTreeMap::new(),
+                method: None,
+                body: None,
+                cache: FetchCachePolicy {
+                    read: true,
+                    write: true,
+                    ttl_s: Some(60),
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
+                },
+            };
+            cache
+                .put(
+                    &req,
+                    &webpipe_core::FetchResponse {
+                        url: url.clone(),
+                        final_url: url.clone(),
+                        status: 200,
+                        content_type: Some("text/html".to_string()),
+                        headers: BTreeMap::new(),
+                        bytes: html.as_bytes().to_vec(),
+                        wire_bytes: html.len() as u64,
+                        truncated: false,
+                        source: FetchSource::Network,
+                        cache_status: None,
+                        body_path: None,
+                        revalidating: false,
+                        timings_ms: BTreeMap::new(),
+                    },
+                )
+                .expect("cache put");
+
+            // Not configured: no key.
+            let svc = WebpipeMcp::new().expect("new");
+            let args = || WebDeepResearchArgs {
+                query: "messages api evidence".to_string(),
+                urls: Some(vec![url.clone()]),
+                fetch_backend: Some("local".to_string()),
+                max_urls: Some(1),
+                max_bytes: Some(200_000),
+                top_chunks: Some(2),
+                include_evidence: Some(false),
+                llm_backend: Some("anthropic".to_string()),
+                llm_model: Some("claude-test".to_string()),
+                max_tokens: Some(256),
+                temperature: Some(0.2),
+                top_p: Some(0.9),
+                ..Default::default()
+            };
+            let r = svc.web_deep_research(p(args())).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(false), "payload={v}");
+            assert_eq!(v["error"]["code"].as_str(), Some("not_configured"));
+            assert!(v["error"]["hint"]
+                .as_str()
+                .unwrap()
+                .contains("WEBPIPE_ANTHROPIC_API_KEY"));
+
+            let seen: Arc<Mutex<Option<(HeaderMap, serde_json::Value)>>> =
+                Arc::new(Mutex::new(None));
+            let seen2 = seen.clone();
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("bind");
+            let addr = listener.local_addr().expect("addr");
+            let app = Router::new().route(
+                "/v1/messages",
+                post(move |headers: HeaderMap, body: Json<serde_json::Value>| {
+                    let seen = seen2.clone();
+                    async move {
+                        *seen.lock().unwrap() = Some((headers, body.0));
+                        Json(serde_json::json!({
+                            "id": "msg_1",
+                            "type": "message",
+                            "role": "assistant",
+                            "model": "claude-test",
+                            "content": [
+                                {"type": "text", "text": "Claude "},
+                                {"type": "text", "text": "synthesis."}
+                            ],
+                            "stop_reason": "end_turn",
+                            "usage": {"input_tokens": 40, "output_tokens": 5}
+                        }))
+                    }
+                }),
+            );
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set("WEBPIPE_ANTHROPIC_API_KEY", "sk-ant-test");
+            env.set("WEBPIPE_ANTHROPIC_BASE_URL", &format!("http://{addr}"));
+
+            let r = svc.web_deep_research(p(args())).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["provider"].as_str(), Some("anthropic"));
+            assert_eq!(v["answer"]["text"].as_str(), Some("Claude synthesis."));
+            assert_eq!(v["answer"]["citations"][0].as_str(), Some(url.as_str()));
+            assert_eq!(v["usage"]["prompt_tokens"].as_u64(), Some(40));
+            assert_eq!(v["usage"]["completion_tokens"].as_u64(), Some(5));
+            assert_eq!(v["usage"]["total_tokens"].as_u64(), Some(45));
+
+            let (headers, body) = seen.lock().unwrap().clone().expect("stub was called");
+            assert_eq!(
+                headers.get("x-api-key").and_then(|h| h.to_str().ok()),
+                Some("sk-ant-test")
+            );
+            assert_eq!(
+                headers
+                    .get("anthropic-version")
+                    .and_then(|h| h.to_str().ok()),
+                Some("2023-06-01")
+            );
+            assert_eq!(body["model"].as_str(), Some("claude-test"));
+            assert_eq!(body["max_tokens"].as_u64(), Some(256));
+            assert_eq!(body["temperature"].as_f64(), Some(0.2));
+            assert_eq!(body["top_p"].as_f64(), Some(0.9));
+            assert!(body["system"].as_str().is_some_and(|s| !s.is_empty()));
+            let msgs = body["messages"].as_array().expect("messages");
+            assert_eq!(msgs.len(), 1);
+            assert_eq!(msgs[0]["role"].as_str(), Some("user"));
+            assert!(msgs[0]["content"]
+                .as_str()
+                .unwrap()
+                .contains("messages api evidence"));
+        }
+
+        #[tokio::test]
+        async fn web_deep_research_openai_compat_rejects_non_localhost_in_no_network_mode() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.extend_from_slice(&PERPLEXITY_ENV_KEYS);
+            keys.extend_from_slice(&[
+                "WEBPIPE_OPENAI_COMPAT_BASE_URL",
+                "WEBPIPE_OPENAI_COMPAT_MODEL",
+            ]);
+            let env = EnvGuard::new(&keys);
+            env.set("WEBPIPE_OPENAI_COMPAT_BASE_URL", "https://example.com");
+            env.set("WEBPIPE_OPENAI_COMPAT_MODEL", "x");
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_deep_research(Parameters(Some(WebDeepResearchArgs {
+                    query: "q".to_string(),
+                    audit: None,
+                    synthesize: None,
+                    urls: Some(vec!["http://example.invalid/fixture".to_string()]),
+                    provider: Some("auto".to_string()),
+                    auto_mode: Some("fallback".to_string()),
+                    selection_mode: None,
+                    exploration: None,
+                    fetch_backend: Some("local".to_string()),
+                    no_network: Some(true),
+                    max_results: Some(1),
+                    max_urls: Some(1),
+                    timeout_ms: Some(2_000),
+                    max_bytes: Some(200_000),
+                    width: Some(80),
+                    max_chars: Some(2_000),
+                    top_chunks: Some(1),
+                    max_chunk_chars: Some(200),
+                    include_links: Some(false),
+                    max_links: Some(10),
+                    arxiv_mode: None,
+                    arxiv_max_papers: None,
+                    arxiv_categories: None,
+                    arxiv_years: None,
+                    arxiv_timeout_ms: None,
+                    papers_mode: None,
+                    papers_backends: None,
+                    papers_max_papers: None,
+                    papers_years: None,
+                    papers_timeout_ms: None,
+                    papers_include_abstract: None,
+                    model: Some("sonar-deep-research".to_string()),
+                    llm_model: Some("x".to_string()),
+                    search_mode: None,
+                    reasoning_effort: None,
+                    max_tokens: None,
+                    temperature: None,
+                    top_p: None,
+                    max_answer_chars: Some(500),
+                    include_evidence: Some(false),
+                    now_epoch_s: Some(1700000000),
+                    llm_backend: Some("openai_compat".to_string()),
+                    suggest_followups: None,
+                    passes: None,
+                    structured_output: None,
+                })))
+                .await
+                .expect("call");
+
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(false));
+            assert_eq!(
+                v["error"]["code"].as_str(),
+                Some(ErrorCode::NotSupported.as_str())
+            );
+        }
+
+        #[tokio::test]
+        async fn web_deep_research_audit_rejects_search_mode() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.extend_from_slice(&PERPLEXITY_ENV_KEYS);
+            let _env = EnvGuard::new(&keys);
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_deep_research(Parameters(Some(WebDeepResearchArgs {
+                    query: "q".to_string(),
+                    audit: Some(true),
+                    synthesize: Some(false),
+                    urls: Some(vec!["http://example.invalid/fixture".to_string()]),
+                    provider: Some("auto".to_string()),
+                    auto_mode: Some("fallback".to_string()),
+                    selection_mode: Some("score".to_string()),
+                    exploration: Some("balanced".to_string()),
+                    fetch_backend: Some("local".to_string()),
+                    no_network: Some(true),
+                    max_results: Some(1),
+                    max_urls: Some(1),
+                    timeout_ms: Some(2_000),
+                    max_bytes: Some(200_000),
+                    width: Some(80),
+                    max_chars: Some(2_000),
+                    top_chunks: Some(1),
+                    max_chunk_chars: Some(200),
+                    include_links: Some(false),
+                    max_links: Some(10),
+                    arxiv_mode: Some("off".to_string()),
+                    arxiv_max_papers: None,
+                    arxiv_categories: None,
+                    arxiv_years: None,
+                    arxiv_timeout_ms: None,
+                    papers_mode: None,
+                    papers_backends: None,
+                    papers_max_papers: None,
+                    papers_years: None,
+                    papers_timeout_ms: None,
+                    papers_include_abstract: None,
+                    model: Some("sonar-deep-research".to_string()),
+                    llm_model: None,
+                    search_mode: Some("on".to_string()),
+                    reasoning_effort: None,
+                    max_tokens: None,
+                    temperature: None,
+                    top_p: None,
+                    max_answer_chars: Some(500),
+                    include_evidence: Some(true),
+                    now_epoch_s: Some(1700000000),
+                    llm_backend: Some("auto".to_string()),
+                    suggest_followups: None,
+                    passes: None,
+                    structured_output: None,
+                })))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(false));
+            assert_eq!(
+                v["error"]["code"].as_str(),
+                Some(ErrorCode::NotSupported.as_str())
+            );
+        }
+
+        #[tokio::test]
+        async fn web_deep_research_audit_requires_include_evidence() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.extend_from_slice(&PERPLEXITY_ENV_KEYS);
+            let _env = EnvGuard::new(&keys);
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_deep_research(Parameters(Some(WebDeepResearchArgs {
+                    query: "q".to_string(),
+                    audit: Some(true),
+                    synthesize: Some(false),
+                    urls: Some(vec!["http://example.invalid/fixture".to_string()]),
+                    provider: Some("auto".to_string()),
+                    auto_mode: Some("fallback".to_string()),
+                    selection_mode: Some("score".to_string()),
+                    exploration: Some("balanced".to_string()),
+                    fetch_backend: Some("local".to_string()),
+                    no_network: Some(true),
+                    max_results: Some(1),
+                    max_urls: Some(1),
+                    timeout_ms: Some(2_000),
+                    max_bytes: Some(200_000),
+                    width: Some(80),
+                    max_chars: Some(2_000),
+                    top_chunks: Some(1),
                     max_chunk_chars: Some(200),
                     include_links: Some(false),
                     max_links: Some(10),
@@ -23241,6 +32176,9 @@ Rules:
                     include_evidence: Some(false),
                     now_epoch_s: Some(1700000000),
                     llm_backend: Some("auto".to_string()),
+                    suggest_followups: None,
+                    passes: None,
+                    structured_output: None,
                 })))
                 .await
                 .expect("call");
@@ -23367,6 +32305,9 @@ Rules:
                     include_evidence: Some(true),
                     now_epoch_s: Some(1700000000),
                     llm_backend: Some("perplexity".to_string()),
+                    suggest_followups: None,
+                    passes: None,
+                    structured_output: None,
                 })))
                 .await
                 .expect("call");
@@ -23450,37 +32391,43 @@ Rules:
         }
 
         #[tokio::test]
-        async fn web_search_auto_fallback_can_choose_tavily_first_when_brave_is_unhealthy() {
+        async fn web_search_merge_order_is_independent_of_provider_timing() {
             let mut keys = Vec::new();
             keys.extend_from_slice(&SEARCH_ENV_KEYS);
-            keys.extend_from_slice(&["WEBPIPE_BRAVE_ENDPOINT", "WEBPIPE_TAVILY_ENDPOINT"]);
-            keys.extend_from_slice(&["WEBPIPE_ROUTING_CONTEXT", "WEBPIPE_ROUTING_WINDOW"]);
+            keys.push("WEBPIPE_BRAVE_ENDPOINT");
             let env = EnvGuard::new(&keys);
-            env.set("WEBPIPE_ROUTING_CONTEXT", "global");
-            env.set("WEBPIPE_ROUTING_WINDOW", "50");
 
-            // Local stub search endpoints: both succeed.
-            use axum::{routing::get, routing::post, Json, Router};
+            use axum::{extract::Query, routing::get, Router};
+            use std::collections::HashMap;
             use std::net::SocketAddr;
+            use std::time::Duration;
+
+            // The query names the provider that answers first; the other one stalls.
+            let stall = |q: &HashMap<String, String>, me: &str| {
+                let first = q.get("q").map(|s| s.as_str()) == Some(me);
+                Duration::from_millis(if first { 0 } else { 150 })
+            };
             let app = Router::new()
                 .route(
-                    "/brave",
-                    get(|| async {
-                        (
-                            [(axum::http::header::CONTENT_TYPE, "application/json")],
-                            r#"{"web":{"results":[{"url":"https://example.com","title":"Example","description":"Hello"}]}}"#,
-                        )
+                    "/search",
+                    get(move |q: Query<HashMap<String, String>>| async move {
+                        tokio::time::sleep(stall(&q, "searxng")).await;
+                        axum::Json(serde_json::json!({ "results": [
+                            {"url": "https://b.example/x", "title": "B"},
+                            {"url": "https://shared.example/one#top", "title": "Shared (searxng)"},
+                            {"url": "https://a.example/y", "title": "A"}
+                        ]}))
                     }),
                 )
                 .route(
-                    "/tavily",
-                    post(|_body: Json<serde_json::Value>| async move {
-                        Json(serde_json::json!({
-                            "results": [
-                                {"url":"https://example.com","title":"Example","content":"Hello"}
-                            ],
-                            "usage": { "credits": 1 }
-                        }))
+                    "/brave",
+                    get(move |q: Query<HashMap<String, String>>| async move {
+                        tokio::time::sleep(stall(&q, "brave")).await;
+                        axum::Json(serde_json::json!({"web":{"results":[
+                            {"url":"https://shared.example/one","title":"Shared (brave)","description":"d"},
+                            {"url":"https://c.example/z","title":"C","description":"d"},
+                            {"url":"https://d.example/w","title":"D","description":"d"}
+                        ]}}))
                     }),
                 );
             let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -23488,30 +32435,715 @@ Rules:
             tokio::spawn(async move {
                 axum::serve(listener, app).await.expect("axum serve");
             });
-
+            env.set("WEBPIPE_SEARXNG_ENDPOINT", &format!("http://{addr}"));
             env.set("WEBPIPE_BRAVE_API_KEY", "dummy");
-            env.set("WEBPIPE_TAVILY_API_KEY", "dummy");
             env.set("WEBPIPE_BRAVE_ENDPOINT", &format!("http://{addr}/brave"));
-            env.set("WEBPIPE_TAVILY_ENDPOINT", &format!("http://{addr}/tavily"));
 
             let svc = WebpipeMcp::new().expect("new");
-
-            // Seed global stats: Brave is currently rate-limiting; Tavily is healthy.
-            for _ in 0..10 {
-                svc.stats_record_search_provider_qk("brave", false, 0, 10, Some("HTTP 429"), None);
-            }
-            for _ in 0..10 {
-                svc.stats_record_search_provider_qk("tavily", true, 1, 10, None, None);
-            }
-
-            let r = svc
-                .web_search(p(WebSearchArgs {
+            let run = |first: &str| {
+                let call = svc.web_search(p(WebSearchArgs {
+                    query: Some(first.to_string()),
                     provider: Some("auto".to_string()),
-                    auto_mode: Some("fallback".to_string()),
-                    query: Some("q".to_string()),
-                    country: None,
-                    language: None,
-                    max_results: Some(1),
+                    auto_mode: Some("merge".to_string()),
+                    max_results: Some(10),
+                    ..Default::default()
+                }));
+                async move {
+                    let v = payload_from_call_tool_result(&call.await.expect("call"));
+                    assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+                    serde_json::to_string(&v["results"]).unwrap()
+                }
+            };
+            let a = run("brave").await;
+            let b = run("searxng").await;
+            assert_eq!(a, b);
+
+            let results: serde_json::Value = serde_json::from_str(&a).unwrap();
+            let urls: Vec<&str> = results
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["url"].as_str().unwrap())
+                .collect();
+            // Agreement first (and the best-ranked provider's entry), then rank, then URL.
+            assert_eq!(
+                urls,
+                [
+                    "https://shared.example/one",
+                    "https://b.example/x",
+                    "https://c.example/z",
+                    "https://a.example/y",
+                    "https://d.example/w",
+                ]
+            );
+            assert_eq!(results[0]["source"].as_str(), Some("brave"));
+        }
+
+        #[tokio::test]
+        async fn web_related_derives_query_from_page_and_excludes_source_domain() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.push("WEBPIPE_CACHE_DIR");
+            let env = EnvGuard::new(&keys);
+
+            use axum::{extract::Query, routing::get, Router};
+            use std::collections::HashMap;
+            use std::net::SocketAddr;
+            use std::sync::{Arc, Mutex};
+
+            let seen_query = Arc::new(Mutex::new(String::new()));
+            let seen_query2 = seen_query.clone();
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            let app = Router::new()
+                .route(
+                    "/article",
+                    get(|| async {
+                        (
+                            [(axum::http::header::CONTENT_TYPE, "text/html")],
+                            "<html><head><title>Tokamak Plasma Confinement | Fusion Blog</title></head>\
+                             <body><main><h1>Tokamak Plasma Confinement</h1>\
+                             <p>Magnetic fields hold the plasma inside the tokamak. Stellarator \
+                             designs twist the field instead; stellarator coils are harder to build.</p>\
+                             </main></body></html>",
+                        )
+                    }),
+                )
+                .route(
+                    "/search",
+                    get(move |q: Query<HashMap<String, String>>| {
+                        let seen = seen_query2.clone();
+                        async move {
+                            *seen.lock().unwrap() = q.get("q").cloned().unwrap_or_default();
+                            axum::Json(serde_json::json!({ "results": [
+                                {"url": format!("http://{addr}/other"), "title": "Same site"},
+                                {"url": "https://fusion.example/a", "title": "A", "content": "a"},
+                                {"url": "https://iter.example/b", "title": "B", "content": "b"}
+                            ]}))
+                        }
+                    }),
+                );
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set("WEBPIPE_SEARXNG_ENDPOINT", &format!("http://{addr}"));
+            let tmp = tempfile::tempdir().unwrap();
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_related(p(WebRelatedArgs {
+                    url: Some(format!("http://{addr}/article")),
+                    provider: Some("searxng".to_string()),
+                    max_results: Some(5),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["kind"].as_str(), Some("web_related"));
+            let q = v["derived_query"]["query"].as_str().unwrap();
+            for term in ["tokamak", "plasma", "confinement", "stellarator"] {
+                assert!(q.split_whitespace().any(|t| t == term), "query={q:?}");
+            }
+            assert!(!q.contains("blog"), "site-name suffix leaked: {q:?}");
+            assert_eq!(*seen_query.lock().unwrap(), q);
+
+            let urls: Vec<&str> = v["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|r| r["url"].as_str())
+                .collect();
+            assert_eq!(
+                urls,
+                vec!["https://fusion.example/a", "https://iter.example/b"]
+            );
+            assert_eq!(v["excluded_count"].as_u64(), Some(1));
+            assert_eq!(v["source_domain"].as_str(), Some("127.0.0.1"));
+
+            // Invalid URLs fail before any fetch/search.
+            let r = svc
+                .web_related(p(WebRelatedArgs {
+                    url: Some("not a url".to_string()),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(false));
+            assert_eq!(v["error"]["code"].as_str(), Some("invalid_url"));
+        }
+
+        #[tokio::test]
+        async fn web_summarize_condenses_page_chunks_through_llm_backend() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&PERPLEXITY_ENV_KEYS);
+            keys.extend_from_slice(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_ANTHROPIC_API_KEY",
+                "ANTHROPIC_API_KEY",
+                "WEBPIPE_OPENAI_COMPAT_BASE_URL",
+                "WEBPIPE_OLLAMA_ENABLE",
+            ]);
+            let env = EnvGuard::new(&keys);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+            let app = Router::new().route(
+                "/tides",
+                get(|| async {
+                    axum::response::Html(
+                        "<html><body><h1>Tides</h1>\
+                         <p>Tide tables predict the height of the water at a harbour for every hour of the day.</p>\
+                         <p>Spring tides happen near new and full moon, when the sun and moon pull together.</p>\
+                         </body></html>",
+                    )
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            use std::sync::Mutex;
+            use webpipe_local::llm::{Completion, CompletionOpts, LlmBackend, Prompt};
+            #[derive(Default)]
+            struct Stub {
+                prompts: Mutex<Vec<Prompt>>,
+            }
+            #[async_trait::async_trait]
+            impl LlmBackend for Stub {
+                async fn complete(
+                    &self,
+                    prompt: &Prompt,
+                    _opts: &CompletionOpts,
+                ) -> webpipe_core::Result<Completion> {
+                    self.prompts.lock().unwrap().push(prompt.clone());
+                    Ok(Completion {
+                        text: "Summary:\n- Tide tables give hourly water heights.\n- Spring tides follow new and full moon.\n- Extra."
+                            .to_string(),
+                        ..Default::default()
+                    })
+                }
+            }
+            let stub = Arc::new(Stub::default());
+            let svc = WebpipeMcp::new()
+                .expect("new")
+                .with_llm_backend("stub", stub.clone());
+
+            let r = svc
+                .web_summarize(p(WebSummarizeArgs {
+                    url: Some(format!("http://{addr}/tides")),
+                    llm_backend: Some("stub".to_string()),
+                    max_bullets: Some(2),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["kind"].as_str(), Some("web_summarize"));
+            assert_eq!(v["provider"].as_str(), Some("stub"));
+            assert_eq!(
+                v["summary"]["bullets"],
+                serde_json::json!([
+                    "Tide tables give hourly water heights.",
+                    "Spring tides follow new and full moon."
+                ])
+            );
+            assert_eq!(v["summary"]["truncated"].as_bool(), Some(true));
+            let chunks = v["chunks"].as_array().expect("chunks");
+            assert!(!chunks.is_empty());
+            assert_eq!(chunks[0]["index"].as_u64(), Some(1));
+            {
+                let prompts = stub.prompts.lock().unwrap();
+                assert_eq!(prompts.len(), 1);
+                assert!(prompts[0].system.contains("at most 2 bullet points"));
+                assert!(prompts[0].user.contains("[1] "));
+                assert!(prompts[0]
+                    .user
+                    .contains("Spring tides happen near new and full moon"));
+            }
+            {
+                let s = svc.stats_lock();
+                assert_eq!(
+                    s.llm_backends.get("stub").map(|u| (u.calls, u.ok)),
+                    Some((1, 1))
+                );
+                assert!(s.fetch_backends.get("local").is_some_and(|u| u.calls >= 1));
+            }
+
+            // Text input skips the fetch; max_answer_chars bounds the bullets.
+            let r = svc
+                .web_summarize(p(WebSummarizeArgs {
+                    text: Some(
+                        "Tide tables predict the height of the water at a harbour for every hour."
+                            .to_string(),
+                    ),
+                    llm_backend: Some("stub".to_string()),
+                    max_answer_chars: Some(20),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(
+                v["summary"]["bullets"],
+                serde_json::json!(["Tide tables give hou"])
+            );
+            assert_eq!(v["summary"]["truncated"].as_bool(), Some(true));
+            assert_eq!(v["url"], serde_json::Value::Null);
+
+            // no_network: hosted backends are refused, and auto with nothing local configured errors.
+            env.set("WEBPIPE_PERPLEXITY_API_KEY", "dummy");
+            for (backend, code) in [("perplexity", "not_supported"), ("auto", "not_configured")] {
+                let r = svc
+                    .web_summarize(p(WebSummarizeArgs {
+                        text: Some("some text to summarize".to_string()),
+                        llm_backend: Some(backend.to_string()),
+                        no_network: Some(true),
+                        ..Default::default()
+                    }))
+                    .await
+                    .expect("call");
+                let v = payload_from_call_tool_result(&r);
+                assert_eq!(v["ok"].as_bool(), Some(false), "{backend}: {v}");
+                assert_eq!(v["error"]["code"].as_str(), Some(code), "{backend}: {v}");
+            }
+
+            // Exactly one of url / text.
+            let r = svc
+                .web_summarize(p(WebSummarizeArgs {
+                    url: Some("https://example.com/".to_string()),
+                    text: Some("x".to_string()),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["error"]["code"].as_str(), Some("invalid_params"));
+        }
+
+        #[tokio::test]
+        async fn web_site_search_scopes_query_and_keeps_only_on_domain_results() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.push("WEBPIPE_TAVILY_ENDPOINT");
+            let env = EnvGuard::new(&keys);
+
+            use axum::{extract::Query, routing::get, routing::post, Json, Router};
+            use std::collections::HashMap;
+            use std::net::SocketAddr;
+            use std::sync::{Arc, Mutex};
+
+            // Both stubs ignore the filter and return a mix of on- and off-domain hits.
+            let mixed = serde_json::json!({ "results": [
+                {"url": "https://docs.rs/serde/latest/serde/", "title": "serde", "content": "a"},
+                {"url": "https://evil-docs.rs.example/serde", "title": "lookalike", "content": "b"},
+                {"url": "https://crates.io/crates/serde", "title": "crates.io", "content": "c"},
+                {"url": "https://api.docs.rs/serde_json", "title": "subdomain", "content": "d"},
+                {"url": "https://notdocs.rs/serde", "title": "suffix", "content": "e"}
+            ]});
+            let seen_q = Arc::new(Mutex::new(String::new()));
+            let seen_tavily = Arc::new(Mutex::new(serde_json::Value::Null));
+            let (seen_q2, seen_tavily2) = (seen_q.clone(), seen_tavily.clone());
+            let (mixed2, mixed3) = (mixed.clone(), mixed.clone());
+            let app = Router::new()
+                .route(
+                    "/search",
+                    get(move |q: Query<HashMap<String, String>>| {
+                        let (seen, body) = (seen_q2.clone(), mixed2.clone());
+                        async move {
+                            *seen.lock().unwrap() = q.get("q").cloned().unwrap_or_default();
+                            Json(body)
+                        }
+                    }),
+                )
+                .route(
+                    "/tavily",
+                    post(move |b: Json<serde_json::Value>| {
+                        let (seen, body) = (seen_tavily2.clone(), mixed3.clone());
+                        async move {
+                            *seen.lock().unwrap() = b.0;
+                            Json(body)
+                        }
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set("WEBPIPE_SEARXNG_ENDPOINT", &format!("http://{addr}"));
+            env.set("WEBPIPE_TAVILY_API_KEY", "dummy");
+            env.set("WEBPIPE_TAVILY_ENDPOINT", &format!("http://{addr}/tavily"));
+
+            let on_domain_urls = |v: &serde_json::Value| -> Vec<String> {
+                v["results"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .filter_map(|r| r["url"].as_str().map(|s| s.to_string()))
+                    .collect()
+            };
+            let svc = WebpipeMcp::new().expect("new");
+
+            // SearXNG has no native domain filter: `site:` goes into the query, and the
+            // response is post-filtered.
+            let r = svc
+                .web_site_search(p(WebSiteSearchArgs {
+                    domain: Some("https://Docs.rs/some/path".to_string()),
+                    query: Some("serde derive".to_string()),
+                    provider: Some("searxng".to_string()),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["kind"].as_str(), Some("web_site_search"));
+            assert_eq!(v["domain"].as_str(), Some("docs.rs"));
+            assert_eq!(v["site_filter"].as_str(), Some("query_operator"));
+            assert_eq!(*seen_q.lock().unwrap(), "site:docs.rs serde derive");
+            assert_eq!(
+                on_domain_urls(&v),
+                vec![
+                    "https://docs.rs/serde/latest/serde/",
+                    "https://api.docs.rs/serde_json"
+                ]
+            );
+            assert_eq!(v["filtered_count"].as_u64(), Some(3));
+
+            // Tavily filters natively (include_domains, query untouched); results are still
+            // post-filtered.
+            let r = svc
+                .web_site_search(p(WebSiteSearchArgs {
+                    domain: Some("site:docs.rs".to_string()),
+                    query: Some("serde derive".to_string()),
+                    provider: Some("tavily".to_string()),
+                    max_results: Some(1),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["site_filter"].as_str(), Some("native"));
+            {
+                let body = seen_tavily.lock().unwrap();
+                assert_eq!(body["query"].as_str(), Some("serde derive"));
+                assert_eq!(body["include_domains"], serde_json::json!(["docs.rs"]));
+            }
+            assert_eq!(
+                on_domain_urls(&v),
+                vec!["https://docs.rs/serde/latest/serde/"]
+            );
+
+            // A missing/invalid domain fails before any search.
+            let r = svc
+                .web_site_search(p(WebSiteSearchArgs {
+                    domain: Some("not a domain".to_string()),
+                    query: Some("q".to_string()),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(false));
+            assert_eq!(v["error"]["code"].as_str(), Some("invalid_params"));
+        }
+
+        #[tokio::test]
+        async fn web_search_cost_cascade_escalates_to_paid_only_on_poor_free_results() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.extend_from_slice(&[
+                "WEBPIPE_BRAVE_ENDPOINT",
+                "WEBPIPE_COST_CASCADE_MIN_RESULTS",
+                "WEBPIPE_COST_CASCADE_MAX_JUNK_FRAC",
+            ]);
+            let env = EnvGuard::new(&keys);
+
+            use axum::{extract::Query, routing::get, Router};
+            use std::collections::HashMap;
+            use std::net::SocketAddr;
+            use std::sync::atomic::{AtomicUsize, Ordering};
+            use std::sync::Arc;
+
+            let brave_calls = Arc::new(AtomicUsize::new(0));
+            let brave_calls2 = brave_calls.clone();
+            let app = Router::new()
+                .route(
+                    "/search",
+                    get(|q: Query<HashMap<String, String>>| async move {
+                        let results = if q.get("q").map(|s| s.as_str()) == Some("junk") {
+                            serde_json::json!([
+                                {"url": "https://example.com/login?next=/a", "title": "Sign in"},
+                                {"url": "https://example.com/a", "title": "A"}
+                            ])
+                        } else {
+                            serde_json::json!([
+                                {"url": "https://example.com/a", "title": "A"},
+                                {"url": "https://example.org/b", "title": "B"},
+                                {"url": "https://example.net/c", "title": "C"}
+                            ])
+                        };
+                        axum::Json(serde_json::json!({ "results": results }))
+                    }),
+                )
+                .route(
+                    "/brave",
+                    get(move || {
+                        let brave_calls = brave_calls2.clone();
+                        async move {
+                            brave_calls.fetch_add(1, Ordering::SeqCst);
+                            axum::Json(serde_json::json!({"web":{"results":[
+                                {"url":"https://paid.example/1","title":"P1","description":"d"},
+                                {"url":"https://paid.example/2","title":"P2","description":"d"}
+                            ]}}))
+                        }
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set("WEBPIPE_SEARXNG_ENDPOINT", &format!("http://{addr}"));
+            env.set("WEBPIPE_BRAVE_API_KEY", "dummy");
+            env.set("WEBPIPE_BRAVE_ENDPOINT", &format!("http://{addr}/brave"));
+            env.set("WEBPIPE_COST_CASCADE_MIN_RESULTS", "2");
+            env.set("WEBPIPE_COST_CASCADE_MAX_JUNK_FRAC", "0.25");
+
+            let svc = WebpipeMcp::new().expect("new");
+            let call = |query: &str| {
+                p(WebSearchArgs {
+                    query: Some(query.to_string()),
+                    provider: Some("auto".to_string()),
+                    auto_mode: Some("cost_cascade".to_string()),
+                    max_results: Some(5),
+                    ..Default::default()
+                })
+            };
+
+            // Good free results: paid tier is never called.
+            let v = payload_from_call_tool_result(&svc.web_search(call("good")).await.unwrap());
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["backend_provider"].as_str(), Some("searxng"));
+            assert_eq!(brave_calls.load(Ordering::SeqCst), 0);
+            let steps = v["routing_explain"]["steps"].as_array().unwrap();
+            assert_eq!(steps.len(), 1);
+            assert_eq!(steps[0]["decision"].as_str(), Some("accept"));
+            assert_eq!(v["routing_explain"]["paid_used"].as_bool(), Some(false));
+            assert_eq!(
+                v["routing_explain"]["gate"]["min_results"].as_u64(),
+                Some(2)
+            );
+
+            // Junk free results (half auth walls): escalate to the paid tier.
+            let v = payload_from_call_tool_result(&svc.web_search(call("junk")).await.unwrap());
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["backend_provider"].as_str(), Some("brave"));
+            assert_eq!(brave_calls.load(Ordering::SeqCst), 1);
+            let steps = v["routing_explain"]["steps"].as_array().unwrap();
+            assert_eq!(steps.len(), 2);
+            assert_eq!(steps[0]["provider"].as_str(), Some("searxng"));
+            assert_eq!(steps[0]["decision"].as_str(), Some("escalate"));
+            assert_eq!(steps[0]["reason"].as_str(), Some("mostly_auth_wall_urls"));
+            assert_eq!(steps[1]["provider"].as_str(), Some("brave"));
+            assert_eq!(steps[1]["tier"].as_str(), Some("paid"));
+            assert_eq!(v["routing_explain"]["paid_used"].as_bool(), Some(true));
+            assert_eq!(
+                v["results"][0]["url"].as_str(),
+                Some("https://paid.example/1")
+            );
+
+            // Without a paid tier, below-gate free results are returned with a warning.
+            env.remove("WEBPIPE_BRAVE_API_KEY");
+            let v = payload_from_call_tool_result(&svc.web_search(call("junk")).await.unwrap());
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["backend_provider"].as_str(), Some("searxng"));
+            assert!(v["warning_codes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|c| c.as_str() == Some("cost_cascade_below_quality_gate")));
+        }
+
+        #[tokio::test]
+        async fn web_search_max_per_domain_caps_skewed_results() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.push("WEBPIPE_BRAVE_ENDPOINT");
+            let env = EnvGuard::new(&keys);
+
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+            let app = Router::new().route(
+                "/brave",
+                get(|| async {
+                    let urls = [
+                        "https://www.example.com/1",
+                        "https://example.com/2",
+                        "https://docs.example.com/3",
+                        "https://blog.example.com/4",
+                        "https://other.org/a",
+                        "https://news.bbc.co.uk/x",
+                        "https://sport.bbc.co.uk/y",
+                        "https://bbc.co.uk/z",
+                    ];
+                    let results: Vec<serde_json::Value> = urls
+                        .iter()
+                        .map(|u| serde_json::json!({"url": u, "title": u, "description": "d"}))
+                        .collect();
+                    axum::Json(serde_json::json!({ "web": { "results": results } }))
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set("WEBPIPE_BRAVE_API_KEY", "dummy");
+            env.set("WEBPIPE_BRAVE_ENDPOINT", &format!("http://{addr}/brave"));
+
+            fn domains(v: &serde_json::Value) -> std::collections::BTreeSet<String> {
+                v["results"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|r| {
+                        let u = reqwest::Url::parse(r["url"].as_str().unwrap()).unwrap();
+                        registrable_domain(u.host_str().unwrap())
+                    })
+                    .collect()
+            }
+
+            let svc = WebpipeMcp::new().expect("new");
+            let base = svc
+                .web_search(p(WebSearchArgs {
+                    query: Some("q".to_string()),
+                    provider: Some("brave".to_string()),
+                    max_results: Some(10),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let base = payload_from_call_tool_result(&base);
+            assert_eq!(base["results"].as_array().unwrap().len(), 8);
+            assert!(base.get("diversified_count").is_none());
+
+            let r = svc
+                .web_search(p(WebSearchArgs {
+                    query: Some("q".to_string()),
+                    provider: Some("brave".to_string()),
+                    max_results: Some(10),
+                    diversify: Some(true),
+                    max_per_domain: Some(2),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["request"]["max_per_domain"].as_u64(), Some(2));
+            assert_eq!(v["diversified_count"].as_u64(), Some(3));
+            let urls: Vec<&str> = v["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["url"].as_str().unwrap())
+                .collect();
+            // Highest-ranked per domain survive, in original order.
+            assert_eq!(
+                urls,
+                vec![
+                    "https://www.example.com/1",
+                    "https://example.com/2",
+                    "https://other.org/a",
+                    "https://news.bbc.co.uk/x",
+                    "https://sport.bbc.co.uk/y",
+                ]
+            );
+            // Same set of domains, but the top-k is no longer dominated by one of them.
+            assert_eq!(domains(&v), domains(&base));
+            let top4 = |v: &serde_json::Value| {
+                let mut x = v.clone();
+                x["results"].as_array_mut().unwrap().truncate(4);
+                domains(&x).len()
+            };
+            assert!(top4(&v) > top4(&base));
+        }
+
+        #[tokio::test]
+        async fn web_search_auto_fallback_can_choose_tavily_first_when_brave_is_unhealthy() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.extend_from_slice(&["WEBPIPE_BRAVE_ENDPOINT", "WEBPIPE_TAVILY_ENDPOINT"]);
+            keys.extend_from_slice(&["WEBPIPE_ROUTING_CONTEXT", "WEBPIPE_ROUTING_WINDOW"]);
+            let env = EnvGuard::new(&keys);
+            env.set("WEBPIPE_ROUTING_CONTEXT", "global");
+            env.set("WEBPIPE_ROUTING_WINDOW", "50");
+
+            // Local stub search endpoints: both succeed.
+            use axum::{routing::get, routing::post, Json, Router};
+            use std::net::SocketAddr;
+            let app = Router::new()
+                .route(
+                    "/brave",
+                    get(|| async {
+                        (
+                            [(axum::http::header::CONTENT_TYPE, "application/json")],
+                            r#"{"web":{"results":[{"url":"https://example.com","title":"Example","description":"Hello"}]}}"#,
+                        )
+                    }),
+                )
+                .route(
+                    "/tavily",
+                    post(|_body: Json<serde_json::Value>| async move {
+                        Json(serde_json::json!({
+                            "results": [
+                                {"url":"https://example.com","title":"Example","content":"Hello"}
+                            ],
+                            "usage": { "credits": 1 }
+                        }))
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            env.set("WEBPIPE_BRAVE_API_KEY", "dummy");
+            env.set("WEBPIPE_TAVILY_API_KEY", "dummy");
+            env.set("WEBPIPE_BRAVE_ENDPOINT", &format!("http://{addr}/brave"));
+            env.set("WEBPIPE_TAVILY_ENDPOINT", &format!("http://{addr}/tavily"));
+
+            let svc = WebpipeMcp::new().expect("new");
+
+            // Seed global stats: Brave is currently rate-limiting; Tavily is healthy.
+            for _ in 0..10 {
+                svc.stats_record_search_provider_qk("brave", false, 0, 10, Some("HTTP 429"), None);
+            }
+            for _ in 0..10 {
+                svc.stats_record_search_provider_qk("tavily", true, 1, 10, None, None);
+            }
+
+            let r = svc
+                .web_search(p(WebSearchArgs {
+                    provider: Some("auto".to_string()),
+                    auto_mode: Some("fallback".to_string()),
+                    query: Some("q".to_string()),
+                    country: None,
+                    language: None,
+                    max_results: Some(1),
                     ..Default::default()
                 }))
                 .await
@@ -23623,6 +33255,9 @@ Rules:
                     include_evidence: Some(true),
                     now_epoch_s: Some(1700000000),
                     llm_backend: Some("auto".to_string()),
+                    suggest_followups: None,
+                    passes: None,
+                    structured_output: None,
                 })))
                 .await
                 .expect("call");
@@ -23635,6 +33270,240 @@ Rules:
             assert_eq!(ev["arxiv"]["ok"].as_bool(), Some(false));
         }
 
+        #[tokio::test]
+        async fn arxiv_search_include_fulltext_attaches_top_k_and_fails_per_paper() {
+            let env = EnvGuard::new(&["WEBPIPE_ARXIV_ENDPOINT", "WEBPIPE_CACHE_DIR"]);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            use axum::{extract::Path, http::StatusCode, routing::get, Router};
+
+            // One server for both the Atom feed and the papers' "PDF" links.
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let entry = |id: &str, title: &str| {
+                format!(
+                    r#"<entry>
+    <id>http://arxiv.org/abs/{id}v1</id>
+    <published>2024-01-01T00:00:00Z</published>
+    <title>{title}</title>
+    <summary>Abstract of {title}.</summary>
+    <author><name>Alice</name></author>
+    <category term="cs.CL" />
+    <link rel="related" type="application/pdf" href="http://{addr}/pdf/{id}" />
+  </entry>"#
+                )
+            };
+            let atom = format!(
+                r#"<feed xmlns="http://www.w3.org/2005/Atom" xmlns:opensearch="http://a9.com/-/spec/opensearch/1.1/">
+  <opensearch:totalResults>3</opensearch:totalResults>
+  {}
+  {}
+  {}
+</feed>"#,
+                entry("2401.00001", "Good Paper"),
+                entry("2401.00002", "Broken Paper"),
+                entry("2401.00003", "Third Paper")
+            );
+            let app = Router::new()
+                .route(
+                    "/api/query",
+                    get(move || {
+                        let atom = atom.clone();
+                        async move {
+                            (
+                                [(axum::http::header::CONTENT_TYPE, "application/atom+xml")],
+                                atom,
+                            )
+                        }
+                    }),
+                )
+                .route(
+                    "/pdf/:id",
+                    get(|Path(id): Path<String>| async move {
+                        if id == "2401.00002" {
+                            return (StatusCode::INTERNAL_SERVER_ERROR, "oops".to_string());
+                        }
+                        (
+                            StatusCode::OK,
+                            format!("Full text of paper {id}. Introduction and results."),
+                        )
+                    }),
+                );
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set(
+                "WEBPIPE_ARXIV_ENDPOINT",
+                &format!("http://{addr}/api/query"),
+            );
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .arxiv_search(p(ArxivSearchArgs {
+                    query: Some("papers".to_string()),
+                    timeout_ms: Some(2_000),
+                    include_fulltext: Some(true),
+                    fulltext_max_papers: Some(2),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+            let papers = v["papers"].as_array().expect("papers");
+            assert_eq!(papers.len(), 3);
+
+            let good = &papers[0]["fulltext"];
+            assert_eq!(good["ok"].as_bool(), Some(true), "{good}");
+            assert!(good["text"]
+                .as_str()
+                .unwrap_or("")
+                .contains("Full text of paper 2401.00001"));
+            assert_eq!(
+                good["source_url"].as_str(),
+                Some(format!("http://{addr}/pdf/2401.00001").as_str())
+            );
+
+            let broken = &papers[1]["fulltext"];
+            assert_eq!(broken["ok"].as_bool(), Some(false), "{broken}");
+            assert_eq!(broken["error"]["code"].as_str(), Some("http_status"));
+            assert_eq!(broken["status"].as_u64(), Some(500));
+            // Metadata survives the failure.
+            assert_eq!(papers[1]["title"].as_str(), Some("Broken Paper"));
+
+            // Beyond fulltext_max_papers: metadata only.
+            assert!(papers[2].get("fulltext").is_none());
+            assert_eq!(
+                v["fulltext"],
+                serde_json::json!({"requested": 2, "attached": 1, "failed": 1})
+            );
+            assert!(v["warnings"]
+                .as_array()
+                .is_some_and(|w| w.iter().any(|x| x == "arxiv_fulltext_failed")));
+        }
+
+        #[tokio::test]
+        async fn arxiv_enrich_fetch_discussions_is_concurrent_ranked_and_fails_per_url() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.push("WEBPIPE_CACHE_DIR");
+            let env = EnvGuard::new(&keys);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            use axum::{extract::Path, http::StatusCode, routing::get, Json, Router};
+            use std::sync::atomic::{AtomicUsize, Ordering};
+            use std::sync::Arc;
+
+            // One server for the Atom feed, the SearXNG search, and the discussion pages.
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+  <entry>
+    <id>http://arxiv.org/abs/2401.00001v1</id>
+    <published>2024-01-01T00:00:00Z</published>
+    <title>Sparse Mixture Routing</title>
+    <summary>We study sparse mixture routing.</summary>
+    <author><name>Alice</name></author>
+  </entry>
+</feed>"#;
+            let search = serde_json::json!({ "results": [
+                {"url": format!("http://{addr}/d/general"), "title": "General", "content": "x"},
+                {"url": format!("http://{addr}/d/focused"), "title": "Focused", "content": "x"},
+                {"url": format!("http://{addr}/d/broken"), "title": "Broken", "content": "x"},
+            ]});
+            let in_flight = Arc::new(AtomicUsize::new(0));
+            let max_in_flight = Arc::new(AtomicUsize::new(0));
+            let (in_flight2, max_in_flight2) = (in_flight.clone(), max_in_flight.clone());
+            let app = Router::new()
+                .route(
+                    "/api/query",
+                    get(move || async move {
+                        ([(axum::http::header::CONTENT_TYPE, "application/atom+xml")], atom)
+                    }),
+                )
+                .route(
+                    "/search",
+                    get(move || {
+                        let search = search.clone();
+                        async move { Json(search) }
+                    }),
+                )
+                .route(
+                    "/d/:page",
+                    get(move |Path(page): Path<String>| {
+                        let (in_flight, max_in_flight) =
+                            (in_flight2.clone(), max_in_flight2.clone());
+                        async move {
+                            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                            max_in_flight.fetch_max(now, Ordering::SeqCst);
+                            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                            in_flight.fetch_sub(1, Ordering::SeqCst);
+                            let body = match page.as_str() {
+                                "broken" => {
+                                    return (StatusCode::INTERNAL_SERVER_ERROR, [(axum::http::header::CONTENT_TYPE, "text/plain")], "oops".to_string());
+                                }
+                                "focused" => "<html><head><title>Focused thread</title></head><body><main><p>Sparse mixture routing is the key idea; sparse routing beats dense mixture baselines.</p></main></body></html>",
+                                _ => "<html><head><title>General thread</title></head><body><main><p>Some weekly links, one of them about routing.</p></main></body></html>",
+                            };
+                            (StatusCode::OK, [(axum::http::header::CONTENT_TYPE, "text/html")], body.to_string())
+                        }
+                    }),
+                );
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set(
+                "WEBPIPE_ARXIV_ENDPOINT",
+                &format!("http://{addr}/api/query"),
+            );
+            env.set("WEBPIPE_SEARXNG_ENDPOINT", &format!("http://{addr}"));
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .arxiv_enrich(p(ArxivEnrichArgs {
+                    id_or_url: Some("2401.00001".to_string()),
+                    include_discussions: Some(true),
+                    max_discussion_urls: Some(3),
+                    fetch_discussions: Some(true),
+                    discussion_concurrency: Some(3),
+                    timeout_ms: Some(2_000),
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+
+            let ds = v["discussions"].as_array().expect("discussions");
+            let urls: Vec<&str> = ds.iter().map(|d| d["url"].as_str().unwrap()).collect();
+            assert_eq!(
+                urls,
+                vec![
+                    format!("http://{addr}/d/focused"),
+                    format!("http://{addr}/d/general"),
+                    format!("http://{addr}/d/broken"),
+                ],
+                "{v}"
+            );
+            assert!(ds[0]["score"].as_u64() > ds[1]["score"].as_u64(), "{v}");
+            assert!(ds[0]["snippet"]
+                .as_str()
+                .unwrap_or("")
+                .contains("Sparse mixture routing"));
+            assert_eq!(ds[0]["title"].as_str(), Some("Focused thread"));
+            assert_eq!(ds[0]["search_rank"].as_u64(), Some(2));
+            assert_eq!(ds[2]["ok"].as_bool(), Some(false));
+            assert_eq!(ds[2]["error"]["code"].as_str(), Some("http_status"));
+            assert_eq!(
+                v["discussion_fetch"],
+                serde_json::json!({"requested": 3, "fetched": 2, "failed": 1, "concurrency": 3})
+            );
+            assert!(v["warning_codes"]
+                .as_array()
+                .is_some_and(|w| w.iter().any(|x| x == "arxiv_discussion_fetch_failed")));
+            // All three pages were in flight together.
+            assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+        }
+
         #[tokio::test]
         async fn web_deep_research_includes_arxiv_papers_when_enabled() {
             let mut keys = Vec::new();
@@ -23750,6 +33619,9 @@ Rules:
                     include_evidence: Some(true),
                     now_epoch_s: Some(1700000000),
                     llm_backend: Some("auto".to_string()),
+                    suggest_followups: None,
+                    passes: None,
+                    structured_output: None,
                 })))
                 .await
                 .expect("call");
@@ -23862,6 +33734,9 @@ Rules:
                     include_evidence: Some(true),
                     now_epoch_s: Some(1700000000),
                     llm_backend: Some("ollama".to_string()),
+                    suggest_followups: None,
+                    passes: None,
+                    structured_output: None,
                 })))
                 .await
                 .expect("call");
@@ -23965,27 +33840,455 @@ Rules:
         }
 
         #[tokio::test]
-        async fn web_search_extract_include_links_extracts_links_for_html_main_pages() {
+        async fn web_search_extract_early_exit_stops_after_strong_first_url() {
             let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
-
-            // Local fixture server: include a nav with many links and an article with a few key links.
-            // This shape tends to trigger html_main extraction, but regardless, link extraction should
-            // come from raw HTML.
-            use axum::{routing::get, Router};
+            use axum::{extract::Path, routing::get, Router};
             use std::net::SocketAddr;
-            let html = r#"
-<html>
-  <head><title>Test</title></head>
-  <body>
-    <nav>
-      <a href="/a">A</a><a href="/b">B</a><a href="/c">C</a><a href="/d">D</a>
-      <a href="/e">E</a><a href="/f">F</a><a href="/g">G</a><a href="/h">H</a>
-    </nav>
-    <article>
-      <h1>Main content</h1>
-      <p>See <a href="https://example.com/x">X</a> and <a href="/y">Y</a>.</p>
-    </article>
-  </body>
+            use std::sync::atomic::{AtomicUsize, Ordering};
+            use std::sync::Arc;
+
+            let later_hits = Arc::new(AtomicUsize::new(0));
+            let later_hits2 = later_hits.clone();
+            let app = Router::new().route(
+                "/:page",
+                get(move |Path(page): Path<String>| {
+                    let later_hits = later_hits2.clone();
+                    async move {
+                        let body = if page == "a" {
+                            "<html><body><p>Tokamak plasma confinement: tokamak plasma \
+                             confinement relies on magnetic fields.</p></body></html>"
+                                .to_string()
+                        } else {
+                            later_hits.fetch_add(1, Ordering::SeqCst);
+                            format!("<html><body><p>Unrelated page {page}.</p></body></html>")
+                        };
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], body)
+                    }
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            let urls: Vec<String> = ["a", "b", "c"]
+                .iter()
+                .map(|x| format!("http://{addr}/{x}"))
+                .collect();
+
+            let svc = WebpipeMcp::new().expect("new");
+            let call = |early_exit: Option<EarlyExitArgs>| WebSearchExtractArgs {
+                query: Some("tokamak plasma confinement".to_string()),
+                urls: Some(urls.clone()),
+                url_selection_mode: Some("preserve".to_string()),
+                fetch_backend: Some("local".to_string()),
+                no_network: Some(false),
+                max_urls: Some(3),
+                max_parallel_urls: Some(1),
+                timeout_ms: Some(2_000),
+                agentic: Some(false),
+                include_structure: Some(false),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                early_exit,
+                ..Default::default()
+            };
+
+            let r = svc
+                .web_search_extract(p(call(Some(EarlyExitArgs {
+                    min_top_score: Some(2),
+                    min_chunks: Some(1),
+                }))))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["early_exit"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["url_count_used"].as_u64(), Some(1));
+            assert_eq!(
+                v["request"]["early_exit"]["min_top_score"].as_u64(),
+                Some(2)
+            );
+            assert_eq!(later_hits.load(Ordering::SeqCst), 0);
+
+            // Default: no early exit, every URL is processed.
+            let r = svc.web_search_extract(p(call(None))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert!(v.get("early_exit").is_none());
+            assert_eq!(v["url_count_used"].as_u64(), Some(3));
+            assert_eq!(later_hits.load(Ordering::SeqCst), 2);
+        }
+
+        #[tokio::test]
+        async fn web_search_extract_picks_exploration_preset_from_query_intent() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.push("WEBPIPE_CACHE_DIR");
+            let env = EnvGuard::new(&keys);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            let base = format!("http://{addr}");
+            let app = Router::new()
+                .route(
+                    "/search",
+                    get(move || async move {
+                        axum::Json(serde_json::json!({"results":[
+                            {"url": format!("{base}/page"), "title":"T", "content":"c"}
+                        ]}))
+                    }),
+                )
+                .route(
+                    "/page",
+                    get(|| async {
+                        (
+                            [(axum::http::header::CONTENT_TYPE, "text/html")],
+                            "<html><body><p>Tokamak and stellarator designs compared.</p></body></html>",
+                        )
+                    }),
+                );
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set("WEBPIPE_SEARXNG_ENDPOINT", &format!("http://{addr}"));
+
+            let svc = WebpipeMcp::new().expect("new");
+            let call = |query: &str, exploration: Option<&str>| WebSearchExtractArgs {
+                query: Some(query.to_string()),
+                provider: Some("searxng".to_string()),
+                exploration: exploration.map(str::to_string),
+                timeout_ms: Some(2_000),
+                agentic: Some(false),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                ..Default::default()
+            };
+
+            let r = svc
+                .web_search_extract(p(call("compare tokamak vs stellarator", None)))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["intent"].as_str(), Some("survey"));
+            assert_eq!(v["request"]["exploration"].as_str(), Some("wide"));
+            assert_eq!(v["request"]["exploration_auto"].as_bool(), Some(true));
+            assert_eq!(v["request"]["max_results"].as_u64(), Some(10));
+
+            let r = svc
+                .web_search_extract(p(call("what year did the first tokamak run", None)))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["request"]["intent"].as_str(), Some("factoid"));
+            assert_eq!(v["request"]["exploration"].as_str(), Some("balanced"));
+
+            // An explicit preset always wins; the intent is still reported.
+            let r = svc
+                .web_search_extract(p(call("compare tokamak vs stellarator", Some("balanced"))))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["request"]["intent"].as_str(), Some("survey"));
+            assert_eq!(v["request"]["exploration"].as_str(), Some("balanced"));
+            assert_eq!(v["request"]["exploration_auto"].as_bool(), Some(false));
+            assert_eq!(v["request"]["max_results"].as_u64(), Some(5));
+        }
+
+        #[cfg(feature = "eval")]
+        #[tokio::test]
+        async fn eval_matrix_run_dry_run_plans_without_fetching_or_writing_artifacts() {
+            use axum::{routing::get, Router};
+            use std::sync::atomic::{AtomicUsize, Ordering};
+            use std::sync::Arc;
+
+            let hits = Arc::new(AtomicUsize::new(0));
+            let hits2 = hits.clone();
+            let app = Router::new().fallback(get(move || {
+                let hits = hits2.clone();
+                async move {
+                    hits.fetch_add(1, Ordering::SeqCst);
+                    "nope"
+                }
+            }));
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            let base = format!("http://{addr}/");
+
+            let tmp = tempfile::tempdir().expect("tempdir");
+            let queries = tmp.path().join("queries.json");
+            std::fs::write(
+                &queries,
+                serde_json::json!({
+                    "schema_version": 1,
+                    "kind": "webpipe_e2e_queries",
+                    "queries": [
+                        {"query_id": "q1", "query": "tokamak plasma", "url_paths": ["/page/a", "page/b"]},
+                        {"query_id": "q2", "query": "stellarator", "url_paths": ["https://example.com/x"]}
+                    ]
+                })
+                .to_string(),
+            )
+            .unwrap();
+            let qrels = tmp.path().join("qrels.json");
+            std::fs::write(
+                &qrels,
+                serde_json::json!({
+                    "schema_version": 1,
+                    "kind": "webpipe_e2e_qrels",
+                    "qrels": [ {"query_id": "q1", "expected_url_substrings": ["/page/a"]} ]
+                })
+                .to_string(),
+            )
+            .unwrap();
+            let out_dir = tmp.path().join("out");
+            let plan_out = tmp.path().join("plans").join("plan.json");
+
+            let args = crate::EvalMatrixRunCmd {
+                queries_json: queries.clone(),
+                qrels,
+                base_url: base.clone(),
+                provider: "searxng".to_string(),
+                auto_mode: "fallback".to_string(),
+                selection_mode: "score".to_string(),
+                fetch_backend: "local".to_string(),
+                out_dir: Some(out_dir.clone()),
+                max_text_chars: 4000,
+                now_epoch_s: Some(7),
+                git_sha: None,
+                output: "json".to_string(),
+                dry_run: true,
+                plan_out: Some(plan_out.clone()),
+            };
+            let plan = crate::eval_matrix_run_dry_run(&args, 7).expect("dry run");
+
+            assert_eq!(plan["kind"].as_str(), Some("webpipe_eval_matrix_run_plan"));
+            assert_eq!(plan["query_count"].as_u64(), Some(2));
+            assert_eq!(plan["qrels_count"].as_u64(), Some(1));
+            let root = base.trim_end_matches('/');
+            assert_eq!(
+                plan["queries"][0]["urls"],
+                serde_json::json!([format!("{root}/page/a"), format!("{root}/page/b")])
+            );
+            assert_eq!(
+                plan["queries"][1]["urls"],
+                serde_json::json!(["https://example.com/x"])
+            );
+            let matrix = out_dir.join("webpipe-eval-matrix-run-7.jsonl");
+            assert_eq!(plan["artifacts"]["matrix"].as_str(), matrix.to_str());
+            assert_eq!(
+                plan["artifacts"]["manifest"].as_str(),
+                out_dir
+                    .join("webpipe-eval-matrix-manifest-run-7.json")
+                    .to_str()
+            );
+            let steps = plan["steps"].as_array().expect("steps");
+            let names: Vec<&str> = steps.iter().map(|s| s[0].as_str().unwrap()).collect();
+            assert_eq!(
+                names,
+                vec![
+                    "eval-matrix",
+                    "eval-matrix-score",
+                    "eval-matrix-export",
+                    "eval-matrix-judge"
+                ]
+            );
+            assert!(steps[0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|a| a.as_str() == matrix.to_str()));
+
+            // Nothing fetched, no artifacts written; only the plan.
+            assert_eq!(hits.load(Ordering::SeqCst), 0);
+            assert!(!out_dir.exists());
+            let written: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(&plan_out).unwrap()).unwrap();
+            assert_eq!(written, plan);
+        }
+
+        #[cfg(feature = "eval")]
+        #[tokio::test]
+        async fn eval_matrix_providers_tags_search_rows_and_scores_per_provider() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.extend_from_slice(&["WEBPIPE_BRAVE_ENDPOINT", "WEBPIPE_CACHE_DIR"]);
+            let env = EnvGuard::new(&keys);
+            use axum::{extract::Path, routing::get, Router};
+            use std::net::SocketAddr;
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            let base = format!("http://{addr}");
+            let (b1, b2) = (base.clone(), base.clone());
+            let app = Router::new()
+                .route(
+                    "/brave",
+                    get(move || async move {
+                        axum::Json(serde_json::json!({"web":{"results":[
+                            {"url": format!("{b1}/page/brave"), "title":"B", "description":"b"}
+                        ]}}))
+                    }),
+                )
+                .route(
+                    "/search",
+                    get(move || async move {
+                        axum::Json(serde_json::json!({"results":[
+                            {"url": format!("{b2}/page/searxng"), "title":"S", "content":"s"}
+                        ]}))
+                    }),
+                )
+                .route(
+                    "/page/:name",
+                    get(|Path(name): Path<String>| async move {
+                        (
+                            [(axum::http::header::CONTENT_TYPE, "text/html")],
+                            format!(
+                                "<html><body><p>Tokamak plasma page from {name}.</p></body></html>"
+                            ),
+                        )
+                    }),
+                );
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set(
+                "WEBPIPE_CACHE_DIR",
+                tmp.path().join("cache").to_str().unwrap(),
+            );
+            env.set("WEBPIPE_BRAVE_API_KEY", "dummy");
+            env.set("WEBPIPE_BRAVE_ENDPOINT", &format!("{base}/brave"));
+            env.set("WEBPIPE_SEARXNG_ENDPOINT", &base);
+
+            let queries = tmp.path().join("queries.json");
+            std::fs::write(
+                &queries,
+                serde_json::json!({
+                    "schema_version": 1,
+                    "kind": "webpipe_e2e_queries",
+                    "queries": [
+                        {"query_id": "q1", "query": "tokamak plasma", "url_paths": ["/page/fixture"]}
+                    ]
+                })
+                .to_string(),
+            )
+            .unwrap();
+            let qrels = tmp.path().join("qrels.json");
+            std::fs::write(
+                &qrels,
+                serde_json::json!({
+                    "schema_version": 1,
+                    "kind": "webpipe_e2e_qrels",
+                    "qrels": [ {"query_id": "q1", "expected_url_substrings": ["/page/brave"]} ]
+                })
+                .to_string(),
+            )
+            .unwrap();
+
+            let out = crate::run_eval_matrix(crate::EvalMatrixCmd {
+                queries_json: queries,
+                base_url: base.clone(),
+                provider: "searxng".to_string(),
+                providers: Some("brave, searxng,brave".to_string()),
+                provider_concurrency: 2,
+                auto_mode: "fallback".to_string(),
+                selection_mode: "score".to_string(),
+                fetch_backend: "local".to_string(),
+                max_results: 1,
+                max_urls: 1,
+                timeout_ms: 5_000,
+                max_bytes: 1_000_000,
+                top_chunks: 3,
+                max_chunk_chars: 500,
+                out: Some(tmp.path().join("matrix.jsonl")),
+                now_epoch_s: Some(1),
+            })
+            .await
+            .expect("eval-matrix");
+
+            let rows: Vec<serde_json::Value> = std::fs::read_to_string(&out)
+                .unwrap()
+                .lines()
+                .map(|l| serde_json::from_str(l).unwrap())
+                .collect();
+            let search: Vec<&serde_json::Value> =
+                rows.iter().filter(|r| r["case"] == "search").collect();
+            assert_eq!(search.len(), 2, "rows={rows:?}");
+            assert_eq!(search[0]["provider"].as_str(), Some("brave"));
+            assert_eq!(search[1]["provider"].as_str(), Some("searxng"));
+            for r in &search {
+                assert_eq!(r["result"]["ok"].as_bool(), Some(true), "row={r}");
+            }
+            assert_eq!(rows.iter().filter(|r| r["case"] == "warm_urls").count(), 1);
+            assert_eq!(
+                rows.iter().filter(|r| r["case"] == "offline_urls").count(),
+                1
+            );
+
+            let score = crate::eval_matrix_score_payload(&out, &qrels, 1).expect("score");
+            let pp = score["per_provider"].as_array().expect("per_provider");
+            assert_eq!(pp.len(), 2);
+            assert_eq!(pp[0]["provider"].as_str(), Some("brave"));
+            assert_eq!(pp[0]["hit"].as_u64(), Some(1), "score={score}");
+            assert_eq!(pp[1]["provider"].as_str(), Some("searxng"));
+            assert_eq!(pp[1]["hit"].as_u64(), Some(0), "score={score}");
+            assert_eq!(
+                score["per_query"][0]["providers"][0]["hit"].as_bool(),
+                Some(true)
+            );
+
+            let err = crate::run_eval_matrix(crate::EvalMatrixCmd {
+                queries_json: tmp.path().join("queries.json"),
+                base_url: base,
+                provider: "searxng".to_string(),
+                providers: Some("brave,bing".to_string()),
+                provider_concurrency: 2,
+                auto_mode: "fallback".to_string(),
+                selection_mode: "score".to_string(),
+                fetch_backend: "local".to_string(),
+                max_results: 1,
+                max_urls: 1,
+                timeout_ms: 5_000,
+                max_bytes: 1_000_000,
+                top_chunks: 3,
+                max_chunk_chars: 500,
+                out: Some(tmp.path().join("matrix2.jsonl")),
+                now_epoch_s: Some(1),
+            })
+            .await
+            .expect_err("unknown provider");
+            assert!(err.to_string().contains("bing"));
+        }
+
+        #[tokio::test]
+        async fn web_search_extract_include_links_extracts_links_for_html_main_pages() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+
+            // Local fixture server: include a nav with many links and an article with a few key links.
+            // This shape tends to trigger html_main extraction, but regardless, link extraction should
+            // come from raw HTML.
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+            let html = r#"
+<html>
+  <head><title>Test</title></head>
+  <body>
+    <nav>
+      <a href="/a">A</a><a href="/b">B</a><a href="/c">C</a><a href="/d">D</a>
+      <a href="/e">E</a><a href="/f">F</a><a href="/g">G</a><a href="/h">H</a>
+    </nav>
+    <article>
+      <h1>Main content</h1>
+      <p>See <a href="https://example.com/x">X</a> and <a href="/y">Y</a>.</p>
+    </article>
+  </body>
 </html>
 "#;
             let html = html.to_string();
@@ -24610,6 +34913,479 @@ Rules:
             }));
         }
 
+        #[tokio::test]
+        async fn web_search_extract_agentic_prefetch_serves_later_picks_from_cache() {
+            let env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+
+            use axum::{extract::Path, routing::get, Router};
+            use std::collections::HashMap;
+            use std::net::SocketAddr;
+            use std::sync::{Arc, Mutex};
+
+            // Hub links to three leaves whose URLs match the query progressively less well,
+            // so the lexical selector picks them in a fixed order. Leaves have no links.
+            let hits: Arc<Mutex<HashMap<String, usize>>> = Arc::new(Mutex::new(HashMap::new()));
+            let hits2 = hits.clone();
+            let app = Router::new().route(
+                "/:page",
+                get(move |Path(page): Path<String>| {
+                    let hits = hits2.clone();
+                    async move {
+                        *hits.lock().unwrap().entry(page.clone()).or_insert(0) += 1;
+                        let body = if page == "hub" {
+                            r#"<html><body><main><h1>Hub</h1><p>Index page.</p><nav>
+  <a href="/route-handlers-guide">one</a>
+  <a href="/route-handlers">two</a>
+  <a href="/route">three</a>
+</nav></main></body></html>"#
+                                .to_string()
+                        } else {
+                            format!(
+                                "<html><body><main><p>Route handlers guide page {page}.</p></main></body></html>"
+                            )
+                        };
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], body)
+                    }
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_search_extract(p(WebSearchExtractArgs {
+                    query: Some("route handlers guide".to_string()),
+                    urls: Some(vec![format!("http://{addr}/hub")]),
+                    url_selection_mode: Some("preserve".to_string()),
+                    fetch_backend: Some("local".to_string()),
+                    no_network: Some(false),
+                    max_urls: Some(3),
+                    timeout_ms: Some(2_000),
+                    max_bytes: Some(200_000),
+                    include_structure: Some(false),
+                    cache_read: Some(true),
+                    cache_write: Some(true),
+                    agentic: Some(true),
+                    agentic_selector: Some("lexical".to_string()),
+                    agentic_prefetch: Some(3),
+                    planner_max_calls: Some(0),
+                    compact: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["request"]["agentic_prefetch"].as_u64(), Some(3));
+            let results = v["results"].as_array().expect("results array");
+            assert_eq!(results.len(), 3, "{v}");
+            let picked: Vec<&str> = results
+                .iter()
+                .map(|x| x["url"].as_str().unwrap_or(""))
+                .collect();
+            assert!(picked[1].ends_with("/route-handlers-guide"), "{picked:?}");
+            assert!(picked[2].ends_with("/route-handlers"), "{picked:?}");
+            assert_eq!(results[1]["fetch_source"].as_str(), Some("network"));
+            // The third pick was prefetched while the second was processed.
+            assert_eq!(results[2]["fetch_source"].as_str(), Some("cache"));
+
+            let pf = &v["agentic"]["prefetch"];
+            assert_eq!(pf["enabled"].as_bool(), Some(true));
+            assert_eq!(pf["hits"].as_u64(), Some(1), "{pf}");
+            // Prefetches do not count as processed URLs.
+            assert_eq!(v["url_count_used"].as_u64(), Some(3));
+            let trace = v["agentic"]["trace"].as_array().expect("trace");
+            assert!(trace
+                .iter()
+                .any(|t| t["prefetched"].as_array().is_some_and(|a| a
+                    .iter()
+                    .any(|u| u.as_str().is_some_and(|u| u.ends_with("/route-handlers"))))));
+
+            // Same-host cap: only one prefetch rides along with each pick, and nothing is
+            // fetched twice.
+            let hits = hits.lock().unwrap();
+            assert_eq!(hits.get("route-handlers").copied(), Some(1), "{hits:?}");
+            assert_eq!(
+                hits.get("route-handlers-guide").copied(),
+                Some(1),
+                "{hits:?}"
+            );
+            assert_eq!(hits.get("hub").copied(), Some(1), "{hits:?}");
+        }
+
+        #[tokio::test]
+        async fn web_search_extract_agentic_quality_floor_triggers_another_search_round() {
+            let mut keys = Vec::new();
+            keys.extend_from_slice(&SEARCH_ENV_KEYS);
+            keys.push("WEBPIPE_CACHE_DIR");
+            let env = EnvGuard::new(&keys);
+            use axum::{routing::get, Router};
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let base = format!("http://{addr}");
+            let b = base.clone();
+            let page = |body: &'static str| {
+                get(move || async move {
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "text/html")],
+                        format!("<html><body><main>{body}</main></body></html>"),
+                    )
+                })
+            };
+            let app = Router::new()
+                .route(
+                    "/weak",
+                    page(r#"<p>Gardening notes on compost and tomatoes.</p><a href="/weak2">more gardening</a>"#),
+                )
+                .route("/weak2", page("<p>More gardening notes on mulch.</p>"))
+                .route(
+                    "/strong",
+                    page("<p>Tokamak plasma confinement: the tokamak holds the plasma with magnetic fields.</p>"),
+                )
+                .route(
+                    "/search",
+                    get(move || async move {
+                        axum::Json(serde_json::json!({"results":[
+                            {"url": format!("{b}/strong"), "title":"Tokamak", "content":"plasma"}
+                        ]}))
+                    }),
+                );
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set("WEBPIPE_SEARXNG_ENDPOINT", &base);
+
+            let svc = WebpipeMcp::new().expect("new");
+            let call = |floor: Option<u64>| WebSearchExtractArgs {
+                query: Some("tokamak plasma".to_string()),
+                urls: Some(vec![format!("{base}/weak")]),
+                url_selection_mode: Some("preserve".to_string()),
+                fetch_backend: Some("local".to_string()),
+                no_network: Some(false),
+                max_urls: Some(2),
+                timeout_ms: Some(2_000),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                agentic: Some(true),
+                agentic_selector: Some("lexical".to_string()),
+                agentic_max_search_rounds: Some(1),
+                agentic_min_quality_score: floor,
+                planner_max_calls: Some(0),
+                compact: Some(false),
+                ..Default::default()
+            };
+            let fetched = |v: &serde_json::Value| -> Vec<String> {
+                v["results"]
+                    .as_array()
+                    .expect("results")
+                    .iter()
+                    .filter_map(|r| Some(r["url"].as_str()?.rsplit('/').next()?.to_string()))
+                    .collect()
+            };
+
+            let r = svc
+                .web_search_extract(p(call(Some(3))))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(fetched(&v), ["weak", "strong"], "payload={v}");
+            let trace = v["agentic"]["trace"].as_array().expect("trace");
+            let floor_at = trace
+                .iter()
+                .position(|t| t["quality_floor"].as_bool() == Some(true))
+                .expect("quality_floor trace entry");
+            assert_eq!(trace[floor_at]["min_quality_score"].as_u64(), Some(3));
+            assert!(trace[floor_at]["best_score"].as_u64().unwrap() < 3);
+            assert_eq!(trace[floor_at]["urls_processed"].as_u64(), Some(1));
+            assert!(trace[floor_at + 1..]
+                .iter()
+                .any(|t| t["search_more"].as_bool() == Some(true) && t["round"] == 1));
+            assert_eq!(
+                v["top_chunks"][0]["url"].as_str(),
+                Some(format!("{base}/strong").as_str())
+            );
+            assert_eq!(v["request"]["agentic_min_quality_score"].as_u64(), Some(3));
+
+            // Without a floor the weak pages fill the budget and no extra search runs.
+            let r = svc.web_search_extract(p(call(None))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(fetched(&v), ["weak", "weak2"], "payload={v}");
+            assert!(!v["agentic"]["trace"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|t| t.get("quality_floor").is_some() || t.get("search_more").is_some()));
+        }
+
+        #[tokio::test]
+        async fn web_search_extract_agentic_max_depth_stops_enqueueing_deeper_links() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{extract::Path, routing::get, Router};
+            use std::net::SocketAddr;
+
+            // Chain: /chain/a -> /chain/b -> /chain/c (each page links only to the next hop).
+            let app = Router::new().route(
+                "/chain/:page",
+                get(|Path(page): Path<String>| async move {
+                    let next = match page.as_str() {
+                        "a" => r#"<a href="/chain/b">tokamak plasma part b</a>"#,
+                        "b" => r#"<a href="/chain/c">tokamak plasma part c</a>"#,
+                        _ => "",
+                    };
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "text/html")],
+                        format!(
+                            "<html><body><main><h1>Part {page}</h1>\
+                             <p>Tokamak plasma notes, part {page}.</p>{next}</main></body></html>"
+                        ),
+                    )
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let call = |agentic_max_depth: Option<usize>| WebSearchExtractArgs {
+                query: Some("tokamak plasma".to_string()),
+                urls: Some(vec![format!("http://{addr}/chain/a")]),
+                url_selection_mode: Some("preserve".to_string()),
+                fetch_backend: Some("local".to_string()),
+                no_network: Some(false),
+                max_urls: Some(5),
+                timeout_ms: Some(2_000),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                agentic: Some(true),
+                agentic_selector: Some("lexical".to_string()),
+                agentic_max_depth,
+                planner_max_calls: Some(0),
+                compact: Some(false),
+                ..Default::default()
+            };
+            let picked_depths = |v: &serde_json::Value| -> Vec<(String, u64)> {
+                v["agentic"]["trace"]
+                    .as_array()
+                    .expect("trace")
+                    .iter()
+                    .filter_map(|t| {
+                        let u = t.get("picked_url")?.as_str()?;
+                        let path = u.rsplit('/').next()?.to_string();
+                        Some((path, t.get("depth")?.as_u64()?))
+                    })
+                    .collect()
+            };
+
+            let r = svc
+                .web_search_extract(p(call(Some(1))))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(
+                picked_depths(&v),
+                vec![("a".to_string(), 0), ("b".to_string(), 1)]
+            );
+            assert_eq!(v["agentic"]["depth_skipped"].as_u64(), Some(1));
+            assert_eq!(v["request"]["agentic_max_depth"].as_u64(), Some(1));
+            assert_eq!(v["results"].as_array().map(|a| a.len()), Some(2));
+
+            // Default: unlimited depth, so the third hop is enqueued and fetched.
+            let r = svc.web_search_extract(p(call(None))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(
+                picked_depths(&v),
+                vec![
+                    ("a".to_string(), 0),
+                    ("b".to_string(), 1),
+                    ("c".to_string(), 2)
+                ]
+            );
+            assert_eq!(v["agentic"]["depth_skipped"].as_u64(), Some(0));
+        }
+
+        #[tokio::test]
+        async fn web_search_extract_balance_surfaces_contrasting_chunks() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{extract::Path, routing::get, Router};
+            use std::net::SocketAddr;
+
+            let app = Router::new().route(
+                "/doc/:page",
+                get(|Path(page): Path<String>| async move {
+                    let body = match page.as_str() {
+                        "pro" => {
+                            "Intermittent fasting is effective for weight loss. Intermittent \
+                                  fasting improves insulin sensitivity and supports weight loss."
+                        }
+                        "con" => {
+                            "Intermittent fasting is not more effective for weight loss than \
+                                  calorie restriction; trials found no insulin sensitivity benefit."
+                        }
+                        _ => "Sourdough bread needs a mature starter and a long cold proof.",
+                    };
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "text/html")],
+                        format!("<html><body><main><p>{body}</p></main></body></html>"),
+                    )
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let call = |balance: Option<bool>| WebSearchExtractArgs {
+                query: Some("intermittent fasting weight loss".to_string()),
+                urls: Some(vec![
+                    format!("http://{addr}/doc/pro"),
+                    format!("http://{addr}/doc/con"),
+                    format!("http://{addr}/doc/bread"),
+                ]),
+                url_selection_mode: Some("preserve".to_string()),
+                fetch_backend: Some("local".to_string()),
+                no_network: Some(false),
+                max_urls: Some(3),
+                top_chunks: Some(1),
+                timeout_ms: Some(2_000),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                agentic: Some(false),
+                balance,
+                ..Default::default()
+            };
+
+            let r = svc
+                .web_search_extract(p(call(Some(true))))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["balance"].as_bool(), Some(true));
+            assert_eq!(v["balance"]["best_effort"].as_bool(), Some(true));
+            let top_url = v["top_chunks"][0]["url"]
+                .as_str()
+                .expect("top chunk")
+                .to_string();
+            let contrasting = v["contrasting_chunks"]
+                .as_array()
+                .expect("contrasting_chunks");
+            assert!(!contrasting.is_empty(), "payload={v}");
+            let c = &contrasting[0];
+            let c_url = c["url"].as_str().unwrap();
+            assert_ne!(c_url, top_url);
+            assert!(
+                c_url.ends_with("/doc/con") || top_url.ends_with("/doc/con"),
+                "payload={v}"
+            );
+            assert!(contrasting
+                .iter()
+                .all(|c| !c["url"].as_str().unwrap().ends_with("/doc/bread")));
+            assert_eq!(
+                c["contrast"]["against_url"].as_str(),
+                Some(top_url.as_str())
+            );
+            assert!(c["contrast"]["shared_terms"].as_u64().unwrap() >= 2);
+
+            // Off by default: no contrasting_chunks key at all.
+            let r = svc.web_search_extract(p(call(None))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert!(v.get("contrasting_chunks").is_none(), "payload={v}");
+        }
+
+        #[tokio::test]
+        async fn web_search_extract_mmr_selection_prefers_a_distinct_page_over_a_mirror() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{extract::Path, routing::get, Router};
+            use std::net::SocketAddr;
+
+            let app = Router::new().route(
+                "/doc/:page",
+                get(|Path(page): Path<String>| async move {
+                    let body = match page.as_str() {
+                        "a" | "mirror" => {
+                            "Tide tables list high and low tide times for the harbor. \
+                             Check the tide tables before launching from the harbor ramp."
+                        }
+                        _ => "Spring tides follow the new and full moon, so the tide range grows.",
+                    };
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "text/html")],
+                        format!("<html><body><main><p>{body}</p></main></body></html>"),
+                    )
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let call = |selection_mode: &str| WebSearchExtractArgs {
+                query: Some("tide tables harbor".to_string()),
+                urls: Some(vec![
+                    format!("http://{addr}/doc/a"),
+                    format!("http://{addr}/doc/mirror"),
+                    format!("http://{addr}/doc/moon"),
+                ]),
+                url_selection_mode: Some("preserve".to_string()),
+                selection_mode: Some(selection_mode.to_string()),
+                mmr_lambda: Some(0.3),
+                fetch_backend: Some("local".to_string()),
+                max_urls: Some(3),
+                top_chunks: Some(2),
+                timeout_ms: Some(2_000),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                agentic: Some(false),
+                ..Default::default()
+            };
+            let top_urls = |v: &serde_json::Value| -> Vec<String> {
+                v["top_chunks"]
+                    .as_array()
+                    .expect("top_chunks")
+                    .iter()
+                    .map(|c| c["url"].as_str().unwrap().to_string())
+                    .collect()
+            };
+
+            let v = payload_from_call_tool_result(
+                &svc.web_search_extract(p(call("score")))
+                    .await
+                    .expect("call"),
+            );
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert!(v.get("mmr").is_none(), "payload={v}");
+            assert!(
+                top_urls(&v).iter().all(|u| !u.ends_with("/doc/moon")),
+                "payload={v}"
+            );
+
+            let v = payload_from_call_tool_result(
+                &svc.web_search_extract(p(call("mmr"))).await.expect("call"),
+            );
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["selection_mode"].as_str(), Some("mmr"));
+            assert_eq!(v["request"]["mmr_lambda"].as_f64(), Some(0.3));
+            assert_eq!(v["mmr"]["similarity"].as_str(), Some("lexical"));
+            let urls = top_urls(&v);
+            assert_eq!(urls.len(), 2, "payload={v}");
+            assert!(urls[1].ends_with("/doc/moon"), "payload={v}");
+        }
+
         #[tokio::test]
         async fn web_search_extract_agentic_can_hop_via_anchor_text_even_when_parent_has_no_hits() {
             let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
@@ -24771,7 +35547,11 @@ Rules:
 
         #[tokio::test]
         async fn web_extract_warns_on_empty_extraction_for_html() {
-            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            // Own cache dir: loopback ports are reused, so a shared cache can serve another
+            // test's body for this URL.
+            let env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
 
             // HTML that is likely to extract to empty text.
             use axum::{routing::get, Router};
@@ -24800,12 +35580,32 @@ Rules:
                     no_network: Some(false),
                     width: Some(80),
                     max_chars: Some(2_000),
+                    truncation_strategy: None,
+                    engine: None,
                     query: None,
                     top_chunks: Some(3),
                     max_chunk_chars: Some(200),
                     include_text: Some(false),
                     include_links: Some(false),
                     max_links: Some(10),
+                    include_alternates: None,
+                    prefer_amp: None,
+                    include_entities: None,
+                    include_tables: None,
+                    include_metadata: None,
+                    table_format: None,
+                    include_dates: None,
+                    date_order: None,
+                    include_keywords: None,
+                    max_keywords: None,
+                    chunk_dom_paths: None,
+                    referer: None,
+                    sentences: None,
+                    include_noscript: None,
+                    follow_iframes: None,
+                    allow_cross_origin_iframes: None,
+                    max_iframes: None,
+                    include_error_body: None,
                     timeout_ms: Some(2_000),
                     max_bytes: Some(200_000),
                     retry_on_truncation: None,
@@ -24817,9 +35617,11 @@ Rules:
                     max_outline_items: None,
                     max_blocks: None,
                     max_block_chars: None,
+                    sectioned: None,
                     semantic_rerank: None,
                     semantic_auto_fallback: None,
                     semantic_top_k: None,
+                    include_raw_html: None,
                 }))
                 .await
                 .expect("call");
@@ -24885,11 +35687,16 @@ Rules:
                     max_bytes: Some(200),
                     max_text_chars: Some(1_000),
                     headers: None,
+                    referer: None,
                     cache_read: Some(false),
                     cache_write: Some(false),
                     cache_ttl_s: None,
                     include_text: Some(false),
                     include_headers: Some(false),
+                    expect_content_type: None,
+                    include_error_body: None,
+                    known_sha256: None,
+                    include_metadata: None,
                 }))
                 .await
                 .expect("call");
@@ -24910,6 +35717,768 @@ Rules:
                 .any(|x| x.as_str() == Some("body_truncated_by_max_bytes")));
         }
 
+        #[tokio::test]
+        async fn web_fetch_reports_wire_and_decoded_bytes_for_gzip() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::io::Write;
+            use std::net::SocketAddr;
+            let plain = "<p>repeated body text</p>".repeat(1_000);
+            let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            enc.write_all(plain.as_bytes()).unwrap();
+            let gz = enc.finish().unwrap();
+            let gz_len = gz.len() as u64;
+            let app = Router::new().route(
+                "/",
+                get(move || {
+                    let gz = gz.clone();
+                    async move {
+                        (
+                            [
+                                (axum::http::header::CONTENT_TYPE, "text/html"),
+                                (axum::http::header::CONTENT_ENCODING, "gzip"),
+                            ],
+                            gz,
+                        )
+                    }
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_fetch(p(WebFetchArgs {
+                    url: Some(format!("http://{}/", addr)),
+                    fetch_backend: Some("local".to_string()),
+                    no_network: Some(false),
+                    timeout_ms: Some(2_000),
+                    max_bytes: Some(200_000),
+                    max_text_chars: Some(1_000),
+                    headers: None,
+                    referer: None,
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    cache_ttl_s: None,
+                    include_text: Some(false),
+                    include_headers: Some(false),
+                    expect_content_type: None,
+                    include_error_body: None,
+                    known_sha256: None,
+                    include_metadata: None,
+                }))
+                .await
+                .expect("call");
+
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["wire_bytes"].as_u64(), Some(gz_len));
+            assert_eq!(v["decoded_bytes"].as_u64(), Some(plain.len() as u64));
+            assert_eq!(v["bytes"], v["decoded_bytes"]);
+            assert!(gz_len < plain.len() as u64);
+        }
+
+        /// Serves `/gated` only when `Referer` equals `http://<addr>/<expect>`; `/hub` links to it.
+        async fn referer_gated_server(expect: &'static str) -> std::net::SocketAddr {
+            use axum::{http::HeaderMap, http::StatusCode, routing::get, Router};
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let want = format!("http://{addr}/{expect}");
+            let app = Router::new()
+                .route(
+                    "/hub",
+                    get(|| async {
+                        (
+                            [(axum::http::header::CONTENT_TYPE, "text/html")],
+                            r#"<html><body><main><h1>Hub</h1><p>Index page.</p>
+<a href="/gated">gated download guide</a></main></body></html>"#,
+                        )
+                    }),
+                )
+                .route(
+                    "/gated",
+                    get(move |headers: HeaderMap| {
+                        let want = want.clone();
+                        async move {
+                            let ok = headers
+                                .get("referer")
+                                .and_then(|v| v.to_str().ok())
+                                .is_some_and(|v| v == want);
+                            if ok {
+                                (
+                                    StatusCode::OK,
+                                    [(axum::http::header::CONTENT_TYPE, "text/html")],
+                                    "<html><body><main><p>Gated download guide content.</p></main></body></html>",
+                                )
+                            } else {
+                                (
+                                    StatusCode::FORBIDDEN,
+                                    [(axum::http::header::CONTENT_TYPE, "text/plain")],
+                                    "hotlinking not allowed",
+                                )
+                            }
+                        }
+                    }),
+                );
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            addr
+        }
+
+        #[tokio::test]
+        async fn web_fetch_and_web_extract_send_referer_and_key_the_cache_on_it() {
+            let env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            let addr = referer_gated_server("hub").await;
+            let svc = WebpipeMcp::new().expect("new");
+            let fetch = |referer: Option<String>| WebFetchArgs {
+                url: Some(format!("http://{addr}/gated")),
+                fetch_backend: Some("local".to_string()),
+                no_network: Some(false),
+                timeout_ms: Some(2_000),
+                max_bytes: Some(10_000),
+                cache_read: Some(true),
+                cache_write: Some(true),
+                referer,
+                ..Default::default()
+            };
+
+            // With the Referer: 200, and the request echoes it.
+            let r = svc
+                .web_fetch(p(fetch(Some(format!("http://{addr}/hub#top")))))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+            assert_eq!(v["status"].as_u64(), Some(200));
+            assert_eq!(
+                v["request"]["referer"].as_str(),
+                Some(format!("http://{addr}/hub").as_str())
+            );
+
+            // Without it: the cached 200 is not reused (different cache key), and the server 403s.
+            let r = svc.web_fetch(p(fetch(None))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(false), "{v}");
+            assert_eq!(v["status"].as_u64(), Some(403), "{v}");
+            assert!(v["request"].get("referer").is_none());
+
+            let r = svc
+                .web_fetch(p(fetch(Some("not a url".to_string()))))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["error"]["code"].as_str(), Some("invalid_params"));
+
+            let extract = |referer: Option<String>| WebExtractArgs {
+                url: Some(format!("http://{addr}/gated")),
+                fetch_backend: Some("local".to_string()),
+                no_network: Some(false),
+                timeout_ms: Some(2_000),
+                max_bytes: Some(10_000),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                referer,
+                ..Default::default()
+            };
+            let r = svc.web_extract(p(extract(None))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(false), "{v}");
+            let r = svc
+                .web_extract(p(extract(Some(format!("http://{addr}/hub")))))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+            assert!(v["extract"]["text"]
+                .as_str()
+                .unwrap_or("")
+                .contains("Gated download guide"));
+        }
+
+        #[tokio::test]
+        async fn web_search_extract_agentic_sends_discovering_page_as_referer() {
+            let env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            let addr = referer_gated_server("hub").await;
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_search_extract(p(WebSearchExtractArgs {
+                    query: Some("gated download guide".to_string()),
+                    urls: Some(vec![format!("http://{addr}/hub")]),
+                    url_selection_mode: Some("preserve".to_string()),
+                    fetch_backend: Some("local".to_string()),
+                    no_network: Some(false),
+                    max_urls: Some(2),
+                    timeout_ms: Some(2_000),
+                    max_bytes: Some(200_000),
+                    include_structure: Some(false),
+                    agentic: Some(true),
+                    agentic_selector: Some("lexical".to_string()),
+                    planner_max_calls: Some(0),
+                    compact: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            let results = v["results"].as_array().expect("results array");
+            let gated = results
+                .iter()
+                .find(|x| x["url"].as_str().is_some_and(|u| u.ends_with("/gated")))
+                .unwrap_or_else(|| panic!("gated page not picked: {v}"));
+            assert_eq!(gated["status"].as_u64(), Some(200), "{gated}");
+        }
+
+        #[tokio::test]
+        async fn web_extract_head_tail_truncation_keeps_conclusion() {
+            let env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            use axum::{routing::get, Router};
+            let body = format!(
+                "<html><body><main><p>Introduction to the study.</p>{}<p>Conclusion: the method works.</p></main></body></html>",
+                "<p>Filler paragraph about methodology and setup details.</p>".repeat(200)
+            );
+            let app = Router::new().route(
+                "/long",
+                get(move || {
+                    let body = body.clone();
+                    async move { ([(axum::http::header::CONTENT_TYPE, "text/html")], body) }
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let extract = |strategy: Option<&str>| WebExtractArgs {
+                url: Some(format!("http://{addr}/long")),
+                fetch_backend: Some("local".to_string()),
+                no_network: Some(false),
+                timeout_ms: Some(2_000),
+                max_chars: Some(1_000),
+                truncation_strategy: strategy.map(str::to_string),
+                include_text: Some(true),
+                include_structure: Some(false),
+                ..Default::default()
+            };
+
+            let r = svc.web_extract(p(extract(None))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            let text = v["extract"]["text"].as_str().expect("text");
+            assert!(
+                text.contains("Introduction") && !text.contains("Conclusion"),
+                "{v}"
+            );
+            assert_eq!(v["request"]["truncation_strategy"].as_str(), Some("head"));
+
+            let r = svc
+                .web_extract(p(extract(Some("head_tail"))))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+            let text = v["extract"]["text"].as_str().expect("text");
+            assert!(text.contains("Introduction"), "{text}");
+            assert!(text.contains("Conclusion: the method works."), "{text}");
+            assert!(text.contains("[... truncated "), "{text}");
+            assert!(text.chars().count() <= 1_000);
+            assert!(v["warnings"]
+                .as_array()
+                .is_some_and(|w| w.iter().any(|x| x == "text_truncated_by_max_chars")));
+
+            let r = svc
+                .web_extract(p(extract(Some("tail"))))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["error"]["code"].as_str(), Some("invalid_params"));
+        }
+
+        async fn dead_end_server(delay_ms: u64) -> std::net::SocketAddr {
+            use axum::{routing::get, Router};
+            let app = Router::new().route(
+                "/:page",
+                get(move || async move {
+                    tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "text/html")],
+                        "<html><body><main></main></body></html>",
+                    )
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            addr
+        }
+
+        fn dead_end_args(addr: std::net::SocketAddr, timeout_ms: u64) -> WebSearchExtractArgs {
+            WebSearchExtractArgs {
+                query: Some("route handlers".to_string()),
+                urls: Some(vec![format!("http://{addr}/a"), format!("http://{addr}/b")]),
+                url_selection_mode: Some("preserve".to_string()),
+                fetch_backend: Some("local".to_string()),
+                no_network: Some(false),
+                max_urls: Some(2),
+                timeout_ms: Some(timeout_ms),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                ..Default::default()
+            }
+        }
+
+        #[tokio::test]
+        async fn web_search_extract_diagnoses_all_empty_extraction() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            let addr = dead_end_server(0).await;
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_search_extract(p(dead_end_args(addr, 2_000)))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+            let d = &v["diagnostics"];
+            assert_eq!(d["reason"].as_str(), Some("all_urls_failed"), "{d}");
+            assert_eq!(d["dominant_failure"].as_str(), Some("empty_extraction"));
+            assert_eq!(d["unanimous"].as_bool(), Some(true));
+            assert_eq!(d["failure_counts"]["empty_extraction"].as_u64(), Some(2));
+            assert!(d["hint"]
+                .as_str()
+                .unwrap_or("")
+                .contains("firecrawl_fallback_on_empty_extraction"));
+        }
+
+        #[tokio::test]
+        async fn web_search_extract_diagnoses_all_timeouts() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            let addr = dead_end_server(3_000).await;
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_search_extract(p(dead_end_args(addr, 200)))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+            let d = &v["diagnostics"];
+            assert_eq!(d["dominant_failure"].as_str(), Some("timeout"), "{d}");
+            assert_eq!(d["failure_counts"]["timeout"].as_u64(), Some(2));
+            assert!(d["hint"].as_str().unwrap_or("").contains("timeout_ms"));
+        }
+
+        #[test]
+        fn search_extract_diagnostics_only_for_dead_end_runs() {
+            let ok = serde_json::json!({
+                "ok": true, "status": 200,
+                "extract": { "text_chars": 100, "chunks": [{ "text": "x" }] }
+            });
+            let forbidden = serde_json::json!({ "ok": false, "status": 403, "error": { "code": "http_status" } });
+            assert!(search_extract_diagnostics(&[ok.clone(), forbidden.clone()], 1).is_none());
+
+            let d = search_extract_diagnostics(&[ok, forbidden.clone()], 0).expect("no chunks");
+            assert_eq!(d["reason"].as_str(), Some("no_chunks_selected"));
+            assert_eq!(d["dominant_failure"].as_str(), Some("http_4xx"));
+            assert_eq!(d["unanimous"].as_bool(), Some(false));
+
+            let d = search_extract_diagnostics(&[], 0).expect("no urls");
+            assert_eq!(d["reason"].as_str(), Some("no_urls"));
+            assert_eq!(d["dominant_failure"].as_str(), Some("no_urls"));
+        }
+
+        #[tokio::test]
+        async fn web_fetch_surfaces_content_disposition_filename() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+            let app = Router::new().route(
+                "/dl",
+                get(|| async {
+                    (
+                        [
+                            (axum::http::header::CONTENT_TYPE, "application/octet-stream"),
+                            (
+                                axum::http::header::CONTENT_DISPOSITION,
+                                "attachment; filename=\"fallback.bin\"; filename*=UTF-8''r%C3%A9sum%C3%A9.bin",
+                            ),
+                        ],
+                        "data",
+                    )
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_fetch(p(WebFetchArgs {
+                    url: Some(format!("http://{}/dl", addr)),
+                    fetch_backend: Some("local".to_string()),
+                    no_network: Some(false),
+                    timeout_ms: Some(2_000),
+                    max_bytes: Some(10_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["filename"].as_str(), Some("résumé.bin"));
+        }
+
+        #[tokio::test]
+        async fn web_fetch_known_sha256_short_circuits_unchanged_bodies() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            let app = Router::new().route(
+                "/feed",
+                get(|| async {
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "text/plain")],
+                        "version 1",
+                    )
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let fetch = |known_sha256: Option<String>| {
+                svc.web_fetch(p(WebFetchArgs {
+                    url: Some(format!("http://{addr}/feed")),
+                    timeout_ms: Some(2_000),
+                    include_text: Some(true),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    known_sha256,
+                    ..Default::default()
+                }))
+            };
+
+            let v = payload_from_call_tool_result(&fetch(None).await.expect("call"));
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert!(v.get("changed").is_none());
+            let sha = v["body_sha256"].as_str().expect("body_sha256").to_string();
+            assert_eq!(sha, webpipe_local::doc_id::body_sha256(b"version 1"));
+
+            let v = payload_from_call_tool_result(
+                &fetch(Some(sha.to_ascii_uppercase())).await.expect("call"),
+            );
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["changed"].as_bool(), Some(false));
+            assert!(v.get("body_text").is_none(), "{v}");
+            assert_eq!(v["body_sha256"].as_str(), Some(sha.as_str()));
+
+            let stale = webpipe_local::doc_id::body_sha256(b"version 0");
+            let v = payload_from_call_tool_result(&fetch(Some(stale)).await.expect("call"));
+            assert_eq!(v["changed"].as_bool(), Some(true));
+            assert_eq!(v["body_text"].as_str(), Some("version 1"));
+        }
+
+        #[tokio::test]
+        async fn web_fetch_expect_content_type_fails_fast_on_mismatch() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+            let app = Router::new()
+                .route(
+                    "/page",
+                    get(|| async {
+                        (
+                            [(axum::http::header::CONTENT_TYPE, "text/html; charset=utf-8")],
+                            "<html><body><h1>Sign in</h1></body></html>",
+                        )
+                    }),
+                )
+                .route(
+                    "/api",
+                    get(|| async {
+                        (
+                            [(
+                                axum::http::header::CONTENT_TYPE,
+                                "application/json; charset=utf-8",
+                            )],
+                            r#"{"ok":true}"#,
+                        )
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let fetch = |path: &str| WebFetchArgs {
+                url: Some(format!("http://{addr}{path}")),
+                fetch_backend: Some("local".to_string()),
+                timeout_ms: Some(2_000),
+                include_text: Some(true),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                expect_content_type: Some("Application/JSON".to_string()),
+                ..Default::default()
+            };
+
+            let r = svc.web_fetch(p(fetch("/page"))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(false), "payload={v}");
+            assert_eq!(v["error"]["code"].as_str(), Some("content_type_mismatch"));
+            assert_eq!(v["content_type"].as_str(), Some("text/html"));
+            assert_eq!(v["sniffed_content_type"].as_str(), Some("text/html"));
+            assert!(v["error"]["message"]
+                .as_str()
+                .unwrap_or("")
+                .contains("text/html"));
+            assert!(v.get("body_text").is_none());
+
+            let r = svc.web_fetch(p(fetch("/api"))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["body_text"].as_str(), Some(r#"{"ok":true}"#));
+            assert_eq!(
+                v["request"]["expect_content_type"].as_str(),
+                Some("Application/JSON")
+            );
+        }
+
+        #[tokio::test]
+        async fn http_error_status_fails_calls_and_include_error_body_keeps_the_page() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+            let app = Router::new()
+                .route(
+                    "/missing",
+                    get(|| async {
+                        (
+                            axum::http::StatusCode::NOT_FOUND,
+                            [(axum::http::header::CONTENT_TYPE, "text/html")],
+                            "<html><body><main><h1>Not Found</h1>\
+                             <p>NEEDLE_404 the page you requested does not exist.</p></main></body></html>",
+                        )
+                    }),
+                )
+                .route(
+                    "/ok",
+                    get(|| async {
+                        (
+                            [(axum::http::header::CONTENT_TYPE, "text/html")],
+                            "<html><body><main><p>NEEDLE_404 real content here.</p></main></body></html>",
+                        )
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            let missing = format!("http://{addr}/missing");
+            let svc = WebpipeMcp::new().expect("new");
+
+            // web_fetch
+            let fetch = |include_error_body: Option<bool>| WebFetchArgs {
+                url: Some(missing.clone()),
+                fetch_backend: Some("local".to_string()),
+                timeout_ms: Some(2_000),
+                include_text: Some(true),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                include_error_body,
+                ..Default::default()
+            };
+            let v =
+                payload_from_call_tool_result(&svc.web_fetch(p(fetch(None))).await.expect("call"));
+            assert_eq!(v["ok"].as_bool(), Some(false), "payload={v}");
+            assert_eq!(v["error"]["code"].as_str(), Some("http_status"));
+            assert_eq!(v["error"]["status"].as_u64(), Some(404));
+            assert_eq!(v["error"]["retryable"].as_bool(), Some(false));
+            assert_eq!(v["status"].as_u64(), Some(404));
+            assert!(v.get("body_text").is_none(), "payload={v}");
+            let v = payload_from_call_tool_result(
+                &svc.web_fetch(p(fetch(Some(true)))).await.expect("call"),
+            );
+            assert_eq!(v["ok"].as_bool(), Some(false));
+            assert!(v["body_text"].as_str().unwrap_or("").contains("NEEDLE_404"));
+
+            // web_extract
+            let extract = |include_error_body: Option<bool>| WebExtractArgs {
+                url: Some(missing.clone()),
+                fetch_backend: Some("local".to_string()),
+                timeout_ms: Some(2_000),
+                include_text: Some(true),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                include_error_body,
+                ..Default::default()
+            };
+            let v = payload_from_call_tool_result(
+                &svc.web_extract(p(extract(None))).await.expect("call"),
+            );
+            assert_eq!(v["ok"].as_bool(), Some(false), "payload={v}");
+            assert_eq!(v["error"]["code"].as_str(), Some("http_status"));
+            assert!(v["extract"].get("text").is_none(), "payload={v}");
+            assert_eq!(v["top_chunks"].as_array().map(|a| a.len()), Some(0));
+            let v = payload_from_call_tool_result(
+                &svc.web_extract(p(extract(Some(true)))).await.expect("call"),
+            );
+            assert_eq!(v["ok"].as_bool(), Some(false));
+            assert!(v["extract"]["text"]
+                .as_str()
+                .unwrap_or("")
+                .contains("NEEDLE_404"));
+
+            // web_search_extract: the 404 is a failed per-URL result and never reaches top_chunks.
+            let search_extract = |include_error_body: Option<bool>| WebSearchExtractArgs {
+                query: Some("NEEDLE_404".to_string()),
+                urls: Some(vec![missing.clone(), format!("http://{addr}/ok")]),
+                url_selection_mode: Some("preserve".to_string()),
+                fetch_backend: Some("local".to_string()),
+                no_network: Some(false),
+                agentic: Some(false),
+                include_text: Some(true),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                timeout_ms: Some(2_000),
+                compact: Some(false),
+                include_error_body,
+                ..Default::default()
+            };
+            let v = payload_from_call_tool_result(
+                &svc.web_search_extract(p(search_extract(None)))
+                    .await
+                    .expect("call"),
+            );
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            let bad = v["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|r| r["url"].as_str() == Some(missing.as_str()))
+                .expect("404 result")
+                .clone();
+            assert_eq!(bad["ok"].as_bool(), Some(false), "bad={bad}");
+            assert_eq!(bad["error"]["code"].as_str(), Some("http_status"));
+            assert!(bad["extract"].get("text").is_none(), "bad={bad}");
+            let top = v["top_chunks"].as_array().unwrap();
+            assert!(!top.is_empty());
+            assert!(top
+                .iter()
+                .all(|c| c["url"].as_str() != Some(missing.as_str())));
+            let v = payload_from_call_tool_result(
+                &svc.web_search_extract(p(search_extract(Some(true))))
+                    .await
+                    .expect("call"),
+            );
+            let bad = v["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|r| r["url"].as_str() == Some(missing.as_str()))
+                .expect("404 result")
+                .clone();
+            assert_eq!(bad["ok"].as_bool(), Some(false));
+            assert!(bad["extract"]["text"]
+                .as_str()
+                .unwrap_or("")
+                .contains("NEEDLE_404"));
+        }
+
+        #[tokio::test]
+        async fn web_extract_sectioned_returns_heading_keyed_sections() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+            let app = Router::new().route(
+                "/doc",
+                get(|| async {
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "text/html")],
+                        "<html><body><main><p>Preface text.</p>\
+                         <h1>Manual</h1><p>Overview.</p>\
+                         <h2>Setup</h2><p>Install it.</p><h3>Windows</h3><p>Use the MSI.</p>\
+                         <h2>Config</h2><p>Edit the file.</p></main></body></html>",
+                    )
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_extract(p(WebExtractArgs {
+                    url: Some(format!("http://{addr}/doc")),
+                    fetch_backend: Some("local".to_string()),
+                    timeout_ms: Some(2_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    include_structure: Some(false),
+                    sectioned: Some(true),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["sectioned"].as_bool(), Some(true));
+            // Structure is computed for sectioning but not returned when not requested.
+            assert!(v["extract"].get("structure").is_none());
+
+            let secs = v["extract"]["sections"].as_array().expect("sections");
+            let got: Vec<(serde_json::Value, u64, &str)> = secs
+                .iter()
+                .map(|s| {
+                    (
+                        s["heading"].clone(),
+                        s["level"].as_u64().unwrap(),
+                        s["text"].as_str().unwrap(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                got,
+                vec![
+                    (serde_json::Value::Null, 0, "Preface text."),
+                    (
+                        serde_json::json!("Manual"),
+                        1,
+                        "Manual\n\nOverview.\n\nSetup\n\nInstall it.\n\nWindows\n\nUse the MSI.\n\nConfig\n\nEdit the file."
+                    ),
+                    (
+                        serde_json::json!("Setup"),
+                        2,
+                        "Setup\n\nInstall it.\n\nWindows\n\nUse the MSI."
+                    ),
+                    (serde_json::json!("Windows"), 3, "Windows\n\nUse the MSI."),
+                    (serde_json::json!("Config"), 2, "Config\n\nEdit the file."),
+                ]
+            );
+            let last = secs.last().unwrap();
+            assert!(last["start_char"].as_u64() < last["end_char"].as_u64());
+        }
+
         #[tokio::test]
         async fn web_search_extract_firecrawl_fallback_on_empty_extraction_is_bounded() {
             // This is a fully offline test: we stand up one local server that:
@@ -25085,12 +36654,32 @@ Rules:
                     no_network: Some(false),
                     width: Some(80),
                     max_chars: Some(2_000),
+                    truncation_strategy: None,
+                    engine: None,
                     query: None,
                     top_chunks: Some(3),
                     max_chunk_chars: Some(200),
                     include_text: Some(true),
                     include_links: Some(true),
                     max_links: Some(10),
+                    include_alternates: None,
+                    prefer_amp: None,
+                    include_entities: None,
+                    include_tables: None,
+                    include_metadata: None,
+                    table_format: None,
+                    include_dates: None,
+                    date_order: None,
+                    include_keywords: None,
+                    max_keywords: None,
+                    chunk_dom_paths: None,
+                    referer: None,
+                    sentences: None,
+                    include_noscript: None,
+                    follow_iframes: None,
+                    allow_cross_origin_iframes: None,
+                    max_iframes: None,
+                    include_error_body: None,
                     timeout_ms: Some(2_000),
                     max_bytes: Some(200_000),
                     retry_on_truncation: None,
@@ -25102,9 +36691,11 @@ Rules:
                     max_outline_items: None,
                     max_blocks: None,
                     max_block_chars: None,
+                    sectioned: None,
                     semantic_rerank: None,
                     semantic_auto_fallback: None,
                     semantic_top_k: None,
+                    include_raw_html: None,
                 }))
                 .await
                 .expect("call");
@@ -25117,6 +36708,2128 @@ Rules:
             assert_eq!(v["extract"]["text_chars"].as_u64(), Some(expected_bytes));
         }
 
+        #[tokio::test]
+        async fn web_extract_sentences_splits_selected_chunks_with_absolute_offsets() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+            let html = "<html><body><article><p>Pi is roughly 3.14 in the U.S. and elsewhere. \
+                        Measurement tables, e.g. the NIST ones, agree. Dr. Lee wrote the tables.</p>\
+                        </article></body></html>";
+            let app =
+                Router::new().route(
+                    "/",
+                    get(move || async move {
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], html)
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_extract(p(WebExtractArgs {
+                    url: Some(format!("http://{addr}/")),
+                    query: Some("measurement tables".to_string()),
+                    sentences: Some(true),
+                    include_text: Some(true),
+                    width: Some(1_000),
+                    timeout_ms: Some(2_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["sentences"].as_bool(), Some(true));
+            let text: Vec<char> = v["extract"]["text"].as_str().unwrap().chars().collect();
+            let chunk = &v["extract"]["chunks"][0];
+            let sents = chunk["sentences"].as_array().expect("sentences");
+            let got: Vec<&str> = sents.iter().filter_map(|s| s["text"].as_str()).collect();
+            assert_eq!(
+                got,
+                vec![
+                    "Pi is roughly 3.14 in the U.S. and elsewhere.",
+                    "Measurement tables, e.g. the NIST ones, agree.",
+                    "Dr. Lee wrote the tables.",
+                ]
+            );
+            for s in sents {
+                let a = s["start_char"].as_u64().unwrap() as usize;
+                let b = s["end_char"].as_u64().unwrap() as usize;
+                let slice: String = text[a..b].iter().collect();
+                assert_eq!(Some(slice.as_str()), s["text"].as_str());
+            }
+        }
+
+        #[tokio::test]
+        async fn web_extract_include_noscript_recovers_js_shell_content_only_when_enabled() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+            let html = r#"<html><head><title>App</title></head><body>
+<div id="root">Loading...</div>
+<noscript><p>You need to enable JavaScript to run this app.</p></noscript>
+<noscript><article><h1>Fusion primer</h1>
+<p>Tokamaks confine plasma with strong magnetic fields shaped like a torus.</p>
+<p>Loading...</p></article></noscript>
+<script>window.__APP__ = {};</script>
+</body></html>"#;
+            let app =
+                Router::new().route(
+                    "/",
+                    get(move || async move {
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], html)
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let call = |include_noscript: Option<bool>| WebExtractArgs {
+                url: Some(format!("http://{addr}/")),
+                include_text: Some(true),
+                include_noscript,
+                timeout_ms: Some(2_000),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                ..Default::default()
+            };
+
+            let r = svc.web_extract(p(call(None))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            let text = v["extract"]["text"].as_str().unwrap_or("");
+            assert!(!text.contains("magnetic fields"), "text={text}");
+
+            let r = svc.web_extract(p(call(Some(true)))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["include_noscript"].as_bool(), Some(true));
+            assert_eq!(v["extract"]["engine"].as_str(), Some("html_noscript"));
+            let text = v["extract"]["text"].as_str().unwrap_or("");
+            assert!(text.contains("magnetic fields"), "text={text}");
+            assert_eq!(text.matches("Loading...").count(), 1, "text={text}");
+            assert!(!text.contains("enable JavaScript"), "text={text}");
+            assert!(v["warning_codes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|w| w.as_str() == Some("noscript_merged")));
+        }
+
+        #[test]
+        fn trim_to_max_output_bytes_drops_lowest_value_content_first() {
+            let chunk = |i: u64| {
+                serde_json::json!({
+                    "url": format!("https://example.com/{i}"),
+                    "score": 100 - i,
+                    "start_char": 0,
+                    "end_char": 400,
+                    "text": "x".repeat(400),
+                })
+            };
+            let result = |i: u64| {
+                serde_json::json!({
+                    "url": format!("https://example.com/{i}"),
+                    "ok": true,
+                    "status": 200,
+                    "warning_hints": {"a": "y".repeat(100)},
+                    "extract": {
+                        "engine": "html_main",
+                        "text": "t".repeat(2_000),
+                        "chunks": [chunk(i)],
+                        "text_preview": "p".repeat(300),
+                    },
+                })
+            };
+            let mut payload = serde_json::json!({
+                "ok": true,
+                "query": "q",
+                "request": {"max_urls": 8},
+                "top_chunks": (0..8).map(chunk).collect::<Vec<_>>(),
+                "contrasting_chunks": [chunk(20), chunk(21)],
+                "results": (0..8).map(result).collect::<Vec<_>>(),
+                "diagnostics": {"note": "d".repeat(500)},
+                "warning_codes": [],
+            });
+            add_envelope_fields(&mut payload, "web_search_extract", 5);
+            let before = serde_json::to_vec(&payload).unwrap().len();
+            assert!(before > 20_000, "{before}");
+
+            // A generous cap is a no-op.
+            let mut same = payload.clone();
+            trim_to_max_output_bytes(&mut same, before);
+            assert_eq!(same, payload);
+
+            // Dropping contrasting chunks and the 3 worst top_chunks isn't enough; per-URL text
+            // has to go too, and nothing past that.
+            let mut v = payload.clone();
+            trim_to_max_output_bytes(&mut v, 5_500);
+            let after = serde_json::to_vec(&v).unwrap().len();
+            assert!(after <= 5_500, "{after}");
+            let t = &v["output_truncated"];
+            assert_eq!(t["fits"], true);
+            assert_eq!(t["bytes_after"].as_u64(), Some(after as u64));
+            assert_eq!(t["bytes_before"].as_u64(), Some(before as u64));
+            assert_eq!(t["dropped_contrasting_chunks"], 2);
+            assert_eq!(t["dropped_top_chunks"], 7);
+            assert_eq!(t["kept_top_chunks"], 1);
+            assert_eq!(v["top_chunks"][0]["score"], 100, "the best chunk survives");
+            assert_eq!(
+                t["dropped_result_fields"],
+                serde_json::json!(["extract.text", "extract.chunks", "extract.text_preview"])
+            );
+            assert_eq!(t["dropped_results"], 0);
+            assert_eq!(v["results"].as_array().unwrap().len(), 8);
+            assert!(v.get("diagnostics").is_some());
+            assert!(v["warning_codes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|c| c == "output_truncated"));
+            for k in [
+                "ok",
+                "schema_version",
+                "kind",
+                "elapsed_ms",
+                "request",
+                "attempts",
+            ] {
+                assert_eq!(v[k], payload[k], "{k}");
+            }
+
+            // A tight cap also drops other top-level keys, then whole results from the end.
+            let mut v = payload.clone();
+            trim_to_max_output_bytes(&mut v, 1_000);
+            let t = &v["output_truncated"];
+            assert_eq!(t["fits"], true, "{v}");
+            assert!(serde_json::to_vec(&v).unwrap().len() <= 1_000);
+            assert!(t["dropped_keys"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|k| k == "diagnostics"));
+            assert!(t["dropped_results"].as_u64().unwrap() > 0);
+            assert_eq!(v["ok"], true);
+        }
+
+        #[tokio::test]
+        async fn typed_extraction_results_match_captured_json_fixtures() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+
+            let app = Router::new().route(
+                "/guide",
+                get(|| async {
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "text/html")],
+                        r#"<html><head><title>Retry guide</title></head><body><main>
+<h1>Retry guide</h1>
+<p>Workers retry failed jobs with exponential backoff and jitter.</p>
+<p>However, idempotent jobs can retry immediately without backoff.</p>
+<p>Unrelated footer text about the company and its history.</p>
+</main></body></html>"#,
+                    )
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            let url = format!("http://{addr}/guide");
+            // Stable across runs: fixed host, no wall-clock or port-derived fields.
+            fn normalize(v: &mut serde_json::Value, url: &str) {
+                match v {
+                    serde_json::Value::Object(m) => {
+                        m.remove("elapsed_ms");
+                        m.remove("doc_id");
+                        m.values_mut().for_each(|x| normalize(x, url));
+                    }
+                    serde_json::Value::Array(xs) => xs.iter_mut().for_each(|x| normalize(x, url)),
+                    serde_json::Value::String(s) if s.as_str() == url => {
+                        *s = "http://fixture.test/guide".to_string();
+                    }
+                    _ => {}
+                }
+            }
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_extract(p(WebExtractArgs {
+                    url: Some(url.clone()),
+                    query: Some("retry backoff".to_string()),
+                    include_text: Some(true),
+                    max_chunk_chars: Some(100),
+                    timeout_ms: Some(2_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let mut extract = payload_from_call_tool_result(&r)["extract"].clone();
+            normalize(&mut extract, &url);
+
+            let r = svc
+                .web_search_extract(p(WebSearchExtractArgs {
+                    query: Some("retry backoff".to_string()),
+                    urls: Some(vec![url.clone()]),
+                    max_parallel_urls: Some(1),
+                    fetch_backend: Some("local".to_string()),
+                    timeout_ms: Some(2_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    top_chunks: Some(2),
+                    max_chunk_chars: Some(100),
+                    compact: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            let mut search_extract = serde_json::json!({
+                "result": v["results"][0],
+                "top_chunks": v["top_chunks"],
+            });
+            normalize(&mut search_extract, &url);
+
+            let got = serde_json::json!({
+                "web_extract.extract": extract,
+                "web_search_extract": search_extract,
+            });
+            // Compare serialized text, not parsed values: the output must stay byte-identical.
+            assert_eq!(
+                serde_json::to_string_pretty(&got).unwrap(),
+                include_str!("../fixtures/typed_results_v1.json").trim_end()
+            );
+        }
+
+        #[tokio::test]
+        async fn web_extract_follow_iframes_inlines_same_origin_frames_only() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+
+            let html_route = |body: String| {
+                get(move || {
+                    let body = body.clone();
+                    async move { ([(axum::http::header::CONTENT_TYPE, "text/html")], body) }
+                })
+            };
+            // A second server is a different origin (different port).
+            let other = Router::new().route(
+                "/widget",
+                html_route(
+                    "<html><body><p>Cross origin advertising widget copy.</p></body></html>"
+                        .to_string(),
+                ),
+            );
+            let other_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let other_addr = other_listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(other_listener, other)
+                    .await
+                    .expect("axum serve");
+            });
+
+            let page = format!(
+                r#"<html><body><main><h1>API reference</h1>
+<p>The reference lives in the frame below.</p>
+<iframe src="/frame/reference.html#top"></iframe>
+<iframe src="http://{other_addr}/widget"></iframe>
+</main></body></html>"#
+            );
+            let app = Router::new().route("/docs", html_route(page)).route(
+                "/frame/reference.html",
+                html_route(
+                    "<html><body><article><p>Call connect() before sending any frames.</p>\
+                     </article></body></html>"
+                        .to_string(),
+                ),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let extract = |follow_iframes: Option<bool>, cross: Option<bool>| {
+                let svc = &svc;
+                async move {
+                    let r = svc
+                        .web_extract(p(WebExtractArgs {
+                            url: Some(format!("http://{addr}/docs")),
+                            include_text: Some(true),
+                            follow_iframes,
+                            allow_cross_origin_iframes: cross,
+                            timeout_ms: Some(2_000),
+                            cache_read: Some(false),
+                            cache_write: Some(false),
+                            ..Default::default()
+                        }))
+                        .await
+                        .expect("call");
+                    let v = payload_from_call_tool_result(&r);
+                    assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+                    v
+                }
+            };
+
+            let v = extract(None, None).await;
+            let text = v["extract"]["text"].as_str().unwrap_or("");
+            assert!(text.contains("API reference"), "{text}");
+            assert!(!text.contains("connect()"), "{text}");
+            assert!(v["extract"].get("iframes").is_none());
+
+            let v = extract(Some(true), None).await;
+            let text = v["extract"]["text"].as_str().unwrap_or("");
+            let frame_url = format!("http://{addr}/frame/reference.html");
+            assert!(text.contains("API reference"), "{text}");
+            assert!(
+                text.contains(&format!("[iframe: {frame_url}]\nCall connect() before")),
+                "{text}"
+            );
+            assert!(!text.contains("advertising widget"), "{text}");
+            let frames = v["extract"]["iframes"].as_array().expect("iframes");
+            assert_eq!(frames.len(), 2, "{v}");
+            assert_eq!(frames[0]["url"].as_str(), Some(frame_url.as_str()));
+            assert_eq!(frames[0]["merged"].as_bool(), Some(true));
+            assert_eq!(frames[1]["skipped"].as_str(), Some("cross_origin"));
+            assert!(v["warning_codes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|w| w.as_str() == Some("iframes_merged")));
+
+            let v = extract(Some(true), Some(true)).await;
+            let text = v["extract"]["text"].as_str().unwrap_or("");
+            assert!(text.contains("advertising widget"), "{text}");
+            assert_eq!(
+                v["extract"]["iframes"][1]["same_origin"].as_bool(),
+                Some(false)
+            );
+        }
+
+        #[tokio::test]
+        async fn web_extract_include_entities_returns_merged_schema_org_entities() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+
+            let html = r#"<html><head>
+  <script type="application/ld+json">{"@context":"https://schema.org","@type":"Recipe","name":"Pancakes","recipeYield":"4"}</script>
+</head><body>
+  <div itemscope itemtype="https://schema.org/Recipe">
+    <h1 itemprop="name">Pancakes</h1>
+    <span itemprop="recipeYield">6</span>
+    <span itemprop="cookTime" content="PT15M">15 minutes</span>
+  </div>
+  <article><p>Whisk eggs and flour, then fry.</p></article>
+</body></html>"#;
+            let app =
+                Router::new().route(
+                    "/recipe",
+                    get(move || async move {
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], html)
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_extract(p(WebExtractArgs {
+                    url: Some(format!("http://{addr}/recipe")),
+                    include_entities: Some(true),
+                    timeout_ms: Some(2_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["include_entities"].as_bool(), Some(true));
+            let es = v["extract"]["entities"].as_array().expect("entities");
+            assert_eq!(es.len(), 1, "entities={es:?}");
+            let e = &es[0];
+            assert_eq!(e["type"].as_str(), Some("Recipe"));
+            assert_eq!(e["sources"], serde_json::json!(["json-ld", "microdata"]));
+            assert_eq!(e["properties"]["cookTime"].as_str(), Some("PT15M"));
+            assert_eq!(e["properties"]["recipeYield"].as_str(), Some("4"));
+            assert_eq!(e["conflicts"][0]["property"].as_str(), Some("recipeYield"));
+            assert_eq!(
+                e["conflicts"][0]["values"][1]["source"].as_str(),
+                Some("microdata")
+            );
+            assert_eq!(e["conflicts"][0]["values"][1]["value"].as_str(), Some("6"));
+        }
+
+        #[tokio::test]
+        async fn web_extract_engine_hint_selects_the_html_extractor() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+
+            let html = r#"<html><body>
+  <nav><a href="/a">Docs</a> <a href="/b">Blog</a> <a href="/c">Pricing</a></nav>
+  <div class="post-body">
+    <p>The first paragraph sets up the question, names the dataset, and states the result.</p>
+    <p>The second paragraph walks through the method, step by step, with the caveats.</p>
+    <p>The third paragraph compares against prior work, and explains the remaining gap.</p>
+    <p>The last paragraph lists open problems, future datasets, and where to get the code.</p>
+  </div>
+</body></html>"#;
+            let app =
+                Router::new().route(
+                    "/post",
+                    get(move || async move {
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], html)
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let args = |engine: &str| WebExtractArgs {
+                url: Some(format!("http://{addr}/post")),
+                engine: Some(engine.to_string()),
+                include_text: Some(true),
+                timeout_ms: Some(2_000),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                ..Default::default()
+            };
+            let v = payload_from_call_tool_result(
+                &svc.web_extract(p(args("readability"))).await.expect("call"),
+            );
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["engine"].as_str(), Some("readability"));
+            assert_eq!(v["extract"]["engine"].as_str(), Some("readability"));
+            let text = v["extract"]["text"].as_str().unwrap_or_default();
+            assert!(
+                text.contains("third paragraph") && !text.contains("Pricing"),
+                "{text}"
+            );
+
+            let v = payload_from_call_tool_result(
+                &svc.web_extract(p(args("html2text"))).await.expect("call"),
+            );
+            assert_eq!(v["extract"]["engine"].as_str(), Some("html2text"));
+            assert!(v["extract"]["text"]
+                .as_str()
+                .unwrap_or_default()
+                .contains("Pricing"));
+
+            let v = payload_from_call_tool_result(
+                &svc.web_extract(p(args("boilerpipe"))).await.expect("call"),
+            );
+            assert_eq!(v["ok"].as_bool(), Some(false));
+            assert_eq!(v["error"]["code"].as_str(), Some("invalid_params"));
+        }
+
+        #[tokio::test]
+        async fn web_fetch_and_web_extract_include_metadata_resolve_preview_urls() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+
+            let html = r#"<html><head>
+  <meta property="og:title" content="Preview title">
+  <meta property="og:site_name" content="Example">
+  <meta property="og:image" content="/img/cover.jpg">
+  <link rel="canonical" href="/posts/1">
+  <script type="application/ld+json">{"@type":"BlogPosting","author":{"name":"Sam Doe"}}</script>
+</head><body><article><p>Short post body.</p></article></body></html>"#;
+            let app =
+                Router::new().route(
+                    "/p",
+                    get(move || async move {
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], html)
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_fetch(p(WebFetchArgs {
+                    url: Some(format!("http://{addr}/p")),
+                    include_metadata: Some(true),
+                    timeout_ms: Some(2_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["include_metadata"].as_bool(), Some(true));
+            let m = &v["metadata"];
+            assert_eq!(m["title"].as_str(), Some("Preview title"));
+            assert_eq!(m["site_name"].as_str(), Some("Example"));
+            assert_eq!(m["author"].as_str(), Some("Sam Doe"));
+            let image = format!("http://{addr}/img/cover.jpg");
+            assert_eq!(m["image"].as_str(), Some(image.as_str()));
+            let canonical = format!("http://{addr}/posts/1");
+            assert_eq!(m["canonical_url"].as_str(), Some(canonical.as_str()));
+            assert_eq!(m["json_ld"][0]["@type"].as_str(), Some("BlogPosting"));
+
+            let r = svc
+                .web_extract(p(WebExtractArgs {
+                    url: Some(format!("http://{addr}/p")),
+                    include_metadata: Some(true),
+                    timeout_ms: Some(2_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["extract"]["metadata"], m.clone());
+        }
+
+        #[tokio::test]
+        async fn web_extract_include_tables_returns_rows_and_optional_markdown() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+
+            let html = r#"<html><body>
+  <table role="presentation"><tr><td>nav</td><td>menu</td></tr></table>
+  <article><p>Quarterly numbers below.</p>
+  <table>
+    <thead><tr><th>Quarter</th><th>Revenue</th></tr></thead>
+    <tbody><tr><td>Q1</td><td>10 | 12</td></tr><tr><td colspan="2">n/a</td></tr></tbody>
+  </table></article>
+</body></html>"#;
+            let app =
+                Router::new().route(
+                    "/report",
+                    get(move || async move {
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], html)
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_extract(p(WebExtractArgs {
+                    url: Some(format!("http://{addr}/report")),
+                    include_tables: Some(true),
+                    table_format: Some("markdown".to_string()),
+                    timeout_ms: Some(2_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["include_tables"].as_bool(), Some(true));
+            assert_eq!(v["request"]["table_format"].as_str(), Some("markdown"));
+            let ts = v["extract"]["tables"].as_array().expect("tables");
+            assert_eq!(ts.len(), 1, "tables={ts:?}");
+            assert_eq!(ts[0]["headers"], serde_json::json!(["Quarter", "Revenue"]));
+            assert_eq!(
+                ts[0]["rows"],
+                serde_json::json!([["Q1", "10 | 12"], ["n/a", ""]])
+            );
+            assert!(ts[0].get("truncated").is_none());
+            let md = ts[0]["markdown"].as_str().expect("markdown");
+            assert!(md.contains("| Q1 | 10 \\| 12 |"), "md={md}");
+
+            // Off by default.
+            let r = svc
+                .web_extract(p(WebExtractArgs {
+                    url: Some(format!("http://{addr}/report")),
+                    timeout_ms: Some(2_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert!(v["extract"].get("tables").is_none(), "payload={v}");
+        }
+
+        #[tokio::test]
+        async fn web_extract_include_raw_html_returns_bounded_body_prefix_only_when_requested() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+
+            let html = format!(
+                "<html><head><title>Raw</title></head><body><article><p>{}</p></article></body></html>",
+                "Snapshot text for the raw html option. ".repeat(40)
+            );
+            let body = html.clone();
+            let app = Router::new().route(
+                "/page",
+                get(move || {
+                    let body = body.clone();
+                    async move { ([(axum::http::header::CONTENT_TYPE, "text/html")], body) }
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let args = |include_raw_html: Option<bool>| WebExtractArgs {
+                url: Some(format!("http://{addr}/page")),
+                include_raw_html,
+                max_chars: Some(300),
+                timeout_ms: Some(2_000),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                ..Default::default()
+            };
+
+            let r = svc.web_extract(p(args(None))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["include_raw_html"].as_bool(), Some(false));
+            assert!(
+                v["extract"].get("raw_html").is_none(),
+                "extract={}",
+                v["extract"]
+            );
+
+            let r = svc.web_extract(p(args(Some(true)))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            let raw = v["extract"]["raw_html"].as_str().expect("raw_html");
+            assert_eq!(raw.chars().count(), 300);
+            assert!(html.starts_with(raw));
+            assert_eq!(v["extract"]["raw_html_chars"].as_u64(), Some(300));
+            assert_eq!(v["extract"]["raw_html_truncated"].as_bool(), Some(true));
+            assert_eq!(v["bytes"].as_u64(), Some(html.len() as u64));
+        }
+
+        #[tokio::test]
+        async fn web_extract_include_dates_normalizes_and_flags_ambiguous_dates() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+
+            let app = Router::new().route(
+                "/timeline",
+                get(|| async {
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "text/html")],
+                        "<html><body><main><p>Founded on March 3, 2019. \
+                         Series A closed 2021-06-30. Next review due 5/1/24.</p></main></body></html>",
+                    )
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let extract = |date_order: Option<&str>| {
+                let svc = &svc;
+                let date_order = date_order.map(str::to_string);
+                async move {
+                    let r = svc
+                        .web_extract(p(WebExtractArgs {
+                            url: Some(format!("http://{addr}/timeline")),
+                            include_dates: Some(true),
+                            date_order,
+                            include_text: Some(true),
+                            timeout_ms: Some(2_000),
+                            cache_read: Some(false),
+                            cache_write: Some(false),
+                            ..Default::default()
+                        }))
+                        .await
+                        .expect("call");
+                    let v = payload_from_call_tool_result(&r);
+                    assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+                    v
+                }
+            };
+
+            let v = extract(None).await;
+            assert_eq!(v["request"]["date_order"].as_str(), Some("auto"));
+            let text = v["extract"]["text"].as_str().expect("text");
+            let dates = v["extract"]["dates"].as_array().expect("dates");
+            let isos: Vec<Option<&str>> = dates.iter().map(|d| d["iso"].as_str()).collect();
+            assert_eq!(
+                isos,
+                vec![Some("2019-03-03"), Some("2021-06-30"), None],
+                "{v}"
+            );
+            for d in dates {
+                let raw = d["raw"].as_str().unwrap();
+                let at = d["char_offset"].as_u64().unwrap() as usize;
+                let found: String = text.chars().skip(at).take(raw.chars().count()).collect();
+                assert_eq!(found, raw);
+            }
+            assert_eq!(dates[2]["raw"].as_str(), Some("5/1/24"));
+            assert_eq!(dates[2]["ambiguous"].as_bool(), Some(true));
+            assert_eq!(
+                dates[2]["alternatives"],
+                serde_json::json!(["2024-05-01", "2024-01-05"])
+            );
+
+            let v = extract(Some("dmy")).await;
+            assert_eq!(v["extract"]["dates"][2]["iso"].as_str(), Some("2024-01-05"));
+            assert!(v["extract"]["dates"][2].get("alternatives").is_none());
+        }
+
+        #[tokio::test]
+        async fn web_extract_chunk_dom_paths_tags_html_chunks_only() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+
+            let para = |topic: &str| {
+                format!(
+                    "<p>{}</p>",
+                    format!("Notes on {topic} for the reactor design review. ").repeat(6)
+                )
+            };
+            let html = format!(
+                "<html><body><article><h1>Reactor design notes</h1>\
+                 <section><h2>Plant</h2>{}{}</section><section><h2>Fuel</h2>{}{}</section>\
+                 </article></body></html>",
+                para("cooling loops"),
+                para("turbine halls"),
+                para("fuel handling"),
+                para("containment domes"),
+            );
+            let text = "Notes on containment domes for the reactor design review.\n".repeat(20);
+            let app = Router::new()
+                .route(
+                    "/page",
+                    get(move || {
+                        let html = html.clone();
+                        async move { ([(axum::http::header::CONTENT_TYPE, "text/html")], html) }
+                    }),
+                )
+                .route(
+                    "/plain",
+                    get(move || {
+                        let text = text.clone();
+                        async move { ([(axum::http::header::CONTENT_TYPE, "text/plain")], text) }
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let extract = |path: &str, query: &str| WebExtractArgs {
+                url: Some(format!("http://{addr}{path}")),
+                query: Some(query.to_string()),
+                chunk_dom_paths: Some(true),
+                timeout_ms: Some(2_000),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                ..Default::default()
+            };
+
+            let r = svc
+                .web_extract(p(extract("/page", "containment domes")))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["chunk_dom_paths"].as_bool(), Some(true));
+            let chunks = v["extract"]["chunks"].as_array().expect("chunks");
+            assert!(!chunks.is_empty(), "payload={v}");
+            let top = &chunks[0];
+            // The chunk starts at its section heading (heading context), so that's its element.
+            assert!(
+                top["text"]
+                    .as_str()
+                    .unwrap_or("")
+                    .contains("containment domes"),
+                "chunks={chunks:?}"
+            );
+            assert_eq!(
+                top["dom_path"].as_str(),
+                Some("article > section:nth-of-type(2) > h2"),
+                "chunks={chunks:?}"
+            );
+
+            // Non-HTML engines: the flag is a no-op.
+            let r = svc
+                .web_extract(p(extract("/plain", "containment domes")))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            let chunks = v["extract"]["chunks"].as_array().expect("chunks");
+            assert!(!chunks.is_empty(), "payload={v}");
+            assert!(
+                chunks.iter().all(|c| c.get("dom_path").is_none()),
+                "{chunks:?}"
+            );
+        }
+
+        /// `semantic_rerank=true` when semantic can't run: every tool keeps lexical chunks and
+        /// reports the same `semantic_unavailable` result, and `webpipe_meta` says so.
+        async fn assert_semantic_fallback_is_uniform(
+            env: &EnvGuard,
+            expected_reason: &'static str,
+        ) {
+            use axum::{routing::get, Router};
+            let html = "<html><body><main><h1>Gear care</h1>\
+                        <p>Lubricate the gearbox monthly and check the gearbox seals.</p>\
+                        <p>Unrelated paragraph about the weather.</p></main></body></html>";
+            let app =
+                Router::new().route(
+                    "/doc",
+                    get(move || async move {
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], html)
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            env.remove("WEBPIPE_SEMANTIC_TIMEOUT_MS");
+            let svc = WebpipeMcp::new().expect("new");
+            let url = format!("http://{addr}/doc");
+            let query = "gearbox seals";
+
+            let expected = serde_json::json!(
+                webpipe_local::semantic::SemanticRerankResult::unavailable(expected_reason)
+            );
+            let has_code = |v: &serde_json::Value| {
+                v["warning_codes"]
+                    .as_array()
+                    .is_some_and(|a| a.iter().any(|c| c == "semantic_unavailable"))
+            };
+
+            let r = svc
+                .web_extract(p(WebExtractArgs {
+                    url: Some(url.clone()),
+                    query: Some(query.to_string()),
+                    fetch_backend: Some("local".to_string()),
+                    semantic_rerank: Some(true),
+                    cache_write: Some(true),
+                    ..Default::default()
+                }))
+                .await
+                .expect("web_extract");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert!(has_code(&v), "payload={v}");
+            assert_eq!(v["extract"]["semantic"], expected, "payload={v}");
+            assert!(v["extract"]["chunks"][0]["text"]
+                .as_str()
+                .is_some_and(|t| t.contains("gearbox")));
+
+            let r = svc
+                .web_search_extract(p(WebSearchExtractArgs {
+                    query: Some(query.to_string()),
+                    urls: Some(vec![url.clone()]),
+                    fetch_backend: Some("local".to_string()),
+                    semantic_rerank: Some(true),
+                    ..Default::default()
+                }))
+                .await
+                .expect("web_search_extract");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert!(has_code(&v), "payload={v}");
+            assert_eq!(
+                v["results"][0]["extract"]["semantic"], expected,
+                "payload={v}"
+            );
+
+            let r = svc
+                .web_cache_search_extract(p(WebCacheSearchExtractArgs {
+                    query: Some(query.to_string()),
+                    semantic_rerank: Some(true),
+                    ..Default::default()
+                }))
+                .await
+                .expect("web_cache_search_extract");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert!(has_code(&v), "payload={v}");
+            assert_eq!(v["results"][0]["semantic"], expected, "payload={v}");
+            assert_eq!(
+                v["semantic_status"]["unavailable_reason"].as_str(),
+                Some(expected_reason)
+            );
+
+            let r = svc
+                .webpipe_meta(p(WebpipeMetaArgs::default()))
+                .await
+                .expect("meta");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["capabilities"]["semantic_rerank"].as_bool(), Some(false));
+            assert_eq!(
+                v["capabilities"]["semantic_unavailable_reason"].as_str(),
+                Some(expected_reason)
+            );
+        }
+
+        #[cfg(feature = "semantic")]
+        #[tokio::test]
+        async fn semantic_rerank_without_backend_falls_back_uniformly() {
+            let env = EnvGuard::new(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_SEMANTIC_TIMEOUT_MS",
+                "WEBPIPE_OPENROUTER_API_KEY",
+                "OPENROUTER_API_KEY",
+            ]);
+            env.remove("WEBPIPE_OPENROUTER_API_KEY");
+            env.remove("OPENROUTER_API_KEY");
+            assert_semantic_fallback_is_uniform(&env, "backend_not_configured").await;
+        }
+
+        #[cfg(all(feature = "semantic", not(feature = "embed-local")))]
+        #[tokio::test]
+        async fn semantic_rerank_with_local_backend_compiled_out_falls_back_uniformly() {
+            let env = EnvGuard::new(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_SEMANTIC_TIMEOUT_MS",
+                "WEBPIPE_EMBED_BACKEND",
+            ]);
+            env.set("WEBPIPE_EMBED_BACKEND", "local");
+            assert_semantic_fallback_is_uniform(&env, "embed_local_feature_disabled").await;
+        }
+
+        #[cfg(feature = "embed-local")]
+        #[tokio::test]
+        async fn semantic_rerank_with_local_backend_and_no_model_dir_falls_back_uniformly() {
+            let env = EnvGuard::new(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_SEMANTIC_TIMEOUT_MS",
+                "WEBPIPE_EMBED_BACKEND",
+                "WEBPIPE_EMBED_LOCAL_MODEL_DIR",
+            ]);
+            env.set("WEBPIPE_EMBED_BACKEND", "local");
+            env.remove("WEBPIPE_EMBED_LOCAL_MODEL_DIR");
+            assert_semantic_fallback_is_uniform(&env, "backend_not_configured").await;
+        }
+
+        #[cfg(not(feature = "semantic"))]
+        #[tokio::test]
+        async fn semantic_rerank_with_feature_off_falls_back_uniformly() {
+            let env = EnvGuard::new(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_SEMANTIC_TIMEOUT_MS",
+                "WEBPIPE_OPENROUTER_API_KEY",
+                "OPENROUTER_API_KEY",
+            ]);
+            // A configured backend doesn't help when the feature is compiled out.
+            env.set("WEBPIPE_OPENROUTER_API_KEY", "test-key");
+            assert_semantic_fallback_is_uniform(&env, "feature_disabled").await;
+        }
+
+        #[tokio::test]
+        async fn web_crawl_is_breadth_first_bounded_and_same_domain() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR", "WEBPIPE_PRIVACY_MODE"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            // `localhost` is a different host (and site) than `127.0.0.1` for scoping purposes.
+            let ext = format!("http://localhost:{}/ext", addr.port());
+            let pages: Vec<(&'static str, String, String)> = vec![
+                (
+                    "/",
+                    "Home".to_string(),
+                    format!(
+                        r#"<a href="/a">A</a> <a href="/b">B</a> <a href="/a#top">A again</a>
+                           <a href="/private">Private</a> <a href="{ext}">Elsewhere</a>"#
+                    ),
+                ),
+                (
+                    "/a",
+                    "Page A".to_string(),
+                    r#"<a href="/a/deep">Deep</a> <a href="/">Home</a>"#.to_string(),
+                ),
+                (
+                    "/b",
+                    "Page B".to_string(),
+                    r#"<a href="/b/deep">Deep</a>"#.to_string(),
+                ),
+                (
+                    "/a/deep",
+                    "A Deep".to_string(),
+                    r#"<a href="/a/deeper">Deeper</a>"#.to_string(),
+                ),
+                ("/b/deep", "B Deep".to_string(), String::new()),
+                ("/a/deeper", "A Deeper".to_string(), String::new()),
+                ("/private", "Private".to_string(), String::new()),
+                ("/ext", "External".to_string(), String::new()),
+            ];
+            let mut app = Router::new().route(
+                "/robots.txt",
+                get(|| async { "User-agent: *\nDisallow: /private\n" }),
+            );
+            for (path, title, links) in pages {
+                let html = format!(
+                    "<html><head><title>{title}</title></head><body><main>\
+                     <p>{title} has enough body text to be extracted as a chunk.</p>\
+                     {links}</main></body></html>"
+                );
+                app = app.route(
+                    path,
+                    get(move || {
+                        let html = html.clone();
+                        async move { ([(axum::http::header::CONTENT_TYPE, "text/html")], html) }
+                    }),
+                );
+            }
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let tmp = tempfile::tempdir().expect("tempdir");
+            _env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            _env.remove("WEBPIPE_PRIVACY_MODE");
+            let base = format!("http://{addr}");
+            let svc = WebpipeMcp::new().expect("new");
+            let crawl = |args: WebCrawlArgs| {
+                let svc = &svc;
+                async move {
+                    let r = svc.web_crawl(p(args)).await.expect("call");
+                    payload_from_call_tool_result(&r)
+                }
+            };
+            let paths = |v: &serde_json::Value| -> Vec<String> {
+                v["pages"]
+                    .as_array()
+                    .expect("pages")
+                    .iter()
+                    .map(|p| {
+                        let u = p["url"].as_str().unwrap();
+                        let u = reqwest::Url::parse(u).unwrap();
+                        format!("{}{}", u.host_str().unwrap(), u.path())
+                    })
+                    .collect()
+            };
+            let h = "127.0.0.1";
+
+            // Depth 1, same-domain: BFS over the start page's links; fragment dup, robots-blocked
+            // and external links are not crawled.
+            let v = crawl(WebCrawlArgs {
+                start_url: Some(format!("{base}/")),
+                max_depth: Some(1),
+                per_host_delay_ms: Some(0),
+                ..Default::default()
+            })
+            .await;
+            assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+            assert_eq!(v["kind"].as_str(), Some("web_crawl"));
+            assert_eq!(
+                paths(&v),
+                vec![format!("{h}/"), format!("{h}/a"), format!("{h}/b")]
+            );
+            let pages = v["pages"].as_array().unwrap();
+            assert_eq!(pages[0]["depth"].as_u64(), Some(0));
+            assert_eq!(pages[0]["title"].as_str(), Some("Home"));
+            assert!(pages[0]["chunk_count"].as_u64().unwrap_or(0) >= 1);
+            assert!(pages[0].get("text").is_none());
+            assert_eq!(pages[1]["depth"].as_u64(), Some(1));
+            assert_eq!(pages[1]["title"].as_str(), Some("Page A"));
+            assert_eq!(
+                pages[1]["found_on"].as_str(),
+                Some(format!("{base}/").as_str())
+            );
+            assert_eq!(v["skipped"][0]["reason"].as_str(), Some("robots"));
+            assert!(v["skipped"][0]["url"]
+                .as_str()
+                .unwrap()
+                .ends_with("/private"));
+            assert_eq!(
+                v["summary"]["stop_reason"].as_str(),
+                Some("frontier_exhausted")
+            );
+
+            // Depth 2 capped at 4 pages: level 1 finishes before level 2 starts.
+            let v = crawl(WebCrawlArgs {
+                start_url: Some(format!("{base}/")),
+                max_depth: Some(2),
+                max_pages: Some(4),
+                per_host_delay_ms: Some(0),
+                include_text: Some(true),
+                ..Default::default()
+            })
+            .await;
+            assert_eq!(
+                paths(&v),
+                vec![
+                    format!("{h}/"),
+                    format!("{h}/a"),
+                    format!("{h}/b"),
+                    format!("{h}/a/deep")
+                ]
+            );
+            assert_eq!(v["pages"][3]["depth"].as_u64(), Some(2));
+            assert!(v["pages"][3]["text"]
+                .as_str()
+                .unwrap()
+                .contains("A Deep has enough"));
+            assert_eq!(v["summary"]["stop_reason"].as_str(), Some("max_pages"));
+            assert_eq!(v["summary"]["frontier_remaining"].as_u64(), Some(1));
+            assert!(v["warnings"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|w| w == "crawl_truncated_by_max_pages"));
+
+            // Leaving same_domain off follows the external host too; robots off crawls /private.
+            let v = crawl(WebCrawlArgs {
+                start_url: Some(format!("{base}/")),
+                max_depth: Some(1),
+                same_domain: Some(false),
+                respect_robots: Some(false),
+                per_host_delay_ms: Some(0),
+                ..Default::default()
+            })
+            .await;
+            assert_eq!(
+                paths(&v),
+                vec![
+                    format!("{h}/"),
+                    format!("{h}/a"),
+                    format!("{h}/b"),
+                    format!("{h}/private"),
+                    "localhost/ext".to_string()
+                ]
+            );
+
+            let v = crawl(WebCrawlArgs {
+                start_url: Some("not a url".to_string()),
+                ..Default::default()
+            })
+            .await;
+            assert_eq!(v["ok"].as_bool(), Some(false));
+            assert_eq!(v["error"]["code"].as_str(), Some("invalid_url"));
+        }
+
+        #[tokio::test]
+        async fn web_crawl_firecrawl_backend_polls_the_job_and_ranks_chunks_across_pages() {
+            let env = EnvGuard::new(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_FIRECRAWL_API_KEY",
+                "FIRECRAWL_API_KEY",
+                "WEBPIPE_FIRECRAWL_CRAWL_ENDPOINT_V2",
+                "WEBPIPE_FIRECRAWL_CRAWL_POLL_MS",
+            ]);
+            use axum::{
+                extract::Path,
+                routing::{get, post},
+                Router,
+            };
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            let polls = Arc::new(AtomicUsize::new(0));
+            let polls2 = polls.clone();
+            let app = Router::new()
+                .route(
+                    "/v2/crawl",
+                    post(|axum::Json(req): axum::Json<serde_json::Value>| async move {
+                        assert_eq!(req["limit"].as_u64(), Some(5));
+                        axum::Json(serde_json::json!({ "success": true, "id": "job-1" }))
+                    }),
+                )
+                .route(
+                    "/v2/crawl/:id",
+                    get(move |Path(id): Path<String>| {
+                        let polls = polls2.clone();
+                        async move {
+                            assert_eq!(id, "job-1");
+                            // First poll: still running; second: done.
+                            if polls.fetch_add(1, Ordering::SeqCst) == 0 {
+                                return axum::Json(serde_json::json!({ "status": "scraping" }));
+                            }
+                            axum::Json(serde_json::json!({
+                                "status": "completed",
+                                "creditsUsed": 2,
+                                "data": [
+                                    { "markdown": "# Bonds\n\nBond yields rose again this quarter.",
+                                      "metadata": { "sourceURL": "https://docs.example/bonds", "title": "Bonds", "statusCode": 200 } },
+                                    { "markdown": "# Tides\n\nTide tables list high and low water for the estuary.",
+                                      "metadata": { "sourceURL": "https://docs.example/tides", "title": "Tides", "statusCode": 200 } }
+                                ]
+                            }))
+                        }
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            env.remove("WEBPIPE_FIRECRAWL_API_KEY");
+            env.remove("FIRECRAWL_API_KEY");
+            env.set(
+                "WEBPIPE_FIRECRAWL_CRAWL_ENDPOINT_V2",
+                &format!("http://{addr}/v2/crawl"),
+            );
+            env.set("WEBPIPE_FIRECRAWL_CRAWL_POLL_MS", "10");
+
+            let svc = WebpipeMcp::new().expect("new");
+            let args = || WebCrawlArgs {
+                start_url: Some("https://docs.example/".to_string()),
+                backend: Some("firecrawl".to_string()),
+                query: Some("tide tables".to_string()),
+                max_pages: Some(5),
+                top_chunks: Some(1),
+                ..Default::default()
+            };
+
+            // Without a key the backend is reported as not configured (no request is made).
+            let r = svc.web_crawl(p(args())).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(false));
+            assert_eq!(v["error"]["code"].as_str(), Some("not_configured"));
+            assert_eq!(polls.load(Ordering::SeqCst), 0);
+
+            env.set("WEBPIPE_FIRECRAWL_API_KEY", "test-key");
+            let r = svc.web_crawl(p(args())).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+            assert_eq!(v["backend"].as_str(), Some("firecrawl"));
+            assert_eq!(polls.load(Ordering::SeqCst), 2);
+            let pages = v["pages"].as_array().unwrap();
+            assert_eq!(pages.len(), 2);
+            assert_eq!(pages[0]["url"].as_str(), Some("https://docs.example/bonds"));
+            assert_eq!(pages[1]["title"].as_str(), Some("Tides"));
+            assert_eq!(
+                v["summary"]["stop_reason"].as_str(),
+                Some("firecrawl_completed")
+            );
+            let top = v["top_chunks"].as_array().unwrap();
+            assert_eq!(top.len(), 1);
+            assert_eq!(top[0]["url"].as_str(), Some("https://docs.example/tides"));
+
+            let r = svc
+                .web_crawl(p(WebCrawlArgs {
+                    backend: Some("spider".to_string()),
+                    ..args()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["error"]["code"].as_str(), Some("invalid_params"));
+        }
+
+        #[tokio::test]
+        async fn semantic_rerank_reuses_embeddings_persisted_on_disk() {
+            let env = EnvGuard::new(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_OPENROUTER_API_KEY",
+                "OPENROUTER_API_KEY",
+                "WEBPIPE_OPENROUTER_BASE_URL",
+                "WEBPIPE_EMBEDDINGS_CACHE",
+            ]);
+            env.remove("WEBPIPE_EMBEDDINGS_CACHE");
+            use axum::{routing::post, Router};
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            let inputs_seen = Arc::new(AtomicUsize::new(0));
+            let seen = inputs_seen.clone();
+            let app = Router::new().route(
+                "/v1/embeddings",
+                post(move |axum::Json(req): axum::Json<serde_json::Value>| {
+                    let seen = seen.clone();
+                    async move {
+                        let data: Vec<serde_json::Value> = req["input"]
+                            .as_array()
+                            .into_iter()
+                            .flatten()
+                            .map(|t| {
+                                seen.fetch_add(1, Ordering::SeqCst);
+                                let t = t.as_str().unwrap_or("");
+                                let tide = t.contains("tide") as u8 as f32;
+                                serde_json::json!({"embedding": [tide, 1.0 - tide]})
+                            })
+                            .collect();
+                        axum::Json(serde_json::json!({ "data": data }))
+                    }
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            env.set("WEBPIPE_OPENROUTER_BASE_URL", &format!("http://{addr}"));
+            env.set("WEBPIPE_OPENROUTER_API_KEY", "test-key");
+
+            let cands = vec![
+                (0, 20, "bond yields and earnings".to_string()),
+                (20, 40, "tide tables for the estuary".to_string()),
+            ];
+            let first = WebpipeMcp::new()
+                .expect("new")
+                .semantic_rerank_chunks_best("tide", &cands, 2)
+                .await;
+            assert_eq!(first.backend, "openrouter_embeddings");
+            assert_eq!(first.chunks[0].start_char, 20);
+            assert_eq!((first.cache_hits, first.cache_misses), (0, 3));
+            assert_eq!(inputs_seen.load(Ordering::SeqCst), 3);
+            assert!(tmp.path().join("embeddings").is_dir());
+
+            // A fresh server (empty in-memory cache) reads the vectors back from disk.
+            let svc = WebpipeMcp::new().expect("new");
+            let again = svc.semantic_rerank_chunks_best("tide", &cands, 2).await;
+            assert_eq!(again.chunks[0].start_char, 20);
+            assert_eq!((again.cache_hits, again.cache_misses), (3, 0));
+            assert_eq!(inputs_seen.load(Ordering::SeqCst), 3);
+            let v =
+                payload_from_call_tool_result(&svc.webpipe_usage(Parameters(None)).await.unwrap());
+            // Counters are process-wide (other tests may reset them); check the shape only.
+            for k in ["hits", "misses", "writes"] {
+                assert!(
+                    v["usage"]["embedding_cache"][k].is_u64(),
+                    "usage={}",
+                    v["usage"]
+                );
+            }
+        }
+
+        #[tokio::test]
+        async fn web_cache_search_extract_hybrid_surfaces_exact_term_and_paraphrase_docs() {
+            let env = EnvGuard::new(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_OPENROUTER_API_KEY",
+                "OPENROUTER_API_KEY",
+                "WEBPIPE_OPENROUTER_BASE_URL",
+            ]);
+            use axum::{routing::get, routing::post, Router};
+            use std::net::SocketAddr;
+
+            // Exact-term doc: shares "zorblax" with the query but is about gardening.
+            // Paraphrase doc: about capacitor upkeep, but shares no query terms.
+            let pages = [
+                (
+                    "/exact",
+                    "Zorblax is the name of our garden gnome. He watches the tomatoes, the soil, \
+                     and the compost heap during watering.",
+                ),
+                (
+                    "/para",
+                    "Servicing an electrical condenser: drain the stored charge before any \
+                     routine upkeep of the condenser.",
+                ),
+                (
+                    "/noise",
+                    "Quarterly market report covering bond yields and earnings.",
+                ),
+            ];
+            let mut app = Router::new();
+            for (path, body) in pages {
+                let html = format!("<html><body><article><p>{body}</p></article></body></html>");
+                app = app.route(
+                    path,
+                    get(move || {
+                        let html = html.clone();
+                        async move { ([(axum::http::header::CONTENT_TYPE, "text/html")], html) }
+                    }),
+                );
+            }
+            // Stub embeddings: dim 0 = capacitor-care vocabulary, dim 1 = gardening vocabulary.
+            fn embed(t: &str) -> Vec<f32> {
+                let mut v = vec![0.0f32; 2];
+                for w in t.to_lowercase().split(|c: char| !c.is_alphanumeric()) {
+                    match w {
+                        "capacitor" | "condenser" | "maintenance" | "servicing" | "upkeep"
+                        | "charge" | "electrical" => v[0] += 1.0,
+                        "garden" | "tomatoes" | "soil" | "compost" | "watering" => v[1] += 1.0,
+                        _ => {}
+                    }
+                }
+                v
+            }
+            app = app.route(
+                "/v1/embeddings",
+                post(
+                    |axum::Json(req): axum::Json<serde_json::Value>| async move {
+                        let data: Vec<serde_json::Value> = req["input"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .map(|t| serde_json::json!({"embedding": embed(t.as_str().unwrap_or(""))}))
+                        .collect();
+                        axum::Json(serde_json::json!({ "data": data }))
+                    },
+                ),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            env.set("WEBPIPE_OPENROUTER_BASE_URL", &format!("http://{addr}"));
+            let svc = WebpipeMcp::new().expect("new");
+            for (path, _) in pages {
+                let r = svc
+                    .web_fetch(p(WebFetchArgs {
+                        url: Some(format!("http://{addr}{path}")),
+                        fetch_backend: Some("local".to_string()),
+                        timeout_ms: Some(2_000),
+                        cache_read: Some(false),
+                        cache_write: Some(true),
+                        ..Default::default()
+                    }))
+                    .await
+                    .expect("warm");
+                assert_eq!(
+                    payload_from_call_tool_result(&r)["ok"].as_bool(),
+                    Some(true)
+                );
+            }
+
+            let search = |scoring: &str, weight: Option<f64>| WebCacheSearchExtractArgs {
+                query: Some("zorblax capacitor maintenance".to_string()),
+                scoring: Some(scoring.to_string()),
+                hybrid_weight: weight,
+                ..Default::default()
+            };
+            let paths = |v: &serde_json::Value| -> Vec<String> {
+                v["results"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|d| {
+                        let u = d["url"].as_str().unwrap_or("");
+                        u[u.rfind('/').unwrap_or(0)..].to_string()
+                    })
+                    .collect()
+            };
+
+            // Without embeddings: hybrid falls back to lexical (paraphrase doc is invisible).
+            let r = svc
+                .web_cache_search_extract(p(search("hybrid", None)))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(paths(&v), vec!["/exact"], "payload={v}");
+            assert_eq!(v["scoring"]["mode"].as_str(), Some("lexical"));
+            assert_eq!(
+                v["scoring"]["fallback_reason"].as_str(),
+                Some("embeddings_not_configured")
+            );
+            assert!(v["warning_codes"]
+                .as_array()
+                .is_some_and(|a| a.iter().any(|c| c == "hybrid_scoring_fell_back_to_lexical")));
+
+            env.set("WEBPIPE_OPENROUTER_API_KEY", "test-key");
+            // Pure semantic (weight 1): the exact-term doc drops out with the noise doc.
+            let r = svc
+                .web_cache_search_extract(p(search("hybrid", Some(1.0))))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(paths(&v), vec!["/para"], "payload={v}");
+
+            // Hybrid: both the exact-term and the paraphrase doc, noise excluded.
+            let r = svc
+                .web_cache_search_extract(p(search("hybrid", None)))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["scoring"]["mode"].as_str(), Some("hybrid"), "payload={v}");
+            let mut got = paths(&v);
+            got.sort();
+            assert_eq!(got, vec!["/exact", "/para"], "payload={v}");
+            let top = &v["results"][0]["hybrid"];
+            assert!(top["score"].as_f64().unwrap_or(0.0) > 0.0, "payload={v}");
+            // The earlier weight=1 call already embedded these chunks.
+            assert_eq!(
+                v["scoring"]["cache_misses"].as_u64(),
+                Some(0),
+                "payload={v}"
+            );
+            assert!(v["scoring"]["cache_hits"].as_u64().unwrap_or(0) >= 3);
+        }
+
+        #[tokio::test]
+        async fn extract_confidence_is_reported_per_url_high_for_articles_low_for_js_shells() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+
+            let para = "Tokamaks confine hot plasma with strong magnetic fields so that fusion \
+                        reactions can happen at useful rates. The field lines twist around the \
+                        torus, which keeps charged particles from drifting into the walls.";
+            let article = format!(
+                "<html><body><main><h1>How tokamaks work</h1><p>{para}</p><p>{para}</p>\
+                 <p>{para}</p><p>{para}</p></main></body></html>"
+            );
+            let shell = r#"<html><body><noscript>Please enable JavaScript to continue.</noscript>
+<div id="__next"></div></body></html>"#;
+            let app = Router::new()
+                .route(
+                    "/article",
+                    get(move || {
+                        let article = article.clone();
+                        async move { ([(axum::http::header::CONTENT_TYPE, "text/html")], article) }
+                    }),
+                )
+                .route(
+                    "/shell",
+                    get(move || async move {
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], shell)
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let mut conf = Vec::new();
+            for path in ["article", "shell"] {
+                let r = svc
+                    .web_extract(p(WebExtractArgs {
+                        url: Some(format!("http://{addr}/{path}")),
+                        timeout_ms: Some(2_000),
+                        cache_read: Some(false),
+                        cache_write: Some(false),
+                        ..Default::default()
+                    }))
+                    .await
+                    .expect("call");
+                let v = payload_from_call_tool_result(&r);
+                assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+                conf.push(v["extract"]["confidence"].as_f64().expect("confidence"));
+            }
+            let low = webpipe_local::extract::EXTRACTION_CONFIDENCE_LOW as f64;
+            assert!(conf[0] >= 0.8, "article confidence={}", conf[0]);
+            assert!(conf[1] < low, "shell confidence={}", conf[1]);
+
+            let r = svc
+                .web_search_extract(p(WebSearchExtractArgs {
+                    query: Some("tokamak plasma".to_string()),
+                    urls: Some(vec![
+                        format!("http://{addr}/article"),
+                        format!("http://{addr}/shell"),
+                    ]),
+                    url_selection_mode: Some("preserve".to_string()),
+                    fetch_backend: Some("local".to_string()),
+                    no_network: Some(false),
+                    max_urls: Some(2),
+                    timeout_ms: Some(2_000),
+                    agentic: Some(false),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            let results = v["results"].as_array().expect("results");
+            assert_eq!(results.len(), 2, "payload={v}");
+            let by_url = |suffix: &str| {
+                results
+                    .iter()
+                    .find(|x| x["url"].as_str().is_some_and(|u| u.ends_with(suffix)))
+                    .and_then(|x| x["extract"]["confidence"].as_f64())
+                    .expect("confidence")
+            };
+            assert!(by_url("/article") >= 0.8);
+            assert!(by_url("/shell") < low);
+        }
+
+        #[tokio::test]
+        async fn doc_id_is_stable_across_calls_and_ignores_tracking_params() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+
+            let app = Router::new().route(
+                "/docs/page",
+                get(|| async {
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "text/html")],
+                        "<html><body><main><p>Stable document body.</p></main></body></html>",
+                    )
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            let base = format!("http://{addr}/docs/page");
+
+            let svc = WebpipeMcp::new().expect("new");
+            let extract_doc_id = |url: String| {
+                let svc = &svc;
+                async move {
+                    let r = svc
+                        .web_extract(p(WebExtractArgs {
+                            url: Some(url),
+                            timeout_ms: Some(2_000),
+                            cache_read: Some(false),
+                            cache_write: Some(false),
+                            ..Default::default()
+                        }))
+                        .await
+                        .expect("call");
+                    let v = payload_from_call_tool_result(&r);
+                    assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+                    v["doc_id"].as_str().expect("doc_id").to_string()
+                }
+            };
+            let id = extract_doc_id(base.clone()).await;
+            assert_eq!(id, webpipe_local::doc_id::doc_id(&base));
+            assert_eq!(id, extract_doc_id(base.clone()).await);
+            assert_eq!(
+                id,
+                extract_doc_id(format!("{base}?utm_source=news&utm_medium=email#top")).await
+            );
+
+            let r = svc
+                .web_search_extract(p(WebSearchExtractArgs {
+                    query: Some("stable document".to_string()),
+                    urls: Some(vec![
+                        format!("{base}?fbclid=abc"),
+                        format!("{base}?gclid=1"),
+                    ]),
+                    url_selection_mode: Some("preserve".to_string()),
+                    fetch_backend: Some("local".to_string()),
+                    timeout_ms: Some(2_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            let results = v["results"].as_array().expect("results");
+            assert_eq!(results.len(), 2, "{v}");
+            for one in results {
+                assert_eq!(one["doc_id"].as_str(), Some(id.as_str()), "{one}");
+            }
+        }
+
+        #[tokio::test]
+        async fn recency_boost_ranks_newer_copies_of_identical_content_higher() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+
+            fn page(date: &str) -> String {
+                let meta = if date.is_empty() {
+                    String::new()
+                } else {
+                    format!(r#"<meta property="article:published_time" content="{date}">"#)
+                };
+                format!(
+                    "<html><head><title>Release notes</title>{meta}</head><body><main>\
+                     <p>The widget scheduler now batches retries and backs off per host.</p>\
+                     </main></body></html>"
+                )
+            }
+            let html = |date: &'static str| {
+                get(move || async move {
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "text/html")],
+                        page(date),
+                    )
+                })
+            };
+            let app = Router::new()
+                .route("/old", html("2019-03-01T09:00:00Z"))
+                .route("/undated", html(""))
+                .route("/new", html("2025-11-20"));
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            let urls: Vec<String> = ["old", "undated", "new"]
+                .iter()
+                .map(|n| format!("http://{addr}/{n}"))
+                .collect();
+
+            let svc = WebpipeMcp::new().expect("new");
+            let run = |boost: Option<f32>, parallel: usize| {
+                let svc = &svc;
+                let urls = urls.clone();
+                async move {
+                    let r = svc
+                        .web_search_extract(p(WebSearchExtractArgs {
+                            query: Some("widget scheduler retries".to_string()),
+                            urls: Some(urls),
+                            url_selection_mode: Some("preserve".to_string()),
+                            fetch_backend: Some("local".to_string()),
+                            max_parallel_urls: Some(parallel),
+                            timeout_ms: Some(2_000),
+                            cache_read: Some(false),
+                            cache_write: Some(false),
+                            top_chunks: Some(3),
+                            recency_boost: boost,
+                            recency_half_life_days: Some(365.0),
+                            ..Default::default()
+                        }))
+                        .await
+                        .expect("call");
+                    let v = payload_from_call_tool_result(&r);
+                    assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+                    v
+                }
+            };
+            let score_of = |v: &serde_json::Value, name: &str| {
+                v["top_chunks"]
+                    .as_array()
+                    .expect("top_chunks")
+                    .iter()
+                    .find(|c| c["url"].as_str().is_some_and(|u| u.ends_with(name)))
+                    .and_then(|c| c["score"].as_u64())
+                    .unwrap_or_else(|| panic!("no chunk for {name}: {v}"))
+            };
+
+            let plain = run(None, 1).await;
+            assert_eq!(
+                score_of(&plain, "/old"),
+                score_of(&plain, "/new"),
+                "{plain}"
+            );
+            assert!(plain["results"][0].get("recency_factor").is_none());
+            assert_eq!(
+                plain["results"][2]["published_time"].as_str(),
+                Some("2025-11-20")
+            );
+
+            for parallel in [1, 3] {
+                let v = run(Some(2.0), parallel).await;
+                let top = v["top_chunks"][0]["url"].as_str().unwrap_or("");
+                assert!(top.ends_with("/new"), "parallel={parallel}: {v}");
+                assert!(score_of(&v, "/new") > score_of(&v, "/old"), "{v}");
+                assert!(score_of(&v, "/old") > score_of(&v, "/undated"), "{v}");
+                assert_eq!(score_of(&v, "/undated"), score_of(&plain, "/undated"));
+                let results = v["results"].as_array().expect("results");
+                let factor = |name: &str| {
+                    results
+                        .iter()
+                        .find(|r| r["url"].as_str().is_some_and(|u| u.ends_with(name)))
+                        .and_then(|r| r["recency_factor"].as_f64())
+                        .expect("recency_factor")
+                };
+                assert_eq!(factor("/undated"), 1.0);
+                assert!(factor("/new") > factor("/old"), "{v}");
+                assert!(factor("/old") > 1.0, "{v}");
+            }
+        }
+
+        #[tokio::test]
+        async fn web_extract_prefer_amp_swaps_low_signal_shell_for_amp_alternate() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            let shell = r#"<html><head><title>Story</title>
+  <link rel="amphtml" href="/amp/story">
+</head><body><div id="root"></div>
+  <script>window.__APP__={"bundle":"main.js","hydrate":true};function r(){return 1}</script>
+</body></html>"#;
+            let para = "The council approved the riverside park plan after a long public hearing, \
+                        and construction of the new walking paths begins in the spring. ";
+            let amp = format!(
+                r#"<html amp><head><title>Story</title>
+  <link rel="amphtml" href="/amp/story2">
+</head><body><article><h1>Park plan approved</h1>{}</article></body></html>"#,
+                format!("<p>{}</p>", para.repeat(3)).repeat(6)
+            );
+            let amp2_hits = Arc::new(AtomicUsize::new(0));
+            let hits = amp2_hits.clone();
+            let app = Router::new()
+                .route(
+                    "/story",
+                    get(move || async move {
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], shell)
+                    }),
+                )
+                .route(
+                    "/amp/story",
+                    get(move || {
+                        let amp = amp.clone();
+                        async move { ([(axum::http::header::CONTENT_TYPE, "text/html")], amp) }
+                    }),
+                )
+                .route(
+                    "/amp/story2",
+                    get(move || {
+                        hits.fetch_add(1, Ordering::SeqCst);
+                        async move { "unreachable" }
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let args = |prefer_amp: Option<bool>| WebExtractArgs {
+                url: Some(format!("http://{addr}/story")),
+                prefer_amp,
+                include_text: Some(true),
+                timeout_ms: Some(2_000),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                ..Default::default()
+            };
+
+            let r = svc.web_extract(p(args(None))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert!(v["final_url"].as_str().unwrap().ends_with("/story"));
+            assert!(v["attempts"].get("amp_alternate").is_none(), "payload={v}");
+            assert!(!v["extract"]["text"]
+                .as_str()
+                .unwrap_or("")
+                .contains("riverside park"));
+
+            let r = svc.web_extract(p(args(Some(true)))).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["prefer_amp"].as_bool(), Some(true));
+            assert!(v["final_url"].as_str().unwrap().ends_with("/amp/story"));
+            let a = &v["attempts"]["amp_alternate"];
+            assert_eq!(a["ok"].as_bool(), Some(true), "attempt={a}");
+            assert!(a["from"].as_str().unwrap().ends_with("/story"));
+            assert!(
+                a["confidence_after"].as_f64().unwrap() > a["confidence_before"].as_f64().unwrap()
+            );
+            assert!(v["extract"]["text"]
+                .as_str()
+                .unwrap()
+                .contains("riverside park plan"));
+            assert!(v["warning_codes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|c| c == "amp_alternate_used"));
+            // The AMP page's own amphtml link is not followed.
+            assert_eq!(amp2_hits.load(Ordering::SeqCst), 0);
+        }
+
+        #[tokio::test]
+        async fn web_extract_include_keywords_ranks_keyphrases_and_caches_per_doc() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+
+            let html = r#"<html><head><title>Solar</title></head><body><article>
+  <h1>Perovskite solar cells</h1>
+  <p>Researchers at the National Renewable Energy Laboratory reported that perovskite solar
+  cells have reached record conversion efficiency in outdoor tests. The perovskite solar cells
+  also kept their conversion efficiency for a year. Questions go to media@nrel.example or
+  https://nrel.example/news/perovskite.</p>
+</article></body></html>"#;
+            let app =
+                Router::new().route(
+                    "/solar",
+                    get(move || async move {
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], html)
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let args = || WebExtractArgs {
+                url: Some(format!("http://{addr}/solar")),
+                include_keywords: Some(true),
+                max_keywords: Some(5),
+                timeout_ms: Some(2_000),
+                cache_read: Some(false),
+                cache_write: Some(false),
+                ..Default::default()
+            };
+            let r = svc.web_extract(p(args())).await.expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+            assert_eq!(v["request"]["include_keywords"].as_bool(), Some(true));
+            let kw = &v["extract"]["keywords"];
+            assert_eq!(kw["cached"].as_bool(), Some(false));
+            let phrases: Vec<&str> = kw["keyphrases"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|k| k["phrase"].as_str())
+                .collect();
+            assert_eq!(phrases.len(), 5, "keywords={kw}");
+            assert_eq!(
+                phrases[0], "national renewable energy laboratory",
+                "keywords={kw}"
+            );
+            let cells = kw["keyphrases"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|k| k["phrase"] == "perovskite solar cells")
+                .unwrap_or_else(|| panic!("keywords={kw}"));
+            // Heading plus both mentions in the paragraph.
+            assert_eq!(cells["count"].as_u64(), Some(3));
+            let has = |kind: &str, text: &str| {
+                kw["entities"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .any(|e| e["kind"] == kind && e["text"] == text)
+            };
+            assert!(
+                has("name", "National Renewable Energy Laboratory"),
+                "keywords={kw}"
+            );
+            assert!(has("email", "media@nrel.example"), "keywords={kw}");
+            assert!(
+                has("url", "https://nrel.example/news/perovskite"),
+                "keywords={kw}"
+            );
+
+            let r = svc.web_extract(p(args())).await.expect("call");
+            let v2 = payload_from_call_tool_result(&r);
+            assert_eq!(v2["extract"]["keywords"]["cached"].as_bool(), Some(true));
+            assert_eq!(
+                v2["extract"]["keywords"]["keyphrases"],
+                v["extract"]["keywords"]["keyphrases"]
+            );
+
+            let r = svc
+                .web_extract(p(WebExtractArgs {
+                    include_keywords: None,
+                    ..args()
+                }))
+                .await
+                .expect("call");
+            let v3 = payload_from_call_tool_result(&r);
+            assert!(v3["extract"].get("keywords").is_none());
+        }
+
+        #[tokio::test]
+        async fn web_extract_include_alternates_resolves_canonical_amp_and_hreflang() {
+            let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::net::SocketAddr;
+
+            let html = r#"<html><head>
+  <title>Article</title>
+  <link rel="canonical" href="/articles/42">
+  <link rel="amphtml" href="/amp/articles/42">
+  <link rel="alternate" hreflang="en" href="/articles/42">
+  <link rel="alternate" hreflang="fr" href="https://fr.example.com/articles/42">
+  <link rel="alternate" hreflang="x-default" href="/articles/42">
+</head><body><article><p>Main body text.</p></article></body></html>"#;
+            let app =
+                Router::new().route(
+                    "/articles/42",
+                    get(move || async move {
+                        ([(axum::http::header::CONTENT_TYPE, "text/html")], html)
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr: SocketAddr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            let base = format!("http://{}", addr);
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_extract(p(WebExtractArgs {
+                    url: Some(format!("{base}/articles/42?utm_source=x")),
+                    include_alternates: Some(true),
+                    timeout_ms: Some(2_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["request"]["include_alternates"].as_bool(), Some(true));
+            let a = &v["extract"]["alternates"];
+            let canonical = format!("{base}/articles/42");
+            assert_eq!(a["canonical"].as_str(), Some(canonical.as_str()));
+            assert_eq!(
+                a["amp"].as_str(),
+                Some(format!("{base}/amp/articles/42").as_str())
+            );
+            let hreflang = a["hreflang"].as_array().expect("hreflang");
+            assert_eq!(hreflang.len(), 3);
+            assert_eq!(hreflang[0]["lang"].as_str(), Some("en"));
+            assert_eq!(hreflang[0]["url"].as_str(), Some(canonical.as_str()));
+            assert_eq!(
+                hreflang[1]["url"].as_str(),
+                Some("https://fr.example.com/articles/42")
+            );
+            assert_eq!(hreflang[2]["lang"].as_str(), Some("x-default"));
+
+            // Opt-in: absent unless requested.
+            let r = svc
+                .web_extract(p(WebExtractArgs {
+                    url: Some(format!("{base}/articles/42")),
+                    timeout_ms: Some(2_000),
+                    cache_read: Some(false),
+                    cache_write: Some(false),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert!(v["extract"].get("alternates").is_none());
+        }
+
         #[tokio::test]
         async fn warning_codes_normalize_links_unavailable_for_firecrawl() {
             // Offline: Firecrawl path always has links unavailable (we return []), so it emits
@@ -25157,12 +38870,32 @@ Rules:
                     no_network: Some(false),
                     width: Some(80),
                     max_chars: Some(2_000),
+                    truncation_strategy: None,
+                    engine: None,
                     query: None,
                     top_chunks: Some(3),
                     max_chunk_chars: Some(200),
                     include_text: Some(true),
                     include_links: Some(true),
                     max_links: Some(10),
+                    include_alternates: None,
+                    prefer_amp: None,
+                    include_entities: None,
+                    include_tables: None,
+                    include_metadata: None,
+                    table_format: None,
+                    include_dates: None,
+                    date_order: None,
+                    include_keywords: None,
+                    max_keywords: None,
+                    chunk_dom_paths: None,
+                    referer: None,
+                    sentences: None,
+                    include_noscript: None,
+                    follow_iframes: None,
+                    allow_cross_origin_iframes: None,
+                    max_iframes: None,
+                    include_error_body: None,
                     timeout_ms: Some(2_000),
                     max_bytes: Some(200_000),
                     retry_on_truncation: None,
@@ -25174,9 +38907,11 @@ Rules:
                     max_outline_items: None,
                     max_blocks: None,
                     max_block_chars: None,
+                    sectioned: None,
                     semantic_rerank: None,
                     semantic_auto_fallback: None,
                     semantic_top_k: None,
+                    include_raw_html: None,
                 }))
                 .await
                 .expect("call");
@@ -25576,6 +39311,76 @@ Rules:
             );
         }
 
+        #[cfg(feature = "msgpack")]
+        #[test]
+        fn msgpack_envelope_round_trips_payloads_like_json() {
+            let mut payload = serde_json::json!({
+                "ok": true,
+                "url": "https://example.com/π?q=1",
+                "status": 200,
+                "bytes": u64::MAX,
+                "delta": -42,
+                "score": 0.125,
+                "truncated": false,
+                "final_url": null,
+                "results": [
+                    {"url": "https://a.example/", "top_chunks": [{"text": "naïve — “quoted”", "score": 3}]},
+                    {"url": "https://b.example/", "warnings": []}
+                ],
+                "request": {"cache": {"read": true, "write": false, "ttl_s": null}}
+            });
+            add_envelope_fields(&mut payload, "web_fetch", 7);
+
+            let via_json: serde_json::Value =
+                serde_json::from_str(&payload.to_string()).expect("json");
+            let via_msgpack = payload_from_msgpack(&payload_to_msgpack(&payload).expect("encode"))
+                .expect("decode");
+            assert_eq!(via_json, payload);
+            assert_eq!(via_msgpack, payload);
+        }
+
+        #[cfg(feature = "msgpack")]
+        #[tokio::test]
+        async fn msgpack_envelope_replaces_structured_content_and_keeps_json_text() {
+            let env = EnvGuard::new(&["WEBPIPE_CACHE_DIR", "WEBPIPE_ENVELOPE_FORMAT"]);
+            let svc = WebpipeMcp::new().expect("new");
+            let call = || {
+                svc.web_fetch(p(WebFetchArgs {
+                    url: Some("   ".to_string()),
+                    ..Default::default()
+                }))
+            };
+            let json_r = call().await.expect("call");
+            let expected = payload_from_call_tool_result(&json_r);
+
+            env.set("WEBPIPE_ENVELOPE_FORMAT", "msgpack");
+            let r = call().await.expect("call");
+            assert!(r.structured_content.is_none());
+            assert_eq!(r.is_error, Some(true));
+            assert_eq!(r.content.len(), 2);
+            let text: serde_json::Value =
+                serde_json::from_str(&r.content[0].as_text().expect("text").text).expect("json");
+            let ResourceContents::BlobResourceContents {
+                mime_type, blob, ..
+            } = &r.content[1].as_resource().expect("resource").resource
+            else {
+                panic!("expected a blob resource");
+            };
+            assert_eq!(mime_type.as_deref(), Some(MSGPACK_MIME_TYPE));
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(blob)
+                .expect("base64");
+            let decoded = payload_from_msgpack(&bytes).expect("msgpack");
+
+            // Same schema as the JSON envelope; only elapsed_ms may differ between calls.
+            let strip = |mut v: serde_json::Value| {
+                v["elapsed_ms"] = serde_json::Value::Null;
+                v
+            };
+            assert_eq!(decoded, text);
+            assert_eq!(strip(decoded), strip(expected));
+        }
+
         #[tokio::test]
         async fn web_fetch_rejects_empty_url() {
             let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
@@ -25589,11 +39394,16 @@ Rules:
                     max_bytes: None,
                     max_text_chars: None,
                     headers: None,
+                    referer: None,
                     cache_read: None,
                     cache_write: None,
                     cache_ttl_s: None,
                     include_headers: None,
                     include_text: None,
+                    expect_content_type: None,
+                    include_error_body: None,
+                    known_sha256: None,
+                    include_metadata: None,
                 })))
                 .await
                 .expect("call");
@@ -25605,6 +39415,347 @@ Rules:
             );
         }
 
+        #[tokio::test]
+        async fn web_compare_backends_reports_per_url_winners_and_win_rate() {
+            let env = EnvGuard::new(&[
+                "WEBPIPE_CACHE_DIR",
+                "WEBPIPE_FIRECRAWL_API_KEY",
+                "FIRECRAWL_API_KEY",
+                "WEBPIPE_FIRECRAWL_ENDPOINT_V2",
+            ]);
+            env.set("WEBPIPE_FIRECRAWL_API_KEY", "test-key");
+
+            use axum::{routing::get, routing::post, Json, Router};
+            use serde_json::json;
+
+            const PROSE: &str = "The extraction pipeline keeps the main article text and drops the navigation, footers and cookie banners around it. Each paragraph is scored by its density of real words, so long runs of prose rank above menus and link lists. When a page is rendered by a script, the local backend sees only the empty shell, while a hosted scraper that runs a browser returns the finished article. Comparing both on the same URLs shows which pages need the slower, paid path and which are already served well by the plain fetch.";
+            fn html(body: &str) -> ([(axum::http::HeaderName, &'static str); 1], String) {
+                (
+                    [(axum::http::header::CONTENT_TYPE, "text/html")],
+                    format!("<html><body><article>{body}</article></body></html>"),
+                )
+            }
+            let prose = format!("<p>{PROSE}</p><p>{PROSE}</p>");
+            let (p1, p2) = (prose.clone(), prose.clone());
+            let app = Router::new()
+                .route("/rich", get(move || async move { html(&p1) }))
+                .route("/same", get(move || async move { html(&p2) }))
+                .route(
+                    "/shell",
+                    get(|| async {
+                        html("<noscript>Please enable JavaScript to continue.</noscript>")
+                    }),
+                )
+                .route(
+                    "/v2/scrape",
+                    post(|Json(body): Json<serde_json::Value>| async move {
+                        let url = body["url"].as_str().unwrap_or("");
+                        let markdown = if url.ends_with("/rich") {
+                            "Loading...".to_string()
+                        } else {
+                            format!("{PROSE}\n\n{PROSE}")
+                        };
+                        Json(json!({ "success": true, "data": { "markdown": markdown } }))
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            env.set(
+                "WEBPIPE_FIRECRAWL_ENDPOINT_V2",
+                &format!("http://{addr}/v2/scrape"),
+            );
+            let urls: Vec<String> = ["rich", "shell", "same"]
+                .iter()
+                .map(|p| format!("http://{addr}/{p}"))
+                .collect();
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_compare_backends(p(WebCompareBackendsArgs {
+                    urls: Some(urls.clone()),
+                    timeout_ms: Some(5_000),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["kind"].as_str(), Some("web_compare_backends"));
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["firecrawl_configured"].as_bool(), Some(true));
+            let rows = v["results"].as_array().expect("results");
+            let winners: Vec<&str> = rows.iter().map(|r| r["winner"].as_str().unwrap()).collect();
+            assert_eq!(winners, ["local", "firecrawl", "tie"], "{v}");
+            assert_eq!(rows[0]["url"].as_str(), Some(urls[0].as_str()));
+            assert_eq!(rows[0]["local"]["low_signal"].as_bool(), Some(false));
+            assert_eq!(rows[0]["firecrawl"]["low_signal"].as_bool(), Some(true));
+            assert_eq!(rows[1]["local"]["low_signal"].as_bool(), Some(true));
+            for side in ["local", "firecrawl"] {
+                let s = &rows[2][side];
+                assert!(s["chars"].as_u64().unwrap() > 500, "{s}");
+                assert!(s["confidence"].as_f64().unwrap() >= 0.4, "{s}");
+                assert!(s["ms"].is_u64(), "{s}");
+            }
+            let agg = &v["aggregate"];
+            assert_eq!(agg["compared"].as_u64(), Some(3));
+            assert_eq!(agg["local_wins"].as_u64(), Some(1));
+            assert_eq!(agg["firecrawl_wins"].as_u64(), Some(1));
+            assert_eq!(agg["ties"].as_u64(), Some(1));
+            assert_eq!(agg["local_win_rate"].as_f64(), Some(0.33));
+            assert_eq!(agg["firecrawl_win_rate"].as_f64(), Some(0.33));
+
+            // Without a key the local side is still measured; nothing is compared.
+            env.remove("WEBPIPE_FIRECRAWL_API_KEY");
+            let r = svc
+                .web_compare_backends(p(WebCompareBackendsArgs {
+                    urls: Some(urls[..1].to_vec()),
+                    metrics: Some(vec!["confidence".to_string()]),
+                    timeout_ms: Some(5_000),
+                    ..Default::default()
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["firecrawl_configured"].as_bool(), Some(false));
+            let row = &v["results"][0];
+            assert!(
+                row["firecrawl"].is_null() && row["winner"].is_null(),
+                "{row}"
+            );
+            assert!(row["local"]["confidence"].is_number());
+            assert!(row["local"].get("chars").is_none());
+            assert_eq!(v["aggregate"]["compared"].as_u64(), Some(0));
+            assert!(v["aggregate"]["local_win_rate"].is_null());
+            assert!(v["warning_codes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|c| c == "firecrawl_not_configured"));
+        }
+
+        #[tokio::test]
+        async fn web_sitemap_returns_robots_allowed_urls_bounded_by_max_urls() {
+            let env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            use axum::{extract::Host, routing::get, Router};
+            let app = Router::new()
+                .route(
+                    "/robots.txt",
+                    get(|Host(host): Host| async move {
+                        format!("User-agent: *\nDisallow: /drafts/\nSitemap: http://{host}/map.xml\n")
+                    }),
+                )
+                .route(
+                    "/map.xml",
+                    get(|Host(host): Host| async move {
+                        format!(
+                            "<urlset><url><loc>http://{host}/a</loc></url><url><loc>http://{host}/drafts/b</loc></url><url><loc>http://{host}/c</loc></url></urlset>"
+                        )
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_sitemap(p(WebSitemapArgs {
+                    site_url: Some(format!("http://{addr}/docs/")),
+                    max_urls: None,
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["kind"].as_str(), Some("web_sitemap"));
+            assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+            assert_eq!(
+                v["urls"],
+                serde_json::json!([format!("http://{addr}/a"), format!("http://{addr}/c")])
+            );
+            assert_eq!(v["capped"].as_bool(), Some(false));
+
+            let r = svc
+                .web_sitemap(p(WebSitemapArgs {
+                    site_url: Some(format!("http://{addr}/")),
+                    max_urls: Some(1),
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["count"].as_u64(), Some(1));
+            assert_eq!(v["capped"].as_bool(), Some(true));
+
+            let r = svc
+                .web_sitemap(p(WebSitemapArgs {
+                    site_url: Some("ftp://example.com/".to_string()),
+                    max_urls: None,
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(
+                v["error"]["code"].as_str(),
+                Some(ErrorCode::InvalidUrl.as_str())
+            );
+        }
+
+        #[tokio::test]
+        async fn web_diff_reports_first_seen_then_line_changes_against_the_cache() {
+            let env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            use axum::{routing::get, Router};
+            use std::sync::atomic::{AtomicUsize, Ordering};
+            let hits = Arc::new(AtomicUsize::new(0));
+            let h = hits.clone();
+            let app = Router::new().route(
+                "/page",
+                get(move || {
+                    let n = h.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        let price = if n == 0 {
+                            "Price: 10 EUR"
+                        } else {
+                            "Price: 12 EUR"
+                        };
+                        (
+                            [(axum::http::header::CONTENT_TYPE, "text/plain")],
+                            format!(
+                                "Release notes\nFirst line stays.\n{price}\nLast line stays.\n"
+                            ),
+                        )
+                    }
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.expect("axum serve");
+            });
+            let tmp = tempfile::tempdir().expect("tempdir");
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            let url = format!("http://{addr}/page");
+
+            let svc = WebpipeMcp::new().expect("new");
+            let call = |no_network: bool| {
+                svc.web_diff(p(WebDiffArgs {
+                    url: Some(url.clone()),
+                    timeout_ms: Some(5_000),
+                    no_network: Some(no_network),
+                    ..Default::default()
+                }))
+            };
+
+            let v = payload_from_call_tool_result(&call(false).await.expect("call"));
+            assert_eq!(v["kind"].as_str(), Some("web_diff"));
+            assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+            assert_eq!(v["first_seen"].as_bool(), Some(true));
+            assert!(v["diff"].is_null() && v["old"].is_null());
+            assert!(v["warning_codes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|c| c == "diff_first_seen"));
+
+            let v = payload_from_call_tool_result(&call(false).await.expect("call"));
+            assert_eq!(v["ok"].as_bool(), Some(true), "{v}");
+            assert_eq!(v["first_seen"].as_bool(), Some(false));
+            assert_eq!(v["changed"].as_bool(), Some(true));
+            assert_eq!(v["diff"]["added"].as_u64(), Some(1), "{v}");
+            assert_eq!(v["diff"]["removed"].as_u64(), Some(1));
+            assert_eq!(v["diff"]["unchanged"].as_u64(), Some(3));
+            assert_eq!(v["diff"]["similarity"].as_f64(), Some(0.75));
+            let unified = v["diff"]["unified"].as_str().unwrap();
+            assert!(
+                unified.contains("-Price: 10 EUR") && unified.contains("+Price: 12 EUR"),
+                "{unified}"
+            );
+            assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+            // The old side comes only from the cache, and no_network has no new side.
+            let v = payload_from_call_tool_result(&call(true).await.expect("call"));
+            assert_eq!(v["ok"].as_bool(), Some(false));
+            assert_eq!(
+                v["error"]["code"].as_str(),
+                Some(ErrorCode::NotSupported.as_str())
+            );
+            assert_eq!(hits.load(Ordering::SeqCst), 2);
+        }
+
+        #[tokio::test]
+        async fn web_cache_migrate_reports_counts_and_requires_cache_dir() {
+            let env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
+            let svc = WebpipeMcp::new().expect("new");
+            let r = svc
+                .web_cache_migrate(p(WebCacheMigrateArgs::default()))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["kind"].as_str(), Some("web_cache_migrate"));
+            assert_eq!(v["ok"].as_bool(), Some(false));
+            assert_eq!(
+                v["error"]["code"].as_str(),
+                Some(ErrorCode::NotConfigured.as_str())
+            );
+
+            let tmp = tempfile::tempdir().unwrap();
+            env.set("WEBPIPE_CACHE_DIR", tmp.path().to_str().unwrap());
+            let cache = webpipe_local::FsCache::new(tmp.path().to_path_buf());
+            let req = FetchRequest {
+                url: "https://example.com/".to_string(),
+                timeout_ms: None,
+                max_bytes: Some(1_000),
+                headers: BTreeMap::new(),
+                method: None,
+                body: None,
+                cache: FetchCachePolicy {
+                    read: true,
+                    write: true,
+                    ttl_s: None,
+                    read_non_idempotent: false,
+                    stale_while_revalidate_s: None,
+                },
+            };
+            cache
+                .put(
+                    &req,
+                    &webpipe_core::FetchResponse {
+                        url: req.url.clone(),
+                        final_url: req.url.clone(),
+                        status: 200,
+                        content_type: Some("text/plain".to_string()),
+                        headers: BTreeMap::new(),
+                        bytes: b"hi".to_vec(),
+                        wire_bytes: b"hi".to_vec().len() as u64,
+                        truncated: false,
+                        source: webpipe_core::FetchSource::Network,
+                        cache_status: None,
+                        body_path: None,
+                        revalidating: false,
+                        timings_ms: BTreeMap::new(),
+                    },
+                )
+                .unwrap();
+
+            let r = svc
+                .web_cache_migrate(p(WebCacheMigrateArgs {
+                    max_entries: Some(10),
+                    remove_legacy: Some(true),
+                }))
+                .await
+                .expect("call");
+            let v = payload_from_call_tool_result(&r);
+            assert_eq!(v["ok"].as_bool(), Some(true));
+            assert_eq!(v["request"]["remove_legacy"].as_bool(), Some(true));
+            assert_eq!(v["counts"]["scanned"].as_u64(), Some(1));
+            assert_eq!(v["counts"]["not_legacy"].as_u64(), Some(1));
+            assert_eq!(v["counts"]["migrated"].as_u64(), Some(0));
+        }
+
         #[tokio::test]
         async fn web_extract_rejects_empty_url() {
             let _env = EnvGuard::new(&["WEBPIPE_CACHE_DIR"]);
@@ -25616,12 +39767,32 @@ Rules:
                     no_network: None,
                     width: None,
                     max_chars: None,
+                    truncation_strategy: None,
+                    engine: None,
                     query: None,
                     top_chunks: None,
                     max_chunk_chars: None,
                     include_text: None,
                     include_links: None,
                     max_links: None,
+                    include_alternates: None,
+                    prefer_amp: None,
+                    include_entities: None,
+                    include_tables: None,
+                    include_metadata: None,
+                    table_format: None,
+                    include_dates: None,
+                    date_order: None,
+                    include_keywords: None,
+                    max_keywords: None,
+                    chunk_dom_paths: None,
+                    referer: None,
+                    sentences: None,
+                    include_noscript: None,
+                    follow_iframes: None,
+                    allow_cross_origin_iframes: None,
+                    max_iframes: None,
+                    include_error_body: None,
                     timeout_ms: None,
                     max_bytes: None,
                     retry_on_truncation: None,
@@ -25633,9 +39804,11 @@ Rules:
                     max_outline_items: None,
                     max_blocks: None,
                     max_block_chars: None,
+                    sectioned: None,
                     semantic_rerank: None,
                     semantic_auto_fallback: None,
                     semantic_top_k: None,
+                    include_raw_html: None,
                 })))
                 .await
                 .expect("call");
@@ -25660,12 +39833,32 @@ Rules:
                     no_network: Some(false),
                     width: Some(80),
                     max_chars: Some(2_000),
+                    truncation_strategy: None,
+                    engine: None,
                     query: None,
                     top_chunks: Some(3),
                     max_chunk_chars: Some(200),
                     include_text: Some(false),
                     include_links: Some(false),
                     max_links: Some(10),
+                    include_alternates: None,
+                    prefer_amp: None,
+                    include_entities: None,
+                    include_tables: None,
+                    include_metadata: None,
+                    table_format: None,
+                    include_dates: None,
+                    date_order: None,
+                    include_keywords: None,
+                    max_keywords: None,
+                    chunk_dom_paths: None,
+                    referer: None,
+                    sentences: None,
+                    include_noscript: None,
+                    follow_iframes: None,
+                    allow_cross_origin_iframes: None,
+                    max_iframes: None,
+                    include_error_body: None,
                     timeout_ms: Some(2_000),
                     max_bytes: Some(200_000),
                     retry_on_truncation: None,
@@ -25677,9 +39870,11 @@ Rules:
                     max_outline_items: None,
                     max_blocks: None,
                     max_block_chars: None,
+                    sectioned: None,
                     semantic_rerank: None,
                     semantic_auto_fallback: None,
                     semantic_top_k: None,
+                    include_raw_html: None,
                 })))
                 .await
                 .expect("call");
@@ -25774,12 +39969,32 @@ Rules:
                     no_network: Some(false),
                     width: Some(80),
                     max_chars: Some(2_000),
+                    truncation_strategy: None,
+                    engine: None,
                     query: None,
                     top_chunks: Some(3),
                     max_chunk_chars: Some(200),
                     include_text: Some(false),
                     include_links: Some(false),
                     max_links: Some(10),
+                    include_alternates: None,
+                    prefer_amp: None,
+                    include_entities: None,
+                    include_tables: None,
+                    include_metadata: None,
+                    table_format: None,
+                    include_dates: None,
+                    date_order: None,
+                    include_keywords: None,
+                    max_keywords: None,
+                    chunk_dom_paths: None,
+                    referer: None,
+                    sentences: None,
+                    include_noscript: None,
+                    follow_iframes: None,
+                    allow_cross_origin_iframes: None,
+                    max_iframes: None,
+                    include_error_body: None,
                     timeout_ms: Some(2_000),
                     max_bytes: Some(200_000),
                     retry_on_truncation: None,
@@ -25791,9 +40006,11 @@ Rules:
                     max_outline_items: None,
                     max_blocks: None,
                     max_block_chars: None,
+                    sectioned: None,
                     semantic_rerank: None,
                     semantic_auto_fallback: None,
                     semantic_top_k: None,
+                    include_raw_html: None,
                 })))
                 .await
                 .expect("call");
@@ -25827,12 +40044,32 @@ Rules:
                     no_network: Some(false),
                     width: Some(80),
                     max_chars: Some(2_000),
+                    truncation_strategy: None,
+                    engine: None,
                     query: None,
                     top_chunks: Some(3),
                     max_chunk_chars: Some(200),
                     include_text: Some(false),
                     include_links: Some(false),
                     max_links: Some(10),
+                    include_alternates: None,
+                    prefer_amp: None,
+                    include_entities: None,
+                    include_tables: None,
+                    include_metadata: None,
+                    table_format: None,
+                    include_dates: None,
+                    date_order: None,
+                    include_keywords: None,
+                    max_keywords: None,
+                    chunk_dom_paths: None,
+                    referer: None,
+                    sentences: None,
+                    include_noscript: None,
+                    follow_iframes: None,
+                    allow_cross_origin_iframes: None,
+                    max_iframes: None,
+                    include_error_body: None,
                     timeout_ms: Some(2_000),
                     max_bytes: Some(200_000),
                     retry_on_truncation: None,
@@ -25844,9 +40081,11 @@ Rules:
                     max_outline_items: None,
                     max_blocks: None,
                     max_block_chars: None,
+                    sectioned: None,
                     semantic_rerank: None,
                     semantic_auto_fallback: None,
                     semantic_top_k: None,
+                    include_raw_html: None,
                 })))
                 .await
                 .expect("call");
@@ -26012,6 +40251,738 @@ mod dotenv_tests {
     }
 }
 
+/// Score an `eval-matrix` JSONL artifact against e2e qrels (`eval-matrix-score` payload).
+///
+/// Rows tagged with `provider` (from `eval-matrix --providers`) additionally get a
+/// `per_provider` comparison of the search leg.
+#[cfg(feature = "eval")]
+fn eval_matrix_score_payload(
+    matrix_artifact: &std::path::Path,
+    qrels_path: &std::path::Path,
+    now: u64,
+) -> Result<serde_json::Value> {
+    let qrels = eval::load_e2e_qrels_v1(qrels_path)?;
+
+    // Read + parse JSONL rows.
+    let raw = std::fs::read_to_string(matrix_artifact)?;
+    let mut rows: Vec<serde_json::Value> = Vec::new();
+    for (i, line) in raw.lines().enumerate() {
+        let s = line.trim();
+        if s.is_empty() {
+            continue;
+        }
+        let v: serde_json::Value = serde_json::from_str(s)
+            .map_err(|e| anyhow::anyhow!("matrix_artifact line {}: invalid json: {}", i + 1, e))?;
+        rows.push(v);
+    }
+
+    // Index: query_id -> case -> row
+    let mut by_q: std::collections::BTreeMap<
+        String,
+        std::collections::BTreeMap<String, serde_json::Value>,
+    > = std::collections::BTreeMap::new();
+    // Multi-provider artifacts (`eval-matrix --providers`): provider -> query_id -> search row
+    // (first row wins; providers keep their first-seen order).
+    let mut provider_order: Vec<String> = Vec::new();
+    let mut by_provider: std::collections::BTreeMap<
+        String,
+        std::collections::BTreeMap<String, serde_json::Value>,
+    > = std::collections::BTreeMap::new();
+    for r in rows {
+        let qid = r
+            .get("query_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        let case = r
+            .get("case")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        if qid.is_empty() || case.is_empty() {
+            continue;
+        }
+        if case == "search" {
+            if let Some(provider) = r.get("provider").and_then(|v| v.as_str()) {
+                if !provider_order.iter().any(|p| p == provider) {
+                    provider_order.push(provider.to_string());
+                }
+                by_provider
+                    .entry(provider.to_string())
+                    .or_default()
+                    .entry(qid.clone())
+                    .or_insert_with(|| r.clone());
+            }
+            by_q.entry(qid).or_default().entry(case).or_insert(r);
+            continue;
+        }
+        by_q.entry(qid).or_default().insert(case, r);
+    }
+
+    fn extract_urls_from_result(res: &serde_json::Value) -> std::collections::BTreeSet<String> {
+        let mut out = std::collections::BTreeSet::new();
+        if let Some(top) = res.get("top_chunks").and_then(|v| v.as_array()) {
+            for c in top {
+                if let Some(u) = c.get("url").and_then(|v| v.as_str()) {
+                    out.insert(u.to_string());
+                }
+            }
+        }
+        if let Some(per_url) = res.get("results").and_then(|v| v.as_array()) {
+            for r in per_url {
+                if let Some(u) = r.get("url").and_then(|v| v.as_str()) {
+                    out.insert(u.to_string());
+                }
+                if let Some(u) = r.get("final_url").and_then(|v| v.as_str()) {
+                    out.insert(u.to_string());
+                }
+            }
+        }
+        out
+    }
+
+    fn score_row(row: &serde_json::Value, expected: &[String]) -> (bool, bool) {
+        let res = row.get("result").unwrap_or(&serde_json::Value::Null);
+        let ok = res.get("ok").and_then(|v| v.as_bool()).unwrap_or(false);
+        let urls = extract_urls_from_result(res);
+        let hit = ok
+            && expected
+                .iter()
+                .any(|needle| urls.iter().any(|u| u.contains(needle.as_str())));
+        (ok, hit)
+    }
+
+    let expected_cases = ["search", "warm_urls", "offline_urls"];
+    let mut per_query: Vec<serde_json::Value> = Vec::new();
+    let mut totals = serde_json::json!({
+        "queries": qrels.qrels.len(),
+        "cases": { "search": {"ok": 0, "hit": 0}, "warm_urls": {"ok": 0, "hit": 0}, "offline_urls": {"ok": 0, "hit": 0} }
+    });
+
+    for q in &qrels.qrels {
+        let mut per_case = Vec::new();
+        for case in expected_cases {
+            let row = by_q.get(&q.query_id).and_then(|m| m.get(case)).cloned();
+
+            if let Some(row) = row {
+                let res = row
+                    .get("result")
+                    .cloned()
+                    .unwrap_or(serde_json::Value::Null);
+                let ok = res.get("ok").and_then(|v| v.as_bool()).unwrap_or(false);
+                let urls = extract_urls_from_result(&res);
+                let hit = ok
+                    && q.expected_url_substrings.iter().any(|needle| {
+                        let needle = needle.as_str();
+                        urls.iter().any(|u| u.contains(needle))
+                    });
+
+                if ok {
+                    totals["cases"][case]["ok"] =
+                        serde_json::json!(totals["cases"][case]["ok"].as_u64().unwrap_or(0) + 1);
+                }
+                if hit {
+                    totals["cases"][case]["hit"] =
+                        serde_json::json!(totals["cases"][case]["hit"].as_u64().unwrap_or(0) + 1);
+                }
+
+                per_case.push(serde_json::json!({
+                    "case": case,
+                    "row_present": true,
+                    "ok": ok,
+                    "hit": hit,
+                    "expected_url_substrings": q.expected_url_substrings,
+                    "observed_url_count": urls.len(),
+                }));
+            } else {
+                per_case.push(serde_json::json!({
+                    "case": case,
+                    "row_present": false,
+                    "ok": false,
+                    "hit": false,
+                    "expected_url_substrings": q.expected_url_substrings,
+                    "observed_url_count": 0,
+                }));
+            }
+        }
+        let mut pq = serde_json::json!({
+            "query_id": q.query_id,
+            "cases": per_case
+        });
+        if !provider_order.is_empty() {
+            let per_provider: Vec<serde_json::Value> = provider_order
+                .iter()
+                .map(|provider| {
+                    let row = by_provider.get(provider).and_then(|m| m.get(&q.query_id));
+                    let (ok, hit) = row
+                        .map(|r| score_row(r, &q.expected_url_substrings))
+                        .unwrap_or((false, false));
+                    serde_json::json!({
+                        "provider": provider,
+                        "row_present": row.is_some(),
+                        "ok": ok,
+                        "hit": hit,
+                    })
+                })
+                .collect();
+            pq["providers"] = serde_json::json!(per_provider);
+        }
+        per_query.push(pq);
+    }
+
+    // Per-provider comparison of the search leg (only for multi-provider artifacts).
+    let per_provider: Vec<serde_json::Value> = provider_order
+        .iter()
+        .map(|provider| {
+            let rows = by_provider.get(provider);
+            let (mut present, mut ok_n, mut hit_n) = (0usize, 0usize, 0usize);
+            let mut elapsed_ms_total = 0u64;
+            for q in &qrels.qrels {
+                let Some(row) = rows.and_then(|m| m.get(&q.query_id)) else {
+                    continue;
+                };
+                present += 1;
+                elapsed_ms_total += row.get("elapsed_ms").and_then(|v| v.as_u64()).unwrap_or(0);
+                let (ok, hit) = score_row(row, &q.expected_url_substrings);
+                ok_n += ok as usize;
+                hit_n += hit as usize;
+            }
+            let n = qrels.qrels.len().max(1) as f64;
+            serde_json::json!({
+                "provider": provider,
+                "rows": present,
+                "ok": ok_n,
+                "hit": hit_n,
+                "hit_rate": hit_n as f64 / n,
+                "mean_elapsed_ms": if present > 0 { elapsed_ms_total / present as u64 } else { 0 },
+            })
+        })
+        .collect();
+
+    let mut payload = serde_json::json!({
+        "schema_version": 1,
+        "kind": "webpipe_eval_matrix_score",
+        "generated_at_epoch_s": now,
+        "inputs": {
+            "matrix_artifact": matrix_artifact,
+            "qrels": qrels_path
+        },
+        "totals": totals,
+        "per_query": per_query
+    });
+    if !per_provider.is_empty() {
+        payload["per_provider"] = serde_json::json!(per_provider);
+    }
+
+    Ok(payload)
+}
+
+/// Rate metrics of an `eval-matrix-score` payload, in a stable order:
+/// `<case>.hit_rate` / `<case>.ok_rate` per case, then `provider.<name>.hit_rate`.
+#[cfg(feature = "eval")]
+fn eval_matrix_score_metrics(score: &serde_json::Value) -> Vec<(String, f64)> {
+    let queries = score["totals"]["queries"].as_u64().unwrap_or(0).max(1) as f64;
+    let mut out = Vec::new();
+    for case in ["search", "warm_urls", "offline_urls"] {
+        let c = &score["totals"]["cases"][case];
+        for (k, name) in [("hit", "hit_rate"), ("ok", "ok_rate")] {
+            if let Some(n) = c[k].as_u64() {
+                out.push((format!("{case}.{name}"), n as f64 / queries));
+            }
+        }
+    }
+    for p in score["per_provider"].as_array().into_iter().flatten() {
+        if let (Some(name), Some(r)) = (p["provider"].as_str(), p["hit_rate"].as_f64()) {
+            out.push((format!("provider.{name}.hit_rate"), r));
+        }
+    }
+    out
+}
+
+/// `eval-matrix-score-diff` payload: compare a candidate score artifact against a baseline.
+///
+/// A metric is "regressed" when it drops by more than `tolerance`, "improved" when it rises
+/// by more than `tolerance`. The verdict is "regressed"/"improved" when all changed metrics
+/// agree, "mixed" when they disagree, and "unchanged" otherwise. Per-query flips compare the
+/// `hit` label of each (query_id, case) present in both artifacts.
+#[cfg(feature = "eval")]
+fn eval_matrix_score_diff_payload(
+    baseline: &serde_json::Value,
+    candidate: &serde_json::Value,
+    tolerance: f64,
+    now: u64,
+) -> Result<serde_json::Value> {
+    for (side, v) in [("baseline", baseline), ("candidate", candidate)] {
+        if v["kind"].as_str() != Some("webpipe_eval_matrix_score") {
+            anyhow::bail!("{side}: expected an eval-matrix-score artifact");
+        }
+    }
+    let tolerance = tolerance.max(0.0);
+
+    let base_metrics = eval_matrix_score_metrics(baseline);
+    let cand_metrics: std::collections::BTreeMap<String, f64> =
+        eval_matrix_score_metrics(candidate).into_iter().collect();
+    let (mut improved, mut regressed) = (0usize, 0usize);
+    let metrics: Vec<serde_json::Value> = base_metrics
+        .iter()
+        .filter_map(|(name, b)| {
+            let c = *cand_metrics.get(name)?;
+            let delta = c - b;
+            let status = if delta < -tolerance {
+                regressed += 1;
+                "regressed"
+            } else if delta > tolerance {
+                improved += 1;
+                "improved"
+            } else {
+                "unchanged"
+            };
+            Some(serde_json::json!({
+                "metric": name,
+                "baseline": b,
+                "candidate": c,
+                "delta": delta,
+                "status": status,
+            }))
+        })
+        .collect();
+
+    fn hits(score: &serde_json::Value) -> std::collections::BTreeMap<(String, String), bool> {
+        let mut out = std::collections::BTreeMap::new();
+        for q in score["per_query"].as_array().into_iter().flatten() {
+            let Some(qid) = q["query_id"].as_str() else {
+                continue;
+            };
+            for c in q["cases"].as_array().into_iter().flatten() {
+                if let Some(case) = c["case"].as_str() {
+                    let hit = c["hit"].as_bool().unwrap_or(false);
+                    out.insert((qid.to_string(), case.to_string()), hit);
+                }
+            }
+        }
+        out
+    }
+    let base_hits = hits(baseline);
+    let cand_hits = hits(candidate);
+    let mut newly_pass = Vec::new();
+    let mut newly_fail = Vec::new();
+    for ((qid, case), b) in &base_hits {
+        match (b, cand_hits.get(&(qid.clone(), case.clone()))) {
+            (false, Some(true)) => {
+                newly_pass.push(serde_json::json!({"query_id": qid, "case": case}))
+            }
+            (true, Some(false)) => {
+                newly_fail.push(serde_json::json!({"query_id": qid, "case": case}))
+            }
+            _ => {}
+        }
+    }
+    let query_ids = |m: &std::collections::BTreeMap<(String, String), bool>| {
+        m.keys()
+            .map(|(q, _)| q.clone())
+            .collect::<std::collections::BTreeSet<_>>()
+    };
+    let (base_q, cand_q) = (query_ids(&base_hits), query_ids(&cand_hits));
+
+    let verdict = match (improved > 0, regressed > 0) {
+        (true, true) => "mixed",
+        (true, false) => "improved",
+        (false, true) => "regressed",
+        (false, false) => "unchanged",
+    };
+    Ok(serde_json::json!({
+        "schema_version": 1,
+        "kind": "webpipe_eval_matrix_score_diff",
+        "generated_at_epoch_s": now,
+        "inputs": {
+            "baseline": baseline["inputs"],
+            "candidate": candidate["inputs"],
+            "tolerance": tolerance
+        },
+        "verdict": verdict,
+        "regressed_metrics": regressed,
+        "improved_metrics": improved,
+        "metrics": metrics,
+        "per_query": {
+            "newly_pass": newly_pass,
+            "newly_fail": newly_fail,
+            "only_in_baseline": base_q.difference(&cand_q).collect::<Vec<_>>(),
+            "only_in_candidate": cand_q.difference(&base_q).collect::<Vec<_>>()
+        }
+    }))
+}
+
+/// Artifact paths written by `eval-matrix-run`.
+#[cfg(feature = "eval")]
+struct EvalMatrixRunPaths {
+    out_dir: std::path::PathBuf,
+    matrix: std::path::PathBuf,
+    score: std::path::PathBuf,
+    export: std::path::PathBuf,
+    judge: std::path::PathBuf,
+    manifest: std::path::PathBuf,
+}
+
+#[cfg(feature = "eval")]
+impl EvalMatrixRunPaths {
+    fn new(args: &EvalMatrixRunCmd, now: u64) -> Self {
+        let out_dir = args
+            .out_dir
+            .clone()
+            .unwrap_or_else(|| std::path::PathBuf::from(".generated"));
+        Self {
+            matrix: out_dir.join(format!("webpipe-eval-matrix-run-{now}.jsonl")),
+            score: out_dir.join(format!("webpipe-eval-matrix-score-run-{now}.json")),
+            export: out_dir.join(format!("webpipe-eval-matrix-export-run-{now}.jsonl")),
+            judge: out_dir.join(format!("webpipe-eval-matrix-judge-run-{now}.json")),
+            manifest: out_dir.join(format!("webpipe-eval-matrix-manifest-run-{now}.json")),
+            out_dir,
+        }
+    }
+}
+
+/// Sub-command argv (without the executable) for each `eval-matrix-run` stage, in order.
+#[cfg(feature = "eval")]
+fn eval_matrix_run_steps(
+    args: &EvalMatrixRunCmd,
+    paths: &EvalMatrixRunPaths,
+    now: u64,
+) -> Vec<Vec<String>> {
+    let s = |p: &std::path::Path| p.to_string_lossy().to_string();
+    let now_s = now.to_string();
+    let steps = [
+        vec![
+            "eval-matrix".to_string(),
+            "--queries-json".to_string(),
+            s(&args.queries_json),
+            "--base-url".to_string(),
+            args.base_url.clone(),
+            "--provider".to_string(),
+            args.provider.clone(),
+            "--auto-mode".to_string(),
+            args.auto_mode.clone(),
+            "--selection-mode".to_string(),
+            args.selection_mode.clone(),
+            "--fetch-backend".to_string(),
+            args.fetch_backend.clone(),
+            "--out".to_string(),
+            s(&paths.matrix),
+        ],
+        vec![
+            "eval-matrix-score".to_string(),
+            "--matrix-artifact".to_string(),
+            s(&paths.matrix),
+            "--qrels".to_string(),
+            s(&args.qrels),
+            "--out".to_string(),
+            s(&paths.score),
+        ],
+        vec![
+            "eval-matrix-export".to_string(),
+            "--matrix-artifact".to_string(),
+            s(&paths.matrix),
+            "--qrels".to_string(),
+            s(&args.qrels),
+            "--max-text-chars".to_string(),
+            args.max_text_chars.to_string(),
+            "--out".to_string(),
+            s(&paths.export),
+        ],
+        vec![
+            "eval-matrix-judge".to_string(),
+            "--examples-artifact".to_string(),
+            s(&paths.export),
+            "--out".to_string(),
+            s(&paths.judge),
+        ],
+    ];
+    steps
+        .into_iter()
+        .map(|mut argv| {
+            argv.extend(["--now-epoch-s".to_string(), now_s.clone()]);
+            argv
+        })
+        .collect()
+}
+
+/// `eval-matrix-run --dry-run`: resolve the dataset and expand URLs, but fetch and write nothing
+/// (except the plan itself when `--plan-out` is set).
+#[cfg(feature = "eval")]
+fn eval_matrix_run_dry_run(args: &EvalMatrixRunCmd, now: u64) -> Result<serde_json::Value> {
+    let e2e = eval::load_e2e_queries_v1(&args.queries_json)?;
+    let qrels = eval::load_e2e_qrels_v1(&args.qrels)?;
+    let paths = EvalMatrixRunPaths::new(args, now);
+
+    let queries: Vec<serde_json::Value> = e2e
+        .queries
+        .iter()
+        .map(|q| {
+            serde_json::json!({
+                "query_id": q.query_id,
+                "query": q.query,
+                "urls": q.expand_urls(&args.base_url),
+            })
+        })
+        .collect();
+    let plan = serde_json::json!({
+        "schema_version": 1,
+        "kind": "webpipe_eval_matrix_run_plan",
+        "generated_at_epoch_s": now,
+        "inputs": {
+            "queries_json": args.queries_json,
+            "qrels": args.qrels,
+            "base_url": args.base_url,
+            "provider": args.provider,
+            "auto_mode": args.auto_mode,
+            "selection_mode": args.selection_mode,
+            "fetch_backend": args.fetch_backend,
+            "max_text_chars": args.max_text_chars
+        },
+        "query_count": queries.len(),
+        "qrels_count": qrels.qrels.len(),
+        "queries": queries,
+        "artifacts": {
+            "out_dir": paths.out_dir,
+            "matrix": paths.matrix,
+            "score": paths.score,
+            "export": paths.export,
+            "judge": paths.judge,
+            "manifest": paths.manifest
+        },
+        "steps": eval_matrix_run_steps(args, &paths, now),
+    });
+    if let Some(p) = args.plan_out.as_ref() {
+        if let Some(dir) = p.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(p, serde_json::to_string_pretty(&plan)? + "\n")?;
+    }
+    Ok(plan)
+}
+
+/// Run `eval-matrix` and write its JSONL artifact, returning the artifact path.
+///
+/// With `--providers a,b,...` the `search` leg runs once per provider (at most
+/// `--provider-concurrency` in flight) and each search row carries its `provider` tag; the
+/// cache-warm and offline legs are provider-independent and run once per query.
+#[cfg(all(feature = "eval", feature = "stdio"))]
+async fn run_eval_matrix(args: EvalMatrixCmd) -> Result<std::path::PathBuf> {
+    use futures::StreamExt;
+    use rmcp::handler::server::wrapper::Parameters;
+    use std::io::Write;
+
+    let now = args.now_epoch_s.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    });
+    let out = args.out.clone().unwrap_or_else(|| {
+        std::path::PathBuf::from(format!(".generated/webpipe-eval-matrix-{now}.jsonl"))
+    });
+    std::fs::create_dir_all(
+        out.parent()
+            .unwrap_or_else(|| std::path::Path::new(".generated")),
+    )?;
+
+    let providers: Vec<String> = match args.providers.as_deref() {
+        Some(list) => {
+            let mut v: Vec<String> = Vec::new();
+            for p in list.split(',') {
+                let p = p.trim().to_ascii_lowercase();
+                if p.is_empty() || v.contains(&p) {
+                    continue;
+                }
+                if !matches!(p.as_str(), "auto" | "brave" | "tavily" | "searxng") {
+                    anyhow::bail!(
+                        "unknown provider in --providers: {p} (allowed: auto, brave, tavily, searxng)"
+                    );
+                }
+                v.push(p);
+            }
+            if v.is_empty() {
+                anyhow::bail!("--providers must list at least one provider");
+            }
+            v
+        }
+        None => vec![args.provider.clone()],
+    };
+    let provider_concurrency = args.provider_concurrency.clamp(1, 8);
+
+    let base_url = args.base_url.trim_end_matches('/').to_string();
+    let e2e = eval::load_e2e_queries_v1(&args.queries_json)?;
+
+    let svc = mcp::WebpipeMcp::new().map_err(|e| anyhow::anyhow!(e.to_string()))?;
+
+    let mut f = std::io::BufWriter::new(std::fs::File::create(&out)?);
+
+    let max_results = args.max_results.clamp(1, 20);
+    let max_urls = args.max_urls.clamp(1, 10);
+    let top_chunks = args.top_chunks.clamp(1, 50);
+    let max_chunk_chars = args.max_chunk_chars.clamp(20, 5_000);
+
+    let call_json = |r: rmcp::model::CallToolResult| -> Result<serde_json::Value> {
+        if let Some(v) = r.structured_content {
+            return Ok(v);
+        }
+        // Back-compat: older tool results echoed JSON as the first `content` text item.
+        // Newer tools may include Markdown first, so scan for any parseable JSON text.
+        for c in &r.content {
+            let s = c.as_text().map(|t| t.text.as_str()).unwrap_or("").trim();
+            if s.is_empty() {
+                continue;
+            }
+            if let Ok(v) = serde_json::from_str::<serde_json::Value>(s) {
+                return Ok(v);
+            }
+        }
+        anyhow::bail!("tool result had no structured_content and no JSON text payload");
+    };
+
+    for q in e2e.queries {
+        let urls = q.expand_urls(&base_url);
+
+        // Case A: search leg (uses configured providers/endpoints via env), once per provider.
+        let search_leg = |provider: String| {
+            let svc = &svc;
+            let args = &args;
+            let query = q.query.clone();
+            async move {
+                let t0 = std::time::Instant::now();
+                let r = svc
+                    .web_search_extract(Parameters(Some(mcp::WebSearchExtractArgs {
+                        query: Some(query),
+                        urls: None,
+                        provider: Some(provider.clone()),
+                        auto_mode: Some(args.auto_mode.clone()),
+                        selection_mode: Some(args.selection_mode.clone()),
+                        fetch_backend: Some(args.fetch_backend.clone()),
+                        no_network: Some(false),
+                        max_results: Some(max_results),
+                        max_urls: Some(max_urls),
+                        timeout_ms: Some(args.timeout_ms),
+                        max_bytes: Some(args.max_bytes),
+                        top_chunks: Some(top_chunks),
+                        max_chunk_chars: Some(max_chunk_chars),
+                        include_links: Some(false),
+                        include_text: Some(false),
+                        agentic: Some(false),
+                        url_selection_mode: Some("preserve".to_string()),
+                        cache_read: Some(true),
+                        cache_write: Some(true),
+                        ..Default::default()
+                    })))
+                    .await;
+                (provider, t0.elapsed().as_millis(), r)
+            }
+        };
+        let legs: Vec<_> = futures::stream::iter(providers.iter().cloned().map(search_leg))
+            .buffered(provider_concurrency)
+            .collect()
+            .await;
+        for (provider, elapsed_ms, res) in legs {
+            let res = res.map_err(|e| anyhow::anyhow!(e.to_string()))?;
+            let row_a = serde_json::json!({
+                "schema_version": 1,
+                "kind": "webpipe_eval_matrix_row",
+                "generated_at_epoch_s": now,
+                "case": "search",
+                "provider": provider,
+                "query_id": q.query_id,
+                "query": q.query,
+                "tags": q.tags,
+                "urls": urls,
+                "elapsed_ms": elapsed_ms,
+                "result": call_json(res)?
+            });
+            writeln!(f, "{}", serde_json::to_string(&row_a)?)?;
+        }
+
+        // Case B: warm cache via urls-mode (bounded)
+        let t1 = std::time::Instant::now();
+        let res_b = svc
+            .web_search_extract(Parameters(Some(mcp::WebSearchExtractArgs {
+                query: Some(q.query.clone()),
+                urls: Some(urls.clone()),
+                url_selection_mode: Some("preserve".to_string()),
+                provider: Some("auto".to_string()),
+                auto_mode: Some("fallback".to_string()),
+                selection_mode: Some(args.selection_mode.clone()),
+                fetch_backend: Some(args.fetch_backend.clone()),
+                no_network: Some(false),
+                max_urls: Some(max_urls),
+                timeout_ms: Some(args.timeout_ms),
+                max_bytes: Some(args.max_bytes),
+                top_chunks: Some(top_chunks),
+                max_chunk_chars: Some(max_chunk_chars),
+                include_links: Some(false),
+                include_text: Some(false),
+                agentic: Some(false),
+                cache_read: Some(true),
+                cache_write: Some(true),
+                ..Default::default()
+            })))
+            .await
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        let row_b = serde_json::json!({
+            "schema_version": 1,
+            "kind": "webpipe_eval_matrix_row",
+            "generated_at_epoch_s": now,
+            "case": "warm_urls",
+            "query_id": q.query_id,
+            "query": q.query,
+            "tags": q.tags,
+            "urls": urls,
+            "elapsed_ms": t1.elapsed().as_millis(),
+            "result": call_json(res_b)?
+        });
+        writeln!(f, "{}", serde_json::to_string(&row_b)?)?;
+
+        // Case C: offline replay (urls-mode + no_network=true)
+        let t2 = std::time::Instant::now();
+        let res_c = svc
+            .web_search_extract(Parameters(Some(mcp::WebSearchExtractArgs {
+                query: Some(q.query.clone()),
+                urls: Some(urls.clone()),
+                url_selection_mode: Some("preserve".to_string()),
+                provider: Some("auto".to_string()),
+                auto_mode: Some("fallback".to_string()),
+                selection_mode: Some(args.selection_mode.clone()),
+                fetch_backend: Some(args.fetch_backend.clone()),
+                no_network: Some(true),
+                max_urls: Some(max_urls),
+                timeout_ms: Some(args.timeout_ms),
+                max_bytes: Some(args.max_bytes),
+                top_chunks: Some(top_chunks),
+                max_chunk_chars: Some(max_chunk_chars),
+                include_links: Some(false),
+                include_text: Some(false),
+                agentic: Some(false),
+                cache_read: Some(true),
+                cache_write: Some(false),
+                ..Default::default()
+            })))
+            .await
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        let row_c = serde_json::json!({
+            "schema_version": 1,
+            "kind": "webpipe_eval_matrix_row",
+            "generated_at_epoch_s": now,
+            "case": "offline_urls",
+            "query_id": q.query_id,
+            "query": q.query,
+            "tags": q.tags,
+            "urls": urls,
+            "elapsed_ms": t2.elapsed().as_millis(),
+            "result": call_json(res_c)?
+        });
+        writeln!(f, "{}", serde_json::to_string(&row_c)?)?;
+    }
+    f.flush()?;
+
+    Ok(out)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Env-file loader.
@@ -26103,6 +41074,33 @@ async fn main() -> Result<()> {
             let p = eval::eval_fetch(spec).await?;
             println!("{}", p.display());
         }
+        #[cfg(feature = "eval")]
+        Commands::EvalExtract(args) => {
+            let now = args.now_epoch_s.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+            });
+            let out = args.out.unwrap_or_else(|| {
+                std::path::PathBuf::from(format!(".generated/webpipe-eval-extract-{now}.json"))
+            });
+            let engines = args
+                .engines
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>();
+            let spec = eval::EvalExtractSpec {
+                fixtures_dir: args.fixtures_dir,
+                engines,
+                width: args.width,
+                out,
+                now_epoch_s: Some(now),
+            };
+            let p = eval::eval_extract(spec)?;
+            println!("{}", p.display());
+        }
         #[cfg(all(feature = "eval", feature = "stdio"))]
         Commands::EvalSearchExtract(args) => {
             use rmcp::handler::server::wrapper::Parameters;
@@ -26173,8 +41171,8 @@ async fn main() -> Result<()> {
                 );
             }
             for m in &selection_modes {
-                if m.as_str() != "score" && m.as_str() != "pareto" {
-                    anyhow::bail!("unknown selection_mode in compare_selection_modes: {m} (allowed: score, pareto)");
+                if !matches!(m.as_str(), "score" | "pareto" | "mmr") {
+                    anyhow::bail!("unknown selection_mode in compare_selection_modes: {m} (allowed: score, pareto, mmr)");
                 }
             }
 
@@ -26252,6 +41250,9 @@ async fn main() -> Result<()> {
                                 agentic_selector: Some(args.agentic_selector.clone()),
                                 agentic_max_search_rounds: args.agentic_max_search_rounds,
                                 agentic_frontier_max: args.agentic_frontier_max,
+                                agentic_max_depth: args.agentic_max_depth,
+                                agentic_min_quality_score: args.agentic_min_quality_score,
+                                agentic_prefetch: args.agentic_prefetch,
                                 planner_max_calls: args.planner_max_calls,
                                 compact: None,
                                 ..Default::default()
@@ -26383,6 +41384,9 @@ async fn main() -> Result<()> {
                                 agentic_selector: Some(args.agentic_selector.clone()),
                                 agentic_max_search_rounds: args.agentic_max_search_rounds,
                                 agentic_frontier_max: args.agentic_frontier_max,
+                                agentic_max_depth: args.agentic_max_depth,
+                                agentic_min_quality_score: args.agentic_min_quality_score,
+                                agentic_prefetch: args.agentic_prefetch,
                                 planner_max_calls: args.planner_max_calls,
                                 compact: None,
                                 ..Default::default()
@@ -26492,8 +41496,11 @@ async fn main() -> Result<()> {
         }
         #[cfg(all(feature = "eval", feature = "stdio"))]
         Commands::EvalMatrix(args) => {
-            use rmcp::handler::server::wrapper::Parameters;
-
+            let out = run_eval_matrix(args).await?;
+            println!("{}", out.display());
+        }
+        #[cfg(feature = "eval")]
+        Commands::EvalMatrixScore(args) => {
             let now = args.now_epoch_s.unwrap_or_else(|| {
                 std::time::SystemTime::now()
                     .duration_since(std::time::UNIX_EPOCH)
@@ -26501,207 +41508,19 @@ async fn main() -> Result<()> {
                     .as_secs()
             });
             let out = args.out.unwrap_or_else(|| {
-                std::path::PathBuf::from(format!(".generated/webpipe-eval-matrix-{now}.jsonl"))
+                std::path::PathBuf::from(format!(".generated/webpipe-eval-matrix-score-{now}.json"))
             });
             std::fs::create_dir_all(
                 out.parent()
                     .unwrap_or_else(|| std::path::Path::new(".generated")),
             )?;
 
-            let base_url = args.base_url.trim_end_matches('/').to_string();
-            let e2e = eval::load_e2e_queries_v1(&args.queries_json)?;
-
-            let svc = mcp::WebpipeMcp::new().map_err(|e| anyhow::anyhow!(e.to_string()))?;
-
-            let mut f = std::io::BufWriter::new(std::fs::File::create(&out)?);
-
-            let max_results = args.max_results.clamp(1, 20);
-            let max_urls = args.max_urls.clamp(1, 10);
-            let top_chunks = args.top_chunks.clamp(1, 50);
-            let max_chunk_chars = args.max_chunk_chars.clamp(20, 5_000);
-
-            let call_json = |r: rmcp::model::CallToolResult| -> Result<serde_json::Value> {
-                if let Some(v) = r.structured_content {
-                    return Ok(v);
-                }
-                // Back-compat: older tool results echoed JSON as the first `content` text item.
-                // Newer tools may include Markdown first, so scan for any parseable JSON text.
-                for c in &r.content {
-                    let s = c.as_text().map(|t| t.text.as_str()).unwrap_or("").trim();
-                    if s.is_empty() {
-                        continue;
-                    }
-                    if let Ok(v) = serde_json::from_str::<serde_json::Value>(s) {
-                        return Ok(v);
-                    }
-                }
-                anyhow::bail!("tool result had no structured_content and no JSON text payload");
-            };
-
-            for q in e2e.queries {
-                let urls: Vec<String> = q
-                    .url_paths
-                    .iter()
-                    .map(|p| {
-                        let p = p.trim();
-                        if p.starts_with("http://") || p.starts_with("https://") {
-                            p.to_string()
-                        } else {
-                            format!("{base_url}/{}", p.trim_start_matches('/'))
-                        }
-                    })
-                    .collect();
-
-                // Case A: search leg (uses configured providers/endpoints via env)
-                let t0 = std::time::Instant::now();
-                let res_a = svc
-                    .web_search_extract(Parameters(Some(mcp::WebSearchExtractArgs {
-                        query: Some(q.query.clone()),
-                        urls: None,
-                        provider: Some(args.provider.clone()),
-                        auto_mode: Some(args.auto_mode.clone()),
-                        selection_mode: Some(args.selection_mode.clone()),
-                        fetch_backend: Some(args.fetch_backend.clone()),
-                        no_network: Some(false),
-                        max_results: Some(max_results),
-                        max_urls: Some(max_urls),
-                        timeout_ms: Some(args.timeout_ms),
-                        max_bytes: Some(args.max_bytes),
-                        top_chunks: Some(top_chunks),
-                        max_chunk_chars: Some(max_chunk_chars),
-                        include_links: Some(false),
-                        include_text: Some(false),
-                        agentic: Some(false),
-                        url_selection_mode: Some("preserve".to_string()),
-                        cache_read: Some(true),
-                        cache_write: Some(true),
-                        ..Default::default()
-                    })))
-                    .await
-                    .map_err(|e| anyhow::anyhow!(e.to_string()))?;
-
-                let row_a = serde_json::json!({
-                    "schema_version": 1,
-                    "kind": "webpipe_eval_matrix_row",
-                    "generated_at_epoch_s": now,
-                    "case": "search",
-                    "query_id": q.query_id,
-                    "query": q.query,
-                    "tags": q.tags,
-                    "urls": urls,
-                    "elapsed_ms": t0.elapsed().as_millis(),
-                    "result": call_json(res_a)?
-                });
-                use std::io::Write;
-                writeln!(f, "{}", serde_json::to_string(&row_a)?)?;
-
-                // Case B: warm cache via urls-mode (bounded)
-                let t1 = std::time::Instant::now();
-                let res_b = svc
-                    .web_search_extract(Parameters(Some(mcp::WebSearchExtractArgs {
-                        query: row_a
-                            .get("query")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string()),
-                        urls: Some(
-                            row_a
-                                .get("urls")
-                                .and_then(|v| v.as_array())
-                                .into_iter()
-                                .flatten()
-                                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                                .collect(),
-                        ),
-                        url_selection_mode: Some("preserve".to_string()),
-                        provider: Some("auto".to_string()),
-                        auto_mode: Some("fallback".to_string()),
-                        selection_mode: Some(args.selection_mode.clone()),
-                        fetch_backend: Some(args.fetch_backend.clone()),
-                        no_network: Some(false),
-                        max_urls: Some(max_urls),
-                        timeout_ms: Some(args.timeout_ms),
-                        max_bytes: Some(args.max_bytes),
-                        top_chunks: Some(top_chunks),
-                        max_chunk_chars: Some(max_chunk_chars),
-                        include_links: Some(false),
-                        include_text: Some(false),
-                        agentic: Some(false),
-                        cache_read: Some(true),
-                        cache_write: Some(true),
-                        ..Default::default()
-                    })))
-                    .await
-                    .map_err(|e| anyhow::anyhow!(e.to_string()))?;
-                let row_b = serde_json::json!({
-                    "schema_version": 1,
-                    "kind": "webpipe_eval_matrix_row",
-                    "generated_at_epoch_s": now,
-                    "case": "warm_urls",
-                    "query_id": row_a["query_id"].clone(),
-                    "query": row_a["query"].clone(),
-                    "tags": row_a["tags"].clone(),
-                    "urls": row_a["urls"].clone(),
-                    "elapsed_ms": t1.elapsed().as_millis(),
-                    "result": call_json(res_b)?
-                });
-                writeln!(f, "{}", serde_json::to_string(&row_b)?)?;
-
-                // Case C: offline replay (urls-mode + no_network=true)
-                let t2 = std::time::Instant::now();
-                let res_c = svc
-                    .web_search_extract(Parameters(Some(mcp::WebSearchExtractArgs {
-                        query: row_a
-                            .get("query")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string()),
-                        urls: Some(
-                            row_a
-                                .get("urls")
-                                .and_then(|v| v.as_array())
-                                .into_iter()
-                                .flatten()
-                                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                                .collect(),
-                        ),
-                        url_selection_mode: Some("preserve".to_string()),
-                        provider: Some("auto".to_string()),
-                        auto_mode: Some("fallback".to_string()),
-                        selection_mode: Some(args.selection_mode.clone()),
-                        fetch_backend: Some(args.fetch_backend.clone()),
-                        no_network: Some(true),
-                        max_urls: Some(max_urls),
-                        timeout_ms: Some(args.timeout_ms),
-                        max_bytes: Some(args.max_bytes),
-                        top_chunks: Some(top_chunks),
-                        max_chunk_chars: Some(max_chunk_chars),
-                        include_links: Some(false),
-                        include_text: Some(false),
-                        agentic: Some(false),
-                        cache_read: Some(true),
-                        cache_write: Some(false),
-                        ..Default::default()
-                    })))
-                    .await
-                    .map_err(|e| anyhow::anyhow!(e.to_string()))?;
-                let row_c = serde_json::json!({
-                    "schema_version": 1,
-                    "kind": "webpipe_eval_matrix_row",
-                    "generated_at_epoch_s": now,
-                    "case": "offline_urls",
-                    "query_id": row_a["query_id"].clone(),
-                    "query": row_a["query"].clone(),
-                    "tags": row_a["tags"].clone(),
-                    "urls": row_a["urls"].clone(),
-                    "elapsed_ms": t2.elapsed().as_millis(),
-                    "result": call_json(res_c)?
-                });
-                writeln!(f, "{}", serde_json::to_string(&row_c)?)?;
-            }
-
+            let payload = eval_matrix_score_payload(&args.matrix_artifact, &args.qrels, now)?;
+            std::fs::write(&out, serde_json::to_string_pretty(&payload)? + "\n")?;
             println!("{}", out.display());
         }
         #[cfg(feature = "eval")]
-        Commands::EvalMatrixScore(args) => {
+        Commands::EvalMatrixScoreDiff(args) => {
             let now = args.now_epoch_s.unwrap_or_else(|| {
                 std::time::SystemTime::now()
                     .duration_since(std::time::UNIX_EPOCH)
@@ -26709,150 +41528,36 @@ async fn main() -> Result<()> {
                     .as_secs()
             });
             let out = args.out.unwrap_or_else(|| {
-                std::path::PathBuf::from(format!(".generated/webpipe-eval-matrix-score-{now}.json"))
+                std::path::PathBuf::from(format!(
+                    ".generated/webpipe-eval-matrix-score-diff-{now}.json"
+                ))
             });
             std::fs::create_dir_all(
                 out.parent()
                     .unwrap_or_else(|| std::path::Path::new(".generated")),
             )?;
 
-            let qrels = eval::load_e2e_qrels_v1(&args.qrels)?;
-
-            // Read + parse JSONL rows.
-            let raw = std::fs::read_to_string(&args.matrix_artifact)?;
-            let mut rows: Vec<serde_json::Value> = Vec::new();
-            for (i, line) in raw.lines().enumerate() {
-                let s = line.trim();
-                if s.is_empty() {
-                    continue;
-                }
-                let v: serde_json::Value = serde_json::from_str(s).map_err(|e| {
-                    anyhow::anyhow!("matrix_artifact line {}: invalid json: {}", i + 1, e)
-                })?;
-                rows.push(v);
-            }
-
-            // Index: query_id -> case -> row
-            let mut by_q: std::collections::BTreeMap<
-                String,
-                std::collections::BTreeMap<String, serde_json::Value>,
-            > = std::collections::BTreeMap::new();
-            for r in rows {
-                let qid = r
-                    .get("query_id")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                let case = r
-                    .get("case")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                if qid.is_empty() || case.is_empty() {
-                    continue;
-                }
-                by_q.entry(qid).or_default().insert(case, r);
-            }
-
-            fn extract_urls_from_result(
-                res: &serde_json::Value,
-            ) -> std::collections::BTreeSet<String> {
-                let mut out = std::collections::BTreeSet::new();
-                if let Some(top) = res.get("top_chunks").and_then(|v| v.as_array()) {
-                    for c in top {
-                        if let Some(u) = c.get("url").and_then(|v| v.as_str()) {
-                            out.insert(u.to_string());
-                        }
-                    }
-                }
-                if let Some(per_url) = res.get("results").and_then(|v| v.as_array()) {
-                    for r in per_url {
-                        if let Some(u) = r.get("url").and_then(|v| v.as_str()) {
-                            out.insert(u.to_string());
-                        }
-                        if let Some(u) = r.get("final_url").and_then(|v| v.as_str()) {
-                            out.insert(u.to_string());
-                        }
-                    }
-                }
-                out
-            }
-
-            let expected_cases = ["search", "warm_urls", "offline_urls"];
-            let mut per_query: Vec<serde_json::Value> = Vec::new();
-            let mut totals = serde_json::json!({
-                "queries": qrels.qrels.len(),
-                "cases": { "search": {"ok": 0, "hit": 0}, "warm_urls": {"ok": 0, "hit": 0}, "offline_urls": {"ok": 0, "hit": 0} }
-            });
-
-            for q in &qrels.qrels {
-                let mut per_case = Vec::new();
-                for case in expected_cases {
-                    let row = by_q.get(&q.query_id).and_then(|m| m.get(case)).cloned();
-
-                    if let Some(row) = row {
-                        let res = row
-                            .get("result")
-                            .cloned()
-                            .unwrap_or(serde_json::Value::Null);
-                        let ok = res.get("ok").and_then(|v| v.as_bool()).unwrap_or(false);
-                        let urls = extract_urls_from_result(&res);
-                        let hit = ok
-                            && q.expected_url_substrings.iter().any(|needle| {
-                                let needle = needle.as_str();
-                                urls.iter().any(|u| u.contains(needle))
-                            });
-
-                        if ok {
-                            totals["cases"][case]["ok"] = serde_json::json!(
-                                totals["cases"][case]["ok"].as_u64().unwrap_or(0) + 1
-                            );
-                        }
-                        if hit {
-                            totals["cases"][case]["hit"] = serde_json::json!(
-                                totals["cases"][case]["hit"].as_u64().unwrap_or(0) + 1
-                            );
-                        }
-
-                        per_case.push(serde_json::json!({
-                            "case": case,
-                            "row_present": true,
-                            "ok": ok,
-                            "hit": hit,
-                            "expected_url_substrings": q.expected_url_substrings,
-                            "observed_url_count": urls.len(),
-                        }));
-                    } else {
-                        per_case.push(serde_json::json!({
-                            "case": case,
-                            "row_present": false,
-                            "ok": false,
-                            "hit": false,
-                            "expected_url_substrings": q.expected_url_substrings,
-                            "observed_url_count": 0,
-                        }));
-                    }
-                }
-                per_query.push(serde_json::json!({
-                    "query_id": q.query_id,
-                    "cases": per_case
-                }));
-            }
-
-            let payload = serde_json::json!({
-                "schema_version": 1,
-                "kind": "webpipe_eval_matrix_score",
-                "generated_at_epoch_s": now,
-                "inputs": {
-                    "matrix_artifact": args.matrix_artifact,
-                    "qrels": args.qrels
-                },
-                "totals": totals,
-                "per_query": per_query
-            });
-
+            let read = |p: &std::path::Path| -> Result<serde_json::Value> {
+                let raw = std::fs::read_to_string(p)?;
+                serde_json::from_str(&raw)
+                    .map_err(|e| anyhow::anyhow!("{}: invalid json: {e}", p.display()))
+            };
+            let payload = eval_matrix_score_diff_payload(
+                &read(&args.baseline)?,
+                &read(&args.candidate)?,
+                args.tolerance,
+                now,
+            )?;
             std::fs::write(&out, serde_json::to_string_pretty(&payload)? + "\n")?;
             println!("{}", out.display());
+            let regressed = payload["regressed_metrics"].as_u64().unwrap_or(0);
+            if regressed > 0 {
+                anyhow::bail!(
+                    "eval-matrix-score-diff: {regressed} metric(s) regressed beyond tolerance {} (verdict={})",
+                    args.tolerance,
+                    payload["verdict"].as_str().unwrap_or("")
+                );
+            }
         }
         #[cfg(feature = "eval")]
         Commands::EvalMatrixExport(args) => {
@@ -27788,26 +42493,15 @@ Conservatism:
                     .unwrap_or_default()
                     .as_secs()
             });
-            let now_s = now.to_string();
-            let out_dir = args
-                .out_dir
-                .unwrap_or_else(|| std::path::PathBuf::from(".generated"));
-            std::fs::create_dir_all(&out_dir)?;
-
-            let matrix_out = out_dir.join(format!("webpipe-eval-matrix-run-{now}.jsonl"));
-            let score_out = out_dir.join(format!("webpipe-eval-matrix-score-run-{now}.json"));
-            let export_out = out_dir.join(format!("webpipe-eval-matrix-export-run-{now}.jsonl"));
-            let judge_out = out_dir.join(format!("webpipe-eval-matrix-judge-run-{now}.json"));
-            let manifest_out = out_dir.join(format!("webpipe-eval-matrix-manifest-run-{now}.json"));
+            if args.dry_run {
+                let plan = eval_matrix_run_dry_run(&args, now)?;
+                println!("{}", serde_json::to_string_pretty(&plan)?);
+                return Ok(());
+            }
+            let paths = EvalMatrixRunPaths::new(&args, now);
+            std::fs::create_dir_all(&paths.out_dir)?;
 
             let exe = std::env::current_exe()?;
-            let queries_json = args.queries_json.to_string_lossy().to_string();
-            let qrels = args.qrels.to_string_lossy().to_string();
-            let matrix_out_s = matrix_out.to_string_lossy().to_string();
-            let score_out_s = score_out.to_string_lossy().to_string();
-            let export_out_s = export_out.to_string_lossy().to_string();
-            let judge_out_s = judge_out.to_string_lossy().to_string();
-            let max_text_chars_s = args.max_text_chars.to_string();
 
             fn run(mut cmd: std::process::Command) -> Result<()> {
                 let out = cmd.output()?;
@@ -27822,73 +42516,12 @@ Conservatism:
                 Ok(())
             }
 
-            // 1) eval-matrix
-            let mut c = std::process::Command::new(&exe);
-            c.args([
-                "eval-matrix",
-                "--queries-json",
-                &queries_json,
-                "--base-url",
-                &args.base_url,
-                "--provider",
-                &args.provider,
-                "--auto-mode",
-                &args.auto_mode,
-                "--selection-mode",
-                &args.selection_mode,
-                "--fetch-backend",
-                &args.fetch_backend,
-                "--out",
-                &matrix_out_s,
-                "--now-epoch-s",
-                &now_s,
-            ]);
-            run(c)?;
-
-            // 2) eval-matrix-score
-            let mut c = std::process::Command::new(&exe);
-            c.args([
-                "eval-matrix-score",
-                "--matrix-artifact",
-                &matrix_out_s,
-                "--qrels",
-                &qrels,
-                "--out",
-                &score_out_s,
-                "--now-epoch-s",
-                &now_s,
-            ]);
-            run(c)?;
-
-            // 3) eval-matrix-export
-            let mut c = std::process::Command::new(&exe);
-            c.args([
-                "eval-matrix-export",
-                "--matrix-artifact",
-                &matrix_out_s,
-                "--qrels",
-                &qrels,
-                "--max-text-chars",
-                &max_text_chars_s,
-                "--out",
-                &export_out_s,
-                "--now-epoch-s",
-                &now_s,
-            ]);
-            run(c)?;
-
-            // 4) eval-matrix-judge
-            let mut c = std::process::Command::new(&exe);
-            c.args([
-                "eval-matrix-judge",
-                "--examples-artifact",
-                &export_out_s,
-                "--out",
-                &judge_out_s,
-                "--now-epoch-s",
-                &now_s,
-            ]);
-            run(c)?;
+            // eval-matrix → eval-matrix-score → eval-matrix-export → eval-matrix-judge
+            for argv in eval_matrix_run_steps(&args, &paths, now) {
+                let mut c = std::process::Command::new(&exe);
+                c.args(&argv);
+                run(c)?;
+            }
 
             let git_sha = args.git_sha.clone().or_else(best_effort_git_sha);
             let manifest = serde_json::json!({
@@ -27909,19 +42542,19 @@ Conservatism:
                     "sha": git_sha
                 },
                 "artifacts": {
-                    "matrix": matrix_out,
-                    "score": score_out,
-                    "export": export_out,
-                    "judge": judge_out
+                    "matrix": paths.matrix,
+                    "score": paths.score,
+                    "export": paths.export,
+                    "judge": paths.judge
                 }
             });
             std::fs::write(
-                &manifest_out,
+                &paths.manifest,
                 serde_json::to_string_pretty(&manifest)? + "\n",
             )?;
             match args.output.to_ascii_lowercase().as_str() {
                 "json" => println!("{}", serde_json::to_string(&manifest)?),
-                _ => println!("{}", judge_out.display()),
+                _ => println!("{}", paths.judge.display()),
             }
         }
         #[cfg(all(feature = "eval", feature = "stdio"))]
@@ -31108,6 +45741,7 @@ Rules:
                 "features": {
                     "stdio": cfg!(feature = "stdio"),
                     "semantic": true,
+                    "embed_local": cfg!(feature = "embed-local"),
                     "embeddings_openai": false,
                     "embeddings_tei": false,
                     "embeddings_openrouter": has_env("WEBPIPE_OPENROUTER_API_KEY")
@@ -31125,6 +45759,12 @@ Rules:
                     },
                     "llm": {
                         "perplexity": perplexity_configured,
+                        "anthropic": has_env("WEBPIPE_ANTHROPIC_API_KEY") || has_env("ANTHROPIC_API_KEY"),
+                        "anthropic_model": if has_env("WEBPIPE_ANTHROPIC_API_KEY") || has_env("ANTHROPIC_API_KEY") {
+                            std::env::var("WEBPIPE_ANTHROPIC_MODEL").ok().filter(|s| !s.trim().is_empty())
+                        } else {
+                            None
+                        },
                         "openrouter": has_env("WEBPIPE_OPENROUTER_API_KEY") || has_env("OPENROUTER_API_KEY"),
                         "openrouter_model": if has_env("WEBPIPE_OPENROUTER_API_KEY") || has_env("OPENROUTER_API_KEY") {
                             Some(mcp::WebpipeMcp::openrouter_model_from_env())
@@ -31171,7 +45811,7 @@ Rules:
                             .unwrap_or(false),
                     );
                     println!(
-                        "llm: openrouter={} openai={} groq={} perplexity={}",
+                        "llm: openrouter={} openai={} groq={} perplexity={} anthropic={}",
                         payload["configured"]["llm"]["openrouter"]
                             .as_bool()
                             .unwrap_or(false),
@@ -31184,6 +45824,9 @@ Rules:
                         payload["configured"]["llm"]["perplexity"]
                             .as_bool()
                             .unwrap_or(false),
+                        payload["configured"]["llm"]["anthropic"]
+                            .as_bool()
+                            .unwrap_or(false),
                     );
                     println!("checks:");
                     if let Some(arr) = payload["checks"].as_array() {
@@ -33213,6 +47856,55 @@ Rules:
             std::fs::write(&out, serde_json::to_string_pretty(&payload)? + "\n")?;
             println!("{}", out.display());
         }
+        Commands::CacheExport(args) => {
+            let cache_dir = args
+                .cache_dir
+                .or_else(|| {
+                    std::env::var("WEBPIPE_CACHE_DIR")
+                        .ok()
+                        .filter(|s| !s.trim().is_empty())
+                        .map(std::path::PathBuf::from)
+                })
+                .unwrap_or_else(mcp::default_cache_dir);
+            let cache = webpipe_local::FsCache::new(cache_dir.clone());
+            let report = cache.export(&args.out, args.max_entries);
+            let v = serde_json::json!({
+                "schema_version": 1,
+                "kind": "cache_export",
+                "ok": true,
+                "cache_dir": cache_dir.display().to_string(),
+                "out": args.out.display().to_string(),
+                "report": report,
+            });
+            println!("{}", v);
+        }
+        Commands::CachePurge(args) => {
+            let cache_dir = args
+                .cache_dir
+                .or_else(|| {
+                    std::env::var("WEBPIPE_CACHE_DIR")
+                        .ok()
+                        .filter(|s| !s.trim().is_empty())
+                        .map(std::path::PathBuf::from)
+                })
+                .unwrap_or_else(mcp::default_cache_dir);
+            let cache = webpipe_local::FsCache::new(cache_dir.clone());
+            let removed = match (&args.url, &args.prefix) {
+                (Some(url), _) => cache.invalidate_url(url),
+                (None, Some(prefix)) => cache.invalidate_url_prefix(prefix),
+                (None, None) => 0,
+            };
+            let v = serde_json::json!({
+                "schema_version": 1,
+                "kind": "cache_purge",
+                "ok": true,
+                "cache_dir": cache_dir.display().to_string(),
+                "url": args.url,
+                "prefix": args.prefix,
+                "removed": removed,
+            });
+            println!("{}", v);
+        }
         Commands::Version(args) => {
             let v = serde_json::json!({
                 "schema_version": 2,
@@ -29,6 +29,15 @@ pub(crate) fn warning_hint(code: &str) -> Option<&'static str> {
         "body_truncated_by_max_bytes" => Some(
             "The response body was truncated by max_bytes. Increase max_bytes, or enable retry_on_truncation=true (and optionally truncation_retry_max_bytes) to recover tail content.",
         ),
+        "output_truncated" => Some(
+            "The response was trimmed to fit max_output_bytes (see output_truncated for what was dropped). Raise max_output_bytes, or lower max_urls/top_chunks/max_chunk_chars and leave include_text=false.",
+        ),
+        "amp_alternate_used" => Some(
+            "The page extracted with low confidence, so its declared AMP alternate (rel=amphtml) was extracted instead; see attempts.amp_alternate. final_url is the AMP page. Set prefer_amp=false to keep the original page.",
+        ),
+        "streaming_capped" => Some(
+            "The response was an open-ended stream (text/event-stream, ndjson, ...) and was cut off by the streaming caps; what arrived is returned. Raise WEBPIPE_STREAMING_MAX_BYTES / WEBPIPE_STREAMING_MAX_MS (0 disables) if you need more of it.",
+        ),
         "image_no_text_extraction" => Some(
             "This is an image and no text/OCR backend is available in this environment (tesseract/vision).",
         ),
@@ -45,6 +54,9 @@ pub(crate) fn warning_hint(code: &str) -> Option<&'static str> {
         "semantic_backend_not_configured" => Some(
             "Semantic rerank requested but embeddings backend is not configured. Set an embeddings API key or disable semantic_rerank.",
         ),
+        "semantic_unavailable" => Some(
+            "semantic_rerank was requested but is unavailable (see `unavailable_reason`: the `semantic` feature is compiled out, or no embeddings backend is configured), so chunks keep their lexical scoring. Set WEBPIPE_OPENROUTER_API_KEY (or OPENROUTER_API_KEY), or drop semantic_rerank.",
+        ),
         "semantic_rerank_timeout" => Some(
             "Semantic rerank exceeded its bounded timeout and was skipped. If you need it, increase WEBPIPE_SEMANTIC_TIMEOUT_MS or disable semantic_rerank/semantic_auto_fallback.",
         ),
@@ -78,6 +90,15 @@ pub(crate) fn warning_hint(code: &str) -> Option<&'static str> {
         "extract_input_truncated" => Some(
             "The fetched body was large; extraction only used the first WEBPIPE_EXTRACT_MAX_BYTES bytes. To change this, lower max_bytes or increase WEBPIPE_EXTRACT_MAX_BYTES (server env).",
         ),
+        "extract_streamed" => Some(
+            "The fetched body exceeded WEBPIPE_EXTRACT_MAX_BYTES, so it was converted with a streaming (DOM-less) extractor: plain block text, no main-content/readability selection. Set WEBPIPE_EXTRACT_STREAMING=off to truncate and use the DOM extractors instead.",
+        ),
+        "noscript_merged" => Some(
+            "The main body was low-signal (likely a JS app shell), so text from <noscript> fallbacks was merged into the extraction (engine=html_noscript). If it is still thin, try render or firecrawl fallbacks.",
+        ),
+        "iframes_merged" => Some(
+            "Text from the page's iframes was appended to the extraction, each under an [iframe: <url>] note; extract.iframes lists what was merged or skipped. Set follow_iframes=false for the top-level document only.",
+        ),
         "extract_pipeline_timeout" => Some(
             "Extraction exceeded its bounded pipeline timeout and returned a minimal empty result. Try reducing max_bytes/max_chars, switching fetch_backend, or increasing WEBPIPE_EXTRACT_PIPELINE_TIMEOUT_MS.",
         ),
@@ -90,6 +111,9 @@ pub(crate) fn warning_hint(code: &str) -> Option<&'static str> {
         "truncation_retry_failed" => Some(
             "The initial fetch was truncated by max_bytes, and the bounded truncation retry failed. Consider increasing max_bytes or trying a different URL.",
         ),
+        "firecrawl_not_configured" => Some(
+            "No Firecrawl key is configured (WEBPIPE_FIRECRAWL_API_KEY or FIRECRAWL_API_KEY), so only the local backend was measured and nothing was compared.",
+        ),
         "firecrawl_fallback_on_low_signal" => Some(
             "Local extraction looked like low-signal app-shell/JS gunk, so we retried this URL via Firecrawl (bounded).",
         ),
@@ -108,6 +132,12 @@ pub(crate) fn warning_hint(code: &str) -> Option<&'static str> {
         "deadline_exceeded_partial" => Some(
             "Hard deadline hit; returned partial results. Increase deadline_ms (or reduce max_urls/timeout_ms) if you need more coverage.",
         ),
+        "youtube_ytdlp_not_found" => Some(
+            "A YouTube playlist/channel URL could not be expanded into its videos because yt-dlp is not installed, so the page itself was fetched. Install yt-dlp (e.g. `pipx install yt-dlp`) or pass the video URLs directly.",
+        ),
+        "youtube_playlist_expand_failed" => Some(
+            "yt-dlp could not list the videos of a YouTube playlist/channel (private, empty, or it timed out), so the page itself was fetched. Check the URL, raise WEBPIPE_YOUTUBE_PLAYLIST_TIMEOUT_MS, or pass the video URLs directly.",
+        ),
         "no_query_overlap_any_url" => Some(
             "No extracted chunks matched the query tokens across the selected URLs. Try different URLs (or deeper links), increase max_chars, or enable render_fallback_on_low_signal / fetch_backend=\"render\" for JS-heavy docs.",
         ),
@@ -120,6 +150,15 @@ pub(crate) fn warning_hint(code: &str) -> Option<&'static str> {
         "cache_search_timeout" => Some(
             "Cache search+extract exceeded its bounded timeout and returned no results. Increase WEBPIPE_CACHE_SEARCH_TIMEOUT_MS (or reduce max_scan_entries/max_docs).",
         ),
+        "cost_cascade_below_quality_gate" => Some(
+            "auto_mode=cost_cascade returned free-tier results that did not pass the quality gate because no paid provider was configured or reachable. Configure WEBPIPE_BRAVE_API_KEY/WEBPIPE_TAVILY_API_KEY, or relax WEBPIPE_COST_CASCADE_MIN_RESULTS/WEBPIPE_COST_CASCADE_MAX_JUNK_FRAC.",
+        ),
+        "cache_migrate_truncated" => Some(
+            "Cache migration stopped at max_entries before walking the whole cache. Call web_cache_migrate again (it is idempotent) or raise max_entries.",
+        ),
+        "decode_failed" => Some(
+            "The server's Content-Encoding did not match the body (e.g. `gzip` on a plain body), so the body was kept as received. Text extraction is usually fine; if it looks like binary garbage, the server is misconfigured.",
+        ),
         "cache_io_timeout" => Some(
             "Cache filesystem IO exceeded its bounded timeout; cache was bypassed to keep the tool responsive. If this happens often, check filesystem health or increase WEBPIPE_CACHE_IO_TIMEOUT_MS.",
         ),
@@ -129,6 +168,24 @@ pub(crate) fn warning_hint(code: &str) -> Option<&'static str> {
         "render_fallback_not_supported" => Some(
             "Render fallback is not supported in the current mode/config (e.g. privacy_mode=offline, or anonymous mode with a socks5h:// proxy). For anonymous render, use an HTTP proxy endpoint (Tor users often run Privoxy).",
         ),
+        "followups_unavailable" => Some(
+            "suggest_followups=true, but the follow-up LLM call failed or returned no parseable questions, so `followups` was omitted. The synthesized answer is unaffected; retry or check the LLM backend.",
+        ),
+        "deep_research_pass2_skipped" => Some(
+            "passes=2 was requested, but no second evidence round ran (urls=[...] mode has no search step, or no refined query could be derived from pass-1 evidence). The answer uses pass-1 evidence only.",
+        ),
+        "deep_research_pass2_failed" => Some(
+            "passes=2 ran a refined second search/extract round, but it failed; the answer uses pass-1 evidence only. See `rounds` for the refined query.",
+        ),
+        "structured_report_repaired" => Some(
+            "structured_output=true: the first synthesis reply did not validate as a report, so one repair call was made; `report` comes from the repaired reply.",
+        ),
+        "structured_report_invalid" => Some(
+            "structured_output=true, but neither the synthesis reply nor its repair validated as a report (bad JSON, empty sections, or citations to unknown sources), so `report` was omitted and `answer.text` is the raw reply. Retry, or try a stronger llm_model.",
+        ),
+        "structured_report_extractive" => Some(
+            "structured_output=true with llm_backend=\"auto\", but no LLM backend is configured, so `report` was built extractively from the top evidence chunks. Configure a synthesis backend for a written report.",
+        ),
         "perplexity_search_mode_off_rejected" => Some(
             "Tried to disable provider-side browsing (search_mode=\"off\"), but the provider rejected it; we retried without search_mode.",
         ),
@@ -159,6 +216,18 @@ pub(crate) fn warning_hint(code: &str) -> Option<&'static str> {
         "github_release_rewritten_to_api" => Some(
             "This looks like a GitHub release page. We rewrote to fetch release JSON from the GitHub API for higher-signal text.",
         ),
+        "hybrid_scoring_fell_back_to_lexical" => Some(
+            "scoring=\"hybrid\" needs OpenRouter embeddings, which were unavailable (see `scoring.fallback_reason`), so results are ranked lexically and paraphrase-only docs are dropped. Set WEBPIPE_OPENROUTER_API_KEY (or OPENROUTER_API_KEY) to enable hybrid scoring.",
+        ),
+        "robots_unavailable" => Some(
+            "robots.txt could not be fetched (network error or 5xx), so the crawl proceeded as if everything were allowed. Set respect_robots=false to skip the lookup, or retry later.",
+        ),
+        "crawl_truncated_by_max_pages" => Some(
+            "The crawl stopped at max_pages with links still queued (see `summary.frontier_remaining`). Increase max_pages (max 100), lower max_depth, or start from a more specific URL.",
+        ),
+        "arxiv_discussion_fetch_failed" => Some(
+            "Some discussion pages could not be fetched or extracted (see `discussions[].error`); the rest are still ranked. Retry later or open the failed URLs with web_extract.",
+        ),
         "semantic_auto_fallback_used" => Some(
             "Semantic rerank ran automatically because lexical chunk scoring looked ineffective for this query. To avoid embeddings latency/cost, set semantic_auto_fallback=false (or leave semantic_rerank=false).",
         ),
@@ -186,9 +255,27 @@ pub(crate) fn warning_hint(code: &str) -> Option<&'static str> {
         "openreview_pdf_fallback_to_api" => Some(
             "PDF extraction for this OpenReview paper was degraded, so webpipe fell back to the OpenReview notes API (api.openreview.net) to extract higher-signal metadata (title/abstract) as evidence.",
         ),
+        "readability_fallback" => Some(
+            "Readability found no dominant article block, so html_main or whole-page text was used instead. Expect more boilerplate; engine=\"auto\" or fetch_backend=\"firecrawl\" may do better.",
+        ),
+        "extract_engine_fallback" => Some(
+            "The requested engine produced no text, so automatic engine selection was used. Check extract.engine for the extractor that ran.",
+        ),
+        "date_filter_unsupported" => Some(
+            "The provider could not apply part of the date filter (freshness/after/before), so results may fall outside it. Brave and Tavily accept after/before; SearXNG and ddg only take freshness.",
+        ),
         "paper_backend_failed" => Some(
             "All paper search backends failed (Semantic Scholar / OpenAlex are free-tier and may rate-limit). Try again after a brief wait, narrow the query, or use arxiv_search instead (uses arXiv Atom API, more stable). For Google Scholar coverage, set WEBPIPE_SERPAPI_API_KEY.",
         ),
+        "sitemap_empty" => Some(
+            "No sitemap URLs were found (no robots.txt Sitemap: directive and no /sitemap.xml, or everything listed was disallowed). Seed the crawl with web_crawl from the site root instead.",
+        ),
+        "diff_first_seen" => Some(
+            "No cached copy of this URL existed, so there is nothing to diff yet. The fresh copy was cached (unless update_cache=false); call web_diff again later to see changes.",
+        ),
+        "firecrawl_crawl_incomplete" => Some(
+            "The Firecrawl crawl job did not finish within timeout_ms, so pages[] holds only what it had scraped so far. Raise timeout_ms (max 300000) or lower max_pages.",
+        ),
         _ => None,
     }
 }
@@ -213,6 +300,8 @@ pub(crate) enum ErrorCode {
     FetchFailed,
     SearchFailed,
     CacheError,
+    ContentTypeMismatch,
+    HttpStatus,
     UnexpectedError,
 }
 
@@ -227,6 +316,8 @@ impl ErrorCode {
             Self::FetchFailed => "fetch_failed",
             Self::SearchFailed => "search_failed",
             Self::CacheError => "cache_error",
+            Self::ContentTypeMismatch => "content_type_mismatch",
+            Self::HttpStatus => "http_status",
             Self::UnexpectedError => "unexpected_error",
         }
     }
@@ -240,6 +331,9 @@ impl ErrorCode {
             | Self::InvalidParams
             | Self::InvalidUrl
             | Self::ProviderUnavailable
+            | Self::ContentTypeMismatch
+            // Per-status retryability (429/5xx) is set by the caller on the error object.
+            | Self::HttpStatus
             | Self::UnexpectedError => false,
         }
     }
@@ -256,6 +350,7 @@ pub(crate) fn strip_minimal_output(payload: &mut serde_json::Value) {
     const KEEP: &[&str] = &[
         "ok",
         "top_chunks",
+        "contrasting_chunks",
         "warning_codes",
         "warning_hints",
         "schema_version",
@@ -268,6 +363,247 @@ pub(crate) fn strip_minimal_output(payload: &mut serde_json::Value) {
     }
 }
 
+/// Top-level keys [`trim_to_max_output_bytes`] never removes.
+const TRIM_PROTECTED: &[&str] = &[
+    "ok",
+    "error",
+    "schema_version",
+    "kind",
+    "elapsed_ms",
+    "attempts",
+    "request",
+    "warning_codes",
+    "top_chunks",
+    "results",
+    "output_truncated",
+];
+
+/// Per-URL detail fields dropped (from every result, one field at a time) before URLs are.
+const TRIM_RESULT_FIELDS: &[&str] = &[
+    "extract.text",
+    "extract.structure",
+    "extract.links",
+    "extract.semantic",
+    "extract.chunks",
+    "extract.quality",
+    "extract.text_preview",
+    "warning_hints",
+    "attempts",
+];
+
+fn serialized_len(v: &serde_json::Value) -> usize {
+    serde_json::to_vec(v).map_or(0, |b| b.len())
+}
+
+/// Deterministically trim `payload` until it serializes to at most `max_bytes`, and record what
+/// was dropped under `output_truncated` (no-op when it already fits).
+///
+/// Order, lowest value first: `contrasting_chunks`, then the lowest-scoring `top_chunks` (down to
+/// one), then per-URL detail ([`TRIM_RESULT_FIELDS`]), then other unprotected top-level keys,
+/// then whole per-URL results from the end, then the last chunk. Envelope keys and `ok`/`error`
+/// are never touched, so a tiny cap can still be exceeded (`output_truncated.fits=false`).
+pub(crate) fn trim_to_max_output_bytes(payload: &mut serde_json::Value, max_bytes: usize) {
+    let bytes_before = serialized_len(payload);
+    if bytes_before <= max_bytes || !payload.is_object() {
+        return;
+    }
+    let mut dropped_contrasting = 0usize;
+    let mut dropped_top = 0usize;
+    let mut dropped_results = 0usize;
+    let mut dropped_fields: Vec<&str> = Vec::new();
+    let mut dropped_keys: Vec<String> = Vec::new();
+    let note = |payload: &serde_json::Value,
+                dc: usize,
+                dt: usize,
+                dr: usize,
+                fields: &[&str],
+                keys: &[String]| {
+        serde_json::json!({
+            "max_output_bytes": max_bytes,
+            "bytes_before": bytes_before,
+            "bytes_after": serde_json::Value::Null,
+            "fits": serde_json::Value::Null,
+            "dropped_contrasting_chunks": dc,
+            "dropped_top_chunks": dt,
+            "dropped_results": dr,
+            "dropped_result_fields": fields,
+            "dropped_keys": keys,
+            "kept_top_chunks": payload["top_chunks"].as_array().map_or(0, |a| a.len()),
+            "kept_results": payload["results"].as_array().map_or(0, |a| a.len()),
+        })
+    };
+    // Room for the note itself plus the `output_truncated` warning code.
+    let overhead =
+        |payload: &serde_json::Value, dropped_fields: &[&str], dropped_keys: &[String]| {
+            serialized_len(&note(payload, 0, 0, 0, dropped_fields, dropped_keys)) + 64
+        };
+    let fits = |payload: &serde_json::Value, dropped_fields: &[&str], dropped_keys: &[String]| {
+        serialized_len(payload) + overhead(payload, dropped_fields, dropped_keys) <= max_bytes
+    };
+
+    'trim: {
+        while payload["contrasting_chunks"]
+            .as_array()
+            .is_some_and(|a| !a.is_empty())
+        {
+            if fits(payload, &dropped_fields, &dropped_keys) {
+                break 'trim;
+            }
+            if let Some(a) = payload["contrasting_chunks"].as_array_mut() {
+                a.pop();
+                dropped_contrasting += 1;
+            }
+        }
+        // Worst chunk first; ties drop the later (lower-ranked) one.
+        let drop_worst_chunk = |payload: &mut serde_json::Value| {
+            let Some(a) = payload["top_chunks"].as_array_mut() else {
+                return;
+            };
+            let worst = a
+                .iter()
+                .enumerate()
+                .min_by_key(|(i, c)| (c["score"].as_u64().unwrap_or(0), usize::MAX - i))
+                .map(|(i, _)| i);
+            if let Some(i) = worst {
+                a.remove(i);
+            }
+        };
+        while payload["top_chunks"]
+            .as_array()
+            .is_some_and(|a| a.len() > 1)
+        {
+            if fits(payload, &dropped_fields, &dropped_keys) {
+                break 'trim;
+            }
+            drop_worst_chunk(payload);
+            dropped_top += 1;
+        }
+        for field in TRIM_RESULT_FIELDS {
+            if fits(payload, &dropped_fields, &dropped_keys) {
+                break 'trim;
+            }
+            let mut any = false;
+            if let Some(rs) = payload["results"].as_array_mut() {
+                for r in rs.iter_mut() {
+                    let (parent, key) = match field.split_once('.') {
+                        Some((p, k)) => (r.get_mut(p), k),
+                        None => (Some(r), *field),
+                    };
+                    if let Some(o) = parent.and_then(|p| p.as_object_mut()) {
+                        any |= o.remove(key).is_some();
+                    }
+                }
+            }
+            if any {
+                dropped_fields.push(*field);
+            }
+        }
+        let extra: Vec<String> = payload
+            .as_object()
+            .map(|o| {
+                o.keys()
+                    .filter(|k| !TRIM_PROTECTED.contains(&k.as_str()))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        for k in extra {
+            if fits(payload, &dropped_fields, &dropped_keys) {
+                break 'trim;
+            }
+            if let Some(o) = payload.as_object_mut() {
+                o.remove(&k);
+            }
+            dropped_keys.push(k);
+        }
+        while payload["results"].as_array().is_some_and(|a| !a.is_empty()) {
+            if fits(payload, &dropped_fields, &dropped_keys) {
+                break 'trim;
+            }
+            if let Some(a) = payload["results"].as_array_mut() {
+                a.pop();
+                dropped_results += 1;
+            }
+        }
+        if !fits(payload, &dropped_fields, &dropped_keys) {
+            drop_worst_chunk(payload);
+            dropped_top += 1;
+        }
+    }
+
+    let mut out = note(
+        payload,
+        dropped_contrasting,
+        dropped_top,
+        dropped_results,
+        &dropped_fields,
+        &dropped_keys,
+    );
+    if let Some(o) = payload.as_object_mut() {
+        let codes = o
+            .entry("warning_codes")
+            .or_insert_with(|| serde_json::json!([]));
+        if let Some(a) = codes.as_array_mut() {
+            if !a.iter().any(|c| c == "output_truncated") {
+                a.push(serde_json::json!("output_truncated"));
+            }
+        }
+    }
+    // Two passes: filling in `bytes_after` changes the length it reports.
+    for _ in 0..2 {
+        payload["output_truncated"] = out.clone();
+        let bytes_after = serialized_len(payload);
+        out["bytes_after"] = serde_json::json!(bytes_after);
+        out["fits"] = serde_json::json!(bytes_after <= max_bytes);
+    }
+    payload["output_truncated"] = out;
+}
+
+/// How tool results are encoded on the wire (`WEBPIPE_ENVELOPE_FORMAT`). The payload schema is
+/// the same either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EnvelopeFormat {
+    Json,
+    /// Requires the `msgpack` feature; otherwise the setting is ignored.
+    Msgpack,
+}
+
+impl EnvelopeFormat {
+    pub(crate) fn from_env() -> Self {
+        let v = std::env::var("WEBPIPE_ENVELOPE_FORMAT").unwrap_or_default();
+        match v.trim().to_ascii_lowercase().as_str() {
+            "msgpack" | "messagepack" if cfg!(feature = "msgpack") => Self::Msgpack,
+            _ => Self::Json,
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Msgpack => "msgpack",
+        }
+    }
+}
+
+#[cfg(feature = "msgpack")]
+pub(crate) const MSGPACK_MIME_TYPE: &str = "application/msgpack";
+#[cfg(feature = "msgpack")]
+pub(crate) const MSGPACK_PAYLOAD_URI: &str = "webpipe://result/payload.msgpack";
+
+#[cfg(feature = "msgpack")]
+pub(crate) fn payload_to_msgpack(
+    payload: &serde_json::Value,
+) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    rmp_serde::to_vec_named(payload)
+}
+
+#[cfg(all(test, feature = "msgpack"))]
+pub(crate) fn payload_from_msgpack(
+    bytes: &[u8],
+) -> Result<serde_json::Value, rmp_serde::decode::Error> {
+    rmp_serde::from_slice(bytes)
+}
+
 pub(crate) fn add_envelope_fields(payload: &mut serde_json::Value, kind: &str, elapsed_ms: u128) {
     payload["schema_version"] = serde_json::json!(super::SCHEMA_VERSION);
     payload["kind"] = serde_json::json!(kind);
@@ -0,0 +1,49 @@
+/// Write a cache entry in the on-disk `xx/yy/<key>.{json,bin}` layout.
+fn put_raw(cache_dir: &std::path::Path, key: &str, url: &str, body: &[u8]) {
+    let dir = cache_dir.join(&key[0..2]).join(&key[2..4]);
+    std::fs::create_dir_all(&dir).expect("mkdir");
+    let meta = serde_json::json!({ "schema_version": 1, "url": url, "status": 200 });
+    std::fs::write(dir.join(format!("{key}.json")), meta.to_string()).expect("write meta");
+    std::fs::write(dir.join(format!("{key}.bin")), body).expect("write body");
+}
+
+#[test]
+fn webpipe_cache_export_writes_per_url_files() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let cache_dir = tmp.path().join("cache");
+    let out_dir = tmp.path().join("out");
+    put_raw(
+        &cache_dir,
+        &"a".repeat(64),
+        "https://docs.example/guide/intro",
+        b"intro",
+    );
+    put_raw(
+        &cache_dir,
+        &"b".repeat(64),
+        "https://docs.example/a:b*c",
+        b"odd",
+    );
+
+    let bin = assert_cmd::cargo::cargo_bin!("webpipe");
+    let out = std::process::Command::new(bin)
+        .arg("cache-export")
+        .arg("--out")
+        .arg(&out_dir)
+        .env("WEBPIPE_DOTENV", "0")
+        .env("WEBPIPE_CACHE_DIR", &cache_dir)
+        .output()
+        .expect("run webpipe cache-export");
+    assert!(out.status.success());
+    let v: serde_json::Value = serde_json::from_slice(&out.stdout).expect("json stdout");
+    assert_eq!(v["kind"].as_str(), Some("cache_export"));
+    assert_eq!(v["report"]["exported"].as_u64(), Some(2), "{v}");
+
+    for (url, body) in [
+        ("https://docs.example/guide/intro", &b"intro"[..]),
+        ("https://docs.example/a:b*c", &b"odd"[..]),
+    ] {
+        let p = out_dir.join(webpipe_local::fs_path::sanitize_for_fs(url));
+        assert_eq!(std::fs::read(&p).expect("exported file"), body);
+    }
+}
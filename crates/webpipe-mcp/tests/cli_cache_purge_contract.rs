@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+use webpipe_core::{FetchCachePolicy, FetchRequest, FetchResponse, FetchSource};
+
+fn put(cache: &webpipe_local::FsCache, url: &str) -> FetchRequest {
+    let req = FetchRequest {
+        url: url.to_string(),
+        timeout_ms: None,
+        max_bytes: None,
+        headers: BTreeMap::new(),
+        method: None,
+        body: None,
+        cache: FetchCachePolicy::default(),
+    };
+    let resp = FetchResponse {
+        url: url.to_string(),
+        final_url: url.to_string(),
+        status: 200,
+        content_type: Some("text/plain".to_string()),
+        headers: BTreeMap::new(),
+        bytes: b"body".to_vec(),
+        wire_bytes: 4,
+        truncated: false,
+        source: FetchSource::Network,
+        cache_status: None,
+        body_path: None,
+        revalidating: false,
+        timings_ms: BTreeMap::new(),
+    };
+    cache.put(&req, &resp).expect("cache put");
+    req
+}
+
+fn purge(cache_dir: &std::path::Path, args: &[&str]) -> (bool, serde_json::Value) {
+    let bin = assert_cmd::cargo::cargo_bin!("webpipe");
+    let out = std::process::Command::new(bin)
+        .arg("cache-purge")
+        .args(args)
+        .env("WEBPIPE_DOTENV", "0")
+        .env("WEBPIPE_CACHE_DIR", cache_dir)
+        .output()
+        .expect("run webpipe cache-purge");
+    let v = serde_json::from_slice(&out.stdout).unwrap_or(serde_json::Value::Null);
+    (out.status.success(), v)
+}
+
+#[test]
+fn webpipe_cache_purge_removes_by_url_and_prefix() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let cache = webpipe_local::FsCache::new(tmp.path().to_path_buf());
+    let a = put(&cache, "https://docs.example/a");
+    let b = put(&cache, "https://docs.example/guide/b");
+    let other = put(&cache, "https://other.example/");
+
+    let (ok, v) = purge(tmp.path(), &["--url", "https://docs.example/a"]);
+    assert!(ok, "{v}");
+    assert_eq!(v["kind"].as_str(), Some("cache_purge"));
+    assert_eq!(v["removed"].as_u64(), Some(1));
+    assert!(cache.get(&a).unwrap().is_none());
+    assert!(cache.get(&b).unwrap().is_some());
+
+    let (ok, v) = purge(tmp.path(), &["--prefix", "https://docs.example/"]);
+    assert!(ok, "{v}");
+    assert_eq!(v["removed"].as_u64(), Some(1));
+    assert!(cache.get(&b).unwrap().is_none());
+    assert!(cache.get(&other).unwrap().is_some());
+
+    // One of --url / --prefix is required.
+    let (ok, _) = purge(tmp.path(), &[]);
+    assert!(!ok);
+}
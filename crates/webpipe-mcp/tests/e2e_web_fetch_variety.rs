@@ -195,7 +195,7 @@ async fn webpipe_web_fetch_handles_variety_of_urls_and_content_types() {
         "expected html text"
     );
 
-    // HTTP status errors should be surfaced as a warning (even though ok=true).
+    // HTTP status errors fail the call (ok=false, error.code=http_status) and keep the warning.
     let v_404 = call(
         &service,
         "web_fetch",
@@ -210,7 +210,9 @@ async fn webpipe_web_fetch_handles_variety_of_urls_and_content_types() {
         }),
     )
     .await;
-    assert_eq!(v_404["ok"].as_bool(), Some(true));
+    assert_eq!(v_404["ok"].as_bool(), Some(false));
+    assert_eq!(v_404["error"]["code"].as_str(), Some("http_status"));
+    assert_eq!(v_404["error"]["status"].as_u64(), Some(404));
     assert_eq!(v_404["status"].as_u64(), Some(404));
     let codes = v_404["warning_codes"]
         .as_array()
@@ -239,7 +241,9 @@ async fn webpipe_web_fetch_handles_variety_of_urls_and_content_types() {
         }),
     )
     .await;
-    assert_eq!(v_429["ok"].as_bool(), Some(true));
+    assert_eq!(v_429["ok"].as_bool(), Some(false));
+    assert_eq!(v_429["error"]["code"].as_str(), Some("http_status"));
+    assert_eq!(v_429["error"]["retryable"].as_bool(), Some(true));
     assert_eq!(v_429["status"].as_u64(), Some(429));
     let codes = v_429["warning_codes"]
         .as_array()
@@ -280,7 +284,8 @@ async fn webpipe_web_fetch_handles_variety_of_urls_and_content_types() {
         }),
     )
     .await;
-    assert_eq!(v_429_cache["ok"].as_bool(), Some(true));
+    assert_eq!(v_429_cache["ok"].as_bool(), Some(false));
+    assert_eq!(v_429_cache["error"]["code"].as_str(), Some("http_status"));
     assert_eq!(v_429_cache["status"].as_u64(), Some(429));
     assert_eq!(v_429_cache["source"].as_str(), Some("cache"));
     assert_eq!(
@@ -0,0 +1,89 @@
+#![cfg(feature = "eval")]
+
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name)
+}
+
+fn run_diff(
+    baseline: &Path,
+    candidate: &Path,
+    tolerance: &str,
+    out: &Path,
+) -> (bool, serde_json::Value) {
+    let bin = assert_cmd::cargo::cargo_bin!("webpipe");
+    let o = std::process::Command::new(bin)
+        .args(["eval-matrix-score-diff", "--baseline"])
+        .arg(baseline)
+        .arg("--candidate")
+        .arg(candidate)
+        .args(["--tolerance", tolerance, "--now-epoch-s", "1", "--out"])
+        .arg(out)
+        .env("WEBPIPE_DOTENV", "0")
+        .output()
+        .expect("run eval-matrix-score-diff");
+    let v = serde_json::from_str(&std::fs::read_to_string(out).expect("diff artifact"))
+        .expect("diff json");
+    (o.status.success(), v)
+}
+
+fn metric<'a>(v: &'a serde_json::Value, name: &str) -> &'a serde_json::Value {
+    v["metrics"]
+        .as_array()
+        .expect("metrics")
+        .iter()
+        .find(|m| m["metric"] == name)
+        .unwrap_or_else(|| panic!("missing metric {name}: {v}"))
+}
+
+#[test]
+fn eval_matrix_score_diff_reports_deltas_flips_and_fails_on_regression() {
+    // Fixtures (2 queries): candidate gains q2/search but loses q2/warm_urls.
+    let baseline = fixture("eval_matrix_score_diff_baseline.json");
+    let candidate = fixture("eval_matrix_score_diff_candidate.json");
+    let tmp = tempfile::tempdir().expect("tempdir");
+
+    let (ok, v) = run_diff(&baseline, &candidate, "0", &tmp.path().join("d0.json"));
+    assert!(!ok, "regression beyond tolerance must exit non-zero");
+    assert_eq!(v["kind"].as_str(), Some("webpipe_eval_matrix_score_diff"));
+    assert_eq!(v["verdict"].as_str(), Some("mixed"));
+    assert_eq!(v["regressed_metrics"].as_u64(), Some(1));
+    assert_eq!(v["improved_metrics"].as_u64(), Some(1));
+
+    let search = metric(&v, "search.hit_rate");
+    assert_eq!(search["baseline"].as_f64(), Some(0.5));
+    assert_eq!(search["candidate"].as_f64(), Some(1.0));
+    assert_eq!(search["delta"].as_f64(), Some(0.5));
+    assert_eq!(search["status"].as_str(), Some("improved"));
+    let warm = metric(&v, "warm_urls.hit_rate");
+    assert_eq!(warm["delta"].as_f64(), Some(-0.5));
+    assert_eq!(warm["status"].as_str(), Some("regressed"));
+    assert_eq!(
+        metric(&v, "offline_urls.hit_rate")["status"].as_str(),
+        Some("unchanged")
+    );
+    assert_eq!(metric(&v, "search.ok_rate")["delta"].as_f64(), Some(0.0));
+
+    assert_eq!(
+        v["per_query"]["newly_pass"],
+        serde_json::json!([{"query_id": "q2", "case": "search"}])
+    );
+    assert_eq!(
+        v["per_query"]["newly_fail"],
+        serde_json::json!([{"query_id": "q2", "case": "warm_urls"}])
+    );
+
+    // Within tolerance: the 0.5 drop is allowed, and so is the 0.5 gain ("unchanged").
+    let (ok, v) = run_diff(&baseline, &candidate, "0.5", &tmp.path().join("d1.json"));
+    assert!(ok, "diff within tolerance must exit zero: {v}");
+    assert_eq!(v["verdict"].as_str(), Some("unchanged"));
+
+    // Identical artifacts: unchanged, no flips.
+    let (ok, v) = run_diff(&baseline, &baseline, "0", &tmp.path().join("d2.json"));
+    assert!(ok);
+    assert_eq!(v["verdict"].as_str(), Some("unchanged"));
+    assert_eq!(v["per_query"]["newly_fail"], serde_json::json!([]));
+}
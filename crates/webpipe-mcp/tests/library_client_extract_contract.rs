@@ -0,0 +1,68 @@
+/// Contract: the library `Webpipe` client extracts a page into typed output, without the MCP
+/// server in the loop.
+#[tokio::test]
+async fn library_client_extract_returns_typed_output() {
+    use axum::{http::header, routing::get, Router};
+
+    let app = Router::new().route(
+        "/post",
+        get(|| async {
+            (
+                [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+                r#"<html><head><title>Release notes</title>
+<meta property="article:published_time" content="2024-03-05T09:00:00Z"></head>
+<body><main><h1>Release notes</h1>
+<p>Version 2 adds streaming uploads with resumable_token_abc support.</p>
+<p>Other changes are listed in the changelog.</p></main></body></html>"#,
+            )
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let cache_dir = tempfile::TempDir::new().unwrap();
+    let client = webpipe::Webpipe::with_cache_dir(Some(cache_dir.path().to_path_buf())).unwrap();
+    let url = format!("http://{addr}/post");
+    let out = client
+        .extract(
+            &url,
+            &webpipe::ExtractOptions {
+                query: Some("resumable_token_abc".to_string()),
+                top_chunks: 2,
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("extract");
+
+    assert_eq!(out.status, 200);
+    assert_eq!(out.final_url, url);
+    assert!(out.engine.starts_with("html"), "{}", out.engine);
+    assert!(out.text.contains("streaming uploads"), "{}", out.text);
+    assert_eq!(out.text_chars, out.text.chars().count());
+    assert!(!out.text_truncated);
+    assert_eq!(out.published_time.as_deref(), Some("2024-03-05T09:00:00Z"));
+    assert!(!out.chunks.is_empty());
+    assert!(out.chunks[0].text.contains("resumable_token_abc"));
+
+    // The fetch went through the cache like the server's does.
+    let cached = client.fetch(&url).await.expect("fetch");
+    assert_eq!(cached.source, webpipe::core::FetchSource::Cache);
+
+    // Unknown providers are rejected up front rather than falling back.
+    let err = client
+        .search(
+            "anything",
+            &webpipe::SearchOptions {
+                provider: "nonesuch".to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, webpipe::core::Error::NotSupported(_)),
+        "{err}"
+    );
+}
@@ -119,7 +119,7 @@ fn webpipe_mcp_stdio_batch_listings_contract() {
             got_tools.unwrap_or(0) > 0,
             "expected tools/list to return >0 tools in batch mode"
         );
-        assert_eq!(got_prompts, Some(3), "expected 3 prompts in batch mode");
+        assert_eq!(got_prompts, Some(6), "expected 6 prompts in batch mode");
         assert_eq!(got_resources, Some(2), "expected 2 resources in batch mode");
 
         // Terminate the child.
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use webpipe_core::{FetchCachePolicy, FetchRequest, FetchResponse, FetchSource};
+
+fn put(cache: &webpipe_local::FsCache, url: &str, content_type: &str, body: &[u8]) {
+    let req = FetchRequest {
+        url: url.to_string(),
+        timeout_ms: None,
+        max_bytes: None,
+        headers: BTreeMap::new(),
+        method: None,
+        body: None,
+        cache: FetchCachePolicy::default(),
+    };
+    let headers = BTreeMap::from([
+        ("content-type".to_string(), content_type.to_string()),
+        (
+            "set-cookie".to_string(),
+            "session=SECRET_COOKIE".to_string(),
+        ),
+    ]);
+    let resp = FetchResponse {
+        url: url.to_string(),
+        final_url: format!("{url}?final=1"),
+        status: 200,
+        content_type: Some(content_type.to_string()),
+        headers,
+        bytes: body.to_vec(),
+        wire_bytes: body.len() as u64,
+        truncated: false,
+        source: FetchSource::Network,
+        cache_status: None,
+        body_path: None,
+        revalidating: false,
+        timings_ms: BTreeMap::new(),
+    };
+    cache.put(&req, &resp).expect("cache put");
+}
+
+#[test]
+fn webpipe_mcp_stdio_lists_and_reads_cached_documents_as_resources() {
+    let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
+    rt.block_on(async {
+        use rmcp::{
+            model::ReadResourceRequestParam,
+            service::ServiceExt,
+            transport::{ConfigureCommandExt, TokioChildProcess},
+        };
+
+        let cache_dir = tempfile::TempDir::new()?;
+        let cache = webpipe_local::FsCache::new(cache_dir.path().to_path_buf());
+        put(
+            &cache,
+            "https://docs.example/a",
+            "text/html; charset=utf-8",
+            b"<html><body>NEEDLE_CACHED_A</body></html>",
+        );
+        put(
+            &cache,
+            "https://docs.example/b.pdf",
+            "application/pdf",
+            &[0xff, 0xfe, 0x00],
+        );
+
+        let bin = assert_cmd::cargo::cargo_bin!("webpipe");
+        let service = ()
+            .serve(TokioChildProcess::new(
+                tokio::process::Command::new(bin).configure(|cmd| {
+                    cmd.args(["mcp-stdio"]);
+                    cmd.env("WEBPIPE_DOTENV", "0");
+                    cmd.env("WEBPIPE_CACHE_DIR", cache_dir.path());
+                }),
+            )?)
+            .await?;
+
+        let resources = service.list_resources(Default::default()).await?;
+        let cached = resources
+            .resources
+            .iter()
+            .filter(|r| r.raw.uri.starts_with("webpipe-cache://"))
+            .collect::<Vec<_>>();
+        assert_eq!(cached.len(), 2, "{:?}", resources.resources);
+        let a = cached
+            .iter()
+            .find(|r| r.raw.name == "https://docs.example/a?final=1")
+            .expect("html entry named by final_url");
+        assert_eq!(a.raw.mime_type.as_deref(), Some("text/html"));
+        assert!(a
+            .raw
+            .description
+            .as_deref()
+            .unwrap_or("")
+            .contains("https://docs.example/a"));
+        let listing = serde_json::to_string(&resources)?;
+        assert!(!listing.contains("SECRET_COOKIE"), "{listing}");
+
+        let rr = service
+            .read_resource(ReadResourceRequestParam {
+                uri: a.raw.uri.clone(),
+            })
+            .await?;
+        let text = rr
+            .contents
+            .iter()
+            .find_map(|c| match c {
+                rmcp::model::ResourceContents::TextResourceContents { text, .. } => Some(text),
+                _ => None,
+            })
+            .expect("text content");
+        assert!(text.contains("NEEDLE_CACHED_A"), "{text}");
+
+        // Binary bodies and unknown keys are errors, not garbage text.
+        let pdf = cached
+            .iter()
+            .find(|r| r.raw.mime_type.as_deref() == Some("application/pdf"))
+            .expect("pdf entry");
+        assert!(service
+            .read_resource(ReadResourceRequestParam {
+                uri: pdf.raw.uri.clone(),
+            })
+            .await
+            .is_err());
+        assert!(service
+            .read_resource(ReadResourceRequestParam {
+                uri: format!("webpipe-cache://{}", "0".repeat(64)),
+            })
+            .await
+            .is_err());
+
+        service.cancel().await?;
+        Ok::<(), Box<dyn std::error::Error>>(())
+    })
+    .expect("mcp stdio cache resources contract");
+}
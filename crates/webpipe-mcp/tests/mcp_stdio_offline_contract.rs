@@ -174,6 +174,9 @@ fn webpipe_mcp_stdio_offline_contract() {
             "webpipe_search_extract",
             "webpipe_offline_cache_first",
             "webpipe_deep_research",
+            "research_question",
+            "compare_sources",
+            "summarize_url",
         ] {
             assert!(prompt_names.contains(must_have), "missing prompt {must_have}");
         }
@@ -203,6 +206,65 @@ fn webpipe_mcp_stdio_offline_contract() {
             "expected >=1 prompt message, got {}",
             p.messages.len()
         );
+        // Workflow prompts are deterministic templates naming the tool to call.
+        let p = service
+            .get_prompt(GetPromptRequestParam {
+                name: "compare_sources".to_string(),
+                arguments: Some(
+                    serde_json::json!({
+                        "question": "which is faster?",
+                        "urls": "https://a.example/, https://b.example/ https://a.example/"
+                    })
+                    .as_object()
+                    .cloned()
+                    .unwrap(),
+                ),
+            })
+            .await?;
+        let text = serde_json::to_string(&p.messages)?;
+        assert!(text.contains("search_evidence"), "{text}");
+        assert!(text.contains("- https://b.example/"), "{text}");
+        assert!(text.contains("max_urls=2"), "{text}");
+        let again = service
+            .get_prompt(GetPromptRequestParam {
+                name: "compare_sources".to_string(),
+                arguments: Some(
+                    serde_json::json!({
+                        "question": "which is faster?",
+                        "urls": "https://a.example/, https://b.example/ https://a.example/"
+                    })
+                    .as_object()
+                    .cloned()
+                    .unwrap(),
+                ),
+            })
+            .await?;
+        assert_eq!(text, serde_json::to_string(&again.messages)?);
+        let p = service
+            .get_prompt(GetPromptRequestParam {
+                name: "summarize_url".to_string(),
+                arguments: Some(
+                    serde_json::json!({"url": "https://a.example/post"})
+                        .as_object()
+                        .cloned()
+                        .unwrap(),
+                ),
+            })
+            .await?;
+        assert!(serde_json::to_string(&p.messages)?.contains("web_summarize"));
+        // A single URL is not a comparison.
+        assert!(service
+            .get_prompt(GetPromptRequestParam {
+                name: "compare_sources".to_string(),
+                arguments: Some(
+                    serde_json::json!({"question": "q", "urls": "https://a.example/"})
+                        .as_object()
+                        .cloned()
+                        .unwrap(),
+                ),
+            })
+            .await
+            .is_err());
 
         // Resources: server should expose a small set of “documents” for clients.
         let resources = service.list_resources(Default::default()).await?;
@@ -0,0 +1,91 @@
+/// Contract: every tool envelope carries `schema_version`, and it is the library's
+/// `SCHEMA_VERSION` (one constant, not a per-tool copy).
+///
+/// Calls each listed tool offline with empty (or only the required) arguments, so most tools take
+/// their argument-error or not-configured path; that path must still go through the envelope.
+#[test]
+fn every_tool_envelope_carries_schema_version() {
+    let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
+    rt.block_on(async {
+        use rmcp::{
+            model::CallToolRequestParam,
+            service::ServiceExt,
+            transport::{ConfigureCommandExt, TokioChildProcess},
+        };
+
+        let bin = assert_cmd::cargo::cargo_bin!("webpipe");
+        let cache_dir = tempfile::TempDir::new()?;
+        let service = ()
+            .serve(TokioChildProcess::new(
+                tokio::process::Command::new(bin).configure(|cmd| {
+                    cmd.args(["mcp-stdio"]);
+                    cmd.env("WEBPIPE_DOTENV", "0");
+                    cmd.env("WEBPIPE_MCP_TOOLSET", "debug");
+                    cmd.env("WEBPIPE_CACHE_DIR", cache_dir.path());
+                    cmd.env("WEBPIPE_OFFLINE_ONLY", "1");
+                    for k in [
+                        "WEBPIPE_BRAVE_API_KEY",
+                        "BRAVE_SEARCH_API_KEY",
+                        "WEBPIPE_TAVILY_API_KEY",
+                        "TAVILY_API_KEY",
+                        "WEBPIPE_SEARXNG_ENDPOINT",
+                        "WEBPIPE_FIRECRAWL_API_KEY",
+                        "FIRECRAWL_API_KEY",
+                        "WEBPIPE_PERPLEXITY_API_KEY",
+                        "PERPLEXITY_API_KEY",
+                        "WEBPIPE_OPENROUTER_API_KEY",
+                        "OPENROUTER_API_KEY",
+                        "WEBPIPE_OPENAI_API_KEY",
+                        "OPENAI_API_KEY",
+                    ] {
+                        cmd.env_remove(k);
+                    }
+                }),
+            )?)
+            .await?;
+
+        // Required args for tools whose params don't deserialize from `{}`; unreachable hosts.
+        let required = serde_json::json!({
+            "repo_ingest": {"repo_url": "http://127.0.0.1:9/owner/repo"},
+            "web_explore_extract": {"start_url": "http://127.0.0.1:9/"},
+            "web_sitemap_extract": {"site_url": "http://127.0.0.1:9/"},
+            "web_seed_search_extract": {"query": "schema version"},
+            "web_deep_research": {"query": "schema version"},
+        });
+
+        let tools = service.list_tools(Default::default()).await?;
+        assert!(!tools.tools.is_empty(), "no tools listed");
+        for t in &tools.tools {
+            let name = t.name.clone().into_owned();
+            let r = tokio::time::timeout(
+                std::time::Duration::from_secs(30),
+                service.call_tool(CallToolRequestParam {
+                    name: name.clone().into(),
+                    arguments: Some(
+                        required[name.as_str()]
+                            .as_object()
+                            .cloned()
+                            .unwrap_or_default(),
+                    ),
+                }),
+            )
+            .await
+            .unwrap_or_else(|_| panic!("{name} timed out"))
+            .unwrap_or_else(|e| panic!("{name} returned no envelope: {e:?}"));
+            let v = r
+                .structured_content
+                .clone()
+                .unwrap_or_else(|| panic!("{name} returned no structured_content"));
+            assert_eq!(
+                v["schema_version"].as_u64(),
+                Some(webpipe::SCHEMA_VERSION),
+                "{name} envelope schema_version; payload={v}"
+            );
+            assert!(v["kind"].is_string(), "{name} envelope kind; payload={v}");
+        }
+
+        service.cancel().await?;
+        Ok::<(), Box<dyn std::error::Error>>(())
+    })
+    .expect("contract");
+}
@@ -59,6 +59,7 @@ fn webpipe_mcp_stdio_toolset_slim_contract() {
             "repo_ingest",
             "web_explore_extract",
             "web_sitemap_extract",
+            "web_crawl",
             "web_search",
             "web_seed_urls",
             "web_seed_search_extract",
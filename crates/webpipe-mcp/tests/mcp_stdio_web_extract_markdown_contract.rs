@@ -81,7 +81,9 @@ async fn webpipe_mcp_stdio_web_extract_markdown_surfaces_warning_hints() {
         .clone()
         .expect("structured_content exists");
     assert_eq!(payload["kind"].as_str(), Some("web_extract"));
-    assert_eq!(payload["ok"].as_bool(), Some(true));
+    // Non-2xx fails the call, but warnings (and their hints) are still surfaced.
+    assert_eq!(payload["ok"].as_bool(), Some(false));
+    assert_eq!(payload["error"]["code"].as_str(), Some("http_status"));
     assert_eq!(payload["status"].as_u64(), Some(429));
 
     let txt0 = r
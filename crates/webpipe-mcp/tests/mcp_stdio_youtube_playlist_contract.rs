@@ -0,0 +1,105 @@
+#![cfg(unix)]
+
+/// A stand-in `yt-dlp`: `--flat-playlist` lists three videos; any other call writes a one-cue VTT
+/// for the requested video next to the `-o` template.
+const FAKE_YT_DLP: &str = r#"#!/bin/sh
+if [ "$1" = "--flat-playlist" ]; then
+  for id in vid00000001 vid00000002 vid00000003; do
+    printf '{"_type": "url", "ie_key": "Youtube", "id": "%s"}\n' "$id"
+  done
+  exit 0
+fi
+out=""; prev=""; url=""
+for a in "$@"; do
+  [ "$prev" = "-o" ] && out="$a"
+  prev="$a"; url="$a"
+done
+id="${url##*v=}"
+printf 'WEBVTT\n\n00:00:00.000 --> 00:00:02.000\nNEEDLE_PLAYLIST transcript of %s\n' "$id" > "$(dirname "$out")/$id.en.vtt"
+"#;
+
+#[test]
+fn webpipe_mcp_stdio_search_extract_expands_youtube_playlist_into_video_transcripts() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
+    rt.block_on(async {
+        use rmcp::{
+            model::CallToolRequestParam,
+            service::ServiceExt,
+            transport::{ConfigureCommandExt, TokioChildProcess},
+        };
+
+        let bin_dir = tempfile::TempDir::new()?;
+        let yt = bin_dir.path().join("yt-dlp");
+        std::fs::write(&yt, FAKE_YT_DLP)?;
+        std::fs::set_permissions(&yt, std::fs::Permissions::from_mode(0o755))?;
+        let path = format!(
+            "{}:{}",
+            bin_dir.path().display(),
+            std::env::var("PATH").unwrap_or_default()
+        );
+
+        let bin = assert_cmd::cargo::cargo_bin!("webpipe");
+        let cache_dir = tempfile::TempDir::new()?;
+        let service = ()
+            .serve(TokioChildProcess::new(
+                tokio::process::Command::new(bin).configure(|cmd| {
+                    cmd.args(["mcp-stdio"]);
+                    cmd.env("WEBPIPE_DOTENV", "0");
+                    cmd.env("WEBPIPE_CACHE_DIR", cache_dir.path());
+                    cmd.env("PATH", &path);
+                    // Strict: a transcript failure errors instead of fetching youtube.com.
+                    cmd.env("WEBPIPE_YOUTUBE_TRANSCRIPTS", "yt-dlp");
+                }),
+            )?)
+            .await?;
+
+        let r = service
+            .call_tool(CallToolRequestParam {
+                name: "search_evidence".into(),
+                arguments: Some(
+                    serde_json::json!({
+                        "query": "NEEDLE_PLAYLIST transcript",
+                        "urls": ["https://www.youtube.com/playlist?list=PLtest"],
+                        "url_selection_mode": "preserve",
+                        "fetch_backend": "local",
+                        "agentic": false,
+                        "max_urls": 2,
+                        "top_chunks": 4,
+                        "include_text": false,
+                        "timeout_ms": 10_000,
+                        "deadline_ms": 30_000
+                    })
+                    .as_object()
+                    .cloned()
+                    .unwrap(),
+                ),
+            })
+            .await?;
+        let payload = r.structured_content.clone().expect("structured_content");
+        assert_eq!(payload["ok"].as_bool(), Some(true), "{payload}");
+
+        let urls: Vec<&str> = payload["results"]
+            .as_array()
+            .expect("results")
+            .iter()
+            .filter_map(|x| x["url"].as_str())
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://www.youtube.com/watch?v=vid00000001",
+                "https://www.youtube.com/watch?v=vid00000002",
+            ],
+            "{payload}"
+        );
+        let s = payload.to_string();
+        assert!(s.contains("transcript of vid00000001"), "{s}");
+        assert!(s.contains("transcript of vid00000002"), "{s}");
+
+        service.cancel().await?;
+        Ok::<(), Box<dyn std::error::Error>>(())
+    })
+    .expect("mcp stdio youtube playlist contract");
+}
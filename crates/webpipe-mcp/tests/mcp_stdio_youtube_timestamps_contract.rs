@@ -0,0 +1,118 @@
+#![cfg(unix)]
+
+/// A stand-in `yt-dlp`: writes a one-cue VTT for the requested video next to the `-o` template,
+/// plus an info JSON with one chapter when asked for `--write-info-json`.
+const FAKE_YT_DLP: &str = r#"#!/bin/sh
+out=""; prev=""; url=""; info=0
+for a in "$@"; do
+  [ "$prev" = "-o" ] && out="$a"
+  [ "$a" = "--write-info-json" ] && info=1
+  prev="$a"; url="$a"
+done
+id="${url##*v=}"
+dir="$(dirname "$out")"
+printf 'WEBVTT\n\n00:01:05.500 --> 00:01:08.000\nNEEDLE_TIMESTAMPS transcript of %s\n' "$id" > "$dir/$id.en.vtt"
+if [ "$info" = 1 ]; then
+  printf '{"id": "%s", "chapters": [{"start_time": 60.0, "end_time": 90.0, "title": "Findings"}]}' "$id" > "$dir/$id.info.json"
+fi
+"#;
+
+#[test]
+fn webpipe_mcp_stdio_youtube_timestamps_return_timed_transcript_json() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
+    rt.block_on(async {
+        use rmcp::{
+            model::CallToolRequestParam,
+            service::ServiceExt,
+            transport::{ConfigureCommandExt, TokioChildProcess},
+        };
+
+        let bin_dir = tempfile::TempDir::new()?;
+        let yt = bin_dir.path().join("yt-dlp");
+        std::fs::write(&yt, FAKE_YT_DLP)?;
+        std::fs::set_permissions(&yt, std::fs::Permissions::from_mode(0o755))?;
+        let path = format!(
+            "{}:{}",
+            bin_dir.path().display(),
+            std::env::var("PATH").unwrap_or_default()
+        );
+
+        let bin = assert_cmd::cargo::cargo_bin!("webpipe");
+        let cache_dir = tempfile::TempDir::new()?;
+        let service = ()
+            .serve(TokioChildProcess::new(
+                tokio::process::Command::new(bin).configure(|cmd| {
+                    cmd.args(["mcp-stdio"]);
+                    cmd.env("WEBPIPE_DOTENV", "0");
+                    cmd.env("WEBPIPE_CACHE_DIR", cache_dir.path());
+                    cmd.env("PATH", &path);
+                    // Strict: a transcript failure errors instead of fetching youtube.com.
+                    cmd.env("WEBPIPE_YOUTUBE_TRANSCRIPTS", "yt-dlp");
+                    cmd.env("WEBPIPE_YOUTUBE_TIMESTAMPS", "1");
+                }),
+            )?)
+            .await?;
+
+        let r = service
+            .call_tool(CallToolRequestParam {
+                name: "search_evidence".into(),
+                arguments: Some(
+                    serde_json::json!({
+                        "query": "NEEDLE_TIMESTAMPS transcript",
+                        "urls": ["https://www.youtube.com/watch?v=vid00000009"],
+                        "url_selection_mode": "preserve",
+                        "fetch_backend": "local",
+                        "agentic": false,
+                        "max_urls": 1,
+                        "top_chunks": 2,
+                        "include_text": false,
+                        "timeout_ms": 10_000,
+                        "deadline_ms": 30_000
+                    })
+                    .as_object()
+                    .cloned()
+                    .unwrap(),
+                ),
+            })
+            .await?;
+        let payload = r.structured_content.clone().expect("structured_content");
+        assert_eq!(payload["ok"].as_bool(), Some(true), "{payload}");
+        let s = payload.to_string();
+        assert!(s.contains("application/x-youtube-transcript+json"), "{s}");
+        // Chunks come from the text projection, not the JSON.
+        assert!(
+            s.contains("NEEDLE_TIMESTAMPS transcript of vid00000009"),
+            "{s}"
+        );
+        assert!(!s.contains("start_s"), "{s}");
+
+        // The cached body is the timed JSON.
+        fn bodies(dir: &std::path::Path, out: &mut Vec<Vec<u8>>) {
+            for e in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+                let p = e.path();
+                if p.is_dir() {
+                    bodies(&p, out);
+                } else if p.extension().is_some_and(|x| x == "bin") {
+                    out.extend(std::fs::read(&p).ok());
+                }
+            }
+        }
+        let mut found = Vec::new();
+        bodies(cache_dir.path(), &mut found);
+        let body = found
+            .into_iter()
+            .find(|b| b.starts_with(b"{"))
+            .expect("transcript cached");
+        let timed: webpipe_local::youtube::TimedTranscript = serde_json::from_slice(&body)?;
+        assert_eq!(timed.cues.len(), 1);
+        assert_eq!(timed.cues[0].start_s, 65.5);
+        assert_eq!(timed.chapters.len(), 1);
+        assert_eq!(timed.chapters[0].title, "Findings");
+
+        service.cancel().await?;
+        Ok::<(), Box<dyn std::error::Error>>(())
+    })
+    .expect("mcp stdio youtube timestamps contract");
+}
@@ -46,9 +46,19 @@ async fn searxng_provider_e2e_via_stdio_server() {
                 {
                     tokio::time::sleep(Duration::from_millis(1_500)).await;
                 }
+                // Echo `time_range` so the test can see the freshness mapping.
+                let title = q
+                    .get("time_range")
+                    .cloned()
+                    .unwrap_or_else(|| "A".to_string());
+                // ...and `pageno` for the offset mapping.
+                let content = q
+                    .get("pageno")
+                    .map(|p| format!("page {p}"))
+                    .unwrap_or_else(|| "alpha".to_string());
                 axum::Json(serde_json::json!({
                     "results": [
-                        {"url": "https://example.com/a", "title": "A", "content": "alpha"},
+                        {"url": "https://example.com/a", "title": title, "content": content},
                         {"url": "https://example.com/b", "title": "B", "content": "beta"}
                     ]
                 }))
@@ -145,5 +155,57 @@ async fn searxng_provider_e2e_via_stdio_server() {
     assert_eq!(v4["ok"].as_bool(), Some(false));
     assert!(v4.get("error").is_some());
 
+    // Freshness maps to SearXNG's `time_range`; absolute dates can't, and say so.
+    let v5 = call(
+        &service,
+        "web_search",
+        serde_json::json!({
+            "provider": "searxng",
+            "query": "hello",
+            "max_results": 2,
+            "freshness": "week"
+        }),
+    )
+    .await;
+    assert_eq!(v5["ok"].as_bool(), Some(true));
+    assert_eq!(v5["results"][0]["title"].as_str(), Some("week"));
+    assert!(v5.get("warnings").is_none(), "{v5}");
+    let v6 = call(
+        &service,
+        "web_search",
+        serde_json::json!({
+            "provider": "searxng",
+            "query": "hello",
+            "max_results": 2,
+            "after": "2024-01-01"
+        }),
+    )
+    .await;
+    assert_eq!(v6["ok"].as_bool(), Some(true));
+    assert_eq!(
+        v6["warnings"],
+        serde_json::json!(["date_filter_unsupported"])
+    );
+    assert!(v6["warning_hints"]["date_filter_unsupported"].is_string());
+
+    // `offset` becomes SearXNG's 1-based `pageno`; a short page means no next page.
+    assert_eq!(v["offset"].as_u64(), Some(0));
+    let v7 = call(
+        &service,
+        "web_search",
+        serde_json::json!({
+            "provider": "searxng",
+            "query": "hello",
+            "max_results": 2,
+            "offset": 10
+        }),
+    )
+    .await;
+    assert_eq!(v7["ok"].as_bool(), Some(true));
+    assert_eq!(v7["results"][0]["snippet"].as_str(), Some("page 2"));
+    assert_eq!(v7["offset"].as_u64(), Some(10));
+    assert_eq!(v7["has_more"].as_bool(), Some(false));
+    assert_eq!(v7["request"]["offset"].as_u64(), Some(10));
+
     service.cancel().await.expect("cancel");
 }
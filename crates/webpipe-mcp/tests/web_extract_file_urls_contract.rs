@@ -0,0 +1,159 @@
+use rmcp::{
+    model::CallToolRequestParam,
+    service::{RoleClient, RunningService, ServiceExt},
+    transport::{ConfigureCommandExt, TokioChildProcess},
+};
+
+/// Minimal single-page PDF with one line of text (xref offsets computed, so strict parsers accept it).
+fn tiny_pdf(text: &str) -> Vec<u8> {
+    let stream = format!("BT /F1 12 Tf 72 720 Td ({text}) Tj ET");
+    let objs = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> >> >>".to_string(),
+        format!("<< /Length {} >>\nstream\n{stream}\nendstream", stream.len()),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+    let mut out = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, o) in objs.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n{o}\nendobj\n", i + 1).as_bytes());
+    }
+    let xref = out.len();
+    out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objs.len() + 1).as_bytes());
+    for off in offsets {
+        out.extend_from_slice(format!("{off:010} 00000 n \n").as_bytes());
+    }
+    out.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objs.len() + 1
+        )
+        .as_bytes(),
+    );
+    out
+}
+
+async fn spawn(envs: &[(&str, &std::path::Path)]) -> RunningService<RoleClient, ()> {
+    let bin = assert_cmd::cargo::cargo_bin!("webpipe");
+    let envs: Vec<(String, std::path::PathBuf)> = envs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_path_buf()))
+        .collect();
+    ().serve(
+        TokioChildProcess::new(tokio::process::Command::new(bin).configure(|cmd| {
+            cmd.args(["mcp-stdio"]);
+            // Disable `.env` autoload so this test stays hermetic.
+            cmd.env("WEBPIPE_DOTENV", "0");
+            cmd.env_remove("WEBPIPE_ALLOW_FILE_URLS");
+            cmd.env_remove("WEBPIPE_FILE_URL_ROOT");
+            for (k, v) in &envs {
+                cmd.env(k, v);
+            }
+        }))
+        .expect("spawn mcp child"),
+    )
+    .await
+    .expect("serve mcp child")
+}
+
+async fn call_extract(service: &RunningService<RoleClient, ()>, url: &str) -> serde_json::Value {
+    let r = service
+        .call_tool(CallToolRequestParam {
+            name: "web_extract".into(),
+            arguments: Some(
+                serde_json::json!({
+                    "url": url,
+                    "include_text": true,
+                    "max_chars": 2000
+                })
+                .as_object()
+                .cloned()
+                .unwrap(),
+            ),
+        })
+        .await
+        .expect("call web_extract");
+    r.structured_content.clone().unwrap_or_else(|| {
+        let s = r.content[0].as_text().expect("text").text.clone();
+        serde_json::from_str(&s).expect("json payload")
+    })
+}
+
+#[tokio::test]
+async fn web_extract_reads_file_urls_only_when_enabled_and_within_root() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let root = tmp.path().join("corpus");
+    std::fs::create_dir_all(&root).unwrap();
+    let html = root.join("page.html");
+    std::fs::write(
+        &html,
+        "<html><body><article><h1>Local</h1><p>Offline corpus paragraph about ownership.</p></article></body></html>",
+    )
+    .unwrap();
+    let pdf = root.join("paper.pdf");
+    std::fs::write(&pdf, tiny_pdf("Local PDF evidence")).unwrap();
+    let outside = tmp.path().join("secret.txt");
+    std::fs::write(&outside, "do not read").unwrap();
+
+    let url_of = |p: &std::path::Path| reqwest::Url::from_file_path(p).unwrap().to_string();
+    let cache = tmp.path().join("cache");
+
+    // Off by default.
+    let service = spawn(&[("WEBPIPE_CACHE_DIR", &cache)]).await;
+    let v = call_extract(&service, &url_of(&html)).await;
+    assert_eq!(v["ok"].as_bool(), Some(false), "payload={v}");
+    assert!(
+        v["error"]["message"]
+            .as_str()
+            .unwrap_or("")
+            .contains("WEBPIPE_ALLOW_FILE_URLS"),
+        "payload={v}"
+    );
+    service.cancel().await.ok();
+
+    let service = spawn(&[
+        ("WEBPIPE_CACHE_DIR", &cache),
+        ("WEBPIPE_ALLOW_FILE_URLS", std::path::Path::new("1")),
+        ("WEBPIPE_FILE_URL_ROOT", &root),
+    ])
+    .await;
+
+    let v = call_extract(&service, &url_of(&html)).await;
+    assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+    assert_eq!(v["status"].as_u64(), Some(200));
+    assert_eq!(v["content_type"].as_str(), Some("text/html"));
+    assert!(v["extract"]["text"]
+        .as_str()
+        .unwrap_or("")
+        .contains("Offline corpus paragraph"));
+
+    let v = call_extract(&service, &url_of(&pdf)).await;
+    assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+    assert_eq!(v["content_type"].as_str(), Some("application/pdf"));
+    assert!(
+        matches!(
+            v["extract"]["engine"].as_str(),
+            Some("pdf-extract") | Some("pdf-strings") | Some("pdftotext") | Some("mutool")
+        ),
+        "payload={v}"
+    );
+    assert!(v["extract"]["text"]
+        .as_str()
+        .unwrap_or("")
+        .contains("Local PDF evidence"));
+
+    // Traversal outside the root is refused.
+    let escape = format!("{}/../secret.txt", url_of(&root));
+    let v = call_extract(&service, &escape).await;
+    assert_eq!(v["ok"].as_bool(), Some(false), "payload={v}");
+    assert!(
+        v["error"]["message"]
+            .as_str()
+            .unwrap_or("")
+            .contains("outside the allowed root"),
+        "payload={v}"
+    );
+    service.cancel().await.ok();
+}
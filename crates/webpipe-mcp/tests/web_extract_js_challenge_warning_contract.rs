@@ -109,7 +109,12 @@ Performance &amp; security by Cloudflare
     )
     .await;
 
-    assert_eq!(v["ok"].as_bool(), Some(true), "payload={v}");
+    assert_eq!(v["ok"].as_bool(), Some(false), "payload={v}");
+    assert_eq!(
+        v["error"]["code"].as_str(),
+        Some("http_status"),
+        "payload={v}"
+    );
     assert_eq!(v["status"].as_u64(), Some(403), "payload={v}");
     let codes = v["warning_codes"].as_array().cloned().unwrap_or_default();
     assert!(